@@ -1,6 +1,52 @@
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
 
+/// Write the sidecar manifest with the SHA-256 of each bundled binary to OUT_DIR
+///
+/// `sidecar-manifest.json` lists the sidecars the app launches. Tauri bundles each one from
+/// `binaries/<name>-<target triple>`, which is the file hashed here. A sidecar whose binary
+/// isn't present is left out, and release builds then refuse to launch it.
+fn generate_sidecar_manifest() {
+    println!("cargo:rerun-if-changed=sidecar-manifest.json");
+    // A path that doesn't exist would rerun this script on every build
+    if Path::new("binaries").exists() {
+        println!("cargo:rerun-if-changed=binaries");
+    }
+
+    let listed: serde_json::Value = fs::read_to_string("sidecar-manifest.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .expect("sidecar-manifest.json must be valid JSON");
+    let target = std::env::var("TARGET").unwrap_or_default();
+    let exe_suffix = if target.contains("windows") { ".exe" } else { "" };
+
+    let mut sidecars = Vec::new();
+    for entry in listed["sidecars"].as_array().into_iter().flatten() {
+        let Some(name) = entry["name"].as_str() else {
+            continue;
+        };
+        let binary = Path::new("binaries").join(format!("{}-{}{}", name, target, exe_suffix));
+        match fs::read(&binary) {
+            Ok(bytes) => sidecars.push(serde_json::json!({
+                "name": name,
+                "version": entry.get("version"),
+                "sha256": format!("{:x}", Sha256::digest(&bytes)),
+            })),
+            Err(_) => println!(
+                "cargo:warning=Sidecar {} not found at {}; it is left out of the manifest",
+                name,
+                binary.display()
+            ),
+        }
+    }
+
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set for build scripts");
+    let manifest = serde_json::json!({ "sidecars": sidecars });
+    fs::write(Path::new(&out_dir).join("sidecar-manifest.json"), manifest.to_string())
+        .expect("failed to write the sidecar manifest");
+}
+
 fn main() {
     // Read version from package.json
     let package_json_path = Path::new("../package.json");
@@ -29,6 +75,8 @@ fn main() {
         }
     }
 
+    generate_sidecar_manifest();
+
     // Run tauri build
     tauri_build::build()
 }
@@ -98,11 +98,32 @@ CREATE TABLE IF NOT EXISTS ai_usage_events (
     session_id TEXT, -- For tracking within sessions
     user_prompt_tokens INTEGER,
     assistant_response_tokens INTEGER,
+    cached_tokens INTEGER, -- prompt tokens served from the context cache
+    estimated BOOLEAN NOT NULL DEFAULT 0, -- token counts are estimates, not provider-reported
     cost REAL NOT NULL DEFAULT 0.0,
     session_date TEXT NOT NULL, -- YYYY-MM-DD format
     timestamp INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
 );
 
+-- Spend attribution tags (team, ticket, feature) attached to a usage session
+CREATE TABLE IF NOT EXISTS ai_usage_tags (
+    session_id TEXT NOT NULL,
+    tag_key TEXT NOT NULL,
+    tag_value TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    PRIMARY KEY (session_id, tag_key)
+);
+
+-- Provider failover events: a request moved from one model to the next in a fallback chain
+CREATE TABLE IF NOT EXISTS provider_failover_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    from_model TEXT NOT NULL,
+    to_model TEXT NOT NULL,
+    reason TEXT NOT NULL, -- rate_limited, unavailable, timeout, network, model_error
+    error_message TEXT,
+    timestamp INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
 -- Workflow Stages Table
 CREATE TABLE IF NOT EXISTS workflow_stages (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -165,6 +186,8 @@ CREATE INDEX IF NOT EXISTS idx_ai_events_project_session ON ai_usage_events(proj
 CREATE INDEX IF NOT EXISTS idx_ai_events_project_date ON ai_usage_events(project_id, session_date DESC);
 CREATE INDEX IF NOT EXISTS idx_ai_events_model ON ai_usage_events(project_id, model_name, timestamp DESC);
 CREATE INDEX IF NOT EXISTS idx_ai_events_agent_mcp ON ai_usage_events(project_id, agent_type, mcp_server, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_ai_usage_tags_key ON ai_usage_tags(tag_key, tag_value);
+CREATE INDEX IF NOT EXISTS idx_failover_events_time ON provider_failover_events(timestamp DESC);
 
 CREATE INDEX IF NOT EXISTS idx_workflow_project_order ON workflow_stages(project_id, stage_order);
 
@@ -1,8 +1,10 @@
 use log::{info, debug, warn, error};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager, Emitter, Listener};
+use tokio::io::AsyncWriteExt;
 use tokio::sync::RwLock;
 
 use crate::commands::universal_tool_executor::{
@@ -12,9 +14,18 @@ use crate::commands::universal_tool_executor::{
 use crate::adapters::{ClaudeToolAdapter, GeminiToolAdapter, OllamaToolAdapter};
 use crate::commands::mcp::mcp_list;
 use crate::commands::agents::AgentDb;
+use crate::commands::project_permissions::authorize_tool_call;
+use crate::commands::session_event_log::emit_session_event;
 use crate::commands::slash_commands::slash_commands_list;
 
+/// Gemini function names allow letters, digits, `_`, `.` and `-`, up to 64 characters
+const GEMINI_FUNCTION_NAME_MAX: usize = 64;
+
+/// Bytes a file write puts out between progress events
+const FILE_WRITE_CHUNK: usize = 64 * 1024;
+
 /// Universal Tool Bridge - Central coordinator for all tool operations
+#[derive(Clone)]
 pub struct UniversalToolBridge {
     pub app_handle: AppHandle,
     pub registry: Arc<UniversalToolRegistry>,
@@ -177,7 +188,7 @@ impl UniversalToolBridge {
         let mut count = 0;
         
         // File operations tool
-        let file_tool = Arc::new(FileOperationTool::new());
+        let file_tool = Arc::new(FileOperationTool::new(self.app_handle.clone()));
         self.registry.register_tool(file_tool).await;
         count += 1;
         
@@ -257,6 +268,8 @@ impl UniversalToolBridge {
             return Err(format!("Tool {} does not support model {}", tool_name, model_id));
         }
         
+        self.authorize(tool.tool_type(), &parameters, &context)?;
+        
         // Get appropriate adapter
         let provider = crate::commands::universal_tool_executor::determine_provider(&model_id);
         let adapter = self.registry.get_adapter(&provider).await
@@ -283,6 +296,241 @@ impl UniversalToolBridge {
         
         Ok(result)
     }
+
+    /// Execute a tool the model called through native function calling
+    ///
+    /// Unlike `execute_tool` this skips the provider adapter, whose job is to
+    /// simulate tools for models that can only ask for them in prose.
+    pub async fn execute_function_call(
+        &self,
+        tool_name: String,
+        model_id: String,
+        parameters: HashMap<String, Value>,
+        context: ToolContext,
+    ) -> Result<ToolExecutionResult, String> {
+        if !*self.initialized.read().await {
+            self.initialize().await?;
+        }
+
+        let tool = self.registry.get_tool(&tool_name).await
+            .ok_or_else(|| format!("Tool not found: {}", tool_name))?;
+        if !tool.supports_model(&model_id) {
+            return Err(format!("Tool {} does not support model {}", tool_name, model_id));
+        }
+
+        self.authorize(tool.tool_type(), &parameters, &context)?;
+        let result = tool.execute(parameters, &context).await?;
+
+        self.app_handle.emit("universal-tool-executed", json!({
+            "tool": tool_name,
+            "model": model_id,
+            "success": result.success,
+            "execution_time_ms": result.execution_time_ms
+        })).map_err(|e| format!("Failed to emit execution event: {}", e))?;
+
+        Ok(result)
+    }
+
+    /// Reject file and shell operations outside what the call's project permits
+    fn authorize(
+        &self,
+        tool_type: ToolType,
+        parameters: &HashMap<String, Value>,
+        context: &ToolContext,
+    ) -> Result<(), String> {
+        let db = self.app_handle.state::<AgentDb>();
+        let conn = db.lock_conn();
+        authorize_tool_call(&conn, &tool_type, parameters, context)
+    }
+
+    /// Serialize registered tools into Gemini function declarations
+    ///
+    /// Unknown tools and tools the model can't use are skipped with a warning.
+    pub async fn gemini_tool_catalog(&self, tool_names: &[String], model_id: &str) -> GeminiToolCatalog {
+        if !*self.initialized.read().await {
+            if let Err(e) = self.initialize().await {
+                warn!("Failed to initialize tool bridge for Gemini tools: {}", e);
+            }
+        }
+
+        let mut catalog = GeminiToolCatalog::default();
+        for tool_name in tool_names {
+            match self.registry.get_tool(tool_name).await {
+                Some(tool) if tool.supports_model(model_id) => {
+                    catalog.push(&tool.name(), &tool.description(), &tool.parameters_schema());
+                }
+                Some(_) => warn!("Tool {} does not support model {}, not offering it", tool_name, model_id),
+                None => warn!("Tool not found, not offering it to Gemini: {}", tool_name),
+            }
+        }
+        catalog
+    }
+}
+
+// =============================================================================
+// Gemini Function Declarations
+// =============================================================================
+
+/// Tools offered to Gemini in a request's `tools` field
+#[derive(Debug, Clone, Default)]
+pub struct GeminiToolCatalog {
+    declarations: Vec<Value>,
+    /// Gemini function name -> universal tool name
+    tool_names: HashMap<String, String>,
+}
+
+impl GeminiToolCatalog {
+    /// Add a tool, sanitizing its name and schema for Gemini
+    pub fn push(&mut self, tool_name: &str, description: &str, schema: &Value) {
+        let function_name = gemini_function_name(tool_name);
+        if self.tool_names.contains_key(&function_name) {
+            warn!("Skipping tool {}: its Gemini name {} is already taken", tool_name, function_name);
+            return;
+        }
+
+        let mut declaration = json!({
+            "name": function_name,
+            "description": description,
+        });
+        if let Some(parameters) = gemini_parameter_schema(schema) {
+            declaration["parameters"] = parameters;
+        }
+        self.declarations.push(declaration);
+        self.tool_names.insert(function_name, tool_name.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.declarations.is_empty()
+    }
+
+    /// Universal tool behind a function name Gemini called
+    pub fn tool_name(&self, function_name: &str) -> Option<&str> {
+        self.tool_names.get(function_name).map(String::as_str)
+    }
+
+    /// Universal names of every offered tool
+    pub fn offered_tools(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tool_names.values().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Value for the request's `tools` field
+    pub fn tools_json(&self) -> Value {
+        json!([{ "functionDeclarations": self.declarations }])
+    }
+}
+
+fn gemini_function_name(tool_name: &str) -> String {
+    let mut name: String = tool_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') { c } else { '_' })
+        .take(GEMINI_FUNCTION_NAME_MAX)
+        .collect();
+    // Names must start with a letter or underscore
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+        name.truncate(GEMINI_FUNCTION_NAME_MAX);
+    }
+    name
+}
+
+/// Reduce a JSON schema to the OpenAPI subset Gemini accepts
+///
+/// Gemini rejects object schemas without properties, so those are dropped.
+fn gemini_parameter_schema(schema: &Value) -> Option<Value> {
+    let schema = schema.as_object()?;
+    let mut reduced = serde_json::Map::new();
+    for key in ["type", "description", "enum", "format", "nullable"] {
+        if let Some(value) = schema.get(key) {
+            reduced.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(items) = schema.get("items").and_then(gemini_parameter_schema) {
+        reduced.insert("items".to_string(), items);
+    }
+
+    if schema.get("type").and_then(|t| t.as_str()) == Some("object") {
+        let properties: serde_json::Map<String, Value> = schema
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .map(|props| {
+                props
+                    .iter()
+                    .filter_map(|(name, prop)| gemini_parameter_schema(prop).map(|p| (name.clone(), p)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if properties.is_empty() {
+            return None;
+        }
+        let required: Vec<Value> = schema
+            .get("required")
+            .and_then(|r| r.as_array())
+            .map(|r| {
+                r.iter()
+                    .filter(|name| name.as_str().is_some_and(|n| properties.contains_key(n)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        reduced.insert("properties".to_string(), Value::Object(properties));
+        if !required.is_empty() {
+            reduced.insert("required".to_string(), Value::Array(required));
+        }
+    }
+
+    Some(Value::Object(reduced))
+}
+
+/// Claude's own file tools, which write without going through the bridge
+const CLAUDE_FILE_TOOLS: [&str; 3] = ["Write", "Edit", "MultiEdit"];
+
+/// Turns the file tool calls in Claude's stream into the write events bridge writes emit
+///
+/// The CLI writes the file itself, so there is no chunk progress to report: a
+/// `file-write-progress` goes out when Claude asks for the write and a
+/// `file-write-complete` when its result comes back. Only whole writes know
+/// their size up front; edits report `null` bytes.
+#[derive(Debug, Default)]
+pub(crate) struct ClaudeFileWrites {
+    /// Path and size of each write awaiting its result, by tool use id
+    pending: HashMap<String, (String, Option<u64>)>,
+}
+
+impl ClaudeFileWrites {
+    /// The write events a stream message starts or finishes
+    pub fn observe(&mut self, msg: &Value) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        for block in msg["message"]["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("tool_use") if CLAUDE_FILE_TOOLS.contains(&block["name"].as_str().unwrap_or_default()) => {
+                    let (Some(id), Some(path)) = (block["id"].as_str(), block["input"]["file_path"].as_str()) else {
+                        continue;
+                    };
+                    let bytes = block["input"]["content"].as_str().map(|content| content.len() as u64);
+                    events.push(("file-write-progress", json!({
+                        "path": path,
+                        "bytes_written": 0,
+                        "total_bytes": bytes,
+                    })));
+                    self.pending.insert(id.to_string(), (path.to_string(), bytes));
+                }
+                Some("tool_result") => {
+                    let Some((path, bytes)) = block["tool_use_id"].as_str().and_then(|id| self.pending.remove(id)) else {
+                        continue;
+                    };
+                    events.push(("file-write-complete", json!({
+                        "path": path,
+                        "bytes_written": bytes,
+                        "success": block["is_error"] != true,
+                    })));
+                }
+                _ => {}
+            }
+        }
+        events
+    }
 }
 
 // =============================================================================
@@ -290,14 +538,60 @@ impl UniversalToolBridge {
 // =============================================================================
 
 /// File operation tool
-struct FileOperationTool;
+///
+/// Writes go out a chunk at a time with `file-write-progress:{session_id}`
+/// after each chunk and `file-write-complete:{session_id}` at the end. The
+/// bridge checks the project's permissions before the tool runs, so a
+/// disallowed write is rejected before any byte of it is written.
+struct FileOperationTool {
+    app_handle: AppHandle,
+}
 
 impl FileOperationTool {
-    fn new() -> Self {
-        Self
+    fn new(app_handle: AppHandle) -> Self {
+        Self { app_handle }
     }
 }
 
+/// Write `content` to `target` in chunks, emitting progress for `path` after each one
+///
+/// Returns the number of bytes written.
+async fn write_with_progress<E: Fn(&str, Value)>(
+    target: &Path,
+    path: &str,
+    content: &[u8],
+    chunk_size: usize,
+    emit: E,
+) -> Result<u64, String> {
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await
+            .map_err(|e| format!("Failed to create the directory of {}: {}", path, e))?;
+    }
+    let mut file = tokio::fs::File::create(target).await
+        .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+
+    let total_bytes = content.len() as u64;
+    let mut bytes_written = 0;
+    for chunk in content.chunks(chunk_size.max(1)) {
+        file.write_all(chunk).await
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        bytes_written += chunk.len() as u64;
+        emit("file-write-progress", json!({
+            "path": path,
+            "bytes_written": bytes_written,
+            "total_bytes": total_bytes,
+        }));
+    }
+    file.flush().await
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    emit("file-write-complete", json!({
+        "path": path,
+        "bytes_written": bytes_written,
+    }));
+    Ok(bytes_written)
+}
+
 #[async_trait::async_trait]
 impl UniversalTool for FileOperationTool {
     fn name(&self) -> String {
@@ -329,12 +623,44 @@ impl UniversalTool for FileOperationTool {
     }
     
     async fn execute(&self, params: HashMap<String, Value>, context: &ToolContext) -> Result<ToolExecutionResult, String> {
+        let start = std::time::Instant::now();
         let operation = params.get("operation")
             .and_then(|v| v.as_str())
             .unwrap_or("read");
         
         info!("Executing file operation: {} in {}", operation, context.project_path);
         
+        if operation == "write" {
+            let path = params.get("path")
+                .or_else(|| params.get("file_path"))
+                .and_then(|v| v.as_str())
+                .ok_or("A write needs a path")?;
+            // A missing body is refused rather than taken for an empty file, which would truncate it
+            let content = params.get("content")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| format!("A write to {} needs its content", path))?;
+            let target = Path::new(&context.project_path).join(path);
+            let bytes_written = write_with_progress(&target, path, content.as_bytes(), FILE_WRITE_CHUNK, |event, payload| {
+                let event = format!("{}:{}", event, context.session_id);
+                if let Err(e) = emit_session_event(&self.app_handle, &event, payload) {
+                    warn!("Failed to emit {}: {}", event, e);
+                }
+            }).await?;
+            
+            return Ok(ToolExecutionResult {
+                success: true,
+                output: json!({
+                    "operation": operation,
+                    "path": path,
+                    "bytes_written": bytes_written,
+                    "status": "completed"
+                }),
+                error: None,
+                execution_time_ms: start.elapsed().as_millis() as u64,
+                tokens_used: Some(20),
+            });
+        }
+        
         Ok(ToolExecutionResult {
             success: true,
             output: json!({
@@ -474,4 +800,62 @@ impl UniversalTool for CodeAnalysisTool {
     fn supports_model(&self, _model_id: &str) -> bool {
         true // All models can use code analysis
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_large_write_emits_progress_then_completes() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("src").join("generated.rs");
+        let content = "x".repeat(2 * FILE_WRITE_CHUNK + 10);
+        let events = Mutex::new(Vec::new());
+
+        let written = write_with_progress(&target, "src/generated.rs", content.as_bytes(), FILE_WRITE_CHUNK, |event, payload| {
+            events.lock().unwrap().push((event.to_string(), payload));
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(written, content.len() as u64);
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), content);
+
+        let events = events.into_inner().unwrap();
+        let names: Vec<&str> = events.iter().map(|(event, _)| event.as_str()).collect();
+        assert_eq!(names, ["file-write-progress", "file-write-progress", "file-write-progress", "file-write-complete"]);
+        let progress: Vec<u64> = events[..3].iter().map(|(_, p)| p["bytes_written"].as_u64().unwrap()).collect();
+        assert_eq!(progress, [FILE_WRITE_CHUNK as u64, 2 * FILE_WRITE_CHUNK as u64, content.len() as u64]);
+        assert_eq!(events[0].1["path"], "src/generated.rs");
+        assert_eq!(events[0].1["total_bytes"], content.len() as u64);
+        assert_eq!(events[3].1["bytes_written"], content.len() as u64);
+    }
+
+    #[test]
+    fn test_claude_write_tool_calls_emit_write_events() {
+        let mut writes = ClaudeFileWrites::default();
+        let call = json!({"type": "assistant", "message": {"content": [
+            {"type": "text", "text": "Writing it now"},
+            {"type": "tool_use", "id": "t1", "name": "Write", "input": {"file_path": "/work/app/a.rs", "content": "fn main() {}"}},
+            {"type": "tool_use", "id": "t2", "name": "Edit", "input": {"file_path": "/work/app/b.rs", "old_string": "a", "new_string": "b"}},
+            {"type": "tool_use", "id": "t3", "name": "Read", "input": {"file_path": "/work/app/c.rs"}},
+        ]}});
+        let started = writes.observe(&call);
+        assert_eq!(started.iter().map(|(event, _)| *event).collect::<Vec<_>>(), ["file-write-progress", "file-write-progress"]);
+        assert_eq!(started[0].1["total_bytes"], 12);
+        assert_eq!(started[1].1["total_bytes"], Value::Null);
+
+        let results = json!({"type": "user", "message": {"content": [
+            {"type": "tool_result", "tool_use_id": "t2", "is_error": true},
+            {"type": "tool_result", "tool_use_id": "t3"},
+            {"type": "tool_result", "tool_use_id": "t1"},
+        ]}});
+        let finished = writes.observe(&results);
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0].1, json!({"path": "/work/app/b.rs", "bytes_written": null, "success": false}));
+        assert_eq!(finished[1].1, json!({"path": "/work/app/a.rs", "bytes_written": 12, "success": true}));
+        assert!(writes.observe(&results).is_empty());
+    }
+}
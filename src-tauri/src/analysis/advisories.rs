@@ -0,0 +1,597 @@
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::fs;
+
+use super::CancellationToken;
+use crate::windows_command::long_path;
+
+const OSV_QUERY_URL: &str = "https://api.osv.dev/v1/query";
+
+/// How long an online answer is reused before the package is looked up again
+const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Upper bound on one OSV lookup
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+const BUNDLED_SNAPSHOT: &str = include_str!("advisory_snapshot.json");
+
+lazy_static::lazy_static! {
+    /// Online answers by ecosystem, package and version, shared by every analysis
+    static ref ADVISORY_CACHE: Mutex<HashMap<Dependency, (Instant, Vec<Advisory>)>> = Mutex::new(HashMap::new());
+}
+
+/// How advisories are looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdvisoryOptions {
+    /// Lookups in flight at once
+    pub concurrency: usize,
+    /// Use only the bundled snapshot, without network access
+    pub offline: bool,
+}
+
+impl Default for AdvisoryOptions {
+    fn default() -> Self {
+        Self { concurrency: 8, offline: false }
+    }
+}
+
+impl AdvisoryOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=64).contains(&self.concurrency) {
+            return Err(format!("Advisory lookup concurrency must be between 1 and 64, got {}", self.concurrency));
+        }
+        Ok(())
+    }
+}
+
+/// A package a project depends on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dependency {
+    /// `npm` or `crates.io`, as OSV names them
+    pub ecosystem: &'static str,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Advisory {
+    pub id: String,
+    pub summary: String,
+    /// `low`, `moderate`, `high`, `critical` or `unknown`
+    pub severity: String,
+}
+
+/// An advisory affecting one of the project's dependencies
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AdvisoryFinding {
+    pub package: String,
+    pub version: String,
+    pub advisory: Advisory,
+}
+
+/// Findings and where the answer for each package came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AdvisoryReport {
+    pub findings: Vec<AdvisoryFinding>,
+    pub checked_online: usize,
+    pub from_cache: usize,
+    pub from_snapshot: usize,
+    /// When the bundled snapshot was made, if any package was checked against it
+    pub snapshot_date: Option<String>,
+}
+
+impl AdvisoryReport {
+    pub fn packages_checked(&self) -> usize {
+        self.checked_online + self.from_cache + self.from_snapshot
+    }
+
+    /// Points off the dependency score for the findings
+    pub fn penalty(&self) -> f64 {
+        self.findings
+            .iter()
+            .map(|finding| match finding.advisory.severity.as_str() {
+                "critical" => 20.0,
+                "high" => 10.0,
+                "moderate" => 5.0,
+                _ => 2.0,
+            })
+            .sum()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut sources = vec![
+            format!("{} online", self.checked_online),
+            format!("{} from cache", self.from_cache),
+        ];
+        if let Some(date) = &self.snapshot_date {
+            sources.push(format!("{} from the advisory snapshot of {}", self.from_snapshot, date));
+        }
+        format!(
+            "{} known advisories in {} packages checked ({})",
+            self.findings.len(),
+            self.packages_checked(),
+            sources.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotRange {
+    introduced: String,
+    fixed: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotAdvisory {
+    id: String,
+    ecosystem: String,
+    package: String,
+    summary: String,
+    severity: String,
+    ranges: Vec<SnapshotRange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Snapshot {
+    generated_at: String,
+    advisories: Vec<SnapshotAdvisory>,
+}
+
+impl Snapshot {
+    fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_SNAPSHOT).expect("bundled advisory snapshot is valid")
+    }
+
+    fn lookup(&self, dependency: &Dependency) -> Vec<Advisory> {
+        let Some(version) = parse_version(&dependency.version) else {
+            return Vec::new();
+        };
+        self.advisories
+            .iter()
+            .filter(|advisory| advisory.ecosystem == dependency.ecosystem && advisory.package == dependency.name)
+            .filter(|advisory| {
+                advisory.ranges.iter().any(|range| {
+                    parse_version(&range.introduced).is_some_and(|introduced| introduced <= version)
+                        && range.fixed.as_deref().and_then(parse_version).is_none_or(|fixed| version < fixed)
+                })
+            })
+            .map(|advisory| Advisory {
+                id: advisory.id.clone(),
+                summary: advisory.summary.clone(),
+                severity: advisory.severity.clone(),
+            })
+            .collect()
+    }
+}
+
+/// `major.minor.patch` of a version, missing parts counting as 0 and pre-release tags ignored
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// The lowest version a requirement like `^4.17.15` or `>=1.2, <2` allows
+fn lowest_version(requirement: &str) -> Option<String> {
+    let first = requirement.split([',', ' ', '|']).find(|part| !part.trim().is_empty())?;
+    let version = first.trim().trim_start_matches(['^', '~', '=', '>', 'v']);
+    parse_version(version).map(|_| version.to_string())
+}
+
+/// Installed versions by package name from `package-lock.json`, in either lockfile layout
+fn npm_locked_versions(lock: &serde_json::Value) -> HashMap<String, Vec<String>> {
+    let mut locked: HashMap<String, Vec<String>> = HashMap::new();
+    // lockfileVersion 2 and 3 key top-level installs by their node_modules path
+    for (path, package) in lock["packages"].as_object().into_iter().flatten() {
+        let Some(name) = path.strip_prefix("node_modules/").filter(|name| !name.contains("/node_modules/")) else {
+            continue;
+        };
+        if let Some(version) = package["version"].as_str() {
+            locked.entry(name.to_string()).or_default().push(version.to_string());
+        }
+    }
+    // lockfileVersion 1 only has the dependency tree
+    if locked.is_empty() {
+        for (name, package) in lock["dependencies"].as_object().into_iter().flatten() {
+            if let Some(version) = package["version"].as_str() {
+                locked.entry(name.clone()).or_default().push(version.to_string());
+            }
+        }
+    }
+    locked
+}
+
+/// Every version of each package in `Cargo.lock`; a crate can be in the tree more than once
+fn cargo_locked_versions(lock: &toml::Table) -> HashMap<String, Vec<String>> {
+    let mut locked: HashMap<String, Vec<String>> = HashMap::new();
+    for package in lock.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+        if let (Some(name), Some(version)) = (
+            package.get("name").and_then(|v| v.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) {
+            locked.entry(name.to_string()).or_default().push(version.to_string());
+        }
+    }
+    locked
+}
+
+/// `name` at its locked versions, or at the lowest version `requirement` allows when no lockfile lists it
+fn resolved(
+    ecosystem: &'static str,
+    name: &str,
+    requirement: Option<&str>,
+    locked: &HashMap<String, Vec<String>>,
+) -> Vec<Dependency> {
+    let versions = match locked.get(name) {
+        Some(versions) => versions.clone(),
+        None => requirement.and_then(lowest_version).into_iter().collect(),
+    };
+    versions
+        .into_iter()
+        .map(|version| Dependency { ecosystem, name: name.to_string(), version })
+        .collect()
+}
+
+async fn read_json(path: &Path) -> Option<serde_json::Value> {
+    let content = fs::read_to_string(long_path(path)).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+async fn read_toml(path: &Path) -> Option<toml::Table> {
+    let content = fs::read_to_string(long_path(path)).await.ok()?;
+    content.parse().ok()
+}
+
+/// Dependencies declared in `package.json` and `src-tauri/Cargo.toml` under `project_path`
+///
+/// Versions are those `package-lock.json` and `Cargo.lock` have installed; a package
+/// no lockfile lists is taken at the lowest version its requirement allows.
+pub async fn declared_dependencies(project_path: &Path) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+
+    if let Some(json) = read_json(&project_path.join("package.json")).await {
+        let locked = read_json(&project_path.join("package-lock.json"))
+            .await
+            .map(|lock| npm_locked_versions(&lock))
+            .unwrap_or_default();
+        for section in ["dependencies", "devDependencies"] {
+            for (name, requirement) in json[section].as_object().into_iter().flatten() {
+                dependencies.extend(resolved("npm", name, requirement.as_str(), &locked));
+            }
+        }
+    }
+
+    let tauri_dir = project_path.join("src-tauri");
+    if let Some(manifest) = read_toml(&tauri_dir.join("Cargo.toml")).await {
+        // The lockfile sits next to the manifest, or at a workspace root above it
+        let lock = match read_toml(&tauri_dir.join("Cargo.lock")).await {
+            Some(lock) => Some(lock),
+            None => read_toml(&project_path.join("Cargo.lock")).await,
+        };
+        let locked = lock.map(|lock| cargo_locked_versions(&lock)).unwrap_or_default();
+        for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            for (name, requirement) in manifest.get(section).and_then(|s| s.as_table()).into_iter().flatten() {
+                let requirement = match requirement {
+                    toml::Value::String(version) => Some(version.as_str()),
+                    toml::Value::Table(table) => table.get("version").and_then(|v| v.as_str()),
+                    _ => None,
+                };
+                dependencies.extend(resolved("crates.io", name, requirement, &locked));
+            }
+        }
+    }
+
+    dependencies.sort_by(|a, b| (a.ecosystem, &a.name).cmp(&(b.ecosystem, &b.name)));
+    dependencies.dedup();
+    dependencies
+}
+
+enum Source {
+    Online,
+    Cache,
+    Snapshot,
+}
+
+fn cached(dependency: &Dependency) -> Option<Vec<Advisory>> {
+    let cache = ADVISORY_CACHE.lock().ok()?;
+    cache
+        .get(dependency)
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+        .map(|(_, advisories)| advisories.clone())
+}
+
+async fn query_osv(client: &reqwest::Client, dependency: &Dependency) -> Result<Vec<Advisory>, String> {
+    let response = client
+        .post(OSV_QUERY_URL)
+        .json(&serde_json::json!({
+            "package": { "name": dependency.name, "ecosystem": dependency.ecosystem },
+            "version": dependency.version,
+        }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("OSV returned {}", response.status()));
+    }
+    let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    Ok(body["vulns"].as_array().into_iter().flatten().map(osv_advisory).collect())
+}
+
+/// CVSS v3 base score of a vector like `CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H`
+fn cvss3_base_score(vector: &str) -> Option<f64> {
+    let mut rest = vector.split('/');
+    if !rest.next()?.starts_with("CVSS:3") {
+        return None;
+    }
+    let metrics: HashMap<&str, &str> = rest.filter_map(|metric| metric.split_once(':')).collect();
+    let metric = |name: &str| metrics.get(name).copied();
+    let changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let av = match metric("AV")? { "N" => 0.85, "A" => 0.62, "L" => 0.55, "P" => 0.2, _ => return None };
+    let ac = match metric("AC")? { "L" => 0.77, "H" => 0.44, _ => return None };
+    let pr = match (metric("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match metric("UI")? { "N" => 0.85, "R" => 0.62, _ => return None };
+    let impact_of = |name: &str| match metric(name) {
+        Some("H") => Some(0.56),
+        Some("L") => Some(0.22),
+        Some("N") => Some(0.0),
+        _ => None,
+    };
+    let iss = 1.0 - (1.0 - impact_of("C")?) * (1.0 - impact_of("I")?) * (1.0 - impact_of("A")?);
+    let impact = if changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02f64).powi(15)
+    } else {
+        6.42 * iss
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * av * ac * pr * ui;
+    let score = if changed { 1.08 * (impact + exploitability) } else { impact + exploitability };
+    // CVSS rounds up to one decimal, working in integers to avoid float noise
+    let scaled = (score.min(10.0) * 100_000.0).round() as u64;
+    Some(if scaled.is_multiple_of(10_000) { scaled as f64 / 100_000.0 } else { (scaled / 10_000 + 1) as f64 / 10.0 })
+}
+
+/// The severity name of a CVSS score, in the terms the GitHub advisories use
+fn cvss_severity(score: f64) -> &'static str {
+    match score {
+        s if s >= 9.0 => "critical",
+        s if s >= 7.0 => "high",
+        s if s >= 4.0 => "moderate",
+        _ => "low",
+    }
+}
+
+/// An OSV vulnerability as an advisory
+///
+/// GitHub advisories name their severity; RUSTSEC ones only carry a CVSS
+/// vector in `severity`, which is scored instead.
+fn osv_advisory(vuln: &serde_json::Value) -> Advisory {
+    let named = vuln["database_specific"]["severity"].as_str().map(str::to_lowercase);
+    let scored = || {
+        vuln["severity"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let score = entry["score"].as_str()?;
+                score.parse::<f64>().ok().or_else(|| cvss3_base_score(score))
+            })
+            .reduce(f64::max)
+            .map(|score| cvss_severity(score).to_string())
+    };
+    Advisory {
+        id: vuln["id"].as_str().unwrap_or_default().to_string(),
+        summary: vuln["summary"].as_str().unwrap_or_default().to_string(),
+        severity: named.or_else(scored).unwrap_or_else(|| "unknown".to_string()),
+    }
+}
+
+async fn lookup(
+    client: Option<&reqwest::Client>,
+    snapshot: &Snapshot,
+    dependency: &Dependency,
+) -> (Source, Vec<Advisory>) {
+    let Some(client) = client else {
+        return (Source::Snapshot, snapshot.lookup(dependency));
+    };
+    if let Some(advisories) = cached(dependency) {
+        return (Source::Cache, advisories);
+    }
+    match query_osv(client, dependency).await {
+        Ok(advisories) => {
+            if let Ok(mut cache) = ADVISORY_CACHE.lock() {
+                cache.insert(dependency.clone(), (Instant::now(), advisories.clone()));
+            }
+            (Source::Online, advisories)
+        }
+        Err(e) => {
+            log::warn!("Advisory lookup for {} {} failed, using the snapshot: {}", dependency.name, dependency.version, e);
+            (Source::Snapshot, snapshot.lookup(dependency))
+        }
+    }
+}
+
+/// Look up advisories for `dependencies`, at most `options.concurrency` at a time
+///
+/// Online lookups go to the OSV database and are cached for every analysis in the
+/// process. Offline, and for any lookup that fails, advisories come from the
+/// snapshot bundled with the app, whose date is reported so users know how fresh
+/// it is. Once `cancel` fires, lookups in flight are dropped and the report covers
+/// the packages checked so far.
+pub async fn check_dependencies(
+    dependencies: &[Dependency],
+    options: AdvisoryOptions,
+    cancel: &CancellationToken,
+) -> AdvisoryReport {
+    let snapshot = Snapshot::bundled();
+    let client = if options.offline {
+        None
+    } else {
+        match reqwest::Client::builder().timeout(LOOKUP_TIMEOUT).build() {
+            Ok(client) => Some(client),
+            Err(e) => {
+                log::warn!("Failed to create advisory client, using the snapshot: {}", e);
+                None
+            }
+        }
+    };
+
+    let results: Vec<(Source, &Dependency, Vec<Advisory>)> = stream::iter(dependencies)
+        .map(|dependency| {
+            let (client, snapshot) = (client.as_ref(), &snapshot);
+            async move {
+                let (source, advisories) = lookup(client, snapshot, dependency).await;
+                (source, dependency, advisories)
+            }
+        })
+        .buffer_unordered(options.concurrency.max(1))
+        .take_until(cancel.cancelled())
+        .collect()
+        .await;
+    if cancel.is_cancelled() {
+        log::info!("Advisory lookup cancelled after {} of {} packages", results.len(), dependencies.len());
+    }
+
+    let mut report = AdvisoryReport::default();
+    for (source, dependency, advisories) in results {
+        match source {
+            Source::Online => report.checked_online += 1,
+            Source::Cache => report.from_cache += 1,
+            Source::Snapshot => report.from_snapshot += 1,
+        }
+        report.findings.extend(advisories.into_iter().map(|advisory| AdvisoryFinding {
+            package: dependency.name.clone(),
+            version: dependency.version.clone(),
+            advisory,
+        }));
+    }
+    if report.from_snapshot > 0 {
+        report.snapshot_date = Some(snapshot.generated_at.clone());
+    }
+    report.findings.sort_by(|a, b| (&a.package, &a.advisory.id).cmp(&(&b.package, &b.advisory.id)));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_offline_mode_uses_the_bundled_snapshot() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(
+            project.path().join("package.json"),
+            r#"{ "dependencies": { "lodash": "^4.17.15", "react": "^18.2.0" }, "devDependencies": { "semver": "~7.5.4" } }"#,
+        )
+        .unwrap();
+        std::fs::create_dir(project.path().join("src-tauri")).unwrap();
+        std::fs::write(
+            project.path().join("src-tauri").join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[dependencies]\nsmallvec = \"1.4\"\ntokio = { version = \"1.28\", features = [\"full\"] }\n",
+        )
+        .unwrap();
+
+        let dependencies = declared_dependencies(project.path()).await;
+        assert_eq!(dependencies.len(), 5);
+        assert!(dependencies.contains(&Dependency { ecosystem: "npm", name: "lodash".to_string(), version: "4.17.15".to_string() }));
+
+        let report = check_dependencies(&dependencies, AdvisoryOptions { concurrency: 2, offline: true }, &CancellationToken::new()).await;
+        let found: Vec<(&str, &str)> = report
+            .findings
+            .iter()
+            .map(|finding| (finding.package.as_str(), finding.advisory.id.as_str()))
+            .collect();
+        assert_eq!(found, [("lodash", "GHSA-35jh-r3h4-6jhm"), ("smallvec", "RUSTSEC-2021-0003")]);
+        assert_eq!((report.checked_online, report.from_cache, report.from_snapshot), (0, 0, 5));
+        assert_eq!(report.snapshot_date.as_deref(), Some(Snapshot::bundled().generated_at.as_str()));
+        assert_eq!(report.penalty(), 30.0);
+        assert!(report.describe().contains("5 from the advisory snapshot of"), "{}", report.describe());
+    }
+
+    #[tokio::test]
+    async fn test_lockfile_versions_win_over_requirements() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(
+            project.path().join("package.json"),
+            r#"{ "dependencies": { "lodash": "^4.17.15", "minimist": "^1.2.0" } }"#,
+        )
+        .unwrap();
+        // lodash was installed at a fixed version; minimist is missing from the lockfile
+        std::fs::write(
+            project.path().join("package-lock.json"),
+            r#"{ "lockfileVersion": 3, "packages": {
+                "": { "name": "app" },
+                "node_modules/lodash": { "version": "4.17.21" },
+                "node_modules/a/node_modules/lodash": { "version": "4.17.4" }
+            } }"#,
+        )
+        .unwrap();
+        let tauri = project.path().join("src-tauri");
+        std::fs::create_dir(&tauri).unwrap();
+        std::fs::write(tauri.join("Cargo.toml"), "[package]\nname = \"app\"\n\n[dependencies]\nsmallvec = \"1.4\"\n").unwrap();
+        std::fs::write(
+            tauri.join("Cargo.lock"),
+            "[[package]]\nname = \"smallvec\"\nversion = \"1.13.2\"\n\n[[package]]\nname = \"smallvec\"\nversion = \"0.6.10\"\n",
+        )
+        .unwrap();
+
+        let dependencies = declared_dependencies(project.path()).await;
+        let versions: Vec<(&str, &str)> = dependencies.iter().map(|d| (d.name.as_str(), d.version.as_str())).collect();
+        assert_eq!(versions, [("smallvec", "1.13.2"), ("smallvec", "0.6.10"), ("lodash", "4.17.21"), ("minimist", "1.2.0")]);
+
+        let report = check_dependencies(&dependencies, AdvisoryOptions { concurrency: 2, offline: true }, &CancellationToken::new()).await;
+        let mut found: Vec<(&str, &str)> = report.findings.iter().map(|f| (f.package.as_str(), f.version.as_str())).collect();
+        found.sort();
+        assert_eq!(found, [("minimist", "1.2.0"), ("smallvec", "0.6.10")]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_check_stops_looking_up() {
+        let dependencies: Vec<Dependency> = (0..20)
+            .map(|i| Dependency { ecosystem: "npm", name: format!("pkg-{}", i), version: "1.0.0".to_string() })
+            .collect();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let report = check_dependencies(&dependencies, AdvisoryOptions { concurrency: 2, offline: true }, &cancel).await;
+        assert!(report.packages_checked() < dependencies.len(), "{}", report.describe());
+    }
+
+    #[test]
+    fn test_rustsec_severity_comes_from_its_cvss_vector() {
+        let rustsec = serde_json::json!({
+            "id": "RUSTSEC-2021-0003",
+            "summary": "Buffer overflow in SmallVec::insert_many",
+            "severity": [{ "type": "CVSS_V3", "score": "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H" }],
+        });
+        assert_eq!(osv_advisory(&rustsec).severity, "critical");
+
+        let named = serde_json::json!({ "id": "GHSA-x", "database_specific": { "severity": "MODERATE" }, "severity": rustsec["severity"] });
+        assert_eq!(osv_advisory(&named).severity, "moderate");
+        assert_eq!(osv_advisory(&serde_json::json!({ "id": "RUSTSEC-x" })).severity, "unknown");
+
+        assert_eq!(cvss3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), Some(9.8));
+        assert_eq!(cvss3_base_score("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:N/I:N/A:H"), Some(5.9));
+        assert_eq!(cvss3_base_score("CVSS:3.0/AV:N/AC:L/PR:L/UI:N/S:C/C:L/I:L/A:N"), Some(6.4));
+        assert_eq!(cvss3_base_score("CVSS:4.0/AV:N"), None);
+    }
+}
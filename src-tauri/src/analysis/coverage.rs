@@ -0,0 +1,242 @@
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+/// Directories never searched for reports or counted as source
+const SKIPPED_DIRS: &[&str] = &["node_modules", ".git", "target", "dist", "build", "vendor"];
+
+/// How far below the project root to look for a report
+const REPORT_SEARCH_DEPTH: usize = 4;
+
+/// Test files per source file that the estimate treats as full coverage
+const FULL_COVERAGE_TEST_RATIO: f64 = 0.5;
+
+/// Extensions of the files counted by the estimate
+const SOURCE_EXTENSIONS: &[&str] = &["rs", "ts", "tsx", "js", "jsx", "py", "go", "java"];
+
+lazy_static::lazy_static! {
+    static ref COBERTURA_ROOT: Regex = Regex::new(r"<coverage\b[^>]*>").unwrap();
+    static ref XML_ATTRIBUTE: Regex = Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReportFormat {
+    Lcov,
+    Cobertura,
+    Istanbul,
+}
+
+impl ReportFormat {
+    fn from_file_name(name: &str) -> Option<Self> {
+        match name {
+            "lcov.info" => Some(Self::Lcov),
+            "cobertura.xml" | "coverage.xml" => Some(Self::Cobertura),
+            "coverage-final.json" => Some(Self::Istanbul),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lcov => "lcov",
+            Self::Cobertura => "Cobertura",
+            Self::Istanbul => "Istanbul",
+        }
+    }
+
+    /// What the report's percentage counts
+    fn measure(self) -> &'static str {
+        match self {
+            Self::Lcov | Self::Cobertura => "line",
+            Self::Istanbul => "statement",
+        }
+    }
+
+    /// Coverage percentage in `content`; `None` when it isn't a report of this format
+    pub fn parse(self, content: &str) -> Option<f64> {
+        match self {
+            Self::Lcov => parse_lcov(content),
+            Self::Cobertura => parse_cobertura(content),
+            Self::Istanbul => parse_istanbul(content),
+        }
+    }
+}
+
+/// How a coverage figure was obtained
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoverageMethod {
+    /// Parsed from a report; `path` is relative to the project root
+    Report { format: ReportFormat, path: PathBuf },
+    /// Estimated from file counts because no usable report was found
+    Heuristic { test_files: usize, source_files: usize },
+}
+
+/// Test coverage of a project, read from a coverage report or estimated when it has none
+///
+/// lcov (`lcov.info`), Cobertura (`cobertura.xml`, `coverage.xml`) and Istanbul
+/// (`coverage-final.json`) reports give line or statement coverage. Without a
+/// usable report the figure is estimated from how many test files there are per
+/// source file, and `method` says which of the two produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCoverage {
+    /// 0-100
+    pub percent: f64,
+    pub method: CoverageMethod,
+}
+
+impl TestCoverage {
+    /// Description for the health metric, naming the method used
+    pub fn details(&self) -> String {
+        match &self.method {
+            CoverageMethod::Report { format, path } => format!(
+                "{:.1}% {} coverage from {} report {}",
+                self.percent,
+                format.measure(),
+                format.name(),
+                path.display()
+            ),
+            CoverageMethod::Heuristic { test_files, source_files } => format!(
+                "Estimated from {} test file(s) for {} source file(s); no coverage report found",
+                test_files, source_files
+            ),
+        }
+    }
+}
+
+/// Lines hit over lines found, from the `LF`/`LH` totals or, without them, the `DA` entries
+pub fn parse_lcov(content: &str) -> Option<f64> {
+    let (mut found, mut hit) = (None::<u64>, 0u64);
+    let (mut da_found, mut da_hit) = (0u64, 0u64);
+    for line in content.lines().map(str::trim) {
+        if let Some(count) = line.strip_prefix("LF:") {
+            *found.get_or_insert(0) += count.parse::<u64>().ok()?;
+        } else if let Some(count) = line.strip_prefix("LH:") {
+            hit += count.parse::<u64>().ok()?;
+        } else if let Some(entry) = line.strip_prefix("DA:") {
+            da_found += 1;
+            let hits = entry.split(',').nth(1)?;
+            if hits.parse::<u64>().ok()? > 0 {
+                da_hit += 1;
+            }
+        }
+    }
+    let (found, hit) = match found {
+        Some(found) => (found, hit),
+        None => (da_found, da_hit),
+    };
+    (found > 0).then(|| hit.min(found) as f64 * 100.0 / found as f64)
+}
+
+/// Line coverage from the attributes of the root `<coverage>` element
+pub fn parse_cobertura(content: &str) -> Option<f64> {
+    let root = COBERTURA_ROOT.find(content)?.as_str();
+    let attribute = |name: &str| -> Option<f64> {
+        XML_ATTRIBUTE
+            .captures_iter(root)
+            .find(|caps| &caps[1] == name)
+            .and_then(|caps| caps[2].parse().ok())
+    };
+    match (attribute("lines-covered"), attribute("lines-valid")) {
+        (Some(covered), Some(valid)) if valid > 0.0 => Some((covered / valid * 100.0).min(100.0)),
+        _ => attribute("line-rate").map(|rate| (rate * 100.0).clamp(0.0, 100.0)),
+    }
+}
+
+/// Statements executed at least once over all statements, across every file
+pub fn parse_istanbul(content: &str) -> Option<f64> {
+    let report: serde_json::Value = serde_json::from_str(content).ok()?;
+    let (mut total, mut covered) = (0u64, 0u64);
+    for file in report.as_object()?.values() {
+        for count in file["s"].as_object().into_iter().flat_map(|s| s.values()) {
+            total += 1;
+            if count.as_u64().unwrap_or(0) > 0 {
+                covered += 1;
+            }
+        }
+    }
+    (total > 0).then(|| covered as f64 * 100.0 / total as f64)
+}
+
+pub fn is_skipped_dir(entry: &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.file_name().to_str().is_some_and(|name| SKIPPED_DIRS.contains(&name))
+}
+
+/// Coverage reports under `root`, lcov first, then shallowest first
+pub fn find_reports(root: &Path) -> Vec<(ReportFormat, PathBuf)> {
+    let mut reports: Vec<(ReportFormat, usize, PathBuf)> = WalkDir::new(root)
+        .max_depth(REPORT_SEARCH_DEPTH)
+        .into_iter()
+        .filter_entry(|entry| !is_skipped_dir(entry))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let format = ReportFormat::from_file_name(entry.file_name().to_str()?)?;
+            Some((format, entry.depth(), entry.into_path()))
+        })
+        .collect();
+    reports.sort();
+    reports.into_iter().map(|(format, _, path)| (format, path)).collect()
+}
+
+pub fn is_source_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext))
+}
+
+/// Whether `path`, relative to the project root, is a test by its name or directory
+pub fn is_test_file(relative: &Path) -> bool {
+    let name = relative.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    let in_test_dir = relative
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.components())
+        .any(|dir| matches!(dir.as_os_str().to_str(), Some("tests" | "test" | "__tests__")));
+    in_test_dir
+        || name.contains(".test.")
+        || name.contains(".spec.")
+        || stem.ends_with("_test")
+        || stem.starts_with("test_")
+}
+
+/// Estimated coverage for `test_files` tests over `source_files` source files
+pub fn estimate_percent(test_files: usize, source_files: usize) -> f64 {
+    if source_files == 0 {
+        return 0.0;
+    }
+    let ratio = test_files as f64 / source_files as f64;
+    (ratio / FULL_COVERAGE_TEST_RATIO).min(1.0) * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cobertura_and_istanbul_reports() {
+        let cobertura = r#"<?xml version="1.0" ?>
+<coverage version="7.4" timestamp="1" lines-valid="200" lines-covered="150" line-rate="0.75">
+  <packages/>
+</coverage>"#;
+        assert_eq!(parse_cobertura(cobertura), Some(75.0));
+        assert_eq!(parse_cobertura(r#"<coverage line-rate="0.4" branch-rate="0">"#), Some(40.0));
+        assert_eq!(parse_cobertura("<report/>"), None);
+
+        let istanbul = r#"{
+            "/app/src/a.js": {"path": "/app/src/a.js", "s": {"0": 3, "1": 0, "2": 1}},
+            "/app/src/b.js": {"path": "/app/src/b.js", "s": {"0": 0}}
+        }"#;
+        assert_eq!(parse_istanbul(istanbul), Some(50.0));
+        assert_eq!(parse_istanbul("{}"), None);
+
+        assert!(is_test_file(Path::new("src/__tests__/api.ts")));
+        assert!(is_test_file(Path::new("src/lib/api.test.ts")));
+        assert!(is_test_file(Path::new("pkg/parse_test.go")));
+        assert!(!is_test_file(Path::new("src/contest.rs")));
+        assert_eq!(estimate_percent(1, 4), 50.0);
+        assert_eq!(estimate_percent(3, 4), 100.0);
+    }
+}
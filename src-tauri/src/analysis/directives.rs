@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+/// Headings, compared case-insensitively, that open the directives section
+const SECTION_HEADINGS: &[&str] = &["analysis", "dashboard analysis"];
+
+/// Kinds of risk `detect_risks` reports, as named by `allow-risk`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskKind {
+    HardcodedCredentials,
+    SqlInjection,
+    LargeFiles,
+}
+
+impl RiskKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "hardcoded-credentials" => Some(Self::HardcodedCredentials),
+            "sql-injection" => Some(Self::SqlInjection),
+            "large-files" => Some(Self::LargeFiles),
+            _ => None,
+        }
+    }
+}
+
+/// Directives read from the project's CLAUDE.md
+///
+/// Teams annotate intentional patterns in an `## Analysis` section:
+///
+/// ```markdown
+/// ## Analysis
+/// - allowed-dir: tests/fixtures
+/// - safe-pattern: password = os.environ
+/// - allow-risk: sql-injection
+/// ```
+///
+/// Other lines in the section are ignored so it can carry prose for human readers too.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskDirectives {
+    /// Directories relative to the project root whose files are never flagged
+    pub allowed_dirs: Vec<PathBuf>,
+    /// Lines containing any of these are left out of content checks
+    pub safe_patterns: Vec<String>,
+    /// Checks switched off for the whole project
+    pub allowed_risks: Vec<RiskKind>,
+}
+
+impl RiskDirectives {
+    /// Read directives from `CLAUDE.md` at the project root; none if it is missing
+    pub async fn load(project_path: &Path) -> Self {
+        match tokio::fs::read_to_string(crate::windows_command::long_path(&project_path.join("CLAUDE.md"))).await {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut directives = Self::default();
+        let mut in_section = false;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                let heading = line.trim_start_matches('#').trim().to_lowercase();
+                in_section = SECTION_HEADINGS.contains(&heading.as_str());
+                continue;
+            }
+            if !in_section {
+                continue;
+            }
+
+            let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) else {
+                continue;
+            };
+            let Some((key, value)) = item.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('`').trim();
+            if value.is_empty() {
+                continue;
+            }
+
+            match key.trim().to_lowercase().as_str() {
+                "allowed-dir" => {
+                    let dir = value.trim_start_matches("./").trim_end_matches(['/', '\\']);
+                    directives.allowed_dirs.push(PathBuf::from(dir));
+                }
+                "safe-pattern" => directives.safe_patterns.push(value.to_string()),
+                "allow-risk" => match RiskKind::parse(value) {
+                    Some(kind) => directives.allowed_risks.push(kind),
+                    None => log::warn!("Unknown risk kind in CLAUDE.md analysis directives: {}", value),
+                },
+                _ => {}
+            }
+        }
+
+        directives
+    }
+
+    pub fn allows_risk(&self, kind: RiskKind) -> bool {
+        self.allowed_risks.contains(&kind)
+    }
+
+    /// Whether `path`, relative to the project root, lies in an allowed directory
+    pub fn is_allowed_path(&self, relative: &Path) -> bool {
+        self.allowed_dirs.iter().any(|dir| relative.starts_with(dir))
+    }
+
+    /// `content` without the lines that match a safe pattern
+    pub fn strip_safe_lines(&self, content: &str) -> String {
+        if self.safe_patterns.is_empty() {
+            return content.to_string();
+        }
+        content
+            .lines()
+            .filter(|line| !self.safe_patterns.iter().any(|pattern| line.contains(pattern.as_str())))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reads_only_the_analysis_section() {
+        let directives = RiskDirectives::parse(
+            "# Project\n- allowed-dir: src\n\n## Analysis\nIntentional patterns:\n\
+             - allowed-dir: `./tests/fixtures/`\n* safe-pattern: password = os.environ\n\
+             - allow-risk: SQL-Injection\n- allow-risk: eval\n\n## Build\n- allow-risk: large-files\n",
+        );
+
+        assert_eq!(directives.allowed_dirs, vec![PathBuf::from("tests/fixtures")]);
+        assert_eq!(directives.safe_patterns, vec!["password = os.environ".to_string()]);
+        assert_eq!(directives.allowed_risks, vec![RiskKind::SqlInjection]);
+        assert!(directives.is_allowed_path(Path::new("tests/fixtures/creds.py")));
+        assert!(!directives.is_allowed_path(Path::new("tests/fixtures_extra/creds.py")));
+    }
+}
@@ -1,24 +1,266 @@
 use anyhow::Result;
 use log::{info, warn};
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use serde::Serialize;
 use tokio::fs;
+use tokio::sync::Notify;
 use walkdir::WalkDir;
 use regex::Regex;
 use chrono::Utc;
+use sha2::{Digest, Sha256};
 
+use crate::windows_command::{long_path, short_path};
+
+mod advisories;
+mod coverage;
+mod directives;
+mod text_files;
+pub use advisories::{AdvisoryOptions, AdvisoryReport};
+pub use directives::{RiskDirectives, RiskKind};
 use crate::commands::dashboard::{
     ProjectHealthMetric, FeatureItem, RiskItem, DocumentationStatus
 };
 
+/// Cooperative cancellation shared between an analysis and whoever may stop it
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<(AtomicBool, Notify)>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.0.store(true, Ordering::SeqCst);
+        self.inner.1.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.0.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.1.notified();
+            tokio::pin!(notified);
+            // Register before checking the flag so a concurrent cancel isn't missed
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Assignments of likely secrets, such as `api_key = "..."`: capture group 1 is
+/// the name and group 2 the value
+pub const SECRET_PATTERNS: [&str; 2] = [
+    r#"(?i)(api[_\-]?key|apikey|secret|password|pwd|token|auth)[\s]*[:=][\s]*["']([^"']+)["']"#,
+    r#"(?i)(api[_\-]?key|apikey|secret|password|pwd|token|auth)[\s]*[:=][\s]*([^\s]+)"#,
+];
+
+/// Called with each risk as soon as `detect_risks` finds it
+pub type RiskListener = Arc<dyn Fn(&RiskItem) + Send + Sync>;
+
+/// Limits past which an analysis samples the project instead of reading every file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalysisBudget {
+    pub max_files: usize,
+    pub max_bytes: u64,
+}
+
+impl Default for AnalysisBudget {
+    fn default() -> Self {
+        Self { max_files: 20_000, max_bytes: 512 * 1024 * 1024 }
+    }
+}
+
+/// How much of the project the scans read
+///
+/// A project over budget is sampled: one file in `stride` is read, picked by a
+/// hash of its path so the same files are chosen by every scan and every run.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AnalysisCoverage {
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub sampled_files: usize,
+    pub stride: usize,
+    /// Share of the project's files that are read, from 0 to 100
+    pub percent: f64,
+}
+
+impl AnalysisCoverage {
+    pub fn is_sampled(&self) -> bool {
+        self.stride > 1
+    }
+}
+
+/// Whether `path` is one of the files read when sampling one in `stride`
+fn in_sample(root: &Path, path: &Path, stride: usize) -> bool {
+    if stride <= 1 {
+        return true;
+    }
+    let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+    let digest = Sha256::digest(relative.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes) % stride as u64 == 0
+}
+
 /// Main project analyzer
 pub struct ProjectAnalyzer {
     project_path: String,
     project_id: String,
+    cancel: CancellationToken,
+    files_scanned: AtomicUsize,
+    files_skipped: AtomicUsize,
+    risk_listener: Option<RiskListener>,
+    budget: AnalysisBudget,
+    coverage: OnceLock<AnalysisCoverage>,
+    advisories: AdvisoryOptions,
 }
 
 impl ProjectAnalyzer {
     pub fn new(project_path: String, project_id: String) -> Self {
-        Self { project_path, project_id }
+        Self {
+            project_path,
+            project_id,
+            cancel: CancellationToken::new(),
+            files_scanned: AtomicUsize::new(0),
+            files_skipped: AtomicUsize::new(0),
+            risk_listener: None,
+            budget: AnalysisBudget::default(),
+            coverage: OnceLock::new(),
+            advisories: AdvisoryOptions::default(),
+        }
+    }
+
+    /// Stop scanning between files once `token` is cancelled, keeping results so far
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Report risks to `listener` while the scan runs, ahead of the final list
+    pub fn with_risk_listener(mut self, listener: RiskListener) -> Self {
+        self.risk_listener = Some(listener);
+        self
+    }
+
+    /// Sample the project rather than read all of it once it exceeds `budget`
+    pub fn with_budget(mut self, budget: AnalysisBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Look up dependency advisories as `options` say, online or from the bundled snapshot
+    pub fn with_advisories(mut self, options: AdvisoryOptions) -> Self {
+        self.advisories = options;
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Size of the project and how much of it is read, counted on first use
+    pub fn coverage(&self) -> AnalysisCoverage {
+        *self.coverage.get_or_init(|| {
+            let root = long_path(Path::new(&self.project_path));
+            let mut paths = Vec::new();
+            let mut total_bytes = 0;
+            // Vendored and build directories are never scanned, so they don't count toward the budget
+            for entry in WalkDir::new(&root)
+                .into_iter()
+                .filter_entry(|e| !coverage::is_skipped_dir(e))
+                .take_while(|_| !self.is_cancelled())
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                paths.push(entry.into_path());
+            }
+            let total_files = paths.len();
+            let stride = total_files
+                .div_ceil(self.budget.max_files.max(1))
+                .max(total_bytes.div_ceil(self.budget.max_bytes.max(1)) as usize)
+                .max(1);
+            let sampled_files = paths.iter().filter(|path| in_sample(&root, path, stride)).count();
+            let percent = if total_files > 0 {
+                sampled_files as f64 / total_files as f64 * 100.0
+            } else {
+                100.0
+            };
+            if stride > 1 {
+                info!(
+                    "{} has {} files ({} bytes), over budget; sampling {} of them ({:.1}%)",
+                    self.project_path, total_files, total_bytes, sampled_files, percent
+                );
+            }
+            AnalysisCoverage { total_files, total_bytes, sampled_files, stride, percent }
+        })
+    }
+
+    /// Whether a scan should read `path`, always true unless the project is sampled
+    fn should_read(&self, path: &Path) -> bool {
+        let stride = self.coverage().stride;
+        in_sample(&long_path(Path::new(&self.project_path)), path, stride)
+    }
+
+    /// Scale a count taken over the sample up to the whole project
+    fn extrapolate(&self, count: usize) -> usize {
+        count * self.coverage().stride
+    }
+
+    /// Health details, noting when they were estimated from a sample
+    fn describe(&self, details: impl Into<String>) -> Option<String> {
+        let details = details.into();
+        let coverage = self.coverage();
+        if !coverage.is_sampled() {
+            return Some(details);
+        }
+        Some(format!(
+            "{} (sampled: {} of {} files, {:.1}% coverage)",
+            details, coverage.sampled_files, coverage.total_files, coverage.percent
+        ))
+    }
+
+    /// Number of files read so far across all scans
+    pub fn files_scanned(&self) -> usize {
+        self.files_scanned.load(Ordering::Relaxed)
+    }
+
+    /// Number of binary files skipped without being read across all scans
+    pub fn files_skipped(&self) -> usize {
+        self.files_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Read a text file, abandoning the read if the analysis is cancelled meanwhile.
+    /// `None` means binary, unreadable or cancelled.
+    async fn read_file(&self, path: &Path) -> Option<String> {
+        let path = long_path(path);
+        let content = tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => return None,
+            result = text_files::read_text_file(&path) => result,
+        };
+        match content {
+            Ok(None) => {
+                self.files_skipped.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            content => {
+                self.files_scanned.fetch_add(1, Ordering::Relaxed);
+                content.ok().flatten()
+            }
+        }
     }
 
     /// Analyze overall project health
@@ -33,7 +275,7 @@ impl ProjectAnalyzer {
             warn!("Project path does not exist: {}. Returning default metrics.", self.project_path);
             
             // Return default metrics so dashboard can still function
-            for metric_type in &["security", "dependencies", "complexity", "scalability", "error_rate"] {
+            for metric_type in &["security", "dependencies", "complexity", "scalability", "error_rate", "test_coverage"] {
                 metrics.push(ProjectHealthMetric {
                     id: None,
                     project_id: self.project_id.clone(),
@@ -58,14 +300,19 @@ impl ProjectAnalyzer {
             metric_type: "security".to_string(),
             value: security_score,
             timestamp,
-            details: Some("Security analysis including vulnerability scanning".to_string()),
+            details: self.describe("Security analysis including vulnerability scanning"),
             trend: Some("stable".to_string()),
         });
         
+        if self.is_cancelled() {
+            info!("Health analysis cancelled for: {}", self.project_path);
+            return Ok(metrics);
+        }
+
         // Analyze dependencies
-        let dependencies_score = self.analyze_dependencies().await.unwrap_or_else(|e| {
+        let (dependencies_score, advisories) = self.analyze_dependencies().await.unwrap_or_else(|e| {
             warn!("Dependencies analysis failed: {}", e);
-            75.0
+            (75.0, AdvisoryReport::default())
         });
         metrics.push(ProjectHealthMetric {
             id: None,
@@ -73,10 +320,15 @@ impl ProjectAnalyzer {
             metric_type: "dependencies".to_string(),
             value: dependencies_score,
             timestamp,
-            details: Some("Dependency health and update status".to_string()),
+            details: Some(format!("Dependency health and update status; {}", advisories.describe())),
             trend: Some("improving".to_string()),
         });
         
+        if self.is_cancelled() {
+            info!("Health analysis cancelled for: {}", self.project_path);
+            return Ok(metrics);
+        }
+
         // Analyze complexity
         let complexity_score = self.analyze_complexity().await.unwrap_or_else(|e| {
             warn!("Complexity analysis failed: {}", e);
@@ -88,10 +340,15 @@ impl ProjectAnalyzer {
             metric_type: "complexity".to_string(),
             value: complexity_score,
             timestamp,
-            details: Some("Code complexity metrics".to_string()),
+            details: self.describe("Code complexity metrics"),
             trend: Some("stable".to_string()),
         });
         
+        if self.is_cancelled() {
+            info!("Health analysis cancelled for: {}", self.project_path);
+            return Ok(metrics);
+        }
+
         // Analyze scalability
         let scalability_score = self.analyze_scalability().await.unwrap_or_else(|e| {
             warn!("Scalability analysis failed: {}", e);
@@ -103,10 +360,15 @@ impl ProjectAnalyzer {
             metric_type: "scalability".to_string(),
             value: scalability_score,
             timestamp,
-            details: Some("Performance and scalability assessment".to_string()),
+            details: self.describe("Performance and scalability assessment"),
             trend: Some("improving".to_string()),
         });
         
+        if self.is_cancelled() {
+            info!("Health analysis cancelled for: {}", self.project_path);
+            return Ok(metrics);
+        }
+
         // Analyze error rate
         let error_rate_score = self.analyze_error_rate().await.unwrap_or_else(|e| {
             warn!("Error rate analysis failed: {}", e);
@@ -118,10 +380,27 @@ impl ProjectAnalyzer {
             metric_type: "error_rate".to_string(),
             value: error_rate_score,
             timestamp,
-            details: Some("Runtime error frequency analysis".to_string()),
+            details: self.describe("Runtime error frequency analysis"),
             trend: Some("improving".to_string()),
         });
         
+        if self.is_cancelled() {
+            info!("Health analysis cancelled for: {}", self.project_path);
+            return Ok(metrics);
+        }
+
+        // Measure test coverage
+        let test_coverage = self.analyze_test_coverage().await;
+        metrics.push(ProjectHealthMetric {
+            id: None,
+            project_id: self.project_id.clone(),
+            metric_type: "test_coverage".to_string(),
+            value: test_coverage.percent,
+            timestamp,
+            details: self.describe(test_coverage.details()),
+            trend: Some("stable".to_string()),
+        });
+        
         Ok(metrics)
     }
 
@@ -131,15 +410,10 @@ impl ProjectAnalyzer {
         // let mut total_checks = 0;
         
         // Check for hardcoded secrets
-        let secret_patterns = vec![
-            r#"(?i)(api[_\-]?key|apikey|secret|password|pwd|token|auth)[\s]*[:=][\s]*["']([^"']+)["']"#,
-            r#"(?i)(api[_\-]?key|apikey|secret|password|pwd|token|auth)[\s]*[:=][\s]*([^\s]+)"#,
-        ];
-        
-        for entry in WalkDir::new(&self.project_path)
+        for entry in WalkDir::new(long_path(Path::new(&self.project_path)))
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.file_type().is_file() && self.should_read(e.path()))
             .filter(|e| {
                 let path = e.path();
                 let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
@@ -147,12 +421,13 @@ impl ProjectAnalyzer {
             })
         {
             // total_checks += 1;
-            let content = match fs::read_to_string(entry.path()).await {
-                Ok(c) => c,
-                Err(_) => continue,
+            let content = match self.read_file(entry.path()).await {
+                Some(c) => c,
+                None if self.is_cancelled() => break,
+                None => continue,
             };
             
-            for pattern in &secret_patterns {
+            for pattern in SECRET_PATTERNS {
                 let re = Regex::new(pattern)?;
                 if re.is_match(&content) {
                     issues += 1;
@@ -170,15 +445,19 @@ impl ProjectAnalyzer {
         ];
         
         for pattern in &vulnerable_patterns {
+            if self.is_cancelled() {
+                break;
+            }
             let re = Regex::new(pattern)?;
-            for entry in WalkDir::new(&self.project_path)
+            for entry in WalkDir::new(long_path(Path::new(&self.project_path)))
                 .into_iter()
                 .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
+                .filter(|e| e.file_type().is_file() && self.should_read(e.path()))
             {
-                let content = match fs::read_to_string(entry.path()).await {
-                    Ok(c) => c,
-                    Err(_) => continue,
+                let content = match self.read_file(entry.path()).await {
+                    Some(c) => c,
+                    None if self.is_cancelled() => break,
+                    None => continue,
                 };
                 
                 if re.is_match(&content) {
@@ -188,17 +467,17 @@ impl ProjectAnalyzer {
         }
         
         // Calculate score (100 - penalty per issue)
-        let score = f64::max(0.0, 100.0 - (issues as f64 * 10.0));
+        let score = f64::max(0.0, 100.0 - (self.extrapolate(issues) as f64 * 10.0));
         Ok(score)
     }
 
-    /// Analyze dependencies
-    async fn analyze_dependencies(&self) -> Result<f64> {
+    /// Analyze dependencies, including known advisories against them
+    async fn analyze_dependencies(&self) -> Result<(f64, AdvisoryReport)> {
         let mut score = 100.0;
         
         // Check package.json
         let package_json_path = Path::new(&self.project_path).join("package.json");
-        if let Ok(content) = fs::read_to_string(package_json_path).await {
+        if let Ok(content) = fs::read_to_string(long_path(&package_json_path)).await {
             if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
                 let deps = json["dependencies"].as_object();
                 let dev_deps = json["devDependencies"].as_object();
@@ -229,15 +508,20 @@ impl ProjectAnalyzer {
         
         // Check Cargo.toml
         let cargo_toml_path = Path::new(&self.project_path).join("src-tauri").join("Cargo.toml");
-        if let Ok(content) = fs::read_to_string(cargo_toml_path).await {
+        if let Ok(content) = fs::read_to_string(long_path(&cargo_toml_path)).await {
             // Simple check for dependency count
             let dep_count = content.matches("[dependencies]").count();
             if dep_count > 30 {
                 score -= 5.0;
             }
         }
-        
-        Ok(f64::max(0.0, score))
+
+        let dependencies = advisories::declared_dependencies(Path::new(&self.project_path)).await;
+        let report = advisories::check_dependencies(&dependencies, self.advisories, &self.cancel).await;
+        info!("Dependency advisories for {}: {}", self.project_path, report.describe());
+        score -= report.penalty();
+
+        Ok((f64::max(0.0, score), report))
     }
 
     /// Analyze code complexity
@@ -245,10 +529,10 @@ impl ProjectAnalyzer {
         let mut total_complexity = 0;
         let mut file_count = 0;
         
-        for entry in WalkDir::new(&self.project_path)
+        for entry in WalkDir::new(long_path(Path::new(&self.project_path)))
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.file_type().is_file() && self.should_read(e.path()))
             .filter(|e| {
                 let path = e.path();
                 let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
@@ -256,9 +540,10 @@ impl ProjectAnalyzer {
             })
         {
             file_count += 1;
-            let content = match fs::read_to_string(entry.path()).await {
-                Ok(c) => c,
-                Err(_) => continue,
+            let content = match self.read_file(entry.path()).await {
+                Some(c) => c,
+                None if self.is_cancelled() => break,
+                None => continue,
             };
             
             // Simple complexity metrics
@@ -312,19 +597,20 @@ impl ProjectAnalyzer {
         let mut async_usage = 0;
         let mut blocking_operations = 0;
         
-        for entry in WalkDir::new(&self.project_path)
+        for entry in WalkDir::new(long_path(Path::new(&self.project_path)))
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.file_type().is_file() && self.should_read(e.path()))
             .filter(|e| {
                 let path = e.path();
                 let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
                 matches!(ext, "rs" | "ts" | "tsx" | "js" | "jsx")
             })
         {
-            let content = match fs::read_to_string(entry.path()).await {
-                Ok(c) => c,
-                Err(_) => continue,
+            let content = match self.read_file(entry.path()).await {
+                Some(c) => c,
+                None if self.is_cancelled() => break,
+                None => continue,
             };
             
             // Check for async patterns
@@ -338,10 +624,10 @@ impl ProjectAnalyzer {
         }
         
         // Penalize blocking operations
-        score -= blocking_operations as f64 * 5.0;
+        score -= self.extrapolate(blocking_operations) as f64 * 5.0;
         
         // Reward async usage
-        if async_usage > 10 {
+        if self.extrapolate(async_usage) > 10 {
             score = f64::min(100.0, score + 5.0);
         }
         
@@ -354,19 +640,20 @@ impl ProjectAnalyzer {
         let mut error_handling = 0;
         let mut total_functions = 0;
         
-        for entry in WalkDir::new(&self.project_path)
+        for entry in WalkDir::new(long_path(Path::new(&self.project_path)))
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.file_type().is_file() && self.should_read(e.path()))
             .filter(|e| {
                 let path = e.path();
                 let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
                 matches!(ext, "rs" | "ts" | "tsx" | "js" | "jsx")
             })
         {
-            let content = match fs::read_to_string(entry.path()).await {
-                Ok(c) => c,
-                Err(_) => continue,
+            let content = match self.read_file(entry.path()).await {
+                Some(c) => c,
+                None if self.is_cancelled() => break,
+                None => continue,
             };
             
             // Count error handling
@@ -394,7 +681,73 @@ impl ProjectAnalyzer {
         Ok(f64::max(0.0, score))
     }
 
-    /// Scan and identify features
+    /// Test coverage from a coverage report, estimated from test files when there is none
+    async fn analyze_test_coverage(&self) -> coverage::TestCoverage {
+        let root = long_path(Path::new(&self.project_path));
+        for (format, path) in coverage::find_reports(&root) {
+            let Ok(content) = fs::read_to_string(&path).await else {
+                continue;
+            };
+            match format.parse(&content) {
+                Some(percent) => {
+                    let path = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+                    return coverage::TestCoverage { percent, method: coverage::CoverageMethod::Report { format, path } };
+                }
+                None => warn!("Could not parse {} coverage report: {:?}", format.name(), path),
+            }
+        }
+
+        // Rust files with inline test modules count as test files as well as source
+        let mut test_files = 0;
+        let mut source_files = 0;
+        for entry in WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(|e| !coverage::is_skipped_dir(e))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && coverage::is_source_file(e.path()) && self.should_read(e.path()))
+        {
+            if coverage::is_test_file(entry.path().strip_prefix(&root).unwrap_or(entry.path())) {
+                test_files += 1;
+                continue;
+            }
+            source_files += 1;
+            if entry.path().extension().and_then(|ext| ext.to_str()) == Some("rs") {
+                match self.read_file(entry.path()).await {
+                    Some(content) if content.contains("#[cfg(test)]") => test_files += 1,
+                    None if self.is_cancelled() => break,
+                    _ => {}
+                }
+            }
+        }
+        coverage::TestCoverage {
+            percent: coverage::estimate_percent(test_files, source_files),
+            method: coverage::CoverageMethod::Heuristic {
+                test_files: self.extrapolate(test_files),
+                source_files: self.extrapolate(source_files),
+            },
+        }
+    }
+
+    /// Id of a feature that stays the same across scans, derived from the project, file and name
+    ///
+    /// The project is part of the hash because ids are primary keys shared by every project.
+    /// Ids keep to 53 bits so the dashboard can hold them in a JavaScript number.
+    fn stable_feature_id(&self, path: &Path, name: &str) -> i64 {
+        let root = long_path(Path::new(&self.project_path));
+        let relative = path.strip_prefix(&root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        let digest = Sha256::new()
+            .chain_update(self.project_id.as_bytes())
+            .chain_update([0])
+            .chain_update(relative.as_bytes())
+            .chain_update([0])
+            .chain_update(name.as_bytes())
+            .finalize();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) >> 11) as i64
+    }
+
+    /// Scan and identify features, ordered by name and file with ids that are stable across runs
     pub async fn scan_features(&self) -> Result<Vec<FeatureItem>> {
         info!("Scanning features in: {}", self.project_path);
         let mut features = Vec::new();
@@ -409,7 +762,8 @@ impl ProjectAnalyzer {
         // Scan React components
         let components_dir = Path::new(&self.project_path).join("src").join("components");
         if components_dir.exists() {
-            for entry in WalkDir::new(&components_dir)
+            for entry in WalkDir::new(long_path(&components_dir))
+                .sort_by_file_name()
                 .into_iter()
                 .filter_map(|e| e.ok())
                 .filter(|e| e.file_type().is_file())
@@ -423,7 +777,11 @@ impl ProjectAnalyzer {
                 let component_name = file_name.trim_end_matches(".tsx").trim_end_matches(".jsx");
                 
                 // Read file to determine status
-                let content = fs::read_to_string(entry.path()).await.unwrap_or_default();
+                let content = match self.read_file(entry.path()).await {
+                    Some(c) => c,
+                    None if self.is_cancelled() => break,
+                    None => String::new(),
+                };
                 let status = if content.contains("TODO") || content.contains("FIXME") {
                     "in_progress"
                 } else if content.len() < 100 {
@@ -432,15 +790,16 @@ impl ProjectAnalyzer {
                     "completed"
                 };
                 
+                let name = format!("Component: {}", component_name);
                 features.push(FeatureItem {
-                    id: None,
+                    id: Some(self.stable_feature_id(entry.path(), &name)),
                     project_id: self.project_id.clone(),
-                    name: format!("Component: {}", component_name),
+                    name,
                     description: Some(format!("React component {}", component_name)),
                     status: status.to_string(),
                     independence_score: Some(self.calculate_independence_score(&content).await),
                     dependencies: Some("[]".to_string()),
-                    file_paths: Some(format!(r#"["{}"]"#, entry.path().display())),
+                    file_paths: Some(format!(r#"["{}"]"#, short_path(entry.path()).display())),
                     complexity_score: Some(self.calculate_complexity_score(&content)),
                     created_at: timestamp,
                     updated_at: timestamp,
@@ -451,28 +810,34 @@ impl ProjectAnalyzer {
         // Scan Rust modules
         let rust_src = Path::new(&self.project_path).join("src-tauri").join("src");
         if rust_src.exists() {
-            for entry in WalkDir::new(&rust_src)
+            for entry in WalkDir::new(long_path(&rust_src))
+                .sort_by_file_name()
                 .into_iter()
                 .filter_map(|e| e.ok())
                 .filter(|e| e.file_type().is_file())
                 .filter(|e| e.path().extension().map_or(false, |ext| ext == "rs"))
             {
-                let content = fs::read_to_string(entry.path()).await.unwrap_or_default();
+                let content = match self.read_file(entry.path()).await {
+                    Some(c) => c,
+                    None if self.is_cancelled() => break,
+                    None => String::new(),
+                };
                 
                 // Look for Tauri commands
                 if content.contains("#[tauri::command]") {
                     let command_regex = Regex::new(r#"#\[tauri::command\]\s*(?:pub\s+)?(?:async\s+)?fn\s+(\w+)"#)?;
                     for cap in command_regex.captures_iter(&content) {
                         if let Some(cmd_name) = cap.get(1) {
+                            let name = format!("API: {}", cmd_name.as_str());
                             features.push(FeatureItem {
-                                id: None,
+                                id: Some(self.stable_feature_id(entry.path(), &name)),
                                 project_id: self.project_id.clone(),
-                                name: format!("API: {}", cmd_name.as_str()),
+                                name,
                                 description: Some(format!("Tauri command endpoint")),
                                 status: "available".to_string(),
                                 independence_score: Some(85.0),
                                 dependencies: Some("[]".to_string()),
-                                file_paths: Some(format!(r#"["{}"]"#, entry.path().display())),
+                                file_paths: Some(format!(r#"["{}"]"#, short_path(entry.path()).display())),
                                 complexity_score: Some(self.calculate_complexity_score(&content)),
                                 created_at: timestamp,
                                 updated_at: timestamp,
@@ -483,6 +848,7 @@ impl ProjectAnalyzer {
             }
         }
         
+        features.sort_by(|a, b| (&a.name, &a.file_paths).cmp(&(&b.name, &b.file_paths)));
         Ok(features)
     }
 
@@ -521,6 +887,7 @@ impl ProjectAnalyzer {
     pub async fn detect_risks(&self) -> Result<Vec<RiskItem>> {
         info!("Detecting risks in: {}", self.project_path);
         let mut risks = Vec::new();
+        let mut seen = HashSet::new();
         let timestamp = Utc::now().timestamp();
         
         // Check if project path exists
@@ -528,21 +895,34 @@ impl ProjectAnalyzer {
             warn!("Project path does not exist: {}. Returning empty risks list.", self.project_path);
             return Ok(risks);
         }
+
+        // Conventions the project declared in CLAUDE.md
+        let directives = RiskDirectives::load(Path::new(&self.project_path)).await;
+        let root = long_path(Path::new(&self.project_path));
+        let is_allowed = |path: &Path| {
+            path.strip_prefix(&root)
+                .map(|relative| directives.is_allowed_path(relative))
+                .unwrap_or(false)
+        };
         
         // Security risks
-        for entry in WalkDir::new(&self.project_path)
+        for entry in WalkDir::new(&root)
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.file_type().is_file() && self.should_read(e.path()))
+            .filter(|e| !is_allowed(e.path()))
         {
-            let content = match fs::read_to_string(entry.path()).await {
-                Ok(c) => c,
-                Err(_) => continue,
+            let content = match self.read_file(entry.path()).await {
+                Some(c) => directives.strip_safe_lines(&c),
+                None if self.is_cancelled() => break,
+                None => continue,
             };
             
             // Check for hardcoded secrets
-            if content.contains("password =") || content.contains("api_key =") {
-                risks.push(RiskItem {
+            if !directives.allows_risk(RiskKind::HardcodedCredentials)
+                && (content.contains("password =") || content.contains("api_key ="))
+            {
+                self.report_risk(&mut risks, &mut seen, RiskItem {
                     id: None,
                     project_id: self.project_id.clone(),
                     category: "security".to_string(),
@@ -555,13 +935,16 @@ impl ProjectAnalyzer {
                     probability: Some(0.9),
                     detected_at: timestamp,
                     resolved_at: None,
-                    file_paths: Some(format!(r#"["{}"]"#, entry.path().display())),
+                    file_paths: Some(format!(r#"["{}"]"#, short_path(entry.path()).display())),
                 });
             }
             
             // Check for SQL injection risks
-            if content.contains("query(") && content.contains("${") {
-                risks.push(RiskItem {
+            if !directives.allows_risk(RiskKind::SqlInjection)
+                && content.contains("query(")
+                && content.contains("${")
+            {
+                self.report_risk(&mut risks, &mut seen, RiskItem {
                     id: None,
                     project_id: self.project_id.clone(),
                     category: "security".to_string(),
@@ -574,21 +957,23 @@ impl ProjectAnalyzer {
                     probability: Some(0.7),
                     detected_at: timestamp,
                     resolved_at: None,
-                    file_paths: Some(format!(r#"["{}"]"#, entry.path().display())),
+                    file_paths: Some(format!(r#"["{}"]"#, short_path(entry.path()).display())),
                 });
             }
         }
         
         // Performance risks
-        let large_files = WalkDir::new(&self.project_path)
+        let large_files = WalkDir::new(&root)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
+            .filter(|e| !is_allowed(e.path()))
+            .take_while(|_| !self.is_cancelled())
             .filter(|e| e.metadata().map(|m| m.len() > 500_000).unwrap_or(false))
             .count();
             
-        if large_files > 0 {
-            risks.push(RiskItem {
+        if large_files > 0 && !directives.allows_risk(RiskKind::LargeFiles) {
+            self.report_risk(&mut risks, &mut seen, RiskItem {
                 id: None,
                 project_id: self.project_id.clone(),
                 category: "performance".to_string(),
@@ -608,6 +993,23 @@ impl ProjectAnalyzer {
         Ok(risks)
     }
 
+    /// Add a risk unless the same finding was already reported, and notify the listener
+    fn report_risk(
+        &self,
+        risks: &mut Vec<RiskItem>,
+        seen: &mut HashSet<(String, String, Option<String>)>,
+        risk: RiskItem,
+    ) {
+        let key = (risk.category.clone(), risk.title.clone(), risk.file_paths.clone());
+        if !seen.insert(key) {
+            return;
+        }
+        if let Some(listener) = &self.risk_listener {
+            listener(&risk);
+        }
+        risks.push(risk);
+    }
+
     /// Analyze documentation status
     pub async fn analyze_documentation(&self) -> Result<Vec<DocumentationStatus>> {
         info!("Analyzing documentation in: {}", self.project_path);
@@ -630,6 +1032,9 @@ impl ProjectAnalyzer {
         ];
         
         for (doc_type, files) in doc_checks {
+            if self.is_cancelled() {
+                break;
+            }
             let mut found_files = Vec::new();
             let mut total_sections = 0;
             let mut completed_sections = 0;
@@ -639,7 +1044,7 @@ impl ProjectAnalyzer {
                 if file_path.exists() {
                     found_files.push(file_path.display().to_string());
                     
-                    if let Ok(content) = fs::read_to_string(&file_path).await {
+                    if let Some(content) = self.read_file(&file_path).await {
                         // Count sections (headers)
                         total_sections += content.matches("##").count();
                         // Assume sections with content > 50 chars after header are complete
@@ -683,4 +1088,254 @@ impl ProjectAnalyzer {
         
         Ok(docs)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn project_with_components(count: usize) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let components = dir.path().join("src").join("components");
+        std::fs::create_dir_all(&components).unwrap();
+        for i in 0..count {
+            std::fs::write(
+                components.join(format!("Component{}.tsx", i)),
+                "export function Component() { return <div>a reasonably sized component body</div>; }\n".repeat(4),
+            ).unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cancel_stops_walk_with_partial_results() {
+        let project = project_with_components(2000);
+        let token = CancellationToken::new();
+        let analyzer = Arc::new(
+            ProjectAnalyzer::new(project.path().display().to_string(), "p".to_string())
+                .with_cancellation(token.clone()),
+        );
+
+        let watcher = {
+            let analyzer = Arc::clone(&analyzer);
+            tokio::spawn(async move {
+                while analyzer.files_scanned() < 10 {
+                    tokio::task::yield_now().await;
+                }
+                token.cancel();
+            })
+        };
+
+        let features = tokio::time::timeout(Duration::from_secs(10), analyzer.scan_features())
+            .await
+            .expect("cancelled scan should return promptly")
+            .unwrap();
+        watcher.await.unwrap();
+
+        assert!(analyzer.is_cancelled());
+        assert!(features.len() >= 10, "kept results gathered before cancelling");
+        assert!(features.len() < 2000, "stopped before walking every file");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_token_skips_remaining_health_checks() {
+        let project = project_with_components(3);
+        let token = CancellationToken::new();
+        token.cancel();
+        let analyzer = ProjectAnalyzer::new(project.path().display().to_string(), "p".to_string())
+            .with_cancellation(token);
+
+        let metrics = analyzer.analyze_health().await.unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(analyzer.files_scanned(), 0);
+        assert!(analyzer.scan_features().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_project_over_budget_is_sampled() {
+        let project = project_with_components(1200);
+        // Vendored files are never scanned and don't count toward the budget
+        let vendored = project.path().join("node_modules").join("dep");
+        std::fs::create_dir_all(&vendored).unwrap();
+        for i in 0..50 {
+            std::fs::write(vendored.join(format!("index{}.js", i)), "module.exports = {};\n").unwrap();
+        }
+        let path = project.path().display().to_string();
+
+        let full = ProjectAnalyzer::new(path.clone(), "p".to_string());
+        assert!(!full.coverage().is_sampled());
+        assert_eq!(full.coverage().percent, 100.0);
+
+        let analyzer = ProjectAnalyzer::new(path.clone(), "p".to_string())
+            .with_budget(AnalysisBudget { max_files: 200, ..AnalysisBudget::default() });
+        let coverage = analyzer.coverage();
+        assert!(coverage.is_sampled());
+        assert_eq!((coverage.total_files, coverage.stride), (1200, 6));
+        assert!((100..=300).contains(&coverage.sampled_files), "{:?}", coverage);
+        assert!(coverage.percent < 25.0);
+
+        analyzer.analyze_complexity().await.unwrap();
+        assert_eq!(analyzer.files_scanned(), coverage.sampled_files);
+
+        let metrics = analyzer.analyze_health().await.unwrap();
+        let complexity = metrics.iter().find(|m| m.metric_type == "complexity").unwrap();
+        assert!(complexity.details.as_deref().unwrap().contains("sampled"));
+        let dependencies = metrics.iter().find(|m| m.metric_type == "dependencies").unwrap();
+        assert!(!dependencies.details.as_deref().unwrap().contains("sampled"));
+
+        // The byte budget alone also switches to sampling
+        let by_size = ProjectAnalyzer::new(path, "p".to_string())
+            .with_budget(AnalysisBudget { max_bytes: coverage.total_bytes / 3, ..AnalysisBudget::default() });
+        assert!(by_size.coverage().stride >= 3);
+    }
+
+    #[tokio::test]
+    async fn test_risk_listener_sees_each_risk_once() {
+        let project = tempfile::tempdir().unwrap();
+        for i in 0..3 {
+            std::fs::write(project.path().join(format!("config{}.py", i)), "password = 'hunter2'\n").unwrap();
+        }
+        std::fs::write(
+            project.path().join("db.js"),
+            "const api_key = 'k';\ndb.query(`SELECT * FROM users WHERE id = ${id}`);\n",
+        ).unwrap();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::<RiskItem>::new()));
+        let analyzer = ProjectAnalyzer::new(project.path().display().to_string(), "p".to_string())
+            .with_risk_listener({
+                let events = Arc::clone(&events);
+                Arc::new(move |risk: &RiskItem| events.lock().unwrap().push(risk.clone()))
+            });
+
+        let risks = analyzer.detect_risks().await.unwrap();
+        let summary = |items: &[RiskItem]| -> Vec<(String, Option<String>)> {
+            items.iter().map(|r| (r.title.clone(), r.file_paths.clone())).collect()
+        };
+        assert_eq!(risks.len(), 5);
+        assert_eq!(summary(&events.lock().unwrap()), summary(&risks));
+
+        // A second pass finding the same risk neither reports nor lists it again
+        let mut seen: HashSet<_> = risks
+            .iter()
+            .map(|r| (r.category.clone(), r.title.clone(), r.file_paths.clone()))
+            .collect();
+        let mut again = Vec::new();
+        analyzer.report_risk(&mut again, &mut seen, risks[0].clone());
+        assert!(again.is_empty());
+        assert_eq!(events.lock().unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_claude_md_directives_suppress_risks() {
+        let project = tempfile::tempdir().unwrap();
+        let fixtures = project.path().join("tests").join("fixtures");
+        std::fs::create_dir_all(&fixtures).unwrap();
+        std::fs::write(fixtures.join("creds.py"), "password = 'fixture'\n").unwrap();
+        std::fs::write(project.path().join("settings.py"), "password = os.environ['DB_PASSWORD']\n").unwrap();
+        std::fs::write(project.path().join("db.js"), "db.query(`SELECT ${table}`);\n").unwrap();
+        let analyzer = ProjectAnalyzer::new(project.path().display().to_string(), "p".to_string());
+
+        let titles = |risks: Vec<RiskItem>| risks.into_iter().map(|r| r.title).collect::<Vec<_>>();
+        let flagged = titles(analyzer.detect_risks().await.unwrap());
+        assert_eq!(flagged.iter().filter(|t| t.starts_with("Hardcoded")).count(), 2);
+        assert!(flagged.iter().any(|t| t.contains("SQL injection")));
+
+        std::fs::write(
+            project.path().join("CLAUDE.md"),
+            "# Notes\n\n## Analysis\n- allowed-dir: tests/fixtures\n\
+             - safe-pattern: os.environ\n- allow-risk: sql-injection\n",
+        ).unwrap();
+        assert!(analyzer.detect_risks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_binary_files_are_skipped_not_scanned() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join("app.js"), "eval(input);\n").unwrap();
+        std::fs::write(project.path().join("bundle.bin"), "eval(input);\n").unwrap();
+        std::fs::write(project.path().join("cache"), b"eval(\x00\x00)").unwrap();
+        let analyzer = ProjectAnalyzer::new(project.path().display().to_string(), "p".to_string());
+
+        analyzer.analyze_security().await.unwrap();
+        // Only app.js is read: once for the secret scan, once per vulnerable pattern
+        assert_eq!(analyzer.files_scanned(), 5);
+        assert_eq!(analyzer.files_skipped(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_coverage_from_lcov() {
+        let project = project_with_components(2);
+        let metric = |metrics: Vec<ProjectHealthMetric>| {
+            metrics.into_iter().find(|m| m.metric_type == "test_coverage").unwrap()
+        };
+        let analyzer = ProjectAnalyzer::new(project.path().display().to_string(), "p".to_string());
+
+        let estimated = metric(analyzer.analyze_health().await.unwrap());
+        assert_eq!(estimated.value, 0.0);
+        assert_eq!(
+            estimated.details.as_deref(),
+            Some("Estimated from 0 test file(s) for 2 source file(s); no coverage report found")
+        );
+
+        let coverage_dir = project.path().join("coverage");
+        std::fs::create_dir_all(&coverage_dir).unwrap();
+        std::fs::write(
+            coverage_dir.join("lcov.info"),
+            "TN:\nSF:src/a.ts\nDA:1,1\nLF:10\nLH:8\nend_of_record\n\
+             SF:src/b.ts\nDA:1,0\nLF:30\nLH:22\nend_of_record\n",
+        ).unwrap();
+        let measured = metric(analyzer.analyze_health().await.unwrap());
+        assert_eq!(measured.value, 75.0);
+        let details = measured.details.unwrap();
+        assert!(details.starts_with("75.0% line coverage from lcov report coverage"), "{}", details);
+        assert!(details.ends_with("lcov.info"), "{}", details);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_scans_give_same_order_and_ids() {
+        let project = project_with_components(0);
+        for name in ["Zebra", "Alpha", "Menu", "Button"] {
+            std::fs::write(
+                project.path().join("src/components").join(format!("{}.tsx", name)),
+                format!("export function {}() {{ return null; }}\n", name),
+            ).unwrap();
+        }
+        let commands = project.path().join("src-tauri/src");
+        std::fs::create_dir_all(&commands).unwrap();
+        std::fs::write(
+            commands.join("api.rs"),
+            "#[tauri::command]\npub async fn save() {}\n#[tauri::command]\nfn load() {}\n",
+        ).unwrap();
+        let root = project.path().display().to_string();
+
+        let first = ProjectAnalyzer::new(root.clone(), "p".to_string()).scan_features().await.unwrap();
+        let second = ProjectAnalyzer::new(root.clone(), "p".to_string()).scan_features().await.unwrap();
+        let summary = |features: &[FeatureItem]| -> Vec<(Option<i64>, String)> {
+            features.iter().map(|f| (f.id, f.name.clone())).collect()
+        };
+        assert_eq!(summary(&first), summary(&second));
+        let names: Vec<&str> = first.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["API: load", "API: save", "Component: Alpha", "Component: Button", "Component: Menu", "Component: Zebra"]
+        );
+        assert!(first.iter().all(|f| f.id.is_some_and(|id| (0..1 << 53).contains(&id))));
+        let ids: HashSet<_> = first.iter().map(|f| f.id).collect();
+        assert_eq!(ids.len(), first.len());
+
+        let other = ProjectAnalyzer::new(root, "q".to_string()).scan_features().await.unwrap();
+        assert_ne!(other[0].id, first[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_resolves_for_late_waiters() {
+        let token = CancellationToken::new();
+        let waiter = {
+            let token = token.clone();
+            tokio::spawn(async move { token.cancelled().await })
+        };
+        token.cancel();
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        tokio::time::timeout(Duration::from_secs(1), token.cancelled()).await.unwrap();
+    }
+}
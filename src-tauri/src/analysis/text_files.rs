@@ -0,0 +1,77 @@
+use std::io;
+use std::path::Path;
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+
+/// Bytes inspected for a NUL before reading the rest of a file
+const SNIFF_LEN: u64 = 8 * 1024;
+
+/// Extensions of files that are never text, compared case-insensitively
+const BINARY_EXTENSIONS: &[&str] = &[
+    // Images and media
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "icns", "webp", "tiff", "psd",
+    "mp3", "mp4", "wav", "ogg", "mov", "avi", "webm", "flac",
+    // Fonts
+    "woff", "woff2", "ttf", "otf", "eot",
+    // Archives and documents
+    "zip", "gz", "tgz", "bz2", "xz", "7z", "rar", "tar", "pdf", "docx", "xlsx",
+    // Compiled artifacts
+    "exe", "dll", "so", "dylib", "o", "obj", "a", "lib", "rlib", "pdb", "class",
+    "jar", "wasm", "pyc", "node", "bin",
+    // Databases
+    "sqlite", "db",
+];
+
+/// Whether `path` has an extension only used by binary files
+pub fn has_binary_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| BINARY_EXTENSIONS.iter().any(|binary| binary.eq_ignore_ascii_case(ext)))
+}
+
+/// Whether the start of a file contains a NUL byte, which text files never do
+pub fn looks_binary(head: &[u8]) -> bool {
+    head.contains(&0)
+}
+
+/// Contents of a text file, or `None` for a binary or non-UTF-8 file
+///
+/// Project walks run into images, archives and build output; those are recognized
+/// by extension without opening them, or by a NUL byte near the start, so only text
+/// is read in full and decoded.
+pub async fn read_text_file(path: &Path) -> io::Result<Option<String>> {
+    if has_binary_extension(path) {
+        return Ok(None);
+    }
+
+    let mut file = fs::File::open(path).await?;
+    let mut bytes = Vec::new();
+    (&mut file).take(SNIFF_LEN).read_to_end(&mut bytes).await?;
+    if looks_binary(&bytes) {
+        return Ok(None);
+    }
+    file.read_to_end(&mut bytes).await?;
+    Ok(String::from_utf8(bytes).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_binaries_are_skipped_and_text_is_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("main.rs");
+        std::fs::write(&text, "fn main() {}\n".repeat(2000)).unwrap();
+        // A compiled artifact without a telling extension is caught by its NUL bytes
+        let artifact = dir.path().join("app");
+        std::fs::write(&artifact, b"\x7fELF\x02\x01\x01\x00\x00\x00password = 'x'").unwrap();
+        let image = dir.path().join("logo.PNG");
+        std::fs::write(&image, "not even opened").unwrap();
+
+        assert_eq!(read_text_file(&text).await.unwrap().unwrap().len(), 13 * 2000);
+        assert_eq!(read_text_file(&artifact).await.unwrap(), None);
+        assert_eq!(read_text_file(&image).await.unwrap(), None);
+        assert!(read_text_file(&dir.path().join("missing.rs")).await.is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::{FileDiff, FileSnapshot};
+
+/// Unchanged lines kept around each change
+const CONTEXT_LINES: usize = 3;
+
+/// How `get_checkpoint_diff` returns the changes of each modified file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffFormat {
+    /// Unified diff text in `diff_content`
+    #[default]
+    Unified,
+    /// Line ranges and lines per hunk in `hunks`, for side-by-side rendering
+    Structured,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// One line of a hunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// 1-based line number in the old file, absent for added lines
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new file, absent for removed lines
+    pub new_line: Option<usize>,
+    /// Line text without its line ending
+    pub content: String,
+    /// The line is the last of its file and has no trailing newline
+    pub missing_newline: bool,
+}
+
+/// A run of changes with surrounding context, as in a `@@ -a,b +c,d @@` header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// 1-based first old line, or the line before the hunk when `old_lines` is 0
+    pub old_start: usize,
+    pub old_lines: usize,
+    /// 1-based first new line, or the line before the hunk when `new_lines` is 0
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// File-level changes between two sets of snapshots, each sorted by path
+#[derive(Debug, Default)]
+pub struct SnapshotChanges {
+    pub modified: Vec<FileDiff>,
+    pub added: Vec<PathBuf>,
+    pub deleted: Vec<PathBuf>,
+    /// Files moved without content changes; `old_path` holds where they came from
+    pub renamed: Vec<FileDiff>,
+}
+
+/// Snapshots store text only, so binary files come back empty or with NUL bytes
+fn is_binary(snapshot: &FileSnapshot) -> bool {
+    snapshot.content.contains('\0') || (snapshot.content.is_empty() && snapshot.size > 0)
+}
+
+/// Start of a hunk range in unified diff terms
+fn range_start(start: usize, len: usize) -> usize {
+    if len == 0 { start } else { start + 1 }
+}
+
+/// Unified diff text between two versions of a file
+pub fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&format!("a/{}", path.display()), &format!("b/{}", path.display()))
+        .to_string()
+}
+
+/// Hunks between two versions of a file, grouped the same way as `unified_diff`
+pub fn structured_diff(old: &str, new: &str) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(CONTEXT_LINES)
+        .iter()
+        .map(|group| {
+            let first = &group[0];
+            let last = &group[group.len() - 1];
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| {
+                    let text = change.value();
+                    DiffLine {
+                        kind: match change.tag() {
+                            ChangeTag::Equal => DiffLineKind::Context,
+                            ChangeTag::Insert => DiffLineKind::Added,
+                            ChangeTag::Delete => DiffLineKind::Removed,
+                        },
+                        old_line: change.old_index().map(|i| i + 1),
+                        new_line: change.new_index().map(|i| i + 1),
+                        content: text.trim_end_matches(['\n', '\r']).to_string(),
+                        missing_newline: change.missing_newline(),
+                    }
+                })
+                .collect();
+
+            DiffHunk {
+                old_start: range_start(old_range.start, old_range.len()),
+                old_lines: old_range.len(),
+                new_start: range_start(new_range.start, new_range.len()),
+                new_lines: new_range.len(),
+                lines,
+            }
+        })
+        .collect()
+}
+
+fn count_lines(hunks: &[DiffHunk], kind: DiffLineKind) -> usize {
+    hunks.iter().flat_map(|h| &h.lines).filter(|l| l.kind == kind).count()
+}
+
+/// Diff of a file present in both checkpoints
+fn file_diff(path: &Path, from: &FileSnapshot, to: &FileSnapshot, format: DiffFormat) -> FileDiff {
+    if is_binary(from) || is_binary(to) {
+        return FileDiff {
+            path: path.to_path_buf(),
+            old_path: None,
+            additions: 0,
+            deletions: 0,
+            is_binary: true,
+            diff_content: (format == DiffFormat::Unified).then(|| {
+                format!("Binary files a/{0} and b/{0} differ\n", path.display())
+            }),
+            hunks: None,
+        };
+    }
+
+    let hunks = structured_diff(&from.content, &to.content);
+    FileDiff {
+        path: path.to_path_buf(),
+        old_path: None,
+        additions: count_lines(&hunks, DiffLineKind::Added),
+        deletions: count_lines(&hunks, DiffLineKind::Removed),
+        is_binary: false,
+        diff_content: (format == DiffFormat::Unified)
+            .then(|| unified_diff(path, &from.content, &to.content)),
+        hunks: (format == DiffFormat::Structured).then_some(hunks),
+    }
+}
+
+/// Snapshots of files that exist at a checkpoint, keyed by path
+fn present_files(files: &[FileSnapshot]) -> BTreeMap<PathBuf, &FileSnapshot> {
+    files
+        .iter()
+        .filter(|file| !file.is_deleted)
+        .map(|file| (file.file_path.clone(), file))
+        .collect()
+}
+
+/// Compare the snapshots of two checkpoints
+///
+/// A file that disappears under one path and appears unchanged under another is
+/// reported as a rename rather than a deletion plus an addition.
+pub fn diff_snapshots(from: &[FileSnapshot], to: &[FileSnapshot], format: DiffFormat) -> SnapshotChanges {
+    let from_map = present_files(from);
+    let to_map = present_files(to);
+
+    let mut changes = SnapshotChanges::default();
+    for (path, from_file) in &from_map {
+        match to_map.get(path) {
+            Some(to_file) if from_file.hash != to_file.hash || from_file.size != to_file.size => {
+                changes.modified.push(file_diff(path, from_file, to_file, format));
+            }
+            Some(_) => {}
+            None => changes.deleted.push(path.clone()),
+        }
+    }
+    let mut added: Vec<&FileSnapshot> = to_map
+        .iter()
+        .filter(|(path, _)| !from_map.contains_key(*path))
+        .map(|(_, file)| *file)
+        .collect();
+
+    // Pair deletions with additions of identical text content
+    changes.deleted.retain(|old_path| {
+        let old = from_map[old_path];
+        if old.content.is_empty() || is_binary(old) {
+            return true;
+        }
+        let Some(index) = added.iter().position(|new| new.hash == old.hash && !is_binary(new)) else {
+            return true;
+        };
+        let new = added.remove(index);
+        changes.renamed.push(FileDiff {
+            path: new.file_path.clone(),
+            old_path: Some(old_path.clone()),
+            additions: 0,
+            deletions: 0,
+            is_binary: false,
+            diff_content: None,
+            hunks: None,
+        });
+        false
+    });
+    changes.added = added.into_iter().map(|file| file.file_path.clone()).collect();
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    fn snapshot(path: &str, content: &str) -> FileSnapshot {
+        FileSnapshot {
+            checkpoint_id: "cp".to_string(),
+            file_path: PathBuf::from(path),
+            content: content.to_string(),
+            hash: CheckpointStorage::calculate_file_hash(content),
+            is_deleted: false,
+            permissions: None,
+            size: content.len() as u64,
+        }
+    }
+
+    /// Render hunks back into unified diff text
+    fn render(path: &str, hunks: &[DiffHunk]) -> String {
+        let range = |start: usize, len: usize| {
+            if len == 1 { start.to_string() } else { format!("{},{}", start, len) }
+        };
+        let mut out = format!("--- a/{0}\n+++ b/{0}\n", path);
+        for hunk in hunks {
+            out.push_str(&format!(
+                "@@ -{} +{} @@\n",
+                range(hunk.old_start, hunk.old_lines),
+                range(hunk.new_start, hunk.new_lines)
+            ));
+            for line in &hunk.lines {
+                let sign = match line.kind {
+                    DiffLineKind::Context => ' ',
+                    DiffLineKind::Added => '+',
+                    DiffLineKind::Removed => '-',
+                };
+                out.push_str(&format!("{}{}\n", sign, line.content));
+                if line.missing_newline {
+                    out.push_str("\\ No newline at end of file\n");
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_structured_hunks_match_unified_text() {
+        let old: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 15\n", "")
+            .replace("line 20\n", "line 20\nline 21");
+        let from = [snapshot("src/lib.rs", &old)];
+        let to = [snapshot("src/lib.rs", &new)];
+
+        let unified = diff_snapshots(&from, &to, DiffFormat::Unified);
+        let structured = diff_snapshots(&from, &to, DiffFormat::Structured);
+        let text = unified.modified[0].diff_content.as_deref().unwrap();
+        let hunks = structured.modified[0].hunks.as_deref().unwrap();
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines, hunks[0].new_start, hunks[0].new_lines), (1, 5, 1, 5));
+        assert_eq!(hunks[0].lines[1].kind, DiffLineKind::Removed);
+        assert_eq!(hunks[0].lines[1].old_line, Some(2));
+        assert_eq!(hunks[0].lines[2].new_line, Some(2));
+        assert_eq!(render("src/lib.rs", hunks), text);
+        assert_eq!((structured.modified[0].additions, structured.modified[0].deletions), (2, 2));
+        assert_eq!(unified.modified[0].additions, structured.modified[0].additions);
+        assert!(structured.modified[0].diff_content.is_none());
+    }
+
+    #[test]
+    fn test_renames_and_binary_files_are_reported_explicitly() {
+        let mut binary_old = snapshot("logo.png", "");
+        binary_old.size = 512;
+        let mut binary_new = snapshot("logo.png", "");
+        binary_new.size = 640;
+        let from = [snapshot("old_name.rs", "fn main() {}\n"), binary_old, snapshot("gone.rs", "x\n")];
+        let to = [snapshot("new_name.rs", "fn main() {}\n"), binary_new, snapshot("fresh.rs", "y\n")];
+
+        let changes = diff_snapshots(&from, &to, DiffFormat::Unified);
+        assert_eq!(changes.renamed.len(), 1);
+        assert_eq!(changes.renamed[0].path, PathBuf::from("new_name.rs"));
+        assert_eq!(changes.renamed[0].old_path, Some(PathBuf::from("old_name.rs")));
+        assert_eq!(changes.deleted, vec![PathBuf::from("gone.rs")]);
+        assert_eq!(changes.added, vec![PathBuf::from("fresh.rs")]);
+
+        assert_eq!(changes.modified.len(), 1);
+        assert!(changes.modified[0].is_binary);
+        assert_eq!(
+            changes.modified[0].diff_content.as_deref(),
+            Some("Binary files a/logo.png and b/logo.png differ\n")
+        );
+    }
+}
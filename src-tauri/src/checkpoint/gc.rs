@@ -0,0 +1,296 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::integrity::subdirectory_names;
+use super::CheckpointPaths;
+
+/// Held shared while a checkpoint is saved and exclusively while a pool is swept
+static CONTENT_POOL_LOCK: RwLock<()> = RwLock::new(());
+
+/// Guard to hold while writing blobs and the references to them
+pub(crate) fn pool_save_guard() -> RwLockReadGuard<'static, ()> {
+    CONTENT_POOL_LOCK.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn pool_sweep_guard() -> RwLockWriteGuard<'static, ()> {
+    CONTENT_POOL_LOCK.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Size of the checkpoint content pools and how well they deduplicate
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointStorageStats {
+    pub sessions: usize,
+    pub blob_count: usize,
+    /// Bytes on disk taken by all blobs, referenced or not
+    pub blob_bytes: u64,
+    pub file_references: usize,
+    /// Bytes the referenced blobs would take if every reference had its own copy
+    pub logical_bytes: u64,
+    /// `logical_bytes` over the bytes of the referenced blobs; 1.0 with nothing stored
+    pub dedup_ratio: f64,
+    /// Blobs no checkpoint references, which `collect_garbage` would delete
+    pub unreferenced_blobs: usize,
+    pub unreferenced_bytes: u64,
+}
+
+/// What a garbage collection run removed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub sessions_scanned: usize,
+    pub blobs_removed: usize,
+    pub bytes_reclaimed: u64,
+}
+
+/// Number of references to each hash from the checkpoints of a session
+fn reference_counts(paths: &CheckpointPaths) -> Result<HashMap<String, usize>> {
+    let refs_dir = paths.files_dir.join("refs");
+    let mut counts = HashMap::new();
+    if !refs_dir.exists() {
+        return Ok(counts);
+    }
+
+    for checkpoint_entry in fs::read_dir(&refs_dir).context("Failed to read file references")? {
+        let checkpoint_dir = checkpoint_entry?.path();
+        if !checkpoint_dir.is_dir() {
+            continue;
+        }
+        for ref_entry in fs::read_dir(&checkpoint_dir)? {
+            let ref_path = ref_entry?.path();
+            if ref_path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(ref_metadata) = fs::read_to_string(&ref_path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            else {
+                continue;
+            };
+            if let Some(hash) = ref_metadata["hash"].as_str() {
+                *counts.entry(hash.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Blobs in a session's content pool with their size on disk
+fn pool_blobs(paths: &CheckpointPaths) -> Result<Vec<(String, u64)>> {
+    let content_pool_dir = paths.files_dir.join("content_pool");
+    if !content_pool_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut blobs = Vec::new();
+    for entry in fs::read_dir(&content_pool_dir).context("Failed to read content pool")? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        if let Some(hash) = entry.file_name().to_str() {
+            blobs.push((hash.to_string(), metadata.len()));
+        }
+    }
+    Ok(blobs)
+}
+
+/// Delete the blobs of one session that no checkpoint references
+pub(crate) fn sweep_session(paths: &CheckpointPaths) -> Result<GcReport> {
+    let _pool = pool_sweep_guard();
+    let references = reference_counts(paths)?;
+    let content_pool_dir = paths.files_dir.join("content_pool");
+
+    let mut report = GcReport { sessions_scanned: 1, ..GcReport::default() };
+    for (hash, size) in pool_blobs(paths)? {
+        if references.contains_key(&hash) {
+            continue;
+        }
+        match fs::remove_file(content_pool_dir.join(&hash)) {
+            Ok(()) => {
+                report.blobs_removed += 1;
+                report.bytes_reclaimed += size;
+            }
+            Err(e) => log::warn!("Failed to remove unreferenced blob {}: {}", hash, e),
+        }
+    }
+    Ok(report)
+}
+
+/// Project and session ids of every timeline under `claude_dir`
+fn sessions(claude_dir: &Path) -> Vec<(String, String)> {
+    let projects_dir = claude_dir.join("projects");
+    let mut sessions = Vec::new();
+    for project_id in subdirectory_names(&projects_dir) {
+        for session_id in subdirectory_names(&projects_dir.join(&project_id).join(".timelines")) {
+            sessions.push((project_id.clone(), session_id));
+        }
+    }
+    sessions.sort();
+    sessions
+}
+
+/// Content pool statistics over every session under `claude_dir`
+pub fn storage_stats(claude_dir: &Path) -> Result<CheckpointStorageStats> {
+    let claude_dir = crate::windows_command::long_path(claude_dir);
+    let mut stats = CheckpointStorageStats::default();
+    let mut referenced_bytes = 0;
+
+    for (project_id, session_id) in sessions(&claude_dir) {
+        let paths = CheckpointPaths::new(&claude_dir, &project_id, &session_id);
+        let references = reference_counts(&paths)?;
+        stats.sessions += 1;
+        stats.file_references += references.values().sum::<usize>();
+
+        for (hash, size) in pool_blobs(&paths)? {
+            stats.blob_count += 1;
+            stats.blob_bytes += size;
+            match references.get(&hash) {
+                Some(&count) => {
+                    referenced_bytes += size;
+                    stats.logical_bytes += size * count as u64;
+                }
+                None => {
+                    stats.unreferenced_blobs += 1;
+                    stats.unreferenced_bytes += size;
+                }
+            }
+        }
+    }
+
+    stats.dedup_ratio = if referenced_bytes == 0 {
+        1.0
+    } else {
+        stats.logical_bytes as f64 / referenced_bytes as f64
+    };
+    Ok(stats)
+}
+
+/// Delete unreferenced blobs from every session's content pool
+///
+/// Checkpoints only keep references to the blobs in their session's pool, so
+/// removing checkpoints leaves blobs nobody references. Safe while sessions are
+/// active: each pool is swept under the pool lock, so a checkpoint being saved
+/// either finishes its references first or writes its blobs after the sweep.
+pub fn collect_garbage(claude_dir: &Path) -> Result<GcReport> {
+    let claude_dir = crate::windows_command::long_path(claude_dir);
+    let mut report = GcReport::default();
+
+    for (project_id, session_id) in sessions(&claude_dir) {
+        let paths = CheckpointPaths::new(&claude_dir, &project_id, &session_id);
+        match sweep_session(&paths) {
+            Ok(session) => {
+                report.sessions_scanned += 1;
+                report.blobs_removed += session.blobs_removed;
+                report.bytes_reclaimed += session.bytes_reclaimed;
+            }
+            Err(e) => log::warn!("Failed to collect garbage for session {}: {:#}", session_id, e),
+        }
+    }
+
+    log::info!(
+        "Checkpoint GC removed {} blob(s), {} bytes, across {} session(s)",
+        report.blobs_removed,
+        report.bytes_reclaimed,
+        report.sessions_scanned
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::storage::CheckpointStorage;
+    use crate::checkpoint::{Checkpoint, CheckpointMetadata, FileSnapshot};
+    use chrono::Utc;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    const PROJECT: &str = "test-project";
+    const SESSION: &str = "session";
+
+    fn save(storage: &CheckpointStorage, id: &str, parent: Option<&str>, files: &[(&str, &str)]) {
+        let checkpoint = Checkpoint {
+            id: id.to_string(),
+            session_id: SESSION.to_string(),
+            project_id: PROJECT.to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: None,
+            parent_checkpoint_id: parent.map(str::to_string),
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "test".to_string(),
+                user_prompt: String::new(),
+                file_changes: files.len(),
+                snapshot_size: 0,
+            },
+            unrestorable_reason: None,
+        };
+        let snapshots = files
+            .iter()
+            .map(|(path, content)| FileSnapshot {
+                checkpoint_id: id.to_string(),
+                file_path: PathBuf::from(path),
+                content: content.to_string(),
+                hash: CheckpointStorage::calculate_file_hash(content),
+                is_deleted: false,
+                permissions: None,
+                size: content.len() as u64,
+            })
+            .collect();
+        storage.save_checkpoint(PROJECT, SESSION, &checkpoint, snapshots, "{}").unwrap();
+    }
+
+    #[test]
+    fn test_gc_reclaims_unreferenced_blob() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage(PROJECT, SESSION).unwrap();
+        // lib.rs is unchanged between the checkpoints and stored once
+        save(&storage, "cp-1", None, &[("main.rs", "fn main() {}"), ("lib.rs", "pub fn lib() {}")]);
+        save(&storage, "cp-2", Some("cp-1"), &[("main.rs", "fn main() { run() }"), ("lib.rs", "pub fn lib() {}")]);
+
+        let paths = CheckpointPaths::new(&storage.claude_dir, PROJECT, SESSION);
+        storage.remove_checkpoint(&paths, "cp-1").unwrap();
+        let orphan = paths.file_snapshot_path("cp-1", &CheckpointStorage::calculate_file_hash("fn main() {}"));
+        let orphan_size = fs::metadata(&orphan).unwrap().len();
+
+        let stats = storage_stats(temp_dir.path()).unwrap();
+        assert_eq!((stats.sessions, stats.blob_count, stats.file_references), (1, 3, 2));
+        assert_eq!((stats.unreferenced_blobs, stats.unreferenced_bytes), (1, orphan_size));
+        assert_eq!(stats.blob_bytes - stats.unreferenced_bytes, stats.logical_bytes);
+
+        let report = collect_garbage(temp_dir.path()).unwrap();
+        assert_eq!(report, GcReport { sessions_scanned: 1, blobs_removed: 1, bytes_reclaimed: orphan_size });
+        assert!(!orphan.exists());
+
+        // cp-2 still restores both files, and a second run finds nothing to do
+        let (_, snapshots, _) = storage.load_checkpoint(PROJECT, SESSION, "cp-2").unwrap();
+        assert!(snapshots.iter().all(|snapshot| !snapshot.content.is_empty()));
+        assert_eq!(collect_garbage(temp_dir.path()).unwrap().blobs_removed, 0);
+        let stats = storage_stats(temp_dir.path()).unwrap();
+        assert_eq!((stats.blob_count, stats.unreferenced_blobs), (2, 0));
+    }
+
+    #[test]
+    fn test_shared_blobs_raise_the_dedup_ratio() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage(PROJECT, SESSION).unwrap();
+        assert_eq!(storage_stats(temp_dir.path()).unwrap().dedup_ratio, 1.0);
+
+        for (id, parent) in [("cp-1", None), ("cp-2", Some("cp-1")), ("cp-3", Some("cp-2"))] {
+            save(&storage, id, parent, &[("main.rs", "fn main() {}")]);
+        }
+        let stats = storage_stats(temp_dir.path()).unwrap();
+        assert_eq!((stats.blob_count, stats.file_references), (1, 3));
+        assert_eq!(stats.logical_bytes, 3 * stats.blob_bytes);
+        assert_eq!(stats.dedup_ratio, 3.0);
+    }
+}
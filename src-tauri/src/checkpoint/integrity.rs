@@ -0,0 +1,606 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointPaths, SessionTimeline, TimelineNode};
+
+/// Kind of inconsistency found by `verify`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityIssueKind {
+    /// Timeline whose session has neither a JSONL file nor a database row
+    OrphanedTimeline,
+    /// timeline.json is missing or does not parse
+    UnreadableTimeline,
+    /// Checkpoint in the timeline without its metadata or messages on disk
+    MissingCheckpointData,
+    /// File reference to a hash that is not in the content pool
+    MissingBlob,
+    /// Checkpoint directory that the timeline does not know about
+    UntrackedCheckpoint,
+    /// Session messages whose session row is gone
+    OrphanedMessages,
+}
+
+/// A single inconsistency, located by project, session and checkpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    /// `None` for database issues, which are not tied to a project directory
+    pub project_id: Option<String>,
+    pub session_id: String,
+    pub checkpoint_id: Option<String>,
+    pub detail: String,
+}
+
+/// Result of scanning the checkpoint store and the session tables
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub sessions_scanned: usize,
+    pub checkpoints_scanned: usize,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+/// How `repair` deals with the issues it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairMode {
+    /// Delete dangling timelines, checkpoints and messages
+    Prune,
+    /// Keep checkpoints with missing data but refuse to restore them
+    MarkUnrestorable,
+}
+
+/// Issues fixed by `repair`, and those a fresh scan still finds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub mode: RepairMode,
+    pub repaired: Vec<IntegrityIssue>,
+    pub remaining: Vec<IntegrityIssue>,
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )
+    .context("Failed to inspect database schema")
+}
+
+/// Session ids with a row in `chat_sessions`
+fn database_sessions(conn: &Connection) -> Result<HashSet<String>> {
+    if !table_exists(conn, "chat_sessions")? {
+        return Ok(HashSet::new());
+    }
+    let mut stmt = conn
+        .prepare("SELECT session_id FROM chat_sessions")
+        .context("Failed to query sessions")?;
+    let sessions = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()
+        .context("Failed to read sessions")?;
+    Ok(sessions)
+}
+
+/// Sessions with messages but no session row, with their message counts
+fn orphaned_messages(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    if !table_exists(conn, "session_messages")? || !table_exists(conn, "chat_sessions")? {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT session_id, COUNT(*) FROM session_messages
+             WHERE session_id NOT IN (SELECT session_id FROM chat_sessions)
+             GROUP BY session_id",
+        )
+        .context("Failed to query session messages")?;
+    let orphans = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read session messages")?;
+    Ok(orphans)
+}
+
+/// The database rows a scan compares the checkpoint store against
+struct SessionRows {
+    sessions: HashSet<String>,
+    orphaned_messages: Vec<(String, i64)>,
+}
+
+impl SessionRows {
+    /// Read the rows under the lock `lock` returns, releasing it before any file is touched
+    fn read<G: Deref<Target = Connection>>(lock: &impl Fn() -> G) -> Result<Self> {
+        let conn = lock();
+        Ok(Self { sessions: database_sessions(&conn)?, orphaned_messages: orphaned_messages(&conn)? })
+    }
+}
+
+pub(crate) fn subdirectory_names(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect()
+}
+
+fn collect_nodes<'a>(node: &'a TimelineNode, checkpoints: &mut Vec<&'a Checkpoint>) {
+    checkpoints.push(&node.checkpoint);
+    for child in &node.children {
+        collect_nodes(child, checkpoints);
+    }
+}
+
+/// Paths referenced by a checkpoint whose content is not in the pool
+fn missing_blobs(paths: &CheckpointPaths, checkpoint_id: &str) -> Vec<String> {
+    let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
+    let content_pool_dir = paths.files_dir.join("content_pool");
+    let Ok(entries) = fs::read_dir(&refs_dir) else {
+        return Vec::new();
+    };
+
+    let mut missing = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let ref_path = entry.path();
+        if ref_path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(ref_metadata) = fs::read_to_string(&ref_path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        else {
+            continue;
+        };
+        if let Some(hash) = ref_metadata["hash"].as_str() {
+            if !content_pool_dir.join(hash).exists() {
+                missing.push(ref_metadata["path"].as_str().unwrap_or(hash).to_string());
+            }
+        }
+    }
+    missing.sort();
+    missing
+}
+
+fn scan_session(
+    storage: &CheckpointStorage,
+    project_id: &str,
+    session_id: &str,
+    database_sessions: &HashSet<String>,
+    report: &mut IntegrityReport,
+) {
+    report.sessions_scanned += 1;
+    let issue = |kind, checkpoint_id: Option<&str>, detail: String| IntegrityIssue {
+        kind,
+        project_id: Some(project_id.to_string()),
+        session_id: session_id.to_string(),
+        checkpoint_id: checkpoint_id.map(str::to_string),
+        detail,
+    };
+
+    let session_file = storage
+        .claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
+    if !session_file.exists() && !database_sessions.contains(session_id) {
+        report.issues.push(issue(
+            IntegrityIssueKind::OrphanedTimeline,
+            None,
+            "Session has no JSONL file and no database row".to_string(),
+        ));
+        return;
+    }
+
+    let paths = CheckpointPaths::new(&storage.claude_dir, project_id, session_id);
+    let timeline = match storage.load_timeline(&paths.timeline_file) {
+        Ok(timeline) => timeline,
+        Err(e) => {
+            report.issues.push(issue(IntegrityIssueKind::UnreadableTimeline, None, format!("{:#}", e)));
+            return;
+        }
+    };
+
+    let mut checkpoints = Vec::new();
+    if let Some(root) = &timeline.root_node {
+        collect_nodes(root, &mut checkpoints);
+    }
+    report.checkpoints_scanned += checkpoints.len();
+
+    for checkpoint in &checkpoints {
+        // Already marked by an earlier repair
+        if checkpoint.unrestorable_reason.is_some() {
+            continue;
+        }
+
+        let missing_files: Vec<&str> = [
+            (paths.checkpoint_metadata_file(&checkpoint.id), "metadata.json"),
+            (paths.checkpoint_messages_file(&checkpoint.id), "messages.jsonl"),
+        ]
+        .iter()
+        .filter(|(path, _)| !path.exists())
+        .map(|(_, name)| *name)
+        .collect();
+        if !missing_files.is_empty() {
+            report.issues.push(issue(
+                IntegrityIssueKind::MissingCheckpointData,
+                Some(&checkpoint.id),
+                format!("Missing {}", missing_files.join(", ")),
+            ));
+            continue;
+        }
+
+        let blobs = missing_blobs(&paths, &checkpoint.id);
+        if !blobs.is_empty() {
+            report.issues.push(issue(
+                IntegrityIssueKind::MissingBlob,
+                Some(&checkpoint.id),
+                format!("Content missing for {} file(s): {}", blobs.len(), blobs.join(", ")),
+            ));
+        }
+    }
+
+    let tracked: HashSet<&str> = checkpoints.iter().map(|checkpoint| checkpoint.id.as_str()).collect();
+    let on_disk: BTreeSet<String> = subdirectory_names(&paths.checkpoints_dir)
+        .into_iter()
+        .chain(subdirectory_names(&paths.files_dir.join("refs")))
+        .collect();
+    for checkpoint_id in on_disk.iter().filter(|id| !tracked.contains(id.as_str())) {
+        report.issues.push(issue(
+            IntegrityIssueKind::UntrackedCheckpoint,
+            Some(checkpoint_id),
+            "Checkpoint data is not referenced by the timeline".to_string(),
+        ));
+    }
+}
+
+/// Scan every session timeline under `claude_dir` and the session tables for dangling references
+///
+/// Restoring a checkpoint whose blobs are gone silently writes empty files, so
+/// these are reported before a restore relies on them. `lock` is called once, to
+/// read the session tables before the scan, so the walk over the checkpoint
+/// store runs with the database unlocked.
+pub fn verify<G: Deref<Target = Connection>>(claude_dir: &Path, lock: impl Fn() -> G) -> Result<IntegrityReport> {
+    Ok(scan(claude_dir, &SessionRows::read(&lock)?))
+}
+
+fn scan(claude_dir: &Path, rows: &SessionRows) -> IntegrityReport {
+    let storage = CheckpointStorage::new(claude_dir.to_path_buf());
+    let mut report = IntegrityReport::default();
+
+    let projects_dir = storage.claude_dir.join("projects");
+    let mut project_ids = subdirectory_names(&projects_dir);
+    project_ids.sort();
+    for project_id in project_ids {
+        let mut session_ids = subdirectory_names(&projects_dir.join(&project_id).join(".timelines"));
+        session_ids.sort();
+        for session_id in session_ids {
+            scan_session(&storage, &project_id, &session_id, &rows.sessions, &mut report);
+        }
+    }
+
+    report.issues.extend(rows.orphaned_messages.iter().map(|(session_id, count)| IntegrityIssue {
+        kind: IntegrityIssueKind::OrphanedMessages,
+        project_id: None,
+        session_id: session_id.clone(),
+        checkpoint_id: None,
+        detail: format!("{} message(s) without a session row", count),
+    }));
+    report
+}
+
+fn find_node_mut<'a>(node: &'a mut TimelineNode, checkpoint_id: &str) -> Option<&'a mut TimelineNode> {
+    if node.checkpoint.id == checkpoint_id {
+        return Some(node);
+    }
+    node.children
+        .iter_mut()
+        .find_map(|child| find_node_mut(child, checkpoint_id))
+}
+
+/// Remove a node below `node`, handing its children to its parent
+fn detach_child(node: &mut TimelineNode, checkpoint_id: &str) -> Option<TimelineNode> {
+    if let Some(position) = node.children.iter().position(|child| child.checkpoint.id == checkpoint_id) {
+        let mut removed = node.children.remove(position);
+        for (offset, mut child) in removed.children.drain(..).enumerate() {
+            child.checkpoint.parent_checkpoint_id = Some(node.checkpoint.id.clone());
+            node.children.insert(position + offset, child);
+        }
+        return Some(removed);
+    }
+    node.children
+        .iter_mut()
+        .find_map(|child| detach_child(child, checkpoint_id))
+}
+
+/// Remove a checkpoint from the timeline without dropping its descendants
+///
+/// Every checkpoint holds a full snapshot, so children stay restorable once
+/// they are attached to the removed checkpoint's parent. Removing the root
+/// promotes its first child.
+fn detach_checkpoint(timeline: &mut SessionTimeline, checkpoint_id: &str) -> Option<TimelineNode> {
+    let root = timeline.root_node.as_mut()?;
+    let removed = if root.checkpoint.id == checkpoint_id {
+        let mut removed = timeline.root_node.take()?;
+        let mut children = std::mem::take(&mut removed.children).into_iter();
+        timeline.root_node = children.next().map(|mut first| {
+            first.checkpoint.parent_checkpoint_id = None;
+            for mut sibling in children {
+                sibling.checkpoint.parent_checkpoint_id = Some(first.checkpoint.id.clone());
+                first.children.push(sibling);
+            }
+            first
+        });
+        removed
+    } else {
+        detach_child(root, checkpoint_id)?
+    };
+
+    timeline.total_checkpoints = timeline.total_checkpoints.saturating_sub(1);
+    if timeline.current_checkpoint_id.as_deref() == Some(checkpoint_id) {
+        timeline.current_checkpoint_id = removed
+            .checkpoint
+            .parent_checkpoint_id
+            .clone()
+            .or_else(|| timeline.root_node.as_ref().map(|root| root.checkpoint.id.clone()));
+    }
+    Some(removed)
+}
+
+fn prune_checkpoint(storage: &CheckpointStorage, project_id: &str, session_id: &str, checkpoint_id: &str) -> Result<()> {
+    let paths = CheckpointPaths::new(&storage.claude_dir, project_id, session_id);
+    let mut timeline = storage.load_timeline(&paths.timeline_file)?;
+    if detach_checkpoint(&mut timeline, checkpoint_id).is_some() {
+        storage.save_timeline(&paths.timeline_file, &timeline)?;
+    }
+    storage.remove_checkpoint(&paths, checkpoint_id)?;
+    storage.garbage_collect_content(project_id, session_id)?;
+    Ok(())
+}
+
+/// Record the reason in both the timeline and the checkpoint's own metadata
+fn mark_unrestorable(
+    storage: &CheckpointStorage,
+    project_id: &str,
+    session_id: &str,
+    checkpoint_id: &str,
+    reason: &str,
+) -> Result<()> {
+    let paths = CheckpointPaths::new(&storage.claude_dir, project_id, session_id);
+    let mut timeline = storage.load_timeline(&paths.timeline_file)?;
+    let node = timeline
+        .root_node
+        .as_mut()
+        .and_then(|root| find_node_mut(root, checkpoint_id))
+        .with_context(|| format!("Checkpoint {} is not in the timeline", checkpoint_id))?;
+    node.checkpoint.unrestorable_reason = Some(reason.to_string());
+    storage.save_timeline(&paths.timeline_file, &timeline)?;
+
+    let metadata_path = paths.checkpoint_metadata_file(checkpoint_id);
+    if let Ok(metadata_json) = fs::read_to_string(&metadata_path) {
+        let mut checkpoint: Checkpoint =
+            serde_json::from_str(&metadata_json).context("Failed to parse checkpoint metadata")?;
+        checkpoint.unrestorable_reason = Some(reason.to_string());
+        fs::write(&metadata_path, serde_json::to_string_pretty(&checkpoint)?)
+            .context("Failed to write checkpoint metadata")?;
+    }
+    Ok(())
+}
+
+fn session_dir(storage: &CheckpointStorage, project_id: &str, session_id: &str) -> PathBuf {
+    storage
+        .claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(".timelines")
+        .join(session_id)
+}
+
+/// Apply `mode` to one issue; `Ok(false)` means the mode leaves this kind alone
+fn repair_issue<G: Deref<Target = Connection>>(
+    storage: &CheckpointStorage,
+    lock: &impl Fn() -> G,
+    issue: &IntegrityIssue,
+    mode: RepairMode,
+) -> Result<bool> {
+    use IntegrityIssueKind::*;
+
+    let project_id = issue.project_id.as_deref().unwrap_or_default();
+    let session_id = issue.session_id.as_str();
+    let checkpoint_id = issue.checkpoint_id.as_deref().unwrap_or_default();
+
+    match (mode, issue.kind) {
+        (RepairMode::Prune, OrphanedTimeline) => {
+            fs::remove_dir_all(session_dir(storage, project_id, session_id))
+                .context("Failed to remove timeline directory")?;
+        }
+        (RepairMode::Prune, MissingCheckpointData | MissingBlob) => {
+            prune_checkpoint(storage, project_id, session_id, checkpoint_id)?;
+        }
+        (RepairMode::Prune, UntrackedCheckpoint) => {
+            let paths = CheckpointPaths::new(&storage.claude_dir, project_id, session_id);
+            storage.remove_checkpoint(&paths, checkpoint_id)?;
+        }
+        (RepairMode::Prune, OrphanedMessages) => {
+            lock()
+                .execute("DELETE FROM session_messages WHERE session_id = ?1", [session_id])
+                .context("Failed to delete session messages")?;
+        }
+        (RepairMode::MarkUnrestorable, MissingCheckpointData | MissingBlob) => {
+            mark_unrestorable(storage, project_id, session_id, checkpoint_id, &issue.detail)?;
+        }
+        // An unreadable timeline may still be recovered by hand, so it is only reported
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Fix the issues `verify` finds according to `mode`, then scan again
+///
+/// `lock` is called for each database read or write, never across file operations.
+pub fn repair<G: Deref<Target = Connection>>(
+    claude_dir: &Path,
+    lock: impl Fn() -> G,
+    mode: RepairMode,
+) -> Result<RepairReport> {
+    let storage = CheckpointStorage::new(claude_dir.to_path_buf());
+    let mut repaired = Vec::new();
+
+    for issue in verify(claude_dir, &lock)?.issues {
+        match repair_issue(&storage, &lock, &issue, mode) {
+            Ok(true) => repaired.push(issue),
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to repair {:?} in session {}: {:#}", issue.kind, issue.session_id, e),
+        }
+    }
+
+    Ok(RepairReport {
+        mode,
+        repaired,
+        remaining: verify(claude_dir, &lock)?.issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{CheckpointMetadata, FileSnapshot};
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    const PROJECT: &str = "test-project";
+
+    fn checkpoint(id: &str, session_id: &str, parent: Option<&str>) -> Checkpoint {
+        Checkpoint {
+            id: id.to_string(),
+            session_id: session_id.to_string(),
+            project_id: PROJECT.to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: None,
+            parent_checkpoint_id: parent.map(str::to_string),
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "test".to_string(),
+                user_prompt: String::new(),
+                file_changes: 1,
+                snapshot_size: 0,
+            },
+            unrestorable_reason: None,
+        }
+    }
+
+    /// A session with a JSONL file and a two-checkpoint timeline
+    fn create_session(storage: &CheckpointStorage, session_id: &str) {
+        let project_dir = storage.claude_dir.join("projects").join(PROJECT);
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(project_dir.join(format!("{}.jsonl", session_id)), "{}\n").unwrap();
+
+        storage.init_storage(PROJECT, session_id).unwrap();
+        for (id, parent, content) in [("cp-1", None, "first"), ("cp-2", Some("cp-1"), "second")] {
+            let snapshot = FileSnapshot {
+                checkpoint_id: id.to_string(),
+                file_path: PathBuf::from("main.rs"),
+                content: content.to_string(),
+                hash: CheckpointStorage::calculate_file_hash(content),
+                is_deleted: false,
+                permissions: None,
+                size: content.len() as u64,
+            };
+            storage
+                .save_checkpoint(PROJECT, session_id, &checkpoint(id, session_id, parent), vec![snapshot], "{}")
+                .unwrap();
+        }
+    }
+
+    fn kinds(issues: &[IntegrityIssue]) -> Vec<(IntegrityIssueKind, String, Option<String>)> {
+        issues
+            .iter()
+            .map(|issue| (issue.kind, issue.session_id.clone(), issue.checkpoint_id.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_and_prunes_dangling_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        let conn = Connection::open_in_memory().unwrap();
+        crate::commands::session_manager::create_session_tables(&conn).unwrap();
+
+        create_session(&storage, "healthy");
+        create_session(&storage, "deleted");
+        create_session(&storage, "broken");
+        // The session behind "deleted" is gone, and "broken" lost cp-1's blob
+        fs::remove_file(storage.claude_dir.join("projects").join(PROJECT).join("deleted.jsonl")).unwrap();
+        let paths = CheckpointPaths::new(&storage.claude_dir, PROJECT, "broken");
+        fs::remove_file(paths.file_snapshot_path("cp-1", &CheckpointStorage::calculate_file_hash("first"))).unwrap();
+        // Messages left behind by a session row deleted before foreign keys were enforced
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        conn.execute(
+            "INSERT INTO session_messages (id, session_id, project_id, sequence_number, message_type, content, timestamp)
+             VALUES ('m1', 'ghost', ?1, 0, 'user', 'hi', 0)",
+            [PROJECT],
+        )
+        .unwrap();
+
+        let report = verify(temp_dir.path(), || &conn).unwrap();
+        assert_eq!(report.sessions_scanned, 3);
+        assert_eq!(
+            kinds(&report.issues),
+            vec![
+                (IntegrityIssueKind::MissingBlob, "broken".to_string(), Some("cp-1".to_string())),
+                (IntegrityIssueKind::OrphanedTimeline, "deleted".to_string(), None),
+                (IntegrityIssueKind::OrphanedMessages, "ghost".to_string(), None),
+            ]
+        );
+
+        let repair_report = repair(temp_dir.path(), || &conn, RepairMode::Prune).unwrap();
+        assert_eq!(repair_report.repaired.len(), 3);
+        assert!(repair_report.remaining.is_empty());
+        assert!(!session_dir(&storage, PROJECT, "deleted").exists());
+        assert!(session_dir(&storage, PROJECT, "healthy").exists());
+
+        // cp-2 survives as the new root of the broken session
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        let root = timeline.root_node.unwrap();
+        assert_eq!(root.checkpoint.id, "cp-2");
+        assert_eq!(root.checkpoint.parent_checkpoint_id, None);
+        assert_eq!(timeline.total_checkpoints, 1);
+        assert!(!paths.checkpoint_dir("cp-1").exists());
+    }
+
+    #[test]
+    fn test_missing_blob_marks_checkpoint_unrestorable() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        let conn = Connection::open_in_memory().unwrap();
+
+        create_session(&storage, "broken");
+        let paths = CheckpointPaths::new(&storage.claude_dir, PROJECT, "broken");
+        fs::remove_file(paths.file_snapshot_path("cp-2", &CheckpointStorage::calculate_file_hash("second"))).unwrap();
+
+        let repair_report = repair(temp_dir.path(), || &conn, RepairMode::MarkUnrestorable).unwrap();
+        assert_eq!(kinds(&repair_report.repaired), vec![(
+            IntegrityIssueKind::MissingBlob,
+            "broken".to_string(),
+            Some("cp-2".to_string()),
+        )]);
+        assert!(repair_report.remaining.is_empty());
+
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        assert!(timeline.find_checkpoint("cp-2").unwrap().checkpoint.unrestorable_reason.is_some());
+        assert!(timeline.find_checkpoint("cp-1").unwrap().checkpoint.unrestorable_reason.is_none());
+        let (checkpoint, _, _) = storage.load_checkpoint(PROJECT, "broken", "cp-2").unwrap();
+        assert!(checkpoint.unrestorable_reason.unwrap().contains("main.rs"));
+    }
+}
@@ -7,6 +7,8 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::windows_command::long_path;
+
 use super::{
     storage::{self, CheckpointStorage},
     Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
@@ -107,7 +109,7 @@ impl CheckpointManager {
     /// Track a file modification
     pub async fn track_file_modification(&self, file_path: &str) -> Result<()> {
         let mut tracker = self.file_tracker.write().await;
-        let full_path = self.project_path.join(file_path);
+        let full_path = long_path(&self.project_path.join(file_path));
 
         // Read current file state
         let (hash, exists, _size, modified) = if full_path.exists() {
@@ -225,7 +227,7 @@ impl CheckpointManager {
             Ok(())
         }
         let mut all_files = Vec::new();
-        let project_dir = &self.project_path;
+        let project_dir = long_path(&self.project_path);
         let _ = collect_files(project_dir.as_path(), project_dir.as_path(), &mut all_files);
         for rel in all_files {
             if let Some(p) = rel.to_str() {
@@ -267,6 +269,7 @@ impl CheckpointManager {
                     &file_snapshots,
                 ),
             },
+            unrestorable_reason: None,
         };
 
         // Save checkpoint
@@ -408,7 +411,7 @@ impl CheckpointManager {
                 continue;
             }
 
-            let full_path = self.project_path.join(rel_path);
+            let full_path = long_path(&self.project_path.join(rel_path));
 
             let (content, exists, permissions, size, current_hash) = if full_path.exists() {
                 let content = fs::read_to_string(&full_path).unwrap_or_default();
@@ -455,6 +458,11 @@ impl CheckpointManager {
             self.storage
                 .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
 
+        // Missing content would otherwise be restored as empty files
+        if let Some(reason) = &checkpoint.unrestorable_reason {
+            anyhow::bail!("Checkpoint {} cannot be restored: {}", checkpoint_id, reason);
+        }
+
         // First, collect all files currently in the project to handle deletions
         fn collect_all_project_files(
             dir: &std::path::Path,
@@ -482,9 +490,9 @@ impl CheckpointManager {
             Ok(())
         }
 
+        let project_dir = long_path(&self.project_path);
         let mut current_files = Vec::new();
-        let _ =
-            collect_all_project_files(&self.project_path, &self.project_path, &mut current_files);
+        let _ = collect_all_project_files(&project_dir, &project_dir, &mut current_files);
 
         // Create a set of files that should exist after restore
         let mut checkpoint_files = std::collections::HashSet::new();
@@ -501,7 +509,7 @@ impl CheckpointManager {
         for current_file in current_files {
             if !checkpoint_files.contains(&current_file) {
                 // This file exists now but not in the checkpoint, so delete it
-                let full_path = self.project_path.join(&current_file);
+                let full_path = long_path(&self.project_path.join(&current_file));
                 match fs::remove_file(&full_path) {
                     Ok(_) => {
                         files_processed += 1;
@@ -549,7 +557,7 @@ impl CheckpointManager {
         }
 
         // Clean up any empty directories left after file deletion
-        let _ = remove_empty_dirs(&self.project_path, &self.project_path);
+        let _ = remove_empty_dirs(&project_dir, &project_dir);
 
         // Restore files from checkpoint
         for snapshot in &file_snapshots {
@@ -600,7 +608,7 @@ impl CheckpointManager {
 
     /// Restore a single file from snapshot
     async fn restore_file_snapshot(&self, snapshot: &FileSnapshot) -> Result<()> {
-        let full_path = self.project_path.join(&snapshot.file_path);
+        let full_path = long_path(&self.project_path.join(&snapshot.file_path));
 
         if snapshot.is_deleted {
             // Delete the file if it exists
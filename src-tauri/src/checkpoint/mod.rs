@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+pub mod diff;
+pub mod gc;
+pub mod integrity;
 pub mod manager;
 pub mod state;
 pub mod storage;
@@ -27,6 +30,9 @@ pub struct Checkpoint {
     pub parent_checkpoint_id: Option<String>,
     /// Metadata about the checkpoint
     pub metadata: CheckpointMetadata,
+    /// Why this checkpoint can no longer be restored, set by integrity repair
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unrestorable_reason: Option<String>,
 }
 
 /// Metadata associated with a checkpoint
@@ -142,6 +148,7 @@ pub struct CheckpointResult {
 
 /// Diff between two checkpoints
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CheckpointDiff {
     /// Source checkpoint ID
     pub from_checkpoint_id: String,
@@ -153,21 +160,34 @@ pub struct CheckpointDiff {
     pub added_files: Vec<PathBuf>,
     /// Files that were deleted
     pub deleted_files: Vec<PathBuf>,
+    /// Files that were moved without content changes
+    #[serde(default)]
+    pub renamed_files: Vec<FileDiff>,
     /// Token usage difference
     pub token_delta: i64,
 }
 
 /// Diff for a single file
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FileDiff {
     /// File path
     pub path: PathBuf,
+    /// Previous path, for renamed files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<PathBuf>,
     /// Number of additions
     pub additions: usize,
     /// Number of deletions
     pub deletions: usize,
+    /// Binary files are reported without line changes
+    #[serde(default)]
+    pub is_binary: bool,
     /// Unified diff content (optional)
     pub diff_content: Option<String>,
+    /// Structured hunks, when requested with `DiffFormat::Structured`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hunks: Option<Vec<diff::DiffHunk>>,
 }
 
 impl Default for CheckpointStrategy {
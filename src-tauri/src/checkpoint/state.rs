@@ -101,7 +101,6 @@ impl CheckpointState {
     /// Clears all managers
     ///
     /// This is useful for cleanup during application shutdown
-    #[allow(dead_code)]
     pub async fn clear_all(&self) {
         let mut managers = self.managers.write().await;
         managers.clear();
@@ -19,7 +19,8 @@ impl CheckpointStorage {
     /// Create a new checkpoint storage instance
     pub fn new(claude_dir: PathBuf) -> Self {
         Self {
-            claude_dir,
+            // Deep project ids can push checkpoint paths past MAX_PATH on Windows
+            claude_dir: crate::windows_command::long_path(&claude_dir),
             compression_level: 3, // Default zstd compression level
         }
     }
@@ -54,6 +55,9 @@ impl CheckpointStorage {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
         let checkpoint_dir = paths.checkpoint_dir(&checkpoint.id);
 
+        // Keep garbage collection from sweeping blobs before their references are written
+        let _pool = super::gc::pool_save_guard();
+
         // Create checkpoint directory
         fs::create_dir_all(&checkpoint_dir).context("Failed to create checkpoint directory")?;
 
@@ -386,7 +390,7 @@ impl CheckpointStorage {
     }
 
     /// Remove a checkpoint and its associated files
-    fn remove_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<()> {
+    pub(crate) fn remove_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<()> {
         // Remove checkpoint metadata directory
         let checkpoint_dir = paths.checkpoint_dir(checkpoint_id);
         if checkpoint_dir.exists() {
@@ -408,53 +412,6 @@ impl CheckpointStorage {
     /// Garbage collect unreferenced content from the content pool
     pub fn garbage_collect_content(&self, project_id: &str, session_id: &str) -> Result<usize> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
-        let content_pool_dir = paths.files_dir.join("content_pool");
-        let refs_dir = paths.files_dir.join("refs");
-
-        if !content_pool_dir.exists() {
-            return Ok(0);
-        }
-
-        // Collect all referenced hashes
-        let mut referenced_hashes = std::collections::HashSet::new();
-
-        if refs_dir.exists() {
-            for checkpoint_entry in fs::read_dir(&refs_dir)? {
-                let checkpoint_dir = checkpoint_entry?.path();
-                if checkpoint_dir.is_dir() {
-                    for ref_entry in fs::read_dir(&checkpoint_dir)? {
-                        let ref_path = ref_entry?.path();
-                        if ref_path.extension().and_then(|e| e.to_str()) == Some("json") {
-                            if let Ok(ref_json) = fs::read_to_string(&ref_path) {
-                                if let Ok(ref_metadata) =
-                                    serde_json::from_str::<serde_json::Value>(&ref_json)
-                                {
-                                    if let Some(hash) = ref_metadata["hash"].as_str() {
-                                        referenced_hashes.insert(hash.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // Remove unreferenced content
-        let mut removed_count = 0;
-        for entry in fs::read_dir(&content_pool_dir)? {
-            let content_file = entry?.path();
-            if content_file.is_file() {
-                if let Some(hash) = content_file.file_name().and_then(|n| n.to_str()) {
-                    if !referenced_hashes.contains(hash) {
-                        if fs::remove_file(&content_file).is_ok() {
-                            removed_count += 1;
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(removed_count)
+        Ok(super::gc::sweep_session(&paths)?.blobs_removed)
     }
 }
@@ -0,0 +1,208 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::path::Path;
+use tauri::State;
+
+use super::agents::{is_pid_running, session_jsonl_path, AgentDb};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryOutcome {
+    /// The session ended normally before the restart
+    Completed,
+    /// The session stopped partway through
+    Interrupted,
+}
+
+/// What happened to one run found running without a process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoveredRun {
+    pub run_id: i64,
+    pub outcome: RecoveryOutcome,
+    /// Messages in the session file at recovery time
+    pub output_messages: usize,
+    /// Session to pass to `resume_claude_code` to continue an interrupted run
+    pub resume_session_id: Option<String>,
+}
+
+/// Whether the last message of a session file closes the conversation
+fn session_finished(jsonl: &str) -> bool {
+    let Some(last) = jsonl
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    else {
+        return false;
+    };
+    match last["type"].as_str() {
+        Some("result") => true,
+        Some("assistant") => last["message"]["stop_reason"].as_str() == Some("end_turn"),
+        _ => false,
+    }
+}
+
+/// Settle every running run whose process `is_alive` reports gone
+///
+/// A restart between a run's process starting and exiting leaves the run marked
+/// running forever. Each one is settled from its session file: a session whose last
+/// turn ended normally is completed, anything else interrupted. Session files are
+/// only read, so an interrupted run can be continued by resuming its session.
+/// Runs whose process survived the restart are left for
+/// `cleanup_finished_processes` to settle when they exit.
+pub fn recover_agent_runs(
+    conn: &Connection,
+    projects_dir: &Path,
+    is_alive: impl Fn(i64) -> bool,
+) -> Result<Vec<RecoveredRun>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, pid, project_path, session_id FROM agent_runs WHERE status = 'running'")
+        .map_err(|e| e.to_string())?;
+    let in_flight = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+
+    let mut recovered = Vec::new();
+    for (run_id, pid, project_path, session_id) in in_flight {
+        if pid.is_some_and(&is_alive) {
+            continue;
+        }
+        let output = if session_id.is_empty() {
+            None
+        } else {
+            std::fs::read_to_string(session_jsonl_path(projects_dir, &project_path, &session_id)).ok()
+        };
+        let (outcome, status) = match &output {
+            Some(jsonl) if session_finished(jsonl) => (RecoveryOutcome::Completed, "completed"),
+            _ => (RecoveryOutcome::Interrupted, "interrupted"),
+        };
+        let updated = conn
+            .execute(
+                "UPDATE agent_runs SET status = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params![status, run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            continue;
+        }
+
+        let output_messages = output
+            .as_deref()
+            .map_or(0, |jsonl| jsonl.lines().filter(|line| !line.trim().is_empty()).count());
+        let resume_session_id = (outcome == RecoveryOutcome::Interrupted && output_messages > 0).then_some(session_id);
+        log::info!(
+            "Recovered agent run {} as {} with {} message(s) of output",
+            run_id,
+            status,
+            output_messages
+        );
+        recovered.push(RecoveredRun { run_id, outcome, output_messages, resume_session_id });
+    }
+    Ok(recovered)
+}
+
+/// Settle in-flight runs from the app's database; called once at startup
+pub fn recover_on_startup(db: &AgentDb) {
+    let projects_dir = match super::claude_dir::claude_dir_path() {
+        Ok(dir) => dir.join("projects"),
+        Err(e) => {
+            log::warn!("Skipping agent run recovery: {}", e);
+            return;
+        }
+    };
+    match recover_agent_runs(&db.lock_conn(), &projects_dir, is_pid_running) {
+        Ok(recovered) if !recovered.is_empty() => {
+            let interrupted = recovered.iter().filter(|run| run.outcome == RecoveryOutcome::Interrupted).count();
+            log::info!(
+                "Recovered {} agent run(s) left running by the last session, {} interrupted",
+                recovered.len(),
+                interrupted
+            );
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to recover agent runs: {}", e),
+    }
+}
+
+/// Settle runs marked running whose process no longer exists
+#[tauri::command]
+pub async fn recover_interrupted_agent_runs(db: State<'_, AgentDb>) -> Result<Vec<RecoveredRun>, String> {
+    let projects_dir = super::claude_dir::claude_dir_path()?.join("projects");
+    recover_agent_runs(&*db.lock_conn(), &projects_dir, is_pid_running)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupted_run_keeps_its_partial_output() {
+        let projects = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, project_path TEXT NOT NULL, session_id TEXT NOT NULL,
+             status TEXT NOT NULL, pid INTEGER, completed_at TEXT)",
+            [],
+        )
+        .unwrap();
+        let runs = [(1, "sess-partial", Some(101)), (2, "sess-done", Some(102)), (3, "", None), (4, "sess-live", Some(104))];
+        for (id, session_id, pid) in runs {
+            conn.execute(
+                "INSERT INTO agent_runs (id, project_path, session_id, status, pid) VALUES (?1, '/work/app', ?2, 'running', ?3)",
+                params![id, session_id, pid],
+            )
+            .unwrap();
+        }
+
+        let partial = concat!(
+            r#"{"type":"user","message":{"role":"user","content":"Refactor the parser"}}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","name":"Edit"}],"stop_reason":"tool_use"}}"#,
+            "\n"
+        );
+        let finished = concat!(
+            r#"{"type":"user","message":{"role":"user","content":"Summarize"}}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Done"}],"stop_reason":"end_turn"}}"#,
+            "\n"
+        );
+        let partial_path = session_jsonl_path(projects.path(), "/work/app", "sess-partial");
+        std::fs::create_dir_all(partial_path.parent().unwrap()).unwrap();
+        std::fs::write(&partial_path, partial).unwrap();
+        std::fs::write(session_jsonl_path(projects.path(), "/work/app", "sess-done"), finished).unwrap();
+
+        let recovered = recover_agent_runs(&conn, projects.path(), |pid| pid == 104).unwrap();
+        assert_eq!(
+            recovered,
+            vec![
+                RecoveredRun {
+                    run_id: 1,
+                    outcome: RecoveryOutcome::Interrupted,
+                    output_messages: 2,
+                    resume_session_id: Some("sess-partial".to_string()),
+                },
+                RecoveredRun { run_id: 2, outcome: RecoveryOutcome::Completed, output_messages: 2, resume_session_id: None },
+                RecoveredRun { run_id: 3, outcome: RecoveryOutcome::Interrupted, output_messages: 0, resume_session_id: None },
+            ]
+        );
+
+        let status = |id: i64| -> String {
+            conn.query_row("SELECT status FROM agent_runs WHERE id = ?1", params![id], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(status(1), "interrupted");
+        assert_eq!(status(2), "completed");
+        assert_eq!(status(4), "running");
+        assert_eq!(std::fs::read_to_string(&partial_path).unwrap(), partial);
+        assert!(recover_agent_runs(&conn, projects.path(), |_| false).unwrap().iter().all(|run| run.run_id == 4));
+    }
+}
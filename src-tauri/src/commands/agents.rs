@@ -5,7 +5,6 @@
 // use super::ollama_execution_service::OllamaExecutionService;
 // use std::sync::Arc; // Unused import
 use chrono;
-use dirs;
 use log::{debug, error, info, warn};
 use uuid;
 use reqwest;
@@ -13,14 +12,20 @@ use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_shell::ShellExt;
 use tauri_plugin_shell::process::CommandEvent;
 use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
 use tokio::process::Command;
 
+use crate::models::ModelProvider;
+use super::project_env::{apply_project_env, load_project_env, resolve_project_env, ProjectEnvVar};
+use super::settings_diff::{text, unset, Setting};
+
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
@@ -46,6 +51,9 @@ pub struct Agent {
     pub enable_file_write: bool,
     pub enable_network: bool,
     pub hooks: Option<String>, // JSON string of hooks configuration
+    /// Provider runs use unless overridden; inferred from `model` when unset
+    #[serde(default)]
+    pub default_provider: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -61,7 +69,7 @@ pub struct AgentRun {
     pub model: String,
     pub project_path: String,
     pub session_id: String, // UUID session ID from Claude Code
-    pub status: String,     // 'pending', 'running', 'completed', 'failed', 'cancelled'
+    pub status: String,     // 'pending', 'running', 'completed', 'failed', 'cancelled', 'interrupted'
     pub pid: Option<u32>,
     pub process_started_at: Option<String>,
     pub created_at: String,
@@ -103,37 +111,128 @@ pub struct AgentData {
     pub default_task: Option<String>,
     pub model: String,
     pub hooks: Option<String>,
+    #[serde(default)]
+    pub default_provider: Option<String>,
+}
+
+/// Columns read by `agent_from_row`, in order
+const AGENT_COLUMNS: &str = "id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at, default_provider";
+
+/// Build an agent from a row selected with `AGENT_COLUMNS`
+fn agent_from_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+    Ok(Agent {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        icon: row.get(2)?,
+        system_prompt: row.get(3)?,
+        default_task: row.get(4)?,
+        model: row.get::<_, String>(5).unwrap_or_else(|_| "sonnet".to_string()),
+        enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
+        enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
+        enable_network: row.get::<_, bool>(8).unwrap_or(false),
+        hooks: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        default_provider: row.get(12)?,
+    })
+}
+
+/// Model and provider an agent run executes with
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunModel {
+    pub model: String,
+    pub provider: ModelProvider,
+}
+
+/// Provider for `model`, checked against an explicitly named `provider`
+///
+/// Models missing from the alias table are taken to belong to the named
+/// provider, or guessed from the name when none is given.
+pub(crate) fn model_provider(model: &str, provider: Option<&str>) -> Result<ModelProvider, String> {
+    let named = match provider.filter(|p| !p.trim().is_empty()) {
+        Some(name) => Some(ModelProvider::parse(name).ok_or_else(|| {
+            let known: Vec<&str> = ModelProvider::ALL.iter().map(|p| p.as_str()).collect();
+            format!("Unknown provider '{}'; expected one of: {}", name, known.join(", "))
+        })?),
+        None => None,
+    };
+
+    match (crate::models::canonicalize(model), named) {
+        (Some(known), Some(named)) if known.provider != named => Err(format!(
+            "Model '{}' is a {} model and cannot run on {}",
+            model,
+            known.provider.as_str(),
+            named.as_str()
+        )),
+        (Some(known), _) => Ok(known.provider),
+        (None, Some(named)) => Ok(named),
+        (None, None) if model.contains("gemini") => Ok(ModelProvider::Gemini),
+        (None, None) if model.contains(':') => Ok(ModelProvider::Ollama),
+        (None, None) => Ok(ModelProvider::Claude),
+    }
 }
 
+/// Model and provider for a run of `agent`, applying the run's overrides
+///
+/// A run that overrides the model without naming a provider gets that model's
+/// provider rather than the agent's default.
+pub fn resolve_run_model(
+    agent: &Agent,
+    model: Option<String>,
+    provider: Option<String>,
+) -> Result<RunModel, String> {
+    let (model, provider) = match model.filter(|m| !m.trim().is_empty()) {
+        Some(model) => (model, provider),
+        None => (agent.model.clone(), provider.or_else(|| agent.default_provider.clone())),
+    };
+    let provider = model_provider(&model, provider.as_deref())?;
+    Ok(RunModel { model, provider })
+}
+
+pub(crate) const SETTINGS: &[Setting] = &[
+    Setting::new("claude_binary_path", text, unset),
+    Setting::new("claude_installation_preference", text, system_installation),
+];
+
+fn system_installation() -> JsonValue {
+    JsonValue::String("system".to_string())
+}
+
+/// How long sqlite waits on a database file locked by another connection
+pub(crate) const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Database connection state
 pub struct AgentDb(pub Mutex<Connection>);
 
+/// Acquire `mutex`, waiting for however long its current holder needs it.
+///
+/// Long holders such as VACUUM or a backup are legitimate, so there is no time
+/// limit. A poisoned lock is logged and recovered: the panic that poisoned it
+/// doesn't leave the sqlite connection itself in a bad state.
+fn lock_recovering<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        error!("Database connection lock was poisoned by a panicking command; recovering it");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
 impl AgentDb {
+    /// Lock the shared connection, waiting for other commands to release it
+    pub fn lock_conn(&self) -> MutexGuard<'_, Connection> {
+        lock_recovering(&self.0)
+    }
+
     /// List all agents from the database
     pub fn list_agents(&self) -> Result<Vec<Agent>, String> {
-        let conn = self.0.lock().map_err(|e| e.to_string())?;
+        let conn = self.lock_conn();
 
         let mut stmt = conn
-            .prepare("SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents ORDER BY created_at DESC")
+            .prepare(&format!("SELECT {} FROM agents ORDER BY created_at DESC", AGENT_COLUMNS))
             .map_err(|e| e.to_string())?;
 
         let agents = stmt
-            .query_map([], |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            })
+            .query_map([], agent_from_row)
             .map_err(|e| e.to_string())?;
 
         let mut result = Vec::new();
@@ -146,7 +245,7 @@ impl AgentDb {
 
     /// Create a new agent run
     pub fn create_agent_run(&self, agent_id: i64, task: String, project_path: String, session_id: String) -> Result<String, String> {
-        let conn = self.0.lock().map_err(|e| e.to_string())?;
+        let conn = self.lock_conn();
         
         let run_id = uuid::Uuid::new_v4().to_string();
         let now = chrono::Utc::now().to_rfc3339();
@@ -232,17 +331,17 @@ impl AgentRunMetrics {
     }
 }
 
-/// Read JSONL content from a session file
-pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<String, String> {
-    let claude_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude")
-        .join("projects");
-
+/// Session file of `session_id` under Claude's `projects` directory
+pub fn session_jsonl_path(projects_dir: &Path, project_path: &str, session_id: &str) -> PathBuf {
     // Encode project path to match Claude Code's directory naming
     let encoded_project = project_path.replace('/', "-");
-    let project_dir = claude_dir.join(&encoded_project);
-    let session_file = project_dir.join(format!("{}.jsonl", session_id));
+    projects_dir.join(encoded_project).join(format!("{}.jsonl", session_id))
+}
+
+/// Read JSONL content from a session file
+pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<String, String> {
+    let claude_dir = super::claude_dir::claude_dir_path()?.join("projects");
+    let session_file = session_jsonl_path(&claude_dir, project_path, session_id);
 
     if !session_file.exists() {
         return Err(format!(
@@ -358,6 +457,8 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
 
     let db_path = app_dir.join("agents.db");
     let conn = Connection::open(db_path)?;
+    // Agent runs write through their own connections; wait for them instead of failing
+    conn.busy_timeout(DB_BUSY_TIMEOUT)?;
 
     // Create agents table
     conn.execute(
@@ -372,6 +473,7 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             enable_file_write BOOLEAN NOT NULL DEFAULT 1,
             enable_network BOOLEAN NOT NULL DEFAULT 0,
             hooks TEXT,
+            default_provider TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
         )",
@@ -397,6 +499,7 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agents ADD COLUMN enable_network BOOLEAN DEFAULT 0",
         [],
     );
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN default_provider TEXT", []);
 
     // Create agent_runs table
     conn.execute(
@@ -430,6 +533,10 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
         [],
     );
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN notify_on_complete BOOLEAN DEFAULT 0",
+        [],
+    );
 
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
@@ -491,6 +598,11 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         // Continue anyway - dashboard migration is not critical for basic app functionality
     }
 
+    // Routing decisions, with the outcomes runs attach when they finish
+    if let Err(e) = super::routing_decisions::create_routing_decision_table(&conn) {
+        error!("Failed to create routing decisions table: {}", e);
+    }
+
     // Seed the current working project
     if let Err(e) = seed_current_project(&conn) {
         warn!("Failed to seed current project: {}", e);
@@ -503,31 +615,14 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
 /// List all agents
 #[tauri::command]
 pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     let mut stmt = conn
-        .prepare("SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents ORDER BY created_at DESC")
+        .prepare(&format!("SELECT {} FROM agents ORDER BY created_at DESC", AGENT_COLUMNS))
         .map_err(|e| e.to_string())?;
 
     let agents = stmt
-        .query_map([], |row| {
-            Ok(Agent {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                icon: row.get(2)?,
-                system_prompt: row.get(3)?,
-                default_task: row.get(4)?,
-                model: row
-                    .get::<_, String>(5)
-                    .unwrap_or_else(|_| "sonnet".to_string()),
-                enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                hooks: row.get(9)?,
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
-            })
-        })
+        .query_map([], agent_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -548,16 +643,18 @@ pub async fn create_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    default_provider: Option<String>,
 ) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
+    model_provider(&model, default_provider.as_deref())?;
+    let conn = db.lock_conn();
     let enable_file_read = enable_file_read.unwrap_or(true);
     let enable_file_write = enable_file_write.unwrap_or(true);
     let enable_network = enable_network.unwrap_or(false);
 
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks],
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, default_provider) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, default_provider],
     )
     .map_err(|e| e.to_string())?;
 
@@ -566,24 +663,9 @@ pub async fn create_agent(
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -604,13 +686,15 @@ pub async fn update_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    default_provider: Option<String>,
 ) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
+    model_provider(&model, default_provider.as_deref())?;
+    let conn = db.lock_conn();
 
     // Build dynamic query based on provided parameters
     let mut query =
-        "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, hooks = ?6"
+        "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, hooks = ?6, default_provider = ?7"
             .to_string();
     let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![
         Box::new(name),
@@ -619,8 +703,9 @@ pub async fn update_agent(
         Box::new(default_task),
         Box::new(model),
         Box::new(hooks),
+        Box::new(default_provider),
     ];
-    let mut param_count = 6;
+    let mut param_count = 7;
 
     if let Some(efr) = enable_file_read {
         param_count += 1;
@@ -651,24 +736,9 @@ pub async fn update_agent(
     // Fetch the updated agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -678,7 +748,7 @@ pub async fn update_agent(
 /// Delete an agent
 #[tauri::command]
 pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
         .map_err(|e| e.to_string())?;
@@ -689,28 +759,13 @@ pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String>
 /// Get a single agent by ID
 #[tauri::command]
 pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get::<_, String>(5).unwrap_or_else(|_| "sonnet".to_string()),
-                    enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                    enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                    enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -723,7 +778,7 @@ pub async fn list_agent_runs(
     db: State<'_, AgentDb>,
     agent_id: Option<i64>,
 ) -> Result<Vec<AgentRun>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     let query = if agent_id.is_some() {
         "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
@@ -774,7 +829,7 @@ pub async fn list_agent_runs(
 /// Get a single agent run by ID
 #[tauri::command]
 pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     let run = conn
         .query_row(
@@ -831,8 +886,16 @@ pub async fn list_agent_runs_with_metrics(
     Ok(runs_with_metrics)
 }
 
-// Temporarily disabled due to compilation issues with execution services
-/*
+/// Longest task passed to the Claude CLI as an argument; longer ones go over stdin
+///
+/// Keeps runs clear of the 8191-character cmd.exe command line limit on Windows.
+const TASK_ARG_MAX_LEN: usize = 8_000;
+
+/// Start a run of an agent, returning its run id
+///
+/// The run uses the requested model and provider, falling back to the agent's
+/// defaults. Agent runs are executed by the Claude CLI, so a run that resolves
+/// to another provider is rejected before anything is recorded.
 #[tauri::command]
 pub async fn execute_agent(
     app: AppHandle,
@@ -840,46 +903,85 @@ pub async fn execute_agent(
     project_path: String,
     task: String,
     model: Option<String>,
+    provider: Option<String>,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
     info!("Executing agent {} with task: {}", agent_id, task);
 
     let agent = get_agent(db.clone(), agent_id).await?;
-    let execution_model = model.unwrap_or(agent.model.clone());
-
-    let execution_service: Arc<dyn ExecutionService> = if execution_model.starts_with("claude") {
-        Arc::new(ClaudeExecutionService)
-    } else if execution_model.starts_with("gemini") {
-        Arc::new(GeminiExecutionService)
-    } else {
-        Arc::new(OllamaExecutionService)
-    };
+    let run_model = resolve_run_model(&agent, model, provider)?;
+    if run_model.provider != ModelProvider::Claude {
+        return Err(format!(
+            "Agent runs need a Claude model; '{}' is a {} model",
+            run_model.model,
+            run_model.provider.as_str()
+        ));
+    }
 
-    let request = ExecutionRequest {
-        agent,
-        task,
-        project_path,
-        model: execution_model,
+    let run_id = {
+        let conn = db.lock_conn();
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![agent_id, agent.name, agent.icon, task, run_model.model, project_path, ""],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.last_insert_rowid()
     };
 
-    let result = execution_service.execute(app, db, request, registry).await?;
-
-    Ok(result.run_id)
+    let claude_path = find_claude_binary(&app)?;
+    let use_sidecar = should_use_sidecar(&claude_path);
+    // The sidecar has no stdin, so it always gets the task as an argument
+    let task_via_stdin = !use_sidecar && task.len() > TASK_ARG_MAX_LEN;
+    let mut args = vec!["-p".to_string()];
+    if !task_via_stdin {
+        args.push(task.clone());
+    }
+    args.extend([
+        "--system-prompt".to_string(),
+        agent.system_prompt.clone(),
+        "--model".to_string(),
+        run_model.model.clone(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+    ]);
+
+    if use_sidecar {
+        spawn_agent_sidecar(
+            app, run_id, agent_id, agent.name, args, project_path, task, run_model.model, db, registry,
+            task_via_stdin,
+        )
+        .await
+    } else {
+        spawn_agent_system(
+            app, run_id, agent_id, agent.name, claude_path, args, project_path, task, run_model.model, db,
+            registry, task_via_stdin,
+        )
+        .await
+    }
 }
-*/
-
 /// Determines whether to use sidecar or system binary execution for agents
 fn should_use_sidecar(claude_path: &str) -> bool {
     claude_path == "claude-code"
 }
 
+/// Report pinned and detected versions of bundled sidecars, with their integrity status
+#[tauri::command]
+pub async fn get_sidecar_versions() -> Result<Vec<crate::sidecar_wrapper::SidecarStatus>, String> {
+    crate::sidecar_wrapper::sidecar_statuses()
+}
+
 /// Creates a sidecar command for agent execution
 fn create_agent_sidecar_command(
     app: &AppHandle,
     args: Vec<String>,
     project_path: &str,
+    project_env: &[ProjectEnvVar],
 ) -> Result<tauri_plugin_shell::process::Command, String> {
+    crate::sidecar_wrapper::ensure_sidecar_verified("claude-code")?;
+
     let mut sidecar_cmd = app
         .shell()
         .sidecar("claude-code")
@@ -902,6 +1004,11 @@ fn create_agent_sidecar_command(
             sidecar_cmd = sidecar_cmd.env(&key, &value);
         }
     }
+
+    // The sidecar inherits this process's PATH, so project entries go ahead of it
+    for (name, value) in resolve_project_env(project_env, std::env::var_os("PATH")) {
+        sidecar_cmd = sidecar_cmd.env(name, value);
+    }
     
     Ok(sidecar_cmd)
 }
@@ -912,6 +1019,7 @@ fn create_agent_system_command(
     args: Vec<String>,
     project_path: &str,
     task_via_stdin: bool,
+    project_env: &[ProjectEnvVar],
 ) -> Command {
     // Handle .cmd files properly on Windows
     let mut cmd = if claude_path.ends_with(".cmd") {
@@ -972,6 +1080,10 @@ fn create_agent_system_command(
         .stdin(if task_via_stdin { Stdio::piped() } else { Stdio::null() })
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    if let Some(config_dir) = super::claude_dir::cli_config_dir() {
+        cmd.env(super::claude_dir::CLAUDE_CONFIG_DIR_ENV, config_dir);
+    }
+    apply_project_env(cmd.as_std_mut(), project_env);
     
     cmd
 }
@@ -996,7 +1108,8 @@ async fn spawn_agent_sidecar(
     }
 
     // Build the sidecar command
-    let sidecar_cmd = create_agent_sidecar_command(&app, args, &project_path)?;
+    let project_env = load_project_env(&*db.lock_conn(), &project_path);
+    let sidecar_cmd = create_agent_sidecar_command(&app, args, &project_path, &project_env)?;
 
     // Spawn the process
     info!("🚀 Spawning Claude sidecar process...");
@@ -1012,7 +1125,7 @@ async fn spawn_agent_sidecar(
 
     // Update the database with PID and status
     {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.lock_conn();
         conn.execute(
             "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2 WHERE id = ?3",
             params![pid as i64, now, run_id],
@@ -1143,16 +1256,18 @@ async fn spawn_agent_sidecar(
                     };
 
                     // Update database with completion
-                    if let Ok(conn) = Connection::open(&db_path) {
-                        let _ = conn.execute(
-                            "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
-                            params![extracted_session_id, run_id],
-                        );
-                    }
+                    let finished = match Connection::open(&db_path) {
+                        Ok(conn) => finish_agent_run(&conn, run_id, &extracted_session_id).unwrap_or(true),
+                        Err(_) => true,
+                    };
 
-                    let success = payload.code.unwrap_or(1) == 0;
-                    let _ = app.emit("agent-complete", success);
-                    let _ = app.emit(&format!("agent-complete:{}", run_id), success);
+                    // A cancelled run already announced its completion
+                    if finished {
+                        let success = payload.code.unwrap_or(1) == 0;
+                        let _ = app.emit("agent-complete", success);
+                        let _ = app.emit(&format!("agent-complete:{}", run_id), success);
+                        super::run_notifications::notify_run_finished(&app, run_id);
+                    }
                     break;
                 }
                 _ => {}
@@ -1181,7 +1296,8 @@ async fn spawn_agent_system(
     task_via_stdin: bool,
 ) -> Result<i64, String> {
     // Build the command
-    let mut cmd = create_agent_system_command(&claude_path, args, &project_path, task_via_stdin);
+    let project_env = load_project_env(&*db.lock_conn(), &project_path);
+    let mut cmd = create_agent_system_command(&claude_path, args, &project_path, task_via_stdin, &project_env);
 
     // Spawn the process
     info!("🚀 Spawning Claude system process...");
@@ -1197,7 +1313,7 @@ async fn spawn_agent_system(
 
     // Update the database with PID and status
     {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.lock_conn();
         conn.execute(
             "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2 WHERE id = ?3",
             params![pid as i64, now, run_id],
@@ -1453,6 +1569,7 @@ async fn spawn_agent_system(
 
                 let _ = app.emit("agent-complete", false);
                 let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+                super::run_notifications::notify_run_finished(&app, run_id);
                 return;
             }
 
@@ -1478,18 +1595,16 @@ async fn spawn_agent_system(
         info!("✅ Claude process execution monitoring complete");
 
         // Update the run record with session ID and mark as completed - open a new connection
+        let mut finished = true;
         if let Ok(conn) = Connection::open(&db_path_for_monitor) {
             info!("🔄 Updating database with extracted session ID: {}", extracted_session_id);
-            match conn.execute(
-                "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
-                params![extracted_session_id, run_id],
-            ) {
-                Ok(rows_affected) => {
-                    if rows_affected > 0 {
-                        info!("✅ Successfully updated agent run {} with session ID: {}", run_id, extracted_session_id);
-                    } else {
-                        warn!("⚠️ No rows affected when updating agent run {} with session ID", run_id);
-                    }
+            match finish_agent_run(&conn, run_id, &extracted_session_id) {
+                Ok(true) => {
+                    info!("✅ Successfully updated agent run {} with session ID: {}", run_id, extracted_session_id);
+                }
+                Ok(false) => {
+                    info!("Agent run {} was cancelled before its process exited", run_id);
+                    finished = false;
                 }
                 Err(e) => {
                     error!("❌ Failed to update agent run {} with session ID: {}", run_id, e);
@@ -1501,8 +1616,12 @@ async fn spawn_agent_system(
 
         // Cleanup will be handled by the cleanup_finished_processes function
 
-        let _ = app.emit("agent-complete", true);
-        let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+        // A cancelled run already announced its completion
+        if finished {
+            let _ = app.emit("agent-complete", true);
+            let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+            super::run_notifications::notify_run_finished(&app, run_id);
+        }
     });
 
     Ok(run_id)
@@ -1514,7 +1633,7 @@ pub async fn list_running_sessions(
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<Vec<AgentRun>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     // First get all running sessions from the database
     let mut stmt = conn.prepare(
@@ -1604,7 +1723,7 @@ pub async fn kill_agent_session(
     // If registry kill didn't work, try fallback with PID from database
     if !killed_via_registry {
         let pid_result = {
-            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let conn = db.lock_conn();
             conn.query_row(
                 "SELECT pid FROM agent_runs WHERE id = ?1 AND status = 'running'",
                 params![run_id],
@@ -1620,7 +1739,7 @@ pub async fn kill_agent_session(
     }
 
     // Update the database to mark as cancelled
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     let updated = conn.execute(
         "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
         params![run_id],
@@ -1628,17 +1747,115 @@ pub async fn kill_agent_session(
 
     // Emit cancellation event with run_id for proper isolation
     let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    if updated > 0 {
+        let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+    }
 
     Ok(updated > 0 || killed_via_registry)
 }
 
+/// Record the session id of a run whose process exited and mark it completed
+///
+/// Returns false when the run was no longer running, i.e. it was cancelled
+/// and its status must be left alone.
+fn finish_agent_run(conn: &Connection, run_id: i64, session_id: &str) -> SqliteResult<bool> {
+    conn.execute(
+        "UPDATE agent_runs SET session_id = ?1 WHERE id = ?2",
+        params![session_id, run_id],
+    )?;
+    let updated = conn.execute(
+        "UPDATE agent_runs SET status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+        params![run_id],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Result of cancelling a single agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRunCancellation {
+    pub run_id: i64,
+    /// False when the run had already finished and was left untouched
+    pub cancelled: bool,
+    /// Status of the run after the call
+    pub status: String,
+}
+
+/// Mark one run cancelled and kill only the process registered for it
+async fn cancel_run(
+    db: &AgentDb,
+    registry: &crate::process::ProcessRegistry,
+    run_id: i64,
+) -> Result<AgentRunCancellation, String> {
+    let pid = {
+        let conn = db.lock_conn();
+        // Marked before the kill so the run's monitor doesn't report it as completed
+        let updated = conn.execute(
+            "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+            params![run_id],
+        ).map_err(|e| e.to_string())?;
+
+        if updated == 0 {
+            return match conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(status) => Ok(AgentRunCancellation { run_id, cancelled: false, status }),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Err(format!("Agent run {} not found", run_id)),
+                Err(e) => Err(e.to_string()),
+            };
+        }
+
+        conn.query_row(
+            "SELECT pid FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .map_err(|e| e.to_string())?
+    };
+
+    if !registry.kill_process(run_id).await? {
+        if let Some(pid) = pid {
+            info!("Run {} not in registry, killing PID {} from database", run_id, pid);
+            registry.kill_process_by_pid(run_id, pid as u32)?;
+        }
+    }
+
+    Ok(AgentRunCancellation {
+        run_id,
+        cancelled: true,
+        status: "cancelled".to_string(),
+    })
+}
+
+/// Cancel one agent run by id, leaving other runs of the same batch running
+#[tauri::command]
+pub async fn cancel_agent_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+) -> Result<AgentRunCancellation, String> {
+    info!("Cancelling agent run {}", run_id);
+    let result = cancel_run(&db, &registry.0, run_id).await?;
+
+    // Only run-scoped events, so sibling runs listening on the generic ones keep going
+    if result.cancelled {
+        let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+        let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+    } else {
+        info!("Agent run {} already finished with status {}", run_id, result.status);
+    }
+    Ok(result)
+}
+
 /// Get the status of a specific agent session
 #[tauri::command]
 pub async fn get_session_status(
     db: State<'_, AgentDb>,
     run_id: i64,
 ) -> Result<Option<String>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     match conn.query_row(
         "SELECT status FROM agent_runs WHERE id = ?1",
@@ -1651,10 +1868,37 @@ pub async fn get_session_status(
     }
 }
 
+/// Whether a process with `pid` exists
+pub fn is_pid_running(pid: i64) -> bool {
+    if cfg!(target_os = "windows") {
+        // On Windows, use tasklist to check if process exists
+        match std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid)])
+            .args(["/FO", "CSV"])
+            .output()
+        {
+            Ok(output) => {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                output_str.lines().count() > 1 // Header + process line if exists
+            }
+            Err(_) => false,
+        }
+    } else {
+        // On Unix-like systems, use kill -0 to check if process exists
+        match std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+        {
+            Ok(output) => output.status.success(),
+            Err(_) => false,
+        }
+    }
+}
+
 /// Cleanup finished processes and update their status
 #[tauri::command]
 pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i64>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     // Get all running processes
     let mut stmt = conn
@@ -1672,32 +1916,7 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
     let mut cleaned_up = Vec::new();
 
     for (run_id, pid) in running_processes {
-        // Check if the process is still running
-        let is_running = if cfg!(target_os = "windows") {
-            // On Windows, use tasklist to check if process exists
-            match std::process::Command::new("tasklist")
-                .args(["/FI", &format!("PID eq {}", pid)])
-                .args(["/FO", "CSV"])
-                .output()
-            {
-                Ok(output) => {
-                    let output_str = String::from_utf8_lossy(&output.stdout);
-                    output_str.lines().count() > 1 // Header + process line if exists
-                }
-                Err(_) => false,
-            }
-        } else {
-            // On Unix-like systems, use kill -0 to check if process exists
-            match std::process::Command::new("kill")
-                .args(["-0", &pid.to_string()])
-                .output()
-            {
-                Ok(output) => output.status.success(),
-                Err(_) => false,
-            }
-        };
-
-        if !is_running {
+        if !is_pid_running(pid) {
             // Process has finished, update status
             let updated = conn.execute(
                 "UPDATE agent_runs SET status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
@@ -1746,9 +1965,7 @@ pub async fn get_session_output(
     }
 
     // Get the Claude directory
-    let claude_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+    let claude_dir = super::claude_dir::claude_dir_path()?;
 
     // Find the correct project directory by searching for the session file
     let projects_dir = claude_dir.join("projects");
@@ -1829,9 +2046,9 @@ pub async fn stream_session_output(
 
     // Spawn a task to monitor the file
     tokio::spawn(async move {
-        let claude_dir = match dirs::home_dir() {
-            Some(home) => home.join(".claude").join("projects"),
-            None => return,
+        let claude_dir = match super::claude_dir::claude_dir_path() {
+            Ok(claude_dir) => claude_dir.join("projects"),
+            Err(_) => return,
         };
 
         let encoded_project = project_path.replace('/', "-");
@@ -1899,12 +2116,12 @@ pub async fn stream_session_output(
 /// Export a single agent to JSON format
 #[tauri::command]
 pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     // Fetch the agent
     let agent = conn
         .query_row(
-            "SELECT name, icon, system_prompt, default_task, model, hooks FROM agents WHERE id = ?1",
+            "SELECT name, icon, system_prompt, default_task, model, hooks, default_provider FROM agents WHERE id = ?1",
             params![id],
             |row| {
                 Ok(serde_json::json!({
@@ -1913,7 +2130,8 @@ pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, Str
                     "system_prompt": row.get::<_, String>(2)?,
                     "default_task": row.get::<_, Option<String>>(3)?,
                     "model": row.get::<_, String>(4)?,
-                    "hooks": row.get::<_, Option<String>>(5)?
+                    "hooks": row.get::<_, Option<String>>(5)?,
+                    "default_provider": row.get::<_, Option<String>>(6)?
                 }))
             },
         )
@@ -1950,7 +2168,7 @@ pub async fn export_agent_to_file(
 /// Get the stored Claude binary path from settings
 #[tauri::command]
 pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<String>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     match conn.query_row(
         "SELECT value FROM app_settings WHERE key = 'claude_binary_path'",
@@ -1966,7 +2184,7 @@ pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<Str
 /// Set the Claude binary path in settings
 #[tauri::command]
 pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     // Validate that the path exists and is executable
     let path_buf = std::path::PathBuf::from(&path);
@@ -2106,7 +2324,7 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
     }
 
     let agent_data = export_data.agent;
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     // Check if an agent with the same name already exists
     let existing_count: i64 = conn
@@ -2126,14 +2344,15 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
 
     // Create the agent
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, 1, 1, 0, ?6)",
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, default_provider) VALUES (?1, ?2, ?3, ?4, ?5, 1, 1, 0, ?6, ?7)",
         params![
             final_name,
             agent_data.icon,
             agent_data.system_prompt,
             agent_data.default_task,
             agent_data.model,
-            agent_data.hooks
+            agent_data.hooks,
+            agent_data.default_provider
         ],
     )
     .map_err(|e| format!("Failed to create agent: {}", e))?;
@@ -2143,24 +2362,9 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| format!("Failed to fetch created agent: {}", e))?;
 
@@ -2319,9 +2523,7 @@ pub async fn load_agent_session_history(
 ) -> Result<Vec<serde_json::Value>, String> {
     log::info!("Loading agent session history for session: {}", session_id);
 
-    let claude_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+    let claude_dir = super::claude_dir::claude_dir_path()?;
 
     let projects_dir = claude_dir.join("projects");
     
@@ -2375,3 +2577,137 @@ pub async fn load_agent_session_history(
         Err(format!("Session file not found: {}", session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    fn agent(model: &str, default_provider: Option<&str>) -> Agent {
+        Agent {
+            id: Some(1),
+            name: "Quick triage".to_string(),
+            icon: "bot".to_string(),
+            system_prompt: String::new(),
+            default_task: None,
+            model: model.to_string(),
+            enable_file_read: true,
+            enable_file_write: false,
+            enable_network: false,
+            hooks: None,
+            default_provider: default_provider.map(str::to_string),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_run_inherits_agent_model_and_provider() {
+        let triage = agent("gemini-2.5-flash", Some("gemini"));
+        let run = resolve_run_model(&triage, None, None).unwrap();
+        assert_eq!(run, RunModel { model: "gemini-2.5-flash".to_string(), provider: ModelProvider::Gemini });
+
+        // A custom Ollama tag relies on the agent's provider
+        let local = agent("my-reviewer:7b", Some("Ollama"));
+        assert_eq!(resolve_run_model(&local, None, None).unwrap().provider, ModelProvider::Ollama);
+
+        // Per-run overrides win, and an overridden model brings its own provider
+        let run = resolve_run_model(&triage, Some("opus".to_string()), None).unwrap();
+        assert_eq!(run, RunModel { model: "opus".to_string(), provider: ModelProvider::Claude });
+
+        let error = resolve_run_model(&triage, None, Some("claude".to_string())).unwrap_err();
+        assert_eq!(error, "Model 'gemini-2.5-flash' is a gemini model and cannot run on claude");
+        let error = resolve_run_model(&agent("sonnet", Some("openai")), None, None).unwrap_err();
+        assert!(error.starts_with("Unknown provider 'openai'"), "{}", error);
+    }
+
+    #[test]
+    fn test_lock_waits_through_briefly_held_lock() {
+        let db = Arc::new(AgentDb(Mutex::new(Connection::open_in_memory().unwrap())));
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+
+        let holder = {
+            let db = Arc::clone(&db);
+            std::thread::spawn(move || {
+                let _guard = db.0.lock().unwrap();
+                locked_tx.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+            })
+        };
+        locked_rx.recv().unwrap();
+
+        let started = Instant::now();
+        let conn = db.lock_conn();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(conn.query_row("SELECT 1", [], |row| row.get::<_, i32>(0)).unwrap(), 1);
+        drop(conn);
+        holder.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_one_of_two_concurrent_runs() {
+        let db = AgentDb(Mutex::new(Connection::open_in_memory().unwrap()));
+        let registry = crate::process::ProcessRegistry::new();
+        {
+            let conn = db.lock_conn();
+            conn.execute(
+                "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, session_id TEXT NOT NULL DEFAULT '',
+                 status TEXT NOT NULL, pid INTEGER, completed_at TEXT)",
+                [],
+            ).unwrap();
+        }
+        for run_id in [1, 2] {
+            let child = Command::new("sleep").arg("30").spawn().unwrap();
+            let pid = child.id().unwrap();
+            db.lock_conn().execute(
+                "INSERT INTO agent_runs (id, status, pid) VALUES (?1, 'running', ?2)",
+                params![run_id, pid],
+            ).unwrap();
+            registry.register_process(
+                run_id, 1, "batch".to_string(), pid, "/tmp".to_string(),
+                "task".to_string(), "sonnet".to_string(), child,
+            ).unwrap();
+        }
+
+        let result = cancel_run(&db, &registry, 1).await.unwrap();
+        assert!(result.cancelled);
+        assert!(registry.get_process(1).unwrap().is_none());
+        assert!(registry.get_process(2).unwrap().is_some());
+
+        let status = |run_id: i64| -> String {
+            db.lock_conn().query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1", params![run_id], |row| row.get(0),
+            ).unwrap()
+        };
+        assert_eq!(status(1), "cancelled");
+        assert_eq!(status(2), "running");
+
+        // The killed process exiting must not flip the run back to completed
+        assert!(!finish_agent_run(&db.lock_conn(), 1, "sid").unwrap());
+        assert_eq!(status(1), "cancelled");
+
+        let again = cancel_run(&db, &registry, 1).await.unwrap();
+        assert!(!again.cancelled);
+        assert_eq!(again.status, "cancelled");
+        assert!(cancel_run(&db, &registry, 99).await.is_err());
+
+        assert!(cancel_run(&db, &registry, 2).await.unwrap().cancelled);
+    }
+
+    #[test]
+    fn test_lock_recovers_from_poison() {
+        let mutex = Arc::new(Mutex::new(1));
+        let poisoner = Arc::clone(&mutex);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+
+        assert!(mutex.is_poisoned());
+        assert_eq!(*lock_recovering(&mutex), 1);
+        assert!(!mutex.is_poisoned());
+    }
+}
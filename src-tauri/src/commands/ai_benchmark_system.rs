@@ -441,8 +441,7 @@ pub async fn save_benchmark_data(
     let serialized_data = serde_json::to_string(&benchmark_data)
         .map_err(|e| format!("Failed to serialize benchmark data: {}", e))?;
     
-    let conn = db.0.lock()
-        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+    let conn = db.lock_conn();
     
     // 벤치마크 테이블 생성
     conn.execute(
@@ -493,8 +492,7 @@ pub async fn get_latest_benchmark_data(
     log::info!("Retrieving latest AI model benchmark data");
     
     let data_result = {
-        let conn = db.0.lock()
-            .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+        let conn = db.lock_conn();
         
         // 최신 벤치마크 데이터 조회
         conn.query_row(
@@ -154,12 +154,15 @@ impl SessionTracker {
             session_id: Some(session_id.to_string()),
             user_prompt_tokens,
             assistant_response_tokens,
+            cached_tokens: None,
+            estimated: false,
             timestamp: now,
+            tags: None,
         };
 
         // Track the event asynchronously
         let event_for_tracking = event.clone();
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.lock_conn();
         
         // Insert individual event for detailed tracking
         conn.execute(
@@ -3,7 +3,7 @@ use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 
 use super::agents::AgentDb;
 
@@ -24,7 +24,16 @@ pub struct AIUsageEvent {
     pub session_id: Option<String>,
     pub user_prompt_tokens: Option<i64>,
     pub assistant_response_tokens: Option<i64>,
+    /// Prompt tokens served from the provider's context cache, counted within `user_prompt_tokens`
+    #[serde(default)]
+    pub cached_tokens: Option<i64>,
+    /// Token counts are estimated because the provider did not report them
+    #[serde(default)]
+    pub estimated: bool,
     pub timestamp: i64,
+    /// Spend attribution tags, e.g. `team`, `ticket` or `feature`
+    #[serde(default)]
+    pub tags: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,15 +106,38 @@ const MODEL_COSTS: &[(&str, f64, f64)] = &[
     ("gpt-4o-mini", 0.00015, 0.0006),
 ];
 
+/// Share of the input rate charged for prompt tokens read from a provider's context cache
+fn cache_read_factor(model_name: &str) -> f64 {
+    let model = model_name.to_ascii_lowercase();
+    if model.starts_with("claude") {
+        0.1
+    } else if model.starts_with("gemini") {
+        0.25
+    } else {
+        0.5
+    }
+}
+
 impl CostCalculation {
     pub fn calculate(model_name: &str, input_tokens: i64, output_tokens: i64) -> Self {
+        Self::calculate_with_cache(model_name, input_tokens, 0, output_tokens)
+    }
+
+    /// Like `calculate`, with `cached_tokens` of the input priced at the cache read rate
+    pub fn calculate_with_cache(model_name: &str, input_tokens: i64, cached_tokens: i64, output_tokens: i64) -> Self {
+        let local = crate::models::canonicalize(model_name)
+            .is_some_and(|model| model.provider == crate::models::ModelProvider::Ollama);
         let (input_rate, output_rate) = MODEL_COSTS
             .iter()
-            .find(|(name, _, _)| name == &model_name)
+            .find(|(name, _, _)| crate::models::same_model(name, model_name))
             .map(|(_, input, output)| (*input, *output))
+            // Local Ollama models cost nothing to run
+            .or(local.then_some((0.0, 0.0)))
             .unwrap_or((0.003, 0.015)); // Default to Claude Sonnet rates
 
-        let input_cost = (input_tokens as f64 / 1000.0) * input_rate;
+        let cached_tokens = cached_tokens.clamp(0, input_tokens.max(0));
+        let input_cost = ((input_tokens - cached_tokens) as f64 / 1000.0) * input_rate
+            + (cached_tokens as f64 / 1000.0) * input_rate * cache_read_factor(model_name);
         let output_cost = (output_tokens as f64 / 1000.0) * output_rate;
         let total_cost = input_cost + output_cost;
 
@@ -120,18 +152,94 @@ impl CostCalculation {
     }
 }
 
+/// Running usage totals of one session, sent after each completed turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionUsageUpdate {
+    pub session_id: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    /// USD, each turn priced for the model that served it
+    pub cost: f64,
+}
+
+/// Sum a session's usage records
+///
+/// Costs are stored per record at the rates of that record's model, so a
+/// session that switched providers keeps each turn priced as it was served.
+pub fn session_usage_totals(conn: &Connection, session_id: &str) -> Result<SessionUsageUpdate, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(user_prompt_tokens), 0), COALESCE(SUM(assistant_response_tokens), 0),
+                COALESCE(SUM(token_count), 0), COALESCE(SUM(cost), 0.0)
+         FROM ai_usage_events WHERE session_id = ?1",
+        params![session_id],
+        |row| {
+            Ok(SessionUsageUpdate {
+                session_id: session_id.to_string(),
+                input_tokens: row.get(0)?,
+                output_tokens: row.get(1)?,
+                total_tokens: row.get(2)?,
+                cost: row.get(3)?,
+            })
+        },
+    )
+    .map_err(|e| format!("Failed to sum session usage: {}", e))
+}
+
+/// Record a completed turn, returning its session's totals including it
+pub fn record_turn_usage(conn: &Connection, event: &mut AIUsageEvent) -> Result<Option<SessionUsageUpdate>, String> {
+    record_usage_event(conn, event)?;
+    event
+        .session_id
+        .as_deref()
+        .map(|session_id| session_usage_totals(conn, session_id))
+        .transpose()
+}
+
+/// Record a completed turn and send the session's running totals to
+/// `session-usage-updated:{session_id}`
+pub fn track_turn_usage(app: &AppHandle, event: &mut AIUsageEvent) -> Result<(), String> {
+    let update = {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        record_turn_usage(&conn, event)?
+    };
+    if let Some(update) = update {
+        let event_name = format!("session-usage-updated:{}", update.session_id);
+        if let Err(e) = super::session_event_log::emit_session_event(app, &event_name, &update) {
+            log::warn!("Failed to emit session usage update: {}", e);
+        }
+    }
+    Ok(())
+}
+
 /// Track a new AI usage event
 #[tauri::command]
 pub async fn track_ai_usage(
-    db: State<'_, AgentDb>,
-    event: AIUsageEvent,
+    app: AppHandle,
+    mut event: AIUsageEvent,
 ) -> Result<String, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    track_turn_usage(&app, &mut event)?;
+
+    Ok("AI usage tracked successfully".to_string())
+}
+
+/// Insert a usage event, its tags and the dashboard aggregate, returning the event's cost
+///
+/// Runs on whatever connection or transaction it is given, so callers can record
+/// usage together with other writes.
+pub fn record_usage_event(conn: &Connection, event: &mut AIUsageEvent) -> Result<f64, String> {
+    // Tags are attributed per session, so untracked requests get a session of their own
+    let tags = event.tags.clone().filter(|tags| !tags.is_empty());
+    if tags.is_some() && event.session_id.is_none() {
+        event.session_id = Some(format!("usage-{}", uuid::Uuid::new_v4()));
+    }
     
     // Calculate cost if token breakdown is available
     let cost = if let (Some(input_tokens), Some(output_tokens)) = 
         (event.user_prompt_tokens, event.assistant_response_tokens) {
-        CostCalculation::calculate(&event.model_name, input_tokens, output_tokens).total_cost
+        let cached_tokens = event.cached_tokens.unwrap_or(0);
+        CostCalculation::calculate_with_cache(&event.model_name, input_tokens, cached_tokens, output_tokens).total_cost
     } else {
         // Fallback to simple calculation
         let (_, _, avg_rate) = MODEL_COSTS
@@ -152,8 +260,8 @@ pub async fn track_ai_usage(
         "INSERT INTO ai_usage_events 
          (project_id, model_name, agent_type, mcp_server, token_count, request_type,
           response_time_ms, success, error_message, session_id, user_prompt_tokens,
-          assistant_response_tokens, cost, session_date, timestamp)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+          assistant_response_tokens, cached_tokens, estimated, cost, session_date, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
         params![
             &event.project_id,
             &event.model_name,
@@ -167,16 +275,72 @@ pub async fn track_ai_usage(
             &event.session_id,
             event.user_prompt_tokens,
             event.assistant_response_tokens,
+            event.cached_tokens,
+            event.estimated,
             cost,
             &session_date,
             event.timestamp
         ],
     ).map_err(|e| e.to_string())?;
 
+    if let (Some(tags), Some(session_id)) = (&tags, &event.session_id) {
+        record_usage_tags(conn, session_id, tags)?;
+    }
+
     // Update aggregated metrics
-    update_aggregated_metrics(&conn, &event, cost)?;
+    update_aggregated_metrics(conn, event, cost)?;
 
-    Ok("AI usage tracked successfully".to_string())
+    Ok(cost)
+}
+
+/// Attach spend attribution tags to a session, replacing earlier values for the same keys
+pub fn record_usage_tags(
+    conn: &Connection,
+    session_id: &str,
+    tags: &HashMap<String, String>,
+) -> Result<(), String> {
+    for (key, value) in tags {
+        let key = key.trim();
+        if key.is_empty() {
+            continue;
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO ai_usage_tags (session_id, tag_key, tag_value) VALUES (?1, ?2, ?3)",
+            params![session_id, key, value],
+        )
+        .map_err(|e| format!("Failed to record usage tags: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Tags of every tagged session, keyed by session id
+pub fn load_usage_tags(conn: &Connection) -> Result<HashMap<String, HashMap<String, String>>, String> {
+    let mut stmt = conn
+        .prepare("SELECT session_id, tag_key, tag_value FROM ai_usage_tags")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut session_tags: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for row in rows {
+        let (session_id, key, value) = row.map_err(|e| e.to_string())?;
+        session_tags.entry(session_id).or_default().insert(key, value);
+    }
+    Ok(session_tags)
+}
+
+/// Tag an execution session once its id is known; failures only cost attribution
+pub fn tag_usage_session(app: &AppHandle, session_id: &str, tags: &HashMap<String, String>) {
+    if tags.is_empty() {
+        return;
+    }
+    let db = app.state::<AgentDb>();
+    if let Err(e) = record_usage_tags(&db.lock_conn(), session_id, tags) {
+        log::warn!("Failed to tag usage session {}: {}", session_id, e);
+    }
 }
 
 /// Update aggregated metrics for dashboard display
@@ -276,7 +440,7 @@ pub async fn get_ai_usage_stats(
     project_id: String,
     days_limit: Option<i64>,
 ) -> Result<AIUsageStats, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     let time_filter = match days_limit {
         Some(days) => format!("AND timestamp > (strftime('%s', 'now') - {} * 24 * 60 * 60)", days),
@@ -478,7 +642,7 @@ pub async fn get_session_ai_usage(
     project_id: String,
     session_id: String,
 ) -> Result<AIUsageStats, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Query events for current session only
     let mut stmt = conn.prepare(
@@ -645,4 +809,74 @@ pub async fn get_ai_model_info() -> Result<Vec<serde_json::Value>, String> {
         .collect();
 
     Ok(models)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed_turn(model_name: &str, input_tokens: i64, output_tokens: i64) -> AIUsageEvent {
+        AIUsageEvent {
+            project_id: "proj-a".to_string(),
+            model_name: model_name.to_string(),
+            agent_type: None,
+            mcp_server: None,
+            token_count: input_tokens + output_tokens,
+            request_type: "completion".to_string(),
+            response_time_ms: Some(1200),
+            success: true,
+            error_message: None,
+            session_id: Some("s1".to_string()),
+            user_prompt_tokens: Some(input_tokens),
+            assistant_response_tokens: Some(output_tokens),
+            cached_tokens: None,
+            estimated: false,
+            timestamp: 1_700_000_000,
+            tags: None,
+        }
+    }
+
+    #[test]
+    fn test_each_completed_turn_updates_the_session_totals() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../../migrations/002_dashboard.sql")).unwrap();
+
+        // Switch models mid-session; each turn stays priced for its own model
+        let updates: Vec<SessionUsageUpdate> = [
+            completed_turn("claude-3-opus", 1000, 200),
+            completed_turn("gpt-4o-mini", 3000, 1000),
+        ]
+        .into_iter()
+        .map(|mut turn| record_turn_usage(&conn, &mut turn).unwrap().unwrap())
+        .collect();
+
+        let opus = CostCalculation::calculate("claude-3-opus", 1000, 200).total_cost;
+        let mini = CostCalculation::calculate("gpt-4o-mini", 3000, 1000).total_cost;
+        assert_eq!(updates.len(), 2);
+        assert_eq!((updates[0].input_tokens, updates[0].output_tokens, updates[0].total_tokens), (1000, 200, 1200));
+        assert!((updates[0].cost - opus).abs() < 1e-9);
+        assert_eq!((updates[1].input_tokens, updates[1].output_tokens, updates[1].total_tokens), (4000, 1200, 5200));
+        assert!((updates[1].cost - (opus + mini)).abs() < 1e-9);
+        assert!(updates.iter().all(|update| update.session_id == "s1"));
+
+        let mut untracked = completed_turn("gpt-4o", 10, 10);
+        untracked.session_id = None;
+        assert_eq!(record_turn_usage(&conn, &mut untracked).unwrap(), None);
+        assert_eq!(session_usage_totals(&conn, "s1").unwrap(), updates[1]);
+    }
+
+    #[test]
+    fn test_cache_reads_are_priced_below_the_input_rate() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../../migrations/002_dashboard.sql")).unwrap();
+
+        let mut cached = completed_turn("claude-3-opus", 10_000, 0);
+        cached.cached_tokens = Some(8_000);
+        let update = record_turn_usage(&conn, &mut cached).unwrap().unwrap();
+
+        // 2,000 fresh tokens at $0.015/1k plus 8,000 cache reads at a tenth of that
+        assert!((update.cost - (0.03 + 0.012)).abs() < 1e-9, "{}", update.cost);
+        assert!(update.cost < CostCalculation::calculate("claude-3-opus", 10_000, 0).total_cost);
+        assert_eq!(update.input_tokens, 10_000);
+    }
+}
@@ -251,7 +251,7 @@ pub async fn get_auto_model_recommendation(prompt: String, app: AppHandle) -> Re
     info!("Getting auto model recommendation for prompt.");
     
     let db_state = app.state::<AgentDb>();
-    let conn = db_state.0.lock().map_err(|e| format!("DB lock failed: {}", e))?;
+    let conn = db_state.lock_conn();
 
     let all_models = get_all_models_from_db(&conn)
         .map_err(|e| format!("Failed to get models from knowledge base: {}", e))?;
@@ -0,0 +1,276 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::State;
+
+/// Delay before the first retry of a failed run; doubled on each further failure
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest delay between retries
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// When a task runs
+#[derive(Debug, Clone, Copy)]
+pub struct TaskSchedule {
+    /// Wait before the first run
+    pub initial_delay: Duration,
+    /// Time between successful runs; `None` runs the task until it succeeds once
+    pub interval: Option<Duration>,
+}
+
+impl TaskSchedule {
+    pub fn every(initial_delay: Duration, interval: Duration) -> Self {
+        Self { initial_delay, interval: Some(interval) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    /// Waiting for its next run
+    Scheduled,
+    Running,
+    /// The last run failed; waiting to retry
+    Failed,
+    /// A one-off task that has succeeded
+    Finished,
+}
+
+/// What the supervisor knows about one task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackgroundTaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    /// Runs started, including retries
+    pub runs: u64,
+    pub failures: u64,
+    /// Failures since the last successful run, which set the retry backoff
+    pub consecutive_failures: u32,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+impl BackgroundTaskStatus {
+    fn new(name: &str, next_run: DateTime<Utc>) -> Self {
+        Self {
+            name: name.to_string(),
+            state: TaskState::Scheduled,
+            runs: 0,
+            failures: 0,
+            consecutive_failures: 0,
+            last_run: None,
+            last_success: None,
+            last_error: None,
+            next_run: Some(next_run),
+        }
+    }
+}
+
+/// Runs background tasks and tracks their status; cloning shares the status table
+///
+/// Each recurring job, such as a Claude command sync or a benchmark refresh, runs on
+/// its schedule and a run that fails or panics is retried with exponential backoff.
+/// The status lets the UI show whether the daily updates are actually happening.
+#[derive(Clone)]
+pub struct BackgroundTaskSupervisor {
+    statuses: Arc<Mutex<BTreeMap<String, BackgroundTaskStatus>>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for BackgroundTaskSupervisor {
+    fn default() -> Self {
+        Self::with_backoff(DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_BACKOFF)
+    }
+}
+
+impl BackgroundTaskSupervisor {
+    pub fn with_backoff(initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            statuses: Arc::new(Mutex::new(BTreeMap::new())),
+            initial_backoff,
+            max_backoff,
+        }
+    }
+
+    /// Status of every task, ordered by name
+    pub fn statuses(&self) -> Vec<BackgroundTaskStatus> {
+        match self.statuses.lock() {
+            Ok(statuses) => statuses.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Delay before retrying after `consecutive_failures` failed runs
+    fn backoff(&self, consecutive_failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(consecutive_failures.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    fn update(&self, name: &str, apply: impl FnOnce(&mut BackgroundTaskStatus)) {
+        if let Ok(mut statuses) = self.statuses.lock() {
+            if let Some(status) = statuses.get_mut(name) {
+                apply(status);
+            }
+        }
+    }
+
+    /// Run `task` on `schedule` under supervision
+    ///
+    /// Each call of `task` is one run. A run that returns an error or panics is
+    /// retried after a backoff, never later than the task's regular interval.
+    pub fn spawn<F, Fut>(&self, name: &str, schedule: TaskSchedule, task: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        if let Ok(mut statuses) = self.statuses.lock() {
+            statuses.insert(
+                name.to_string(),
+                BackgroundTaskStatus::new(name, Utc::now() + schedule.initial_delay),
+            );
+        }
+
+        let supervisor = self.clone();
+        let name = name.to_string();
+        tauri::async_runtime::spawn(async move {
+            let mut delay = schedule.initial_delay;
+            let mut consecutive_failures = 0;
+            loop {
+                tokio::time::sleep(delay).await;
+
+                supervisor.update(&name, |status| {
+                    status.state = TaskState::Running;
+                    status.runs += 1;
+                    status.last_run = Some(Utc::now());
+                    status.next_run = None;
+                });
+
+                // Run on its own task so a panic is caught as a failed run
+                let outcome = match tokio::spawn(task()).await {
+                    Ok(result) => result,
+                    Err(e) if e.is_panic() => Err(format!("Task panicked: {}", panic_message(e.into_panic()))),
+                    Err(e) => Err(format!("Task was cancelled: {}", e)),
+                };
+
+                let now = Utc::now();
+                match outcome {
+                    Ok(()) => {
+                        consecutive_failures = 0;
+                        supervisor.update(&name, |status| {
+                            status.consecutive_failures = 0;
+                            status.last_success = Some(now);
+                            status.state = match schedule.interval {
+                                Some(_) => TaskState::Scheduled,
+                                None => TaskState::Finished,
+                            };
+                            status.next_run = schedule.interval.map(|interval| now + interval);
+                        });
+                        let Some(interval) = schedule.interval else {
+                            log::info!("Background task {} finished", name);
+                            return;
+                        };
+                        delay = interval;
+                    }
+                    Err(error) => {
+                        consecutive_failures += 1;
+                        delay = supervisor.backoff(consecutive_failures);
+                        if let Some(interval) = schedule.interval {
+                            delay = delay.min(interval);
+                        }
+                        log::warn!("Background task {} failed, retrying in {:?}: {}", name, delay, error);
+                        supervisor.update(&name, |status| {
+                            status.failures += 1;
+                            status.consecutive_failures = consecutive_failures;
+                            status.state = TaskState::Failed;
+                            status.last_error = Some(error);
+                            status.next_run = Some(now + delay);
+                        });
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Status of the supervised background tasks
+#[tauri::command]
+pub async fn get_background_task_status(
+    supervisor: State<'_, BackgroundTaskSupervisor>,
+) -> Result<Vec<BackgroundTaskStatus>, String> {
+    Ok(supervisor.statuses())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    async fn wait_for(
+        supervisor: &BackgroundTaskSupervisor,
+        condition: impl Fn(&BackgroundTaskStatus) -> bool,
+    ) -> BackgroundTaskStatus {
+        for _ in 0..200 {
+            if let Some(status) = supervisor.statuses().into_iter().find(|status| condition(status)) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not reached: {:?}", supervisor.statuses());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_is_restarted() {
+        let supervisor = BackgroundTaskSupervisor::with_backoff(Duration::from_millis(300), Duration::from_secs(1));
+        let attempts = Arc::new(AtomicU32::new(0));
+        let task_attempts = attempts.clone();
+        supervisor.spawn("benchmarks", TaskSchedule::every(Duration::ZERO, Duration::from_secs(3600)), move || {
+            let attempt = task_attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt == 0 {
+                    panic!("benchmark source unreachable");
+                }
+                Ok(())
+            }
+        });
+
+        let failed = wait_for(&supervisor, |status| status.failures == 1).await;
+        assert_eq!(failed.state, TaskState::Failed);
+        assert_eq!(failed.consecutive_failures, 1);
+        assert!(failed.last_error.as_deref().unwrap().contains("benchmark source unreachable"));
+        assert!(failed.next_run.unwrap() > failed.last_run.unwrap());
+        assert!(failed.last_success.is_none());
+
+        // Restarted after the backoff, after which the regular interval applies again
+        let recovered = wait_for(&supervisor, |status| status.last_success.is_some()).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(recovered.runs, 2);
+        assert_eq!(recovered.failures, 1);
+        assert_eq!(recovered.consecutive_failures, 0);
+        assert_eq!(recovered.state, TaskState::Scheduled);
+        let until_next = recovered.next_run.unwrap() - recovered.last_success.unwrap();
+        assert_eq!(until_next.num_seconds(), 3600);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let supervisor = BackgroundTaskSupervisor::default();
+        assert_eq!(supervisor.backoff(1), Duration::from_secs(30));
+        assert_eq!(supervisor.backoff(3), Duration::from_secs(120));
+        assert_eq!(supervisor.backoff(40), DEFAULT_MAX_BACKOFF);
+    }
+}
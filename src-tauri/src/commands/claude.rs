@@ -2,6 +2,7 @@
 use anyhow::{Context, Result};
 use log::error;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
@@ -11,6 +12,13 @@ use std::time::SystemTime;
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::process::{Child, Command};
 use tokio::sync::Mutex;
+use super::ai_usage_tracker::{track_turn_usage, AIUsageEvent};
+use super::session_event_log::emit_session_event;
+use super::project_env::{apply_project_env, project_env_for, ProjectEnvVar};
+use super::execution_control::{
+    record_execution_metrics, run_progress_ticker, ExecutionControlState, ProgressTracker,
+    PROGRESS_EMIT_INTERVAL,
+};
 
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
@@ -137,13 +145,12 @@ fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
     crate::claude_binary::find_claude_binary(app_handle)
 }
 
-/// Gets the path to the ~/.claude directory
-fn get_claude_dir() -> Result<PathBuf> {
-    dirs::home_dir()
-        .context("Could not find home directory")?
-        .join(".claude")
+/// Gets the path to the Claude directory, `~/.claude` unless configured otherwise
+pub(crate) fn get_claude_dir() -> Result<PathBuf> {
+    let claude_dir = super::claude_dir::claude_dir_path().map_err(anyhow::Error::msg)?;
+    claude_dir
         .canonicalize()
-        .context("Could not find ~/.claude directory")
+        .with_context(|| format!("Could not find Claude directory {}", claude_dir.display()))
 }
 
 /// Gets the actual project path by reading the cwd from the first JSONL entry
@@ -318,11 +325,31 @@ fn create_command_with_env(program: &str) -> Command {
     }
 }
 
+/// Longest prompt passed to the Claude CLI as an argument; longer ones go over stdin
+///
+/// Attached files easily exceed the 32K command line limit on Windows, and the
+/// 8191-character limit when the CLI is a .cmd shim.
+const PROMPT_ARG_MAX_LEN: usize = 8_000;
+
+fn prompt_via_stdin(prompt: &str) -> bool {
+    prompt.len() > PROMPT_ARG_MAX_LEN
+}
+
+/// `-p` with the prompt, or bare `-p` so the CLI reads a long prompt from stdin
+fn print_args(prompt: &str) -> Vec<String> {
+    let mut args = vec!["-p".to_string()];
+    if !prompt_via_stdin(prompt) {
+        args.push(prompt.to_string());
+    }
+    args
+}
+
 /// Creates a system binary command with the given arguments
 fn create_system_command(
     claude_path: &str,
     args: Vec<String>,
     project_path: &str,
+    project_env: &[ProjectEnvVar],
 ) -> Command {
     let mut cmd = if claude_path.ends_with(".cmd") {
         // For Windows .cmd files, use cmd /c with proper escaping
@@ -374,6 +401,10 @@ fn create_system_command(
     cmd.current_dir(project_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    if let Some(config_dir) = super::claude_dir::cli_config_dir() {
+        cmd.env(super::claude_dir::CLAUDE_CONFIG_DIR_ENV, config_dir);
+    }
+    apply_project_env(cmd.as_std_mut(), project_env);
     
     cmd
 }
@@ -643,14 +674,39 @@ pub struct ClaudeAuthStatus {
 #[tauri::command]
 pub async fn check_claude_auth(app: AppHandle) -> Result<ClaudeAuthStatus, String> {
     log::info!("Checking Claude Code authentication status");
+    Ok(probe_claude_auth(&app).await.into())
+}
+
+/// Outcome of running a cheap authenticated CLI command
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AuthProbe {
+    Authenticated,
+    /// The CLI ran and rejected the stored credentials
+    Rejected(String),
+    /// Claude could not be run, so nothing is known about auth
+    Inconclusive(String),
+}
+
+impl From<AuthProbe> for ClaudeAuthStatus {
+    fn from(probe: AuthProbe) -> Self {
+        match probe {
+            AuthProbe::Authenticated => ClaudeAuthStatus {
+                is_authenticated: true,
+                message: "Claude Code is authenticated and ready to use.".to_string(),
+            },
+            AuthProbe::Rejected(message) | AuthProbe::Inconclusive(message) => ClaudeAuthStatus {
+                is_authenticated: false,
+                message,
+            },
+        }
+    }
+}
 
-    let claude_path = match find_claude_binary(&app) {
+pub(crate) async fn probe_claude_auth(app: &AppHandle) -> AuthProbe {
+    let claude_path = match find_claude_binary(app) {
         Ok(path) => path,
         Err(e) => {
-            return Ok(ClaudeAuthStatus {
-                is_authenticated: false,
-                message: format!("Claude Code not found: {}", e),
-            });
+            return AuthProbe::Inconclusive(format!("Claude Code not found: {}", e));
         }
     };
 
@@ -692,28 +748,16 @@ pub async fn check_claude_auth(app: AppHandle) -> Result<ClaudeAuthStatus, Strin
             // Check for authentication errors in output
             if stderr.contains("Please authenticate") || stderr.contains("not authenticated") || 
                stderr.contains("setup-token") || stderr.contains("login") {
-                Ok(ClaudeAuthStatus {
-                    is_authenticated: false,
-                    message: "Claude Code is not authenticated. Please run 'claude setup-token' to authenticate.".to_string(),
-                })
+                AuthProbe::Rejected(
+                    "Claude Code is not authenticated. Please run 'claude setup-token' to authenticate.".to_string(),
+                )
             } else if output.status.success() || stdout.contains("No MCP servers configured") {
-                Ok(ClaudeAuthStatus {
-                    is_authenticated: true,
-                    message: "Claude Code is authenticated and ready to use.".to_string(),
-                })
+                AuthProbe::Authenticated
             } else {
-                Ok(ClaudeAuthStatus {
-                    is_authenticated: false,
-                    message: format!("Unable to verify authentication status: {}", stderr),
-                })
+                AuthProbe::Inconclusive(format!("Unable to verify authentication status: {}", stderr))
             }
         }
-        Err(e) => {
-            Ok(ClaudeAuthStatus {
-                is_authenticated: false,
-                message: format!("Failed to check authentication: {}", e),
-            })
-        }
+        Err(e) => AuthProbe::Inconclusive(format!("Failed to check authentication: {}", e)),
     }
 }
 
@@ -1141,6 +1185,8 @@ pub async fn execute_claude_code(
     project_path: String,
     prompt: String,
     model: String,
+    tags: Option<HashMap<String, String>>,
+    attachments: Option<Vec<String>>,
 ) -> Result<(), String> {
     log::info!(
         "Starting new Claude Code session in: {} with model: {}",
@@ -1148,22 +1194,25 @@ pub async fn execute_claude_code(
         model
     );
 
+    let prompt = super::file_attachments::attach_files(&prompt, &project_path, attachments.as_deref().unwrap_or_default())?;
+    super::context_guard::ensure_prompt_fits(&app, &model, &prompt)?;
+    let prompt = super::prompt_secrets::screen_prompt(&app, "claude", &prompt)?;
     let claude_path = find_claude_binary(&app)?;
     log::info!("Claude binary path: {}", claude_path);
     
-    let args = vec![
-        "-p".to_string(),
-        prompt.clone(),
+    let mut args = print_args(&prompt);
+    args.extend([
         "--model".to_string(),
-        model.clone(),
+        crate::models::claude_cli_model(&model),
         "--output-format".to_string(),
         "stream-json".to_string(),
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
-    ];
+    ]);
 
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let project_env = project_env_for(&app, &project_path);
+    let cmd = create_system_command(&claude_path, args, &project_path, &project_env);
+    spawn_claude_process(app, cmd, prompt, model, project_path, tags).await
 }
 
 /// Continue an existing Claude Code conversation with streaming output
@@ -1173,6 +1222,7 @@ pub async fn continue_claude_code(
     project_path: String,
     prompt: String,
     model: String,
+    tags: Option<HashMap<String, String>>,
 ) -> Result<(), String> {
     log::info!(
         "Continuing Claude Code conversation in: {} with model: {}",
@@ -1180,22 +1230,24 @@ pub async fn continue_claude_code(
         model
     );
 
+    super::context_guard::ensure_prompt_fits(&app, &model, &prompt)?;
+    let prompt = super::prompt_secrets::screen_prompt(&app, "claude", &prompt)?;
     let claude_path = find_claude_binary(&app)?;
     
-    let args = vec![
-        "-c".to_string(), // Continue flag
-        "-p".to_string(),
-        prompt.clone(),
+    let mut args = vec!["-c".to_string()]; // Continue flag
+    args.extend(print_args(&prompt));
+    args.extend([
         "--model".to_string(),
-        model.clone(),
+        crate::models::claude_cli_model(&model),
         "--output-format".to_string(),
         "stream-json".to_string(),
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
-    ];
+    ]);
 
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let project_env = project_env_for(&app, &project_path);
+    let cmd = create_system_command(&claude_path, args, &project_path, &project_env);
+    spawn_claude_process(app, cmd, prompt, model, project_path, tags).await
 }
 
 /// Resume an existing Claude Code session by ID with streaming output
@@ -1206,6 +1258,7 @@ pub async fn resume_claude_code(
     session_id: String,
     prompt: String,
     model: String,
+    tags: Option<HashMap<String, String>>,
 ) -> Result<(), String> {
     log::info!(
         "Resuming Claude Code session: {} in: {} with model: {}",
@@ -1246,23 +1299,24 @@ pub async fn resume_claude_code(
         ));
     }
 
+    super::context_guard::ensure_prompt_fits(&app, &model, &prompt)?;
+    let prompt = super::prompt_secrets::screen_prompt(&app, "claude", &prompt)?;
     let claude_path = find_claude_binary(&app)?;
     
-    let args = vec![
-        "--resume".to_string(),
-        session_id.clone(),
-        "-p".to_string(),
-        prompt.clone(),
+    let mut args = vec!["--resume".to_string(), session_id.clone()];
+    args.extend(print_args(&prompt));
+    args.extend([
         "--model".to_string(),
-        model.clone(),
+        crate::models::claude_cli_model(&model),
         "--output-format".to_string(),
         "stream-json".to_string(),
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
-    ];
+    ]);
 
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let project_env = project_env_for(&app, &project_path);
+    let cmd = create_system_command(&claude_path, args, &project_path, &project_env);
+    spawn_claude_process(app, cmd, prompt, model, project_path, tags).await
 }
 
 /// Cancel the currently running Claude Code execution
@@ -1372,7 +1426,7 @@ pub async fn cancel_claude_execution(
     if let Some(sid) = session_id {
         let _ = app.emit(&format!("claude-cancelled:{}", sid), true);
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        let _ = app.emit(&format!("claude-complete:{}", sid), false);
+        let _ = emit_session_event(&app, &format!("claude-complete:{}", sid), false);
     }
     
     // Also emit generic events for backward compatibility
@@ -1411,16 +1465,74 @@ pub async fn get_claude_session_output(
     }
 }
 
+/// Usage of a completed turn from Claude's closing `result` message
+///
+/// Cache reads and writes are prompt tokens too, so they count as input.
+fn claude_turn_usage(msg: &serde_json::Value, project_path: &str, model: &str) -> Option<AIUsageEvent> {
+    if msg["type"] != "result" {
+        return None;
+    }
+    let session_id = msg["session_id"].as_str()?;
+    let usage = &msg["usage"];
+    let tokens = |key: &str| usage[key].as_i64().unwrap_or(0);
+    let cached_tokens = tokens("cache_read_input_tokens");
+    let input_tokens = tokens("input_tokens") + tokens("cache_creation_input_tokens") + cached_tokens;
+    let output_tokens = tokens("output_tokens");
+    Some(AIUsageEvent {
+        project_id: project_path.to_string(),
+        model_name: model.to_string(),
+        agent_type: None,
+        mcp_server: None,
+        token_count: input_tokens + output_tokens,
+        request_type: "completion".to_string(),
+        response_time_ms: msg["duration_ms"].as_i64(),
+        success: msg["is_error"] != true,
+        error_message: None,
+        session_id: Some(session_id.to_string()),
+        user_prompt_tokens: Some(input_tokens),
+        assistant_response_tokens: Some(output_tokens),
+        cached_tokens: Some(cached_tokens),
+        estimated: false,
+        timestamp: chrono::Utc::now().timestamp(),
+        tags: None,
+    })
+}
+
 /// Helper function to spawn Claude process and handle streaming
-async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String, model: String, project_path: String) -> Result<(), String> {
+async fn spawn_claude_process(
+    app: AppHandle,
+    mut cmd: Command,
+    prompt: String,
+    model: String,
+    project_path: String,
+    tags: Option<HashMap<String, String>>,
+) -> Result<(), String> {
     use tokio::io::{AsyncBufReadExt, BufReader};
     use std::sync::Mutex;
 
     // Spawn the process
+    let via_stdin = prompt_via_stdin(&prompt);
+    if via_stdin {
+        cmd.stdin(Stdio::piped());
+    }
     let mut child = cmd
         .spawn()
         .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
 
+    if via_stdin {
+        use tokio::io::AsyncWriteExt;
+        let mut stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+        log::info!("Writing prompt to stdin ({} bytes)", prompt.len());
+        // Written from a task so a full pipe can't block reading the output below;
+        // dropping stdin closes it, which ends the prompt
+        let stdin_prompt = prompt.clone();
+        tokio::spawn(async move {
+            if let Err(e) = stdin.write_all(stdin_prompt.as_bytes()).await {
+                log::error!("Failed to write prompt to Claude's stdin: {}", e);
+            }
+        });
+    }
+
     // Get stdout and stderr
     let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
@@ -1439,6 +1551,11 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
     // We'll extract the session ID from Claude's init message
     let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
     let run_id_holder: Arc<Mutex<Option<i64>>> = Arc::new(Mutex::new(None));
+    let progress: Arc<Mutex<ProgressTracker>> = Arc::new(Mutex::new(ProgressTracker::new()));
+    let progress_finished = Arc::new(tokio::sync::Notify::new());
+    // What the run cost, from Claude's closing result message
+    let run_cost: Arc<Mutex<Option<f64>>> = Arc::new(Mutex::new(None));
+    let started = std::time::Instant::now();
 
     // Store the child process in the global state (for backward compatibility)
     let claude_state = app.state::<ClaudeProcessState>();
@@ -1461,13 +1578,38 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
     let project_path_clone = project_path.clone();
     let prompt_clone = prompt.clone();
     let model_clone = model.clone();
+    let progress_clone = progress.clone();
+    let run_cost_clone = run_cost.clone();
     let stdout_task = tokio::spawn(async move {
         let mut lines = stdout_reader.lines();
+        let mut file_writes = crate::adapters::tool_bridge::ClaudeFileWrites::default();
         while let Ok(Some(line)) = lines.next_line().await {
             log::debug!("Claude stdout: {}", line);
             
             // Parse the line to check for init message with session ID
             if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) {
+                if let Ok(mut tracker) = progress_clone.lock() {
+                    if let Some(claude_session_id) = msg["session_id"].as_str() {
+                        tracker.set_session_id(claude_session_id);
+                    }
+                    tracker.record_stream_message(&msg);
+                }
+                if msg["type"] == "result" {
+                    if let (Some(cost), Ok(mut run_cost)) = (msg["total_cost_usd"].as_f64(), run_cost_clone.lock()) {
+                        *run_cost = Some(cost);
+                    }
+                }
+                if let Some(mut usage) = claude_turn_usage(&msg, &project_path_clone, &model_clone) {
+                    if let Err(e) = track_turn_usage(&app_handle, &mut usage) {
+                        log::warn!("Failed to record Claude turn usage: {}", e);
+                    }
+                }
+                // Claude's own Write and Edit calls report like bridge writes do
+                if let Some(claude_session_id) = msg["session_id"].as_str() {
+                    for (event, payload) in file_writes.observe(&msg) {
+                        let _ = emit_session_event(&app_handle, &format!("{}:{}", event, claude_session_id), payload);
+                    }
+                }
                 if msg["type"] == "system" && msg["subtype"] == "init" {
                     if let Some(claude_session_id) = msg["session_id"].as_str() {
                         let mut session_id_guard = match session_id_holder_clone.lock() {
@@ -1480,6 +1622,10 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                         if session_id_guard.is_none() {
                             *session_id_guard = Some(claude_session_id.to_string());
                             log::info!("Extracted Claude session ID: {}", claude_session_id);
+                            if let Some(tags) = &tags {
+                                super::ai_usage_tracker::tag_usage_session(&app_handle, claude_session_id, tags);
+                            }
+                            super::routing_decisions::note_session_model(&app_handle, claude_session_id, &model_clone);
                             
                             // Now register with ProcessRegistry using Claude's session ID
                             match registry_clone.register_claude_session(
@@ -1509,6 +1655,8 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                 }
             }
             
+            super::provider_quota::observe_claude_output(&line);
+
             // Store live output in registry if we have a run_id
             if let Ok(guard) = run_id_holder_clone.lock() {
                 if let Some(run_id) = *guard {
@@ -1519,7 +1667,7 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
             // Emit the line to the frontend with session isolation if we have session ID
             if let Ok(guard) = session_id_holder_clone.lock() {
                 if let Some(ref session_id) = *guard {
-                    let _ = app_handle.emit(&format!("claude-output:{}", session_id), &line);
+                    let _ = emit_session_event(&app_handle, &format!("claude-output:{}", session_id), &line);
                 }
             }
             // Also emit to the generic event for backward compatibility
@@ -1527,6 +1675,27 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
         }
     });
 
+    // Periodic progress so the UI can show elapsed time and token rate
+    let app_handle_progress = app.clone();
+    let execution_sessions = app.state::<ExecutionControlState>().sessions.clone();
+    tokio::spawn(run_progress_ticker(
+        progress,
+        PROGRESS_EMIT_INTERVAL,
+        progress_finished.clone(),
+        move |update| {
+            // Skipping a tick under contention is fine; the next one catches up
+            if let Ok(mut sessions) = execution_sessions.try_lock() {
+                record_execution_metrics(
+                    &mut sessions,
+                    &update.session_id,
+                    Some(update.elapsed_time),
+                    Some(update.total_tokens),
+                );
+            }
+            let _ = app_handle_progress.emit(&format!("claude-progress:{}", update.session_id), &update);
+        },
+    ));
+
     let app_handle_stderr = app.clone();
     let session_id_holder_clone2 = session_id_holder.clone();
     let stderr_task = tokio::spawn(async move {
@@ -1536,7 +1705,7 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
             // Emit error lines to the frontend with session isolation if we have session ID
             if let Ok(guard) = session_id_holder_clone2.lock() {
                 if let Some(ref session_id) = *guard {
-                    let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id), &line);
+                    let _ = emit_session_event(&app_handle_stderr, &format!("claude-error:{}", session_id), &line);
                 }
             }
             // Also emit to the generic event for backward compatibility
@@ -1553,6 +1722,7 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
     tokio::spawn(async move {
         let _ = stdout_task.await;
         let _ = stderr_task.await;
+        progress_finished.notify_one();
 
         // Get the child from the state to wait on it
         let mut current_process = claude_state_wait.lock().await;
@@ -1563,7 +1733,16 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                     // Add a small delay to ensure all messages are processed
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        let _ = app_handle_wait.emit(
+                        let cost = run_cost.lock().ok().and_then(|cost| *cost);
+                        super::routing_decisions::record_session_outcome(
+                            &app_handle_wait,
+                            session_id,
+                            status.success(),
+                            cost,
+                            Some(started.elapsed().as_millis() as i64),
+                        );
+                        let _ = emit_session_event(
+                            &app_handle_wait,
                             &format!("claude-complete:{}", session_id),
                             status.success(),
                         );
@@ -1577,8 +1756,18 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     if let Ok(guard) = session_id_holder_clone3.lock() {
                         if let Some(ref session_id) = *guard {
-                            let _ = app_handle_wait
-                                .emit(&format!("claude-complete:{}", session_id), false);
+                            super::routing_decisions::record_session_outcome(
+                                &app_handle_wait,
+                                session_id,
+                                false,
+                                None,
+                                Some(started.elapsed().as_millis() as i64),
+                            );
+                            let _ = emit_session_event(
+                                &app_handle_wait,
+                                &format!("claude-complete:{}", session_id),
+                                false,
+                            );
                         }
                     }
                     // Also emit to the generic event for backward compatibility
@@ -1739,12 +1928,13 @@ fn search_files_recursive(
         return Ok(());
     }
 
-    let entries = fs::read_dir(current_path)
+    let entries = fs::read_dir(crate::windows_command::long_path(current_path))
         .map_err(|e| format!("Failed to read directory {:?}: {}", current_path, e))?;
 
     for entry in entries {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let entry_path = entry.path();
+        // Keep result paths free of the extended-length prefix
+        let entry_path = current_path.join(entry.file_name());
 
         // Skip hidden files/directories
         if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
@@ -1778,7 +1968,7 @@ fn search_files_recursive(
         }
 
         // Recurse into directories
-        if entry_path.is_dir() {
+        if entry.path().is_dir() {
             // Skip common directories that shouldn't be searched
             if let Some(dir_name) = entry_path.file_name().and_then(|n| n.to_str()) {
                 if matches!(
@@ -2038,12 +2228,15 @@ pub async fn update_checkpoint_settings(
 }
 
 /// Gets diff between two checkpoints
+///
+/// `format` selects unified diff text (the default) or structured hunks for each modified file.
 #[tauri::command]
 pub async fn get_checkpoint_diff(
     from_checkpoint_id: String,
     to_checkpoint_id: String,
     session_id: String,
     project_id: String,
+    format: Option<crate::checkpoint::diff::DiffFormat>,
 ) -> Result<crate::checkpoint::CheckpointDiff, String> {
     use crate::checkpoint::storage::CheckpointStorage;
 
@@ -2064,54 +2257,11 @@ pub async fn get_checkpoint_diff(
         .load_checkpoint(&project_id, &session_id, &to_checkpoint_id)
         .map_err(|e| format!("Failed to load target checkpoint: {}", e))?;
 
-    // Build file maps
-    let mut from_map: std::collections::HashMap<PathBuf, &crate::checkpoint::FileSnapshot> =
-        std::collections::HashMap::new();
-    for file in &from_files {
-        from_map.insert(file.file_path.clone(), file);
-    }
-
-    let mut to_map: std::collections::HashMap<PathBuf, &crate::checkpoint::FileSnapshot> =
-        std::collections::HashMap::new();
-    for file in &to_files {
-        to_map.insert(file.file_path.clone(), file);
-    }
-
-    // Calculate differences
-    let mut modified_files = Vec::new();
-    let mut added_files = Vec::new();
-    let mut deleted_files = Vec::new();
-
-    // Check for modified and deleted files
-    for (path, from_file) in &from_map {
-        if let Some(to_file) = to_map.get(path) {
-            if from_file.hash != to_file.hash {
-                // File was modified
-                let additions = to_file.content.lines().count();
-                let deletions = from_file.content.lines().count();
-
-                // Generate actual diff content
-                let diff_content = generate_diff_content(&from_file.content, &to_file.content);
-                
-                modified_files.push(crate::checkpoint::FileDiff {
-                    path: path.clone(),
-                    additions,
-                    deletions,
-                    diff_content: Some(diff_content),
-                });
-            }
-        } else {
-            // File was deleted
-            deleted_files.push(path.clone());
-        }
-    }
-
-    // Check for added files
-    for (path, _) in &to_map {
-        if !from_map.contains_key(path) {
-            added_files.push(path.clone());
-        }
-    }
+    let changes = crate::checkpoint::diff::diff_snapshots(
+        &from_files,
+        &to_files,
+        format.unwrap_or_default(),
+    );
 
     // Calculate token delta
     let token_delta = (to_checkpoint.metadata.total_tokens as i64)
@@ -2120,9 +2270,10 @@ pub async fn get_checkpoint_diff(
     Ok(crate::checkpoint::CheckpointDiff {
         from_checkpoint_id,
         to_checkpoint_id,
-        modified_files,
-        added_files,
-        deleted_files,
+        modified_files: changes.modified,
+        added_files: changes.added,
+        deleted_files: changes.deleted,
+        renamed_files: changes.renamed,
         token_delta,
     })
 }
@@ -2198,6 +2349,33 @@ pub async fn cleanup_old_checkpoints(
         .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))
 }
 
+/// Scans the checkpoint store and session tables for dangling references and missing blobs
+#[tauri::command]
+pub async fn verify_checkpoint_integrity(
+    db: tauri::State<'_, super::agents::AgentDb>,
+) -> Result<crate::checkpoint::integrity::IntegrityReport, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    crate::checkpoint::integrity::verify(&claude_dir, || db.lock_conn())
+        .map_err(|e| format!("Failed to verify checkpoints: {:#}", e))
+}
+
+/// Prunes dangling checkpoint entries or marks checkpoints with missing data unrestorable
+#[tauri::command]
+pub async fn repair_checkpoints(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    mode: crate::checkpoint::integrity::RepairMode,
+) -> Result<crate::checkpoint::integrity::RepairReport, String> {
+    log::info!("Repairing checkpoints with mode {:?}", mode);
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+
+    // Cached managers hold timelines in memory and would write back pruned entries
+    app.clear_all().await;
+
+    crate::checkpoint::integrity::repair(&claude_dir, || db.lock_conn(), mode)
+        .map_err(|e| format!("Failed to repair checkpoints: {:#}", e))
+}
+
 /// Gets checkpoint settings for a session
 #[tauri::command]
 pub async fn get_checkpoint_settings(
@@ -2243,12 +2421,26 @@ pub async fn get_checkpoint_state_stats(
     let active_count = app.active_count().await;
     let active_sessions = app.list_active_sessions().await;
 
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::gc::storage_stats(&claude_dir)
+        .map_err(|e| format!("Failed to read checkpoint storage stats: {:#}", e))?;
+
     Ok(serde_json::json!({
         "active_managers": active_count,
         "active_sessions": active_sessions,
+        "storage": storage,
     }))
 }
 
+/// Deletes checkpoint content no longer referenced by any checkpoint
+#[tauri::command]
+pub async fn gc_checkpoints() -> Result<crate::checkpoint::gc::GcReport, String> {
+    log::info!("Collecting unreferenced checkpoint content");
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    crate::checkpoint::gc::collect_garbage(&claude_dir)
+        .map_err(|e| format!("Failed to collect checkpoint garbage: {:#}", e))
+}
+
 /// Gets files modified in the last N minutes for a session
 #[tauri::command]
 pub async fn get_recently_modified_files(
@@ -2520,41 +2712,3 @@ pub async fn validate_hook_command(command: String) -> Result<serde_json::Value,
         Err(e) => Err(format!("Failed to validate command: {}", e))
     }
 }
-
-/// Generate diff content between two file contents
-fn generate_diff_content(from_content: &str, to_content: &str) -> String {
-    use similar::{ChangeTag, TextDiff};
-    
-    let diff = TextDiff::from_lines(from_content, to_content);
-    let mut diff_output = String::new();
-    
-    for (idx, group) in diff.grouped_ops(3).iter().enumerate() {
-        if idx > 0 {
-            diff_output.push_str("...\n");
-        }
-        
-        for op in group {
-            for change in diff.iter_changes(op) {
-                match change.tag() {
-                    ChangeTag::Delete => {
-                        diff_output.push('-');
-                        diff_output.push_str(&change.to_string());
-                    }
-                    ChangeTag::Insert => {
-                        diff_output.push('+');
-                        diff_output.push_str(&change.to_string());
-                    }
-                    ChangeTag::Equal => {
-                        diff_output.push(' ');
-                        diff_output.push_str(&change.to_string());
-                    }
-                }
-                if !change.to_string().ends_with('\n') {
-                    diff_output.push('\n');
-                }
-            }
-        }
-    }
-    
-    diff_output
-}
@@ -0,0 +1,193 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::settings_diff::{text, unset, ResetFuture, Setting};
+use crate::checkpoint::state::CheckpointState;
+
+pub(crate) const CLAUDE_DIR_SETTINGS_KEY: &str = "claude_dir";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::new(CLAUDE_DIR_SETTINGS_KEY, text, unset).on_reset(use_default_dir)];
+
+fn use_default_dir(app: AppHandle) -> ResetFuture {
+    Box::pin(async move {
+        use_claude_dir(&app, None).await;
+        Ok(())
+    })
+}
+
+/// Environment variable the Claude CLI reads its configuration directory from
+pub const CLAUDE_CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+lazy_static::lazy_static! {
+    static ref CONFIGURED_CLAUDE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// Where the Claude directory path came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaudeDirSource {
+    Setting,
+    Environment,
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClaudeDirInfo {
+    pub path: String,
+    pub source: ClaudeDirSource,
+    pub exists: bool,
+}
+
+/// The directory to use given the setting, the environment variable and the home directory
+///
+/// The app reads sessions, projects, commands and agents where the Claude CLI keeps
+/// them: the directory chosen in settings, otherwise `CLAUDE_CONFIG_DIR` as the CLI
+/// itself reads it, otherwise `~/.claude`.
+fn resolve_claude_dir(
+    configured: Option<&Path>,
+    env_dir: Option<OsString>,
+    home: Option<PathBuf>,
+) -> Option<(PathBuf, ClaudeDirSource)> {
+    if let Some(dir) = configured {
+        return Some((dir.to_path_buf(), ClaudeDirSource::Setting));
+    }
+    if let Some(dir) = env_dir.filter(|dir| !dir.is_empty()) {
+        return Some((PathBuf::from(dir), ClaudeDirSource::Environment));
+    }
+    home.map(|home| (home.join(".claude"), ClaudeDirSource::Default))
+}
+
+fn current_claude_dir() -> Option<(PathBuf, ClaudeDirSource)> {
+    let configured = CONFIGURED_CLAUDE_DIR.read().unwrap_or_else(|e| e.into_inner()).clone();
+    resolve_claude_dir(configured.as_deref(), std::env::var_os(CLAUDE_CONFIG_DIR_ENV), dirs::home_dir())
+}
+
+/// Path of the Claude directory, which may not exist yet
+pub fn claude_dir_path() -> Result<PathBuf, String> {
+    current_claude_dir()
+        .map(|(dir, _)| dir)
+        .ok_or_else(|| "Failed to get home directory".to_string())
+}
+
+/// The directory to hand to the Claude CLI as `CLAUDE_CONFIG_DIR`, unless it is the default
+///
+/// Spawned commands only inherit selected variables, so the CLI would otherwise fall back
+/// to `~/.claude` and write sessions where the app doesn't look.
+pub fn cli_config_dir() -> Option<PathBuf> {
+    current_claude_dir()
+        .filter(|(_, source)| *source != ClaudeDirSource::Default)
+        .map(|(dir, _)| dir)
+}
+
+fn load_setting(conn: &Connection) -> Option<PathBuf> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![CLAUDE_DIR_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .filter(|value| !value.trim().is_empty())
+    .map(PathBuf::from)
+}
+
+/// Apply the stored setting; called once at startup
+///
+/// The setting is kept in memory since the path is needed in many places that
+/// have no database at hand.
+pub fn load_configured_claude_dir(conn: &Connection) {
+    let configured = load_setting(conn);
+    if let Some(dir) = &configured {
+        log::info!("Using Claude directory from settings: {}", dir.display());
+    }
+    *CONFIGURED_CLAUDE_DIR.write().unwrap_or_else(|e| e.into_inner()) = configured;
+}
+
+fn save_setting(conn: &Connection, dir: Option<&Path>) -> Result<(), String> {
+    match dir {
+        Some(dir) => conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![CLAUDE_DIR_SETTINGS_KEY, dir.to_string_lossy()],
+        ),
+        None => conn.execute("DELETE FROM app_settings WHERE key = ?1", params![CLAUDE_DIR_SETTINGS_KEY]),
+    }
+    .map_err(|e| format!("Failed to save Claude directory: {}", e))?;
+    Ok(())
+}
+
+/// Get the Claude directory in use and where its path came from
+#[tauri::command]
+pub async fn get_claude_dir() -> Result<ClaudeDirInfo, String> {
+    let (dir, source) = current_claude_dir().ok_or("Failed to get home directory")?;
+    Ok(ClaudeDirInfo { path: dir.to_string_lossy().to_string(), exists: dir.is_dir(), source })
+}
+
+/// Use `path` as the Claude directory; an empty or missing path restores the default
+#[tauri::command]
+pub async fn set_claude_dir(
+    path: Option<String>,
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<ClaudeDirInfo, String> {
+    let dir = match path.as_deref().map(str::trim).filter(|path| !path.is_empty()) {
+        Some(path) => Some(
+            Path::new(path)
+                .canonicalize()
+                .ok()
+                .filter(|dir| dir.is_dir())
+                .ok_or_else(|| format!("{} is not an existing directory", path))?,
+        ),
+        None => None,
+    };
+    save_setting(&*db.lock_conn(), dir.as_deref())?;
+    use_claude_dir(&app, dir).await;
+
+    let info = get_claude_dir().await?;
+    log::info!("Claude directory set to {} ({:?})", info.path, info.source);
+    Ok(info)
+}
+
+/// Switch to `dir`, or back to the default, including for checkpoints
+async fn use_claude_dir(app: &AppHandle, dir: Option<PathBuf>) {
+    *CONFIGURED_CLAUDE_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir;
+    if let (Some(checkpoints), Ok(dir)) = (app.try_state::<CheckpointState>(), super::claude::get_claude_dir()) {
+        checkpoints.set_claude_dir(dir).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_dir_overrides_default() {
+        let home = PathBuf::from("/home/dev");
+        let configured = PathBuf::from("/data/claude");
+        let env_dir = || Some(OsString::from("/opt/claude-config"));
+
+        assert_eq!(
+            resolve_claude_dir(None, None, Some(home.clone())),
+            Some((home.join(".claude"), ClaudeDirSource::Default))
+        );
+        assert_eq!(
+            resolve_claude_dir(None, env_dir(), Some(home.clone())),
+            Some((PathBuf::from("/opt/claude-config"), ClaudeDirSource::Environment))
+        );
+        assert_eq!(
+            resolve_claude_dir(Some(&configured), env_dir(), Some(home.clone())),
+            Some((configured.clone(), ClaudeDirSource::Setting))
+        );
+        assert_eq!(resolve_claude_dir(None, Some(OsString::new()), None), None);
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        save_setting(&conn, Some(&configured)).unwrap();
+        assert_eq!(load_setting(&conn), Some(configured));
+        save_setting(&conn, None).unwrap();
+        assert_eq!(load_setting(&conn), None);
+    }
+}
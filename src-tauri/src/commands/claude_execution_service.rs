@@ -20,7 +20,7 @@ impl ExecutionService for ClaudeExecutionService {
         let model = request.model;
 
         let run_id = {
-            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let conn = db.lock_conn();
             conn.execute(
                 "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 rusqlite::params![agent.id.unwrap(), agent.name, agent.icon, task, model, project_path, ""],
@@ -9,11 +9,16 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Manager, Emitter};
 use tokio::sync::Mutex;
-use tokio::time::{timeout, interval};
+use tokio::time::timeout;
 
+use crate::commands::claude::{probe_claude_auth, AuthProbe};
+use crate::commands::background_tasks::{BackgroundTaskSupervisor, TaskSchedule};
 use crate::commands::slash_commands::SlashCommand;
 use crate::claude_binary::find_claude_binary;
 
+/// Shortest lead time for expiry warnings when the sync interval is shorter than this
+const AUTH_EXPIRY_MIN_WARNING_SECS: u64 = 24 * 60 * 60;
+
 /// Claude Code CLI command metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeCliCommand {
@@ -30,6 +35,8 @@ pub struct ClaudeCliCommand {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeSyncResult {
     pub success: bool,
+    /// Human-readable summary, shown as is
+    pub message: String,
     pub commands_found: usize,
     pub new_commands: usize,
     pub updated_commands: usize,
@@ -46,6 +53,40 @@ pub struct ClaudeSyncState {
     pub commands_cache: HashMap<String, ClaudeCliCommand>,
     pub sync_enabled: bool,
     pub auto_sync_interval_hours: u64,
+    /// Last time a background probe confirmed Claude auth worked
+    #[serde(default)]
+    pub last_auth_ok: Option<u64>,
+    #[serde(skip)]
+    pub auth_health: Option<ClaudeAuthHealth>,
+}
+
+/// Claude credential health as seen by the last background probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaudeAuthState {
+    Valid,
+    Expiring,
+    Expired,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeAuthHealth {
+    pub state: ClaudeAuthState,
+    pub message: String,
+    pub checked_at: u64,
+    pub last_known_good: Option<u64>,
+    /// When the stored OAuth token lapses, if the CLI cannot refresh it
+    pub expires_at: Option<u64>,
+}
+
+/// Overall status shown on the settings and startup screens
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemStatus {
+    pub claude_version: Option<String>,
+    pub last_sync: Option<u64>,
+    pub next_sync: Option<u64>,
+    pub claude_auth: ClaudeAuthHealth,
 }
 
 impl Default for ClaudeSyncState {
@@ -56,6 +97,8 @@ impl Default for ClaudeSyncState {
             commands_cache: HashMap::new(),
             sync_enabled: true,
             auto_sync_interval_hours: 24, // Default to 24 hours
+            last_auth_ok: None,
+            auth_health: None,
         }
     }
 }
@@ -215,9 +258,7 @@ async fn discover_from_config() -> Result<Vec<ClaudeCliCommand>> {
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
     
     // Check for Claude configuration directories
-    if let Some(home_dir) = dirs::home_dir() {
-        let claude_dir = home_dir.join(".claude");
-        
+    if let Ok(claude_dir) = super::claude_dir::claude_dir_path() {
         // Look for commands in .claude/commands
         let commands_dir = claude_dir.join("commands");
         if commands_dir.exists() {
@@ -388,6 +429,7 @@ async fn sync_claude_commands_internal(
         if *in_progress {
             return Ok(ClaudeSyncResult {
                 success: false,
+                message: "Sync already in progress".to_string(),
                 commands_found: 0,
                 new_commands: 0,
                 updated_commands: 0,
@@ -430,6 +472,7 @@ async fn sync_claude_commands_internal(
             error!("{}", error_msg);
             return Ok(ClaudeSyncResult {
                 success: false,
+                message: error_msg.clone(),
                 commands_found: 0,
                 new_commands: 0,
                 updated_commands: 0,
@@ -472,9 +515,22 @@ async fn sync_claude_commands_internal(
     // Emit event to notify UI
     app.emit("claude-commands-synced", &slash_commands)
         .map_err(|e| e.to_string())?;
+
+    // Commands whose files, agents or referenced commands went away surface now, not when run
+    match super::slash_commands::validate_slash_commands(None, app.clone()).await {
+        Ok(broken) if !broken.is_empty() => {
+            warn!("{} slash command(s) are broken after sync", broken.len());
+            if let Err(e) = app.emit("slash-commands-broken", &broken) {
+                error!("Failed to emit broken slash commands: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not validate slash commands after sync: {}", e),
+    }
     
     Ok(ClaudeSyncResult {
         success: true,
+        message: format!("Synced {} Claude commands", commands_found),
         commands_found,
         new_commands,
         updated_commands: 0, // We don't track updates yet
@@ -677,8 +733,12 @@ pub async fn force_refresh_claude_commands(
     sync_claude_commands(app, state).await
 }
 
-/// Start automatic sync background task
-pub async fn start_auto_sync(app: AppHandle, global_state: Arc<GlobalSyncState>) {
+/// Start automatic sync as a supervised background task
+pub async fn start_auto_sync(
+    app: AppHandle,
+    global_state: Arc<GlobalSyncState>,
+    supervisor: BackgroundTaskSupervisor,
+) {
     info!("Starting automatic Claude sync background task");
     
     // Load initial state
@@ -687,66 +747,234 @@ pub async fn start_auto_sync(app: AppHandle, global_state: Arc<GlobalSyncState>)
         *state = saved_state;
     }
     
-    // Initial sync after 10 seconds
-    tokio::time::sleep(Duration::from_secs(10)).await;
+    // An interval of zero would sync continuously
+    let interval_hours = {
+        let state = global_state.state.lock().await;
+        state.auto_sync_interval_hours.max(1)
+    };
     
-    // Perform initial sync
-    if let Err(e) = sync_claude_commands_internal(app.clone(), global_state.clone()).await {
-        error!("Initial Claude sync failed: {}", e);
-    }
+    // Initial sync after 10 seconds, then every interval
+    supervisor.spawn(
+        "claude-sync",
+        TaskSchedule::every(Duration::from_secs(10), Duration::from_secs(interval_hours * 60 * 60)),
+        move || run_auto_sync(app.clone(), global_state.clone()),
+    );
+}
+
+/// One scheduled sync; a failed sync is an error so the supervisor retries it
+async fn run_auto_sync(app: AppHandle, global_state: Arc<GlobalSyncState>) -> Result<(), String> {
+    // Auth is probed even with sync disabled so expiry never surprises a run
+    check_claude_auth_health(&app, &global_state).await;
     
-    // Set up periodic sync
-    let interval_hours = {
+    // Check if sync is enabled
+    let sync_enabled = {
         let state = global_state.state.lock().await;
-        state.auto_sync_interval_hours
+        state.sync_enabled
     };
     
-    let mut interval = interval(Duration::from_secs(interval_hours * 60 * 60));
+    if !sync_enabled {
+        info!("Automatic Claude sync is disabled, skipping");
+        return Ok(());
+    }
     
-    loop {
-        interval.tick().await;
-        
-        // Check if sync is enabled
-        let sync_enabled = {
-            let state = global_state.state.lock().await;
-            state.sync_enabled
-        };
-        
-        if !sync_enabled {
-            info!("Automatic Claude sync is disabled, skipping");
-            continue;
-        }
-        
-        info!("Running scheduled Claude sync");
-        
-        match sync_claude_commands_internal(app.clone(), global_state.clone()).await {
-            Ok(result) => {
-                if result.success {
-                    info!("Scheduled Claude sync completed successfully");
+    info!("Running scheduled Claude sync");
+    
+    let result = sync_claude_commands_internal(app, global_state).await?;
+    if !result.success {
+        return Err(format!(
+            "Claude sync completed with errors: {}",
+            result.error.unwrap_or_else(|| "unknown error".to_string())
+        ));
+    }
+    info!("Scheduled Claude sync completed successfully");
+    Ok(())
+}
+
+/// Get next sync time
+#[tauri::command]
+pub async fn get_next_sync_time(state: tauri::State<'_, GlobalSyncState>) -> Result<Option<u64>, String> {
+    let sync_state = state.state.lock().await;
+    Ok(next_sync_time(&sync_state))
+}
+
+fn next_sync_time(state: &ClaudeSyncState) -> Option<u64> {
+    if !state.sync_enabled {
+        return None;
+    }
+    state
+        .last_sync
+        .map(|last_sync| last_sync + (state.auto_sync_interval_hours * 60 * 60))
+}
+
+/// Expiry of the stored OAuth token, when there is no refresh token to renew it
+///
+/// macOS keeps credentials in the keychain, so this is only known on Windows and Linux.
+fn read_credentials_expiry() -> Option<u64> {
+    let path = crate::commands::claude::get_claude_dir().ok()?.join(".credentials.json");
+    let credentials: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let oauth = credentials.get("claudeAiOauth")?;
+    let refreshable = oauth
+        .get("refreshToken")
+        .and_then(|token| token.as_str())
+        .is_some_and(|token| !token.is_empty());
+    if refreshable {
+        return None;
+    }
+    // expiresAt is in milliseconds
+    oauth.get("expiresAt")?.as_u64().map(|ms| ms / 1000)
+}
+
+/// Fold an auth probe into the sync state, emitting an event when auth is lapsing or has lapsed
+fn record_auth_probe<F>(
+    state: &mut ClaudeSyncState,
+    probe: AuthProbe,
+    expires_at: Option<u64>,
+    now: u64,
+    emit: F,
+) -> ClaudeAuthHealth
+where
+    F: Fn(&str, &ClaudeAuthHealth),
+{
+    // Warn early enough that the next scheduled check is not already too late
+    let warning_window = (state.auto_sync_interval_hours * 60 * 60).max(AUTH_EXPIRY_MIN_WARNING_SECS);
+
+    let (auth_state, message) = match probe {
+        AuthProbe::Rejected(message) => (ClaudeAuthState::Expired, message),
+        AuthProbe::Inconclusive(message) => (ClaudeAuthState::Unknown, message),
+        AuthProbe::Authenticated => match expires_at {
+            Some(expiry) if expiry.saturating_sub(now) <= warning_window => {
+                let hours = expiry.saturating_sub(now) / 3600;
+                let remaining = if hours == 0 {
+                    "less than an hour".to_string()
                 } else {
-                    warn!("Scheduled Claude sync completed with errors: {:?}", result.error);
-                }
-            }
-            Err(e) => {
-                error!("Scheduled Claude sync failed: {}", e);
+                    format!("about {} hours", hours)
+                };
+                (
+                    ClaudeAuthState::Expiring,
+                    format!(
+                        "Claude Code credentials expire in {}. Run 'claude setup-token' to renew them.",
+                        remaining
+                    ),
+                )
             }
+            _ => (
+                ClaudeAuthState::Valid,
+                "Claude Code is authenticated and ready to use.".to_string(),
+            ),
+        },
+    };
+
+    if matches!(auth_state, ClaudeAuthState::Valid | ClaudeAuthState::Expiring) {
+        state.last_auth_ok = Some(now);
+    }
+
+    let health = ClaudeAuthHealth {
+        state: auth_state,
+        message,
+        checked_at: now,
+        last_known_good: state.last_auth_ok,
+        expires_at,
+    };
+
+    match auth_state {
+        ClaudeAuthState::Expiring => emit("claude-auth-expiring", &health),
+        ClaudeAuthState::Expired => emit("claude-auth-expired", &health),
+        ClaudeAuthState::Valid | ClaudeAuthState::Unknown => {}
+    }
+
+    state.auth_health = Some(health.clone());
+    health
+}
+
+/// Probe Claude auth and warn the frontend before a run hits expired credentials
+pub async fn check_claude_auth_health(app: &AppHandle, global_state: &GlobalSyncState) -> ClaudeAuthHealth {
+    let probe = probe_claude_auth(app).await;
+    let expires_at = read_credentials_expiry();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    let mut state = global_state.state.lock().await;
+    let health = record_auth_probe(&mut state, probe, expires_at, now, |event, health| {
+        warn!("{}", health.message);
+        if let Err(e) = app.emit(event, health) {
+            error!("Failed to emit {}: {}", event, e);
         }
+    });
+
+    if let Err(e) = save_sync_state(&state, app).await {
+        error!("Failed to save sync state: {}", e);
     }
+
+    health
 }
 
-/// Get next sync time
+/// Get Claude version, sync schedule and auth health in one call
 #[tauri::command]
-pub async fn get_next_sync_time(state: tauri::State<'_, GlobalSyncState>) -> Result<Option<u64>, String> {
+pub async fn get_system_status(
+    app: AppHandle,
+    state: tauri::State<'_, GlobalSyncState>,
+) -> Result<SystemStatus, String> {
+    let cached_auth = state.state.lock().await.auth_health.clone();
+    let claude_auth = match cached_auth {
+        Some(health) => health,
+        None => check_claude_auth_health(&app, &state).await,
+    };
+
     let sync_state = state.state.lock().await;
-    
-    if !sync_state.sync_enabled {
-        return Ok(None);
+    Ok(SystemStatus {
+        claude_version: sync_state.claude_version.clone(),
+        last_sync: sync_state.last_sync,
+        next_sync: next_sync_time(&sync_state),
+        claude_auth,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    #[test]
+    fn test_expired_auth_probe_fires_event() {
+        let mut state = ClaudeSyncState { last_auth_ok: Some(1_000), ..Default::default() };
+        let events = StdMutex::new(Vec::new());
+
+        let health = record_auth_probe(
+            &mut state,
+            AuthProbe::Rejected("Claude Code is not authenticated.".to_string()),
+            None,
+            5_000,
+            |event, _| events.lock().unwrap().push(event.to_string()),
+        );
+
+        assert_eq!(health.state, ClaudeAuthState::Expired);
+        assert_eq!(*events.lock().unwrap(), vec!["claude-auth-expired"]);
+        // A failed probe keeps the last-known-good time
+        assert_eq!(health.last_known_good, Some(1_000));
+        assert_eq!(state.last_auth_ok, Some(1_000));
+        assert_eq!(state.auth_health.unwrap().state, ClaudeAuthState::Expired);
     }
-    
-    if let Some(last_sync) = sync_state.last_sync {
-        let next_sync = last_sync + (sync_state.auto_sync_interval_hours * 60 * 60);
-        Ok(Some(next_sync))
-    } else {
-        Ok(None)
+
+    #[test]
+    fn test_auth_expiring_within_sync_interval_warns() {
+        let mut state = ClaudeSyncState::default();
+        let events = StdMutex::new(Vec::new());
+        let record = |state: &mut ClaudeSyncState, probe, expires_at| {
+            record_auth_probe(state, probe, expires_at, 10_000, |event, _| {
+                events.lock().unwrap().push(event.to_string())
+            })
+        };
+
+        let far = record(&mut state, AuthProbe::Authenticated, Some(10_000 + 48 * 3600));
+        assert_eq!(far.state, ClaudeAuthState::Valid);
+        assert!(events.lock().unwrap().is_empty());
+
+        let soon = record(&mut state, AuthProbe::Authenticated, Some(10_000 + 3 * 3600));
+        assert_eq!(soon.state, ClaudeAuthState::Expiring);
+        assert!(soon.message.contains("about 3 hours"));
+        assert_eq!(soon.last_known_good, Some(10_000));
+
+        let unknown = record(&mut state, AuthProbe::Inconclusive("Claude Code not found".to_string()), None);
+        assert_eq!(unknown.state, ClaudeAuthState::Unknown);
+        assert_eq!(*events.lock().unwrap(), vec!["claude-auth-expiring"]);
     }
 }
\ No newline at end of file
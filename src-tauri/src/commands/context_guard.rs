@@ -0,0 +1,119 @@
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::intelligent_routing::{load_model_benchmarks, AiModelBenchmark};
+
+/// Characters per token used for the estimate; close enough to catch pasted logs or files far over the limit
+const CHARS_PER_TOKEN: u64 = 4;
+
+/// Rough token count for `text`
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Smallest model that can take `tokens`, preferring the same provider as `model`
+///
+/// Ties on window size go to the model with the higher intelligence score.
+fn larger_context_model<'a>(
+    model: &AiModelBenchmark,
+    tokens: u64,
+    benchmarks: &'a [AiModelBenchmark],
+) -> Option<&'a AiModelBenchmark> {
+    benchmarks
+        .iter()
+        .filter(|b| b.model_id != "auto" && u64::from(b.context_window) >= tokens)
+        .min_by(|a, b| {
+            (a.provider != model.provider, a.context_window)
+                .cmp(&(b.provider != model.provider, b.context_window))
+                .then(b.intelligence_score.total_cmp(&a.intelligence_score))
+        })
+}
+
+/// Fail with an actionable message when `prompt` exceeds `model`'s context window
+///
+/// Oversized prompts otherwise fail deep inside execution with an opaque API error.
+/// Models without a benchmark entry, and `auto`, are let through since their
+/// window is unknown until a concrete model is chosen.
+pub fn check_context_window(model: &str, prompt: &str, benchmarks: &[AiModelBenchmark]) -> Result<(), String> {
+    let Some(benchmark) = benchmarks
+        .iter()
+        .find(|b| b.model_id != "auto" && crate::models::same_model(&b.model_id, model))
+    else {
+        return Ok(());
+    };
+
+    let tokens = estimate_tokens(prompt);
+    let limit = u64::from(benchmark.context_window);
+    if tokens <= limit {
+        return Ok(());
+    }
+
+    let advice = match larger_context_model(benchmark, tokens, benchmarks) {
+        Some(larger) => format!(
+            "try model {} with a larger context ({} tokens)",
+            larger.model_id, larger.context_window
+        ),
+        None => "no known model has a large enough context; shorten the prompt or split it up".to_string(),
+    };
+    Err(format!(
+        "Prompt too large for model {} ({} > {} tokens); {}",
+        benchmark.model_id, tokens, limit, advice
+    ))
+}
+
+/// `check_context_window` against the stored benchmarks
+///
+/// Benchmarks that cannot be loaded only skip the check; the provider still
+/// rejects a prompt that is really too large.
+pub fn ensure_prompt_fits(app: &AppHandle, model: &str, prompt: &str) -> Result<(), String> {
+    let benchmarks = {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        load_model_benchmarks(&conn)
+    };
+    match benchmarks {
+        Ok(benchmarks) => check_context_window(model, prompt, &benchmarks),
+        Err(e) => {
+            log::warn!("Skipping context window check for {}: {}", model, e);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn benchmarks() -> Vec<AiModelBenchmark> {
+        load_model_benchmarks(&Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_oversized_prompt_names_a_larger_model() {
+        let benchmarks = benchmarks();
+        let prompt = "x".repeat(200_000 * 4 + 8);
+
+        let error = check_context_window("sonnet-4", &prompt, &benchmarks).unwrap_err();
+        assert!(error.starts_with("Prompt too large for model sonnet-4 (200002 > 200000 tokens)"), "{}", error);
+        assert!(error.ends_with("try model gemini-2.0-flash with a larger context (1048576 tokens)"), "{}", error);
+
+        // Ollama prompts are pointed at the Ollama model with the next larger window first
+        let error = check_context_window("codellama:latest", &"x".repeat(20_000 * 4), &benchmarks).unwrap_err();
+        assert!(error.ends_with("try model qwen2.5:latest with a larger context (32768 tokens)"), "{}", error);
+
+        let error = check_context_window("gemini-1.5-pro", &"x".repeat(2_200_000 * 4), &benchmarks).unwrap_err();
+        assert!(error.contains("shorten the prompt"), "{}", error);
+    }
+
+    #[test]
+    fn test_fitting_or_unknown_models_pass() {
+        let benchmarks = benchmarks();
+        let prompt = "x".repeat(300_000 * 4);
+
+        assert!(check_context_window("gemini-2.5-flash", &prompt, &benchmarks).is_ok());
+        assert!(check_context_window("auto", &prompt, &benchmarks).is_ok());
+        assert!(check_context_window("my-custom-model:7b", &prompt, &benchmarks).is_ok());
+        assert!(check_context_window("opus", "short prompt", &benchmarks).is_ok());
+    }
+}
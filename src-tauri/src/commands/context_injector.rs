@@ -558,7 +558,7 @@ pub async fn update_injection_config(
     config: InjectionConfig,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let config_json = serde_json::to_string(&config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
@@ -577,7 +577,7 @@ pub async fn update_injection_config(
 pub async fn get_injection_config(
     db: State<'_, AgentDb>,
 ) -> Result<InjectionConfig, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let result = conn.query_row(
         "SELECT value FROM system_config WHERE key = 'injection_config'",
@@ -93,7 +93,7 @@ pub struct MemoryStats {
 
 /// Initialize cross-model memory tables
 pub async fn init_memory_tables(db: &AgentDb) -> Result<()> {
-    let conn = db.0.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     // Main memory store table
     conn.execute(
@@ -187,7 +187,7 @@ pub async fn store_memory_entry(
     metadata: HashMap<String, String>,
     priority: Option<String>,
 ) -> Result<MemoryEntry, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     let memory_type = serde_json::from_str::<MemoryType>(&format!("\"{}\"", memory_type))
         .map_err(|e| format!("Invalid memory type: {}", e))?;
@@ -243,7 +243,7 @@ pub async fn retrieve_memory_for_model(
     _target_model: String,
     max_tokens: Option<i32>,
 ) -> Result<Vec<MemoryEntry>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     let max_tokens = max_tokens.unwrap_or(50000);
     
@@ -358,7 +358,7 @@ pub async fn create_context_summary(
     
     // Store the summary
     {
-        let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let conn = db.lock_conn();
         conn.execute(
         "INSERT INTO context_summaries (id, session_id, original_model, summary, key_points, token_count, created_at)
          VALUES (?, ?, ?, ?, ?, ?, ?)",
@@ -380,7 +380,7 @@ pub async fn create_context_summary(
 /// Get memory statistics
 #[tauri::command]
 pub async fn get_memory_stats(db: State<'_, AgentDb>) -> Result<MemoryStats, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     let total_entries: i64 = conn.query_row(
         "SELECT COUNT(*) FROM cross_model_memory",
@@ -426,7 +426,7 @@ pub async fn update_memory_relevance(
     memory_id: String,
     relevance_score: f32,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     conn.execute(
         "UPDATE cross_model_memory SET relevance_score = ? WHERE id = ?",
@@ -439,7 +439,7 @@ pub async fn update_memory_relevance(
 /// Garbage collect old/irrelevant memories
 #[tauri::command]
 pub async fn garbage_collect_memory(db: State<'_, AgentDb>) -> Result<i32, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     // Get configuration
     let config: MemoryConfig = conn.query_row(
@@ -475,7 +475,7 @@ pub async fn garbage_collect_memory(db: State<'_, AgentDb>) -> Result<i32, Strin
 /// Get memory configuration
 #[tauri::command]
 pub async fn get_memory_config(db: State<'_, AgentDb>) -> Result<MemoryConfig, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     let config = conn.query_row(
         "SELECT max_memory_mb, max_tokens_per_session, compression_threshold,
@@ -503,7 +503,7 @@ pub async fn update_memory_config(
     db: State<'_, AgentDb>,
     config: MemoryConfig,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     conn.execute(
         "UPDATE memory_config SET 
@@ -529,7 +529,7 @@ pub async fn clear_session_memory(
     db: State<'_, AgentDb>,
     session_id: String,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     conn.execute(
         "DELETE FROM cross_model_memory WHERE session_id = ?",
@@ -552,7 +552,7 @@ pub async fn search_memories(
     session_id: Option<String>,
     limit: Option<i32>,
 ) -> Result<Vec<MemoryEntry>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     let limit = limit.unwrap_or(50);
     
@@ -614,7 +614,7 @@ pub async fn merge_session_memories(
     session_ids: Vec<String>,
     target_session_id: String,
 ) -> Result<i32, String> {
-    let conn = db.0.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let conn = db.lock_conn();
     
     let mut merged_count = 0;
     
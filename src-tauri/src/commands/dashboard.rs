@@ -2,17 +2,62 @@ use anyhow::Result;
 use log::{error, info, warn};
 use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State};
 
 use super::agents::AgentDb;
+use super::settings_diff::Setting;
 use super::ai_usage_tracker::{get_ai_usage_stats, AIUsageStats};
+use crate::analysis::{AdvisoryOptions, AnalysisCoverage, CancellationToken};
+
+pub(crate) const ADVISORY_SETTINGS_KEY: &str = "dependency_advisories";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<AdvisoryOptions>(ADVISORY_SETTINGS_KEY)];
+
+lazy_static::lazy_static! {
+    /// Cancellation handles of running project analyses, by project id
+    static ref RUNNING_ANALYSES: Mutex<HashMap<String, (u64, CancellationToken)>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_ANALYSIS_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Registers a running analysis for `cancel_analysis` and unregisters it when dropped
+struct RunningAnalysis {
+    project_id: String,
+    id: u64,
+}
+
+impl RunningAnalysis {
+    fn register(project_id: &str, token: CancellationToken) -> Self {
+        let id = NEXT_ANALYSIS_ID.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut running) = RUNNING_ANALYSES.lock() {
+            // A newer run for the same project supersedes the old one
+            if let Some((_, previous)) = running.insert(project_id.to_string(), (id, token)) {
+                previous.cancel();
+            }
+        }
+        Self { project_id: project_id.to_string(), id }
+    }
+}
+
+impl Drop for RunningAnalysis {
+    fn drop(&mut self) {
+        if let Ok(mut running) = RUNNING_ANALYSES.lock() {
+            if running.get(&self.project_id).map(|(id, _)| *id) == Some(self.id) {
+                running.remove(&self.project_id);
+            }
+        }
+    }
+}
 
 /// Project Health Metrics
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProjectHealthMetric {
     pub id: Option<i64>,
     pub project_id: String,
-    pub metric_type: String, // 'security', 'dependencies', 'complexity', 'scalability', 'error_rate'
+    pub metric_type: String, // 'security', 'dependencies', 'complexity', 'scalability', 'error_rate', 'test_coverage'
     pub value: f64,          // 0-100 score
     pub timestamp: i64,      // Unix timestamp
     pub details: Option<String>,
@@ -157,6 +202,12 @@ pub fn apply_dashboard_migration(conn: &Connection) -> SqliteResult<()> {
     // Execute the entire migration as a batch to preserve transaction boundaries
     match conn.execute_batch(migration_sql) {
         Ok(_) => {
+            // Columns added after the table first shipped; fails harmlessly when already present
+            let _ = conn.execute("ALTER TABLE ai_usage_events ADD COLUMN cached_tokens INTEGER", []);
+            let _ = conn.execute(
+                "ALTER TABLE ai_usage_events ADD COLUMN estimated BOOLEAN NOT NULL DEFAULT 0",
+                [],
+            );
             info!("Dashboard migration completed successfully");
             Ok(())
         }
@@ -167,9 +218,20 @@ pub fn apply_dashboard_migration(conn: &Connection) -> SqliteResult<()> {
     }
 }
 
+/// Payload of the `dashboard-analysis-progress` event, emitted as each analysis stage starts
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisProgress {
+    pub project_id: String,
+    /// 'health', 'features', 'risks', 'documentation' or 'complete'
+    pub stage: String,
+    pub files_scanned: usize,
+    pub coverage: AnalysisCoverage,
+}
+
 /// Start background dashboard analysis for a project
 #[tauri::command]
 pub async fn dashboard_analyze_project(
+    app: AppHandle,
     db: State<'_, AgentDb>,
     project_id: String,
     project_path: String,
@@ -192,14 +254,49 @@ pub async fn dashboard_analyze_project(
         }
     };
     
+    let advisories = {
+        let conn = db.lock_conn();
+        load_advisory_options(&conn)
+    };
+
     // Create analyzer instance with working path
-    let analyzer = ProjectAnalyzer::new(working_path.clone(), project_id.clone());
+    let token = CancellationToken::new();
+    let _running = RunningAnalysis::register(&project_id, token.clone());
+    let analyzer = ProjectAnalyzer::new(working_path.clone(), project_id.clone())
+        .with_cancellation(token)
+        .with_advisories(advisories)
+        .with_risk_listener({
+            let app = app.clone();
+            std::sync::Arc::new(move |risk: &RiskItem| {
+                // The risks panel fills in live; the final list below is still saved as before
+                if let Err(e) = app.emit("dashboard-risk-found", risk) {
+                    warn!("Failed to emit dashboard-risk-found: {}", e);
+                }
+            })
+        });
+    let cancelled = |analyzer: &ProjectAnalyzer| {
+        info!("Project analysis cancelled for {} after {} files", project_id, analyzer.files_scanned());
+        format!("Project analysis cancelled for {}; partial results were saved", project_id)
+    };
+    // Counting the project up front decides whether it is sampled, before the first stage reads anything
+    let progress = |analyzer: &ProjectAnalyzer, stage: &str| {
+        let progress = AnalysisProgress {
+            project_id: project_id.clone(),
+            stage: stage.to_string(),
+            files_scanned: analyzer.files_scanned(),
+            coverage: analyzer.coverage(),
+        };
+        if let Err(e) = app.emit("dashboard-analysis-progress", &progress) {
+            warn!("Failed to emit dashboard-analysis-progress: {}", e);
+        }
+    };
     
     // Perform health analysis
+    progress(&analyzer, "health");
     match analyzer.analyze_health().await {
         Ok(health_metrics) => {
             for metric in health_metrics {
-                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                let conn = db.lock_conn();
                 conn.execute(
                     "INSERT OR REPLACE INTO project_health 
                      (project_id, metric_type, value, timestamp, details, trend) 
@@ -220,17 +317,23 @@ pub async fn dashboard_analyze_project(
         }
     }
     
+    if analyzer.is_cancelled() {
+        return Ok(cancelled(&analyzer));
+    }
+
     // Perform feature analysis
+    progress(&analyzer, "features");
     match analyzer.scan_features().await {
         Ok(features) => {
             for feature in features {
-                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                let conn = db.lock_conn();
                 conn.execute(
                     "INSERT OR REPLACE INTO feature_registry 
-                     (project_id, name, description, status, independence_score, 
+                     (id, project_id, name, description, status, independence_score, 
                       dependencies, file_paths, complexity_score, created_at, updated_at) 
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                     params![
+                        feature.id,
                         feature.project_id,
                         feature.name,
                         feature.description,
@@ -250,11 +353,16 @@ pub async fn dashboard_analyze_project(
         }
     }
     
+    if analyzer.is_cancelled() {
+        return Ok(cancelled(&analyzer));
+    }
+
     // Perform risk analysis
+    progress(&analyzer, "risks");
     match analyzer.detect_risks().await {
         Ok(risks) => {
             for risk in risks {
-                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                let conn = db.lock_conn();
                 conn.execute(
                     "INSERT OR REPLACE INTO risk_items 
                      (project_id, category, severity, title, description, mitigation, 
@@ -281,11 +389,16 @@ pub async fn dashboard_analyze_project(
         }
     }
     
+    if analyzer.is_cancelled() {
+        return Ok(cancelled(&analyzer));
+    }
+
     // Perform documentation analysis
+    progress(&analyzer, "documentation");
     match analyzer.analyze_documentation().await {
         Ok(docs) => {
             for doc in docs {
-                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                let conn = db.lock_conn();
                 conn.execute(
                     "INSERT OR REPLACE INTO documentation_status 
                      (project_id, doc_type, completion_percentage, total_sections, 
@@ -310,10 +423,77 @@ pub async fn dashboard_analyze_project(
         }
     }
     
-    info!("Project analysis completed successfully for: {}", project_id);
+    if analyzer.is_cancelled() {
+        return Ok(cancelled(&analyzer));
+    }
+
+    progress(&analyzer, "complete");
+    info!(
+        "Project analysis completed successfully for: {} ({} file reads, {} binary files skipped)",
+        project_id,
+        analyzer.files_scanned(),
+        analyzer.files_skipped()
+    );
+    let coverage = analyzer.coverage();
+    if coverage.is_sampled() {
+        return Ok(format!(
+            "Project analysis completed for {} from a sample of {} of {} files ({:.1}% coverage)",
+            project_id, coverage.sampled_files, coverage.total_files, coverage.percent
+        ));
+    }
     Ok(format!("Project analysis completed for {}", project_id))
 }
 
+/// Cancel a running `dashboard_analyze_project`; returns false if none was running
+#[tauri::command]
+pub async fn cancel_analysis(project_id: String) -> Result<bool, String> {
+    let running = RUNNING_ANALYSES.lock()
+        .map_err(|e| format!("Failed to access running analyses: {}", e))?;
+    match running.get(&project_id) {
+        Some((_, token)) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Load how dependency advisories are looked up, falling back to defaults when unset or unreadable
+pub fn load_advisory_options(conn: &Connection) -> AdvisoryOptions {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![ADVISORY_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str::<AdvisoryOptions>(&value).ok())
+    .filter(|options| options.validate().is_ok())
+    .unwrap_or_default()
+}
+
+/// Get the concurrency and offline mode of dependency advisory lookups
+#[tauri::command]
+pub async fn get_advisory_options(db: State<'_, AgentDb>) -> Result<AdvisoryOptions, String> {
+    let conn = db.lock_conn();
+    Ok(load_advisory_options(&conn))
+}
+
+/// Save the concurrency and offline mode of dependency advisory lookups
+#[tauri::command]
+pub async fn set_advisory_options(db: State<'_, AgentDb>, options: AdvisoryOptions) -> Result<(), String> {
+    options.validate()?;
+    let value = serde_json::to_string(&options)
+        .map_err(|e| format!("Failed to serialize advisory options: {}", e))?;
+
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![ADVISORY_SETTINGS_KEY, value],
+    ).map_err(|e| format!("Failed to save advisory options: {}", e))?;
+
+    Ok(())
+}
+
 /// Seed basic dashboard data for a project
 fn seed_default_dashboard_data(conn: &Connection, project_id: &str, project_path: &str) -> Result<(), String> {
     let timestamp = chrono::Utc::now().timestamp();
@@ -473,7 +653,7 @@ pub async fn dashboard_get_summary(
     
     // Check if project exists in database
     let project_exists = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.lock_conn();
         conn.prepare("SELECT 1 FROM projects WHERE id = ?")
             .and_then(|mut stmt| stmt.query_row([&project_id], |_| Ok(())))
             .is_ok()
@@ -492,7 +672,7 @@ pub async fn dashboard_get_summary(
         info!("Using project path: {}", project_path);
         
         // Create project record directly in projects table for dashboard-only projects
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.lock_conn();
         if let Err(e) = conn.execute(
             "INSERT OR IGNORE INTO projects (id, path, name, created_at) VALUES (?1, ?2, ?3, datetime('now'))",
             params![&project_id, &project_path, &project_id]
@@ -505,7 +685,7 @@ pub async fn dashboard_get_summary(
         drop(conn);
         
         // Seed default data
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.lock_conn();
         if let Err(e) = seed_default_dashboard_data(&conn, &project_id, &project_path) {
             warn!("Failed to seed default data for project '{}': {}", project_id, e);
         } else {
@@ -515,7 +695,7 @@ pub async fn dashboard_get_summary(
     }
 
     // Get fresh connection reference for data retrieval
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     // Get health metrics (latest 10)
     let health_metrics = get_health_metrics(&conn, &project_id, Some(10))?;
@@ -566,7 +746,7 @@ pub async fn dashboard_update_health_metric(
     db: State<'_, AgentDb>,
     metric: ProjectHealthMetric,
 ) -> Result<i64, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     let id = conn
         .query_row(
@@ -594,7 +774,7 @@ pub async fn dashboard_update_feature(
     db: State<'_, AgentDb>,
     feature: FeatureItem,
 ) -> Result<i64, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
 
     let id = conn
         .query_row(
@@ -936,7 +1116,7 @@ pub async fn dashboard_get_ai_cost_trends(
     project_id: String,
     days_limit: Option<i64>,
 ) -> Result<Vec<serde_json::Value>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     let time_filter = match days_limit {
         Some(days) => format!("AND timestamp > (strftime('%s', 'now') - {} * 24 * 60 * 60)", days),
@@ -984,7 +1164,7 @@ pub async fn dashboard_get_model_performance(
     project_id: String,
     days_limit: Option<i64>,
 ) -> Result<Vec<serde_json::Value>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     let time_filter = match days_limit {
         Some(days) => format!("AND timestamp > (strftime('%s', 'now') - {} * 24 * 60 * 60)", days),
@@ -1038,7 +1218,7 @@ pub async fn dashboard_get_mcp_analytics(
     project_id: String,
     days_limit: Option<i64>,
 ) -> Result<Vec<serde_json::Value>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     let time_filter = match days_limit {
         Some(days) => format!("AND timestamp > (strftime('%s', 'now') - {} * 24 * 60 * 60)", days),
@@ -1083,4 +1263,216 @@ pub async fn dashboard_get_mcp_analytics(
     }
 
     Ok(result)
-}
\ No newline at end of file
+}
+/// Why a request moved on to the next model in a fallback chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverReason {
+    RateLimited,
+    Unavailable,
+    Timeout,
+    Network,
+    ModelError,
+}
+
+impl FailoverReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailoverReason::RateLimited => "rate_limited",
+            FailoverReason::Unavailable => "unavailable",
+            FailoverReason::Timeout => "timeout",
+            FailoverReason::Network => "network",
+            FailoverReason::ModelError => "model_error",
+        }
+    }
+
+    /// Classify the error message that made the previous model give up
+    pub fn from_error(error: &str) -> Self {
+        let lower = error.to_lowercase();
+        if error.contains("429") || lower.contains("rate limit") || lower.contains("quota") {
+            FailoverReason::RateLimited
+        } else if error.contains("503") || error.contains("502") || lower.contains("unavailable") || lower.contains("overloaded") {
+            FailoverReason::Unavailable
+        } else if lower.contains("timeout") || lower.contains("timed out") {
+            FailoverReason::Timeout
+        } else if lower.contains("network") || lower.contains("connection") {
+            FailoverReason::Network
+        } else {
+            FailoverReason::ModelError
+        }
+    }
+}
+
+/// Longest error message kept with a failover event
+const MAX_FAILOVER_ERROR_CHARS: usize = 500;
+
+/// Record that a request failed over from one model to another
+pub fn record_failover_event(
+    conn: &Connection,
+    from_model: &str,
+    to_model: &str,
+    reason: FailoverReason,
+    error_message: Option<&str>,
+) -> Result<(), String> {
+    let error_message: Option<String> =
+        error_message.map(|message| message.chars().take(MAX_FAILOVER_ERROR_CHARS).collect());
+    conn.execute(
+        "INSERT INTO provider_failover_events (from_model, to_model, reason, error_message)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![from_model, to_model, reason.as_str(), error_message],
+    )
+    .map_err(|e| format!("Failed to record failover event: {}", e))?;
+    Ok(())
+}
+
+/// Failover count for one reason
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FailoverReasonCount {
+    pub reason: String,
+    pub count: i64,
+}
+
+/// How often requests failed over away from a model
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FailoverModelCount {
+    pub model: String,
+    pub count: i64,
+    pub top_reason: String,
+    pub last_failover_at: i64,
+}
+
+/// Failover telemetry over a time range
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailoverStats {
+    pub total_failovers: i64,
+    pub by_reason: Vec<FailoverReasonCount>,
+    /// Models requests most often failed over from, most unreliable first
+    pub top_failed_models: Vec<FailoverModelCount>,
+}
+
+/// Models listed in `FailoverStats::top_failed_models`
+const TOP_FAILED_MODELS: usize = 10;
+
+fn get_failover_stats(conn: &Connection, days_limit: Option<i64>) -> Result<FailoverStats, String> {
+    let since = days_limit
+        .map(|days| chrono::Utc::now().timestamp() - days * 24 * 60 * 60)
+        .unwrap_or(0);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT reason, COUNT(*) AS count FROM provider_failover_events
+             WHERE timestamp >= ?1
+             GROUP BY reason
+             ORDER BY count DESC, reason",
+        )
+        .map_err(|e| e.to_string())?;
+    let by_reason = stmt
+        .query_map(params![since], |row| {
+            Ok(FailoverReasonCount {
+                reason: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<SqliteResult<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT from_model, reason, COUNT(*), MAX(timestamp) FROM provider_failover_events
+             WHERE timestamp >= ?1
+             GROUP BY from_model, reason",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![since], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+        })
+        .and_then(|rows| rows.collect::<SqliteResult<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+
+    // Per model: total count, and the reason with the most events as the top reason
+    let mut models: HashMap<String, (FailoverModelCount, i64)> = HashMap::new();
+    for (model, reason, count, last_at) in rows {
+        let (entry, top_reason_count) = models.entry(model.clone()).or_insert_with(|| {
+            (
+                FailoverModelCount { model, count: 0, top_reason: String::new(), last_failover_at: 0 },
+                0,
+            )
+        });
+        entry.count += count;
+        entry.last_failover_at = entry.last_failover_at.max(last_at);
+        if count > *top_reason_count || (count == *top_reason_count && reason < entry.top_reason) {
+            entry.top_reason = reason;
+            *top_reason_count = count;
+        }
+    }
+    let mut top_failed_models: Vec<FailoverModelCount> = models.into_values().map(|(model, _)| model).collect();
+    top_failed_models.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.model.cmp(&b.model)));
+    top_failed_models.truncate(TOP_FAILED_MODELS);
+
+    Ok(FailoverStats {
+        total_failovers: by_reason.iter().map(|reason| reason.count).sum(),
+        by_reason,
+        top_failed_models,
+    })
+}
+
+/// Get provider failover counts by reason and the models most often failed over from
+#[tauri::command]
+pub async fn dashboard_get_failover_stats(
+    db: State<'_, AgentDb>,
+    days_limit: Option<i64>,
+) -> Result<FailoverStats, String> {
+    let conn = db.lock_conn();
+    get_failover_stats(&conn, days_limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_stats_aggregate_by_reason_and_model() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_dashboard_migration(&conn).unwrap();
+
+        let events = [
+            ("gemini-2.5-pro", "gemini-2.5-flash", "API error (429 Too Many Requests): quota"),
+            ("gemini-2.5-pro", "gemini-2.5-flash", "API error (429 Too Many Requests): quota"),
+            ("gemini-2.5-pro", "gemini-2.0-flash", "API error (503 Service Unavailable)"),
+            ("gemini-2.5-flash", "gemini-2.0-flash", "Network error: connection reset"),
+            ("gemini-2.0-flash", "gemini-2.0-flash-lite", "request timeout"),
+        ];
+        for (from, to, error) in events {
+            record_failover_event(&conn, from, to, FailoverReason::from_error(error), Some(error)).unwrap();
+        }
+        // Outside a one-day range
+        conn.execute(
+            "INSERT INTO provider_failover_events (from_model, to_model, reason, timestamp)
+             VALUES ('gemini-2.0-flash', 'gemini-2.0-flash-lite', 'model_error', 0)",
+            [],
+        )
+        .unwrap();
+
+        let stats = get_failover_stats(&conn, Some(1)).unwrap();
+        assert_eq!(stats.total_failovers, 5);
+        let count = |reason: &str| {
+            stats.by_reason.iter().find(|r| r.reason == reason).map(|r| r.count)
+        };
+        assert_eq!(stats.by_reason[0].reason, "rate_limited");
+        assert_eq!(count("rate_limited"), Some(2));
+        assert_eq!(count("unavailable"), Some(1));
+        assert_eq!(count("network"), Some(1));
+        assert_eq!(count("timeout"), Some(1));
+        assert_eq!(count("model_error"), None);
+
+        let top = &stats.top_failed_models[0];
+        assert_eq!((top.model.as_str(), top.count, top.top_reason.as_str()), ("gemini-2.5-pro", 3, "rate_limited"));
+        assert_eq!(stats.top_failed_models.len(), 3);
+
+        let all_time = get_failover_stats(&conn, None).unwrap();
+        assert_eq!(all_time.total_failovers, 6);
+        assert_eq!(all_time.top_failed_models[1].model, "gemini-2.0-flash");
+        assert_eq!(all_time.top_failed_models[1].count, 2);
+    }
+}
@@ -12,7 +12,7 @@ pub async fn dashboard_seed_data(
     db: State<'_, AgentDb>,
     project_id: String,
 ) -> Result<String, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     let timestamp = Utc::now().timestamp();
     
     // First, ensure the project exists in the projects table
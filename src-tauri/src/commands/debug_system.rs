@@ -3,10 +3,15 @@ use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::{command, State};
 use log::{info, warn, error, debug, trace};
-use rusqlite::params;
+use rusqlite::{params, Connection};
 use uuid::Uuid;
 
 use super::agents::AgentDb;
+use super::settings_diff::Setting;
+
+const DEBUG_RETENTION_SETTINGS_KEY: &str = "debug_retention";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<DebugRetentionConfig>(DEBUG_RETENTION_SETTINGS_KEY)];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LogLevel {
@@ -73,8 +78,12 @@ pub struct PerformanceProfiler {
 
 /// Initialize debug and tracing tables
 pub async fn init_debug_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    create_debug_tables(&conn)
+}
 
+/// Create the debug log, trace and performance tables with their indexes
+pub(crate) fn create_debug_tables(conn: &Connection) -> Result<(), String> {
     // Create debug logs table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS debug_logs (
@@ -142,6 +151,138 @@ pub async fn init_debug_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
     Ok(())
 }
 
+/// How long rows of one debug table are kept; `None` leaves that limit off
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct TableRetention {
+    pub max_age_days: Option<u32>,
+    pub max_rows: Option<u64>,
+}
+
+impl TableRetention {
+    fn validate(&self, table: &str) -> Result<(), String> {
+        if self.max_age_days == Some(0) {
+            return Err(format!("{} max_age_days must be greater than 0", table));
+        }
+        if self.max_rows == Some(0) {
+            return Err(format!("{} max_rows must be greater than 0", table));
+        }
+        Ok(())
+    }
+}
+
+/// Retention limits for the debug tables, enforced by the scheduled maintenance task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DebugRetentionConfig {
+    #[serde(default = "DebugRetentionConfig::default_debug_logs")]
+    pub debug_logs: TableRetention,
+    #[serde(default = "DebugRetentionConfig::default_operation_traces")]
+    pub operation_traces: TableRetention,
+    #[serde(default = "DebugRetentionConfig::default_performance_metrics")]
+    pub performance_metrics: TableRetention,
+}
+
+impl DebugRetentionConfig {
+    fn default_debug_logs() -> TableRetention {
+        TableRetention { max_age_days: Some(14), max_rows: Some(50_000) }
+    }
+
+    fn default_operation_traces() -> TableRetention {
+        TableRetention { max_age_days: Some(14), max_rows: Some(10_000) }
+    }
+
+    fn default_performance_metrics() -> TableRetention {
+        // Kept longer so trends stay visible across releases
+        TableRetention { max_age_days: Some(30), max_rows: Some(100_000) }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.debug_logs.validate("debug_logs")?;
+        self.operation_traces.validate("operation_traces")?;
+        self.performance_metrics.validate("performance_metrics")
+    }
+}
+
+impl Default for DebugRetentionConfig {
+    fn default() -> Self {
+        Self {
+            debug_logs: Self::default_debug_logs(),
+            operation_traces: Self::default_operation_traces(),
+            performance_metrics: Self::default_performance_metrics(),
+        }
+    }
+}
+
+/// Rows removed from each debug table by one retention pass
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DebugRetentionReport {
+    pub debug_logs: u64,
+    pub operation_traces: u64,
+    pub performance_metrics: u64,
+}
+
+impl DebugRetentionReport {
+    pub fn total(&self) -> u64 {
+        self.debug_logs + self.operation_traces + self.performance_metrics
+    }
+}
+
+/// Load the debug retention config, falling back to defaults when unset or unreadable
+pub fn load_debug_retention_config(conn: &Connection) -> DebugRetentionConfig {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![DEBUG_RETENTION_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str::<DebugRetentionConfig>(&value).ok())
+    .filter(|config| config.validate().is_ok())
+    .unwrap_or_default()
+}
+
+/// Delete rows older than the table's max age, then the oldest rows past its max count
+fn prune_table(
+    conn: &Connection,
+    table: &str,
+    timestamp_column: &str,
+    retention: &TableRetention,
+    now: i64,
+) -> Result<u64, String> {
+    let mut deleted = 0;
+    if let Some(days) = retention.max_age_days {
+        deleted += conn.execute(
+            &format!("DELETE FROM {} WHERE {} < ?1", table, timestamp_column),
+            params![now - days as i64 * 24 * 3600],
+        ).map_err(|e| format!("Failed to prune {} by age: {}", table, e))?;
+    }
+    if let Some(max_rows) = retention.max_rows {
+        deleted += conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE id IN (
+                    SELECT id FROM {table} ORDER BY {column} DESC LIMIT -1 OFFSET ?1
+                )",
+                table = table,
+                column = timestamp_column
+            ),
+            params![max_rows as i64],
+        ).map_err(|e| format!("Failed to prune {} by row count: {}", table, e))?;
+    }
+    Ok(deleted as u64)
+}
+
+/// Apply `config` to the debug log, trace and performance tables as of `now` (seconds)
+pub fn enforce_debug_retention(
+    conn: &Connection,
+    config: &DebugRetentionConfig,
+    now: i64,
+) -> Result<DebugRetentionReport, String> {
+    create_debug_tables(conn)?;
+    Ok(DebugRetentionReport {
+        debug_logs: prune_table(conn, "debug_logs", "timestamp", &config.debug_logs, now)?,
+        operation_traces: prune_table(conn, "operation_traces", "started_at", &config.operation_traces, now)?,
+        performance_metrics: prune_table(conn, "performance_metrics", "timestamp", &config.performance_metrics, now)?,
+    })
+}
+
 /// Log a debug entry with full context
 #[command]
 pub async fn log_debug_entry(
@@ -157,7 +298,7 @@ pub async fn log_debug_entry(
     let entry_id = Uuid::new_v4().to_string();
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     conn.execute(
         "INSERT INTO debug_logs 
@@ -198,7 +339,7 @@ pub async fn start_operation_trace(
     let trace_id = Uuid::new_v4().to_string();
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     conn.execute(
         "INSERT INTO operation_traces 
@@ -228,7 +369,7 @@ pub async fn add_trace_step(
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     // Get current steps
     let current_steps: String = conn.query_row(
@@ -269,7 +410,7 @@ pub async fn complete_operation_trace(
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     conn.execute(
         "UPDATE operation_traces SET 
@@ -310,7 +451,7 @@ pub async fn record_performance_metrics(
 ) -> Result<(), String> {
     let id = Uuid::new_v4().to_string();
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     conn.execute(
         "INSERT INTO performance_metrics 
@@ -341,7 +482,7 @@ pub async fn get_debug_logs(
     offset: Option<u32>,
     db: State<'_, AgentDb>,
 ) -> Result<Vec<DebugEntry>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     let mut query = "SELECT id, timestamp, level, category, message, context, call_stack, session_id, operation_id
                      FROM debug_logs".to_string();
@@ -430,7 +571,7 @@ pub async fn get_operation_traces(
     limit: Option<u32>,
     db: State<'_, AgentDb>,
 ) -> Result<Vec<OperationTrace>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     let mut query = "SELECT id, name, started_at, completed_at, status, steps, performance_metrics, error_info
                      FROM operation_traces".to_string();
@@ -494,7 +635,7 @@ pub async fn get_performance_metrics(
     time_range_hours: Option<u32>,
     db: State<'_, AgentDb>,
 ) -> Result<Vec<PerformanceProfiler>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
 
     let mut query = "SELECT id, operation_name, cpu_usage, memory_usage, response_time, throughput, error_rate, timestamp
                      FROM performance_metrics".to_string();
@@ -568,7 +709,7 @@ pub async fn cleanup_old_debug_entries(
     days_to_keep: u32,
     db: State<'_, AgentDb>,
 ) -> Result<u64, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let timestamp_threshold = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -590,4 +731,88 @@ pub async fn cleanup_old_debug_entries(
     info!("Cleaned up {} old debug/performance entries", total_deleted);
     
     Ok(total_deleted as u64)
-}
\ No newline at end of file
+}
+
+/// Get the retention limits for debug logs, traces and performance metrics
+#[command]
+pub async fn get_debug_retention_config(db: State<'_, AgentDb>) -> Result<DebugRetentionConfig, String> {
+    let conn = db.lock_conn();
+    Ok(load_debug_retention_config(&conn))
+}
+
+/// Save the retention limits for debug logs, traces and performance metrics
+#[command]
+pub async fn set_debug_retention_config(
+    config: DebugRetentionConfig,
+    db: State<'_, AgentDb>,
+) -> Result<(), String> {
+    config.validate()?;
+    let value = serde_json::to_string(&config)
+        .map_err(|e| format!("Failed to serialize debug retention config: {}", e))?;
+
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![DEBUG_RETENTION_SETTINGS_KEY, value],
+    ).map_err(|e| format!("Failed to save debug retention config: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 3600;
+
+    fn ids(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(&format!("SELECT id FROM {} ORDER BY id", table)).unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|id| id.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_retention_prunes_by_age_and_count() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_debug_tables(&conn).unwrap();
+        let now = 100 * DAY;
+        for (id, age_days) in [("log-0", 20), ("log-1", 4), ("log-2", 3), ("log-3", 2), ("log-4", 1)] {
+            conn.execute(
+                "INSERT INTO debug_logs (id, timestamp, level, category, message) VALUES (?1, ?2, 'Info', 'test', 'x')",
+                params![id, now - age_days * DAY],
+            ).unwrap();
+        }
+        for (id, age_days) in [("trace-old", 15), ("trace-new", 1)] {
+            conn.execute(
+                "INSERT INTO operation_traces (id, name, started_at, status) VALUES (?1, 'op', ?2, 'Completed')",
+                params![id, now - age_days * DAY],
+            ).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO performance_metrics (id, operation_name, timestamp) VALUES ('perf-old', 'op', ?1)",
+            params![now - 365 * DAY],
+        ).unwrap();
+
+        let config = DebugRetentionConfig {
+            debug_logs: TableRetention { max_age_days: Some(14), max_rows: Some(3) },
+            operation_traces: TableRetention { max_age_days: Some(14), max_rows: None },
+            performance_metrics: TableRetention { max_age_days: None, max_rows: None },
+        };
+        let report = enforce_debug_retention(&conn, &config, now).unwrap();
+
+        assert_eq!(report, DebugRetentionReport { debug_logs: 2, operation_traces: 1, performance_metrics: 0 });
+        assert_eq!(ids(&conn, "debug_logs"), ["log-2", "log-3", "log-4"]);
+        assert_eq!(ids(&conn, "operation_traces"), ["trace-new"]);
+        assert_eq!(ids(&conn, "performance_metrics"), ["perf-old"]);
+        // Already within limits, so a second pass deletes nothing
+        assert_eq!(enforce_debug_retention(&conn, &config, now).unwrap().total(), 0);
+    }
+
+    #[test]
+    fn test_retention_config_rejects_zero_limits() {
+        let mut config = DebugRetentionConfig::default();
+        assert!(config.validate().is_ok());
+        config.operation_traces.max_rows = Some(0);
+        assert!(config.validate().unwrap_err().contains("operation_traces"));
+    }
+}
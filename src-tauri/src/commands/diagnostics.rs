@@ -0,0 +1,313 @@
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::session_event_log::{is_secret_name, mask_secrets};
+
+/// Placeholder left where a secret or secret-bearing value was removed
+const REDACTED: &str = "[REDACTED]";
+
+/// Most recent debug log entries included
+const MAX_DEBUG_LOGS: usize = 200;
+
+/// Most recent operation traces included
+const MAX_TRACES: usize = 50;
+
+/// Longer strings are cut and marked as truncated
+const MAX_STRING_CHARS: usize = 4_000;
+
+/// Error metrics cover this many hours
+const ERROR_METRICS_HOURS: i64 = 24;
+
+/// Environment variables worth reporting; secret ones only say whether they are set
+const REPORTED_ENV_VARS: &[(&str, bool)] = &[
+    ("ANTHROPIC_API_KEY", true),
+    ("GEMINI_API_KEY", true),
+    ("GOOGLE_API_KEY", true),
+    // Proxy URLs can carry credentials
+    ("HTTP_PROXY", true),
+    ("HTTPS_PROXY", true),
+    ("NO_PROXY", false),
+    ("RUST_LOG", false),
+    ("NODE_OPTIONS", false),
+];
+
+fn to_section<T: Serialize>(result: Result<T, String>) -> Value {
+    match result {
+        Ok(value) => serde_json::to_value(value).unwrap_or(Value::Null),
+        Err(e) => json!({ "unavailable": e }),
+    }
+}
+
+fn environment_section() -> Value {
+    let variables: Map<String, Value> = REPORTED_ENV_VARS
+        .iter()
+        .map(|(name, secret)| {
+            let value = match std::env::var(name) {
+                Ok(value) if value.is_empty() => Value::Null,
+                Ok(_) if *secret => Value::String(REDACTED.to_string()),
+                Ok(value) => Value::String(value),
+                Err(_) => Value::Null,
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+
+    json!({
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "family": std::env::consts::FAMILY,
+        "dev_mode": crate::runtime_utils::is_dev_mode(),
+        "variables": variables,
+    })
+}
+
+/// Newest rows of a query, with the table's total so a cut-off list is visible
+fn recent_rows(
+    conn: &Connection,
+    table: &str,
+    sql: &str,
+    limit: usize,
+    to_json: impl Fn(&rusqlite::Row) -> rusqlite::Result<Value>,
+) -> Result<Value, String> {
+    let total: i64 = conn
+        .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count {}: {}", table, e))?;
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("Failed to query {}: {}", table, e))?;
+    let entries = stmt
+        .query_map([limit as i64], |row| to_json(row))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+
+    Ok(json!({
+        "total": total,
+        "included": entries.len(),
+        "truncated": total as usize > entries.len(),
+        "entries": entries,
+    }))
+}
+
+/// A JSON column, kept as text when it does not parse
+fn json_column(row: &rusqlite::Row, index: usize) -> Value {
+    row.get::<_, Option<String>>(index)
+        .ok()
+        .flatten()
+        .map(|text| serde_json::from_str(&text).unwrap_or(Value::String(text)))
+        .unwrap_or(Value::Null)
+}
+
+fn debug_logs_section(conn: &Connection) -> Result<Value, String> {
+    recent_rows(
+        conn,
+        "debug_logs",
+        "SELECT timestamp, level, category, message, context, session_id, operation_id
+         FROM debug_logs ORDER BY timestamp DESC LIMIT ?1",
+        MAX_DEBUG_LOGS,
+        |row| {
+            Ok(json!({
+                "timestamp": row.get::<_, i64>(0)?,
+                "level": row.get::<_, String>(1)?,
+                "category": row.get::<_, String>(2)?,
+                "message": row.get::<_, String>(3)?,
+                "context": json_column(row, 4),
+                "session_id": row.get::<_, Option<String>>(5)?,
+                "operation_id": row.get::<_, Option<String>>(6)?,
+            }))
+        },
+    )
+}
+
+fn traces_section(conn: &Connection) -> Result<Value, String> {
+    recent_rows(
+        conn,
+        "operation_traces",
+        "SELECT name, started_at, completed_at, status, steps, error_info
+         FROM operation_traces ORDER BY started_at DESC LIMIT ?1",
+        MAX_TRACES,
+        |row| {
+            Ok(json!({
+                "name": row.get::<_, String>(0)?,
+                "started_at": row.get::<_, i64>(1)?,
+                "completed_at": row.get::<_, Option<i64>>(2)?,
+                "status": row.get::<_, String>(3)?,
+                "steps": json_column(row, 4),
+                "error_info": row.get::<_, Option<String>>(5)?,
+            }))
+        },
+    )
+}
+
+fn database_section(conn: &Connection) -> Result<Value, String> {
+    let (size_bytes, free_pages) = super::storage::page_stats(conn)?;
+    let tables: i64 = conn
+        .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count tables: {}", e))?;
+    Ok(json!({ "size_bytes": size_bytes, "free_pages": free_pages, "tables": tables }))
+}
+
+fn truncate(text: String) -> String {
+    let length = text.chars().count();
+    if length <= MAX_STRING_CHARS {
+        return text;
+    }
+    let kept: String = text.chars().take(MAX_STRING_CHARS).collect();
+    format!("{}... [truncated {} chars]", kept, length - MAX_STRING_CHARS)
+}
+
+/// Mask secrets in every string and bound its length
+fn sanitize(value: Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(truncate(mask_secrets(&text))),
+        Value::Array(items) => Value::Array(items.into_iter().map(sanitize).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(_) if is_secret_name(&key) => Value::String(REDACTED.to_string()),
+                        value => sanitize(value),
+                    };
+                    (key, value)
+                })
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Assemble the bundle from sections collected by the caller plus those read from the database
+///
+/// `collected` holds the sections that need the app handle (versions, providers,
+/// processes); sources that fail show up as `{"unavailable": reason}` instead of
+/// failing the whole bundle.
+fn assemble_bundle(conn: &Connection, collected: Map<String, Value>) -> Value {
+    let time_cutoff = Utc::now().timestamp() - ERROR_METRICS_HOURS * 3600;
+
+    let mut sections = collected;
+    sections.insert("environment".to_string(), environment_section());
+    sections.insert("debug_logs".to_string(), to_section(debug_logs_section(conn)));
+    sections.insert("operation_traces".to_string(), to_section(traces_section(conn)));
+    sections.insert(
+        "error_metrics".to_string(),
+        to_section(super::error_tracker::compute_error_metrics(conn, time_cutoff)),
+    );
+    sections.insert("database".to_string(), to_section(database_section(conn)));
+
+    let sections = sanitize(Value::Object(sections));
+    let redactions = serde_json::to_string(&sections)
+        .unwrap_or_default()
+        .matches(REDACTED)
+        .count();
+
+    json!({
+        "type": "diagnostics_bundle",
+        "generated_at": Utc::now().to_rfc3339(),
+        "redaction_marker": REDACTED,
+        "redactions": redactions,
+        "limits": {
+            "debug_logs": MAX_DEBUG_LOGS,
+            "operation_traces": MAX_TRACES,
+            "string_chars": MAX_STRING_CHARS,
+            "error_metrics_hours": ERROR_METRICS_HOURS,
+        },
+        "sections": sections,
+    })
+}
+
+/// Collect versions, environment, logs and metrics into one JSON document for bug reports
+///
+/// Every string is passed through the session event log's secret masking and cut
+/// to a bounded length, so the bundle can be shared as is.
+#[tauri::command]
+pub async fn generate_diagnostics_bundle(app: AppHandle) -> Result<String, String> {
+    let mut collected = Map::new();
+    collected.insert(
+        "app".to_string(),
+        json!({
+            "info": super::app_info::get_app_info(),
+            "build": to_section(super::version::get_version_info().await),
+        }),
+    );
+    collected.insert(
+        "claude".to_string(),
+        to_section(super::claude::check_claude_version(app.clone()).await),
+    );
+    collected.insert(
+        "providers".to_string(),
+        to_section(Ok(super::simple_model_validator::detect_available_providers(&app).await)),
+    );
+    collected.insert(
+        "processes".to_string(),
+        to_section(app.state::<crate::process::ProcessRegistryState>().0.get_running_processes()),
+    );
+
+    let db = app.state::<AgentDb>();
+    let conn = db.lock_conn();
+    super::error_tracker::flush_buffered_errors(&conn);
+    let bundle = assemble_bundle(&conn, collected);
+
+    log::info!("Generated diagnostics bundle with {} redaction(s)", bundle["redactions"]);
+    serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialize diagnostics bundle: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "sk-ant-REDACTED";
+
+    #[test]
+    fn test_bundle_has_sections_and_masks_secrets() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::commands::debug_system::create_debug_tables(&conn).unwrap();
+        crate::commands::error_tracker::create_error_tables(&conn).unwrap();
+
+        for i in 0..MAX_DEBUG_LOGS + 5 {
+            conn.execute(
+                "INSERT INTO debug_logs (id, timestamp, level, category, message, context, call_stack)
+                 VALUES (?1, ?2, 'Error', 'api', ?3, ?4, '[]')",
+                rusqlite::params![
+                    format!("log-{}", i),
+                    i as i64,
+                    format!("request failed with key {}", SECRET),
+                    json!({ "api_key": "abcd1234efgh5678", "body": "x".repeat(MAX_STRING_CHARS + 10) }).to_string(),
+                ],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO operation_traces (id, name, started_at, status, steps, performance_metrics)
+             VALUES ('t1', 'execute', 1, 'Failed', '[]', '{}')",
+            [],
+        )
+        .unwrap();
+
+        let mut collected = Map::new();
+        collected.insert("processes".to_string(), json!([{ "task": format!("export TOKEN={}", SECRET) }]));
+        let bundle = assemble_bundle(&conn, collected);
+
+        let sections = bundle["sections"].as_object().unwrap();
+        for section in ["processes", "environment", "debug_logs", "operation_traces", "error_metrics", "database"] {
+            assert!(sections.contains_key(section), "missing section {}", section);
+            assert!(sections[section].get("unavailable").is_none(), "{} unavailable", section);
+        }
+        assert_eq!(sections["debug_logs"]["included"], MAX_DEBUG_LOGS);
+        assert_eq!(sections["debug_logs"]["truncated"], true);
+        assert_eq!(sections["operation_traces"]["entries"][0]["status"], "Failed");
+        assert!(sections["database"]["size_bytes"].as_i64().unwrap() > 0);
+
+        let newest = &sections["debug_logs"]["entries"][0];
+        assert_eq!(newest["message"], "request failed with key [REDACTED]");
+        assert_eq!(newest["context"]["api_key"], REDACTED);
+        assert!(newest["context"]["body"].as_str().unwrap().ends_with("[truncated 10 chars]"));
+
+        let text = bundle.to_string();
+        assert!(!text.contains(SECRET));
+        assert!(!text.contains("abcd1234efgh5678"));
+        assert!(bundle["redactions"].as_u64().unwrap() > 2 * MAX_DEBUG_LOGS as u64);
+    }
+}
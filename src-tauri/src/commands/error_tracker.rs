@@ -1,15 +1,47 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::{command, AppHandle, State, Emitter};
+use tauri::{command, AppHandle, State, Emitter, Manager};
 use log::{info, warn, debug};
 use rusqlite::{params, Connection};
 use uuid::Uuid;
 use regex::Regex;
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 use super::agents::AgentDb;
+use super::settings_diff::Setting;
+
+/// How long repeated records of one error are coalesced before hitting the database
+const ERROR_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Flush early once this many distinct errors are waiting
+const ERROR_BUFFER_MAX_PENDING: usize = 64;
+
+lazy_static::lazy_static! {
+    static ref ERROR_BUFFER: ErrorRecordBuffer = ErrorRecordBuffer::new();
+
+    /// Parts of messages that change between occurrences of one error, in the order they are replaced
+    static ref MESSAGE_VARIABLES: Vec<(Regex, &'static str)> = [
+        (r#"[a-zA-Z][a-zA-Z0-9+.-]*://[^\s'"]+"#, "<url>"),
+        (r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b", "<uuid>"),
+        (r#"\b[a-zA-Z]:\\[^\s'"]*"#, "<path>"),
+        (r#"[\w.~-]*(?:/[^\s'"/:,;()\[\]{}<>]+)+/?"#, "<path>"),
+        (r"\b(?:0x)?[0-9a-fA-F]*[0-9][0-9a-fA-F]*\b", "<n>"),
+        (r"[0-9]+", "<n>"),
+    ]
+    .into_iter()
+    .map(|(pattern, placeholder)| (Regex::new(pattern).unwrap(), placeholder))
+    .collect();
+}
+
+/// Raw messages kept per error for reference
+const MAX_SAMPLE_MESSAGES: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorEntry {
@@ -32,17 +64,24 @@ pub struct ErrorEntry {
     pub session_id: Option<String>,
     pub auto_resolved: bool,
     pub pattern_id: Option<String>,
+    /// Most recent distinct raw messages, since the code only identifies their normalized form
+    #[serde(default)]
+    pub sample_messages: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum ErrorSeverity {
     Low,
     Medium,
     High,
     Critical,
+    /// A stored severity this version doesn't recognise
+    Unknown,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum ErrorCategory {
     SessionManagement,
     ModelIntegration,
@@ -56,7 +95,8 @@ pub enum ErrorCategory {
     Unknown,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum ErrorStatus {
     New,
     InProgress,
@@ -65,6 +105,139 @@ pub enum ErrorStatus {
     WontFix,
     Recurring,
     AutoResolved,
+    /// A stored status this version doesn't recognise
+    Unknown,
+}
+
+impl ErrorSeverity {
+    /// Severities errors are recorded with; `Unknown` only comes from unrecognised stored values
+    pub const ALL: [ErrorSeverity; 4] = [
+        ErrorSeverity::Low,
+        ErrorSeverity::Medium,
+        ErrorSeverity::High,
+        ErrorSeverity::Critical,
+    ];
+
+    /// Canonical string stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorSeverity::Low => "Low",
+            ErrorSeverity::Medium => "Medium",
+            ErrorSeverity::High => "High",
+            ErrorSeverity::Critical => "Critical",
+            ErrorSeverity::Unknown => "Unknown",
+        }
+    }
+}
+
+impl ErrorCategory {
+    pub const ALL: [ErrorCategory; 10] = [
+        ErrorCategory::SessionManagement,
+        ErrorCategory::ModelIntegration,
+        ErrorCategory::FileSystem,
+        ErrorCategory::Network,
+        ErrorCategory::Authentication,
+        ErrorCategory::Database,
+        ErrorCategory::UI,
+        ErrorCategory::Performance,
+        ErrorCategory::Configuration,
+        ErrorCategory::Unknown,
+    ];
+
+    /// Canonical string stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::SessionManagement => "SessionManagement",
+            ErrorCategory::ModelIntegration => "ModelIntegration",
+            ErrorCategory::FileSystem => "FileSystem",
+            ErrorCategory::Network => "Network",
+            ErrorCategory::Authentication => "Authentication",
+            ErrorCategory::Database => "Database",
+            ErrorCategory::UI => "UI",
+            ErrorCategory::Performance => "Performance",
+            ErrorCategory::Configuration => "Configuration",
+            ErrorCategory::Unknown => "Unknown",
+        }
+    }
+}
+
+impl ErrorStatus {
+    /// Statuses errors are recorded with; `Unknown` only comes from unrecognised stored values
+    pub const ALL: [ErrorStatus; 7] = [
+        ErrorStatus::New,
+        ErrorStatus::InProgress,
+        ErrorStatus::Resolved,
+        ErrorStatus::KnownIssue,
+        ErrorStatus::WontFix,
+        ErrorStatus::Recurring,
+        ErrorStatus::AutoResolved,
+    ];
+
+    /// Canonical string stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorStatus::New => "New",
+            ErrorStatus::InProgress => "InProgress",
+            ErrorStatus::Resolved => "Resolved",
+            ErrorStatus::KnownIssue => "KnownIssue",
+            ErrorStatus::WontFix => "WontFix",
+            ErrorStatus::Recurring => "Recurring",
+            ErrorStatus::AutoResolved => "AutoResolved",
+            ErrorStatus::Unknown => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for ErrorSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for ErrorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|severity| severity.as_str() == s)
+            .ok_or_else(|| format!("Unknown error severity: {}", s))
+    }
+}
+
+impl FromStr for ErrorCategory {
+    type Err = String;
+
+    /// Unrecognised categories map to `Unknown`, which is itself a stored category
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::ALL
+            .into_iter()
+            .find(|category| category.as_str() == s)
+            .unwrap_or(ErrorCategory::Unknown))
+    }
+}
+
+impl FromStr for ErrorStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| format!("Unknown error status: {}", s))
+    }
 }
 
 /// Error pattern for automatic detection
@@ -80,6 +253,70 @@ pub struct ErrorPattern {
     pub enabled: bool,
 }
 
+pub(crate) const AUTO_RESOLUTION_SETTINGS_KEY: &str = "error_auto_resolution";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<AutoResolutionConfig>(AUTO_RESOLUTION_SETTINGS_KEY)];
+
+/// Per-category switches for automatic resolution
+///
+/// Categories without an entry stay enabled, so an empty config keeps the
+/// original behaviour of auto-resolving every matched pattern.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutoResolutionConfig {
+    #[serde(default)]
+    pub categories: HashMap<ErrorCategory, bool>,
+}
+
+impl AutoResolutionConfig {
+    pub fn is_enabled(&self, category: &str) -> bool {
+        let category = ErrorCategory::from_str(category).unwrap_or(ErrorCategory::Unknown);
+        self.categories.get(&category).copied().unwrap_or(true)
+    }
+
+    /// Every category with its effective setting
+    fn with_all_categories(mut self) -> Self {
+        for category in ErrorCategory::ALL {
+            self.categories.entry(category).or_insert(true);
+        }
+        self
+    }
+}
+
+fn load_auto_resolution_config(conn: &Connection) -> AutoResolutionConfig {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![AUTO_RESOLUTION_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_auto_resolution_config(conn: &Connection, config: &AutoResolutionConfig) -> Result<(), String> {
+    let value = serde_json::to_string(config)
+        .map_err(|e| format!("Failed to serialize auto-resolution config: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![AUTO_RESOLUTION_SETTINGS_KEY, value],
+    ).map_err(|e| format!("Failed to save auto-resolution config: {}", e))?;
+    Ok(())
+}
+
+/// The matched pattern's strategy, unless auto-resolution is off for the category
+fn permitted_resolution(
+    conn: &Connection,
+    category: &str,
+    pattern_match: &Option<(String, Option<ResolutionStrategy>)>,
+) -> Option<ResolutionStrategy> {
+    let strategy = pattern_match.as_ref()?.1.clone()?;
+    if !load_auto_resolution_config(conn).is_enabled(category) {
+        debug!("Auto-resolution disabled for {} errors", category);
+        return None;
+    }
+    Some(strategy)
+}
+
 /// Resolution strategy for auto-resolution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolutionStrategy {
@@ -103,16 +340,28 @@ pub enum ResolutionType {
 }
 
 /// Error metrics for dashboard
+///
+/// All counts cover errors whose last occurrence falls inside the requested window.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorMetrics {
     pub total_errors: u32,
+    /// Errors currently in `Resolved` or `AutoResolved` status
     pub resolved_errors: u32,
+    /// Errors currently in `AutoResolved` status
     pub auto_resolved_errors: u32,
     pub recurring_errors: u32,
     pub errors_by_category: HashMap<String, u32>,
     pub errors_by_severity: HashMap<String, u32>,
+    /// resolved_errors / total_errors, in percent
     pub resolution_rate: f32,
+    /// Deprecated alias of `auto_resolution_share`
     pub auto_resolution_rate: f32,
+    /// auto_resolved_errors / total_errors, in percent
+    pub auto_resolution_coverage: f32,
+    /// auto_resolved_errors / resolved_errors, in percent: how many resolutions were automatic
+    pub auto_resolution_share: f32,
+    /// Mean seconds from first occurrence to resolution, over resolved errors that
+    /// have not occurred again since they were resolved
     pub mean_time_to_resolution: Option<i64>,
     pub top_errors: Vec<ErrorSummary>,
 }
@@ -145,8 +394,12 @@ pub struct ResolutionProgress {
 
 /// Initialize error tracking tables
 pub async fn init_error_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    create_error_tables(&conn)
+}
 
+/// Create the error tracking schema and seed the default patterns
+pub(crate) fn create_error_tables(conn: &Connection) -> Result<(), String> {
     // Create errors table with enhanced schema
     conn.execute(
         "CREATE TABLE IF NOT EXISTS error_knowledge (
@@ -174,6 +427,8 @@ pub async fn init_error_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
         )",
         [],
     ).map_err(|e| format!("Failed to create error_knowledge table: {}", e))?;
+    // Added after the table first shipped; fails harmlessly when already present
+    let _ = conn.execute("ALTER TABLE error_knowledge ADD COLUMN sample_messages TEXT", []);
 
     // Create indexes for better performance
     conn.execute(
@@ -232,11 +487,84 @@ pub async fn init_error_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
     ).map_err(|e| format!("Failed to create resolution_history table: {}", e))?;
 
     // Insert default error patterns
-    insert_default_patterns(&conn)?;
+    insert_default_patterns(conn)?;
+
+    migrate_normalized_error_codes(conn)?;
 
     Ok(())
 }
 
+/// Name under which `migrate_normalized_error_codes` records that it ran
+const NORMALIZED_CODES_MIGRATION: &str = "normalized_error_codes";
+
+/// Renew codes generated from raw messages, once per database
+///
+/// Codes used to hash the message as-is, so one error recorded with different
+/// ids or paths was split across entries that new occurrences no longer match.
+/// Entries whose messages now share a code are merged; the rest get their new code.
+fn migrate_normalized_error_codes(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS error_tracker_migrations (
+            name TEXT PRIMARY KEY,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create error_tracker_migrations table: {}", e))?;
+    let applied: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM error_tracker_migrations WHERE name = ?1",
+        [NORMALIZED_CODES_MIGRATION],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to check error code migration: {}", e))?;
+    if applied {
+        return Ok(());
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT id, error_code, title, description FROM error_knowledge WHERE error_code LIKE 'ERR-%' ORDER BY occurred_at, rowid")
+        .map_err(|e| format!("Failed to prepare error code query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?)))
+        .map_err(|e| format!("Failed to query errors: {}", e))?;
+    let mut order = Vec::new();
+    let mut groups: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for row in rows {
+        let (id, code, title, description) = row.map_err(|e| format!("Failed to read error: {}", e))?;
+        let renewed = generate_error_code(&description, &title);
+        if !groups.contains_key(&renewed) {
+            order.push(renewed.clone());
+        }
+        groups.entry(renewed).or_default().push((id, code));
+    }
+    drop(stmt);
+
+    let mut merged = 0;
+    let mut renamed = 0;
+    for renewed in order {
+        let entries = groups.remove(&renewed).unwrap_or_default();
+        if entries.len() > 1 {
+            let ids: Vec<String> = entries.into_iter().map(|(id, _)| id).collect();
+            merge_error_entries(conn, &ids)?;
+            merged += ids.len() - 1;
+        } else if let Some((id, code)) = entries.into_iter().next().filter(|(_, code)| code != &renewed) {
+            renamed += conn.execute(
+                "UPDATE error_knowledge SET error_code = ?1 WHERE id = ?2
+                 AND NOT EXISTS (SELECT 1 FROM error_knowledge WHERE error_code = ?1)",
+                params![renewed, id],
+            ).map_err(|e| format!("Failed to renew error code {}: {}", code, e))?;
+        }
+    }
+
+    let applied_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    conn.execute(
+        "INSERT INTO error_tracker_migrations (name, applied_at) VALUES (?1, ?2)",
+        params![NORMALIZED_CODES_MIGRATION, applied_at],
+    ).map_err(|e| format!("Failed to record error code migration: {}", e))?;
+    if merged + renamed > 0 {
+        info!("Renewed {} error code(s) and merged {} split error(s)", renamed, merged);
+    }
+    Ok(())
+}
+
 /// Insert default error patterns for common issues
 fn insert_default_patterns(conn: &Connection) -> Result<(), String> {
     let patterns = vec![
@@ -352,6 +680,73 @@ fn insert_default_patterns(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
+/// Retry schedule for rate-limited API calls, from the `api_quota_exceeded` pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaBackoff {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub max_retries: u32,
+}
+
+impl Default for QuotaBackoff {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(1000),
+            max_delay: Duration::from_millis(60000),
+            max_retries: 5,
+        }
+    }
+}
+
+impl QuotaBackoff {
+    /// How long to wait before retry `attempt`, counted from 1
+    ///
+    /// A `Retry-After` from the server wins; otherwise the initial delay doubles
+    /// with every retry. Either way the wait is capped at `max_delay`.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after
+            .unwrap_or_else(|| {
+                self.initial_delay
+                    .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            })
+            .min(self.max_delay)
+    }
+}
+
+/// The `api_quota_exceeded` pattern's retry parameters
+///
+/// Missing or unreadable parameters take their defaults, and a disabled
+/// pattern means no retries.
+pub fn load_quota_backoff(conn: &Connection) -> QuotaBackoff {
+    let defaults = QuotaBackoff::default();
+    let Ok((resolution, enabled)) = conn.query_row(
+        "SELECT auto_resolution, enabled FROM error_patterns WHERE id = 'api_quota_exceeded'",
+        [],
+        |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, bool>(1)?)),
+    ) else {
+        return defaults;
+    };
+    if !enabled {
+        return QuotaBackoff { max_retries: 0, ..defaults };
+    }
+
+    let parameters = resolution
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .map(|resolution| resolution["parameters"].clone())
+        .unwrap_or_default();
+    let number = |name: &str| {
+        let value = &parameters[name];
+        value.as_str().and_then(|v| v.trim().parse::<u64>().ok()).or_else(|| value.as_u64())
+    };
+    QuotaBackoff {
+        initial_delay: number("initial_delay").map_or(defaults.initial_delay, Duration::from_millis),
+        max_delay: number("max_delay").map_or(defaults.max_delay, Duration::from_millis),
+        max_retries: number("max_retries")
+            .and_then(|n| u32::try_from(n).ok())
+            .unwrap_or(defaults.max_retries),
+    }
+}
+
 /// Track and potentially auto-resolve an error
 #[command]
 pub async fn track_error(
@@ -365,9 +760,7 @@ pub async fn track_error(
     session_id: Option<String>,
     db: State<'_, AgentDb>,
 ) -> Result<String, String> {
-    let (error_code, category, severity, pattern_match, error_id) = {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
-        
+    let (error_code, category, severity, resolution, record) = {
         // Generate error code based on message and component
         let error_code = generate_error_code(&error_message, &component);
         
@@ -375,37 +768,54 @@ pub async fn track_error(
         let category = category.unwrap_or_else(|| detect_category(&error_message));
         let severity = severity.unwrap_or_else(|| assess_severity(&error_message, &category));
         
-        // Check for matching patterns and potential auto-resolution
-        let pattern_match = check_error_patterns(&conn, &error_message, &category)?;
+        // Check for matching patterns and potential auto-resolution, once per burst
+        let (pattern_match, resolution) = if ERROR_BUFFER.is_pending(&error_code) {
+            (None, None)
+        } else {
+            let conn = db.lock_conn();
+            let pattern_match = check_error_patterns(&conn, &error_message, &category)?;
+            let resolution = permitted_resolution(&conn, &category, &pattern_match);
+            (pattern_match, resolution)
+        };
         
-        // Track the error
-        let error_id = track_error_internal(
-            &conn,
-            error_code.clone(),
-            error_message.clone(),
-            component,
-            category.clone(),
-            severity.clone(),
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let record = PendingErrorRecord {
+            id: Uuid::new_v4().to_string(),
+            error_code: error_code.clone(),
+            title: component,
+            description: error_message.clone(),
+            severity: severity.clone(),
+            category: category.clone(),
+            context: context.unwrap_or_default(),
             stack_trace,
-            context.unwrap_or_default(),
             session_id,
-            pattern_match.as_ref().map(|p| p.0.clone()),
-        )?;
+            pattern_id: pattern_match.as_ref().map(|p| p.0.clone()),
+            first_seen: timestamp,
+            last_seen: timestamp,
+            count: 1,
+            samples: vec![error_message.clone()],
+        };
         
-        Ok::<_, String>((error_code, category, severity, pattern_match, error_id))
+        Ok::<_, String>((error_code, category, severity, resolution, record))
     }?;
     
-    // Attempt auto-resolution if pattern matched (outside of lock)
-    if let Some((_pattern_id, resolution)) = pattern_match {
-        if let Some(res_strategy) = resolution {
-            attempt_auto_resolution_async(
-                &app_handle,
-                &db,
-                &error_id,
-                &error_code,
-                res_strategy,
-            ).await?;
+    // Track the error through the write buffer
+    let error_id = buffer_error_record(&app_handle, &db, record)?;
+    
+    // Attempt auto-resolution if pattern matched and the category allows it (outside of lock)
+    if let Some(res_strategy) = resolution {
+        // Resolution updates the row, so it has to exist first
+        {
+            let conn = db.lock_conn();
+            ERROR_BUFFER.flush(&conn)?;
         }
+        attempt_auto_resolution(
+            &app_handle,
+            &db,
+            &error_id,
+            &error_code,
+            res_strategy,
+        ).await?;
     }
     
     // Emit error tracking event
@@ -419,30 +829,96 @@ pub async fn track_error(
     Ok(error_id)
 }
 
-/// Async version of attempt_auto_resolution that doesn't hold connections across await points
-async fn attempt_auto_resolution_async(
+/// Attempt automatic resolution of an error
+async fn attempt_auto_resolution(
     app_handle: &AppHandle,
     db: &State<'_, AgentDb>,
     error_id: &str,
     error_code: &str,
     strategy: ResolutionStrategy,
 ) -> Result<(), String> {
+    let success = run_auto_resolution(&db.0, error_id, &strategy.strategy_type, || {
+        execute_resolution_strategy(app_handle, error_code, &strategy)
+    }).await?;
+    
+    if success {
+        info!("Successfully auto-resolved error: {}", error_code);
+        
+        // Emit resolution event
+        app_handle.emit("error-resolved", serde_json::json!({
+            "error_id": error_id,
+            "error_code": error_code,
+            "auto_resolved": true,
+            "strategy": format!("{:?}", strategy.strategy_type),
+        })).map_err(|e| format!("Failed to emit resolution event: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+/// Run a resolution attempt, locking the database only for the bookkeeping
+/// before and after `resolve` so no guard is ever held across an await point
+async fn run_auto_resolution<F, Fut>(
+    db: &Mutex<Connection>,
+    error_id: &str,
+    strategy_type: &ResolutionType,
+    resolve: F,
+) -> Result<bool, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = bool>,
+{
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
     
     // Record resolution attempt
     let history_id = {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let conn = db.lock().map_err(|e| format!("Database lock error: {}", e))?;
         let history_id = Uuid::new_v4().to_string();
         conn.execute(
             "INSERT INTO resolution_history (id, error_id, strategy_type, started_at)
              VALUES (?, ?, ?, ?)",
-            params![history_id.clone(), error_id, format!("{:?}", strategy.strategy_type), timestamp],
+            params![history_id.clone(), error_id, format!("{:?}", strategy_type), timestamp],
         ).map_err(|e| format!("Failed to record resolution attempt: {}", e))?;
         history_id
     };
     
     // Execute resolution strategy (without holding connection)
-    let success = match strategy.strategy_type {
+    let success = resolve().await;
+    
+    // Update resolution history and error status (acquire lock again)
+    {
+        let conn = db.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let completed_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        
+        conn.execute(
+            "UPDATE resolution_history SET completed_at = ?, success = ? WHERE id = ?",
+            params![completed_at, success, history_id],
+        ).map_err(|e| format!("Failed to update resolution history: {}", e))?;
+        
+        if success {
+            // Mark error as auto-resolved
+            conn.execute(
+                "UPDATE error_knowledge SET 
+                 status = 'AutoResolved',
+                 resolved_at = ?,
+                 auto_resolved = 1,
+                 updated_at = ?
+                 WHERE id = ?",
+                params![completed_at, completed_at, error_id],
+            ).map_err(|e| format!("Failed to mark error as resolved: {}", e))?;
+        }
+    }
+    
+    Ok(success)
+}
+
+/// Dispatch to the implementation of a resolution strategy
+async fn execute_resolution_strategy(
+    app_handle: &AppHandle,
+    error_code: &str,
+    strategy: &ResolutionStrategy,
+) -> bool {
+    match strategy.strategy_type {
         ResolutionType::SessionRecovery => {
             recover_session(app_handle, error_code, &strategy.parameters).await
         }
@@ -467,47 +943,11 @@ async fn attempt_auto_resolution_async(
         ResolutionType::Custom => {
             execute_custom_resolution(app_handle, error_code, &strategy.parameters).await
         }
-    };
-    
-    // Update resolution history and error status (acquire lock again)
-    {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
-        
-        conn.execute(
-            "UPDATE resolution_history SET completed_at = ?, success = ? WHERE id = ?",
-            params![timestamp, success, history_id],
-        ).map_err(|e| format!("Failed to update resolution history: {}", e))?;
-        
-        if success {
-            // Mark error as auto-resolved
-            conn.execute(
-                "UPDATE error_knowledge SET 
-                 status = 'AutoResolved',
-                 resolved_at = ?,
-                 auto_resolved = 1,
-                 updated_at = ?
-                 WHERE id = ?",
-                params![timestamp, timestamp, error_id],
-            ).map_err(|e| format!("Failed to mark error as resolved: {}", e))?;
-            
-            info!("Successfully auto-resolved error: {}", error_code);
-        }
     }
-    
-    // Emit resolution event
-    if success {
-        app_handle.emit("error-resolved", serde_json::json!({
-            "error_id": error_id,
-            "error_code": error_code,
-            "auto_resolved": true,
-        })).map_err(|e| format!("Failed to emit resolution event: {}", e))?;
-    }
-    
-    Ok(())
 }
 
 /// Internal function to track error in database
-fn track_error_internal(
+pub(crate) fn track_error_internal(
     conn: &Connection,
     error_code: String,
     error_message: String,
@@ -520,22 +960,69 @@ fn track_error_internal(
     pattern_id: Option<String>,
 ) -> Result<String, String> {
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-    
+    let record = PendingErrorRecord {
+        id: Uuid::new_v4().to_string(),
+        error_code,
+        title: component,
+        samples: vec![error_message.clone()],
+        description: error_message,
+        severity,
+        category,
+        context,
+        stack_trace,
+        session_id,
+        pattern_id,
+        first_seen: timestamp,
+        last_seen: timestamp,
+        count: 1,
+    };
+    upsert_error_record(conn, &record)
+}
+
+/// An error occurrence (or several coalesced ones) waiting to be written
+#[derive(Debug, Clone)]
+struct PendingErrorRecord {
+    /// Id used if the error is new; existing rows keep theirs
+    id: String,
+    error_code: String,
+    title: String,
+    description: String,
+    severity: String,
+    category: String,
+    context: HashMap<String, String>,
+    stack_trace: Option<String>,
+    session_id: Option<String>,
+    pattern_id: Option<String>,
+    first_seen: i64,
+    last_seen: i64,
+    count: u32,
+    /// Distinct raw messages of the coalesced occurrences
+    samples: Vec<String>,
+}
+
+/// Insert a new error row or add `record.count` occurrences to the existing one
+fn upsert_error_record(conn: &Connection, record: &PendingErrorRecord) -> Result<String, String> {
     // Check if error already exists
     let existing_error = conn.query_row(
-        "SELECT id, occurrences, status FROM error_knowledge WHERE error_code = ?",
-        [&error_code],
+        "SELECT id, occurrences, status, sample_messages FROM error_knowledge WHERE error_code = ?",
+        [&record.error_code],
         |row| {
             Ok((
                 row.get::<_, String>(0)?,
                 row.get::<_, u32>(1)?,
                 row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
             ))
         }
     );
     
     match existing_error {
-        Ok((id, occurrences, status)) => {
+        Ok((id, occurrences, status, samples)) => {
+            let mut samples: Vec<String> = samples
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            merge_samples(&mut samples, record.samples.iter().cloned());
+
             // Update existing error
             let new_status = if status == "Resolved" || status == "AutoResolved" {
                 "Recurring"
@@ -545,61 +1032,225 @@ fn track_error_internal(
             
             conn.execute(
                 "UPDATE error_knowledge SET 
-                 occurrences = occurrences + 1,
+                 occurrences = occurrences + ?,
                  last_occurrence = ?,
                  status = ?,
                  context = ?,
                  stack_trace = COALESCE(?, stack_trace),
                  pattern_id = COALESCE(?, pattern_id),
+                 sample_messages = ?,
                  updated_at = ?
                  WHERE id = ?",
                 params![
-                    timestamp,
+                    record.count,
+                    record.last_seen,
                     new_status,
-                    serde_json::to_string(&context).unwrap_or_default(),
-                    stack_trace,
-                    pattern_id,
-                    timestamp,
+                    serde_json::to_string(&record.context).unwrap_or_default(),
+                    record.stack_trace,
+                    record.pattern_id,
+                    serde_json::to_string(&samples).unwrap_or_default(),
+                    record.last_seen,
                     id
                 ],
             ).map_err(|e| format!("Failed to update error: {}", e))?;
             
-            info!("Updated existing error {} (occurrences: {})", error_code, occurrences + 1);
+            info!("Updated existing error {} (occurrences: {})", record.error_code, occurrences + record.count);
             Ok(id)
         }
         Err(_) => {
             // Create new error entry
-            let id = Uuid::new_v4().to_string();
-            
             conn.execute(
                 "INSERT INTO error_knowledge 
                  (id, error_code, title, description, severity, category, occurred_at, status, 
-                  occurrences, last_occurrence, context, stack_trace, session_id, pattern_id)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                  occurrences, last_occurrence, context, stack_trace, session_id, pattern_id,
+                  sample_messages)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
                 params![
-                    id,
-                    error_code,
-                    component.clone(),
-                    error_message,
-                    severity,
-                    category,
-                    timestamp,
+                    record.id,
+                    record.error_code,
+                    record.title,
+                    record.description,
+                    record.severity,
+                    record.category,
+                    record.first_seen,
                     "New",
-                    1,
-                    timestamp,
-                    serde_json::to_string(&context).unwrap_or_default(),
-                    stack_trace,
-                    session_id,
-                    pattern_id
+                    record.count,
+                    record.last_seen,
+                    serde_json::to_string(&record.context).unwrap_or_default(),
+                    record.stack_trace,
+                    record.session_id,
+                    record.pattern_id,
+                    serde_json::to_string(&record.samples).unwrap_or_default()
                 ],
             ).map_err(|e| format!("Failed to insert error: {}", e))?;
             
-            info!("Recorded new error: {}", error_code);
-            Ok(id)
+            info!("Recorded new error: {}", record.error_code);
+            Ok(record.id.clone())
         }
     }
 }
 
+/// Coalesces rapid error records in memory and writes them to the database in batches,
+/// so an error storm doesn't serialize every caller on the database lock
+struct ErrorRecordBuffer {
+    pending: Mutex<HashMap<String, PendingErrorRecord>>,
+    /// error_code -> id for errors seen since startup, so repeats skip the lookup
+    known_ids: Mutex<HashMap<String, String>>,
+    flush_scheduled: AtomicBool,
+    db_writes: AtomicU64,
+}
+
+impl ErrorRecordBuffer {
+    fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            known_ids: Mutex::new(HashMap::new()),
+            flush_scheduled: AtomicBool::new(false),
+            db_writes: AtomicU64::new(0),
+        }
+    }
+
+    /// Buffer one occurrence and return the error's id, plus whether the buffer
+    /// is full enough that the caller should flush now
+    fn record(&self, db: &AgentDb, mut record: PendingErrorRecord) -> Result<(String, bool), String> {
+        if let Some(id) = self.merge_pending(&record)? {
+            return Ok((id, false));
+        }
+        
+        // Resolve the id without holding the buffer lock: flushes take the db lock first
+        let known_id = self.known_ids.lock()
+            .map_err(|e| format!("Error buffer lock error: {}", e))?
+            .get(&record.error_code)
+            .cloned();
+        let stored_id = match known_id {
+            Some(id) => Some(id),
+            None => {
+                let conn = db.lock_conn();
+                conn.query_row(
+                    "SELECT id FROM error_knowledge WHERE error_code = ?",
+                    [&record.error_code],
+                    |row| row.get::<_, String>(0),
+                ).ok()
+            }
+        };
+        if let Some(id) = stored_id {
+            record.id = id;
+        }
+        
+        let mut pending = self.pending.lock().map_err(|e| format!("Error buffer lock error: {}", e))?;
+        if let Some(existing) = pending.get_mut(&record.error_code) {
+            // Another caller buffered the same error while we looked up the id
+            Self::merge_into(existing, record);
+            return Ok((existing.id.clone(), false));
+        }
+        
+        let id = record.id.clone();
+        if let Ok(mut known) = self.known_ids.lock() {
+            known.insert(record.error_code.clone(), id.clone());
+        }
+        pending.insert(record.error_code.clone(), record);
+        Ok((id, pending.len() >= ERROR_BUFFER_MAX_PENDING))
+    }
+
+    /// Fold `record` into an already pending entry for the same error, if there is one
+    fn merge_pending(&self, record: &PendingErrorRecord) -> Result<Option<String>, String> {
+        let mut pending = self.pending.lock().map_err(|e| format!("Error buffer lock error: {}", e))?;
+        Ok(pending.get_mut(&record.error_code).map(|existing| {
+            Self::merge_into(existing, record.clone());
+            existing.id.clone()
+        }))
+    }
+
+    fn merge_into(existing: &mut PendingErrorRecord, record: PendingErrorRecord) {
+        existing.count += record.count;
+        existing.last_seen = record.last_seen;
+        merge_samples(&mut existing.samples, record.samples);
+        existing.context = record.context;
+        if record.stack_trace.is_some() {
+            existing.stack_trace = record.stack_trace;
+        }
+        if record.pattern_id.is_some() {
+            existing.pattern_id = record.pattern_id;
+        }
+    }
+
+    /// Drop cached ids of errors whose rows were merged away
+    fn forget(&self, error_codes: &[String]) {
+        if let Ok(mut known) = self.known_ids.lock() {
+            for error_code in error_codes {
+                known.remove(error_code);
+            }
+        }
+    }
+
+    fn is_pending(&self, error_code: &str) -> bool {
+        self.pending.lock().map(|p| p.contains_key(error_code)).unwrap_or(false)
+    }
+
+    /// Write every pending record in one transaction
+    fn flush(&self, conn: &Connection) -> Result<usize, String> {
+        let records: Vec<PendingErrorRecord> = {
+            let mut pending = self.pending.lock().map_err(|e| format!("Error buffer lock error: {}", e))?;
+            pending.drain().map(|(_, record)| record).collect()
+        };
+        if records.is_empty() {
+            return Ok(0);
+        }
+        
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| format!("Failed to start error flush transaction: {}", e))?;
+        for record in &records {
+            upsert_error_record(&tx, record)?;
+        }
+        tx.commit().map_err(|e| format!("Failed to commit error flush: {}", e))?;
+        
+        self.db_writes.fetch_add(records.len() as u64, Ordering::Relaxed);
+        debug!("Flushed {} buffered error record(s)", records.len());
+        Ok(records.len())
+    }
+
+    /// Flush after `ERROR_FLUSH_INTERVAL` unless a flush is already scheduled
+    fn schedule_flush(&'static self, app_handle: &AppHandle) {
+        if self.flush_scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(ERROR_FLUSH_INTERVAL).await;
+            // Clear the flag first so records arriving mid-flush schedule another pass
+            self.flush_scheduled.store(false, Ordering::Release);
+            let db = app_handle.state::<AgentDb>();
+            let result = self.flush(&db.lock_conn());
+            if let Err(e) = result {
+                warn!("Failed to flush buffered errors: {}", e);
+            }
+        });
+    }
+}
+
+/// Write buffered error records so reads see every occurrence tracked so far
+pub(crate) fn flush_buffered_errors(conn: &Connection) {
+    if let Err(e) = ERROR_BUFFER.flush(conn) {
+        warn!("Failed to flush buffered errors: {}", e);
+    }
+}
+
+/// Buffer an error record, flushing now if the buffer is full or later otherwise
+fn buffer_error_record(
+    app_handle: &AppHandle,
+    db: &AgentDb,
+    record: PendingErrorRecord,
+) -> Result<String, String> {
+    let (id, flush_now) = ERROR_BUFFER.record(db, record)?;
+    if flush_now {
+        let conn = db.lock_conn();
+        ERROR_BUFFER.flush(&conn)?;
+    } else {
+        ERROR_BUFFER.schedule_flush(app_handle);
+    }
+    Ok(id)
+}
+
 /// Check error patterns for automatic detection
 fn check_error_patterns(
     conn: &Connection,
@@ -635,83 +1286,6 @@ fn check_error_patterns(
     Ok(None)
 }
 
-/// Attempt automatic resolution of an error
-async fn attempt_auto_resolution(
-    app_handle: &AppHandle,
-    conn: &Connection,
-    error_id: &str,
-    error_code: &str,
-    strategy: ResolutionStrategy,
-) -> Result<(), String> {
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-    
-    // Record resolution attempt
-    let history_id = Uuid::new_v4().to_string();
-    conn.execute(
-        "INSERT INTO resolution_history (id, error_id, strategy_type, started_at)
-         VALUES (?, ?, ?, ?)",
-        params![history_id, error_id, format!("{:?}", strategy.strategy_type), timestamp],
-    ).map_err(|e| format!("Failed to record resolution attempt: {}", e))?;
-    
-    // Execute resolution strategy
-    let success = match strategy.strategy_type {
-        ResolutionType::SessionRecovery => {
-            recover_session(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::ApiRetry => {
-            retry_api_call(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::AuthRefresh => {
-            refresh_authentication(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::UiCleanup => {
-            cleanup_ui_elements(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::NetworkRetry => {
-            retry_network_request(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::CacheClear => {
-            clear_cache(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::ConfigReload => {
-            reload_configuration(app_handle, error_code, &strategy.parameters).await
-        }
-        ResolutionType::Custom => {
-            execute_custom_resolution(app_handle, error_code, &strategy.parameters).await
-        }
-    };
-    
-    // Update resolution history
-    conn.execute(
-        "UPDATE resolution_history SET completed_at = ?, success = ? WHERE id = ?",
-        params![timestamp, success, history_id],
-    ).map_err(|e| format!("Failed to update resolution history: {}", e))?;
-    
-    if success {
-        // Mark error as auto-resolved
-        conn.execute(
-            "UPDATE error_knowledge SET 
-             status = 'AutoResolved',
-             resolved_at = ?,
-             auto_resolved = 1,
-             updated_at = ?
-             WHERE id = ?",
-            params![timestamp, timestamp, error_id],
-        ).map_err(|e| format!("Failed to mark error as resolved: {}", e))?;
-        
-        info!("Successfully auto-resolved error: {}", error_code);
-        
-        // Emit resolution event
-        app_handle.emit("error-auto-resolved", serde_json::json!({
-            "error_id": error_id,
-            "error_code": error_code,
-            "strategy": format!("{:?}", strategy.strategy_type),
-        })).map_err(|e| format!("Failed to emit resolution event: {}", e))?;
-    }
-    
-    Ok(())
-}
-
 /// Resolution strategy implementations
 async fn recover_session(app: &AppHandle, error_code: &str, params: &HashMap<String, String>) -> bool {
     debug!("Attempting session recovery for error: {}", error_code);
@@ -772,12 +1346,31 @@ fn generate_error_code(message: &str, component: &str) -> String {
     use std::hash::{Hash, Hasher};
     
     let mut hasher = DefaultHasher::new();
-    message.hash(&mut hasher);
+    normalize_error_message(message).hash(&mut hasher);
     component.hash(&mut hasher);
     
     format!("ERR-{:016X}", hasher.finish())
 }
 
+/// `message` with ids, hashes, numbers, paths and URLs replaced by placeholders,
+/// so occurrences of one error that only differ in those get the same code
+fn normalize_error_message(message: &str) -> String {
+    let normalized = MESSAGE_VARIABLES.iter().fold(message.to_string(), |text, (pattern, placeholder)| {
+        pattern.replace_all(&text, *placeholder).into_owned()
+    });
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Add `messages` to `samples`, keeping the most recent `MAX_SAMPLE_MESSAGES` distinct ones
+fn merge_samples(samples: &mut Vec<String>, messages: impl IntoIterator<Item = String>) {
+    for message in messages {
+        samples.retain(|sample| sample != &message);
+        samples.push(message);
+    }
+    let excess = samples.len().saturating_sub(MAX_SAMPLE_MESSAGES);
+    samples.drain(..excess);
+}
+
 fn detect_category(message: &str) -> String {
     let message_lower = message.to_lowercase();
     
@@ -820,9 +1413,27 @@ fn assess_severity(message: &str, category: &str) -> String {
     }.to_string()
 }
 
+/// Per-category auto-resolution switches, listing every category
+#[command]
+pub async fn get_auto_resolution_config(db: State<'_, AgentDb>) -> Result<AutoResolutionConfig, String> {
+    let conn = db.lock_conn();
+    Ok(load_auto_resolution_config(&conn).with_all_categories())
+}
+
+/// Enable or disable auto-resolution per error category
+#[command]
+pub async fn set_auto_resolution_config(
+    db: State<'_, AgentDb>,
+    config: AutoResolutionConfig,
+) -> Result<(), String> {
+    let conn = db.lock_conn();
+    save_auto_resolution_config(&conn, &config)
+}
+
 /// Record a new error or update existing one (backward compatibility)
 #[command]
 pub async fn record_error(
+    app_handle: AppHandle,
     error_code: String,
     title: String,
     description: String,
@@ -831,64 +1442,85 @@ pub async fn record_error(
     context: HashMap<String, String>,
     db: State<'_, AgentDb>,
 ) -> Result<String, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let record = PendingErrorRecord {
+        id: Uuid::new_v4().to_string(),
+        error_code,
+        title,
+        samples: vec![description.clone()],
+        description,
+        severity,
+        category,
+        context,
+        stack_trace: None,
+        session_id: None,
+        pattern_id: None,
+        first_seen: timestamp,
+        last_seen: timestamp,
+        count: 1,
+    };
+    
+    buffer_error_record(&app_handle, &db, record)
+}
 
-    // Check if error already exists
-    let existing_error = conn.query_row(
-        "SELECT id, occurrences FROM error_knowledge WHERE error_code = ?",
-        [&error_code],
-        |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, u32>(1)?
-            ))
-        }
-    );
-
-    match existing_error {
-        Ok((id, occurrences)) => {
-            // Update existing error
-            conn.execute(
-                "UPDATE error_knowledge SET 
-                 occurrences = occurrences + 1,
-                 last_occurrence = ?,
-                 context = ?
-                 WHERE id = ?",
-                params![timestamp, serde_json::to_string(&context).unwrap_or_default(), id],
-            ).map_err(|e| format!("Failed to update error: {}", e))?;
-
-            info!("Updated existing error {} (occurrences: {})", error_code, occurrences + 1);
-            Ok(id)
-        }
-        Err(_) => {
-            // Create new error entry
-            let id = Uuid::new_v4().to_string();
-            
-            conn.execute(
-                "INSERT INTO error_knowledge 
-                 (id, error_code, title, description, severity, category, occurred_at, status, 
-                  occurrences, last_occurrence, context)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-                params![
-                    id,
-                    error_code,
-                    title,
-                    description,
-                    severity,
-                    category,
-                    timestamp,
-                    "New",
-                    1,
-                    timestamp,
-                    serde_json::to_string(&context).unwrap_or_default()
-                ],
-            ).map_err(|e| format!("Failed to insert error: {}", e))?;
+/// Columns selected by every `ErrorEntry` read path, in `error_entry_from_row` order
+const ERROR_ENTRY_COLUMNS: &str = "id, error_code, title, description, severity, category, occurred_at,
+    resolved_at, status, root_cause, resolution_steps, prevention_strategies,
+    occurrences, last_occurrence, context, stack_trace, session_id,
+    auto_resolved, pattern_id, sample_messages";
+
+/// Parse a text column through the enum's canonical `FromStr`
+///
+/// A value this version doesn't recognise, say one written by a newer version,
+/// is logged and read as `unknown` so the rest of the row still loads.
+fn parse_enum_column<T: FromStr<Err = String>>(row: &rusqlite::Row, idx: usize, unknown: T) -> rusqlite::Result<T> {
+    let stored = row.get::<_, String>(idx)?;
+    Ok(stored.parse().unwrap_or_else(|e: String| {
+        warn!("Reading unrecognised value in error column {}: {}", idx, e);
+        unknown
+    }))
+}
 
-            info!("Recorded new error: {}", error_code);
-            Ok(id)
-        }
-    }
+/// Build an `ErrorEntry` from a row selected with `ERROR_ENTRY_COLUMNS`
+fn error_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<ErrorEntry> {
+    let resolution_steps: Vec<String> = serde_json::from_str(
+        &row.get::<_, String>(10).unwrap_or_default()
+    ).unwrap_or_default();
+
+    let prevention_strategies: Vec<String> = serde_json::from_str(
+        &row.get::<_, String>(11).unwrap_or_default()
+    ).unwrap_or_default();
+
+    let context: HashMap<String, String> = serde_json::from_str(
+        &row.get::<_, String>(14).unwrap_or_default()
+    ).unwrap_or_default();
+
+    let sample_messages: Vec<String> = serde_json::from_str(
+        &row.get::<_, String>(19).unwrap_or_default()
+    ).unwrap_or_default();
+
+    Ok(ErrorEntry {
+        id: row.get(0)?,
+        error_code: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        severity: parse_enum_column(row, 4, ErrorSeverity::Unknown)?,
+        category: parse_enum_column(row, 5, ErrorCategory::Unknown)?,
+        occurred_at: row.get(6)?,
+        resolved_at: row.get(7)?,
+        status: parse_enum_column(row, 8, ErrorStatus::Unknown)?,
+        root_cause: row.get(9)?,
+        resolution_steps,
+        prevention_strategies,
+        occurrences: row.get(12)?,
+        last_occurrence: row.get(13)?,
+        context,
+        stack_trace: row.get(15)?,
+        session_id: row.get(16)?,
+        auto_resolved: row.get(17)?,
+        pattern_id: row.get(18)?,
+        sample_messages,
+    })
 }
 
 /// Get error by ID
@@ -897,73 +1529,17 @@ pub async fn get_error(
     error_id: String,
     db: State<'_, AgentDb>,
 ) -> Result<Option<ErrorEntry>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
+    get_error_entry(&conn, &error_id)
+}
 
+/// Load a single error entry with every stored column
+fn get_error_entry(conn: &Connection, error_id: &str) -> Result<Option<ErrorEntry>, String> {
     let result = conn.query_row(
-        "SELECT id, error_code, title, description, severity, category, occurred_at, 
-                resolved_at, status, root_cause, resolution_steps, prevention_strategies,
-                occurrences, last_occurrence, context
-         FROM error_knowledge WHERE id = ?",
+        &format!("SELECT {} FROM error_knowledge WHERE id = ?", ERROR_ENTRY_COLUMNS),
         [error_id],
-        |row| {
-            let resolution_steps: Vec<String> = serde_json::from_str(
-                &row.get::<_, String>(10).unwrap_or_default()
-            ).unwrap_or_default();
-
-            let prevention_strategies: Vec<String> = serde_json::from_str(
-                &row.get::<_, String>(11).unwrap_or_default()
-            ).unwrap_or_default();
-
-            let context: HashMap<String, String> = serde_json::from_str(
-                &row.get::<_, String>(14).unwrap_or_default()
-            ).unwrap_or_default();
-
-            Ok(ErrorEntry {
-                id: row.get(0)?,
-                error_code: row.get(1)?,
-                title: row.get(2)?,
-                description: row.get(3)?,
-                severity: match row.get::<_, String>(4)?.as_str() {
-                    "Low" => ErrorSeverity::Low,
-                    "Medium" => ErrorSeverity::Medium,
-                    "High" => ErrorSeverity::High,
-                    "Critical" => ErrorSeverity::Critical,
-                    _ => ErrorSeverity::Medium,
-                },
-                category: match row.get::<_, String>(5)?.as_str() {
-                    "SessionManagement" => ErrorCategory::SessionManagement,
-                    "ModelIntegration" => ErrorCategory::ModelIntegration,
-                    "FileSystem" => ErrorCategory::FileSystem,
-                    "Network" => ErrorCategory::Network,
-                    "Authentication" => ErrorCategory::Authentication,
-                    "Database" => ErrorCategory::Database,
-                    "UI" => ErrorCategory::UI,
-                    "Performance" => ErrorCategory::Performance,
-                    "Configuration" => ErrorCategory::Configuration,
-                    _ => ErrorCategory::Unknown,
-                },
-                occurred_at: row.get(6)?,
-                resolved_at: row.get(7)?,
-                status: match row.get::<_, String>(8)?.as_str() {
-                    "New" => ErrorStatus::New,
-                    "InProgress" => ErrorStatus::InProgress,
-                    "Resolved" => ErrorStatus::Resolved,
-                    "KnownIssue" => ErrorStatus::KnownIssue,
-                    "WontFix" => ErrorStatus::WontFix,
-                    _ => ErrorStatus::New,
-                },
-                root_cause: row.get(9)?,
-                resolution_steps,
-                prevention_strategies,
-                occurrences: row.get(12)?,
-                last_occurrence: row.get(13)?,
-                context,
-                stack_trace: None,  // Default to None since not available in this query
-                session_id: None,   // Default to None since not available in this query
-                auto_resolved: false,  // Default to false since not available in this query
-                pattern_id: None,   // Default to None since not available in this query
-            })
-        },
+        error_entry_from_row,
     );
 
     match result {
@@ -981,12 +1557,18 @@ pub async fn list_errors(
     limit: Option<u32>,
     db: State<'_, AgentDb>,
 ) -> Result<Vec<ErrorEntry>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
+    list_error_entries(&conn, status_filter, category_filter, limit)
+}
 
-    let mut query = "SELECT id, error_code, title, description, severity, category, occurred_at, 
-                           resolved_at, status, root_cause, resolution_steps, prevention_strategies,
-                           occurrences, last_occurrence, context
-                    FROM error_knowledge".to_string();
+fn list_error_entries(
+    conn: &Connection,
+    status_filter: Option<String>,
+    category_filter: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<ErrorEntry>, String> {
+    let mut query = format!("SELECT {} FROM error_knowledge", ERROR_ENTRY_COLUMNS);
     let mut params: Vec<String> = Vec::new();
 
     // Build WHERE clause
@@ -1015,66 +1597,8 @@ pub async fn list_errors(
 
     let mut stmt = conn.prepare(&query).map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-    let error_iter = stmt.query_map(rusqlite::params_from_iter(params), |row| {
-        let resolution_steps: Vec<String> = serde_json::from_str(
-            &row.get::<_, String>(10).unwrap_or_default()
-        ).unwrap_or_default();
-
-        let prevention_strategies: Vec<String> = serde_json::from_str(
-            &row.get::<_, String>(11).unwrap_or_default()
-        ).unwrap_or_default();
-
-        let context: HashMap<String, String> = serde_json::from_str(
-            &row.get::<_, String>(14).unwrap_or_default()
-        ).unwrap_or_default();
-
-        Ok(ErrorEntry {
-            id: row.get(0)?,
-            error_code: row.get(1)?,
-            title: row.get(2)?,
-            description: row.get(3)?,
-            severity: match row.get::<_, String>(4)?.as_str() {
-                "Low" => ErrorSeverity::Low,
-                "Medium" => ErrorSeverity::Medium,
-                "High" => ErrorSeverity::High,
-                "Critical" => ErrorSeverity::Critical,
-                _ => ErrorSeverity::Medium,
-            },
-            category: match row.get::<_, String>(5)?.as_str() {
-                "SessionManagement" => ErrorCategory::SessionManagement,
-                "ModelIntegration" => ErrorCategory::ModelIntegration,
-                "FileSystem" => ErrorCategory::FileSystem,
-                "Network" => ErrorCategory::Network,
-                "Authentication" => ErrorCategory::Authentication,
-                "Database" => ErrorCategory::Database,
-                "UI" => ErrorCategory::UI,
-                "Performance" => ErrorCategory::Performance,
-                "Configuration" => ErrorCategory::Configuration,
-                _ => ErrorCategory::Unknown,
-            },
-            occurred_at: row.get(6)?,
-            resolved_at: row.get(7)?,
-            status: match row.get::<_, String>(8)?
-                .as_str() {
-                "New" => ErrorStatus::New,
-                "InProgress" => ErrorStatus::InProgress,
-                "Resolved" => ErrorStatus::Resolved,
-                "KnownIssue" => ErrorStatus::KnownIssue,
-                "WontFix" => ErrorStatus::WontFix,
-                _ => ErrorStatus::New,
-            },
-            root_cause: row.get(9)?,
-            resolution_steps,
-            prevention_strategies,
-            occurrences: row.get(12)?,
-            last_occurrence: row.get(13)?,
-            context,
-            stack_trace: None,  // Default to None since not available in this query
-            session_id: None,   // Default to None since not available in this query
-            auto_resolved: false,  // Default to false since not available in this query
-            pattern_id: None,   // Default to None since not available in this query
-        })
-    }).map_err(|e| format!("Failed to query errors: {}", e))?;
+    let error_iter = stmt.query_map(rusqlite::params_from_iter(params), error_entry_from_row)
+        .map_err(|e| format!("Failed to query errors: {}", e))?;
 
     let mut errors = Vec::new();
     for error_result in error_iter {
@@ -1097,7 +1621,8 @@ pub async fn resolve_error(
     prevention_strategies: Vec<String>,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
     let resolved_at = if status == "Resolved" { Some(timestamp) } else { None };
@@ -1124,16 +1649,170 @@ pub async fn resolve_error(
     Ok(())
 }
 
+/// Groups of errors that only differ in ids, numbers or paths, oldest first
+#[command]
+pub async fn find_duplicate_errors(db: State<'_, AgentDb>) -> Result<Vec<Vec<String>>, String> {
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
+    duplicate_error_groups(&conn)
+}
+
+fn duplicate_error_groups(conn: &Connection) -> Result<Vec<Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, title, description FROM error_knowledge ORDER BY occurred_at, id")
+        .map_err(|e| format!("Failed to prepare duplicate query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| format!("Failed to query errors: {}", e))?;
+
+    let mut order = Vec::new();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (id, title, description) = row.map_err(|e| format!("Failed to read error: {}", e))?;
+        let code = generate_error_code(&description, &title);
+        if !groups.contains_key(&code) {
+            order.push(code.clone());
+        }
+        groups.entry(code).or_default().push(id);
+    }
+    Ok(order
+        .into_iter()
+        .filter_map(|code| groups.remove(&code))
+        .filter(|ids| ids.len() > 1)
+        .collect())
+}
+
+/// Consolidate errors split across several entries into the oldest of them
+#[command]
+pub async fn merge_errors(
+    error_ids: Vec<String>,
+    db: State<'_, AgentDb>,
+) -> Result<ErrorEntry, String> {
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
+    merge_error_entries(&conn, &error_ids)
+}
+
+/// Fold every entry in `error_ids` into the one that occurred first
+///
+/// Occurrences add up, the first and last occurrence span all entries and the
+/// status follows the entry that occurred most recently. Raw messages are kept
+/// as samples and resolution history moves to the remaining entry. A generated
+/// code is renewed from the normalized message, so later occurrences land on
+/// the merged entry.
+fn merge_error_entries(conn: &Connection, error_ids: &[String]) -> Result<ErrorEntry, String> {
+    let mut entries = Vec::new();
+    for id in error_ids {
+        if entries.iter().any(|entry: &ErrorEntry| &entry.id == id) {
+            continue;
+        }
+        let entry = get_error_entry(conn, id)?.ok_or_else(|| format!("Error not found: {}", id))?;
+        entries.push(entry);
+    }
+    if entries.len() < 2 {
+        return Err("At least two different errors are needed to merge".to_string());
+    }
+    entries.sort_by_key(|entry| entry.occurred_at);
+
+    let mut merged = entries[0].clone();
+    let latest = entries.iter().max_by_key(|entry| entry.last_occurrence).unwrap().clone();
+    let others = &entries[1..];
+    merged.occurrences = entries.iter().map(|entry| entry.occurrences).sum();
+    merged.last_occurrence = latest.last_occurrence;
+    merged.status = latest.status.clone();
+    merged.resolved_at = latest.resolved_at;
+    merged.auto_resolved = latest.auto_resolved;
+    for other in others {
+        merged.root_cause = merged.root_cause.or_else(|| other.root_cause.clone());
+        if merged.resolution_steps.is_empty() {
+            merged.resolution_steps = other.resolution_steps.clone();
+        }
+        if merged.prevention_strategies.is_empty() {
+            merged.prevention_strategies = other.prevention_strategies.clone();
+        }
+        merged.stack_trace = merged.stack_trace.or_else(|| other.stack_trace.clone());
+        merged.pattern_id = merged.pattern_id.or_else(|| other.pattern_id.clone());
+    }
+    let mut samples = Vec::new();
+    for entry in &entries {
+        let messages = if entry.sample_messages.is_empty() {
+            vec![entry.description.clone()]
+        } else {
+            entry.sample_messages.clone()
+        };
+        merge_samples(&mut samples, messages);
+    }
+    merged.sample_messages = samples;
+
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| format!("Failed to start merge transaction: {}", e))?;
+    for other in others {
+        tx.execute(
+            "UPDATE resolution_history SET error_id = ?1 WHERE error_id = ?2",
+            params![merged.id, other.id],
+        ).map_err(|e| format!("Failed to move resolution history: {}", e))?;
+        tx.execute("DELETE FROM error_knowledge WHERE id = ?", [&other.id])
+            .map_err(|e| format!("Failed to remove merged error: {}", e))?;
+    }
+    if merged.error_code.starts_with("ERR-") {
+        let code = generate_error_code(&merged.description, &merged.title);
+        let taken: bool = tx.query_row(
+            "SELECT COUNT(*) > 0 FROM error_knowledge WHERE error_code = ?1 AND id != ?2",
+            params![code, merged.id],
+            |row| row.get(0),
+        ).map_err(|e| format!("Failed to check error code: {}", e))?;
+        if !taken {
+            merged.error_code = code;
+        }
+    }
+    tx.execute(
+        "UPDATE error_knowledge SET
+         error_code = ?, occurrences = ?, last_occurrence = ?, status = ?, resolved_at = ?,
+         auto_resolved = ?, root_cause = ?, resolution_steps = ?, prevention_strategies = ?,
+         stack_trace = ?, pattern_id = ?, sample_messages = ?, updated_at = ?
+         WHERE id = ?",
+        params![
+            merged.error_code,
+            merged.occurrences,
+            merged.last_occurrence,
+            merged.status.as_str(),
+            merged.resolved_at,
+            merged.auto_resolved,
+            merged.root_cause,
+            serde_json::to_string(&merged.resolution_steps).unwrap_or_default(),
+            serde_json::to_string(&merged.prevention_strategies).unwrap_or_default(),
+            merged.stack_trace,
+            merged.pattern_id,
+            serde_json::to_string(&merged.sample_messages).unwrap_or_default(),
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+            merged.id
+        ],
+    ).map_err(|e| format!("Failed to update merged error: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit merge: {}", e))?;
+
+    let mut stale_codes: Vec<String> = others.iter().map(|other| other.error_code.clone()).collect();
+    stale_codes.push(entries[0].error_code.clone());
+    ERROR_BUFFER.forget(&stale_codes);
+    info!("Merged {} error(s) into {} ({} occurrences)", others.len(), merged.id, merged.occurrences);
+    Ok(merged)
+}
+
 /// Get comprehensive error metrics for dashboard
 #[command]
 pub async fn get_error_metrics(
     time_range_hours: Option<i32>,
     db: State<'_, AgentDb>,
 ) -> Result<ErrorMetrics, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
     let hours = time_range_hours.unwrap_or(24);
     let time_cutoff = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64 - (hours as i64 * 3600);
     
+    compute_error_metrics(&conn, time_cutoff)
+}
+
+/// Compute dashboard metrics for errors last seen after `time_cutoff`
+pub(crate) fn compute_error_metrics(conn: &Connection, time_cutoff: i64) -> Result<ErrorMetrics, String> {
     // Get total errors
     let total_errors: u32 = conn.query_row(
         "SELECT COUNT(*) FROM error_knowledge WHERE last_occurrence > ?",
@@ -1148,9 +1827,9 @@ pub async fn get_error_metrics(
         |row| row.get(0),
     ).unwrap_or(0);
     
-    // Get auto-resolved errors
+    // Get auto-resolved errors (ones that recurred since are counted as recurring instead)
     let auto_resolved_errors: u32 = conn.query_row(
-        "SELECT COUNT(*) FROM error_knowledge WHERE auto_resolved = 1 AND last_occurrence > ?",
+        "SELECT COUNT(*) FROM error_knowledge WHERE status = 'AutoResolved' AND last_occurrence > ?",
         [time_cutoff],
         |row| row.get(0),
     ).unwrap_or(0);
@@ -1201,19 +1880,29 @@ pub async fn get_error_metrics(
         0.0
     };
     
-    let auto_resolution_rate = if resolved_errors > 0 {
+    let auto_resolution_coverage = if total_errors > 0 {
+        (auto_resolved_errors as f32 / total_errors as f32) * 100.0
+    } else {
+        0.0
+    };
+    
+    let auto_resolution_share = if resolved_errors > 0 {
         (auto_resolved_errors as f32 / resolved_errors as f32) * 100.0
     } else {
         0.0
     };
     
-    // Calculate mean time to resolution
+    // Calculate mean time to resolution, skipping errors that came back after being resolved
     let mean_time_to_resolution: Option<i64> = conn.query_row(
         "SELECT AVG(resolved_at - occurred_at) FROM error_knowledge 
-         WHERE resolved_at IS NOT NULL AND last_occurrence > ?",
+         WHERE status IN ('Resolved', 'AutoResolved')
+           AND resolved_at IS NOT NULL
+           AND last_occurrence <= resolved_at
+           AND last_occurrence > ?",
         [time_cutoff],
-        |row| row.get(0),
-    ).ok();
+        |row| row.get::<_, Option<f64>>(0),
+    ).map_err(|e| format!("Failed to compute mean time to resolution: {}", e))?
+        .map(|avg| avg.round() as i64);
     
     // Get top errors
     let mut top_errors = Vec::new();
@@ -1229,37 +1918,11 @@ pub async fn get_error_metrics(
         Ok(ErrorSummary {
             error_code: row.get(0)?,
             title: row.get(1)?,
-            category: match row.get::<_, String>(2)?.as_str() {
-                "SessionManagement" => ErrorCategory::SessionManagement,
-                "ModelIntegration" => ErrorCategory::ModelIntegration,
-                "FileSystem" => ErrorCategory::FileSystem,
-                "Network" => ErrorCategory::Network,
-                "Authentication" => ErrorCategory::Authentication,
-                "Database" => ErrorCategory::Database,
-                "UI" => ErrorCategory::UI,
-                "Performance" => ErrorCategory::Performance,
-                "Configuration" => ErrorCategory::Configuration,
-                _ => ErrorCategory::Unknown,
-            },
-            severity: match row.get::<_, String>(3)?.as_str() {
-                "Low" => ErrorSeverity::Low,
-                "Medium" => ErrorSeverity::Medium,
-                "High" => ErrorSeverity::High,
-                "Critical" => ErrorSeverity::Critical,
-                _ => ErrorSeverity::Medium,
-            },
+            category: parse_enum_column(row, 2, ErrorCategory::Unknown)?,
+            severity: parse_enum_column(row, 3, ErrorSeverity::Unknown)?,
             occurrences: row.get(4)?,
             last_occurrence: row.get(5)?,
-            status: match row.get::<_, String>(6)?.as_str() {
-                "New" => ErrorStatus::New,
-                "InProgress" => ErrorStatus::InProgress,
-                "Resolved" => ErrorStatus::Resolved,
-                "KnownIssue" => ErrorStatus::KnownIssue,
-                "WontFix" => ErrorStatus::WontFix,
-                "Recurring" => ErrorStatus::Recurring,
-                "AutoResolved" => ErrorStatus::AutoResolved,
-                _ => ErrorStatus::New,
-            },
+            status: parse_enum_column(row, 6, ErrorStatus::Unknown)?,
         })
     }).map_err(|e| format!("Failed to query top errors: {}", e))?;
     
@@ -1277,7 +1940,9 @@ pub async fn get_error_metrics(
         errors_by_category,
         errors_by_severity,
         resolution_rate,
-        auto_resolution_rate,
+        auto_resolution_rate: auto_resolution_share,
+        auto_resolution_coverage,
+        auto_resolution_share,
         mean_time_to_resolution,
         top_errors,
     })
@@ -1294,13 +1959,21 @@ pub async fn search_errors(
     limit: Option<u32>,
     db: State<'_, AgentDb>,
 ) -> Result<Vec<ErrorEntry>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
-    
-    let mut query = "SELECT id, error_code, title, description, severity, category, occurred_at, 
-                           resolved_at, status, root_cause, resolution_steps, prevention_strategies,
-                           occurrences, last_occurrence, context, stack_trace, session_id, 
-                           auto_resolved, pattern_id
-                    FROM error_knowledge WHERE 1=1".to_string();
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
+    search_error_entries(&conn, category, severity, status, search_text, session_id, limit)
+}
+
+fn search_error_entries(
+    conn: &Connection,
+    category: Option<String>,
+    severity: Option<String>,
+    status: Option<String>,
+    search_text: Option<String>,
+    session_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<ErrorEntry>, String> {
+    let mut query = format!("SELECT {} FROM error_knowledge WHERE 1=1", ERROR_ENTRY_COLUMNS);
     
     let mut params: Vec<String> = Vec::new();
     
@@ -1340,67 +2013,8 @@ pub async fn search_errors(
     
     let mut stmt = conn.prepare(&query).map_err(|e| format!("Failed to prepare search query: {}", e))?;
     
-    let error_iter = stmt.query_map(rusqlite::params_from_iter(params), |row| {
-        let resolution_steps: Vec<String> = serde_json::from_str(
-            &row.get::<_, String>(10).unwrap_or_default()
-        ).unwrap_or_default();
-        
-        let prevention_strategies: Vec<String> = serde_json::from_str(
-            &row.get::<_, String>(11).unwrap_or_default()
-        ).unwrap_or_default();
-        
-        let context: HashMap<String, String> = serde_json::from_str(
-            &row.get::<_, String>(14).unwrap_or_default()
-        ).unwrap_or_default();
-        
-        Ok(ErrorEntry {
-            id: row.get(0)?,
-            error_code: row.get(1)?,
-            title: row.get(2)?,
-            description: row.get(3)?,
-            severity: match row.get::<_, String>(4)?.as_str() {
-                "Low" => ErrorSeverity::Low,
-                "Medium" => ErrorSeverity::Medium,
-                "High" => ErrorSeverity::High,
-                "Critical" => ErrorSeverity::Critical,
-                _ => ErrorSeverity::Medium,
-            },
-            category: match row.get::<_, String>(5)?.as_str() {
-                "SessionManagement" => ErrorCategory::SessionManagement,
-                "ModelIntegration" => ErrorCategory::ModelIntegration,
-                "FileSystem" => ErrorCategory::FileSystem,
-                "Network" => ErrorCategory::Network,
-                "Authentication" => ErrorCategory::Authentication,
-                "Database" => ErrorCategory::Database,
-                "UI" => ErrorCategory::UI,
-                "Performance" => ErrorCategory::Performance,
-                "Configuration" => ErrorCategory::Configuration,
-                _ => ErrorCategory::Unknown,
-            },
-            occurred_at: row.get(6)?,
-            resolved_at: row.get(7)?,
-            status: match row.get::<_, String>(8)?.as_str() {
-                "New" => ErrorStatus::New,
-                "InProgress" => ErrorStatus::InProgress,
-                "Resolved" => ErrorStatus::Resolved,
-                "KnownIssue" => ErrorStatus::KnownIssue,
-                "WontFix" => ErrorStatus::WontFix,
-                "Recurring" => ErrorStatus::Recurring,
-                "AutoResolved" => ErrorStatus::AutoResolved,
-                _ => ErrorStatus::New,
-            },
-            root_cause: row.get(9)?,
-            resolution_steps,
-            prevention_strategies,
-            occurrences: row.get(12)?,
-            last_occurrence: row.get(13)?,
-            context,
-            stack_trace: row.get(15)?,
-            session_id: row.get(16)?,
-            auto_resolved: row.get(17)?,
-            pattern_id: row.get(18)?,
-        })
-    }).map_err(|e| format!("Failed to query errors: {}", e))?;
+    let error_iter = stmt.query_map(rusqlite::params_from_iter(params), error_entry_from_row)
+        .map_err(|e| format!("Failed to query errors: {}", e))?;
     
     let mut errors = Vec::new();
     for error_result in error_iter {
@@ -1418,7 +2032,8 @@ pub async fn search_errors(
 pub async fn get_error_stats(
     db: State<'_, AgentDb>,
 ) -> Result<HashMap<String, serde_json::Value>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    flush_buffered_errors(&conn);
 
     let mut stats = HashMap::new();
 
@@ -1484,4 +2099,493 @@ pub async fn get_error_stats(
     stats.insert("most_frequent".to_string(), serde_json::Value::Array(frequent_errors));
 
     Ok(stats)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Mutex<Connection> {
+        let conn = Connection::open_in_memory().unwrap();
+        create_error_tables(&conn).unwrap();
+        Mutex::new(conn)
+    }
+
+    fn insert_test_error(conn: &Connection, error_code: &str) -> String {
+        track_error_internal(
+            conn,
+            error_code.to_string(),
+            "Request timed out".to_string(),
+            "network".to_string(),
+            "Network".to_string(),
+            "Medium".to_string(),
+            None,
+            HashMap::new(),
+            None,
+            None,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_quota_backoff_follows_the_pattern() {
+        let conn = test_db().into_inner().unwrap();
+        let backoff = load_quota_backoff(&conn);
+        assert_eq!(backoff, QuotaBackoff::default());
+
+        let secs = Duration::from_secs;
+        let delays: Vec<_> = (1..=7).map(|attempt| backoff.delay(attempt, None)).collect();
+        assert_eq!(delays, [secs(1), secs(2), secs(4), secs(8), secs(16), secs(32), secs(60)]);
+        assert_eq!(backoff.delay(1, Some(secs(7))), secs(7));
+        assert_eq!(backoff.delay(1, Some(secs(600))), secs(60));
+
+        conn.execute(
+            "UPDATE error_patterns SET auto_resolution = ?1 WHERE id = 'api_quota_exceeded'",
+            params![r#"{"strategy_type":"ApiRetry","action":"exponential_backoff","parameters":{"initial_delay":"250","max_delay":"2000","max_retries":"2"}}"#],
+        ).unwrap();
+        assert_eq!(
+            load_quota_backoff(&conn),
+            QuotaBackoff { initial_delay: Duration::from_millis(250), max_delay: secs(2), max_retries: 2 }
+        );
+
+        conn.execute("UPDATE error_patterns SET enabled = 0 WHERE id = 'api_quota_exceeded'", []).unwrap();
+        assert_eq!(load_quota_backoff(&conn).max_retries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_resolution_runs_without_holding_db_lock() {
+        let db = test_db();
+        let error_id = insert_test_error(&db.lock().unwrap(), "ERR-TIMEOUT");
+        
+        // The strategy only succeeds if it can take the lock itself
+        let success = run_auto_resolution(&db, &error_id, &ResolutionType::NetworkRetry, || async {
+            db.try_lock().is_ok()
+        }).await.unwrap();
+        assert!(success);
+        
+        let conn = db.lock().unwrap();
+        let (status, auto_resolved): (String, bool) = conn.query_row(
+            "SELECT status, auto_resolved FROM error_knowledge WHERE id = ?",
+            [&error_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(status, "AutoResolved");
+        assert!(auto_resolved);
+        
+        let completed: Option<i64> = conn.query_row(
+            "SELECT completed_at FROM resolution_history WHERE error_id = ?",
+            [&error_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert!(completed.is_some());
+    }
+
+    fn insert_raw_error(
+        conn: &Connection,
+        id: &str,
+        status: &str,
+        occurred_at: i64,
+        resolved_at: Option<i64>,
+        last_occurrence: i64,
+        auto_resolved: bool,
+    ) {
+        conn.execute(
+            "INSERT INTO error_knowledge
+             (id, error_code, title, description, severity, category, occurred_at, resolved_at,
+              status, occurrences, last_occurrence, auto_resolved)
+             VALUES (?1, ?2, 'title', 'description', 'High', 'Network', ?3, ?4, ?5, 1, ?6, ?7)",
+            params![id, format!("ERR-{}", id), occurred_at, resolved_at, status, last_occurrence, auto_resolved],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_get_error_returns_stack_trace_and_session() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        let error_id = track_error_internal(
+            &conn,
+            "ERR-STACK".to_string(),
+            "Something broke".to_string(),
+            "test".to_string(),
+            "FileSystem".to_string(),
+            "High".to_string(),
+            Some("at main.rs:42".to_string()),
+            HashMap::new(),
+            Some("session-1".to_string()),
+            None,
+        ).unwrap();
+        
+        let entry = get_error_entry(&conn, &error_id).unwrap().unwrap();
+        assert_eq!(entry.stack_trace.as_deref(), Some("at main.rs:42"));
+        assert_eq!(entry.session_id.as_deref(), Some("session-1"));
+        assert!(!entry.auto_resolved);
+        assert!(get_error_entry(&conn, "missing").unwrap().is_none());
+    }
+
+    fn storm_record(error_code: &str) -> PendingErrorRecord {
+        PendingErrorRecord {
+            id: Uuid::new_v4().to_string(),
+            error_code: error_code.to_string(),
+            title: "network".to_string(),
+            description: "connection reset".to_string(),
+            severity: "Medium".to_string(),
+            category: "Network".to_string(),
+            context: HashMap::new(),
+            stack_trace: None,
+            session_id: None,
+            pattern_id: None,
+            first_seen: 1000,
+            last_seen: 1000,
+            count: 1,
+            samples: vec!["connection reset".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_error_storm_is_coalesced_into_one_write() {
+        let db = AgentDb(test_db());
+        let buffer = ErrorRecordBuffer::new();
+        
+        let mut ids = Vec::new();
+        for _ in 0..100 {
+            let (id, flush_now) = buffer.record(&db, storm_record("ERR-STORM")).unwrap();
+            assert!(!flush_now);
+            ids.push(id);
+        }
+        assert!(ids.iter().all(|id| id == &ids[0]));
+        
+        let conn = db.lock_conn();
+        assert_eq!(buffer.flush(&conn).unwrap(), 1);
+        assert_eq!(buffer.db_writes.load(Ordering::Relaxed), 1);
+        let (id, occurrences): (String, u32) = conn.query_row(
+            "SELECT id, occurrences FROM error_knowledge WHERE error_code = 'ERR-STORM'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(id, ids[0]);
+        assert_eq!(occurrences, 100);
+        drop(conn);
+        
+        // A later burst lands on the same row
+        let (later_id, _) = buffer.record(&db, storm_record("ERR-STORM")).unwrap();
+        assert_eq!(later_id, ids[0]);
+        let conn = db.lock_conn();
+        buffer.flush(&conn).unwrap();
+        let entry = get_error_entry(&conn, &later_id).unwrap().unwrap();
+        assert_eq!(entry.occurrences, 101);
+    }
+
+    #[test]
+    fn test_error_buffer_reuses_existing_row_id() {
+        let db = AgentDb(test_db());
+        let existing_id = insert_test_error(&db.lock_conn(), "ERR-EXISTING");
+        let buffer = ErrorRecordBuffer::new();
+        
+        let (id, _) = buffer.record(&db, storm_record("ERR-EXISTING")).unwrap();
+        assert_eq!(id, existing_id);
+        buffer.flush(&db.lock_conn()).unwrap();
+        
+        let conn = db.lock_conn();
+        let count: u32 = conn.query_row("SELECT COUNT(*) FROM error_knowledge", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(get_error_entry(&conn, &id).unwrap().unwrap().occurrences, 2);
+    }
+
+    #[test]
+    fn test_codes_from_raw_messages_are_renewed_once() {
+        let conn = test_db().into_inner().unwrap();
+        // Recorded before normalization, under codes of the raw messages
+        let record = |code: &str, message: &str, component: &str| {
+            track_error_internal(
+                &conn,
+                code.to_string(),
+                message.to_string(),
+                component.to_string(),
+                "FileSystem".to_string(),
+                "High".to_string(),
+                None,
+                HashMap::new(),
+                None,
+                None,
+            ).unwrap()
+        };
+        let split = [
+            record("ERR-RAW-0", "Checkpoint 3f6c1a52-9b1e-4c7a-8d2f-0e5b7a9c1d44 is missing", "checkpoint"),
+            record("ERR-RAW-1", "Checkpoint 0a1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d is missing", "checkpoint"),
+        ];
+        let single = record("ERR-RAW-2", "Failed to read /tmp/a.txt", "files");
+        let custom = record("PERMISSION-DENIED", "Failed to read /tmp/b.txt", "files");
+        conn.execute("DELETE FROM error_tracker_migrations", []).unwrap();
+
+        create_error_tables(&conn).unwrap();
+        let code_of = |id: &str| get_error_entry(&conn, id).unwrap().map(|entry| entry.error_code);
+        assert_eq!(code_of(&split[0]), Some(generate_error_code("Checkpoint 9e8d7c6b-5a4f-4e3d-8c2b-1a0f9e8d7c6b is missing", "checkpoint")));
+        assert_eq!(get_error_entry(&conn, &split[0]).unwrap().unwrap().occurrences, 2);
+        assert_eq!(code_of(&split[1]), None);
+        assert_eq!(code_of(&single), Some(generate_error_code("Failed to read /tmp/c.txt", "files")));
+        assert_eq!(code_of(&custom).as_deref(), Some("PERMISSION-DENIED"));
+
+        // Later starts leave codes alone
+        conn.execute("UPDATE error_knowledge SET error_code = 'ERR-RAW-2' WHERE id = ?1", [&single]).unwrap();
+        create_error_tables(&conn).unwrap();
+        assert_eq!(code_of(&single).as_deref(), Some("ERR-RAW-2"));
+    }
+
+    #[test]
+    fn test_messages_differing_by_uuid_share_an_error_code() {
+        let first = "Session 3f6c1a52-9b1e-4c7a-8d2f-0e5b7a9c1d44 not found";
+        let second = "Session 0a1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d not found";
+        assert_eq!(generate_error_code(first, "session"), generate_error_code(second, "session"));
+        assert_ne!(generate_error_code(first, "session"), generate_error_code(first, "checkpoint"));
+        assert_ne!(
+            generate_error_code("Session expired", "session"),
+            generate_error_code("Session not found", "session")
+        );
+
+        assert_eq!(
+            normalize_error_message("Failed to read /home/dev/app/src/main.rs after 3 retries (took 1500ms)"),
+            "Failed to read <path> after <n> retries (took <n>ms)"
+        );
+        assert_eq!(
+            normalize_error_message(r"Cannot open C:\Users\dev\app.db: request to https://api.example.com/v1 failed"),
+            "Cannot open <path> request to <url> failed"
+        );
+    }
+
+    #[test]
+    fn test_merge_errors_consolidates_split_entries() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        // Recorded before normalization, under codes of the raw messages
+        let messages = [
+            "Checkpoint 3f6c1a52-9b1e-4c7a-8d2f-0e5b7a9c1d44 is missing",
+            "Checkpoint 0a1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d is missing",
+        ];
+        let mut ids = Vec::new();
+        for (i, message) in messages.iter().enumerate() {
+            let id = track_error_internal(
+                &conn,
+                format!("ERR-RAW-{}", i),
+                message.to_string(),
+                "checkpoint".to_string(),
+                "FileSystem".to_string(),
+                "High".to_string(),
+                None,
+                HashMap::new(),
+                None,
+                None,
+            ).unwrap();
+            conn.execute(
+                "UPDATE error_knowledge SET occurred_at = ?1, last_occurrence = ?1, occurrences = ?2 WHERE id = ?3",
+                params![1000 + i as i64, 2 + i as u32, id],
+            ).unwrap();
+            ids.push(id);
+        }
+        conn.execute(
+            "INSERT INTO resolution_history (id, error_id, strategy_type, started_at) VALUES ('res-1', ?1, 'retry', 1)",
+            [&ids[1]],
+        ).unwrap();
+        insert_test_error(&conn, "ERR-UNRELATED");
+        assert_eq!(duplicate_error_groups(&conn).unwrap(), vec![ids.clone()]);
+
+        let merged = merge_error_entries(&conn, &[ids[1].clone(), ids[0].clone()]).unwrap();
+        assert_eq!(merged.id, ids[0]);
+        assert_eq!(merged.occurrences, 5);
+        assert_eq!((merged.occurred_at, merged.last_occurrence), (1000, 1001));
+        assert_eq!(merged.sample_messages, messages);
+        assert_eq!(merged.error_code, generate_error_code(messages[0], "checkpoint"));
+        assert!(get_error_entry(&conn, &ids[1]).unwrap().is_none());
+        let history_owner: String = conn
+            .query_row("SELECT error_id FROM resolution_history WHERE id = 'res-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(history_owner, ids[0]);
+        assert!(duplicate_error_groups(&conn).unwrap().is_empty());
+
+        // A new occurrence with yet another id lands on the merged entry
+        let third = "Checkpoint 9e8d7c6b-5a4f-4e3d-8c2b-1a0f9e8d7c6b is missing";
+        let id = track_error_internal(
+            &conn,
+            generate_error_code(third, "checkpoint"),
+            third.to_string(),
+            "checkpoint".to_string(),
+            "FileSystem".to_string(),
+            "High".to_string(),
+            None,
+            HashMap::new(),
+            None,
+            None,
+        ).unwrap();
+        assert_eq!(id, ids[0]);
+        let entry = get_error_entry(&conn, &id).unwrap().unwrap();
+        assert_eq!(entry.occurrences, 6);
+        assert_eq!(entry.sample_messages.last().map(String::as_str), Some(third));
+
+        assert!(merge_error_entries(&conn, &[ids[0].clone(), ids[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_error_metrics_exact_values() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        // Manually resolved after 600s
+        insert_raw_error(&conn, "a", "Resolved", 1000, Some(1600), 1000, false);
+        // Auto-resolved after 200s
+        insert_raw_error(&conn, "b", "AutoResolved", 2000, Some(2200), 2000, true);
+        // Auto-resolved once, then recurred: not resolved, excluded from MTTR
+        insert_raw_error(&conn, "c", "Recurring", 3000, Some(3100), 5000, true);
+        // Still open
+        insert_raw_error(&conn, "d", "New", 4000, None, 4000, false);
+        
+        let metrics = compute_error_metrics(&conn, 0).unwrap();
+        assert_eq!(metrics.total_errors, 4);
+        assert_eq!(metrics.resolved_errors, 2);
+        assert_eq!(metrics.auto_resolved_errors, 1);
+        assert_eq!(metrics.recurring_errors, 1);
+        assert_eq!(metrics.resolution_rate, 50.0);
+        assert_eq!(metrics.auto_resolution_coverage, 25.0);
+        assert_eq!(metrics.auto_resolution_share, 50.0);
+        assert_eq!(metrics.auto_resolution_rate, metrics.auto_resolution_share);
+        assert_eq!(metrics.mean_time_to_resolution, Some(400));
+    }
+
+    #[test]
+    fn test_enum_values_round_trip_through_read_paths() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        let mut expected = Vec::new();
+        for (i, status) in ErrorStatus::ALL.iter().enumerate() {
+            let severity = &ErrorSeverity::ALL[i % ErrorSeverity::ALL.len()];
+            let category = &ErrorCategory::ALL[i % ErrorCategory::ALL.len()];
+            let error_id = track_error_internal(
+                &conn,
+                format!("ERR-ROUNDTRIP-{}", i),
+                "round trip".to_string(),
+                "test".to_string(),
+                category.to_string(),
+                severity.to_string(),
+                None,
+                HashMap::new(),
+                None,
+                None,
+            ).unwrap();
+            conn.execute(
+                "UPDATE error_knowledge SET status = ? WHERE id = ?",
+                params![status.as_str(), error_id],
+            ).unwrap();
+            expected.push((error_id, status.clone(), severity.clone(), category.clone()));
+        }
+        
+        for (error_id, status, severity, category) in &expected {
+            let entry = get_error_entry(&conn, error_id).unwrap().unwrap();
+            assert_eq!((&entry.status, &entry.severity, &entry.category), (status, severity, category));
+            
+            let listed = list_error_entries(&conn, Some(status.to_string()), None, None).unwrap();
+            assert_eq!(listed.len(), 1);
+            assert_eq!(&listed[0].status, status);
+            
+            let searched = search_error_entries(
+                &conn,
+                Some(category.to_string()),
+                Some(severity.to_string()),
+                Some(status.to_string()),
+                None,
+                None,
+                None,
+            ).unwrap();
+            assert_eq!(searched.len(), 1);
+            assert_eq!(&searched[0].id, error_id);
+        }
+        
+        let metrics = compute_error_metrics(&conn, 0).unwrap();
+        assert_eq!(metrics.top_errors.len(), expected.len());
+        for summary in &metrics.top_errors {
+            let (_, status, severity, category) = expected.iter()
+                .find(|(id, ..)| get_error_entry(&conn, id).unwrap().unwrap().error_code == summary.error_code)
+                .unwrap();
+            assert_eq!((&summary.status, &summary.severity, &summary.category), (status, severity, category));
+        }
+    }
+
+    #[test]
+    fn test_enum_parsing_rejects_unknown_values() {
+        assert!("Severe".parse::<ErrorSeverity>().is_err());
+        assert!("Closed".parse::<ErrorStatus>().is_err());
+        assert_eq!("Cosmic".parse::<ErrorCategory>().unwrap(), ErrorCategory::Unknown);
+        assert_eq!(serde_json::to_string(&ErrorStatus::AutoResolved).unwrap(), "\"AutoResolved\"");
+        assert_eq!(serde_json::to_string(&ErrorCategory::UI).unwrap(), "\"UI\"");
+    }
+
+    #[test]
+    fn test_unrecognised_stored_values_load_as_unknown() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        insert_raw_error(&conn, "future", "Closed", 100, None, 100, false);
+        conn.execute("UPDATE error_knowledge SET severity = 'Severe' WHERE id = 'future'", []).unwrap();
+
+        let entry = get_error_entry(&conn, "future").unwrap().unwrap();
+        assert_eq!((entry.status, entry.severity), (ErrorStatus::Unknown, ErrorSeverity::Unknown));
+        assert_eq!(entry.category, ErrorCategory::Network);
+    }
+
+    #[test]
+    fn test_error_metrics_empty_window() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        insert_raw_error(&conn, "old", "Resolved", 100, Some(200), 100, false);
+        
+        let metrics = compute_error_metrics(&conn, 1000).unwrap();
+        assert_eq!(metrics.total_errors, 0);
+        assert_eq!(metrics.auto_resolution_coverage, 0.0);
+        assert_eq!(metrics.auto_resolution_share, 0.0);
+        assert_eq!(metrics.mean_time_to_resolution, None);
+    }
+
+    #[test]
+    fn test_disabled_category_skips_auto_resolution() {
+        let db = test_db();
+        let conn = db.lock().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+
+        let matched = |pattern_id: &str, strategy_type: ResolutionType| {
+            Some((pattern_id.to_string(), Some(ResolutionStrategy {
+                strategy_type,
+                action: "retry".to_string(),
+                parameters: HashMap::new(),
+                success_rate: 0.9,
+                attempt_count: 0,
+            })))
+        };
+        let auth_match = matched("auth_failure", ResolutionType::AuthRefresh);
+        let network_match = matched("network_timeout", ResolutionType::NetworkRetry);
+        assert!(permitted_resolution(&conn, "Authentication", &auth_match).is_some());
+
+        let mut config = AutoResolutionConfig::default();
+        config.categories.insert(ErrorCategory::Authentication, false);
+        save_auto_resolution_config(&conn, &config).unwrap();
+
+        assert!(permitted_resolution(&conn, "Authentication", &auth_match).is_none());
+        let network = permitted_resolution(&conn, "Network", &network_match).unwrap();
+        assert_eq!(network.strategy_type, ResolutionType::NetworkRetry);
+
+        let effective = load_auto_resolution_config(&conn).with_all_categories();
+        assert_eq!(effective.categories.len(), ErrorCategory::ALL.len());
+        assert!(!effective.categories[&ErrorCategory::Authentication]);
+        assert!(effective.categories[&ErrorCategory::Database]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_resolution_leaves_error_open() {
+        let db = test_db();
+        let error_id = insert_test_error(&db.lock().unwrap(), "ERR-TIMEOUT-2");
+        
+        let success = run_auto_resolution(&db, &error_id, &ResolutionType::ApiRetry, || async { false })
+            .await
+            .unwrap();
+        assert!(!success);
+        
+        let status: String = db.lock().unwrap().query_row(
+            "SELECT status FROM error_knowledge WHERE id = ?",
+            [&error_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(status, "New");
+    }
+}
@@ -2,9 +2,20 @@ use anyhow::{Context, Result};
 use log::{info, error, warn};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager, State, Emitter};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 use std::collections::HashMap;
+use std::future::Future;
+
+/// How often `claude-progress:{session_id}` is emitted while Claude runs
+pub const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a stop waits for the session's process to exit after killing it
+pub const STOP_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Description given to checkpoints made by `stop_and_checkpoint`
+const STOP_CHECKPOINT_DESCRIPTION: &str = "Stopped by user";
 
 /// Represents the state of an execution session
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,10 +38,239 @@ pub enum ExecutionStatus {
     Error,
 }
 
+/// Live progress of a running Claude execution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionProgress {
+    pub session_id: String,
+    pub elapsed_time: u64,
+    /// Output tokens generated so far
+    pub total_tokens: u64,
+    pub tokens_per_minute: f64,
+}
+
+/// Accumulates token usage from Claude's stream-json output
+pub struct ProgressTracker {
+    session_id: Option<String>,
+    started: Instant,
+    /// Output tokens by message id; Claude repeats usage on every line of a message
+    message_tokens: HashMap<String, u64>,
+    unidentified_tokens: u64,
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        Self {
+            session_id: None,
+            started: Instant::now(),
+            message_tokens: HashMap::new(),
+            unidentified_tokens: 0,
+        }
+    }
+
+    pub fn set_session_id(&mut self, session_id: &str) {
+        if self.session_id.is_none() {
+            self.session_id = Some(session_id.to_string());
+        }
+    }
+
+    /// Count the output tokens reported by an assistant message line
+    pub fn record_stream_message(&mut self, msg: &serde_json::Value) {
+        if msg["type"] != "assistant" {
+            return;
+        }
+        let Some(tokens) = msg["message"]["usage"]["output_tokens"].as_u64() else {
+            return;
+        };
+        match msg["message"]["id"].as_str() {
+            Some(id) => {
+                let entry = self.message_tokens.entry(id.to_string()).or_insert(0);
+                *entry = (*entry).max(tokens);
+            }
+            None => self.unidentified_tokens += tokens,
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.message_tokens.values().sum::<u64>() + self.unidentified_tokens
+    }
+
+    /// Progress so far, once Claude has reported its session id
+    pub fn snapshot(&self) -> Option<ExecutionProgress> {
+        let session_id = self.session_id.clone()?;
+        let elapsed = self.started.elapsed();
+        let total_tokens = self.total_tokens();
+        let minutes = elapsed.as_secs_f64() / 60.0;
+        Some(ExecutionProgress {
+            session_id,
+            elapsed_time: elapsed.as_secs(),
+            total_tokens,
+            tokens_per_minute: if minutes > 0.0 { total_tokens as f64 / minutes } else { 0.0 },
+        })
+    }
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Report progress every `interval` until `finished` is notified
+pub async fn run_progress_ticker<F>(
+    tracker: Arc<std::sync::Mutex<ProgressTracker>>,
+    interval: Duration,
+    finished: Arc<Notify>,
+    mut emit: F,
+) where
+    F: FnMut(ExecutionProgress),
+{
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // The first tick completes immediately; there is nothing to report yet
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = finished.notified() => break,
+            _ = ticker.tick() => {
+                let progress = tracker.lock().ok().and_then(|tracker| tracker.snapshot());
+                if let Some(progress) = progress {
+                    emit(progress);
+                }
+            }
+        }
+    }
+}
+
+/// Store elapsed time and token count for a session, creating its state if needed
+pub fn record_execution_metrics(
+    sessions: &mut HashMap<String, ExecutionState>,
+    session_id: &str,
+    elapsed_time: Option<u64>,
+    total_tokens: Option<u64>,
+) {
+    let session_state = sessions.entry(session_id.to_string())
+        .or_insert_with(|| {
+            info!("Auto-creating session state for metrics update: {}", session_id);
+            ExecutionState {
+                session_id: session_id.to_string(),
+                status: ExecutionStatus::Idle,
+                can_continue: false,
+                checkpoint_data: None,
+                elapsed_time: 0,
+                total_tokens: 0,
+            }
+        });
+
+    if let Some(time) = elapsed_time {
+        session_state.elapsed_time = time;
+    }
+    if let Some(tokens) = total_tokens {
+        session_state.total_tokens = tokens;
+    }
+}
+
 /// Global execution control state
+#[derive(Clone)]
 pub struct ExecutionControlState {
     pub sessions: Arc<Mutex<HashMap<String, ExecutionState>>>,
     pub active_processes: Arc<Mutex<HashMap<String, tokio::process::Child>>>,
+    /// Woken whenever a session is stopped; waiters check which one
+    pub stopped: Arc<Notify>,
+    /// Provider requests in flight by session, for runs without a process of their own
+    requests: Arc<std::sync::Mutex<HashMap<String, usize>>>,
+    /// Woken whenever an in-flight request finishes
+    settled: Arc<Notify>,
+}
+
+/// Counts a provider request as in flight until dropped
+pub struct InFlightRequest {
+    requests: Arc<std::sync::Mutex<HashMap<String, usize>>>,
+    settled: Arc<Notify>,
+    session_id: String,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = requests.get_mut(&self.session_id) {
+            *count -= 1;
+            if *count == 0 {
+                requests.remove(&self.session_id);
+            }
+        }
+        drop(requests);
+        self.settled.notify_waiters();
+    }
+}
+
+impl ExecutionControlState {
+    /// Whether the user stopped a session's execution
+    pub async fn is_stopped(&self, session_id: &str) -> bool {
+        self.sessions
+            .lock()
+            .await
+            .get(session_id)
+            .is_some_and(|session| session.status == ExecutionStatus::Stopped)
+    }
+
+    /// Resolves once `session_id` is stopped, right away if it already is
+    pub async fn wait_until_stopped(&self, session_id: &str) {
+        loop {
+            // Registered before the check, so a stop in between still wakes us
+            let notified = self.stopped.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_stopped(session_id).await {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Count a request for `session_id` as in flight until the returned guard drops
+    ///
+    /// Stopping a session waits for its in-flight requests to finish.
+    pub fn track_request(&self, session_id: &str) -> InFlightRequest {
+        *self.requests.lock().unwrap_or_else(|e| e.into_inner()).entry(session_id.to_string()).or_insert(0) += 1;
+        InFlightRequest {
+            requests: self.requests.clone(),
+            settled: self.settled.clone(),
+            session_id: session_id.to_string(),
+        }
+    }
+
+    fn requests_in_flight(&self, session_id: &str) -> usize {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).get(session_id).copied().unwrap_or(0)
+    }
+
+    /// Run `request` as an in-flight request, abandoning it if the session is stopped
+    ///
+    /// Returns `None` when stopped; dropping the request closes its connection.
+    pub async fn run_unless_stopped<T>(&self, session_id: &str, request: impl Future<Output = T>) -> Option<T> {
+        let _in_flight = self.track_request(session_id);
+        tokio::select! {
+            biased;
+            _ = self.wait_until_stopped(session_id) => None,
+            output = request => Some(output),
+        }
+    }
+
+    /// Wait up to `timeout` for the session's in-flight requests to finish; false if some didn't
+    async fn wait_for_requests(&self, session_id: &str, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let notified = self.settled.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.requests_in_flight(session_id) == 0 {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return false;
+            }
+        }
+    }
 }
 
 impl Default for ExecutionControlState {
@@ -38,8 +278,62 @@ impl Default for ExecutionControlState {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             active_processes: Arc::new(Mutex::new(HashMap::new())),
+            stopped: Arc::new(Notify::new()),
+            requests: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            settled: Arc::new(Notify::new()),
+        }
+    }
+}
+
+/// Mark a session stopped and wait for its run to halt
+///
+/// A registered process is killed. Runs without a process of their own, like
+/// Gemini and Ollama requests, abandon their in-flight requests when signalled
+/// and are waited for, and check the stopped status before each new call.
+pub async fn halt_execution(state: &ExecutionControlState, session_id: &str) -> ExecutionState {
+    let session_state = {
+        let mut sessions = state.sessions.lock().await;
+        let session_state = sessions.entry(session_id.to_string())
+            .or_insert_with(|| ExecutionState {
+                session_id: session_id.to_string(),
+                status: ExecutionStatus::Executing,
+                can_continue: false,
+                checkpoint_data: None,
+                elapsed_time: 0,
+                total_tokens: 0,
+            });
+        session_state.status = ExecutionStatus::Stopped;
+        session_state.can_continue = true;
+        session_state.clone()
+    };
+    state.stopped.notify_waiters();
+
+    // `kill` waits for the process to exit, so the run has halted once it returns
+    let child = state.active_processes.lock().await.remove(session_id);
+    if let Some(mut child) = child {
+        match tokio::time::timeout(STOP_WAIT_TIMEOUT, child.kill()).await {
+            Ok(Ok(())) => info!("Successfully killed process for session: {}", session_id),
+            Ok(Err(e)) => error!("Failed to kill process for session {}: {}", session_id, e),
+            Err(_) => warn!("Process for session {} did not exit within {:?}", session_id, STOP_WAIT_TIMEOUT),
         }
     }
+    if !state.wait_for_requests(session_id, STOP_WAIT_TIMEOUT).await {
+        warn!("Requests for session {} were still in flight after {:?}", session_id, STOP_WAIT_TIMEOUT);
+    }
+
+    session_state
+}
+
+/// Remember the checkpoint a stopped session can be resumed from
+async fn record_stop_checkpoint(
+    state: &ExecutionControlState,
+    session_id: &str,
+    checkpoint_data: serde_json::Value,
+) -> Option<ExecutionState> {
+    let mut sessions = state.sessions.lock().await;
+    let session_state = sessions.get_mut(session_id)?;
+    session_state.checkpoint_data = Some(checkpoint_data);
+    Some(session_state.clone())
 }
 
 /// Stop execution for a specific session
@@ -51,48 +345,77 @@ pub async fn stop_execution(
 ) -> Result<ExecutionState, String> {
     info!("Stopping execution for session: {}", session_id);
     
-    // Update session state
-    let mut sessions = state.sessions.lock().await;
-    let session_state = sessions.entry(session_id.clone())
-        .or_insert_with(|| ExecutionState {
-            session_id: session_id.clone(),
-            status: ExecutionStatus::Executing,
-            can_continue: false,
-            checkpoint_data: None,
-            elapsed_time: 0,
-            total_tokens: 0,
-        });
-    
     // Check if already stopped
-    if session_state.status == ExecutionStatus::Stopped {
-        warn!("Session {} is already stopped", session_id);
-        return Ok(session_state.clone());
-    }
-    
-    // Update status
-    session_state.status = ExecutionStatus::Stopped;
-    session_state.can_continue = true;
-    
-    // Try to kill the process if it exists
-    let mut processes = state.active_processes.lock().await;
-    if let Some(mut child) = processes.remove(&session_id) {
-        match child.kill().await {
-            Ok(_) => {
-                info!("Successfully killed process for session: {}", session_id);
-            }
-            Err(e) => {
-                error!("Failed to kill process for session {}: {}", session_id, e);
-            }
+    if let Some(session_state) = state.sessions.lock().await.get(&session_id) {
+        if session_state.status == ExecutionStatus::Stopped {
+            warn!("Session {} is already stopped", session_id);
+            return Ok(session_state.clone());
         }
     }
     
+    let session_state = halt_execution(&state, &session_id).await;
+    
     // Emit stop event
     app_handle.emit(
         &format!("execution-stopped:{}", session_id),
         &session_state,
     ).map_err(|e| format!("Failed to emit stop event: {}", e))?;
     
-    Ok(session_state.clone())
+    Ok(session_state)
+}
+
+/// Stop a session's run and checkpoint the state it stopped in
+///
+/// Claude runs are killed through the process registry; Gemini, Ollama and
+/// slash command runs through this module. Once the run has halted, the
+/// project files and session transcript are checkpointed and the checkpoint
+/// id is returned and kept in the session's `checkpoint_data`.
+#[tauri::command]
+pub async fn stop_and_checkpoint(
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    app_handle: AppHandle,
+    state: State<'_, ExecutionControlState>,
+) -> Result<String, String> {
+    info!("Stopping and checkpointing session: {}", session_id);
+
+    let registry = app_handle.state::<crate::process::ProcessRegistryState>();
+    match registry.0.get_claude_session_by_id(&session_id) {
+        Ok(Some(process)) => {
+            if let Err(e) = registry.0.kill_process(process.run_id).await {
+                warn!("Failed to kill Claude process for session {}: {}", session_id, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to look up Claude process for session {}: {}", session_id, e),
+    }
+    halt_execution(&state, &session_id).await;
+
+    let result = super::claude::create_checkpoint(
+        app_handle.state(),
+        session_id.clone(),
+        project_id.clone(),
+        project_path.clone(),
+        None,
+        Some(STOP_CHECKPOINT_DESCRIPTION.to_string()),
+    )
+    .await?;
+    let checkpoint_id = result.checkpoint.id;
+
+    let checkpoint_data = serde_json::json!({
+        "checkpoint_id": checkpoint_id,
+        "project_id": project_id,
+        "project_path": project_path,
+    });
+    if let Some(session_state) = record_stop_checkpoint(&state, &session_id, checkpoint_data).await {
+        app_handle.emit(
+            &format!("execution-stopped:{}", session_id),
+            &session_state,
+        ).map_err(|e| format!("Failed to emit stop event: {}", e))?;
+    }
+
+    Ok(checkpoint_id)
 }
 
 /// Continue execution from where it was stopped
@@ -192,28 +515,7 @@ pub async fn update_execution_metrics(
     state: State<'_, ExecutionControlState>,
 ) -> Result<(), String> {
     let mut sessions = state.sessions.lock().await;
-    
-    // Auto-create session if it doesn't exist, then update metrics
-    let session_state = sessions.entry(session_id.clone())
-        .or_insert_with(|| {
-            info!("Auto-creating session state for metrics update: {}", session_id);
-            ExecutionState {
-                session_id: session_id.clone(),
-                status: ExecutionStatus::Idle,
-                can_continue: false,
-                checkpoint_data: None,
-                elapsed_time: 0,
-                total_tokens: 0,
-            }
-        });
-    
-    if let Some(time) = elapsed_time {
-        session_state.elapsed_time = time;
-    }
-    if let Some(tokens) = total_tokens {
-        session_state.total_tokens = tokens;
-    }
-    
+    record_execution_metrics(&mut sessions, &session_id, elapsed_time, total_tokens);
     Ok(())
 }
 
@@ -265,4 +567,137 @@ pub async fn mark_execution_completed(
     processes.remove(&session_id);
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assistant_line(id: &str, output_tokens: u64) -> serde_json::Value {
+        json!({
+            "type": "assistant",
+            "message": { "id": id, "usage": { "input_tokens": 500, "output_tokens": output_tokens } }
+        })
+    }
+
+    #[test]
+    fn test_tracker_counts_each_message_once() {
+        let mut tracker = ProgressTracker::new();
+        assert!(tracker.snapshot().is_none());
+
+        tracker.set_session_id("s1");
+        tracker.record_stream_message(&assistant_line("msg_1", 40));
+        tracker.record_stream_message(&assistant_line("msg_1", 40));
+        tracker.record_stream_message(&assistant_line("msg_2", 10));
+        tracker.record_stream_message(&json!({ "type": "user", "message": { "usage": { "output_tokens": 99 } } }));
+
+        let progress = tracker.snapshot().unwrap();
+        assert_eq!(progress.session_id, "s1");
+        assert_eq!(progress.total_tokens, 50);
+    }
+
+    #[tokio::test]
+    async fn test_progress_events_emitted_during_long_run() {
+        let tracker = Arc::new(std::sync::Mutex::new(ProgressTracker::new()));
+        let finished = Arc::new(Notify::new());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let ticker = tokio::spawn(run_progress_ticker(
+            tracker.clone(),
+            Duration::from_millis(50),
+            finished.clone(),
+            move |progress| { let _ = tx.send(progress); },
+        ));
+
+        // Simulated run: output keeps arriving for a while
+        tracker.lock().unwrap().set_session_id("s1");
+        for i in 0..8 {
+            tracker.lock().unwrap().record_stream_message(&assistant_line(&format!("msg_{}", i), 25));
+            tokio::time::sleep(Duration::from_millis(40)).await;
+        }
+        finished.notify_one();
+        ticker.await.unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(progress) = rx.try_recv() {
+            events.push(progress);
+        }
+        assert!(events.len() >= 3, "expected periodic progress, got {} events", events.len());
+        // Throttled to the interval, not one event per message
+        assert!(events.len() <= 8);
+        assert!(events.windows(2).all(|w| w[0].total_tokens <= w[1].total_tokens));
+        assert!(events.last().unwrap().total_tokens > 0);
+        assert!(events.last().unwrap().tokens_per_minute > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_stop_abandons_and_awaits_in_flight_requests() {
+        let state = ExecutionControlState::default();
+        let request = tokio::spawn({
+            let state = state.clone();
+            async move {
+                state.run_unless_stopped("s1", tokio::time::sleep(Duration::from_secs(60))).await
+            }
+        });
+        while state.requests_in_flight("s1") == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let started = Instant::now();
+        halt_execution(&state, "s1").await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(state.requests_in_flight("s1"), 0);
+        assert_eq!(request.await.unwrap(), None);
+        // Requests for a stopped session don't start
+        assert_eq!(state.run_unless_stopped("s1", async { 1 }).await, None);
+        assert_eq!(state.run_unless_stopped("s2", async { 1 }).await, Some(1));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stopped_run_leaves_restorable_checkpoint() {
+        use crate::checkpoint::state::CheckpointState;
+        use std::fs;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project_path = temp_dir.path().join("project");
+        fs::create_dir_all(&project_path).unwrap();
+        fs::write(project_path.join("main.rs"), "fn main() {}\n").unwrap();
+
+        // Simulated run: a long-lived process registered for the session
+        let state = ExecutionControlState::default();
+        let child = tokio::process::Command::new("sleep").arg("30").spawn().unwrap();
+        register_process("s1".to_string(), child, &state).await.unwrap();
+
+        let stopped = halt_execution(&state, "s1").await;
+        assert_eq!(stopped.status, ExecutionStatus::Stopped);
+        assert!(stopped.can_continue);
+        assert!(state.active_processes.lock().await.is_empty());
+
+        let checkpoints = CheckpointState::new();
+        checkpoints.set_claude_dir(temp_dir.path().join(".claude")).await;
+        let manager = checkpoints
+            .get_or_create_manager("s1".to_string(), "test-project".to_string(), project_path.clone())
+            .await
+            .unwrap();
+        let checkpoint_id = manager
+            .create_checkpoint(Some(STOP_CHECKPOINT_DESCRIPTION.to_string()), None)
+            .await
+            .unwrap()
+            .checkpoint
+            .id;
+        let recorded = record_stop_checkpoint(&state, "s1", json!({ "checkpoint_id": checkpoint_id }))
+            .await
+            .unwrap();
+        assert_eq!(recorded.checkpoint_data.unwrap()["checkpoint_id"], checkpoint_id.as_str());
+
+        // Whatever happens after the stop is undone by restoring the checkpoint
+        fs::write(project_path.join("main.rs"), "broken").unwrap();
+        fs::write(project_path.join("scratch.rs"), "// left over").unwrap();
+        manager.restore_checkpoint(&checkpoint_id).await.unwrap();
+
+        assert_eq!(fs::read_to_string(project_path.join("main.rs")).unwrap(), "fn main() {}\n");
+        assert!(!project_path.join("scratch.rs").exists());
+    }
+}
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use super::intelligence_bridge::{CodeSection, FileContext};
+use crate::analysis::text_files::{has_binary_extension, looks_binary};
+
+/// Lines included before and after a requested range
+const CONTEXT_LINES: u32 = 3;
+
+lazy_static::lazy_static! {
+    /// `path:line` or `path:start-end`; Windows drive colons never match since a range must follow
+    static ref LINE_RANGE: Regex = Regex::new(r"^(.+):(\d+)(?:-(\d+))?$").unwrap();
+}
+
+/// A file to attach, with the lines to include when only part of it is needed
+///
+/// A reference such as `src/main.rs:40-120` attaches only those lines, plus a few
+/// either side for context, so a large file doesn't crowd out the rest of the prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileAttachment {
+    pub path: String,
+    /// 1-based, inclusive; `None` attaches the whole file
+    pub lines: Option<(u32, u32)>,
+}
+
+impl FileAttachment {
+    /// Parse `path`, `path:line` or `path:start-end`
+    pub fn parse(reference: &str) -> Result<Self, String> {
+        let reference = reference.trim();
+        if reference.is_empty() {
+            return Err("File reference is empty".to_string());
+        }
+        let Some(caps) = LINE_RANGE.captures(reference) else {
+            return Ok(Self { path: reference.to_string(), lines: None });
+        };
+
+        let line = |index: usize| -> Result<u32, String> {
+            caps[index].parse().map_err(|_| format!("Line number in {} is too large", reference))
+        };
+        let start = line(2)?;
+        let end = match caps.get(3) {
+            Some(_) => line(3)?,
+            None => start,
+        };
+        if start == 0 {
+            return Err(format!("Line numbers in {} start at 1", reference));
+        }
+        if start > end {
+            return Err(format!("Range in {} ends before it starts", reference));
+        }
+        Ok(Self { path: caps[1].to_string(), lines: Some((start, end)) })
+    }
+}
+
+/// An attachment read from disk
+#[derive(Debug, Clone)]
+pub struct AttachedFile {
+    /// The file, with the requested range as its relevant section
+    pub context: FileContext,
+    pub total_lines: u32,
+    /// Lines included in the prompt, requested range plus context
+    pub shown_lines: (u32, u32),
+    pub text: String,
+}
+
+/// Read the lines `attachment` asks for, resolving relative paths against `project_path`
+pub fn read_attachment(project_path: &Path, attachment: &FileAttachment) -> Result<AttachedFile, String> {
+    let path = project_path.join(&attachment.path);
+    if has_binary_extension(&path) {
+        return Err(format!("{} is a binary file and can't be attached", attachment.path));
+    }
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", attachment.path, e))?;
+    if looks_binary(&bytes) {
+        return Err(format!("{} is a binary file and can't be attached", attachment.path));
+    }
+    let content = String::from_utf8(bytes).map_err(|_| format!("{} is not valid UTF-8", attachment.path))?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let total_lines = lines.len() as u32;
+    let (start, end) = match attachment.lines {
+        Some((start, end)) if end > total_lines => {
+            return Err(format!(
+                "Lines {}-{} of {} are out of range; the file has {} lines",
+                start, end, attachment.path, total_lines
+            ))
+        }
+        Some(range) => range,
+        None => (1, total_lines),
+    };
+
+    let shown_lines = match attachment.lines {
+        Some(_) => (start.saturating_sub(CONTEXT_LINES).max(1), (end + CONTEXT_LINES).min(total_lines)),
+        None => (start, end),
+    };
+    let width = shown_lines.1.to_string().len();
+    let text = (shown_lines.0..=shown_lines.1)
+        .filter_map(|number| lines.get(number as usize - 1).map(|line| format!("{:>width$} | {}", number, line)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let last_modified = std::fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+    Ok(AttachedFile {
+        context: FileContext {
+            path: attachment.path.clone(),
+            content_hash: format!("{:x}", Sha256::digest(content.as_bytes())),
+            last_modified,
+            relevant_sections: vec![CodeSection {
+                start_line: start,
+                end_line: end,
+                purpose: "Attached to the request".to_string(),
+                dependencies: Vec::new(),
+            }],
+            pending_changes: Vec::new(),
+        },
+        total_lines,
+        shown_lines,
+        text,
+    })
+}
+
+/// The attachment with a provenance header, ready to put in a prompt
+fn render_attachment(attached: &AttachedFile) -> String {
+    let path = &attached.context.path;
+    let (first, last) = attached.shown_lines;
+    let coverage = match attached.context.relevant_sections.first() {
+        Some(section) if (section.start_line, section.end_line) != (1, attached.total_lines) => format!(
+            "lines {}-{} of {}; requested {}-{}",
+            first, last, attached.total_lines, section.start_line, section.end_line
+        ),
+        _ => format!("all {} lines", attached.total_lines),
+    };
+    format!(
+        "--- Attached file: {} ({}) ---\n{}\n--- End of {} ---",
+        path, coverage, attached.text, path
+    )
+}
+
+/// Put the referenced files, or just their requested lines, ahead of `prompt`
+///
+/// Every reference is read and validated before anything is sent, so a bad range
+/// fails the request rather than silently attaching the wrong lines.
+pub fn attach_files(prompt: &str, project_path: &str, references: &[String]) -> Result<String, String> {
+    if references.is_empty() {
+        return Ok(prompt.to_string());
+    }
+    let project_path = Path::new(project_path);
+    let sections = references
+        .iter()
+        .map(|reference| {
+            let attachment = FileAttachment::parse(reference)?;
+            read_attachment(project_path, &attachment).map(|attached| render_attachment(&attached))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    log::info!("Attached {} file reference(s) to the prompt", sections.len());
+    Ok(format!("{}\n\n{}", sections.join("\n\n"), prompt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(
+            FileAttachment::parse("src/main.rs:40-120").unwrap(),
+            FileAttachment { path: "src/main.rs".to_string(), lines: Some((40, 120)) }
+        );
+        assert_eq!(FileAttachment::parse("lib.rs:7").unwrap().lines, Some((7, 7)));
+        assert_eq!(
+            FileAttachment::parse(r"C:\work\app\main.rs").unwrap(),
+            FileAttachment { path: r"C:\work\app\main.rs".to_string(), lines: None }
+        );
+        assert_eq!(FileAttachment::parse(r"C:\work\main.rs:3-4").unwrap().path, r"C:\work\main.rs");
+        assert!(FileAttachment::parse("main.rs:0-4").is_err());
+        assert!(FileAttachment::parse("main.rs:9-4").is_err());
+    }
+
+    #[test]
+    fn test_attaching_a_range_includes_only_those_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let content: String = (1..=200).map(|n| format!("line {}\n", n)).collect();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/big.rs"), content).unwrap();
+        let project = dir.path().to_str().unwrap();
+
+        let prompt = attach_files("Explain this", project, &["src/big.rs:40-120".to_string()]).unwrap();
+        assert!(prompt.starts_with("--- Attached file: src/big.rs (lines 37-123 of 200; requested 40-120) ---\n"));
+        assert!(prompt.ends_with("--- End of src/big.rs ---\n\nExplain this"));
+        let included: Vec<u32> = prompt
+            .lines()
+            .filter_map(|line| line.split_once(" | "))
+            .map(|(number, text)| {
+                assert_eq!(text, format!("line {}", number.trim()));
+                number.trim().parse().unwrap()
+            })
+            .collect();
+        assert_eq!(included, (37..=123).collect::<Vec<_>>());
+
+        let err = attach_files("Explain this", project, &["src/big.rs:190-240".to_string()]).unwrap_err();
+        assert!(err.contains("the file has 200 lines"), "{}", err);
+        assert_eq!(attach_files("Explain this", project, &[]).unwrap(), "Explain this");
+    }
+}
@@ -1,22 +1,117 @@
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::hash::{Hash, Hasher, DefaultHasher};
-use tauri::{State, Emitter};
+use futures::stream::StreamExt;
+use tauri::{State, Emitter, Manager};
 use uuid::Uuid;
 use super::{claude::ClaudeProcessState, agents::AgentDb};
-use super::session_deduplication::{MessageDeduplicationManager, SessionIsolationManager};
-use super::execution_control::{ExecutionControlState, ExecutionStatus};
+use super::settings_diff::Setting;
+use super::ai_usage_tracker::{track_turn_usage, AIUsageEvent};
+use super::session_deduplication::{DeduplicationMode, MessageDeduplicationManager, SessionIsolationManager, LENIENT_WINDOW_MS};
+use super::execution_control::{ExecutionControlState, ExecutionState, ExecutionStatus};
+use super::session_event_log::emit_session_event;
+use super::gemini_history::{context_window, conversation_contents, ConversationTurn};
+use super::gemini_processor::GeminiStreamAccumulator;
+use super::intelligent_routing::{load_model_benchmarks, AiModelBenchmark};
+use super::request_hooks::request_hooks;
+use super::session_lifecycle::SessionManagers;
+use super::universal_tool_executor::ToolContext;
+use crate::adapters::UniversalToolBridge;
 use log;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Default generation parameters used when neither the request nor the
+/// persisted `GeminiConfig` provides a value
+pub const DEFAULT_GEMINI_TEMPERATURE: f32 = 0.7;
+pub const DEFAULT_GEMINI_MAX_TOKENS: u32 = 8192;
+pub const DEFAULT_GEMINI_TOP_K: u32 = 40;
+pub const DEFAULT_GEMINI_TOP_P: f32 = 0.95;
+/// Function-call round trips allowed in one Gemini execution
+pub const DEFAULT_GEMINI_TOOL_ROUNDS: u32 = 5;
+
+/// app_settings key holding the persisted default `GeminiConfig` (JSON)
+pub(crate) const GEMINI_CONFIG_SETTINGS_KEY: &str = "gemini_config";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<GeminiConfig>(GEMINI_CONFIG_SETTINGS_KEY)];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GeminiConfig {
     pub api_key: Option<String>,
+    #[serde(default)]
     pub model: String,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Upper bound on function-call round trips before the execution fails
+    #[serde(default)]
+    pub max_tool_rounds: Option<u32>,
+}
+
+impl GeminiConfig {
+    /// Validate generation parameter ranges
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(format!("Temperature must be between 0 and 2, got {}", temperature));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(format!("topP must be between 0 and 1, got {}", top_p));
+            }
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            if max_tokens == 0 {
+                return Err("max_tokens must be greater than 0".to_string());
+            }
+        }
+        if let Some(top_k) = self.top_k {
+            if top_k == 0 {
+                return Err("topK must be greater than 0".to_string());
+            }
+        }
+        if let Some(max_tool_rounds) = self.max_tool_rounds {
+            if max_tool_rounds == 0 {
+                return Err("max_tool_rounds must be greater than 0".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Overlay the parameters set in `overrides` on top of this config
+    pub fn merged_with(&self, overrides: &GeminiConfig) -> GeminiConfig {
+        GeminiConfig {
+            api_key: overrides.api_key.clone().or_else(|| self.api_key.clone()),
+            model: if overrides.model.is_empty() { self.model.clone() } else { overrides.model.clone() },
+            temperature: overrides.temperature.or(self.temperature),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            top_k: overrides.top_k.or(self.top_k),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tool_rounds: overrides.max_tool_rounds.or(self.max_tool_rounds),
+        }
+    }
+
+    /// How many function-call round trips one execution may take
+    pub fn tool_rounds(&self) -> usize {
+        self.max_tool_rounds.unwrap_or(DEFAULT_GEMINI_TOOL_ROUNDS) as usize
+    }
+
+    /// Build the `generationConfig` object, falling back to defaults for unset values
+    pub fn generation_config(&self) -> serde_json::Value {
+        serde_json::json!({
+            "temperature": self.temperature.unwrap_or(DEFAULT_GEMINI_TEMPERATURE),
+            "maxOutputTokens": self.max_tokens.unwrap_or(DEFAULT_GEMINI_MAX_TOKENS),
+            "topK": self.top_k.unwrap_or(DEFAULT_GEMINI_TOP_K),
+            "topP": self.top_p.unwrap_or(DEFAULT_GEMINI_TOP_P),
+            "stopSequences": [],
+            "candidateCount": 1
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,6 +120,10 @@ pub struct GeminiRequest {
     pub model: String,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -46,8 +145,10 @@ pub struct GeminiSessionState {
     pub project_id: String,
     pub model: String,
     pub created_at: u64,
-    pub message_ids: HashSet<String>,
+    /// Content ids seen in this session, with when they were last seen
+    pub message_ids: HashMap<String, u64>,
     pub last_activity: u64,
+    pub dedup_mode: DeduplicationMode,
 }
 
 impl GeminiSessionRegistry {
@@ -59,6 +160,17 @@ impl GeminiSessionRegistry {
     
     /// Register a new session with isolation
     pub fn register_session(&self, session_id: &str, project_id: &str, model: &str) -> Result<(), String> {
+        self.register_session_with_mode(session_id, project_id, model, DeduplicationMode::default())
+    }
+    
+    /// Register a new session with isolation and the given deduplication mode
+    pub fn register_session_with_mode(
+        &self,
+        session_id: &str,
+        project_id: &str,
+        model: &str,
+        dedup_mode: DeduplicationMode,
+    ) -> Result<(), String> {
         let mut sessions = self.active_sessions.lock()
             .map_err(|e| format!("Failed to acquire session registry lock: {}", e))?;
         
@@ -72,8 +184,9 @@ impl GeminiSessionRegistry {
             project_id: project_id.to_string(),
             model: model.to_string(),
             created_at: current_time,
-            message_ids: HashSet::new(),
+            message_ids: HashMap::new(),
             last_activity: current_time,
+            dedup_mode,
         };
         
         sessions.insert(session_id.to_string(), state);
@@ -83,6 +196,15 @@ impl GeminiSessionRegistry {
     
     /// Check if message already exists (deduplication)
     pub fn is_duplicate_message(&self, session_id: &str, content: &str) -> Result<bool, String> {
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        self.is_duplicate_message_at(session_id, content, current_time)
+    }
+    
+    /// `is_duplicate_message` at a given time in milliseconds since the epoch
+    pub(crate) fn is_duplicate_message_at(&self, session_id: &str, content: &str, current_time: u64) -> Result<bool, String> {
         let mut sessions = self.active_sessions.lock()
             .map_err(|e| format!("Failed to acquire session registry lock: {}", e))?;
         
@@ -93,21 +215,23 @@ impl GeminiSessionRegistry {
             let content_hash = hasher.finish();
             let message_id = format!("{}:{:x}", session_id, content_hash);
             
-            if session.message_ids.contains(&message_id) {
+            let is_duplicate = match (session.dedup_mode, session.message_ids.get(&message_id)) {
+                (DeduplicationMode::Strict, Some(_)) => true,
+                (DeduplicationMode::Lenient, Some(&seen_at)) => current_time.saturating_sub(seen_at) < LENIENT_WINDOW_MS,
+                _ => false,
+            };
+            if is_duplicate {
                 log::warn!("Duplicate message detected for session {}: {}", session_id, message_id);
                 return Ok(true);
             }
             
             // Add message ID to prevent future duplicates
-            session.message_ids.insert(message_id);
-            session.last_activity = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64;
+            session.message_ids.insert(message_id, current_time);
+            session.last_activity = current_time;
             
             // Cleanup old message IDs if we have too many (prevent memory leak)
             if session.message_ids.len() > 1000 {
-                let old_ids: Vec<String> = session.message_ids.iter().take(500).cloned().collect();
+                let old_ids: Vec<String> = session.message_ids.keys().take(500).cloned().collect();
                 for old_id in old_ids {
                     session.message_ids.remove(&old_id);
                 }
@@ -167,7 +291,7 @@ impl GeminiSessionRegistry {
 }
 
 /// Generate secure session ID using UUID v4 + timestamp + salt
-fn generate_secure_gemini_session_id(project_id: &str, model: &str) -> String {
+pub(super) fn generate_secure_gemini_session_id(project_id: &str, model: &str) -> String {
     let uuid = Uuid::new_v4();
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -235,6 +359,7 @@ pub async fn get_gemini_api_key_command(db: State<'_, AgentDb>) -> Result<String
 /// Set the Gemini API key
 #[tauri::command]
 pub async fn set_gemini_api_key(
+    app: tauri::AppHandle,
     api_key: String,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
@@ -249,8 +374,7 @@ pub async fn set_gemini_api_key(
         return Err("Invalid Gemini API key format. Keys should start with 'AIza'".to_string());
     }
     
-    let conn = db.0.lock()
-        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+    let conn = db.lock_conn();
     
     // Use a transaction for atomic upsert
     let tx = conn.unchecked_transaction()
@@ -272,12 +396,16 @@ pub async fn set_gemini_api_key(
     
     tx.commit()
         .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    drop(conn);
+    reset_gemini_endpoints();
+    crate::commands::simple_model_validator::invalidate_available_providers();
+    tauri::async_runtime::spawn(probe_gemini_endpoints(app));
     
     Ok(())
 }
 
 /// Get the Gemini API key from storage
-fn get_gemini_api_key_sync(conn: &rusqlite::Connection) -> Result<String, String> {
+pub(crate) fn get_gemini_api_key_sync(conn: &rusqlite::Connection) -> Result<String, String> {
     // First check environment variable
     if let Ok(api_key) = env::var("GEMINI_API_KEY") {
         return Ok(api_key);
@@ -295,6 +423,540 @@ fn get_gemini_api_key_sync(conn: &rusqlite::Connection) -> Result<String, String
     }
 }
 
+/// Load the persisted default Gemini config, or an empty one if none is stored
+pub(crate) fn load_gemini_config_sync(conn: &rusqlite::Connection) -> Result<GeminiConfig, String> {
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [GEMINI_CONFIG_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => serde_json::from_str(&value)
+            .map_err(|e| format!("Failed to parse stored Gemini config: {}", e)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(GeminiConfig::default()),
+        Err(e) => Err(format!("Failed to load Gemini config: {}", e)),
+    }
+}
+
+/// Get the persisted default Gemini generation config
+#[tauri::command]
+pub async fn get_gemini_config(db: State<'_, AgentDb>) -> Result<GeminiConfig, String> {
+    let conn = db.lock_conn();
+    let mut config = load_gemini_config_sync(&conn)?;
+    // Never hand the stored key back through the config payload
+    config.api_key = None;
+    Ok(config)
+}
+
+/// Persist the default Gemini generation config
+#[tauri::command]
+pub async fn save_gemini_config(
+    config: GeminiConfig,
+    db: State<'_, AgentDb>,
+) -> Result<(), String> {
+    config.validate()?;
+
+    // The API key is managed separately by set_gemini_api_key
+    let config = GeminiConfig { api_key: None, ..config };
+    let value = serde_json::to_string(&config)
+        .map_err(|e| format!("Failed to serialize Gemini config: {}", e))?;
+
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![GEMINI_CONFIG_SETTINGS_KEY, value],
+    ).map_err(|e| format!("Failed to save Gemini config: {}", e))?;
+
+    Ok(())
+}
+
+/// Build the generateContent request body for a prompt and generation config
+pub(super) fn build_gemini_request_body(prompt: &str, config: &GeminiConfig) -> serde_json::Value {
+    serde_json::json!({
+        "contents": [{
+            "role": "user",
+            "parts": [{
+                "text": prompt
+            }]
+        }],
+        "generationConfig": config.generation_config(),
+        "safetySettings": [
+            {
+                "category": "HARM_CATEGORY_HARASSMENT",
+                "threshold": "BLOCK_ONLY_HIGH"
+            },
+            {
+                "category": "HARM_CATEGORY_HATE_SPEECH", 
+                "threshold": "BLOCK_ONLY_HIGH"
+            },
+            {
+                "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+                "threshold": "BLOCK_ONLY_HIGH"
+            },
+            {
+                "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
+                "threshold": "BLOCK_ONLY_HIGH"
+            }
+        ]
+    })
+}
+
+/// The `systemInstruction` of a request, or `None` when the prompt is blank
+pub(super) fn gemini_system_instruction(system_prompt: &str) -> Option<serde_json::Value> {
+    let system_prompt = system_prompt.trim();
+    if system_prompt.is_empty() {
+        return None;
+    }
+    Some(serde_json::json!({ "parts": [{ "text": system_prompt }] }))
+}
+
+/// Image types Gemini accepts as inline data
+const GEMINI_IMAGE_TYPES: [&str; 5] = ["image/png", "image/jpeg", "image/webp", "image/heic", "image/heif"];
+
+/// `inlineData` parts for base64 images, given as `data:` URLs or bare PNG data
+pub(super) fn gemini_image_parts(images: &[String]) -> Result<Vec<serde_json::Value>, String> {
+    use base64::Engine as _;
+    images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let (mime_type, bytes) = super::image_handler::decode_base64_image(image.trim(), None)
+                .map_err(|e| format!("Image {}: {}", i + 1, e))?;
+            let mime_type = match mime_type.to_lowercase().as_str() {
+                "image/jpg" => "image/jpeg".to_string(),
+                other => other.to_string(),
+            };
+            if !GEMINI_IMAGE_TYPES.contains(&mime_type.as_str()) {
+                return Err(format!(
+                    "Image {}: Gemini doesn't accept {} images; use PNG, JPEG, WebP, HEIC or HEIF",
+                    i + 1,
+                    mime_type
+                ));
+            }
+            Ok(serde_json::json!({
+                "inlineData": {
+                    "mimeType": mime_type,
+                    "data": base64::engine::general_purpose::STANDARD.encode(bytes)
+                }
+            }))
+        })
+        .collect()
+}
+
+/// Add image parts to the user's turn, the last of the request's contents
+pub(super) fn attach_image_parts(request_body: &mut serde_json::Value, image_parts: Vec<serde_json::Value>) -> Result<(), String> {
+    request_body["contents"]
+        .as_array_mut()
+        .and_then(|contents| contents.last_mut())
+        .and_then(|turn| turn["parts"].as_array_mut())
+        .ok_or("Gemini request has no user turn to attach images to")?
+        .extend(image_parts);
+    Ok(())
+}
+
+/// Fail when `model`'s benchmark says it can't take images
+///
+/// Models without a benchmark entry are let through; the API rejects images
+/// they can't handle.
+pub(super) fn check_vision_support(model: &str, benchmarks: &[AiModelBenchmark]) -> Result<(), String> {
+    let Some(benchmark) = benchmarks
+        .iter()
+        .find(|b| b.model_id != "auto" && crate::models::same_model(&b.model_id, model))
+    else {
+        return Ok(());
+    };
+    if benchmark.supports_vision {
+        return Ok(());
+    }
+    let alternative = benchmarks
+        .iter()
+        .filter(|b| b.model_id != "auto" && b.provider == benchmark.provider && b.supports_vision)
+        .max_by(|a, b| a.intelligence_score.total_cmp(&b.intelligence_score));
+    Err(match alternative {
+        Some(alternative) => format!(
+            "Model {} doesn't accept images; try {}, which does",
+            benchmark.model_id, alternative.model_id
+        ),
+        None => format!("Model {} doesn't accept images", benchmark.model_id),
+    })
+}
+
+/// How a Gemini candidate finished
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeminiFinishState {
+    pub finish_reason: Option<String>,
+    /// The response was cut off by the output token limit
+    pub truncated: bool,
+}
+
+/// Inspect a candidate's finishReason, turning blocking reasons into errors
+pub(super) fn inspect_finish_reason(candidate: &serde_json::Value, session_id: &str) -> Result<GeminiFinishState, String> {
+    let finish_reason = candidate["finishReason"].as_str();
+    let mut truncated = false;
+    
+    if let Some(finish_reason) = finish_reason {
+        match finish_reason {
+            "SAFETY" => {
+                log::warn!("Gemini response blocked by safety filters for session: {}", session_id);
+                return Err("Response was blocked by Gemini safety filters. Try rephrasing your request.".to_string());
+            },
+            "RECITATION" => {
+                log::warn!("Gemini response blocked due to recitation for session: {}", session_id);
+                return Err("Response was blocked due to potential copyright concerns. Try asking in a different way.".to_string());
+            },
+            "OTHER" => {
+                log::warn!("Gemini response failed for unknown reasons for session: {}", session_id);
+                return Err("Response generation failed. This may be a temporary issue - please try again.".to_string());
+            },
+            "MAX_TOKENS" => {
+                log::info!("Gemini response hit max tokens limit for session: {}", session_id);
+                // Not an error, but the caller must tell the user the answer is incomplete
+                truncated = true;
+            },
+            "STOP" | "STOP_SEQUENCE" => {
+                log::info!("Gemini response completed normally for session: {}", session_id);
+            },
+            _ => {
+                log::info!("Gemini response finished with reason: {} for session: {}", finish_reason, session_id);
+            }
+        }
+    }
+    
+    Ok(GeminiFinishState {
+        finish_reason: finish_reason.map(|r| r.to_string()),
+        truncated,
+    })
+}
+
+/// Build the structured completion payload emitted alongside `claude-complete`
+fn build_completion_payload(session_id: &str, finish_state: &GeminiFinishState) -> serde_json::Value {
+    serde_json::json!({
+        "session_id": session_id,
+        "success": true,
+        "finish_reason": finish_state.finish_reason,
+        "truncated": finish_state.truncated,
+        "can_continue": finish_state.truncated,
+    })
+}
+
+/// One entry of Gemini's `safetyRatings`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeminiSafetyRating {
+    pub category: String,
+    pub probability: String,
+    pub blocked: bool,
+}
+
+/// Why Gemini refused a request, emitted as `claude-blocked:{session_id}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeminiBlock {
+    pub session_id: String,
+    /// `blockReason` or `finishReason`, e.g. `SAFETY` or `RECITATION`
+    pub reason: String,
+    /// Whether the prompt itself was rejected rather than the generated answer
+    pub prompt_blocked: bool,
+    /// Harm category that triggered the block, when Gemini reported one
+    pub category: Option<String>,
+    pub probability: Option<String>,
+    pub ratings: Vec<GeminiSafetyRating>,
+    pub message: String,
+    pub can_rephrase: bool,
+}
+
+/// Finish reasons meaning the answer was withheld rather than completed
+const GEMINI_BLOCK_FINISH_REASONS: [&str; 5] = ["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII"];
+
+fn parse_safety_ratings(ratings: &serde_json::Value) -> Vec<GeminiSafetyRating> {
+    ratings
+        .as_array()
+        .map(|ratings| {
+            ratings
+                .iter()
+                .filter_map(|rating| {
+                    Some(GeminiSafetyRating {
+                        category: rating["category"].as_str()?.to_string(),
+                        probability: rating["probability"].as_str().unwrap_or("UNKNOWN").to_string(),
+                        blocked: rating["blocked"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn probability_rank(probability: &str) -> u8 {
+    match probability {
+        "HIGH" => 4,
+        "MEDIUM" => 3,
+        "LOW" => 2,
+        "NEGLIGIBLE" => 1,
+        _ => 0,
+    }
+}
+
+/// Recognise a prompt or response Gemini blocked, naming the harm category behind it
+pub(super) fn detect_gemini_block(response: &serde_json::Value, session_id: &str) -> Option<GeminiBlock> {
+    let feedback = &response["promptFeedback"];
+    let candidate = &response["candidates"][0];
+    let (reason, prompt_blocked, ratings) = if let Some(reason) = feedback["blockReason"].as_str() {
+        (reason.to_string(), true, parse_safety_ratings(&feedback["safetyRatings"]))
+    } else {
+        let reason = candidate["finishReason"].as_str()?;
+        if !GEMINI_BLOCK_FINISH_REASONS.contains(&reason) {
+            return None;
+        }
+        (reason.to_string(), false, parse_safety_ratings(&candidate["safetyRatings"]))
+    };
+
+    // Prefer the rating Gemini flagged; otherwise the most probable harm
+    let trigger = ratings
+        .iter()
+        .find(|rating| rating.blocked)
+        .or_else(|| ratings.iter().max_by_key(|rating| probability_rank(&rating.probability)))
+        .filter(|rating| rating.blocked || probability_rank(&rating.probability) > 1);
+
+    let subject = if prompt_blocked { "Your request" } else { "The response" };
+    let message = match (reason.as_str(), trigger) {
+        ("RECITATION", _) => format!("{} was blocked due to potential copyright concerns. Try asking in a different way.", subject),
+        (_, Some(rating)) => format!(
+            "{} was blocked by Gemini safety filters ({}, probability {}). Try rephrasing your request.",
+            subject,
+            rating.category.trim_start_matches("HARM_CATEGORY_").replace('_', " ").to_lowercase(),
+            rating.probability.to_lowercase()
+        ),
+        _ => format!("{} was blocked by Gemini ({}). Try rephrasing your request.", subject, reason),
+    };
+
+    Some(GeminiBlock {
+        session_id: session_id.to_string(),
+        reason,
+        prompt_blocked,
+        category: trigger.map(|rating| rating.category.clone()),
+        probability: trigger.map(|rating| rating.probability.clone()),
+        ratings: ratings.clone(),
+        message,
+        can_rephrase: true,
+    })
+}
+
+/// Build a prompt asking the model to pick up where a truncated answer stopped
+fn build_continuation_prompt(original_prompt: &str, partial_output: &str) -> String {
+    format!(
+        "Your previous answer to the request below was cut off by the output length limit.\n\n\
+         ## Original request\n{}\n\n\
+         ## Your answer so far\n{}\n\n\
+         Continue exactly where the answer stops. Do not repeat any text that was already written.",
+        original_prompt.trim(),
+        partial_output
+    )
+}
+
+/// Map a Gemini model name to the API endpoint that serves it
+fn mapped_gemini_endpoint(model: &str) -> Option<&'static str> {
+    let endpoint = match model {
+        // 2.5 models are GA under their own names
+        "gemini-2.5-pro" | "gemini-2.5-pro-exp" => "gemini-2.5-pro",
+        "gemini-2.5-flash" => "gemini-2.5-flash",
+        "gemini-2.5-flash-lite" => "gemini-2.5-flash-lite",
+        
+        // 2024 models - use correct API endpoints  
+        "gemini-2.0-pro-exp" => "gemini-2.0-flash-exp", // Pro experimental uses flash-exp endpoint
+        "gemini-2.0-flash" => "gemini-2.0-flash-exp", // Maps to experimental endpoint
+        "gemini-2.0-flash-exp" => "gemini-2.0-flash-exp", // Direct mapping
+        "gemini-2.0-flash-lite" => "gemini-2.0-flash-exp", // Uses flash-exp endpoint
+        
+        // Stable 1.5 models - officially supported
+        "gemini-1.5-pro" | "gemini-1.5-pro-002" => "gemini-1.5-pro",
+        "gemini-1.5-flash" | "gemini-1.5-flash-002" => "gemini-1.5-flash",
+        
+        // Legacy experimental models
+        "gemini-exp-1206" => "gemini-exp-1206",
+        
+        // Legacy models with fallbacks
+        "gemini-pro" => "gemini-1.5-flash", // Map to stable working model
+        "gemini-pro-vision" => "gemini-1.5-pro", // Map to stable working model
+        
+        _ => return None,
+    };
+    Some(endpoint)
+}
+
+/// Endpoint serving a model when the key can't reach the one it maps to
+fn gemini_endpoint_fallback(endpoint: &str) -> Option<&'static str> {
+    match endpoint {
+        "gemini-2.5-pro" => Some("gemini-1.5-pro"),
+        "gemini-2.5-flash" | "gemini-2.5-flash-lite" => Some("gemini-1.5-flash"),
+        _ => None,
+    }
+}
+
+/// What is known about which endpoints the configured key can reach
+///
+/// Until a probe lists the key's models every endpoint is assumed reachable;
+/// an endpoint that answers 404 is taken off either way.
+#[derive(Debug, Default)]
+pub(crate) struct GeminiEndpointTable {
+    /// Model ids the key listed, when a probe succeeded
+    reachable: Option<HashSet<String>>,
+    not_found: HashSet<String>,
+}
+
+/// The endpoint a model's requests go to
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GeminiEndpoint {
+    pub endpoint: &'static str,
+    /// The endpoint the model maps to, when it was unreachable and `endpoint` is its fallback
+    pub unreachable: Option<&'static str>,
+}
+
+impl GeminiEndpointTable {
+    fn is_reachable(&self, endpoint: &str) -> bool {
+        !self.not_found.contains(endpoint)
+            && self.reachable.as_ref().is_none_or(|reachable| reachable.contains(endpoint))
+    }
+
+    pub fn resolve(&self, model: &str) -> Option<GeminiEndpoint> {
+        let endpoint = mapped_gemini_endpoint(model)?;
+        match gemini_endpoint_fallback(endpoint) {
+            Some(fallback) if !self.is_reachable(endpoint) => {
+                Some(GeminiEndpoint { endpoint: fallback, unreachable: Some(endpoint) })
+            }
+            _ => Some(GeminiEndpoint { endpoint, unreachable: None }),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref GEMINI_ENDPOINTS: RwLock<GeminiEndpointTable> = RwLock::new(GeminiEndpointTable::default());
+}
+
+/// The endpoint serving `model` with the configured key, falling back from unreachable ones
+pub(super) fn resolve_gemini_endpoint(model: &str) -> Option<GeminiEndpoint> {
+    let resolved = match GEMINI_ENDPOINTS.read() {
+        Ok(table) => table.resolve(model),
+        Err(_) => GeminiEndpointTable::default().resolve(model),
+    };
+    if let Some(GeminiEndpoint { endpoint, unreachable: Some(unreachable) }) = &resolved {
+        log::warn!("Gemini endpoint {} is unreachable with this key, sending {} to {}", unreachable, model, endpoint);
+    }
+    resolved
+}
+
+/// The API endpoint serving `model`, see `resolve_gemini_endpoint`
+pub(super) fn gemini_model_endpoint(model: &str) -> Option<&'static str> {
+    resolve_gemini_endpoint(model).map(|resolved| resolved.endpoint)
+}
+
+fn mark_gemini_endpoint_not_found(endpoint: &str) {
+    if let Ok(mut table) = GEMINI_ENDPOINTS.write() {
+        table.not_found.insert(endpoint.to_string());
+    }
+}
+
+/// Forget what the previous key could reach
+fn reset_gemini_endpoints() {
+    if let Ok(mut table) = GEMINI_ENDPOINTS.write() {
+        *table = GeminiEndpointTable::default();
+    }
+}
+
+/// Ask Gemini which models the configured key can reach, so unreachable endpoints fall back
+///
+/// Run at startup and whenever the key changes. When the models can't be listed every
+/// endpoint stays assumed reachable.
+pub async fn probe_gemini_endpoints(app: tauri::AppHandle) {
+    let current_key = || get_gemini_api_key_sync(&app.state::<AgentDb>().lock_conn());
+    let api_key = match current_key() {
+        Ok(api_key) => api_key,
+        Err(e) => {
+            log::info!("Skipping Gemini endpoint probe: {}", e);
+            return;
+        }
+    };
+
+    let registry = super::gemini_universal::GeminiModelRegistry::new(api_key.clone());
+    match registry.discover_models().await {
+        // The key may have changed again while its models were listed
+        Ok(_) if current_key().ok().as_ref() != Some(&api_key) => {
+            log::info!("Gemini key changed during the endpoint probe; discarding its result");
+        }
+        Ok(models) if !models.is_empty() => {
+            let reachable: HashSet<String> = models.into_iter().map(|model| model.id).collect();
+            log::info!("Gemini key reaches {} models", reachable.len());
+            if let Ok(mut table) = GEMINI_ENDPOINTS.write() {
+                table.reachable = Some(reachable);
+            }
+        }
+        Ok(_) => log::warn!("Gemini listed no models; assuming every endpoint is reachable"),
+        Err(e) => log::info!("Skipping Gemini endpoint probe: {}", e),
+    }
+}
+
+/// Feed a Gemini response into the endpoint's adaptive delay and the provider's quota status
+fn record_gemini_pacing(
+    limiter: &super::gemini_performance::RateLimiter,
+    endpoint: &str,
+    response: &reqwest::Response,
+) {
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = super::provider_quota::retry_after_header(response.headers());
+        limiter.record_throttled(endpoint, retry_after);
+        super::provider_quota::PROVIDER_QUOTAS.record_throttled("gemini", retry_after);
+    } else if status.is_success() {
+        limiter.record_success(endpoint);
+        super::provider_quota::PROVIDER_QUOTAS.record_success("gemini");
+    }
+}
+
+/// Send a single non-streaming prompt to Gemini and return the response text
+///
+/// Used for internal helper calls (e.g. summarization) that don't belong to a chat session.
+pub(crate) async fn generate_gemini_text(
+    api_key: &str,
+    config: &GeminiConfig,
+    timeouts: &super::provider_timeouts::ProviderTimeout,
+    hooks: &super::request_hooks::RequestHooks,
+    model: &str,
+    prompt: &str,
+) -> Result<String, String> {
+    if api_key.trim().is_empty() {
+        return Err("Gemini API key is not configured".to_string());
+    }
+    let model = crate::models::resolve(model)?.id;
+    let endpoint = gemini_model_endpoint(model)
+        .ok_or_else(|| format!("Model '{}' is not supported", model))?;
+
+    let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", endpoint), api_key).await;
+    let limiter = super::gemini_backend::shared_rate_limiter();
+    let _permit = limiter.wait_turn(endpoint).await.map_err(|e| e.to_string())?;
+    let response = hooks
+        .prepare("gemini", timeouts.build_client(false)?.post(&url), build_gemini_request_body(prompt, config))?
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request to Gemini: {}", e))?;
+    record_gemini_pacing(&limiter, endpoint, &response);
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Gemini API returned error {}: {}", status, error_text));
+    }
+
+    let json = response.json::<serde_json::Value>().await
+        .map_err(|e| format!("Failed to parse Gemini response: {}", e))?;
+    let candidate = json["candidates"].get(0)
+        .ok_or("Response was blocked by safety filters")?;
+    inspect_finish_reason(candidate, "internal")?;
+
+    let text: String = candidate["content"]["parts"].as_array()
+        .map(|parts| parts.iter().filter_map(|part| part["text"].as_str()).collect())
+        .unwrap_or_default();
+    if text.trim().is_empty() {
+        return Err("Gemini returned an empty response".to_string());
+    }
+    Ok(text)
+}
+
 /// Verify a Gemini API key by making a test request
 #[tauri::command]
 pub async fn verify_gemini_api_key(
@@ -303,10 +965,7 @@ pub async fn verify_gemini_api_key(
     // Create a simple test request to verify the API key
     let client = reqwest::Client::new();
     
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash:generateContent?key={}",
-        api_key
-    );
+    let url = super::gemini_backend::gemini_url("v1beta/models/gemini-1.5-flash:generateContent", &api_key).await;
     
     let test_body = serde_json::json!({
         "contents": [{
@@ -365,7 +1024,7 @@ pub async fn test_gemini_events(
     let init_message_str = serde_json::to_string(&init_message).unwrap();
     
     // Only emit session-specific events for tests too
-    app_handle.emit(&format!("claude-output:{}", test_session_id), init_message_str)
+    emit_session_event(&app_handle, &format!("claude-output:{}", test_session_id), init_message_str)
         .map_err(|e| format!("Failed to emit test session-specific init event: {}", e))?;
     
     // Emit test message
@@ -398,43 +1057,513 @@ pub async fn test_gemini_events(
     
     let test_message_str = serde_json::to_string(&test_message).unwrap();
     
-    app_handle.emit(&format!("claude-output:{}", test_session_id), test_message_str)
+    emit_session_event(&app_handle, &format!("claude-output:{}", test_session_id), test_message_str)
         .map_err(|e| format!("Failed to emit test session-specific message event: {}", e))?;
     
     // Emit completion - session-specific only
-    app_handle.emit(&format!("claude-complete:{}", test_session_id), true)
+    emit_session_event(&app_handle, &format!("claude-complete:{}", test_session_id), true)
         .map_err(|e| format!("Failed to emit test session-specific complete event: {}", e))?;
     
     log::info!("Test events emitted successfully for session: {}", test_session_id);
     Ok(())
 }
 
-/// Execute Gemini model with proper session isolation and stop support
-#[tauri::command]
-pub async fn execute_gemini_code(
-    prompt: String,
-    model: String,
-    project_path: String,
+/// Send one generateContent request, turning HTTP and network failures into user-facing errors
+pub(super) async fn send_gemini_request(
     app_handle: tauri::AppHandle,
-    db: State<'_, AgentDb>,
-    _claude_state: State<'_, ClaudeProcessState>,
-    session_registry: State<'_, GeminiSessionRegistry>,
-    dedup_manager: State<'_, MessageDeduplicationManager>,
-    isolation_manager: State<'_, SessionIsolationManager>,
-    execution_state: State<'_, ExecutionControlState>,
-) -> Result<(), String> {
-    log::info!("Starting Gemini execution - model: {}, project: {}", model, project_path);
-    
-    // Validate inputs
-    let trimmed_prompt = prompt.trim();
-    if trimmed_prompt.is_empty() {
-        return Err("Prompt cannot be empty".to_string());
+    client: reqwest::Client,
+    url: String,
+    request_body: serde_json::Value,
+    session_id: String,
+    trimmed_model: String,
+    endpoint: String,
+    request_secs: u64,
+) -> Result<serde_json::Value, String> {
+    post_gemini_request(app_handle, client, url, request_body, session_id, trimmed_model, endpoint, request_secs)
+        .await?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Failed to parse Gemini response: {}", e))
+}
+
+/// A status message announcing that a rate-limited request will be retried
+fn gemini_retry_message(session_id: &str, attempt: u32, max_retries: u32, wait: std::time::Duration) -> serde_json::Value {
+    let retry_in_secs = wait.as_secs_f64().ceil() as u64;
+    serde_json::json!({
+        "type": "system",
+        "subtype": "retry",
+        "session_id": session_id,
+        "attempt": attempt,
+        "max_retries": max_retries,
+        "retry_in_secs": retry_in_secs,
+        "message": format!("Gemini rate limit reached, retrying in {}s ({} of {})", retry_in_secs, attempt, max_retries),
+        "timestamp": std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    })
+}
+
+/// Tell the session that `model` is being answered by `fallback` because `endpoint` was not found
+fn gemini_fallback_message(session_id: &str, model: &str, endpoint: &str, fallback: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "system",
+        "subtype": "endpoint_fallback",
+        "session_id": session_id,
+        "model": model,
+        "endpoint": fallback,
+        "unreachable_endpoint": endpoint,
+        "message": format!("Gemini endpoint '{}' was not found for this API key, answering '{}' with '{}'", endpoint, model, fallback),
+        "timestamp": std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    })
+}
+
+/// Point a `models/{endpoint}:method` request URL at `fallback` instead
+fn gemini_fallback_url(url: &str, endpoint: &str, fallback: &str) -> String {
+    url.replacen(&format!("models/{}:", endpoint), &format!("models/{}:", fallback), 1)
+}
+
+/// Sleep for `wait`, waking as soon as the session's execution is stopped; true when it was
+async fn wait_unless_stopped(app_handle: &tauri::AppHandle, session_id: &str, wait: std::time::Duration) -> bool {
+    let Some(execution_state) = app_handle.try_state::<ExecutionControlState>() else {
+        tokio::time::sleep(wait).await;
+        return false;
+    };
+    tokio::select! {
+        biased;
+        _ = execution_state.wait_until_stopped(session_id) => true,
+        _ = tokio::time::sleep(wait) => false,
+    }
+}
+
+/// Post a request to Gemini, returning the response once its status is a success
+async fn post_gemini_request(
+    app_handle: tauri::AppHandle,
+    client: reqwest::Client,
+    mut url: String,
+    request_body: serde_json::Value,
+    session_id: String,
+    trimmed_model: String,
+    mut endpoint: String,
+    request_secs: u64,
+) -> Result<reqwest::Response, String> {
+    let limiter = super::gemini_backend::shared_rate_limiter();
+    let hooks = request_hooks(&app_handle);
+    let backoff = super::error_tracker::load_quota_backoff(&app_handle.state::<AgentDb>().lock_conn());
+
+    // A 404 moves the request to the endpoint's fallback once
+    let mut fell_back = false;
+    let response = loop {
+        // Rate-limited requests are retried with backoff until the retries run out
+        let mut attempt = 0;
+        let response = loop {
+            let permit = limiter.acquire(&endpoint).await.map_err(|e| e.to_string())?;
+            // The endpoint's adaptive delay paces first attempts; a retry has already
+            // waited out its backoff, which covers the same throttle
+            let delay = limiter.current_delay(&endpoint);
+            if attempt == 0 && !delay.is_zero() {
+                log::info!("Waiting {:?} before calling throttled Gemini endpoint {}", delay, endpoint);
+                if wait_unless_stopped(&app_handle, &session_id, delay).await {
+                    log::info!("Execution stopped while waiting to call Gemini for session: {}", session_id);
+                    return Err("Execution was stopped".to_string());
+                }
+            }
+            let request = hooks.prepare("gemini", client.post(&url), request_body.clone())?;
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    log::error!("Failed to call Gemini API for session {}: {}", session_id, e);
+
+                    // Provide specific error messages based on error type
+                    let enhanced_error = if e.is_timeout() || e.to_string().contains("timeout") {
+                        format!("⏰ Gemini API Timeout\n\n• Request took longer than {}s to complete\n• Try again with a shorter prompt\n• Raise the Gemini request timeout in settings\n• Consider switching to a faster model like 'gemini-2.5-flash'", request_secs)
+                    } else if e.to_string().contains("dns") || e.to_string().contains("connection") {
+                        "🌐 Connection Error\n\n• Cannot reach Gemini API\n• Check your internet connection\n• Verify firewall settings\n• Try switching to Claude or Ollama models".to_string()
+                    } else {
+                        format!("🚫 Gemini API Error\n\n• {}", e)
+                    };
+
+                    return Err(enhanced_error);
+                }
+            };
+
+            log::info!("Gemini API response status: {} for session: {}", response.status(), session_id);
+            record_gemini_pacing(&limiter, &endpoint, &response);
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || attempt >= backoff.max_retries {
+                break response;
+            }
+
+            attempt += 1;
+            let wait = backoff.delay(attempt, super::provider_quota::retry_after_header(response.headers()));
+            log::warn!(
+                "Gemini rate limited session {}, retry {} of {} in {:?}",
+                session_id, attempt, backoff.max_retries, wait
+            );
+            let retry_message = gemini_retry_message(&session_id, attempt, backoff.max_retries, wait);
+            if let Err(e) = emit_session_event(&app_handle, &format!("claude-output:{}", session_id), retry_message.to_string()) {
+                log::warn!("Failed to emit retry status for session {}: {}", session_id, e);
+            }
+            // Other requests to the endpoint may go ahead while this one backs off
+            drop(response);
+            drop(permit);
+            if wait_unless_stopped(&app_handle, &session_id, wait).await {
+                log::info!("Execution stopped while waiting to retry session: {}", session_id);
+                return Err("Execution was stopped".to_string());
+            }
+        };
+
+        let fallback = gemini_endpoint_fallback(&endpoint)
+            .filter(|_| !fell_back && response.status() == reqwest::StatusCode::NOT_FOUND);
+        let Some(fallback) = fallback else {
+            break response;
+        };
+        mark_gemini_endpoint_not_found(&endpoint);
+        log::warn!(
+            "Gemini endpoint {} was not found for session {}, retrying on {}",
+            endpoint, session_id, fallback
+        );
+        let notice = gemini_fallback_message(&session_id, &trimmed_model, &endpoint, fallback);
+        if let Err(e) = emit_session_event(&app_handle, &format!("claude-output:{}", session_id), notice.to_string()) {
+            log::warn!("Failed to emit endpoint fallback for session {}: {}", session_id, e);
+        }
+        drop(response);
+        url = gemini_fallback_url(&url, &endpoint, fallback);
+        endpoint = fallback.to_string();
+        fell_back = true;
+    };
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+
+    // Enhanced error handling for different scenarios
+    let enhanced_error = if status == 400 && error_text.contains("model") {
+        format!("🤖 Unsupported Gemini Model\n\n• Model '{}' may not exist or be available\n• Try using 'gemini-2.5-flash' or 'gemini-2.5-pro'\n• Check Google AI Studio for available models\n• Use 'Auto' model selection for intelligent switching", trimmed_model)
+    } else if status == 429 && error_text.contains("quota") {
+        super::provider_quota::PROVIDER_QUOTAS.mark_quota_exhausted("gemini");
+        if error_text.contains("free_tier") {
+            "🔑 Gemini Free Tier Quota Exceeded\n\n• Your free tier quota has been exhausted\n• Solutions:\n  1. Wait for quota reset (24 hours)\n  2. Upgrade to paid tier\n  3. Switch to Claude models\n  4. Use Ollama (local models)\n\n💡 Tip: Use 'Auto' model selection for intelligent switching between providers".to_string()
+        } else {
+            "🔑 Gemini API Quota Exceeded\n\n• Rate limit or quota exceeded\n• Try again in a few minutes\n• Consider switching to Claude or Ollama models".to_string()
+        }
+    } else if status == 404 {
+        mark_gemini_endpoint_not_found(&endpoint);
+        format!("🤖 Gemini endpoint '{}' was not found for this API key", endpoint)
+    } else if status == 401 {
+        "🔑 Gemini API Authentication Failed\n\n• Check your API key in Settings\n• Ensure key starts with 'AIza'\n• Generate new key if needed".to_string()
+    } else if status == 403 {
+        "🚫 Gemini API Access Forbidden\n\n• API key may be invalid or restricted\n• Check Google Cloud Console permissions\n• Consider switching to Claude or Ollama".to_string()
+    } else {
+        format!("Gemini API error ({}): {}", status, error_text)
+    };
+
+    // Emit enhanced error message to frontend
+    let error_message = serde_json::json!({
+        "type": "system",
+        "subtype": "error",
+        "error": enhanced_error,
+        "error_code": status.as_u16(),
+        "is_quota_error": status == 429 && error_text.contains("quota"),
+        "timestamp": std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    });
+
+    // Emit session-specific error event ONLY to prevent cross-contamination
+    let error_message_str = serde_json::to_string(&error_message)
+        .map_err(|e| format!("Failed to serialize error message: {}", e))?;
+
+    emit_session_event(&app_handle, &format!("claude-error:{}", session_id), error_message_str)
+        .map_err(|e| format!("Failed to emit session-specific error: {}", e))?;
+
+    Err(enhanced_error)
+}
+
+/// Whether the user stopped a session's execution
+async fn execution_stopped(
+    sessions: &tokio::sync::Mutex<HashMap<String, ExecutionState>>,
+    session_id: &str,
+) -> bool {
+    sessions
+        .lock()
+        .await
+        .get(session_id)
+        .is_some_and(|session| session.status == ExecutionStatus::Stopped)
+}
+
+/// Read a streamed Gemini response, passing each text delta to `on_delta`
+///
+/// Reading ends as soon as the execution is stopped, even while waiting for the
+/// next chunk, which drops the connection.
+async fn read_gemini_stream(
+    response: reqwest::Response,
+    execution: &ExecutionControlState,
+    session_id: &str,
+    mut on_delta: impl FnMut(&str),
+) -> Result<serde_json::Value, String> {
+    let mut stream = response.bytes_stream();
+    let mut accumulator = GeminiStreamAccumulator::default();
+    let stopped = execution.wait_until_stopped(session_id);
+    tokio::pin!(stopped);
+    loop {
+        let chunk = tokio::select! {
+            _ = &mut stopped => {
+                log::info!("Execution stopped mid-stream for session: {}", session_id);
+                return Err("Execution was stopped".to_string());
+            }
+            chunk = stream.next() => chunk,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let bytes = chunk.map_err(|e| format!("Gemini stream was interrupted: {}", e))?;
+        for delta in accumulator.push(&bytes) {
+            on_delta(&delta);
+        }
+    }
+    if let Some(delta) = accumulator.flush() {
+        on_delta(&delta);
+    }
+    Ok(accumulator.into_response())
+}
+
+/// An incremental assistant message carrying one streamed text delta
+fn gemini_delta_message(session_id: &str, message_id: &str, model: &str, delta: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "assistant",
+        "subtype": "text",
+        "session_id": session_id,
+        "message": {
+            "id": message_id,
+            "type": "message",
+            "role": "assistant",
+            "model": model,
+            "content": [{
+                "type": "text",
+                "text": delta
+            }],
+            "stop_reason": null
+        }
+    })
+}
+
+/// A `functionCall` part from a Gemini response
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GeminiFunctionCall {
+    pub name: String,
+    pub args: serde_json::Value,
+}
+
+fn extract_function_calls(response: &serde_json::Value) -> Vec<GeminiFunctionCall> {
+    response["candidates"][0]["content"]["parts"]
+        .as_array()
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| {
+                    let call = part.get("functionCall")?;
+                    Some(GeminiFunctionCall {
+                        name: call["name"].as_str()?.to_string(),
+                        args: call.get("args").cloned().unwrap_or_else(|| serde_json::json!({})),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Send a request and, while Gemini answers with function calls, dispatch them and
+/// send the results back in a follow-up request
+async fn run_gemini_tool_rounds<S, SFut, D, DFut>(
+    mut request_body: serde_json::Value,
+    mut send: S,
+    mut dispatch: D,
+    max_rounds: usize,
+) -> Result<serde_json::Value, String>
+where
+    S: FnMut(serde_json::Value) -> SFut,
+    SFut: std::future::Future<Output = Result<serde_json::Value, String>>,
+    D: FnMut(GeminiFunctionCall) -> DFut,
+    DFut: std::future::Future<Output = serde_json::Value>,
+{
+    let mut response = send(request_body.clone()).await?;
+
+    for _ in 0..max_rounds {
+        let calls = extract_function_calls(&response);
+        if calls.is_empty() {
+            return Ok(response);
+        }
+
+        let mut function_responses = Vec::with_capacity(calls.len());
+        for call in calls {
+            let name = call.name.clone();
+            let result = dispatch(call).await;
+            function_responses.push(serde_json::json!({
+                "functionResponse": { "name": name, "response": result }
+            }));
+        }
+
+        let mut model_turn = response["candidates"][0]["content"].clone();
+        model_turn["role"] = serde_json::json!("model");
+        let contents = request_body["contents"]
+            .as_array_mut()
+            .ok_or("Gemini request has no contents")?;
+        contents.push(model_turn);
+        contents.push(serde_json::json!({ "role": "user", "parts": function_responses }));
+
+        response = send(request_body.clone()).await?;
+    }
+
+    if extract_function_calls(&response).is_empty() {
+        Ok(response)
+    } else {
+        Err(format!("Gemini was still calling tools after {} rounds", max_rounds))
+    }
+}
+
+/// Run one tool Gemini called, streaming the call and its result as Claude-style messages
+async fn dispatch_gemini_function_call(
+    app_handle: tauri::AppHandle,
+    bridge: Option<UniversalToolBridge>,
+    tool_name: Option<String>,
+    call: GeminiFunctionCall,
+    context: ToolContext,
+) -> serde_json::Value {
+    let session_id = context.session_id.clone();
+    let tool_use_id = format!("gemini-tool-{}", Uuid::new_v4());
+    let tool_use = serde_json::json!({
+        "type": "assistant",
+        "message": {
+            "role": "assistant",
+            "model": context.model_id,
+            "content": [{
+                "type": "tool_use",
+                "id": tool_use_id,
+                "name": tool_name.as_deref().unwrap_or(&call.name),
+                "input": call.args
+            }]
+        }
+    });
+    let _ = emit_session_event(&app_handle, &format!("claude-output:{}", session_id), tool_use.to_string());
+
+    let parameters: HashMap<String, serde_json::Value> = call
+        .args
+        .as_object()
+        .map(|args| args.clone().into_iter().collect())
+        .unwrap_or_default();
+    let outcome = match (bridge, tool_name) {
+        (Some(bridge), Some(tool_name)) => {
+            log::info!("Gemini called tool {} in session {}", tool_name, session_id);
+            bridge
+                .execute_function_call(tool_name, context.model_id.clone(), parameters, context)
+                .await
+                .and_then(|result| match result.error {
+                    Some(error) if !result.success => Err(error),
+                    _ => Ok(result.output),
+                })
+        }
+        (None, _) => Err("Tool bridge is not available".to_string()),
+        (_, None) => Err(format!("Unknown function: {}", call.name)),
+    };
+
+    let (content, is_error, response) = match outcome {
+        Ok(output) => (output.to_string(), false, serde_json::json!({ "output": output })),
+        Err(error) => {
+            log::warn!("Gemini tool call {} failed: {}", call.name, error);
+            (error.clone(), true, serde_json::json!({ "error": error }))
+        }
+    };
+    let tool_result = serde_json::json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": tool_use_id,
+                "content": content,
+                "is_error": is_error
+            }]
+        }
+    });
+    let _ = emit_session_event(&app_handle, &format!("claude-output:{}", session_id), tool_result.to_string());
+
+    response
+}
+
+/// Optional settings of an `execute_gemini_code` request; anything left out uses the stored config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GeminiExecuteOptions {
+    pub temperature: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+    pub top_k: Option<u32>,
+    pub top_p: Option<f32>,
+    /// Names of the tools offered to the model
+    pub tools: Option<Vec<String>>,
+    pub tags: Option<HashMap<String, String>>,
+    /// Strict unless set
+    pub dedup_mode: Option<DeduplicationMode>,
+    /// Paths of files whose contents are sent with the prompt
+    pub attachments: Option<Vec<String>>,
+    /// Earlier turns to send ahead of the prompt, instead of the session's own
+    pub conversation_history: Option<Vec<ConversationTurn>>,
+    /// Replaces the saved CLAUDE.md as the system instruction
+    pub system_prompt: Option<String>,
+    /// Base64 images sent with the prompt, as `data:` URLs or bare PNG data
+    pub images: Option<Vec<String>>,
+}
+
+/// Execute Gemini model with proper session isolation and stop support
+#[tauri::command]
+pub async fn execute_gemini_code(
+    prompt: String,
+    model: String,
+    project_path: String,
+    options: Option<GeminiExecuteOptions>,
+    app_handle: tauri::AppHandle,
+    db: State<'_, AgentDb>,
+    _claude_state: State<'_, ClaudeProcessState>,
+    session_registry: State<'_, GeminiSessionRegistry>,
+    dedup_manager: State<'_, MessageDeduplicationManager>,
+    isolation_manager: State<'_, SessionIsolationManager>,
+    execution_state: State<'_, ExecutionControlState>,
+) -> Result<(), String> {
+    log::info!("Starting Gemini execution - model: {}, project: {}", model, project_path);
+    let GeminiExecuteOptions {
+        temperature,
+        max_output_tokens,
+        top_k,
+        top_p,
+        tools,
+        tags,
+        dedup_mode,
+        attachments,
+        conversation_history,
+        system_prompt,
+        images,
+    } = options.unwrap_or_default();
+    
+    // Validate inputs
+    let trimmed_prompt = prompt.trim();
+    if trimmed_prompt.is_empty() {
+        return Err("Prompt cannot be empty".to_string());
     }
     
     let trimmed_model = model.trim();
     if trimmed_model.is_empty() {
         return Err("Model must be specified".to_string());
     }
+    // Accept aliases such as "gemini flash" by resolving them to the canonical id
+    let trimmed_model = crate::models::canonicalize(trimmed_model)
+        .filter(|m| m.provider == crate::models::ModelProvider::Gemini)
+        .map_or(trimmed_model, |m| m.id);
     
     let trimmed_project_path = project_path.trim();
     if trimmed_project_path.is_empty() {
@@ -446,17 +1575,81 @@ pub async fn execute_gemini_code(
         return Err(format!("Project path does not exist: {}", trimmed_project_path));
     }
     
-    // Get API key with better error handling
-    let api_key = {
-        let conn = db.0.lock()
-            .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
-        get_gemini_api_key_sync(&conn)?
+    // Attached files and line ranges go ahead of the prompt and count toward the context window
+    let prompt = super::file_attachments::attach_files(&prompt, trimmed_project_path, attachments.as_deref().unwrap_or_default())?;
+    let trimmed_prompt = prompt.trim();
+    super::context_guard::ensure_prompt_fits(&app_handle, trimmed_model, trimmed_prompt)?;
+
+    // A persona given with the request, otherwise the one saved for Claude in CLAUDE.md
+    let system_prompt = match system_prompt.filter(|text| !text.trim().is_empty()) {
+        Some(text) => text,
+        None => super::claude::get_system_prompt().await.unwrap_or_else(|e| {
+            log::warn!("Sending Gemini request without the saved system prompt: {}", e);
+            String::new()
+        }),
+    };
+    
+    let conversation_history = conversation_history.unwrap_or_default();
+    
+    // Get API key and persisted generation config with better error handling
+    let (api_key, stored_config, timeouts) = {
+        let conn = db.lock_conn();
+        let stored_config = load_gemini_config_sync(&conn).unwrap_or_else(|e| {
+            log::warn!("Ignoring stored Gemini config: {}", e);
+            GeminiConfig::default()
+        });
+        let timeouts = super::provider_timeouts::load_provider_timeouts(&conn).gemini;
+        (get_gemini_api_key_sync(&conn)?, stored_config, timeouts)
     };
     
+    // Per-request parameters win over the persisted defaults
+    let generation_config = stored_config.merged_with(&GeminiConfig {
+        temperature,
+        max_tokens: max_output_tokens,
+        top_k,
+        top_p,
+        ..Default::default()
+    });
+    generation_config.validate()?;
+    
     if api_key.is_empty() {
         return Err("Gemini API key is not configured. Please set your API key in Settings.".to_string());
     }
     
+    let benchmarks = {
+        let conn = db.lock_conn();
+        load_model_benchmarks(&conn)
+    };
+    
+    // Screenshots and other images go along with the prompt, to models that can see them
+    let image_parts = match images.as_deref() {
+        Some(images) if !images.is_empty() => {
+            match &benchmarks {
+                Ok(benchmarks) => check_vision_support(trimmed_model, benchmarks)?,
+                Err(e) => log::warn!("Sending images without checking vision support: {}", e),
+            }
+            gemini_image_parts(images)?
+        }
+        _ => Vec::new(),
+    };
+    
+    // Earlier turns of the caller's conversation, trimmed to the model's context window
+    let history_contents = match conversation_history.as_slice() {
+        history if !history.is_empty() => {
+            let window = match &benchmarks {
+                Ok(benchmarks) => context_window(trimmed_model, benchmarks),
+                Err(e) => {
+                    log::warn!("Sending full conversation history, context window unknown: {}", e);
+                    None
+                }
+            };
+            let reserved = u64::from(generation_config.max_tokens.unwrap_or(0));
+            Some(conversation_contents(history, trimmed_prompt, window, reserved)?)
+        }
+        _ => None,
+    };
+    let system_instruction = gemini_system_instruction(&system_prompt);
+    
     // Generate secure session ID with UUID + salt
     let project_id = std::path::Path::new(&trimmed_project_path)
         .file_name()
@@ -466,364 +1659,368 @@ pub async fn execute_gemini_code(
     
     let session_id = generate_secure_gemini_session_id(&project_id, &trimmed_model);
     
-    // Register session for isolation and deduplication
-    session_registry.register_session(&session_id, &project_id, &trimmed_model)?;
-    
-    // Create isolated session state
-    let _isolation_state = isolation_manager.create_isolated_session(
-        session_id.clone(),
-        project_id.clone(),
-        trimmed_model.to_string(),
-    );
+    // Register with the session registry, deduplication, isolation and execution control
+    let managers = SessionManagers {
+        registry: &session_registry,
+        dedup: &dedup_manager,
+        isolation: &isolation_manager,
+        execution: &execution_state,
+    };
+    managers.register(&session_id, &project_id, trimmed_model, dedup_mode.unwrap_or_default()).await?;
     
     log::info!("Created isolated Gemini session: {} for project: {}", session_id, project_id);
-    
-    // Register session with execution control for stop functionality
-    {
-        let mut sessions = execution_state.sessions.lock().await;
-        sessions.insert(session_id.clone(), super::execution_control::ExecutionState {
-            session_id: session_id.clone(),
-            status: ExecutionStatus::Executing,
-            can_continue: false,
-            checkpoint_data: None,
-            elapsed_time: 0,
-            total_tokens: 0,
-        });
+    if let Some(tags) = &tags {
+        super::ai_usage_tracker::tag_usage_session(&app_handle, &session_id, tags);
     }
-    
-    // Emit system:init event to match Claude's format
-    let init_message = serde_json::json!({
-        "type": "system",
-        "subtype": "init",
-        "session_id": session_id,
-        "model": trimmed_model,
-        "cwd": trimmed_project_path,
-        "tools": [],
-        "timestamp": std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    });
-    
-    // Emit session-specific init event ONLY to prevent cross-contamination
-    let init_message_str = serde_json::to_string(&init_message)
-        .map_err(|e| format!("Failed to serialize init message: {}", e))?;
-    
-    app_handle.emit(&format!("claude-output:{}", session_id), init_message_str)
-        .map_err(|e| format!("Failed to emit session-specific init event: {}", e))?;
-    
-    // Create HTTP client with timeout and retry settings
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(120)) // 2 minute timeout
-        .connect_timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-    
-    // Determine the correct model endpoint - Use proper API names for all supported models
-    let model_endpoint = match trimmed_model {
-        // 2025 models - use correct API endpoints
-        "gemini-2.5-pro" | "gemini-2.5-pro-exp" => "gemini-1.5-pro", // Currently uses 1.5 Pro endpoint  
-        "gemini-2.5-flash" => "gemini-1.5-flash", // Currently uses 1.5 Flash endpoint
-        "gemini-2.5-flash-lite" => "gemini-1.5-flash", // Currently uses 1.5 Flash endpoint
-        
-        // 2024 models - use correct API endpoints  
-        "gemini-2.0-pro-exp" => "gemini-2.0-flash-exp", // Pro experimental uses flash-exp endpoint
-        "gemini-2.0-flash" => "gemini-2.0-flash-exp", // Maps to experimental endpoint
-        "gemini-2.0-flash-exp" => "gemini-2.0-flash-exp", // Direct mapping
-        "gemini-2.0-flash-lite" => "gemini-2.0-flash-exp", // Uses flash-exp endpoint
-        
-        // Stable 1.5 models - officially supported
-        "gemini-1.5-pro" | "gemini-1.5-pro-002" => "gemini-1.5-pro",
-        "gemini-1.5-flash" | "gemini-1.5-flash-002" => "gemini-1.5-flash",
-        
-        // Legacy experimental models
-        "gemini-exp-1206" => "gemini-exp-1206",
-        
-        // Legacy models with fallbacks
-        "gemini-pro" => "gemini-1.5-flash", // Map to stable working model
-        "gemini-pro-vision" => "gemini-1.5-pro", // Map to stable working model
-        
-        _ => {
-            // Log the unsupported model and provide comprehensive error
-            log::warn!("Unsupported Gemini model requested: {}", trimmed_model);
-            return Err(format!(
-                "🤖 Model '{}' is not supported.\n\n✅ Supported models:\n• gemini-1.5-pro\n• gemini-2.5-flash\n• gemini-2.0-pro-exp\n• gemini-2.0-flash\n• gemini-2.0-flash-lite\n\n💡 Use 'Auto' selection for intelligent model switching", 
-                trimmed_model
-            ));
+    super::routing_decisions::note_session_model(&app_handle, &session_id, trimmed_model);
+    
+    // Every way out, including stops and errors, releases the session from all managers
+    let run_started = std::time::Instant::now();
+    let result: Result<(), String> = async {
+        // Universal tools Gemini may call, declared as functions
+        let tool_catalog = match (tools.as_deref(), app_handle.try_state::<UniversalToolBridge>()) {
+            (Some(names), Some(bridge)) if !names.is_empty() => {
+                Some(bridge.gemini_tool_catalog(names, trimmed_model).await)
+            }
+            (Some(names), None) if !names.is_empty() => {
+                log::warn!("Tool bridge not ready, running Gemini session {} without tools", session_id);
+                None
+            }
+            _ => None,
         }
-    };
-    
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model_endpoint,
-        api_key
-    );
-    
-    // Build request body with optimized parameters
-    let request_body = serde_json::json!({
-        "contents": [{
-            "parts": [{
-                "text": trimmed_prompt
-            }]
-        }],
-        "generationConfig": {
-            "temperature": 0.7,
-            "maxOutputTokens": 8192,
-            "topK": 40,
-            "topP": 0.95,
-            "stopSequences": [],
-            "candidateCount": 1
-        },
-        "safetySettings": [
-            {
-                "category": "HARM_CATEGORY_HARASSMENT",
-                "threshold": "BLOCK_ONLY_HIGH"
-            },
-            {
-                "category": "HARM_CATEGORY_HATE_SPEECH", 
-                "threshold": "BLOCK_ONLY_HIGH"
-            },
-            {
-                "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT",
-                "threshold": "BLOCK_ONLY_HIGH"
-            },
-            {
-                "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
-                "threshold": "BLOCK_ONLY_HIGH"
+        .filter(|catalog| !catalog.is_empty());
+
+        // Determine the correct model endpoint - Use proper API names for all supported models
+        let resolved_endpoint = match resolve_gemini_endpoint(trimmed_model) {
+            Some(endpoint) => endpoint,
+            None => {
+                // Log the unsupported model and provide comprehensive error
+                log::warn!("Unsupported Gemini model requested: {}", trimmed_model);
+                let suggestions = crate::models::suggestions(trimmed_model);
+                if !suggestions.is_empty() {
+                    return Err(format!(
+                        "🤖 Model '{}' is not supported. Did you mean '{}'?",
+                        trimmed_model,
+                        suggestions.join("' or '")
+                    ));
+                }
+                return Err(format!(
+                    "🤖 Model '{}' is not supported.\n\n✅ Supported models:\n• gemini-1.5-pro\n• gemini-2.5-flash\n• gemini-2.0-pro-exp\n• gemini-2.0-flash\n• gemini-2.0-flash-lite\n\n💡 Use 'Auto' selection for intelligent model switching", 
+                    trimmed_model
+                ));
             }
-        ]
-    });
-    
-    // Add adaptive delay based on model type to avoid rate limits
-    let delay_ms = match trimmed_model {
-        m if m.contains("2.5") => 500,  // Newer models may have better rate limits
-        m if m.contains("2.0") => 750,  // Moderate delay for 2.0 models
-        _ => 1000,                       // Conservative delay for older models
-    };
-    
-    tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
-    log::info!("Applied {}ms delay for model: {} in session: {}", delay_ms, trimmed_model, session_id);
+        };
+        let model_endpoint = resolved_endpoint.endpoint;
 
-    // Check if execution was stopped before sending request
-    {
-        let sessions = execution_state.sessions.lock().await;
-        if let Some(session) = sessions.get(&session_id) {
-            if session.status == ExecutionStatus::Stopped {
-                log::info!("Execution stopped before request for session: {}", session_id);
-                app_handle.emit(&format!("claude-complete:{}", session_id), false)
+        // Emit system:init event to match Claude's format
+        let init_message = serde_json::json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": session_id,
+            "model": trimmed_model,
+            // The REST model that serves the request, and the one it stands in for if any
+            "endpoint": model_endpoint,
+            "unreachable_endpoint": resolved_endpoint.unreachable,
+            "cwd": trimmed_project_path,
+            "tools": tool_catalog.as_ref().map(|catalog| catalog.offered_tools()).unwrap_or_default(),
+            "timestamp": std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        });
+
+        // Emit session-specific init event ONLY to prevent cross-contamination
+        let init_message_str = serde_json::to_string(&init_message)
+            .map_err(|e| format!("Failed to serialize init message: {}", e))?;
+
+        emit_session_event(&app_handle, &format!("claude-output:{}", session_id), init_message_str)
+            .map_err(|e| format!("Failed to emit session-specific init event: {}", e))?;
+
+        // Streaming: fail on a stalled stream rather than capping total generation time;
+        // the pooled client reuses a connection left open by a warm-up or earlier request
+        let client = timeouts.shared_client(true)?;
+
+        // Without alt=sse the stream arrives as one JSON array instead of SSE events
+        let url = format!(
+            "{}&alt=sse",
+            super::gemini_backend::gemini_url(&format!("v1beta/models/{}:streamGenerateContent", model_endpoint), &api_key).await
+        );
+
+        // Build request body with the configured generation parameters
+        let mut request_body = build_gemini_request_body(trimmed_prompt, &generation_config);
+        if let Some(contents) = history_contents {
+            log::info!("Sending {} conversation turn(s) with session: {}", contents.len(), session_id);
+            request_body["contents"] = serde_json::Value::Array(contents);
+        }
+        if !image_parts.is_empty() {
+            log::info!("Sending {} image(s) with session: {}", image_parts.len(), session_id);
+            attach_image_parts(&mut request_body, image_parts)?;
+        }
+        if let Some(instruction) = system_instruction {
+            request_body["systemInstruction"] = instruction;
+        }
+        if let Some(catalog) = &tool_catalog {
+            request_body["tools"] = catalog.tools_json();
+        }
+
+        // Check if execution was stopped before sending request
+        if execution_stopped(&execution_state.sessions, &session_id).await {
+            log::info!("Execution stopped before request for session: {}", session_id);
+            emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), false)
+                .map_err(|e| format!("Failed to emit stop complete event: {}", e))?;
+            return Ok(());
+        }
+
+        // Stream the request, letting Gemini call offered tools before it answers;
+        // every text delta goes out at once as part of one assistant message
+        log::info!("Sending request to Gemini API for session: {} with model: {} (endpoint: {})", session_id, trimmed_model, model_endpoint);
+        let message_id = format!("gemini-msg-{}", std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis());
+        let send = |body: serde_json::Value| {
+            let app_handle = app_handle.clone();
+            let client = client.clone();
+            let url = url.clone();
+            let session_id = session_id.clone();
+            let message_id = message_id.clone();
+            let execution = execution_state.inner().clone();
+            let request_secs = timeouts.request_secs;
+            async move {
+                // Tracked so a stop abandons the request and waits for it to let go
+                let request = async {
+                    let response = post_gemini_request(
+                        app_handle.clone(),
+                        client,
+                        url,
+                        body,
+                        session_id.clone(),
+                        trimmed_model.to_string(),
+                        model_endpoint.to_string(),
+                        request_secs,
+                    )
+                    .await?;
+                    read_gemini_stream(response, &execution, &session_id, |delta| {
+                        let chunk = gemini_delta_message(&session_id, &message_id, trimmed_model, delta);
+                        let _ = emit_session_event(&app_handle, &format!("claude-output:{}", session_id), chunk.to_string());
+                    })
+                    .await
+                };
+                execution
+                    .run_unless_stopped(&session_id, request)
+                    .await
+                    .unwrap_or_else(|| Err("Execution was stopped".to_string()))
+            }
+        };
+        let dispatch = |call: GeminiFunctionCall| {
+            let bridge = app_handle
+                .try_state::<UniversalToolBridge>()
+                .map(|bridge| bridge.inner().clone());
+            let tool_name = tool_catalog
+                .as_ref()
+                .and_then(|catalog| catalog.tool_name(&call.name))
+                .map(str::to_string);
+            let context = ToolContext {
+                session_id: session_id.clone(),
+                model_id: trimmed_model.to_string(),
+                project_path: trimmed_project_path.to_string(),
+                user_prompt: trimmed_prompt.to_string(),
+                system_context: None,
+                history: vec![],
+            };
+            dispatch_gemini_function_call(app_handle.clone(), bridge, tool_name, call, context)
+        };
+        let started = std::time::Instant::now();
+        let json = match run_gemini_tool_rounds(request_body, send, dispatch, generation_config.tool_rounds()).await {
+            Ok(json) => json,
+            Err(_) if execution_stopped(&execution_state.sessions, &session_id).await => {
+                emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), false)
                     .map_err(|e| format!("Failed to emit stop complete event: {}", e))?;
                 return Ok(());
             }
+            Err(e) => return Err(e),
+        };
+
+        // Explain blocks with the harm category instead of a generic error
+        if let Some(block) = detect_gemini_block(&json, &session_id) {
+            log::warn!(
+                "Gemini blocked session {}: {} ({:?}, {:?})",
+                session_id, block.reason, block.category, block.probability
+            );
+            super::gemini_monitoring::GEMINI_MONITOR.record_block(&block.reason, block.category.as_deref());
+            emit_session_event(&app_handle, &format!("claude-blocked:{}", session_id), &block)
+                .map_err(|e| format!("Failed to emit block details: {}", e))?;
+            return Err(block.message);
         }
-    }
 
-    // Send request
-    log::info!("Sending request to Gemini API for session: {} with model: {} (endpoint: {})", session_id, trimmed_model, model_endpoint);
-    match client.post(&url)
-        .json(&request_body)
-        .send()
-        .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            log::info!("Gemini API response status: {} for session: {}", status, session_id);
-            if status.is_success() {
-                match response.json::<serde_json::Value>().await {
-                    Ok(json) => {
-                        // Check for safety blocks first
-                        if let Some(candidates) = json["candidates"].as_array() {
-                            if candidates.is_empty() {
-                                return Err("Response was blocked by safety filters".to_string());
-                            }
-                            
-                            let candidate = &candidates[0];
-                            
-                            // Check finish reason for safety blocks and other issues
-                            if let Some(finish_reason) = candidate["finishReason"].as_str() {
-                                match finish_reason {
-                                    "SAFETY" => {
-                                        log::warn!("Gemini response blocked by safety filters for session: {}", session_id);
-                                        return Err("Response was blocked by Gemini safety filters. Try rephrasing your request.".to_string());
-                                    },
-                                    "RECITATION" => {
-                                        log::warn!("Gemini response blocked due to recitation for session: {}", session_id);
-                                        return Err("Response was blocked due to potential copyright concerns. Try asking in a different way.".to_string());
-                                    },
-                                    "OTHER" => {
-                                        log::warn!("Gemini response failed for unknown reasons for session: {}", session_id);
-                                        return Err("Response generation failed. This may be a temporary issue - please try again.".to_string());
-                                    },
-                                    "MAX_TOKENS" => {
-                                        log::info!("Gemini response hit max tokens limit for session: {}", session_id);
-                                        // This is not an error - the response was just truncated
-                                    },
-                                    "STOP" | "STOP_SEQUENCE" => {
-                                        log::info!("Gemini response completed normally for session: {}", session_id);
-                                        // Normal completion
-                                    },
-                                    _ => {
-                                        log::info!("Gemini response finished with reason: {} for session: {}", finish_reason, session_id);
-                                        // Continue with normal processing
-                                    }
-                                }
-                            }
-                            
-                            // Extract the response text with better error handling
-                            if let Some(content) = candidate["content"]["parts"][0]["text"].as_str() {
-                                // Check for duplicate content before processing
-                                if session_registry.is_duplicate_message(&session_id, content)? {
-                                    log::warn!("Duplicate response detected for session {}, skipping emission", session_id);
-                                    return Ok(());
-                                }
-                                
-                                // Additional deduplication check with manager
-                                let content_for_dedup = format!("gemini-response-{}", content);
-                                if !dedup_manager.is_duplicate(&session_id, &session_id, &content_for_dedup) {
-                                    log::info!("Content passed deduplication checks for session: {}", session_id);
-                                } else {
-                                    log::warn!("Content failed deduplication manager check for session: {}", session_id);
-                                    return Ok(());
-                                }
-                                // Get token usage if available
-                                let (input_tokens, output_tokens) = if let Some(usage) = json["usageMetadata"].as_object() {
-                                    let input = usage.get("promptTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                                    let output = usage.get("candidatesTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                                    (input, output)
-                                } else {
-                                    // Fallback to rough estimation if usage metadata not available
-                                    (trimmed_prompt.len() as u32 / 4, content.len() as u32 / 4)
-                                };
-                                
-                                // Emit the response as a Claude-compatible message
-                                let message = serde_json::json!({
-                                    "id": format!("gemini-msg-{}", std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_millis()),
-                                    "type": "assistant",
-                                    "message": {
-                                        "id": format!("gemini-msg-{}", std::time::SystemTime::now()
-                                            .duration_since(std::time::UNIX_EPOCH)
-                                            .unwrap()
-                                            .as_millis()),
-                                        "type": "message",
-                                        "role": "assistant",
-                                        "content": [{
-                                            "type": "text",
-                                            "text": content
-                                        }],
-                                        "model": trimmed_model,
-                                        "stop_reason": "end_turn",
-                                        "stop_sequence": null,
-                                        "usage": {
-                                            "input_tokens": input_tokens,
-                                            "output_tokens": output_tokens
-                                        }
-                                    }
-                                });
-                                
-                                // Emit session-specific event ONLY to prevent cross-contamination
-                                let message_str = serde_json::to_string(&message)
-                                    .map_err(|e| format!("Failed to serialize message: {}", e))?;
-                                
-                                // Only emit session-specific event to maintain isolation
-                                app_handle.emit(&format!("claude-output:{}", session_id), message_str.clone())
-                                    .map_err(|e| format!("Failed to emit session-specific message: {}", e))?;
-                                
-                                log::info!("Emitted Gemini response for session: {} (length: {})", session_id, content.len());
-                            } else {
-                                log::error!("No text content found in Gemini response for session: {}, candidate structure: {}", session_id, serde_json::to_string_pretty(&candidate).unwrap_or_default());
-                                return Err("No content found in Gemini API response. The model may have returned an empty response or the response structure is unexpected.".to_string());
-                            }
-                        } else {
-                            log::error!("No candidates found in Gemini response for session: {}, full response: {}", session_id, serde_json::to_string_pretty(&json).unwrap_or_default());
-                            return Err("No response candidates found. This may be due to safety filters or content policy restrictions. Try rephrasing your request.".to_string());
-                        }
-                    }
-                    Err(e) => return Err(format!("Failed to parse Gemini response: {}", e)),
+        // Check for safety blocks first
+        if let Some(candidates) = json["candidates"].as_array() {
+            if candidates.is_empty() {
+                return Err("Response was blocked by safety filters".to_string());
+            }
+
+            let candidate = &candidates[0];
+
+            // Check finish reason for safety blocks and other issues
+            let finish_state = inspect_finish_reason(candidate, &session_id)?;
+            if finish_state.truncated {
+                super::gemini_monitoring::GEMINI_MONITOR.record_truncation(trimmed_model);
+            }
+
+            // Extract the streamed text with better error handling
+            if let Some(content) = candidate["content"]["parts"][0]["text"].as_str() {
+                // Deltas are already out; only the whole answer is checked for duplicates
+                let duplicate = if session_registry.is_duplicate_message(&session_id, content)? {
+                    log::warn!("Duplicate response detected for session {}, skipping completion details", session_id);
+                    true
+                } else if dedup_manager.is_duplicate(&session_id, &session_id, &format!("gemini-response-{}", content)) {
+                    log::warn!("Content failed deduplication manager check for session: {}", session_id);
+                    true
+                } else {
+                    log::info!("Content passed deduplication checks for session: {}", session_id);
+                    false
+                };
+                if duplicate {
+                    emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), true)
+                        .map_err(|e| format!("Failed to emit session complete event: {}", e))?;
+                    return Ok(());
                 }
-            } else {
-                let status = response.status();
-                let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                
-                // Enhanced error handling for different scenarios
-                let enhanced_error = if status == 400 && error_text.contains("model") {
-                    format!("🤖 Unsupported Gemini Model\n\n• Model '{}' may not exist or be available\n• Try using 'gemini-2.5-flash' or 'gemini-2.5-pro'\n• Check Google AI Studio for available models\n• Use 'Auto' model selection for intelligent switching", trimmed_model)
-                } else if status == 429 && error_text.contains("quota") {
-                    if error_text.contains("free_tier") {
-                        "🔑 Gemini Free Tier Quota Exceeded\n\n• Your free tier quota has been exhausted\n• Solutions:\n  1. Wait for quota reset (24 hours)\n  2. Upgrade to paid tier\n  3. Switch to Claude models\n  4. Use Ollama (local models)\n\n💡 Tip: Use 'Auto' model selection for intelligent switching between providers".to_string()
-                    } else {
-                        "🔑 Gemini API Quota Exceeded\n\n• Rate limit or quota exceeded\n• Try again in a few minutes\n• Consider switching to Claude or Ollama models".to_string()
-                    }
-                } else if status == 401 {
-                    "🔑 Gemini API Authentication Failed\n\n• Check your API key in Settings\n• Ensure key starts with 'AIza'\n• Generate new key if needed".to_string()
-                } else if status == 403 {
-                    "🚫 Gemini API Access Forbidden\n\n• API key may be invalid or restricted\n• Check Google Cloud Console permissions\n• Consider switching to Claude or Ollama".to_string()
+                // Get token usage if available
+                let (input_tokens, output_tokens, cached_tokens, estimated) = if let Some(usage) = json["usageMetadata"].as_object() {
+                    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+                    (count("promptTokenCount"), count("candidatesTokenCount"), count("cachedContentTokenCount"), false)
                 } else {
-                    format!("Gemini API error ({}): {}", status, error_text)
+                    // Fallback to rough estimation if usage metadata not available
+                    (trimmed_prompt.len() as u32 / 4, content.len() as u32 / 4, 0, true)
                 };
-                
-                // Emit enhanced error message to frontend
-                let error_message = serde_json::json!({
-                    "type": "system",
-                    "subtype": "error",
-                    "error": enhanced_error,
-                    "error_code": status.as_u16(),
-                    "is_quota_error": status == 429 && error_text.contains("quota"),
-                    "timestamp": std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs()
+
+                // Close the streamed message with its whole text, stop reason and usage; it
+                // replaces the deltas streamed under the same message id
+                let message = serde_json::json!({
+                    "id": message_id,
+                    "type": "assistant",
+                    "subtype": "complete",
+                    "session_id": session_id,
+                    "message": {
+                        "id": message_id,
+                        "type": "message",
+                        "role": "assistant",
+                        "content": [{
+                            "type": "text",
+                            "text": content
+                        }],
+                        "model": trimmed_model,
+                        "stop_reason": if finish_state.truncated { "max_tokens" } else { "end_turn" },
+                        "stop_sequence": null,
+                        "usage": {
+                            "input_tokens": input_tokens,
+                            "output_tokens": output_tokens
+                        }
+                    },
+                    "truncated": finish_state.truncated
                 });
-                
-                // Emit session-specific error event ONLY to prevent cross-contamination
-                let error_message_str = serde_json::to_string(&error_message)
-                    .map_err(|e| format!("Failed to serialize error message: {}", e))?;
-                
-                app_handle.emit(&format!("claude-error:{}", session_id), error_message_str)
-                    .map_err(|e| format!("Failed to emit session-specific error: {}", e))?;
-                
-                return Err(enhanced_error);
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to call Gemini API for session {}: {}", session_id, e);
-            
-            // Provide specific error messages based on error type
-            let enhanced_error = if e.to_string().contains("timeout") {
-                "⏰ Gemini API Timeout\n\n• Request took too long to process\n• Try again with a shorter prompt\n• Check your internet connection\n• Consider switching to a faster model like 'gemini-2.5-flash'".to_string()
-            } else if e.to_string().contains("dns") || e.to_string().contains("connection") {
-                "🌐 Connection Error\n\n• Cannot reach Gemini API\n• Check your internet connection\n• Verify firewall settings\n• Try switching to Claude or Ollama models".to_string()
+
+                // Emit session-specific event ONLY to prevent cross-contamination
+                let message_str = serde_json::to_string(&message)
+                    .map_err(|e| format!("Failed to serialize message: {}", e))?;
+
+                // Only emit session-specific event to maintain isolation
+                emit_session_event(&app_handle, &format!("claude-output:{}", session_id), message_str.clone())
+                    .map_err(|e| format!("Failed to emit session-specific message: {}", e))?;
+
+                log::info!("Streamed Gemini response for session: {} (length: {})", session_id, content.len());
+
+                let mut usage = AIUsageEvent {
+                    project_id: trimmed_project_path.to_string(),
+                    model_name: trimmed_model.to_string(),
+                    agent_type: None,
+                    mcp_server: None,
+                    token_count: i64::from(input_tokens + output_tokens),
+                    request_type: "completion".to_string(),
+                    response_time_ms: Some(started.elapsed().as_millis() as i64),
+                    success: true,
+                    error_message: None,
+                    session_id: Some(session_id.clone()),
+                    user_prompt_tokens: Some(i64::from(input_tokens)),
+                    assistant_response_tokens: Some(i64::from(output_tokens)),
+                    cached_tokens: Some(i64::from(cached_tokens)),
+                    estimated,
+                    timestamp: chrono::Utc::now().timestamp(),
+                    tags: None,
+                };
+                if let Err(e) = track_turn_usage(&app_handle, &mut usage) {
+                    log::warn!("Failed to record Gemini turn usage: {}", e);
+                }
+
+                // Structured completion details so the UI can offer "continue" on truncation
+                let completion = build_completion_payload(&session_id, &finish_state);
+                app_handle.emit(&format!("gemini-completion:{}", session_id), completion)
+                    .map_err(|e| format!("Failed to emit completion details: {}", e))?;
             } else {
-                format!("🚫 Gemini API Error\n\n• {}", e)
-            };
-            
-            return Err(enhanced_error);
+                log::error!("No text content found in Gemini response for session: {}, candidate structure: {}", session_id, serde_json::to_string_pretty(&candidate).unwrap_or_default());
+                return Err("No content found in Gemini API response. The model may have returned an empty response or the response structure is unexpected.".to_string());
+            }
+        } else {
+            log::error!("No candidates found in Gemini response for session: {}, full response: {}", session_id, serde_json::to_string_pretty(&json).unwrap_or_default());
+            return Err("No response candidates found. This may be due to safety filters or content policy restrictions. Try rephrasing your request.".to_string());
         }
+
+        // Emit session-specific completion event ONLY to prevent cross-contamination
+        emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), true)
+            .map_err(|e| format!("Failed to emit session complete event: {}", e))?;
+
+        Ok(())
     }
+    .await;
+    managers.release(&session_id).await;
+    super::routing_decisions::record_session_outcome(
+        &app_handle,
+        &session_id,
+        result.is_ok(),
+        None,
+        Some(run_started.elapsed().as_millis() as i64),
+    );
     
-    // Emit session-specific completion event ONLY to prevent cross-contamination
-    app_handle.emit(&format!("claude-complete:{}", session_id), true)
-        .map_err(|e| format!("Failed to emit session complete event: {}", e))?;
-    
-    // Clean up session deduplication data
-    dedup_manager.clear_session(&session_id);
-    
-    // Unregister session from registry
-    session_registry.unregister_session(&session_id);
-    
-    // Cleanup isolation manager
-    isolation_manager.cleanup_session(&session_id);
-    
-    // Clean up execution state
-    {
-        let mut sessions = execution_state.sessions.lock().await;
-        sessions.remove(&session_id);
+    if result.is_ok() {
+        log::info!("Gemini execution completed successfully for session: {}", session_id);
+    }
+    result
+}
+
+/// Continue a response that was truncated by the output token limit
+#[tauri::command]
+pub async fn continue_gemini_response(
+    original_prompt: String,
+    partial_output: String,
+    model: String,
+    project_path: String,
+    max_output_tokens: Option<u32>,
+    app_handle: tauri::AppHandle,
+    db: State<'_, AgentDb>,
+    claude_state: State<'_, ClaudeProcessState>,
+    session_registry: State<'_, GeminiSessionRegistry>,
+    dedup_manager: State<'_, MessageDeduplicationManager>,
+    isolation_manager: State<'_, SessionIsolationManager>,
+    execution_state: State<'_, ExecutionControlState>,
+) -> Result<(), String> {
+    if partial_output.trim().is_empty() {
+        return Err("There is no partial output to continue from".to_string());
     }
     
-    log::info!("Gemini execution completed successfully for session: {}", session_id);
-    
-    Ok(())
+    let prompt = build_continuation_prompt(&original_prompt, &partial_output);
+    execute_gemini_code(
+        prompt,
+        model,
+        project_path,
+        Some(GeminiExecuteOptions { max_output_tokens, ..Default::default() }),
+        app_handle,
+        db,
+        claude_state,
+        session_registry,
+        dedup_manager,
+        isolation_manager,
+        execution_state,
+    ).await
 }
 
 /// Create a secure Gemini session with proper isolation
@@ -832,13 +2029,17 @@ pub async fn create_secure_gemini_session(
     project_id: String,
     project_path: String,
     model: String,
+    dedup_mode: Option<DeduplicationMode>,
     session_registry: State<'_, GeminiSessionRegistry>,
+    dedup_manager: State<'_, MessageDeduplicationManager>,
     isolation_manager: State<'_, SessionIsolationManager>,
 ) -> Result<String, String> {
     let session_id = generate_secure_gemini_session_id(&project_id, &model);
     
     // Register in session registry
-    session_registry.register_session(&session_id, &project_id, &model)?;
+    let dedup_mode = dedup_mode.unwrap_or_default();
+    session_registry.register_session_with_mode(&session_id, &project_id, &model, dedup_mode)?;
+    dedup_manager.set_session_mode(&session_id, dedup_mode);
     
     // Create isolation state
     let _isolation_state = isolation_manager.create_isolated_session(
@@ -860,18 +2061,19 @@ pub async fn cleanup_gemini_session(
     session_registry: State<'_, GeminiSessionRegistry>,
     dedup_manager: State<'_, MessageDeduplicationManager>,
     isolation_manager: State<'_, SessionIsolationManager>,
+    execution_state: State<'_, ExecutionControlState>,
 ) -> Result<(), String> {
     // Validate session exists
     session_registry.validate_session(&session_id)?;
     
-    // Clean up deduplication data
-    dedup_manager.clear_session(&session_id);
-    
-    // Unregister from session registry
-    session_registry.unregister_session(&session_id);
-    
-    // Clean up isolation manager
-    isolation_manager.cleanup_session(&session_id);
+    SessionManagers {
+        registry: &session_registry,
+        dedup: &dedup_manager,
+        isolation: &isolation_manager,
+        execution: &execution_state,
+    }
+    .release(&session_id)
+    .await;
     
     log::info!("Cleaned up Gemini session: {}", session_id);
     Ok(())
@@ -990,4 +2192,401 @@ pub async fn cleanup_old_gemini_sessions(
     session_registry.cleanup_old_sessions(age_limit);
     log::info!("Cleaned up Gemini sessions older than {} minutes", age_limit);
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_temperature_in_request_body() {
+        let config = GeminiConfig {
+            temperature: Some(1.3),
+            ..Default::default()
+        };
+        let body = build_gemini_request_body("Hello", &config);
+        let temperature = body["generationConfig"]["temperature"].as_f64().unwrap();
+        assert!((temperature - 1.3).abs() < 1e-6);
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], DEFAULT_GEMINI_MAX_TOKENS);
+    }
+
+    #[test]
+    fn test_blank_system_prompt_is_omitted() {
+        assert_eq!(gemini_system_instruction(""), None);
+        assert_eq!(gemini_system_instruction(" \n\t "), None);
+        assert_eq!(
+            gemini_system_instruction("\nYou are a careful Rust reviewer.\n"),
+            Some(serde_json::json!({ "parts": [{ "text": "You are a careful Rust reviewer." }] }))
+        );
+    }
+
+    #[test]
+    fn test_images_become_inline_data_parts() {
+        let png = "data:image/png;base64,iVBORw0KGgo=";
+        let jpeg = "data:image/jpg;base64,/9j/4AAQ";
+        let mut body = build_gemini_request_body("What is in this screenshot?", &GeminiConfig::default());
+        attach_image_parts(&mut body, gemini_image_parts(&[png.to_string(), jpeg.to_string()]).unwrap()).unwrap();
+
+        let parts = body["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0]["text"], "What is in this screenshot?");
+        assert_eq!(parts[1], serde_json::json!({ "inlineData": { "mimeType": "image/png", "data": "iVBORw0KGgo=" } }));
+        assert_eq!(parts[2]["inlineData"]["mimeType"], "image/jpeg");
+
+        let error = gemini_image_parts(&[png.to_string(), "data:image/svg+xml;base64,PHN2Zz4=".to_string()]).unwrap_err();
+        assert!(error.starts_with("Image 2: Gemini doesn't accept image/svg+xml images"), "{}", error);
+        assert!(gemini_image_parts(&["data:image/png;base64,not base64!".to_string()]).is_err());
+
+        let mut benchmarks = crate::commands::intelligent_routing::load_model_benchmarks(
+            &rusqlite::Connection::open_in_memory().unwrap(),
+        )
+        .unwrap();
+        assert!(check_vision_support("gemini-2.0-flash-lite", &benchmarks).is_ok());
+        assert!(check_vision_support("my-custom-model", &benchmarks).is_ok());
+        benchmarks
+            .iter_mut()
+            .find(|b| b.model_id == "gemini-2.0-flash-lite")
+            .unwrap()
+            .supports_vision = false;
+        assert_eq!(
+            check_vision_support("gemini-2.0-flash-lite", &benchmarks).unwrap_err(),
+            "Model gemini-2.0-flash-lite doesn't accept images; try gemini-1.5-pro, which does"
+        );
+    }
+
+    #[test]
+    fn test_unreachable_endpoints_fall_back_explicitly() {
+        let mut table = GeminiEndpointTable::default();
+        // Before a probe, 2.5 models go to their own endpoints
+        assert_eq!(
+            table.resolve("gemini-2.5-pro"),
+            Some(GeminiEndpoint { endpoint: "gemini-2.5-pro", unreachable: None })
+        );
+
+        table.reachable = Some(["gemini-2.5-flash", "gemini-1.5-pro"].iter().map(|id| id.to_string()).collect());
+        assert_eq!(
+            table.resolve("gemini-2.5-pro"),
+            Some(GeminiEndpoint { endpoint: "gemini-1.5-pro", unreachable: Some("gemini-2.5-pro") })
+        );
+        assert_eq!(table.resolve("gemini-2.5-flash").unwrap().endpoint, "gemini-2.5-flash");
+
+        // A 404 takes an endpoint off even when the key listed it
+        table.not_found.insert("gemini-2.5-flash".to_string());
+        assert_eq!(
+            table.resolve("gemini-2.5-flash"),
+            Some(GeminiEndpoint { endpoint: "gemini-1.5-flash", unreachable: Some("gemini-2.5-flash") })
+        );
+        assert_eq!(table.resolve("gemini-1.5-pro").unwrap().endpoint, "gemini-1.5-pro");
+        assert!(table.resolve("gpt-4").is_none());
+    }
+
+    #[test]
+    fn test_fallback_url_swaps_only_the_model_segment() {
+        let url = "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-pro:streamGenerateContent?key=k&alt=sse";
+        assert_eq!(
+            gemini_fallback_url(url, "gemini-2.5-pro", "gemini-1.5-pro"),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-pro:streamGenerateContent?key=k&alt=sse"
+        );
+        // The flash-lite endpoint must not be matched by its flash prefix
+        let lite = "https://example.test/v1beta/models/gemini-2.5-flash-lite:generateContent";
+        assert_eq!(gemini_fallback_url(lite, "gemini-2.5-flash", "gemini-1.5-flash"), lite);
+    }
+
+    #[test]
+    fn test_request_overrides_stored_config() {
+        let stored = GeminiConfig {
+            temperature: Some(0.2),
+            max_tokens: Some(1024),
+            ..Default::default()
+        };
+        let merged = stored.merged_with(&GeminiConfig {
+            temperature: Some(0.9),
+            ..Default::default()
+        });
+        assert_eq!(merged.temperature, Some(0.9));
+        assert_eq!(merged.max_tokens, Some(1024));
+    }
+
+    #[test]
+    fn test_max_tokens_response_sets_truncation_flag() {
+        let response = serde_json::json!({
+            "candidates": [{
+                "content": { "parts": [{ "text": "The answer starts here and then" }] },
+                "finishReason": "MAX_TOKENS"
+            }]
+        });
+        let state = inspect_finish_reason(&response["candidates"][0], "test-session").unwrap();
+        assert!(state.truncated);
+        assert_eq!(state.finish_reason.as_deref(), Some("MAX_TOKENS"));
+
+        let payload = build_completion_payload("test-session", &state);
+        assert_eq!(payload["truncated"], true);
+        assert_eq!(payload["can_continue"], true);
+    }
+
+    #[test]
+    fn test_normal_stop_is_not_truncated() {
+        let candidate = serde_json::json!({ "finishReason": "STOP" });
+        let state = inspect_finish_reason(&candidate, "test-session").unwrap();
+        assert!(!state.truncated);
+
+        let blocked = serde_json::json!({ "finishReason": "SAFETY" });
+        assert!(inspect_finish_reason(&blocked, "test-session").is_err());
+    }
+
+    #[test]
+    fn test_safety_block_surfaces_category() {
+        let response = serde_json::json!({
+            "candidates": [{
+                "finishReason": "SAFETY",
+                "safetyRatings": [
+                    { "category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE" },
+                    { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true },
+                    { "category": "HARM_CATEGORY_HATE_SPEECH", "probability": "LOW" }
+                ]
+            }]
+        });
+        let block = detect_gemini_block(&response, "s1").unwrap();
+        assert_eq!(block.reason, "SAFETY");
+        assert!(!block.prompt_blocked);
+        assert_eq!(block.category.as_deref(), Some("HARM_CATEGORY_DANGEROUS_CONTENT"));
+        assert_eq!(block.probability.as_deref(), Some("HIGH"));
+        assert_eq!(block.ratings.len(), 3);
+        assert!(block.message.contains("dangerous content"), "{}", block.message);
+
+        let prompt_blocked = serde_json::json!({
+            "promptFeedback": {
+                "blockReason": "SAFETY",
+                "safetyRatings": [{ "category": "HARM_CATEGORY_HARASSMENT", "probability": "MEDIUM" }]
+            }
+        });
+        let block = detect_gemini_block(&prompt_blocked, "s2").unwrap();
+        assert!(block.prompt_blocked);
+        assert_eq!(block.category.as_deref(), Some("HARM_CATEGORY_HARASSMENT"));
+
+        let normal = serde_json::json!({ "candidates": [{ "finishReason": "STOP" }] });
+        assert!(detect_gemini_block(&normal, "s3").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_function_call_is_dispatched_and_answered() {
+        use crate::adapters::tool_bridge::GeminiToolCatalog;
+        use std::collections::VecDeque;
+
+        let mut catalog = GeminiToolCatalog::default();
+        catalog.push(
+            "mcp_docs:search",
+            "Execute MCP server: docs:search",
+            &serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": {"type": "string"},
+                    "env": {"type": "object"}
+                },
+                "required": ["command", "env"]
+            }),
+        );
+        let mut body = build_gemini_request_body("Find the setup docs", &GeminiConfig::default());
+        body["tools"] = catalog.tools_json();
+        let declaration = &body["tools"][0]["functionDeclarations"][0];
+        assert_eq!(declaration["name"], "mcp_docs_search");
+        // Gemini rejects object schemas without properties
+        assert!(declaration["parameters"]["properties"].get("env").is_none());
+        assert_eq!(declaration["parameters"]["required"], serde_json::json!(["command"]));
+
+        // Mocked Gemini: first a function call, then a text answer
+        let responses = Mutex::new(VecDeque::from(vec![
+            serde_json::json!({"candidates": [{"content": {"role": "model", "parts": [
+                {"functionCall": {"name": "mcp_docs_search", "args": {"command": "setup"}}}
+            ]}}]}),
+            serde_json::json!({"candidates": [{"content": {"role": "model", "parts": [
+                {"text": "Run the installer."}
+            ]}, "finishReason": "STOP"}]}),
+        ]));
+        let sent = Mutex::new(Vec::new());
+        let dispatched = Mutex::new(Vec::new());
+
+        let send = |body: serde_json::Value| {
+            sent.lock().unwrap().push(body);
+            let response = responses.lock().unwrap().pop_front().unwrap();
+            async move { Ok(response) }
+        };
+        let dispatch = |call: GeminiFunctionCall| {
+            dispatched
+                .lock()
+                .unwrap()
+                .push((catalog.tool_name(&call.name).map(str::to_string), call.args));
+            async { serde_json::json!({"output": "docs/setup.md"}) }
+        };
+        let answer = run_gemini_tool_rounds(body, send, dispatch, GeminiConfig::default().tool_rounds()).await.unwrap();
+
+        assert_eq!(answer["candidates"][0]["content"]["parts"][0]["text"], "Run the installer.");
+        assert_eq!(
+            *dispatched.lock().unwrap(),
+            vec![(Some("mcp_docs:search".to_string()), serde_json::json!({"command": "setup"}))]
+        );
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        let contents = sent[1]["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        let function_response = &contents[2]["parts"][0]["functionResponse"];
+        assert_eq!(function_response["name"], "mcp_docs_search");
+        assert_eq!(function_response["response"]["output"], "docs/setup.md");
+
+        // A model that keeps calling tools is cut off at the configured round limit
+        let config = GeminiConfig::default().merged_with(&GeminiConfig { max_tool_rounds: Some(2), ..Default::default() });
+        let requests = Mutex::new(0);
+        let calling = |_body: serde_json::Value| {
+            *requests.lock().unwrap() += 1;
+            async {
+                Ok(serde_json::json!({"candidates": [{"content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "mcp_docs_search", "args": {}}}
+                ]}}]}))
+            }
+        };
+        let answer = |_call: GeminiFunctionCall| async { serde_json::json!({"output": ""}) };
+        let error = run_gemini_tool_rounds(build_gemini_request_body("Loop", &config), calling, answer, config.tool_rounds())
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Gemini was still calling tools after 2 rounds");
+        assert_eq!(*requests.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_generation_config_validation() {
+        let too_hot = GeminiConfig { temperature: Some(2.5), ..Default::default() };
+        assert!(too_hot.validate().is_err());
+        let bad_top_p = GeminiConfig { top_p: Some(1.5), ..Default::default() };
+        assert!(bad_top_p.validate().is_err());
+        let no_tool_rounds = GeminiConfig { max_tool_rounds: Some(0), ..Default::default() };
+        assert!(no_tool_rounds.validate().is_err());
+        assert!(GeminiConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_deduplication_modes_in_registry() {
+        let registry = GeminiSessionRegistry::new();
+        let content = "What does this function do?";
+        let start = 1_000_000;
+        let later = start + LENIENT_WINDOW_MS * 10;
+
+        registry.register_session("strict", "project", "gemini-2.5-flash").unwrap();
+        registry
+            .register_session_with_mode("lenient", "project", "gemini-2.5-flash", DeduplicationMode::Lenient)
+            .unwrap();
+        registry
+            .register_session_with_mode("off", "project", "gemini-2.5-flash", DeduplicationMode::Off)
+            .unwrap();
+
+        // Strict drops repeated content however far apart
+        assert!(!registry.is_duplicate_message_at("strict", content, start).unwrap());
+        assert!(registry.is_duplicate_message_at("strict", content, later).unwrap());
+
+        // Lenient only drops it within the window
+        assert!(!registry.is_duplicate_message_at("lenient", content, start).unwrap());
+        assert!(registry.is_duplicate_message_at("lenient", content, start + 100).unwrap());
+        assert!(!registry.is_duplicate_message_at("lenient", content, later).unwrap());
+
+        // Off never drops it
+        assert!(!registry.is_duplicate_message_at("off", content, start).unwrap());
+        assert!(!registry.is_duplicate_message_at("off", content, start).unwrap());
+    }
+
+    #[test]
+    fn test_stream_chunks_assemble_into_one_response() {
+        let events = concat!(
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Hel\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"lo, world\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"!\"}]},\"finishReason\":\"STOP\"}],",
+            "\"usageMetadata\":{\"promptTokenCount\":4,\"candidatesTokenCount\":3}}"
+        );
+        // Network chunks split events mid-line
+        let mut accumulator = GeminiStreamAccumulator::default();
+        let mut deltas = Vec::new();
+        for chunk in events.as_bytes().chunks(23) {
+            deltas.extend(accumulator.push(chunk));
+        }
+        assert_eq!(deltas, vec!["Hel", "lo, world"]);
+        // The last event has no line ending; it is still emitted when the stream closes
+        assert_eq!(accumulator.flush().as_deref(), Some("!"));
+        let response = accumulator.into_response();
+
+        let candidate = &response["candidates"][0];
+        assert_eq!(candidate["content"]["parts"][0]["text"], "Hello, world!");
+        assert_eq!(inspect_finish_reason(candidate, "s1").unwrap().finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(response["usageMetadata"]["candidatesTokenCount"], 3);
+
+        // Only the whole answer is checked, so a repeated partial doesn't count
+        let registry = GeminiSessionRegistry::new();
+        registry.register_session_with_mode("s1", "project", "gemini-2.5-flash", DeduplicationMode::Strict).unwrap();
+        assert!(!registry.is_duplicate_message("s1", "Hel").unwrap());
+        assert!(!registry.is_duplicate_message("s1", "Hello, world!").unwrap());
+    }
+
+    #[test]
+    fn test_streamed_function_call_and_block_survive_assembly() {
+        let mut accumulator = GeminiStreamAccumulator::default();
+        accumulator.push(b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"functionCall\":{\"name\":\"read_file\",\"args\":{\"path\":\"a.rs\"}}}]}}]}\n");
+        let calls = extract_function_calls(&accumulator.into_response());
+        assert_eq!(calls, vec![GeminiFunctionCall { name: "read_file".to_string(), args: serde_json::json!({ "path": "a.rs" }) }]);
+
+        let mut accumulator = GeminiStreamAccumulator::default();
+        accumulator.push(b"data: {\"promptFeedback\":{\"blockReason\":\"SAFETY\"}}\n");
+        let response = accumulator.into_response();
+        assert_eq!(response["candidates"], serde_json::json!([]));
+        assert!(detect_gemini_block(&response, "s1").is_some_and(|block| block.prompt_blocked));
+    }
+
+    #[tokio::test]
+    async fn test_stop_aborts_an_open_stream() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Sends one event, then holds the stream open
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let event = "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Partial\"}]}}]}\n\n";
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n";
+            let chunk = format!("{}{:x}\r\n{}\r\n", head, event.len(), event);
+            socket.write_all(chunk.as_bytes()).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        });
+
+        let execution = ExecutionControlState::default();
+        let response = reqwest::Client::new().get(format!("http://{}/stream", addr)).send().await.unwrap();
+
+        // Stopped while the stream is idle, with no further chunk to wake the reader
+        let stopper = execution.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            super::super::execution_control::halt_execution(&stopper, "s1").await;
+        });
+
+        let mut deltas = Vec::new();
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            read_gemini_stream(response, &execution, "s1", |delta| deltas.push(delta.to_string())),
+        )
+        .await
+        .expect("a stopped execution must not wait for the next chunk");
+        assert_eq!(result.unwrap_err(), "Execution was stopped");
+        assert_eq!(deltas, vec!["Partial"]);
+    }
+
+    #[tokio::test]
+    async fn test_internal_gemini_text_is_screened_for_secrets() {
+        let mut hooks = super::super::request_hooks::RequestHooks::default();
+        hooks.secret_mode = super::super::prompt_secrets::PromptSecretMode::Warn;
+        let timeouts = super::super::provider_timeouts::ProviderTimeouts::default().gemini;
+        let prompt = "Summarize: the deploy used AIzaSyD3mo7kQ2x9Vb8LpWc4Rt6Yh1Nj5Ue0Ia as its key";
+
+        let error = generate_gemini_text("test-key", &GeminiConfig::default(), &timeouts, &hooks, "gemini-2.5-flash", prompt)
+            .await
+            .unwrap_err();
+        assert!(error.contains("Google API key AIza****"), "{}", error);
+    }
+}
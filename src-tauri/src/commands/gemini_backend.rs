@@ -12,7 +12,7 @@ use super::gemini_performance::{
     BatchAggregator, PerformanceMonitor
 };
 use super::gemini_processor::{
-    GeminiRequestProcessor, ProcessRequest, RequestPriority,
+    GeminiRequestProcessor, ProcessRequest, RequestPreview, RequestPriority,
     GenerationConfig, PreprocessConfig
 };
 use super::gemini_resilience::{
@@ -23,6 +23,9 @@ use super::gemini_monitoring::{
     RequestMetrics, RequestStatus
 };
 
+/// Public Gemini API host, used unless a gateway or proxy is configured
+pub const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
 lazy_static! {
     static ref GEMINI_BACKEND: Arc<GeminiBackendService> = Arc::new(
         GeminiBackendService::new()
@@ -74,6 +77,22 @@ pub struct BackendConfig {
     pub monitoring_enabled: bool,
     pub logging_config: LoggingConfig,
     pub metrics_retention_hours: u64,
+
+    // Endpoint settings
+    /// Root that every Gemini API URL is built from, e.g. an API gateway; must be https
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+}
+
+fn default_base_url() -> String {
+    DEFAULT_GEMINI_BASE_URL.to_string()
+}
+
+impl BackendConfig {
+    /// Reject settings that would send requests somewhere unusable or insecure
+    pub fn validate(&self) -> Result<(), String> {
+        validate_base_url(&self.base_url)
+    }
 }
 
 impl Default for BackendConfig {
@@ -91,6 +110,7 @@ impl Default for BackendConfig {
             monitoring_enabled: true,
             logging_config: LoggingConfig::default(),
             metrics_retention_hours: 24,
+            base_url: default_base_url(),
         }
     }
 }
@@ -208,8 +228,8 @@ impl GeminiBackendService {
             return Ok(());
         }
         
-        // Acquire rate limit permit
-        let _permit = self.rate_limiter.acquire(&request.model).await?;
+        // Acquire rate limit permit, waiting out any delay learned from throttling
+        let _permit = self.rate_limiter.wait_turn(&request.model).await?;
         
         // Build process request
         let process_request = ProcessRequest {
@@ -232,6 +252,9 @@ impl GeminiBackendService {
             },
         };
         
+        // Screen once up front, so a request held back for its secrets is not retried
+        let process_request = self.request_processor.prepare_request(process_request, &app_handle).await?;
+        
         // Execute with resilience
         let processor = self.request_processor.clone();
         let result = self.resilience_manager.execute_resilient(
@@ -243,7 +266,7 @@ impl GeminiBackendService {
                 let processor = processor.clone();
                 
                 Box::pin(async move {
-                    processor.process_request(req, key, handle).await
+                    processor.send_request(req, key, handle).await
                 })
             },
         ).await;
@@ -252,6 +275,8 @@ impl GeminiBackendService {
         
         match result {
             Ok(_) => {
+                self.rate_limiter.record_success(&request.model);
+                
                 // Record success metrics
                 self.record_metrics(
                     &request_id,
@@ -352,6 +377,42 @@ impl GeminiBackendService {
     pub async fn get_config(&self) -> BackendConfig {
         self.config.read().await.clone()
     }
+
+    /// Configured root of the Gemini API
+    pub async fn base_url(&self) -> String {
+        self.config.read().await.base_url.clone()
+    }
+}
+
+/// Accept only https URLs without query or fragment, which API paths are appended to
+pub fn validate_base_url(base_url: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(base_url.trim())
+        .map_err(|e| format!("Invalid Gemini base URL '{}': {}", base_url, e))?;
+    if url.scheme() != "https" {
+        return Err(format!("Gemini base URL must use https: '{}'", base_url));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("Gemini base URL has no host: '{}'", base_url));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(format!("Gemini base URL must not contain a query or fragment: '{}'", base_url));
+    }
+    Ok(())
+}
+
+/// URL of a Gemini API path such as `v1beta/models/gemini-2.5-flash:generateContent`
+pub fn gemini_api_url(base_url: &str, path: &str, api_key: &str) -> String {
+    format!(
+        "{}/{}?key={}",
+        base_url.trim().trim_end_matches('/'),
+        path.trim_start_matches('/'),
+        api_key
+    )
+}
+
+/// URL of a Gemini API path under the configured base URL
+pub async fn gemini_url(path: &str, api_key: &str) -> String {
+    gemini_api_url(&GEMINI_BACKEND.base_url().await, path, api_key)
 }
 
 /// Unified Gemini request structure
@@ -384,8 +445,7 @@ pub async fn execute_gemini_enhanced(
 ) -> Result<(), String> {
     // Get API key
     let api_key = {
-        let conn = db.0.lock()
-            .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+        let conn = db.lock_conn();
         
         // First check environment variable
         if let Ok(key) = std::env::var("GEMINI_API_KEY") {
@@ -409,6 +469,60 @@ pub async fn execute_gemini_enhanced(
         .map_err(|e| e.to_string())
 }
 
+/// Preview the request `execute_gemini_enhanced` would send for a prompt, without sending it
+///
+/// The session's system instruction and history are included as they would be. Sessions
+/// run through `execute_gemini_code` assemble their requests separately and aren't covered.
+#[tauri::command]
+pub async fn preview_gemini_enhanced_request(
+    session_id: Option<String>,
+    prompt: String,
+    model: String,
+) -> Result<RequestPreview, String> {
+    let request = ProcessRequest {
+        prompt,
+        model,
+        session_id,
+        images: Vec::new(),
+        files: Vec::new(),
+        stream: false,
+        priority: RequestPriority::Normal,
+        preprocess_config: None,
+        generation_config: GenerationConfig {
+            temperature: None,
+            max_output_tokens: None,
+            top_k: None,
+            top_p: None,
+            stop_sequences: None,
+            response_mime_type: None,
+            response_schema: None,
+        },
+    };
+    GEMINI_BACKEND.request_processor.preview_request(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Rate limiter shared by every Gemini request, including those outside the backend service
+pub fn shared_rate_limiter() -> Arc<RateLimiter> {
+    GEMINI_BACKEND.rate_limiter.clone()
+}
+
+/// Gemini models whose circuit breaker is open, so routing can skip them
+pub fn open_gemini_circuits() -> Vec<String> {
+    GEMINI_BACKEND.resilience_manager.open_circuits()
+}
+
+/// Close the circuit breaker of a Gemini model, returning the models reset
+pub fn reset_gemini_circuit(model: &str) -> Vec<String> {
+    GEMINI_BACKEND.resilience_manager.reset_circuit(model)
+}
+
+/// Close every Gemini circuit breaker, returning the models reset
+pub fn reset_all_gemini_circuits() -> Vec<String> {
+    GEMINI_BACKEND.resilience_manager.reset_all_circuits()
+}
+
 /// Get backend configuration command
 #[tauri::command]
 pub async fn get_gemini_backend_config() -> Result<BackendConfig, String> {
@@ -418,6 +532,7 @@ pub async fn get_gemini_backend_config() -> Result<BackendConfig, String> {
 /// Update backend configuration command
 #[tauri::command]
 pub async fn update_gemini_backend_config(config: BackendConfig) -> Result<(), String> {
+    config.validate()?;
     GEMINI_BACKEND.update_config(config).await;
     Ok(())
 }
@@ -437,4 +552,42 @@ pub async fn get_gemini_backend_status() -> Result<serde_json::Value, String> {
         "health_statuses": health_statuses,
         "config": GEMINI_BACKEND.get_config().await,
     }))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_base_url_builds_request_urls() {
+        let default = BackendConfig::default();
+        assert_eq!(
+            gemini_api_url(&default.base_url, "v1beta/models/gemini-2.5-flash:generateContent", "key123"),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent?key=key123"
+        );
+
+        let gateway = BackendConfig {
+            base_url: "https://gateway.example.com/gemini/".to_string(),
+            ..BackendConfig::default()
+        };
+        assert!(gateway.validate().is_ok());
+        assert_eq!(
+            gemini_api_url(&gateway.base_url, "v1beta/models", "k"),
+            "https://gateway.example.com/gemini/v1beta/models?key=k"
+        );
+        assert_eq!(
+            gemini_api_url(&gateway.base_url, "/v1beta/models/gemini-1.5-flash:generateContent", "k"),
+            "https://gateway.example.com/gemini/v1beta/models/gemini-1.5-flash:generateContent?key=k"
+        );
+
+        // Older saved configs without the field fall back to the public endpoint
+        let mut saved = serde_json::to_value(&default).unwrap();
+        saved.as_object_mut().unwrap().remove("base_url");
+        let restored: BackendConfig = serde_json::from_value(saved).unwrap();
+        assert_eq!(restored.base_url, DEFAULT_GEMINI_BASE_URL);
+
+        for invalid in ["http://gateway.example.com", "gateway.example.com", "https://gateway.example.com/?x=1"] {
+            assert!(validate_base_url(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tauri::State;
+use tauri::{AppHandle, State};
 use super::agents::AgentDb;
+use super::request_hooks::{request_hooks, RequestHooks};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeminiChatRequest {
@@ -24,6 +25,7 @@ pub struct GeminiChatResponse {
 /// Process a Gemini chat message and return the response directly
 #[tauri::command]
 pub async fn send_gemini_chat_message(
+    app: AppHandle,
     request: GeminiChatRequest,
     db: State<'_, AgentDb>,
 ) -> Result<GeminiChatResponse, String> {
@@ -44,6 +46,15 @@ pub async fn send_gemini_chat_message(
         }
     };
 
+    chat_completion(request, &api_key, &request_hooks(&app)).await
+}
+
+/// Send one chat message through the request hooks and read the reply
+async fn chat_completion(
+    request: GeminiChatRequest,
+    api_key: &str,
+    hooks: &RequestHooks,
+) -> Result<GeminiChatResponse, String> {
     // Build request body
     let request_body = json!({
         "contents": [{
@@ -67,18 +78,12 @@ pub async fn send_gemini_chat_message(
     });
 
     // Build URL
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        request.model,
-        api_key
-    );
+    let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", request.model), api_key).await;
 
     // Make request
     let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
+    let response = hooks
+        .prepare("gemini", client.post(&url), request_body)?
         .send()
         .await
         .map_err(|e| format!("Network error: {}", e))?;
@@ -129,4 +134,25 @@ pub async fn send_gemini_chat_message(
         safety_ratings,
         usage_metadata,
     })
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::prompt_secrets::PromptSecretMode;
+
+    #[tokio::test]
+    async fn test_chat_message_is_screened_for_secrets() {
+        let mut hooks = RequestHooks::default();
+        hooks.secret_mode = PromptSecretMode::Warn;
+        let request = GeminiChatRequest {
+            prompt: "Why is my key rejected?".to_string(),
+            model: "gemini-2.5-flash".to_string(),
+            temperature: None,
+            max_output_tokens: None,
+            system_instruction: Some("The user's key is AIzaSyD3mo7kQ2x9Vb8LpWc4Rt6Yh1Nj5Ue0Ia".to_string()),
+        };
+
+        let error = chat_completion(request, "test-key", &hooks).await.unwrap_err();
+        assert!(error.contains("Google API key AIza****"), "{}", error);
+    }
+}
@@ -3,6 +3,8 @@ use std::env;
 use std::time::Duration;
 use tauri::{State, Emitter};
 use super::{claude::ClaudeProcessState, agents::AgentDb};
+use super::request_hooks::request_hooks;
+use super::session_event_log::emit_session_event;
 use tokio::time::timeout;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -133,8 +135,7 @@ pub async fn execute_gemini_code_enhanced(
     
     // Get API key with better error handling
     let api_key = {
-        let conn = db.0.lock()
-            .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+        let conn = db.lock_conn();
         get_gemini_api_key_sync(&conn)?
     };
     
@@ -198,21 +199,55 @@ pub async fn execute_gemini_code_enhanced(
         }
     };
     
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model_endpoint,
-        api_key
-    );
+    let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", model_endpoint), &api_key).await;
     
-    // Build request body with configurable parameters
+    let request_body = enhanced_request_body(&GeminiRequest {
+        prompt: trimmed_prompt.to_string(),
+        model: trimmed_model.to_string(),
+        temperature,
+        max_output_tokens,
+        top_k,
+        top_p,
+        stop_sequences,
+        system_instruction,
+    });
+    
+    // Send request with timeout
+    let request = match request_hooks(&app_handle).prepare("gemini", client.post(&url), request_body) {
+        Ok(request) => request,
+        Err(e) => {
+            emit_gemini_error(&app_handle, &e)?;
+            return Err(e);
+        }
+    };
+    let request_future = request.send();
+    
+    match timeout(timeout_duration, request_future).await {
+        Ok(Ok(response)) => {
+            // Process successful response
+            handle_gemini_response(response, &app_handle, &session_id, &trimmed_model).await
+        }
+        Ok(Err(e)) => {
+            emit_gemini_error(&app_handle, &format!("Failed to call Gemini API: {}", e))?;
+            Err(format!("Failed to call Gemini API: {}", e))
+        }
+        Err(_) => {
+            emit_gemini_error(&app_handle, "Request timed out")?;
+            Err("Request timed out".to_string())
+        }
+    }
+}
+
+/// Request body with the given parameters, or the enhanced defaults where unset
+fn enhanced_request_body(request: &GeminiRequest) -> serde_json::Value {
     let mut contents = vec![serde_json::json!({
         "parts": [{
-            "text": trimmed_prompt
+            "text": request.prompt
         }]
     })];
     
     // Add system instruction if provided
-    if let Some(sys_instruction) = system_instruction {
+    if let Some(sys_instruction) = &request.system_instruction {
         contents.insert(0, serde_json::json!({
             "role": "system",
             "parts": [{
@@ -222,18 +257,18 @@ pub async fn execute_gemini_code_enhanced(
     }
     
     let mut generation_config = serde_json::json!({
-        "temperature": temperature.unwrap_or(0.7),
-        "maxOutputTokens": max_output_tokens.unwrap_or(8192),
-        "topK": top_k.unwrap_or(10),
-        "topP": top_p.unwrap_or(0.95),
+        "temperature": request.temperature.unwrap_or(0.7),
+        "maxOutputTokens": request.max_output_tokens.unwrap_or(8192),
+        "topK": request.top_k.unwrap_or(10),
+        "topP": request.top_p.unwrap_or(0.95),
     });
     
     // Add stop sequences if provided
-    if let Some(sequences) = stop_sequences {
+    if let Some(sequences) = &request.stop_sequences {
         generation_config["stopSequences"] = serde_json::json!(sequences);
     }
     
-    let request_body = serde_json::json!({
+    serde_json::json!({
         "contents": contents,
         "generationConfig": generation_config,
         "safetySettings": [
@@ -254,27 +289,7 @@ pub async fn execute_gemini_code_enhanced(
                 "threshold": "BLOCK_ONLY_HIGH"
             }
         ]
-    });
-    
-    // Send request with timeout
-    let request_future = client.post(&url)
-        .json(&request_body)
-        .send();
-    
-    match timeout(timeout_duration, request_future).await {
-        Ok(Ok(response)) => {
-            // Process successful response
-            handle_gemini_response(response, &app_handle, &session_id, &trimmed_model).await
-        }
-        Ok(Err(e)) => {
-            emit_gemini_error(&app_handle, &format!("Failed to call Gemini API: {}", e))?;
-            Err(format!("Failed to call Gemini API: {}", e))
-        }
-        Err(_) => {
-            emit_gemini_error(&app_handle, "Request timed out")?;
-            Err("Request timed out".to_string())
-        }
-    }
+    })
 }
 
 /// Handle Gemini API response
@@ -361,7 +376,7 @@ async fn handle_gemini_response(
                 app_handle.emit("claude-complete", true)
                     .map_err(|e| format!("Failed to emit complete event: {}", e))?;
                 
-                app_handle.emit(&format!("claude-complete:{}", session_id), true)
+                emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), true)
                     .map_err(|e| format!("Failed to emit session complete event: {}", e))?;
                 
                 Ok(())
@@ -412,4 +427,36 @@ fn get_gemini_api_key_sync(conn: &rusqlite::Connection) -> Result<String, String
         Err(rusqlite::Error::QueryReturnedNoRows) => Err("Gemini API key not set".to_string()),
         Err(e) => Err(format!("Failed to get Gemini API key: {}", e)),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::prompt_secrets::PromptSecretMode;
+    use crate::commands::request_hooks::RequestHooks;
+
+    #[test]
+    fn test_enhanced_request_is_screened_for_secrets() {
+        let body = enhanced_request_body(&GeminiRequest {
+            prompt: "Why is this key rejected? AIzaSyD3mo7kQ2x9Vb8LpWc4Rt6Yh1Nj5Ue0Ia".to_string(),
+            model: "gemini-2.5-flash".to_string(),
+            temperature: None,
+            max_output_tokens: None,
+            top_k: None,
+            top_p: None,
+            stop_sequences: None,
+            system_instruction: Some("You are a reviewer.".to_string()),
+        });
+        let request = || reqwest::Client::new().post("http://127.0.0.1:9/v1beta");
+
+        let mut hooks = RequestHooks::default();
+        hooks.secret_mode = PromptSecretMode::Warn;
+        let error = hooks.prepare("gemini", request(), body.clone()).unwrap_err();
+        assert!(error.contains("Google API key AIza****"), "{}", error);
+
+        hooks.secret_mode = PromptSecretMode::Redact;
+        let sent = hooks.prepare("gemini", request(), body).unwrap().build().unwrap();
+        let sent: serde_json::Value = serde_json::from_slice(sent.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(sent["contents"][1]["parts"][0]["text"], "Why is this key rejected? [REDACTED]");
+        assert_eq!(sent["contents"][0]["parts"][0]["text"], "You are a reviewer.");
+    }
+}
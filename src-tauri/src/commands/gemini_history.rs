@@ -0,0 +1,124 @@
+use serde_json::Value;
+
+use super::context_guard::estimate_tokens;
+use super::intelligent_routing::AiModelBenchmark;
+
+/// An earlier turn: its role ("user", or "model"/"assistant") and text
+pub type ConversationTurn = (String, String);
+
+fn gemini_role(role: &str) -> Result<&'static str, String> {
+    match role.trim().to_lowercase().as_str() {
+        "user" => Ok("user"),
+        "model" | "assistant" => Ok("model"),
+        other => Err(format!(
+            "Unknown conversation role '{}': expected user, model or assistant",
+            other
+        )),
+    }
+}
+
+/// Context window of `model` in the benchmark table, if it has an entry
+pub fn context_window(model: &str, benchmarks: &[AiModelBenchmark]) -> Option<u64> {
+    benchmarks
+        .iter()
+        .find(|b| b.model_id != "auto" && crate::models::same_model(&b.model_id, model))
+        .map(|b| u64::from(b.context_window))
+}
+
+/// Append a turn, merging it into the last one when both have the same role
+fn push_turn(turns: &mut Vec<(&'static str, String)>, role: &'static str, text: &str) {
+    match turns.last_mut() {
+        Some((last_role, last_text)) if *last_role == role => {
+            last_text.push_str("\n\n");
+            last_text.push_str(text);
+        }
+        _ => turns.push((role, text.to_string())),
+    }
+}
+
+/// The `contents` of a request: `history` followed by `prompt` as the user's turn
+///
+/// The API keeps no state, so Gemini only remembers what a request carries. The
+/// history goes out with that one request and is never stored in the shared
+/// session managers, so no session sees another's history. Gemini expects user and model turns to alternate, so adjacent turns of one
+/// role are merged. With a known `context_window`, the oldest turns are dropped
+/// until the history, the prompt and `reserved_tokens` of output fit in it, and
+/// the history always starts on a user turn.
+pub fn conversation_contents(
+    history: &[ConversationTurn],
+    prompt: &str,
+    context_window: Option<u64>,
+    reserved_tokens: u64,
+) -> Result<Vec<Value>, String> {
+    let mut turns = Vec::new();
+    for (role, text) in history {
+        let role = gemini_role(role)?;
+        if !text.trim().is_empty() {
+            push_turn(&mut turns, role, text);
+        }
+    }
+
+    let mut start = 0;
+    if let Some(window) = context_window {
+        let budget = window.saturating_sub(estimate_tokens(prompt) + reserved_tokens);
+        let mut used: u64 = turns.iter().map(|(_, text)| estimate_tokens(text)).sum();
+        while used > budget && start < turns.len() {
+            used -= estimate_tokens(&turns[start].1);
+            start += 1;
+        }
+    }
+    while turns.get(start).is_some_and(|(role, _)| *role == "model") {
+        start += 1;
+    }
+    if start > 0 {
+        log::info!("Dropped the {} oldest of {} conversation turn(s) to fit the context window", start, turns.len());
+        turns.drain(..start);
+    }
+
+    push_turn(&mut turns, "user", prompt);
+    Ok(turns
+        .into_iter()
+        .map(|(role, text)| serde_json::json!({ "role": role, "parts": [{ "text": text }] }))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn turn(role: &str, text: &str) -> ConversationTurn {
+        (role.to_string(), text.to_string())
+    }
+
+    #[test]
+    fn test_history_alternates_and_trims_oldest_turns() {
+        let history = vec![
+            turn("user", "What does main.rs do?"),
+            turn("assistant", "It starts the Tauri app."),
+            turn("assistant", "It also registers the commands."),
+            turn("user", &"x".repeat(400)),
+            turn("model", "That is a long log."),
+        ];
+
+        let contents = conversation_contents(&history, "Which commands?", None, 0).unwrap();
+        let roles: Vec<_> = contents.iter().map(|c| c["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, ["user", "model", "user", "model", "user"]);
+        assert_eq!(contents[1]["parts"][0]["text"], "It starts the Tauri app.\n\nIt also registers the commands.");
+        assert_eq!(contents[4], json!({ "role": "user", "parts": [{ "text": "Which commands?" }] }));
+
+        // With the prompt and 30 output tokens reserved, only the last exchange fits
+        let contents = conversation_contents(&history, "Which commands?", Some(150), 30).unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[0]["parts"][0]["text"], "x".repeat(400));
+        assert_eq!(contents[0]["role"], "user");
+
+        // Dropping the oversized turn leaves a model turn first, which goes too
+        let contents = conversation_contents(&history, "Which commands?", Some(60), 30).unwrap();
+        assert_eq!(contents.len(), 1);
+
+        assert!(conversation_contents(&[turn("system", "Be brief")], "Hi", None, 0)
+            .unwrap_err()
+            .contains("Unknown conversation role 'system'"));
+    }
+}
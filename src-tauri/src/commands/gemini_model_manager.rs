@@ -124,10 +124,7 @@ impl CapabilityDetector {
     
     async fn test_capability(&self, model_id: &str, api_key: &str, prompt: &str) -> Result<String> {
         let client = reqwest::Client::new();
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model_id, api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", model_id), api_key).await;
         
         let body = serde_json::json!({
             "contents": [{
@@ -158,10 +155,7 @@ impl CapabilityDetector {
     async fn detect_token_limits(&self, model_id: &str, api_key: &str) -> Result<(u32, u32)> {
         // Query model metadata endpoint
         let client = reqwest::Client::new();
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}?key={}",
-            model_id, api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}", model_id), api_key).await;
         
         match client.get(&url).send().await {
             Ok(response) if response.status().is_success() => {
@@ -209,10 +203,7 @@ struct GoogleAIStudioSource {
 impl ModelSource for GoogleAIStudioSource {
     async fn fetch_models(&self) -> Result<Vec<ModelMetadata>> {
         let client = reqwest::Client::new();
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models?key={}",
-            self.api_key
-        );
+        let url = super::gemini_backend::gemini_url("v1beta/models", &self.api_key).await;
         
         let response = client.get(&url).send().await?;
         
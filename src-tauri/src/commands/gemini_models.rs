@@ -354,11 +354,7 @@ impl ModelRegistry {
         
         // Create a simple test request
         let client = reqwest::Client::new();
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model.metadata.id,
-            api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", model.metadata.id), api_key).await;
         
         let test_body = serde_json::json!({
             "contents": [{
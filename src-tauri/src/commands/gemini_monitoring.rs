@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
+use lazy_static::lazy_static;
 
 /// Request/Response metrics
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -121,9 +122,16 @@ pub struct MonitoringCollector {
     request_history: Arc<RwLock<VecDeque<RequestMetrics>>>,
     usage_metrics: Arc<RwLock<HashMap<(String, UsagePeriod), UsageMetrics>>>,
     realtime_metrics: Arc<RwLock<RealtimeMetrics>>,
+    truncation_counts: Arc<RwLock<HashMap<String, u64>>>,
+    block_counts: Arc<RwLock<HashMap<String, u64>>>,
     max_history_size: usize,
 }
 
+lazy_static! {
+    /// Process-wide collector shared by the execution paths and monitoring commands
+    pub static ref GEMINI_MONITOR: MonitoringCollector = MonitoringCollector::new(10000);
+}
+
 impl MonitoringCollector {
     pub fn new(max_history_size: usize) -> Self {
         Self {
@@ -139,6 +147,8 @@ impl MonitoringCollector {
                 queue_depth: 0,
                 circuit_breaker_status: HashMap::new(),
             })),
+            truncation_counts: Arc::new(RwLock::new(HashMap::new())),
+            block_counts: Arc::new(RwLock::new(HashMap::new())),
             max_history_size,
         }
     }
@@ -258,6 +268,29 @@ impl MonitoringCollector {
             .collect()
     }
     
+    /// Record a response that was cut off by the output token limit
+    pub fn record_truncation(&self, model: &str) {
+        let mut counts = self.truncation_counts.write().unwrap();
+        *counts.entry(model.to_string()).or_insert(0) += 1;
+    }
+    
+    /// Get MAX_TOKENS truncation counts per model
+    pub fn get_truncation_counts(&self) -> HashMap<String, u64> {
+        self.truncation_counts.read().unwrap().clone()
+    }
+    
+    /// Record a request Gemini blocked, keyed by reason and harm category
+    pub fn record_block(&self, reason: &str, category: Option<&str>) {
+        let key = format!("{}:{}", reason, category.unwrap_or("UNSPECIFIED"));
+        let mut counts = self.block_counts.write().unwrap();
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    
+    /// Get block counts per `reason:category`
+    pub fn get_block_counts(&self) -> HashMap<String, u64> {
+        self.block_counts.read().unwrap().clone()
+    }
+    
     /// Get usage metrics
     pub fn get_usage_metrics(
         &self,
@@ -551,8 +584,7 @@ pub async fn get_gemini_monitoring_metrics(
     model: Option<String>,
     limit: Option<usize>,
 ) -> Result<HashMap<String, serde_json::Value>, String> {
-    // In a real implementation, this would use a global collector instance
-    let collector = MonitoringCollector::new(10000);
+    let collector = &*GEMINI_MONITOR;
     
     let mut result = HashMap::new();
     
@@ -570,6 +602,20 @@ pub async fn get_gemini_monitoring_metrics(
             .map_err(|e| e.to_string())?,
     );
     
+    // Get truncation counts
+    result.insert(
+        "truncations".to_string(),
+        serde_json::to_value(collector.get_truncation_counts())
+            .map_err(|e| e.to_string())?,
+    );
+    
+    // Get safety/recitation block counts
+    result.insert(
+        "blocks".to_string(),
+        serde_json::to_value(collector.get_block_counts())
+            .map_err(|e| e.to_string())?,
+    );
+    
     // Get usage metrics if model specified
     if let Some(model) = model {
         let hourly = collector.get_usage_metrics(&model, UsagePeriod::Hourly);
@@ -599,7 +645,7 @@ pub async fn get_gemini_analytics(
     start_time: String,
     end_time: String,
 ) -> Result<PerformanceAnalytics, String> {
-    let collector = MonitoringCollector::new(10000);
+    let collector = &*GEMINI_MONITOR;
     
     let time_range = TimeRange {
         start: DateTime::parse_from_rfc3339(&start_time)
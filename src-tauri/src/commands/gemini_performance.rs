@@ -205,6 +205,41 @@ pub struct CacheStats {
 pub struct RateLimiter {
     semaphores: Arc<RwLock<HashMap<String, Arc<Semaphore>>>>,
     limits: Arc<RwLock<HashMap<String, RateLimit>>>,
+    delays: Arc<RwLock<HashMap<String, AdaptiveDelay>>>,
+}
+
+/// First delay applied after an endpoint throttles us
+const THROTTLE_INITIAL_DELAY: Duration = Duration::from_millis(500);
+/// Longest delay we will wait between requests to one endpoint
+const THROTTLE_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Delays below this are dropped entirely once requests succeed again
+const THROTTLE_MIN_DELAY: Duration = Duration::from_millis(50);
+
+/// Pre-request delay for one endpoint, learned from 429 responses
+///
+/// Doubles on every throttle (or jumps to the server's `Retry-After`) and halves on
+/// every success, so unthrottled endpoints are not slowed down at all.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AdaptiveDelay {
+    current: Duration,
+}
+
+impl AdaptiveDelay {
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    pub fn on_throttled(&mut self, retry_after: Option<Duration>) {
+        let doubled = (self.current * 2).max(THROTTLE_INITIAL_DELAY);
+        self.current = doubled.max(retry_after.unwrap_or_default()).min(THROTTLE_MAX_DELAY);
+    }
+
+    pub fn on_success(&mut self) {
+        self.current /= 2;
+        if self.current < THROTTLE_MIN_DELAY {
+            self.current = Duration::ZERO;
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -219,9 +254,45 @@ impl RateLimiter {
         Self {
             semaphores: Arc::new(RwLock::new(HashMap::new())),
             limits: Arc::new(RwLock::new(HashMap::new())),
+            delays: Arc::new(RwLock::new(HashMap::new())),
         }
     }
     
+    /// Delay currently applied before requests to an endpoint
+    pub fn current_delay(&self, endpoint: &str) -> Duration {
+        self.delays.read().unwrap().get(endpoint).map(AdaptiveDelay::current).unwrap_or_default()
+    }
+    
+    /// Slow an endpoint down after it answered 429
+    pub fn record_throttled(&self, endpoint: &str, retry_after: Option<Duration>) {
+        let mut delays = self.delays.write().unwrap();
+        let delay = delays.entry(endpoint.to_string()).or_default();
+        delay.on_throttled(retry_after);
+        log::warn!("Gemini endpoint {} throttled, delaying requests by {:?}", endpoint, delay.current());
+    }
+    
+    /// Let an endpoint's delay decay after a successful request
+    pub fn record_success(&self, endpoint: &str) {
+        let mut delays = self.delays.write().unwrap();
+        if let Some(delay) = delays.get_mut(endpoint) {
+            delay.on_success();
+            if delay.current().is_zero() {
+                delays.remove(endpoint);
+            }
+        }
+    }
+    
+    /// Take a concurrency permit, then wait out the endpoint's adaptive delay
+    pub async fn wait_turn(&self, endpoint: &str) -> Result<RateLimitPermit> {
+        let permit = self.acquire(endpoint).await?;
+        let delay = self.current_delay(endpoint);
+        if !delay.is_zero() {
+            log::info!("Waiting {:?} before calling throttled Gemini endpoint {}", delay, endpoint);
+            tokio::time::sleep(delay).await;
+        }
+        Ok(permit)
+    }
+    
     /// Set rate limit for a model
     pub fn set_limit(&self, model: String, limit: RateLimit) {
         self.limits.write().unwrap().insert(model.clone(), limit.clone());
@@ -439,4 +510,37 @@ pub async fn get_gemini_cache_stats() -> Result<CacheStats, String> {
     // In a real implementation, this would be a global instance
     let cache = ResponseCache::new(1000, 100 * 1024 * 1024); // 100MB cache
     Ok(cache.get_stats())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_delay_grows_on_throttle_and_decays_on_success() {
+        let limiter = RateLimiter::new();
+        let endpoint = "gemini-2.0-flash";
+        assert_eq!(limiter.current_delay(endpoint), Duration::ZERO);
+
+        limiter.record_throttled(endpoint, None);
+        limiter.record_throttled(endpoint, None);
+        limiter.record_throttled(endpoint, None);
+        assert_eq!(limiter.current_delay(endpoint), Duration::from_secs(2));
+        // The server's Retry-After wins when it asks for longer
+        limiter.record_throttled(endpoint, Some(Duration::from_secs(10)));
+        assert_eq!(limiter.current_delay(endpoint), Duration::from_secs(10));
+        // Other endpoints are not slowed down
+        assert_eq!(limiter.current_delay("gemini-1.5-pro"), Duration::ZERO);
+
+        limiter.record_success(endpoint);
+        assert_eq!(limiter.current_delay(endpoint), Duration::from_secs(5));
+        for _ in 0..10 {
+            limiter.record_success(endpoint);
+        }
+        assert_eq!(limiter.current_delay(endpoint), Duration::ZERO);
+
+        for _ in 0..20 {
+            limiter.record_throttled(endpoint, None);
+        }
+        assert_eq!(limiter.current_delay(endpoint), THROTTLE_MAX_DELAY);
+    }
+}
@@ -8,7 +8,14 @@ use tauri::{AppHandle, Emitter};
 use tokio::sync::{Mutex, Semaphore};
 use tokio::time::{sleep, Duration};
 
+use super::ai_usage_tracker::{track_turn_usage, AIUsageEvent};
+use super::context_guard::estimate_tokens;
 use super::gemini_models::{MODEL_REGISTRY, ModelMetadata};
+use super::request_hooks::request_hooks;
+use super::session_event_log::{mask_secrets, mask_value};
+
+/// Stands in for the API key in previewed endpoints
+const MASKED_API_KEY: &str = "[REDACTED]";
 
 /// Request preprocessing configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +92,140 @@ pub struct StreamingUsage {
     pub total_tokens: u32,
 }
 
+/// Token counts for a finished stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamUsageTotals {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Prompt tokens served from the context cache
+    pub cached_tokens: u32,
+    /// The stream ended without `usageMetadata`, so the counts are estimates
+    pub estimated: bool,
+}
+
+/// Collects a `streamGenerateContent?alt=sse` response into its text, usage and
+/// the response `generateContent` would have returned
+///
+/// Network chunks can split SSE lines, so bytes are buffered until a full line
+/// arrives. Text parts are concatenated while other parts, such as function
+/// calls, are kept in order. Gemini repeats `usageMetadata` as the stream goes and
+/// sends the totals with the final chunk; like the finish reason, safety ratings
+/// and prompt feedback, the last one seen wins.
+#[derive(Debug, Default)]
+pub struct GeminiStreamAccumulator {
+    pending: Vec<u8>,
+    text: String,
+    parts: Vec<serde_json::Value>,
+    candidate_seen: bool,
+    finish_reason: Option<serde_json::Value>,
+    safety_ratings: Option<serde_json::Value>,
+    prompt_feedback: Option<serde_json::Value>,
+    usage: Option<serde_json::Value>,
+}
+
+impl GeminiStreamAccumulator {
+    /// Feed raw response bytes, returning the text deltas of the events completed by them
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut deltas = Vec::new();
+        while let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            deltas.extend(self.handle_line(&String::from_utf8_lossy(&line)));
+        }
+        deltas
+    }
+
+    fn handle_line(&mut self, line: &str) -> Option<String> {
+        let data = line.trim().strip_prefix("data:")?.trim();
+        let event: serde_json::Value = serde_json::from_str(data).ok()?;
+        if event["promptFeedback"].is_object() {
+            self.prompt_feedback = Some(event["promptFeedback"].clone());
+        }
+        if event["usageMetadata"].is_object() {
+            self.usage = Some(event["usageMetadata"].clone());
+        }
+
+        let candidate = event["candidates"].get(0)?;
+        self.candidate_seen = true;
+        if candidate["finishReason"].is_string() {
+            self.finish_reason = Some(candidate["finishReason"].clone());
+        }
+        if candidate["safetyRatings"].is_array() {
+            self.safety_ratings = Some(candidate["safetyRatings"].clone());
+        }
+        let mut delta = String::new();
+        for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+            match part["text"].as_str() {
+                Some(text) => delta.push_str(text),
+                None => self.parts.push(part.clone()),
+            }
+        }
+        self.text.push_str(&delta);
+        (!delta.is_empty()).then_some(delta)
+    }
+
+    /// Handle an event left unterminated when the stream closed, returning its text delta
+    pub fn flush(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned();
+        self.handle_line(&line)
+    }
+
+    /// Text received so far
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Finish the stream, estimating token counts from `prompt` and the text when no usage arrived
+    pub fn finish(&mut self, prompt: &str) -> StreamUsageTotals {
+        self.flush();
+
+        let count = |usage: &serde_json::Value, key: &str| usage[key].as_u64().unwrap_or(0) as u32;
+        match &self.usage {
+            Some(usage) => StreamUsageTotals {
+                input_tokens: count(usage, "promptTokenCount"),
+                output_tokens: count(usage, "candidatesTokenCount"),
+                cached_tokens: count(usage, "cachedContentTokenCount"),
+                estimated: false,
+            },
+            None => StreamUsageTotals {
+                input_tokens: (prompt.len() / 4) as u32,
+                output_tokens: (self.text.len() / 4) as u32,
+                cached_tokens: 0,
+                estimated: true,
+            },
+        }
+    }
+
+    /// The assembled response, including an event left unterminated when the stream closed
+    pub fn into_response(mut self) -> serde_json::Value {
+        self.flush();
+
+        let mut parts = Vec::with_capacity(self.parts.len() + 1);
+        if !self.text.is_empty() {
+            parts.push(serde_json::json!({ "text": self.text }));
+        }
+        parts.extend(self.parts);
+        let mut response = serde_json::json!({ "candidates": [] });
+        if self.candidate_seen {
+            response["candidates"] = serde_json::json!([{
+                "content": { "role": "model", "parts": parts },
+                "finishReason": self.finish_reason,
+                "safetyRatings": self.safety_ratings,
+            }]);
+        }
+        if let Some(prompt_feedback) = self.prompt_feedback {
+            response["promptFeedback"] = prompt_feedback;
+        }
+        if let Some(usage) = self.usage {
+            response["usageMetadata"] = usage;
+        }
+        response
+    }
+}
+
 /// Request processor with advanced features
 pub struct GeminiRequestProcessor {
     client: reqwest::Client,
@@ -134,6 +275,34 @@ pub struct GenerationConfig {
     pub response_schema: Option<serde_json::Value>,
 }
 
+/// A request assembled exactly as it would be sent, without sending it
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestPreview {
+    pub model: String,
+    /// URL the request would be posted to, with the API key masked
+    pub endpoint: String,
+    /// Request body with any secrets in its text masked
+    pub body: serde_json::Value,
+    pub system_instruction: Option<String>,
+    /// Earlier messages of the session sent ahead of the prompt
+    pub history_messages: usize,
+    /// Estimated input tokens over every text part of the body
+    pub estimated_tokens: u64,
+}
+
+/// URL a request for `model_id` is posted to
+async fn request_url(model_id: &str, stream: bool, api_key: &str) -> String {
+    if stream {
+        // Without alt=sse the stream arrives as one JSON array instead of SSE events
+        format!(
+            "{}&alt=sse",
+            super::gemini_backend::gemini_url(&format!("v1beta/models/{}:streamGenerateContent", model_id), api_key).await
+        )
+    } else {
+        super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", model_id), api_key).await
+    }
+}
+
 impl GeminiRequestProcessor {
     pub fn new() -> Self {
         let client = reqwest::Client::builder()
@@ -227,13 +396,40 @@ impl GeminiRequestProcessor {
     /// Process request with streaming support
     pub async fn process_request(
         &self,
-        mut request: ProcessRequest,
+        request: ProcessRequest,
         api_key: String,
         app_handle: AppHandle,
     ) -> Result<()> {
-        // Preprocess request
+        let request = self.prepare_request(request, &app_handle).await?;
+        self.send_request(request, api_key, app_handle).await
+    }
+    
+    /// Preprocess a request and check that it gets past the secret screening
+    ///
+    /// The body is run through the request hooks, where every Gemini request is
+    /// screened, so a request held back for its secrets fails here rather than
+    /// on each attempt of a retried send.
+    pub async fn prepare_request(&self, mut request: ProcessRequest, app_handle: &AppHandle) -> Result<ProcessRequest> {
         self.preprocess_request(&mut request).await?;
         
+        let model = MODEL_REGISTRY.get_model(&request.model)
+            .ok_or_else(|| anyhow!("Model not found"))?;
+        let url = request_url(&model.metadata.id, request.stream, MASKED_API_KEY).await;
+        let body = self.build_request_body(&request, &model.metadata).await?;
+        request_hooks(app_handle)
+            .prepare("gemini", self.client.post(&url), body)
+            .map_err(|e| anyhow!(e))?;
+        
+        Ok(request)
+    }
+    
+    /// Send a request already through `prepare_request`
+    pub async fn send_request(
+        &self,
+        request: ProcessRequest,
+        api_key: String,
+        app_handle: AppHandle,
+    ) -> Result<()> {
         // Acquire rate limit permit
         let _permit = self.rate_limiter.acquire().await
             .map_err(|e| anyhow!("Failed to acquire rate limit permit: {}", e))?;
@@ -245,6 +441,40 @@ impl GeminiRequestProcessor {
         }
     }
     
+    /// Assemble a request the way `process_request` would, without sending it
+    pub async fn preview_request(&self, mut request: ProcessRequest) -> Result<RequestPreview> {
+        self.preprocess_request(&mut request).await?;
+        
+        let model = MODEL_REGISTRY.get_model(&request.model)
+            .ok_or_else(|| anyhow!("Model not found"))?;
+        let endpoint = request_url(&model.metadata.id, request.stream, MASKED_API_KEY).await;
+        let body = self.build_request_body(&request, &model.metadata).await?;
+        
+        let (system_instruction, history_messages) = match &request.session_id {
+            Some(session_id) => self.conversations.lock().await
+                .get(session_id)
+                .map(|context| (context.system_instruction.clone(), context.messages.len()))
+                .unwrap_or_default(),
+            None => (None, 0),
+        };
+        
+        let estimated_tokens = body["contents"].as_array().into_iter().flatten()
+            .filter_map(|content| content["parts"].as_array())
+            .flatten()
+            .filter_map(|part| part["text"].as_str())
+            .map(estimate_tokens)
+            .sum();
+        
+        Ok(RequestPreview {
+            model: model.metadata.id.clone(),
+            endpoint,
+            body: mask_value(&body),
+            system_instruction: system_instruction.as_deref().map(mask_secrets),
+            history_messages,
+            estimated_tokens,
+        })
+    }
+    
     /// Process standard (non-streaming) request
     async fn process_standard(
         &self,
@@ -255,17 +485,16 @@ impl GeminiRequestProcessor {
         let model = MODEL_REGISTRY.get_model(&request.model)
             .ok_or_else(|| anyhow!("Model not found"))?;
         
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model.metadata.id,
-            api_key
-        );
+        let url = request_url(&model.metadata.id, false, &api_key).await;
         
         let body = self.build_request_body(&request, &model.metadata).await?;
         
+        let request = request_hooks(&app_handle)
+            .prepare("gemini", self.client.post(&url), body)
+            .map_err(|e| anyhow!(e))?;
         let start_time = std::time::Instant::now();
         
-        match self.client.post(&url).json(&body).send().await {
+        match request.send().await {
             Ok(response) => {
                 let response_time = start_time.elapsed().as_millis() as u64;
                 
@@ -303,16 +532,14 @@ impl GeminiRequestProcessor {
         let model = MODEL_REGISTRY.get_model(&request.model)
             .ok_or_else(|| anyhow!("Model not found"))?;
         
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?key={}",
-            model.metadata.id,
-            api_key
-        );
+        let url = request_url(&model.metadata.id, true, &api_key).await;
         
         let body = self.build_request_body(&request, &model.metadata).await?;
+        let start_time = std::time::Instant::now();
         
-        let response = self.client.post(&url)
-            .json(&body)
+        let response = request_hooks(&app_handle)
+            .prepare("gemini", self.client.post(&url), body)
+            .map_err(|e| anyhow!(e))?
             .send()
             .await?;
         
@@ -323,32 +550,20 @@ impl GeminiRequestProcessor {
         
         // Process streaming response
         let mut stream = response.bytes_stream();
-        let mut accumulated_text = String::new();
+        let mut accumulator = GeminiStreamAccumulator::default();
         
         while let Some(chunk) = stream.next().await {
             match chunk {
                 Ok(bytes) => {
-                    if let Ok(text) = std::str::from_utf8(&bytes) {
-                        // Parse SSE format
-                        for line in text.lines() {
-                            if line.starts_with("data: ") {
-                                let data = &line[6..];
-                                if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
-                                    if let Some(text) = json["candidates"][0]["content"]["parts"][0]["text"].as_str() {
-                                        accumulated_text.push_str(text);
-                                        
-                                        // Emit streaming chunk
-                                        let chunk_event = serde_json::json!({
-                                            "type": "streaming",
-                                            "delta": text,
-                                            "accumulated": accumulated_text.clone(),
-                                        });
-                                        
-                                        app_handle.emit("gemini-stream", chunk_event.to_string())?;
-                                    }
-                                }
-                            }
-                        }
+                    for delta in accumulator.push(&bytes) {
+                        // Emit streaming chunk
+                        let chunk_event = serde_json::json!({
+                            "type": "streaming",
+                            "delta": delta,
+                            "accumulated": accumulator.text(),
+                        });
+                        
+                        app_handle.emit("gemini-stream", chunk_event.to_string())?;
                     }
                 }
                 Err(e) => {
@@ -357,6 +572,19 @@ impl GeminiRequestProcessor {
             }
         }
         
+        let usage = accumulator.finish(&request.prompt);
+        if usage.estimated {
+            log::warn!("Gemini stream for {} ended without usage metadata; recording estimated tokens", request.model);
+        }
+        record_stream_usage(&app_handle, &request, &usage, start_time.elapsed().as_millis() as i64);
+        
+        let done_event = serde_json::json!({
+            "type": "done",
+            "accumulated": accumulator.text(),
+            "usage": usage,
+        });
+        app_handle.emit("gemini-stream", done_event.to_string())?;
+        
         Ok(())
     }
     
@@ -598,6 +826,31 @@ impl GeminiRequestProcessor {
     }
 }
 
+/// Record a finished stream in usage tracking, flagged when its token counts are estimates
+fn record_stream_usage(app_handle: &AppHandle, request: &ProcessRequest, usage: &StreamUsageTotals, response_time_ms: i64) {
+    let mut event = AIUsageEvent {
+        project_id: "gemini".to_string(),
+        model_name: request.model.clone(),
+        agent_type: None,
+        mcp_server: None,
+        token_count: i64::from(usage.input_tokens + usage.output_tokens),
+        request_type: "streaming".to_string(),
+        response_time_ms: Some(response_time_ms),
+        success: true,
+        error_message: None,
+        session_id: request.session_id.clone(),
+        user_prompt_tokens: Some(i64::from(usage.input_tokens)),
+        assistant_response_tokens: Some(i64::from(usage.output_tokens)),
+        cached_tokens: Some(i64::from(usage.cached_tokens)),
+        estimated: usage.estimated,
+        timestamp: chrono::Utc::now().timestamp(),
+        tags: None,
+    };
+    if let Err(e) = track_turn_usage(app_handle, &mut event) {
+        log::warn!("Failed to record Gemini stream usage: {}", e);
+    }
+}
+
 /// Process Gemini request with advanced features
 #[tauri::command]
 pub async fn process_gemini_request(
@@ -633,4 +886,111 @@ pub async fn process_gemini_request(
     processor.process_request(request, api_key, app_handle)
         .await
         .map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_takes_usage_totals_from_final_chunk() {
+        let events = concat!(
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hello\"}]}}],",
+            "\"usageMetadata\":{\"promptTokenCount\":12,\"totalTokenCount\":12}}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\", world\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"!\"}]},\"finishReason\":\"STOP\"}],",
+            "\"usageMetadata\":{\"promptTokenCount\":12,\"candidatesTokenCount\":4,",
+            "\"cachedContentTokenCount\":8,\"totalTokenCount\":16}}",
+        );
+
+        // Deliver in small network chunks that split events mid-line
+        let mut accumulator = GeminiStreamAccumulator::default();
+        let deltas: Vec<String> = events.as_bytes().chunks(7).flat_map(|chunk| accumulator.push(chunk)).collect();
+        let usage = accumulator.finish("Say hello to the world, please");
+
+        assert_eq!(deltas, vec!["Hello", ", world"]);
+        assert_eq!(accumulator.text(), "Hello, world!");
+        assert_eq!(
+            usage,
+            StreamUsageTotals { input_tokens: 12, output_tokens: 4, cached_tokens: 8, estimated: false }
+        );
+    }
+
+    #[test]
+    fn test_stream_without_usage_is_flagged_estimated() {
+        let mut accumulator = GeminiStreamAccumulator::default();
+        accumulator.push(b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"abcdefgh\"}]}}]}\n\n");
+        let usage = accumulator.finish("0123456789abcdef");
+
+        assert!(usage.estimated);
+        assert_eq!((usage.input_tokens, usage.output_tokens, usage.cached_tokens), (4, 2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_preview_includes_system_instruction_and_history() {
+        let processor = GeminiRequestProcessor::new();
+        let message = |role: &str, text: &str| ConversationMessage {
+            role: role.to_string(),
+            content: vec![MessageContent::Text { text: text.to_string() }],
+            timestamp: chrono::Utc::now(),
+            tokens: None,
+        };
+        processor.conversations.lock().await.insert(
+            "gemini-1".to_string(),
+            ConversationContext {
+                session_id: "gemini-1".to_string(),
+                messages: vec![
+                    message("user", "Our API key is AIzaSyA1234567890abcdefghijklmnopqrstu"),
+                    message("assistant", "Noted, the service runs on port 8080."),
+                ],
+                system_instruction: Some("Answer in French.".to_string()),
+                total_tokens: 0,
+                created_at: chrono::Utc::now(),
+                last_updated: chrono::Utc::now(),
+            },
+        );
+        let request = ProcessRequest {
+            prompt: "Which port does it use?".to_string(),
+            model: "gemini-2.5-flash".to_string(),
+            session_id: Some("gemini-1".to_string()),
+            images: Vec::new(),
+            files: Vec::new(),
+            stream: false,
+            priority: RequestPriority::Normal,
+            preprocess_config: None,
+            generation_config: GenerationConfig {
+                temperature: None,
+                max_output_tokens: None,
+                top_k: None,
+                top_p: None,
+                stop_sequences: None,
+                response_mime_type: None,
+                response_schema: None,
+            },
+        };
+
+        let preview = processor.preview_request(request).await.unwrap();
+
+        assert_eq!(preview.system_instruction.as_deref(), Some("Answer in French."));
+        assert_eq!(preview.history_messages, 2);
+        let texts: Vec<&str> = preview.body["contents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|content| content["parts"][0]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Answer in French.",
+                "Our API key is [REDACTED]",
+                "Noted, the service runs on port 8080.",
+                "Which port does it use?",
+            ]
+        );
+        assert_eq!(preview.body["contents"][0]["role"], "system");
+        assert!(preview.endpoint.contains("gemini-2.5-flash:generateContent?key=[REDACTED]"));
+        assert!(preview.estimated_tokens > 0);
+        assert_eq!(processor.conversations.lock().await["gemini-1"].messages.len(), 2);
+    }
+}
@@ -323,10 +323,29 @@ impl CircuitBreaker {
         }
     }
     
+    /// Close the circuit and forget recorded failures
+    pub fn reset(&self) {
+        let mut state = self.state.write().unwrap();
+        *self.failure_count.write().unwrap() = 0;
+        *self.last_failure_time.write().unwrap() = None;
+        *state = CircuitState::Closed;
+    }
+    
     /// Get current state
     pub fn get_state(&self) -> CircuitState {
         *self.state.read().unwrap()
     }
+    
+    /// Whether requests are currently being rejected, without moving to half-open
+    pub fn is_rejecting(&self) -> bool {
+        if self.get_state() != CircuitState::Open {
+            return false;
+        }
+        match *self.last_failure_time.read().unwrap() {
+            Some(last_failure) => last_failure.elapsed() < self.config.timeout,
+            None => false,
+        }
+    }
 }
 
 /// Fallback strategy for graceful degradation
@@ -369,6 +388,44 @@ impl ResilienceManager {
         self.fallback_strategies.write().unwrap().insert(model, strategy);
     }
     
+    /// Models whose circuit breaker is currently rejecting requests
+    pub fn open_circuits(&self) -> Vec<String> {
+        self.circuit_breakers
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, breaker)| breaker.is_rejecting())
+            .map(|(model, _)| model.clone())
+            .collect()
+    }
+    
+    /// Close the circuit breakers of `model`, returning the models whose circuit was reset
+    pub fn reset_circuit(&self, model: &str) -> Vec<String> {
+        self.circuit_breakers
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| crate::models::same_model(name, model))
+            .map(|(name, breaker)| {
+                breaker.reset();
+                name.clone()
+            })
+            .collect()
+    }
+    
+    /// Close every circuit breaker, returning the models whose circuit was reset
+    pub fn reset_all_circuits(&self) -> Vec<String> {
+        self.circuit_breakers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, breaker)| {
+                breaker.reset();
+                name.clone()
+            })
+            .collect()
+    }
+    
     /// Get or create circuit breaker for model
     fn get_circuit_breaker(&self, model: &str) -> CircuitBreaker {
         let mut breakers = self.circuit_breakers.write().unwrap();
@@ -460,11 +517,7 @@ impl HealthCheckManager {
         api_key: &str,
     ) -> Result<HealthStatus> {
         let client = reqwest::Client::new();
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model,
-            api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", model), api_key).await;
         
         let test_body = serde_json::json!({
             "contents": [{
@@ -547,4 +600,41 @@ pub async fn get_gemini_health_status(
     health_manager.check_model_health(&model, &api_key)
         .await
         .map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_reset_circuit_lets_the_next_request_through() {
+        let manager = ResilienceManager::new(RetryConfig { max_attempts: 1, ..RetryConfig::default() });
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counted = attempts.clone();
+        let request = move || {
+            let counted = counted.clone();
+            Box::pin(async move {
+                counted.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }) as std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send>>
+        };
+
+        let breaker = manager.get_circuit_breaker("gemini-2.5-flash");
+        for _ in 0..CircuitBreakerConfig::default().failure_threshold {
+            breaker.record_failure();
+        }
+        let rejected = manager.execute_resilient("gemini-2.5-flash", request.clone()).await;
+        assert!(rejected.unwrap_err().to_string().contains("Circuit breaker is open"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+        assert_eq!(manager.open_circuits(), vec!["gemini-2.5-flash".to_string()]);
+
+        assert_eq!(manager.reset_circuit("gemini-2.5-pro"), Vec::<String>::new());
+        assert_eq!(manager.reset_circuit("gemini-2.5-flash"), vec!["gemini-2.5-flash".to_string()]);
+        assert_eq!(breaker.get_state(), CircuitState::Closed);
+        assert!(manager.open_circuits().is_empty());
+
+        manager.execute_resilient("gemini-2.5-flash", request).await.unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
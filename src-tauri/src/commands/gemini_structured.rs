@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::gemini::{
+    build_gemini_request_body, detect_gemini_block, gemini_model_endpoint, generate_secure_gemini_session_id,
+    get_gemini_api_key_sync, inspect_finish_reason, load_gemini_config_sync, send_gemini_request, GeminiConfig,
+};
+
+/// The only MIME type whose output can be checked against a schema
+pub const JSON_MIME_TYPE: &str = "application/json";
+
+/// Output constraints added to a request's `generationConfig`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StructuredOutput {
+    /// Defaults to `application/json`
+    #[serde(default)]
+    pub response_mime_type: Option<String>,
+    /// Gemini's OpenAPI schema subset: `type`, `properties`, `required`, `items`, `enum`, `nullable`
+    #[serde(default)]
+    pub response_schema: Option<Value>,
+}
+
+impl StructuredOutput {
+    pub fn mime_type(&self) -> &str {
+        self.response_mime_type.as_deref().unwrap_or(JSON_MIME_TYPE)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.response_schema.is_some() && self.mime_type() != JSON_MIME_TYPE {
+            return Err(format!(
+                "A response schema requires the {} MIME type, got {}",
+                JSON_MIME_TYPE,
+                self.mime_type()
+            ));
+        }
+        if let Some(schema) = &self.response_schema {
+            if !schema.is_object() {
+                return Err("The response schema must be a JSON object".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Set `responseMimeType` and `responseSchema` on a generateContent request body
+    pub fn apply(&self, request_body: &mut Value) {
+        request_body["generationConfig"]["responseMimeType"] = Value::from(self.mime_type());
+        if let Some(schema) = &self.response_schema {
+            request_body["generationConfig"]["responseSchema"] = schema.clone();
+        }
+    }
+}
+
+/// A structured Gemini answer that matched the requested schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiStructuredResult {
+    pub data: Value,
+    pub model: String,
+    pub tokens_used: Option<u32>,
+    /// The answer hit the output token limit; truncated JSON usually fails to parse first
+    pub truncated: bool,
+}
+
+fn type_matches(schema_type: &str, value: &Value) -> Option<bool> {
+    let matches = match schema_type.to_ascii_uppercase().as_str() {
+        "OBJECT" => value.is_object(),
+        "ARRAY" => value.is_array(),
+        "STRING" => value.is_string(),
+        "NUMBER" => value.is_number(),
+        "INTEGER" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "BOOLEAN" => value.is_boolean(),
+        _ => return None,
+    };
+    Some(matches)
+}
+
+fn collect_violations(schema: &Value, value: &Value, path: &str, violations: &mut Vec<String>) {
+    if value.is_null() {
+        if !schema["nullable"].as_bool().unwrap_or(false) && schema.get("type").is_some() {
+            violations.push(format!("{} is null but the schema does not allow null", path));
+        }
+        return;
+    }
+    if let Some(schema_type) = schema["type"].as_str() {
+        match type_matches(schema_type, value) {
+            Some(true) => {}
+            Some(false) => {
+                violations.push(format!("{} should be of type {}, got {}", path, schema_type, value));
+                return;
+            }
+            None => {
+                violations.push(format!("{} has unsupported schema type {}", path, schema_type));
+                return;
+            }
+        }
+    }
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            violations.push(format!("{} should be one of {}, got {}", path, Value::from(allowed.clone()), value));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        for name in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                violations.push(format!("{} is missing required property {}", path, name));
+            }
+        }
+        if let Some(properties) = schema["properties"].as_object() {
+            for (name, property_schema) in properties {
+                if let Some(property) = object.get(name) {
+                    collect_violations(property_schema, property, &format!("{}.{}", path, name), violations);
+                }
+            }
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        let count = items.len() as u64;
+        if let Some(min) = schema["minItems"].as_u64().filter(|&min| count < min) {
+            violations.push(format!("{} has {} item(s), fewer than the minimum of {}", path, count, min));
+        }
+        if let Some(max) = schema["maxItems"].as_u64().filter(|&max| count > max) {
+            violations.push(format!("{} has {} item(s), more than the maximum of {}", path, count, max));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                collect_violations(item_schema, item, &format!("{}[{}]", path, index), violations);
+            }
+        }
+    }
+}
+
+/// Every place `value` departs from `schema`, as `$`-rooted paths with the reason
+pub fn schema_violations(schema: &Value, value: &Value) -> Vec<String> {
+    let mut violations = Vec::new();
+    collect_violations(schema, value, "$", &mut violations);
+    violations
+}
+
+/// Parse the answer text of a generateContent response and check it against `schema`
+pub fn parse_structured_response(
+    response: &Value,
+    schema: Option<&Value>,
+    model: &str,
+    session_id: &str,
+) -> Result<GeminiStructuredResult, String> {
+    let candidate = &response["candidates"][0];
+    if candidate.is_null() {
+        return Err("No response candidates found. This may be due to safety filters or content policy restrictions.".to_string());
+    }
+    let finish_state = inspect_finish_reason(candidate, session_id)?;
+    let text: String = candidate["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part["text"].as_str())
+        .collect();
+    if text.trim().is_empty() {
+        return Err("Gemini returned no content for the structured request".to_string());
+    }
+
+    let data: Value = serde_json::from_str(text.trim()).map_err(|e| {
+        let truncation = if finish_state.truncated { " (the answer hit the output token limit)" } else { "" };
+        format!("Gemini did not return valid JSON{}: {}", truncation, e)
+    })?;
+    if let Some(schema) = schema {
+        let violations = schema_violations(schema, &data);
+        if !violations.is_empty() {
+            return Err(format!(
+                "Gemini's response does not match the schema:\n• {}",
+                violations.join("\n• ")
+            ));
+        }
+    }
+
+    Ok(GeminiStructuredResult {
+        data,
+        model: model.to_string(),
+        tokens_used: response["usageMetadata"]["totalTokenCount"].as_u64().map(|n| n as u32),
+        truncated: finish_state.truncated,
+    })
+}
+
+/// Run a prompt on Gemini in JSON mode and return the parsed, schema-checked answer
+///
+/// Gemini constrains its own output to the `responseSchema`, but the text is still
+/// checked here so callers get either a value they can rely on or every violation.
+#[tauri::command]
+pub async fn execute_gemini_structured(
+    prompt: String,
+    model: String,
+    response_schema: Option<Value>,
+    response_mime_type: Option<String>,
+    temperature: Option<f32>,
+    max_output_tokens: Option<u32>,
+    app_handle: tauri::AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<GeminiStructuredResult, String> {
+    let trimmed_prompt = prompt.trim();
+    if trimmed_prompt.is_empty() {
+        return Err("Prompt cannot be empty".to_string());
+    }
+    let trimmed_model = model.trim();
+    let trimmed_model = crate::models::canonicalize(trimmed_model)
+        .filter(|m| m.provider == crate::models::ModelProvider::Gemini)
+        .map_or(trimmed_model, |m| m.id);
+    let endpoint = gemini_model_endpoint(trimmed_model)
+        .ok_or_else(|| format!("🤖 Model '{}' is not supported", trimmed_model))?;
+
+    let structured = StructuredOutput { response_mime_type, response_schema };
+    structured.validate()?;
+
+    let (api_key, stored_config, timeouts) = {
+        let conn = db.lock_conn();
+        let stored_config = load_gemini_config_sync(&conn).unwrap_or_else(|e| {
+            log::warn!("Ignoring stored Gemini config: {}", e);
+            GeminiConfig::default()
+        });
+        let timeouts = super::provider_timeouts::load_provider_timeouts(&conn).gemini;
+        (get_gemini_api_key_sync(&conn)?, stored_config, timeouts)
+    };
+    if api_key.is_empty() {
+        return Err("Gemini API key is not configured. Please set your API key in Settings.".to_string());
+    }
+    let generation_config = stored_config.merged_with(&GeminiConfig {
+        temperature,
+        max_tokens: max_output_tokens,
+        ..Default::default()
+    });
+    generation_config.validate()?;
+
+    let mut request_body = build_gemini_request_body(trimmed_prompt, &generation_config);
+    structured.apply(&mut request_body);
+
+    let session_id = generate_secure_gemini_session_id("structured", trimmed_model);
+    log::info!("Sending structured Gemini request for session: {} with model: {}", session_id, trimmed_model);
+    let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}:generateContent", endpoint), &api_key).await;
+    let response = send_gemini_request(
+        app_handle,
+        timeouts.build_client(false)?,
+        url,
+        request_body,
+        session_id.clone(),
+        trimmed_model.to_string(),
+        endpoint.to_string(),
+        timeouts.request_secs,
+    )
+    .await?;
+
+    if let Some(block) = detect_gemini_block(&response, &session_id) {
+        return Err(block.message);
+    }
+    parse_structured_response(&response, structured.response_schema.as_ref(), trimmed_model, &session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gemini_response(text: &str) -> Value {
+        serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": text}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 20, "candidatesTokenCount": 30, "totalTokenCount": 50}
+        })
+    }
+
+    #[test]
+    fn test_json_output_request_conforms_to_schema() {
+        let schema = serde_json::json!({
+            "type": "OBJECT",
+            "properties": {
+                "title": {"type": "STRING"},
+                "priority": {"type": "STRING", "enum": ["low", "high"]},
+                "tags": {"type": "ARRAY", "items": {"type": "STRING"}},
+                "estimate_hours": {"type": "INTEGER", "nullable": true}
+            },
+            "required": ["title", "priority", "tags"]
+        });
+        let structured = StructuredOutput { response_mime_type: None, response_schema: Some(schema.clone()) };
+        structured.validate().unwrap();
+        let mut body = build_gemini_request_body("Summarize the bug as a ticket", &GeminiConfig::default());
+        structured.apply(&mut body);
+        assert_eq!(body["generationConfig"]["responseMimeType"], "application/json");
+        assert_eq!(body["generationConfig"]["responseSchema"], schema);
+        assert!(body["generationConfig"]["temperature"].is_number());
+
+        let response = gemini_response(r#"{"title": "Crash on save", "priority": "high", "tags": ["editor"], "estimate_hours": null}"#);
+        let result = parse_structured_response(&response, Some(&schema), "gemini-2.5-flash", "s1").unwrap();
+        assert_eq!(result.data["title"], "Crash on save");
+        assert!(schema_violations(&schema, &result.data).is_empty());
+        assert_eq!(result.tokens_used, Some(50));
+
+        let response = gemini_response(r#"{"title": 7, "priority": "urgent", "tags": ["editor", 3]}"#);
+        let err = parse_structured_response(&response, Some(&schema), "gemini-2.5-flash", "s1").unwrap_err();
+        assert!(err.contains("$.title should be of type STRING"), "{}", err);
+        assert!(err.contains("$.priority should be one of"), "{}", err);
+        assert!(err.contains("$.tags[1] should be of type STRING"), "{}", err);
+
+        let err = parse_structured_response(&gemini_response("Sure! Here it is"), Some(&schema), "gemini-2.5-flash", "s1")
+            .unwrap_err();
+        assert!(err.starts_with("Gemini did not return valid JSON"), "{}", err);
+        let wrong_mime = StructuredOutput { response_mime_type: Some("text/plain".to_string()), response_schema: Some(schema) };
+        assert!(wrong_mime.validate().is_err());
+    }
+}
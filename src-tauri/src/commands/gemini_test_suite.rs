@@ -308,10 +308,7 @@ impl GeminiTestSuite {
             "v1beta"
         };
         
-        let url = format!(
-            "https://generativelanguage.googleapis.com/{}/models/{}:generateContent?key={}",
-            api_version, model_id, self.api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("{}/models/{}:generateContent", api_version, model_id), &self.api_key).await;
         
         // Build request body
         let mut request_body = serde_json::json!({
@@ -2,8 +2,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tauri::{State, Emitter};
+use tauri::{State, Emitter, Manager};
 use super::agents::AgentDb;
+use super::dashboard::{record_failover_event, FailoverReason};
+use super::request_hooks::{request_hooks, RequestHooks};
 use log;
 
 /// Universal Gemini model information
@@ -74,10 +76,7 @@ impl GeminiModelRegistry {
         let client = reqwest::Client::new();
         
         // Try v1 API first (for newer models)
-        let v1_url = format!(
-            "https://generativelanguage.googleapis.com/v1/models?key={}",
-            self.api_key
-        );
+        let v1_url = super::gemini_backend::gemini_url("v1/models", &self.api_key).await;
         
         let mut all_models = Vec::new();
         
@@ -98,10 +97,7 @@ impl GeminiModelRegistry {
         }
         
         // Try v1beta API (for experimental and older models)
-        let v1beta_url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models?key={}",
-            self.api_key
-        );
+        let v1beta_url = super::gemini_backend::gemini_url("v1beta/models", &self.api_key).await;
         
         match client.get(&v1beta_url).send().await {
             Ok(response) if response.status().is_success() => {
@@ -219,10 +215,7 @@ impl GeminiModelRegistry {
             "v1beta"
         };
         
-        let url = format!(
-            "https://generativelanguage.googleapis.com/{}/models/{}:generateContent?key={}",
-            api_version, model_id, self.api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("{}/models/{}:generateContent", api_version, model_id), &self.api_key).await;
         
         // Simple test request
         let test_body = serde_json::json!({
@@ -364,12 +357,25 @@ impl UniversalGeminiExecutor {
         
         log::info!("Executing with fallback chain: {:?}", fallback_chain);
         
+        // Model that gave up last and its final error, recorded once the next model is tried
+        let mut last_failure: Option<(String, String)> = None;
+        let hooks = request_hooks(&app_handle);
+        
         for model in fallback_chain {
             log::info!("Trying model: {}", model);
             
+            if let Some((from_model, error)) = last_failure.take() {
+                let db = app_handle.state::<AgentDb>();
+                if let Err(e) = record_failover_event(
+                    &db.lock_conn(), &from_model, &model, FailoverReason::from_error(&error), Some(&error),
+                ) {
+                    log::warn!("Failed to record failover from {} to {}: {}", from_model, model, e);
+                }
+            }
+            
             // Try multiple times with the same model
             for attempt in 0..self.max_retries {
-                match self.execute_single_request(&prompt, &model).await {
+                match self.execute_single_request(&prompt, &model, &hooks).await {
                     Ok(response) => {
                         log::info!("Success with model {} on attempt {}", model, attempt + 1);
                         
@@ -382,11 +388,15 @@ impl UniversalGeminiExecutor {
                         
                         return Ok(response);
                     },
+                    // The same secrets would be held back on every model
+                    Err(e) if super::prompt_secrets::is_held_back(&e) => return Err(e),
                     Err(e) => {
                         log::warn!("Attempt {} with model {} failed: {}", attempt + 1, model, e);
+                        let retryable = self.is_retryable_error(&e);
+                        last_failure = Some((model.clone(), e));
                         
                         // Check if error is retryable
-                        if !self.is_retryable_error(&e) {
+                        if !retryable {
                             break; // Move to next model
                         }
                         
@@ -407,22 +417,19 @@ impl UniversalGeminiExecutor {
         &self,
         prompt: &str,
         model: &str,
+        hooks: &RequestHooks,
     ) -> Result<serde_json::Value, String> {
         let client = reqwest::Client::new();
         
         // Determine API version
         let api_version = self.determine_api_version(model);
         
-        let url = format!(
-            "https://generativelanguage.googleapis.com/{}/models/{}:generateContent?key={}",
-            api_version, model, self.registry.api_key
-        );
+        let url = super::gemini_backend::gemini_url(&format!("{}/models/{}:generateContent", api_version, model), &self.registry.api_key).await;
         
         let request_body = self.build_request_body(prompt, model);
         
-        let response = client
-            .post(&url)
-            .json(&request_body)
+        let response = hooks
+            .prepare("gemini", client.post(&url), request_body)?
             .send()
             .await
             .map_err(|e| format!("Network error: {}", e))?;
@@ -612,4 +619,22 @@ pub async fn get_gemini_fallback_chain(
     
     let registry = GeminiModelRegistry::new(api_key);
     Ok(registry.get_fallback_chain(&model).await)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::prompt_secrets::{is_held_back, PromptSecretMode};
+
+    #[tokio::test]
+    async fn test_single_request_is_screened_for_secrets() {
+        let executor = UniversalGeminiExecutor::new("test-key".to_string());
+        let mut hooks = RequestHooks::default();
+        hooks.secret_mode = PromptSecretMode::Warn;
+
+        let error = executor
+            .execute_single_request("Deploy with token=9fQ2xLm7Rt4Kp1Zw please", "gemini-2.5-flash", &hooks)
+            .await
+            .unwrap_err();
+        assert!(is_held_back(&error), "{}", error);
+    }
+}
@@ -9,12 +9,10 @@ pub struct SavedImage {
     pub filename: String,
 }
 
-#[tauri::command]
-pub async fn save_base64_image(
-    app: AppHandle,
-    base64_data: String,
-    mime_type: Option<String>,
-) -> Result<SavedImage, String> {
+/// The mime type and bytes of a base64 image, given bare or as a `data:` URL
+///
+/// A bare image takes `mime_type`, or PNG when none is given.
+pub fn decode_base64_image(base64_data: &str, mime_type: Option<String>) -> Result<(String, Vec<u8>), String> {
     // Parse the base64 data URL if it includes the data: prefix
     let (actual_mime_type, base64_content) = if base64_data.starts_with("data:") {
         // Extract mime type and base64 content from data URL
@@ -29,7 +27,7 @@ pub async fn save_base64_image(
             .and_then(|s| s.strip_suffix(";base64"))
             .unwrap_or("image/png");
         
-        (mime.to_string(), parts[1].to_string())
+        (mime.to_string(), parts[1])
     } else {
         // Use provided mime type or default to png
         (mime_type.unwrap_or_else(|| "image/png".to_string()), base64_data)
@@ -37,9 +35,20 @@ pub async fn save_base64_image(
     
     // Decode base64
     let image_data = general_purpose::STANDARD
-        .decode(&base64_content)
+        .decode(base64_content)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
     
+    Ok((actual_mime_type, image_data))
+}
+
+#[tauri::command]
+pub async fn save_base64_image(
+    app: AppHandle,
+    base64_data: String,
+    mime_type: Option<String>,
+) -> Result<SavedImage, String> {
+    let (actual_mime_type, image_data) = decode_base64_image(&base64_data, mime_type)?;
+    
     // Determine file extension from mime type
     let extension = match actual_mime_type.as_str() {
         "image/jpeg" => "jpg",
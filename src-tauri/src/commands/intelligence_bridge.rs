@@ -3,13 +3,15 @@ use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use tauri::State;
 use uuid::Uuid;
 use log::{debug, error, info, warn};
 
 use super::agents::AgentDb;
+use super::context_guard::estimate_tokens;
+use super::intelligent_routing::AiModelBenchmark;
 use super::session_manager::{SessionMessage, SessionMetadata};
 
 /// Universal context format that all models can understand
@@ -458,6 +460,19 @@ impl IntelligenceBridge {
         contexts.get(session_id).cloned()
     }
     
+    /// Append a checkpoint to a session's task continuity
+    pub fn add_checkpoint(&self, session_id: &str, checkpoint: Checkpoint) -> Result<UniversalContext> {
+        let mut contexts = self.contexts.lock().unwrap();
+        let context = contexts
+            .get_mut(session_id)
+            .ok_or_else(|| anyhow::anyhow!("Context not found for session {}", session_id))?;
+        
+        context.task_continuity.checkpoints.push(checkpoint);
+        context.updated_at = Utc::now();
+        
+        Ok(context.clone())
+    }
+    
     /// Transfer context between models
     pub fn transfer_context(
         &self,
@@ -584,9 +599,177 @@ pub enum ContextUpdate {
     },
 }
 
+/// Each model gets a quarter of its context window for rendered context; the rest is left for the prompt and reply
+const CONTEXT_WINDOW_SHARE: u64 = 4;
+/// Longest rendered item, so one long document can't crowd out everything after it
+const MAX_ITEM_CHARS: usize = 600;
+/// Tokens held back for the note listing omitted items
+const OMISSION_NOTE_TOKENS: u64 = 64;
+
+/// Sections of rendered context, in priority order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ContextSection {
+    Decisions,
+    CodeSections,
+    Constraints,
+    Documentation,
+    ExternalResources,
+    CodePatterns,
+}
+
+impl ContextSection {
+    fn title(self) -> &'static str {
+        match self {
+            ContextSection::Decisions => "Decisions",
+            ContextSection::CodeSections => "Code sections",
+            ContextSection::Constraints => "Constraints",
+            ContextSection::Documentation => "Documentation",
+            ContextSection::ExternalResources => "External resources",
+            ContextSection::CodePatterns => "Code patterns",
+        }
+    }
+}
+
+/// One rendered line of context; higher `rank` comes first within a section
+struct ContextItem {
+    section: ContextSection,
+    rank: f64,
+    text: String,
+}
+
+/// `text` collapsed onto one line and cut to `MAX_ITEM_CHARS`
+fn clip(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(MAX_ITEM_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line,
+    }
+}
+
+fn context_items(context: &UniversalContext) -> Vec<ContextItem> {
+    let mut items = Vec::new();
+    let mut push = |section, rank: f64, text: String| items.push(ContextItem { section, rank, text: clip(&text) });
+
+    // Newest first among equally confident decisions
+    for decision in context.current_work.decisions.iter().rev() {
+        push(
+            ContextSection::Decisions,
+            f64::from(decision.confidence),
+            format!("- {} — {} (confidence {:.2})", decision.decision, decision.rationale, decision.confidence),
+        );
+    }
+    for file in &context.current_work.active_files {
+        let rank = file.last_modified.timestamp_millis() as f64;
+        if file.relevant_sections.is_empty() {
+            let pending = if file.pending_changes.is_empty() {
+                String::new()
+            } else {
+                format!(" (pending: {})", file.pending_changes.join("; "))
+            };
+            push(ContextSection::CodeSections, rank, format!("- {}{}", file.path, pending));
+        }
+        for section in &file.relevant_sections {
+            push(
+                ContextSection::CodeSections,
+                rank,
+                format!("- {}:{}-{} — {}", file.path, section.start_line, section.end_line, section.purpose),
+            );
+        }
+    }
+    for constraint in &context.references.constraints {
+        push(
+            ContextSection::Constraints,
+            f64::from(constraint.priority),
+            format!("- [priority {}] {} ({})", constraint.priority, constraint.rule, constraint.context),
+        );
+    }
+    for doc in &context.references.documentation {
+        push(
+            ContextSection::Documentation,
+            f64::from(doc.relevance_score),
+            format!("- {} ({}): {}", doc.title, doc.source, doc.content),
+        );
+    }
+    for resource in &context.references.external_resources {
+        push(
+            ContextSection::ExternalResources,
+            f64::from(resource.relevance_score),
+            format!("- {} <{}>: {}", resource.title, resource.url, resource.summary),
+        );
+    }
+    for pattern in &context.references.code_patterns {
+        push(
+            ContextSection::CodePatterns,
+            f64::from(pattern.usage_count),
+            format!("- {}: {} (used {} times)", pattern.name, pattern.description, pattern.usage_count),
+        );
+    }
+    items
+}
+
+/// Render `context` as prompt text that fits `model`'s share of its context window
+///
+/// Items are ranked by section (decisions, code sections, constraints,
+/// documentation, external resources, code patterns) and then by their own
+/// confidence, recency, priority, relevance or usage. Once the budget runs out
+/// the remaining, lowest-priority items are left out and a closing note counts
+/// them per section.
+pub fn render_context_for_model(context: &UniversalContext, model: &AiModelBenchmark) -> String {
+    let budget = u64::from(model.context_window) / CONTEXT_WINDOW_SHARE;
+    let work = &context.current_work;
+    let mut lines = vec![
+        format!("# Shared context for {} ({} token budget)", model.model_id, budget),
+        "## Current work".to_string(),
+        format!("Task: {}", work.current_task.as_deref().map_or_else(|| "none".to_string(), clip)),
+        format!("State: {} ({}% done)", work.work_state, work.progress),
+        format!("Last model: {}", work.current_model),
+    ];
+    let line_tokens = |line: &str| estimate_tokens(line) + 1;
+    let mut used: u64 = lines.iter().map(|line| line_tokens(line)).sum();
+
+    let mut items = context_items(context);
+    items.sort_by(|a, b| a.section.cmp(&b.section).then(b.rank.total_cmp(&a.rank)));
+
+    let mut included: BTreeMap<ContextSection, Vec<String>> = BTreeMap::new();
+    let mut omitted: BTreeMap<ContextSection, usize> = BTreeMap::new();
+    for item in items {
+        let heading = if included.contains_key(&item.section) {
+            0
+        } else {
+            line_tokens(&format!("## {}", item.section.title()))
+        };
+        let cost = heading + line_tokens(&item.text);
+        if omitted.is_empty() && used + cost + OMISSION_NOTE_TOKENS <= budget {
+            used += cost;
+            included.entry(item.section).or_default().push(item.text);
+        } else {
+            *omitted.entry(item.section).or_default() += 1;
+        }
+    }
+
+    for (section, texts) in included {
+        lines.push(format!("## {}", section.title()));
+        lines.extend(texts);
+    }
+    if !omitted.is_empty() {
+        let counts = omitted
+            .iter()
+            .map(|(section, count)| format!("{} {}", count, section.title().to_lowercase()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push("## Omitted".to_string());
+        lines.push(format!(
+            "{} lower-priority item(s) left out to fit the budget: {}",
+            omitted.values().sum::<usize>(),
+            counts
+        ));
+    }
+    lines.join("\n")
+}
+
 /// Initialize intelligence bridge tables
 pub async fn init_intelligence_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     // Create universal contexts table
     conn.execute(
@@ -673,7 +856,7 @@ pub async fn store_universal_context(
     context: UniversalContext,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let context_json = serde_json::to_string(&context)
         .map_err(|e| format!("Failed to serialize context: {}", e))?;
@@ -701,7 +884,7 @@ pub async fn load_universal_context(
     session_id: String,
     db: State<'_, AgentDb>,
 ) -> Result<Option<UniversalContext>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let result = conn.query_row(
         "SELECT context_data FROM universal_contexts WHERE session_id = ? ORDER BY updated_at DESC LIMIT 1",
@@ -736,7 +919,7 @@ pub async fn transfer_context_between_sessions(
     
     // Store context first (using clone to avoid borrow issues)
     {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let conn = db.lock_conn();
         let context_json = serde_json::to_string(&context)
             .map_err(|e| format!("Failed to serialize context: {}", e))?;
         
@@ -756,7 +939,7 @@ pub async fn transfer_context_between_sessions(
     }
     
     // Record transfer history
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     let transfer_id = Uuid::new_v4().to_string();
     
     conn.execute(
@@ -787,7 +970,7 @@ pub async fn store_shared_knowledge(
     created_by: String,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     let now = Utc::now().to_rfc3339();
     
     conn.execute(
@@ -816,7 +999,7 @@ pub async fn get_shared_knowledge(
     knowledge_type: Option<String>,
     db: State<'_, AgentDb>,
 ) -> Result<HashMap<String, String>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let (query, params): (String, Vec<&dyn rusqlite::ToSql>) = if let Some(ref kt) = knowledge_type {
         (
@@ -856,7 +1039,7 @@ pub async fn record_model_collaboration(
     result: Option<String>,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let session_ids_json = serde_json::to_string(&session_ids)
         .map_err(|e| format!("Failed to serialize session IDs: {}", e))?;
@@ -887,7 +1070,7 @@ pub async fn get_collaboration_history(
     project_id: String,
     db: State<'_, AgentDb>,
 ) -> Result<Vec<JsonValue>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let mut stmt = conn.prepare(
         "SELECT * FROM model_collaborations WHERE project_id = ? ORDER BY timestamp DESC"
@@ -913,4 +1096,71 @@ pub async fn get_collaboration_history(
     }
     
     Ok(collaborations)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::intelligent_routing::load_model_benchmarks;
+
+    fn benchmark(model_id: &str) -> AiModelBenchmark {
+        let conn = Connection::open_in_memory().unwrap();
+        load_model_benchmarks(&conn).unwrap().into_iter().find(|b| b.model_id == model_id).unwrap()
+    }
+
+    fn documented_context(docs: usize) -> UniversalContext {
+        let mut context = IntelligenceBridge::new().create_context("session", "project", "sonnet-4").unwrap();
+        context.current_work.current_task = Some("Port the parser to the new AST".to_string());
+        context.current_work.decisions.push(Decision {
+            id: "d-1".to_string(),
+            decision: "Keep the old lexer".to_string(),
+            rationale: "It already handles every token kind".to_string(),
+            alternatives_considered: Vec::new(),
+            timestamp: Utc::now(),
+            model_used: "sonnet-4".to_string(),
+            confidence: 0.9,
+        });
+        for n in 0..docs {
+            context.references.documentation.push(Documentation {
+                id: format!("doc-{}", n),
+                title: format!("Doc {}", n),
+                content: "grammar notes ".repeat(40),
+                source: "wiki".to_string(),
+                relevance_score: n as f32 / docs as f32,
+                added_by: "sonnet-4".to_string(),
+                timestamp: Utc::now(),
+            });
+        }
+        context
+    }
+
+    #[test]
+    fn test_render_for_model_fits_budget_and_keeps_highest_priority() {
+        let context = documented_context(100);
+        let docs = |rendered: &str| -> Vec<String> {
+            rendered
+                .lines()
+                .filter(|line| line.starts_with("- Doc "))
+                .map(|line| line.split(" (").next().unwrap().to_string())
+                .collect()
+        };
+
+        let small = render_context_for_model(&context, &benchmark("codellama:latest"));
+        assert!(estimate_tokens(&small) <= 16384 / CONTEXT_WINDOW_SHARE);
+        assert!(small.starts_with("# Shared context for codellama:latest (4096 token budget)\n## Current work\n"));
+        assert!(small.contains("## Decisions\n- Keep the old lexer — It already handles every token kind (confidence 0.90)"));
+        let kept = docs(&small);
+        assert!(!kept.is_empty() && kept.len() < 100);
+        assert_eq!(kept[0], "- Doc 99");
+        let omitted = 100 - kept.len();
+        assert!(small.ends_with(&format!(
+            "## Omitted\n{} lower-priority item(s) left out to fit the budget: {} documentation",
+            omitted, omitted
+        )));
+
+        let large = render_context_for_model(&context, &benchmark("sonnet-4"));
+        assert_eq!(docs(&large).len(), 100);
+        assert!(!large.contains("## Omitted"));
+        assert_eq!(render_context_for_model(&context, &benchmark("sonnet-4")), large);
+    }
+}
@@ -3,10 +3,12 @@ use log::{debug, info, warn}; // Removed unused 'error'
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use regex::Regex;
-use tauri::{command, AppHandle, Manager}; // Removed unused 'State'
-use rusqlite::{Connection, Result as SqliteResult};
+use tauri::{command, AppHandle, Manager, State};
+use rusqlite::{params, Connection, Result as SqliteResult};
 use chrono::{DateTime, Utc};
 use super::agents::AgentDb;
+use super::settings_diff::Setting;
+use super::simple_model_validator::AvailableProviders;
 
 /// Tool type that can be invoked
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -27,6 +29,16 @@ pub struct ToolInvocation {
     pub priority: i32,
 }
 
+/// One ordered step of a compound request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubTask {
+    pub index: usize,
+    pub text: String,
+    pub intent: String,
+    pub domain: String,
+    pub recommended_tool: Option<ToolInvocation>,
+}
+
 /// Routing result containing all tools to invoke
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingResult {
@@ -34,8 +46,103 @@ pub struct RoutingResult {
     pub detected_intent: String,
     pub complexity_score: f32,
     pub domain: String,
+    /// Ordered sub-tasks for sequential execution; a single entry for simple requests
+    #[serde(default)]
+    pub sub_tasks: Vec<SubTask>,
+}
+
+pub(crate) const ROUTING_CATEGORIES_SETTINGS_KEY: &str = "routing_tool_categories";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<RoutingToolCategories>(ROUTING_CATEGORIES_SETTINGS_KEY)];
+
+fn enabled() -> bool {
+    true
+}
+
+/// Which kinds of tool routing may invoke; every category is enabled unless switched off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingToolCategories {
+    #[serde(default = "enabled")]
+    pub agents: bool,
+    #[serde(default = "enabled")]
+    pub slash_commands: bool,
+    #[serde(default = "enabled")]
+    pub mcp_servers: bool,
+    #[serde(default = "enabled")]
+    pub superclaude: bool,
+}
+
+impl Default for RoutingToolCategories {
+    fn default() -> Self {
+        Self { agents: true, slash_commands: true, mcp_servers: true, superclaude: true }
+    }
+}
+
+impl RoutingToolCategories {
+    pub fn allows(&self, tool_type: &ToolType) -> bool {
+        match tool_type {
+            ToolType::Agent(_) => self.agents,
+            ToolType::SlashCommand(_) => self.slash_commands,
+            ToolType::SuperClaude => self.superclaude,
+            ToolType::McpServer(_) => self.mcp_servers,
+        }
+    }
 }
 
+fn load_routing_tool_categories(conn: &Connection) -> RoutingToolCategories {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![ROUTING_CATEGORIES_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_routing_tool_categories(conn: &Connection, categories: &RoutingToolCategories) -> Result<(), String> {
+    let value = serde_json::to_string(categories)
+        .map_err(|e| format!("Failed to serialize routing tool categories: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![ROUTING_CATEGORIES_SETTINGS_KEY, value],
+    ).map_err(|e| format!("Failed to save routing tool categories: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+static MATCHER_BUILDS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+lazy_static::lazy_static! {
+    /// The keyword tables never change, so every routing call shares one matcher
+    static ref SHARED_MATCHER: PatternMatcher = {
+        #[cfg(test)]
+        MATCHER_BUILDS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        PatternMatcher::new()
+    };
+
+    static ref MCP_PACKAGE_REGEX: Regex =
+        Regex::new(r"(?:install|add|setup)\s+(\w+)(?:\s+mcp)?").unwrap();
+
+    /// "1. ", "2) " step markers that start a sub-task
+    static ref NUMBERED_STEP_REGEX: Regex = Regex::new(r"(?m)(?:^|\s)\d+[.)]\s+").unwrap();
+
+    /// Sequencing words ("and then", "after that") that separate sub-tasks
+    static ref SUBTASK_CONNECTOR_REGEX: Regex =
+        Regex::new(r"(?i)[,;]?\s*\b(?:and\s+then|and\s+also|after\s+that|then)\b,?\s*").unwrap();
+}
+
+/// Common MCP server names and the words that refer to them
+const KNOWN_MCP_SERVERS: &[(&str, &[&str])] = &[
+    ("playwright", &["playwright", "browser", "e2e"]),
+    ("sequential_thinking", &["sequential", "thinking", "reasoning"]),
+    ("github", &["github", "git", "repository"]),
+    ("filesystem", &["file", "directory", "fs"]),
+    ("slack", &["slack", "messaging"]),
+    ("postgres", &["postgres", "postgresql", "database", "db"]),
+    ("fetch", &["fetch", "http", "api", "rest"]),
+];
+
 /// Pattern matcher for intelligent routing
 #[derive(Debug)]
 pub struct PatternMatcher {
@@ -46,6 +153,11 @@ pub struct PatternMatcher {
 }
 
 impl PatternMatcher {
+    /// Matcher shared by all callers, built on first use
+    pub fn shared() -> &'static PatternMatcher {
+        &SHARED_MATCHER
+    }
+
     pub fn new() -> Self {
         let mut agent_patterns = HashMap::new();
         let mut command_patterns = HashMap::new();
@@ -109,8 +221,12 @@ impl PatternMatcher {
     }
     
     pub fn analyze_input(&self, input: &str) -> RoutingResult {
+        self.analyze_input_with(input, &RoutingToolCategories::default())
+    }
+    
+    /// `analyze_input`, invoking only tools from the enabled categories
+    pub fn analyze_input_with(&self, input: &str, categories: &RoutingToolCategories) -> RoutingResult {
         let input_lower = input.to_lowercase();
-        let mut invocations = Vec::new();
         
         // Calculate complexity score
         let complexity_score = self.calculate_complexity(&input_lower);
@@ -121,8 +237,53 @@ impl PatternMatcher {
         // Detect intent
         let detected_intent = self.detect_intent(&input_lower);
         
+        let invocations = self.collect_invocations(&input_lower, categories);
+        
+        // Split compound requests into ordered sub-tasks
+        let sub_tasks = self.segment_subtasks_with(input, categories);
+        
+        RoutingResult {
+            invocations,
+            detected_intent,
+            complexity_score,
+            domain,
+            sub_tasks,
+        }
+    }
+    
+    /// Split a compound request ("analyze X then fix Y", numbered steps) into ordered sub-tasks
+    pub fn segment_subtasks(&self, input: &str) -> Vec<SubTask> {
+        self.segment_subtasks_with(input, &RoutingToolCategories::default())
+    }
+    
+    fn segment_subtasks_with(&self, input: &str, categories: &RoutingToolCategories) -> Vec<SubTask> {
+        let segments: Vec<String> = NUMBERED_STEP_REGEX.split(input)
+            .flat_map(|step| SUBTASK_CONNECTOR_REGEX.split(step).map(|s| s.to_string()).collect::<Vec<_>>())
+            .map(|segment| segment.trim().trim_matches(|c: char| c == ',' || c == ';' || c == '.').trim().to_string())
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        
+        segments.into_iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let text_lower = text.to_lowercase();
+                SubTask {
+                    index,
+                    intent: self.detect_intent(&text_lower),
+                    domain: self.detect_domain(&text_lower),
+                    recommended_tool: self.collect_invocations(&text_lower, categories).into_iter().next(),
+                    text,
+                }
+            })
+            .collect()
+    }
+    
+    /// Match agents, commands, MCP servers and SuperClaude triggers, highest priority first
+    fn collect_invocations(&self, input_lower: &str, categories: &RoutingToolCategories) -> Vec<ToolInvocation> {
+        let mut invocations = Vec::new();
+        
         // Check for SuperClaude triggers
-        if self.should_use_superclaude(&input_lower) {
+        if self.should_use_superclaude(input_lower) {
             invocations.push(ToolInvocation {
                 tool_type: ToolType::SuperClaude,
                 confidence: 0.95,
@@ -133,7 +294,7 @@ impl PatternMatcher {
         
         // Match agents
         for (agent, patterns) in &self.agent_patterns {
-            let score = self.calculate_pattern_score(&input_lower, patterns);
+            let score = self.calculate_pattern_score(input_lower, patterns);
             if score > 0.3 {
                 invocations.push(ToolInvocation {
                     tool_type: ToolType::Agent(agent.clone()),
@@ -146,7 +307,7 @@ impl PatternMatcher {
         
         // Match commands
         for (command, patterns) in &self.command_patterns {
-            let score = self.calculate_pattern_score(&input_lower, patterns);
+            let score = self.calculate_pattern_score(input_lower, patterns);
             if score > 0.4 {
                 invocations.push(ToolInvocation {
                     tool_type: ToolType::SlashCommand(command.clone()),
@@ -159,7 +320,7 @@ impl PatternMatcher {
         
         // Match MCP servers
         for (mcp, patterns) in &self.mcp_patterns {
-            let score = self.calculate_pattern_score(&input_lower, patterns);
+            let score = self.calculate_pattern_score(input_lower, patterns);
             if score > 0.35 {
                 invocations.push(ToolInvocation {
                     tool_type: ToolType::McpServer(mcp.clone()),
@@ -170,15 +331,12 @@ impl PatternMatcher {
             }
         }
         
+        invocations.retain(|invocation| categories.allows(&invocation.tool_type));
+        
         // Sort by priority
         invocations.sort_by(|a, b| b.priority.cmp(&a.priority));
         
-        RoutingResult {
-            invocations,
-            detected_intent,
-            complexity_score,
-            domain,
-        }
+        invocations
     }
     
     fn calculate_pattern_score(&self, input: &str, patterns: &[String]) -> f32 {
@@ -257,24 +415,45 @@ impl PatternMatcher {
             "troubleshooting".to_string()
         } else if input.contains("improve") || input.contains("optimize") {
             "improvement".to_string()
+        } else if input.contains("analyze") || input.contains("review") || input.contains("examine") {
+            "analysis".to_string()
         } else {
             "general".to_string()
         }
     }
 }
 
-/// Analyze chat input and determine which tools to use
-#[tauri::command]
-pub async fn analyze_chat_input(input: String) -> Result<RoutingResult, String> {
+fn route_chat_input(input: &str, categories: &RoutingToolCategories) -> RoutingResult {
     debug!("Analyzing chat input: {}", input);
     
-    let matcher = PatternMatcher::new();
-    let result = matcher.analyze_input(&input);
+    let result = PatternMatcher::shared().analyze_input_with(input, categories);
     
     info!("Routing result: {} tools identified, complexity: {}", 
           result.invocations.len(), result.complexity_score);
     
-    Ok(result)
+    result
+}
+
+/// Analyze chat input and determine which tools to use
+#[tauri::command]
+pub async fn analyze_chat_input(db: State<'_, AgentDb>, input: String) -> Result<RoutingResult, String> {
+    let categories = load_routing_tool_categories(&*db.lock_conn());
+    Ok(route_chat_input(&input, &categories))
+}
+
+/// Tool categories intelligent routing may invoke
+#[tauri::command]
+pub async fn get_routing_tool_categories(db: State<'_, AgentDb>) -> Result<RoutingToolCategories, String> {
+    Ok(load_routing_tool_categories(&*db.lock_conn()))
+}
+
+/// Enable or disable the tool categories intelligent routing may invoke
+#[tauri::command]
+pub async fn set_routing_tool_categories(
+    db: State<'_, AgentDb>,
+    categories: RoutingToolCategories,
+) -> Result<(), String> {
+    save_routing_tool_categories(&*db.lock_conn(), &categories)
 }
 
 /// MCP installation request
@@ -294,17 +473,6 @@ pub async fn parse_mcp_install_request(input: String) -> Result<McpInstallReques
     let mut detected_packages = Vec::new();
     let mut confidence: f32 = 0.0;
     
-    // Common MCP server names and their variations
-    let known_mcps = vec![
-        ("playwright", vec!["playwright", "browser", "e2e"]),
-        ("sequential_thinking", vec!["sequential", "thinking", "reasoning"]),
-        ("github", vec!["github", "git", "repository"]),
-        ("filesystem", vec!["file", "directory", "fs"]),
-        ("slack", vec!["slack", "messaging"]),
-        ("postgres", vec!["postgres", "postgresql", "database", "db"]),
-        ("fetch", vec!["fetch", "http", "api", "rest"]),
-    ];
-    
     // Check for explicit MCP mentions
     if input_lower.contains("mcp") || input_lower.contains("model context protocol") {
         confidence += 0.3;
@@ -320,8 +488,8 @@ pub async fn parse_mcp_install_request(input: String) -> Result<McpInstallReques
     }
     
     // Detect specific MCP servers
-    for (mcp_name, keywords) in &known_mcps {
-        for keyword in keywords {
+    for (mcp_name, keywords) in KNOWN_MCP_SERVERS {
+        for keyword in keywords.iter() {
             if input_lower.contains(keyword) {
                 detected_packages.push(mcp_name.to_string());
                 confidence += 0.1;
@@ -333,8 +501,7 @@ pub async fn parse_mcp_install_request(input: String) -> Result<McpInstallReques
     // If no specific MCP detected but high confidence it's an install request
     if detected_packages.is_empty() && confidence > 0.4 {
         // Try to extract package name using regex
-        let package_regex = Regex::new(r"(?:install|add|setup)\s+(\w+)(?:\s+mcp)?").unwrap();
-        if let Some(captures) = package_regex.captures(&input_lower) {
+        if let Some(captures) = MCP_PACKAGE_REGEX.captures(&input_lower) {
             if let Some(package) = captures.get(1) {
                 detected_packages.push(package.as_str().to_string());
             }
@@ -350,6 +517,136 @@ pub async fn parse_mcp_install_request(input: String) -> Result<McpInstallReques
     })
 }
 
+/// Parses below this confidence are only installed after the user confirms
+const MCP_PLAN_CONFIRMATION_THRESHOLD: f32 = 0.5;
+
+/// One server to install as part of an MCP install plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpInstallStep {
+    /// Registry id, as passed to `install_mcp_server`
+    pub server_id: String,
+    pub display_name: String,
+    pub npm_package: Option<String>,
+    pub install_command: String,
+    /// Default server config from the registry
+    pub config: serde_json::Value,
+    /// Environment variables the user has to fill in, e.g. API tokens
+    pub required_env: Vec<String>,
+}
+
+/// Servers to install for a natural language request, ready to confirm in one step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpInstallPlan {
+    pub query: String,
+    pub confidence: f32,
+    pub steps: Vec<McpInstallStep>,
+    /// Detected names with no matching server in the registry
+    pub unresolved: Vec<String>,
+    /// The parse was unsure or incomplete, so ask before installing
+    pub needs_confirmation: bool,
+    pub confirmation_reason: Option<String>,
+}
+
+/// Registry ids use dashes where the routing tables use underscores
+fn normalize_server_id(name: &str) -> String {
+    name.trim().to_lowercase().replace(['_', ' '], "-")
+}
+
+/// Env entries of a config template that still hold placeholders like `<your-token>`
+fn required_env_vars(config: &serde_json::Value) -> Vec<String> {
+    config["env"]
+        .as_object()
+        .map(|env| {
+            env.iter()
+                .filter(|(_, value)| match value.as_str() {
+                    Some(v) => v.is_empty() || (v.starts_with('<') && v.ends_with('>')),
+                    None => true,
+                })
+                .map(|(key, _)| key.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Turn a parsed install request into concrete install steps
+///
+/// `search` returns registry candidates for a detected package name. An exact id
+/// match wins, otherwise the most popular candidate is used.
+fn build_mcp_install_plan(
+    request: McpInstallRequest,
+    search: impl Fn(&str) -> Vec<super::mcp_manager::McpServerInfo>,
+) -> McpInstallPlan {
+    let mut steps: Vec<McpInstallStep> = Vec::new();
+    let mut unresolved = Vec::new();
+
+    for package in &request.detected_packages {
+        let wanted = normalize_server_id(package);
+        let candidates = search(package);
+        let server = candidates
+            .iter()
+            .find(|server| normalize_server_id(&server.name) == wanted)
+            .or_else(|| candidates.iter().max_by_key(|server| server.popularity));
+        let Some(server) = server else {
+            unresolved.push(package.clone());
+            continue;
+        };
+        if steps.iter().any(|step| step.server_id == server.name) {
+            continue;
+        }
+        steps.push(McpInstallStep {
+            server_id: server.name.clone(),
+            display_name: server.display_name.clone(),
+            npm_package: server.npm_package.clone(),
+            install_command: server.install_command.clone(),
+            config: server.config_template.clone(),
+            required_env: required_env_vars(&server.config_template),
+        });
+    }
+
+    let confirmation_reason = if steps.is_empty() {
+        Some("No MCP server in the registry matches this request".to_string())
+    } else if request.confidence < MCP_PLAN_CONFIRMATION_THRESHOLD {
+        Some(format!(
+            "Low confidence ({:.0}%) that this is an install request",
+            request.confidence * 100.0
+        ))
+    } else if !unresolved.is_empty() {
+        Some(format!("Could not find {} in the registry", unresolved.join(", ")))
+    } else {
+        None
+    };
+
+    McpInstallPlan {
+        query: request.query,
+        confidence: request.confidence,
+        steps,
+        unresolved,
+        needs_confirmation: confirmation_reason.is_some(),
+        confirmation_reason,
+    }
+}
+
+/// Parse a natural language MCP install request into a plan the user can confirm and install
+#[tauri::command]
+pub async fn plan_mcp_install(input: String) -> Result<McpInstallPlan, String> {
+    let request = parse_mcp_install_request(input).await?;
+
+    let mut candidates = HashMap::new();
+    for package in &request.detected_packages {
+        let result = super::mcp_manager::search_mcp_servers(normalize_server_id(package)).await?;
+        candidates.insert(package.clone(), result.servers);
+    }
+
+    let plan = build_mcp_install_plan(request, |package| {
+        candidates.get(package).cloned().unwrap_or_default()
+    });
+    info!(
+        "MCP install plan: {} step(s), {} unresolved, confirmation needed: {}",
+        plan.steps.len(), plan.unresolved.len(), plan.needs_confirmation
+    );
+    Ok(plan)
+}
+
 // =============================================================================
 // AUTO SMART SELECTION SYSTEM - ENHANCED MODEL ROUTING
 // =============================================================================
@@ -436,6 +733,33 @@ pub struct ModelRecommendationV2 {
     pub estimated_duration: u32,
     pub task_distribution: Option<TaskDistribution>,
     pub selection_criteria: SelectionCriteriaV2,
+    /// Per-candidate scoring, best first, for side-by-side comparison in the UI
+    #[serde(default)]
+    pub score_breakdown: Vec<ScoreBreakdown>,
+    /// Id of the stored routing decision, for reporting the outcome with `record_routing_outcome`
+    #[serde(default)]
+    pub decision_id: Option<String>,
+    /// Why the top-ranked model was passed over for `primary_model`, when it was
+    #[serde(default)]
+    pub fallback_reason: Option<String>,
+}
+
+/// One weighted component of a model's selection score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreComponent {
+    pub name: String,
+    pub raw_score: f64,
+    pub weight: f64,
+    pub weighted_score: f64,
+}
+
+/// Transparent scoring of a single candidate model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub model_id: String,
+    pub components: Vec<ScoreComponent>,
+    pub final_score: f64,
+    pub reasoning: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -619,14 +943,139 @@ fn analyze_context_requirements_v2(text: &str, text_length: usize) -> ContextAna
     }
 }
 
-/// Enhanced model selection with multi-model task distribution
+/// Pick the cheapest usable model for background work such as summarization
+///
+/// Ties on cost go to the model with the faster average response time.
+pub fn select_cheapest_model<'a>(
+    benchmarks: &'a [AiModelBenchmark],
+    usable: impl Fn(&AiModelBenchmark) -> bool,
+) -> Option<&'a AiModelBenchmark> {
+    benchmarks
+        .iter()
+        .filter(|benchmark| usable(benchmark))
+        .min_by(|a, b| {
+            a.cost_per_1k_tokens.total_cmp(&b.cost_per_1k_tokens)
+                .then(a.average_response_time.total_cmp(&b.average_response_time))
+        })
+}
+
+const NO_AVAILABLE_MODELS: &str = "No model can run right now";
+
+/// Providers and models that can serve a request right now
+#[derive(Debug, Clone)]
+pub struct ModelAvailability {
+    /// Configured providers, with Ollama only counted when its daemon answers
+    pub providers: AvailableProviders,
+    /// Models whose circuit breaker is currently rejecting requests
+    pub open_circuits: Vec<String>,
+    /// Providers rate limited until a reset that hasn't passed yet
+    pub throttled_providers: Vec<String>,
+}
+
+impl ModelAvailability {
+    /// Snapshot provider configuration, daemon reachability and circuit breaker state
+    pub async fn detect(app: &AppHandle) -> Self {
+        Self {
+            providers: crate::commands::simple_model_validator::detect_available_providers(app).await,
+            open_circuits: crate::commands::gemini_backend::open_gemini_circuits(),
+            throttled_providers: crate::commands::provider_quota::PROVIDER_QUOTAS.throttled_providers(),
+        }
+    }
+
+    pub fn allows(&self, model_id: &str, provider: &str) -> bool {
+        self.providers.is_available(provider)
+            && !self.open_circuits.iter().any(|open| crate::models::same_model(open, model_id))
+            && !self.throttled_providers.iter().any(|throttled| throttled.eq_ignore_ascii_case(provider))
+    }
+
+    /// Why models are unavailable, for the message shown when nothing can run
+    pub fn unavailable_reasons(&self) -> String {
+        let mut reasons: Vec<String> = self.providers.providers.iter()
+            .filter(|p| !p.available)
+            .map(|p| p.reason.clone())
+            .collect();
+        if !self.open_circuits.is_empty() {
+            reasons.push(format!("circuit breaker open for {}", self.open_circuits.join(", ")));
+        }
+        if !self.throttled_providers.is_empty() {
+            reasons.push(format!("rate limited: {}", self.throttled_providers.join(", ")));
+        }
+        reasons.join("; ")
+    }
+}
+
+/// Provider serving a model, from its benchmark or else from the model catalogue
+fn model_provider(model_id: &str, benchmarks: &[AiModelBenchmark]) -> Option<String> {
+    if let Some(benchmark) = benchmarks.iter().find(|b| crate::models::same_model(&b.model_id, model_id)) {
+        return Some(benchmark.provider.clone());
+    }
+    crate::models::canonicalize(model_id).map(|model| match model.provider {
+        crate::models::ModelProvider::Claude => "claude".to_string(),
+        crate::models::ModelProvider::Gemini => "gemini".to_string(),
+        crate::models::ModelProvider::Ollama => "ollama".to_string(),
+    })
+}
+
+/// Enhanced model selection, only considering models that `is_available` accepts
+///
+/// `is_available` is given a model id and its provider. Unavailable models are dropped
+/// before ranking, and preset recommendations fall back to the next usable model.
 pub fn select_optimal_model_v2(
+    analysis: &TaskComplexityAnalysis,
+    benchmarks: &[AiModelBenchmark],
+    is_available: impl Fn(&str, &str) -> bool,
+) -> Result<ModelRecommendationV2, String> {
+    let usable = |model_id: &str| {
+        model_provider(model_id, benchmarks).is_some_and(|provider| is_available(model_id, &provider))
+    };
+    let available: Vec<AiModelBenchmark> = benchmarks.iter()
+        .filter(|benchmark| is_available(&benchmark.model_id, &benchmark.provider))
+        .cloned()
+        .collect();
+    if available.is_empty() {
+        return Err(NO_AVAILABLE_MODELS.to_string());
+    }
+
+    let mut recommendation = rank_models(analysis, &available);
+    let mut candidates: Vec<String> = std::iter::once(recommendation.primary_model.clone())
+        .chain(recommendation.fallback_models.iter().cloned())
+        .chain(recommendation.score_breakdown.iter().map(|b| b.model_id.clone()))
+        .chain(available.iter().map(|b| b.model_id.clone()))
+        .filter(|model_id| usable(model_id))
+        .collect();
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|model_id| seen.insert(model_id.clone()));
+
+    let primary = candidates.first().ok_or(NO_AVAILABLE_MODELS)?;
+    if *primary != recommendation.primary_model {
+        recommendation.fallback_reason = Some(format!("{} is unavailable", recommendation.primary_model));
+        recommendation.reasoning = format!(
+            "{} is unavailable, using {} instead. {}",
+            recommendation.primary_model, primary, recommendation.reasoning
+        );
+        recommendation.primary_model = primary.clone();
+    }
+    let fallback_count = recommendation.fallback_models.len();
+    recommendation.fallback_models = candidates.into_iter().skip(1).take(fallback_count).collect();
+    Ok(recommendation)
+}
+
+/// Rank models for a task with multi-model task distribution
+fn rank_models(
     analysis: &TaskComplexityAnalysis, 
     benchmarks: &[AiModelBenchmark]
 ) -> ModelRecommendationV2 {
     // For simple tasks, use fast models
     if matches!(analysis.priority_level, TaskPriority::Low) && 
        matches!(analysis.domain_classification, TaskDomain::Simple) {
+        let selection_criteria = SelectionCriteriaV2 {
+            intelligence_weight: 0.1,
+            speed_weight: 0.5,
+            cost_weight: 0.3,
+            reliability_weight: 0.1,
+            capability_weight: 0.0,
+            context_weight: 0.0,
+        };
         return ModelRecommendationV2 {
             primary_model: "gemini-2.5-flash".to_string(),
             fallback_models: vec!["llama3.3:latest".to_string(), "sonnet-3.7".to_string()],
@@ -635,14 +1084,10 @@ pub fn select_optimal_model_v2(
             estimated_cost: 0.02,
             estimated_duration: analysis.estimated_duration,
             task_distribution: None,
-            selection_criteria: SelectionCriteriaV2 {
-                intelligence_weight: 0.1,
-                speed_weight: 0.5,
-                cost_weight: 0.3,
-                reliability_weight: 0.1,
-                capability_weight: 0.0,
-                context_weight: 0.0,
-            },
+            score_breakdown: score_candidates(analysis, benchmarks, &selection_criteria),
+            selection_criteria,
+            decision_id: None,
+            fallback_reason: None,
         };
     }
     
@@ -661,6 +1106,14 @@ pub fn select_optimal_model_v2(
             })
         } else { None };
         
+        let selection_criteria = SelectionCriteriaV2 {
+            intelligence_weight: 0.6,
+            speed_weight: 0.05,
+            cost_weight: 0.05,
+            reliability_weight: 0.15,
+            capability_weight: 0.1,
+            context_weight: 0.05,
+        };
         return ModelRecommendationV2 {
             primary_model: "opus-4.1".to_string(),
             fallback_models: vec!["sonnet-4".to_string(), "gemini-2.5-pro-exp".to_string()],
@@ -669,54 +1122,16 @@ pub fn select_optimal_model_v2(
             estimated_cost: 0.15,
             estimated_duration: analysis.estimated_duration,
             task_distribution,
-            selection_criteria: SelectionCriteriaV2 {
-                intelligence_weight: 0.6,
-                speed_weight: 0.05,
-                cost_weight: 0.05,
-                reliability_weight: 0.15,
-                capability_weight: 0.1,
-                context_weight: 0.05,
-            },
+            score_breakdown: score_candidates(analysis, benchmarks, &selection_criteria),
+            selection_criteria,
+            decision_id: None,
+            fallback_reason: None,
         };
     }
     
     // Calculate weighted scores for available models
-    let mut model_scores: Vec<(String, f64, String)> = Vec::new();
     let criteria = calculate_selection_criteria_v2(analysis);
-    
-    for benchmark in benchmarks {
-        if !benchmark.supports_tools && analysis.required_capabilities.contains(&"tools".to_string()) {
-            continue; // Skip models that don't support required capabilities
-        }
-        
-        let intelligence_score = benchmark.intelligence_score / 100.0;
-        let speed_score = (100.0 - benchmark.average_response_time / 100.0).max(0.0) / 100.0;
-        let cost_score = (1.0 / benchmark.cost_per_1k_tokens.max(0.001)).min(10.0) / 10.0;
-        let reliability_score = benchmark.success_rate / 100.0;
-        let capability_score = calculate_capability_score(benchmark, &analysis.required_capabilities);
-        let context_score = if analysis.context_requirements.needs_large_context {
-            if benchmark.context_window >= 1000000 { 1.0 }
-            else if benchmark.context_window >= 100000 { 0.7 }
-            else { 0.3 }
-        } else { 0.8 };
-        
-        let final_score = (intelligence_score * criteria.intelligence_weight) +
-                         (speed_score * criteria.speed_weight) +
-                         (cost_score * criteria.cost_weight) +
-                         (reliability_score * criteria.reliability_weight) +
-                         (capability_score * criteria.capability_weight) +
-                         (context_score * criteria.context_weight);
-        
-        let reasoning = format!(
-            "{}: Score {:.2} (I:{:.2}, S:{:.2}, C:{:.2}, R:{:.2}, Cap:{:.2}, Ctx:{:.2})",
-            benchmark.model_id, final_score, intelligence_score, speed_score, cost_score, 
-            reliability_score, capability_score, context_score
-        );
-        
-        model_scores.push((benchmark.model_id.clone(), final_score, reasoning));
-    }
-    
-    model_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    let model_scores = score_candidates(analysis, benchmarks, &criteria);
     
     if model_scores.is_empty() {
         return ModelRecommendationV2 {
@@ -728,21 +1143,95 @@ pub fn select_optimal_model_v2(
             estimated_duration: analysis.estimated_duration,
             task_distribution: None,
             selection_criteria: criteria,
+            score_breakdown: Vec::new(),
+            decision_id: None,
+            fallback_reason: None,
         };
     }
     
     let best_model = &model_scores[0];
-    let fallbacks: Vec<String> = model_scores.iter().skip(1).take(3).map(|(id, _, _)| id.clone()).collect();
+    let fallbacks: Vec<String> = model_scores.iter().skip(1).take(3).map(|b| b.model_id.clone()).collect();
     
     ModelRecommendationV2 {
-        primary_model: best_model.0.clone(),
+        primary_model: best_model.model_id.clone(),
         fallback_models: fallbacks,
         confidence: 0.92,
-        reasoning: format!("Selected {} based on weighted analysis: {}", best_model.0, best_model.2),
+        reasoning: format!("Selected {} based on weighted analysis: {}", best_model.model_id, best_model.reasoning),
         estimated_cost: 0.05, // Placeholder - should be calculated from benchmark
         estimated_duration: analysis.estimated_duration,
         task_distribution: None,
         selection_criteria: criteria,
+        score_breakdown: model_scores,
+        decision_id: None,
+        fallback_reason: None,
+    }
+}
+
+/// Score every eligible benchmarked model, best first
+fn score_candidates(
+    analysis: &TaskComplexityAnalysis,
+    benchmarks: &[AiModelBenchmark],
+    criteria: &SelectionCriteriaV2,
+) -> Vec<ScoreBreakdown> {
+    let mut model_scores: Vec<ScoreBreakdown> = benchmarks.iter()
+        .filter(|benchmark| {
+            // Skip models that don't support required capabilities
+            benchmark.supports_tools || !analysis.required_capabilities.contains(&"tools".to_string())
+        })
+        .map(|benchmark| score_model(analysis, benchmark, criteria))
+        .collect();
+    
+    model_scores.sort_by(|a, b| b.final_score.partial_cmp(&a.final_score).unwrap_or(std::cmp::Ordering::Equal));
+    model_scores
+}
+
+/// Weighted score of a single model, keeping each component for explanation
+fn score_model(
+    analysis: &TaskComplexityAnalysis,
+    benchmark: &AiModelBenchmark,
+    criteria: &SelectionCriteriaV2,
+) -> ScoreBreakdown {
+    let intelligence_score = benchmark.intelligence_score / 100.0;
+    let speed_score = (100.0 - benchmark.average_response_time / 100.0).max(0.0) / 100.0;
+    let cost_score = (1.0 / benchmark.cost_per_1k_tokens.max(0.001)).min(10.0) / 10.0;
+    let reliability_score = benchmark.success_rate / 100.0;
+    let capability_score = calculate_capability_score(benchmark, &analysis.required_capabilities);
+    let context_score = if analysis.context_requirements.needs_large_context {
+        if benchmark.context_window >= 1000000 { 1.0 }
+        else if benchmark.context_window >= 100000 { 0.7 }
+        else { 0.3 }
+    } else { 0.8 };
+    
+    let components: Vec<ScoreComponent> = [
+        ("intelligence", intelligence_score, criteria.intelligence_weight),
+        ("speed", speed_score, criteria.speed_weight),
+        ("cost", cost_score, criteria.cost_weight),
+        ("reliability", reliability_score, criteria.reliability_weight),
+        ("capability", capability_score, criteria.capability_weight),
+        ("context", context_score, criteria.context_weight),
+    ]
+    .iter()
+    .map(|(name, raw_score, weight)| ScoreComponent {
+        name: name.to_string(),
+        raw_score: *raw_score,
+        weight: *weight,
+        weighted_score: raw_score * weight,
+    })
+    .collect();
+    
+    let final_score = components.iter().map(|c| c.weighted_score).sum::<f64>();
+    
+    let reasoning = format!(
+        "{}: Score {:.2} (I:{:.2}, S:{:.2}, C:{:.2}, R:{:.2}, Cap:{:.2}, Ctx:{:.2})",
+        benchmark.model_id, final_score, intelligence_score, speed_score, cost_score, 
+        reliability_score, capability_score, context_score
+    );
+    
+    ScoreBreakdown {
+        model_id: benchmark.model_id.clone(),
+        components,
+        final_score,
+        reasoning,
     }
 }
 
@@ -834,6 +1323,8 @@ pub fn init_benchmark_tables(conn: &Connection) -> SqliteResult<()> {
         )",
         [],
     )?;
+    // Unix millis of the last local speed/latency measurement, which updates from older sources keep
+    let _ = conn.execute("ALTER TABLE ai_model_benchmarks ADD COLUMN measured_at INTEGER", []);
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS model_performance_metrics (
@@ -857,41 +1348,63 @@ pub fn init_benchmark_tables(conn: &Connection) -> SqliteResult<()> {
 pub async fn get_intelligent_model_recommendation(
     prompt: String, 
     context: Option<String>,
+    session_id: Option<String>,
     app: AppHandle
 ) -> Result<ModelRecommendationV2, String> {
     info!("Getting intelligent model recommendation for task");
     
+    let availability = ModelAvailability::detect(&app).await;
+    availability.providers.ensure_any()?;
+    
     let analysis = analyze_task_complexity_v2(&prompt, context.as_deref());
     info!("Task analysis completed: domain={:?}, priority={:?}", analysis.domain_classification, analysis.priority_level);
     
-    let db_state = app.state::<AgentDb>();
-    let conn = db_state.0.lock().map_err(|e| format!("DB lock failed: {}", e))?;
+    let benchmarks = {
+        let db_state = app.state::<AgentDb>();
+        let conn = db_state.lock_conn();
+        load_model_benchmarks(&conn)?
+    };
+    
+    let mut recommendation = select_optimal_model_v2(&analysis, &benchmarks, |model_id, provider| {
+        availability.allows(model_id, provider)
+    })
+    .map_err(|e| format!("{}: {}", e, availability.unavailable_reasons()))?;
+    info!("Model recommendation: {} with confidence {:.2}", 
+          recommendation.primary_model, recommendation.confidence);
+    
+    let recorded = super::routing_decisions::record_routing_decision(
+        &app.state::<AgentDb>().lock_conn(), session_id.as_deref(), &prompt, &analysis, &recommendation,
+    );
+    match recorded {
+        Ok(decision_id) => recommendation.decision_id = Some(decision_id),
+        Err(e) => warn!("Failed to record routing decision: {}", e),
+    }
     
+    Ok(recommendation)
+}
+
+/// Load model benchmarks, seeding the defaults when none are stored yet
+pub(crate) fn load_model_benchmarks(conn: &Connection) -> Result<Vec<AiModelBenchmark>, String> {
     // Initialize benchmark tables if they don't exist
-    init_benchmark_tables(&conn)
+    init_benchmark_tables(conn)
         .map_err(|e| format!("Failed to initialize benchmark tables: {}", e))?;
     
-    let benchmarks = get_current_benchmarks(&conn)
+    let benchmarks = get_current_benchmarks(conn)
         .map_err(|e| format!("Failed to get benchmarks: {}", e))?;
-    
-    if benchmarks.is_empty() {
-        warn!("No benchmark data available, updating with default values");
-        update_default_benchmarks(&conn)
-            .map_err(|e| format!("Failed to update default benchmarks: {}", e))?;
-        
-        let benchmarks = get_current_benchmarks(&conn)
-            .map_err(|e| format!("Failed to get updated benchmarks: {}", e))?;
-        
-        if benchmarks.is_empty() {
-            return Err("Could not initialize benchmark data".to_string());
-        }
+    if !benchmarks.is_empty() {
+        return Ok(benchmarks);
     }
     
-    let recommendation = select_optimal_model_v2(&analysis, &benchmarks);
-    info!("Model recommendation: {} with confidence {:.2}", 
-          recommendation.primary_model, recommendation.confidence);
+    warn!("No benchmark data available, updating with default values");
+    update_default_benchmarks(conn)
+        .map_err(|e| format!("Failed to update default benchmarks: {}", e))?;
     
-    Ok(recommendation)
+    let benchmarks = get_current_benchmarks(conn)
+        .map_err(|e| format!("Failed to get updated benchmarks: {}", e))?;
+    if benchmarks.is_empty() {
+        return Err("Could not initialize benchmark data".to_string());
+    }
+    Ok(benchmarks)
 }
 
 fn get_current_benchmarks(conn: &Connection) -> SqliteResult<Vec<AiModelBenchmark>> {
@@ -935,8 +1448,107 @@ fn get_current_benchmarks(conn: &Connection) -> SqliteResult<Vec<AiModelBenchmar
     Ok(benchmarks)
 }
 
+/// Benchmark values from one source; `None` fields are left as they are
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BenchmarkUpdate {
+    pub model_id: String,
+    pub provider: Option<String>,
+    pub intelligence_score: Option<f64>,
+    pub speed_score: Option<f64>,
+    pub coding_excellence: Option<f64>,
+    pub analysis_depth: Option<f64>,
+    pub creative_writing: Option<f64>,
+    pub technical_precision: Option<f64>,
+    pub cost_per_1k_tokens: Option<f64>,
+    pub average_response_time: Option<f64>,
+    pub success_rate: Option<f64>,
+    pub context_window: Option<u32>,
+    pub supports_tools: Option<bool>,
+    pub supports_vision: Option<bool>,
+    pub supports_audio: Option<bool>,
+    pub availability_score: Option<f64>,
+    /// When the source's data was current
+    pub as_of: DateTime<Utc>,
+}
+
+/// When the built-in default benchmarks were compiled
+const DEFAULT_BENCHMARKS_AS_OF: &str = "2025-08-01T00:00:00Z";
+
+/// Merge `update` into the stored benchmark for its model, adding the model if it is new
+///
+/// Only the fields the source provides are written. Speed, latency and
+/// availability measured on this machine are kept unless the source's data
+/// is newer than the measurement. New models get conservative defaults for
+/// anything the source leaves out.
+pub(crate) fn merge_benchmark_update(conn: &Connection, update: &BenchmarkUpdate) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO ai_model_benchmarks
+         (model_id, provider, intelligence_score, speed_score, coding_excellence, analysis_depth,
+          creative_writing, technical_precision, cost_per_1k_tokens, average_response_time,
+          success_rate, context_window, supports_tools, supports_vision, supports_audio,
+          availability_score, last_updated)
+         VALUES (?1, COALESCE(?2, 'unknown'), COALESCE(?3, 70.0), COALESCE(?4, 70.0), COALESCE(?5, 70.0),
+                 COALESCE(?6, 70.0), COALESCE(?7, 70.0), COALESCE(?8, 70.0), COALESCE(?9, 0.0),
+                 COALESCE(?10, 2000.0), COALESCE(?11, 90.0), COALESCE(?12, 8192), COALESCE(?13, 0),
+                 COALESCE(?14, 0), COALESCE(?15, 0), COALESCE(?16, 95.0), ?17)
+         ON CONFLICT(model_id) DO UPDATE SET
+             provider = COALESCE(?2, provider),
+             intelligence_score = COALESCE(?3, intelligence_score),
+             speed_score = CASE WHEN ?4 IS NOT NULL AND (measured_at IS NULL OR measured_at < ?18)
+                                THEN ?4 ELSE speed_score END,
+             coding_excellence = COALESCE(?5, coding_excellence),
+             analysis_depth = COALESCE(?6, analysis_depth),
+             creative_writing = COALESCE(?7, creative_writing),
+             technical_precision = COALESCE(?8, technical_precision),
+             cost_per_1k_tokens = COALESCE(?9, cost_per_1k_tokens),
+             average_response_time = CASE WHEN ?10 IS NOT NULL AND (measured_at IS NULL OR measured_at < ?18)
+                                          THEN ?10 ELSE average_response_time END,
+             success_rate = COALESCE(?11, success_rate),
+             context_window = COALESCE(?12, context_window),
+             supports_tools = COALESCE(?13, supports_tools),
+             supports_vision = COALESCE(?14, supports_vision),
+             supports_audio = COALESCE(?15, supports_audio),
+             availability_score = CASE WHEN ?16 IS NOT NULL AND (measured_at IS NULL OR measured_at < ?18)
+                                       THEN ?16 ELSE availability_score END,
+             last_updated = ?17",
+        params![
+            update.model_id,
+            update.provider,
+            update.intelligence_score,
+            update.speed_score,
+            update.coding_excellence,
+            update.analysis_depth,
+            update.creative_writing,
+            update.technical_precision,
+            update.cost_per_1k_tokens,
+            update.average_response_time,
+            update.success_rate,
+            update.context_window,
+            update.supports_tools,
+            update.supports_vision,
+            update.supports_audio,
+            update.availability_score,
+            Utc::now().to_rfc3339(),
+            update.as_of.timestamp_millis(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Store values measured on this machine, which later updates from older sources keep
+pub(crate) fn record_local_measurement(conn: &Connection, measurement: &BenchmarkUpdate) -> SqliteResult<()> {
+    merge_benchmark_update(conn, measurement)?;
+    conn.execute(
+        "UPDATE ai_model_benchmarks SET measured_at = ?1 WHERE model_id = ?2",
+        params![measurement.as_of.timestamp_millis(), measurement.model_id],
+    )?;
+    Ok(())
+}
+
 fn update_default_benchmarks(conn: &Connection) -> SqliteResult<()> {
-    let now = Utc::now().to_rfc3339();
+    let as_of = DateTime::parse_from_rfc3339(DEFAULT_BENCHMARKS_AS_OF)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+        .with_timezone(&Utc);
     
     // Default benchmark data for 2025 models with enhanced characteristics
     let benchmarks = vec![
@@ -958,18 +1570,25 @@ fn update_default_benchmarks(conn: &Connection) -> SqliteResult<()> {
     ];
     
     for (model_id, provider, intelligence, speed, coding, analysis, creative, technical, cost, response_time, success, context, tools, vision, audio, availability) in benchmarks {
-        conn.execute(
-            "INSERT OR REPLACE INTO ai_model_benchmarks 
-             (model_id, provider, intelligence_score, speed_score, coding_excellence, analysis_depth,
-              creative_writing, technical_precision, cost_per_1k_tokens, average_response_time,
-              success_rate, context_window, supports_tools, supports_vision, supports_audio,
-              availability_score, last_updated)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
-            rusqlite::params![
-                model_id, provider, intelligence, speed, coding, analysis, creative, technical,
-                cost, response_time, success, context, tools, vision, audio, availability, now
-            ],
-        )?;
+        merge_benchmark_update(conn, &BenchmarkUpdate {
+            model_id: model_id.to_string(),
+            provider: Some(provider.to_string()),
+            intelligence_score: Some(intelligence),
+            speed_score: Some(speed),
+            coding_excellence: Some(coding),
+            analysis_depth: Some(analysis),
+            creative_writing: Some(creative),
+            technical_precision: Some(technical),
+            cost_per_1k_tokens: Some(cost),
+            average_response_time: Some(response_time),
+            success_rate: Some(success),
+            context_window: Some(context),
+            supports_tools: Some(tools),
+            supports_vision: Some(vision),
+            supports_audio: Some(audio),
+            availability_score: Some(availability),
+            as_of,
+        })?;
     }
     
     Ok(())
@@ -984,8 +1603,12 @@ pub async fn update_model_performance_metrics(
     user_satisfaction: f64,
     app: AppHandle
 ) -> Result<(), String> {
+    // Store metrics under the same id the benchmarks use
+    let model_id = crate::models::canonicalize(&model_id)
+        .map_or(model_id, |m| m.id.to_string());
+    
     let db_state = app.state::<AgentDb>();
-    let conn = db_state.0.lock().map_err(|e| format!("DB lock failed: {}", e))?;
+    let conn = db_state.lock_conn();
     
     let now = Utc::now().to_rfc3339();
     
@@ -1011,7 +1634,7 @@ pub async fn update_model_benchmarks_from_web(app: AppHandle) -> Result<String,
     info!("Starting daily model benchmark update from web sources");
     
     let db_state = app.state::<AgentDb>();
-    let conn = db_state.0.lock().map_err(|e| format!("DB lock failed: {}", e))?;
+    let conn = db_state.lock_conn();
     
     init_benchmark_tables(&conn)
         .map_err(|e| format!("Failed to initialize benchmark tables: {}", e))?;
@@ -1033,7 +1656,7 @@ pub async fn update_model_benchmarks_from_web(app: AppHandle) -> Result<String,
 #[command] 
 pub async fn get_model_analytics(app: AppHandle) -> Result<HashMap<String, serde_json::Value>, String> {
     let db_state = app.state::<AgentDb>();
-    let conn = db_state.0.lock().map_err(|e| format!("DB lock failed: {}", e))?;
+    let conn = db_state.lock_conn();
     
     let benchmarks = get_current_benchmarks(&conn)
         .map_err(|e| format!("Failed to get benchmarks: {}", e))?;
@@ -1084,4 +1707,226 @@ pub async fn get_model_analytics(app: AppHandle) -> Result<HashMap<String, serde
                      serde_json::to_value(Utc::now().to_rfc3339()).unwrap_or_default());
     
     Ok(analytics)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compound_request_splits_into_subtasks() {
+        let matcher = PatternMatcher::new();
+        let result = matcher.analyze_input("Analyze the login module then fix the error in the api endpoint");
+        
+        assert_eq!(result.sub_tasks.len(), 2);
+        assert_eq!(result.sub_tasks[0].index, 0);
+        assert_eq!(result.sub_tasks[0].intent, "analysis");
+        assert_eq!(result.sub_tasks[1].intent, "troubleshooting");
+        assert_eq!(result.sub_tasks[1].text, "fix the error in the api endpoint");
+    }
+
+    #[tokio::test]
+    async fn test_chat_analysis_reuses_one_matcher() {
+        for i in 0..500 {
+            let result = route_chat_input(&format!("review the api endpoint #{}", i), &RoutingToolCategories::default());
+            assert_eq!(result.detected_intent, "analysis");
+        }
+        let mcp = parse_mcp_install_request("please install the playwright mcp".to_string()).await.unwrap();
+        assert_eq!(mcp.detected_packages, vec!["playwright".to_string()]);
+
+        assert_eq!(MATCHER_BUILDS.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(std::ptr::eq(PatternMatcher::shared(), PatternMatcher::shared()));
+    }
+
+    #[test]
+    fn test_disabled_categories_are_never_invoked() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        assert_eq!(load_routing_tool_categories(&conn), RoutingToolCategories::default());
+
+        let input = "Run a comprehensive full analysis of the api security";
+        let invoked = |result: &RoutingResult| -> Vec<ToolType> {
+            result.invocations.iter().map(|invocation| invocation.tool_type.clone()).collect()
+        };
+        let all = route_chat_input(input, &load_routing_tool_categories(&conn));
+        assert_eq!(invoked(&all)[0], ToolType::SuperClaude);
+
+        save_routing_tool_categories(&conn, &RoutingToolCategories { superclaude: false, ..Default::default() }).unwrap();
+        let categories = load_routing_tool_categories(&conn);
+        assert!(!categories.superclaude && categories.agents);
+        let filtered = route_chat_input(input, &categories);
+        assert!(!invoked(&filtered).contains(&ToolType::SuperClaude));
+        assert_eq!(invoked(&filtered), invoked(&all)[1..]);
+        assert!(filtered.sub_tasks.iter().all(|task| {
+            task.recommended_tool.as_ref().map_or(true, |tool| tool.tool_type != ToolType::SuperClaude)
+        }));
+    }
+
+    #[test]
+    fn test_numbered_steps_split_into_subtasks() {
+        let matcher = PatternMatcher::new();
+        let sub_tasks = matcher.segment_subtasks("1. create a login form\n2. optimize the database query");
+        
+        assert_eq!(sub_tasks.len(), 2);
+        assert_eq!(sub_tasks[0].intent, "creation");
+        assert_eq!(sub_tasks[1].intent, "improvement");
+    }
+
+    fn benchmark(model_id: &str, intelligence: f64, response_time: f64, cost: f64) -> AiModelBenchmark {
+        AiModelBenchmark {
+            model_id: model_id.to_string(),
+            provider: "test".to_string(),
+            intelligence_score: intelligence,
+            speed_score: 80.0,
+            coding_excellence: 85.0,
+            analysis_depth: 80.0,
+            creative_writing: 75.0,
+            technical_precision: 85.0,
+            cost_per_1k_tokens: cost,
+            average_response_time: response_time,
+            success_rate: 98.0,
+            context_window: 200000,
+            supports_tools: true,
+            supports_vision: false,
+            supports_audio: false,
+            last_updated: Utc::now(),
+            availability_score: 99.0,
+        }
+    }
+
+    #[test]
+    fn test_score_breakdown_components_sum_to_final_score() {
+        let analysis = analyze_task_complexity_v2("Refactor the authentication function and add tests", None);
+        let benchmarks = vec![
+            benchmark("model-a", 95.0, 2000.0, 0.06),
+            benchmark("model-b", 80.0, 800.0, 0.01),
+        ];
+        
+        let recommendation = select_optimal_model_v2(&analysis, &benchmarks, |_, _| true).unwrap();
+        assert_eq!(recommendation.score_breakdown.len(), 2);
+        
+        for breakdown in &recommendation.score_breakdown {
+            assert_eq!(breakdown.components.len(), 6);
+            let sum: f64 = breakdown.components.iter().map(|c| c.weighted_score).sum();
+            assert!((sum - breakdown.final_score).abs() < 1e-9);
+        }
+        
+        let scores: Vec<f64> = recommendation.score_breakdown.iter().map(|b| b.final_score).collect();
+        assert!(scores[0] >= scores[1]);
+    }
+
+    fn provider_benchmark(model_id: &str, provider: &str, intelligence: f64, cost: f64) -> AiModelBenchmark {
+        AiModelBenchmark {
+            provider: provider.to_string(),
+            ..benchmark(model_id, intelligence, 1500.0, cost)
+        }
+    }
+
+    #[test]
+    fn test_selector_skips_models_whose_provider_is_down() {
+        let analysis = analyze_task_complexity_v2("Write a short blog article about our release", None);
+        let benchmarks = vec![
+            provider_benchmark("sonnet-4", "claude", 95.0, 0.06),
+            provider_benchmark("gemini-2.5-flash", "gemini", 85.0, 0.02),
+            provider_benchmark("llama3.3:latest", "ollama", 80.0, 0.0),
+        ];
+        let everything_up = select_optimal_model_v2(&analysis, &benchmarks, |_, _| true).unwrap();
+        let top = &everything_up.score_breakdown[0].model_id;
+        let top_provider = model_provider(top, &benchmarks).unwrap();
+        let next = &everything_up.score_breakdown[1].model_id;
+
+        let recommendation =
+            select_optimal_model_v2(&analysis, &benchmarks, |_, provider| provider != top_provider).unwrap();
+        assert_eq!(&recommendation.primary_model, next);
+        assert!(recommendation.score_breakdown.iter().all(|b| &b.model_id != top));
+        assert!(!recommendation.fallback_models.contains(top));
+
+        let err = select_optimal_model_v2(&analysis, &benchmarks, |_, _| false).unwrap_err();
+        assert_eq!(err, NO_AVAILABLE_MODELS);
+    }
+
+    #[test]
+    fn test_preset_recommendation_falls_back_when_its_provider_is_down() {
+        let mut analysis = analyze_task_complexity_v2("Audit the authentication flow", None);
+        analysis.priority_level = TaskPriority::Critical;
+        analysis.context_requirements.context_complexity = 0.1;
+        let benchmarks = vec![
+            provider_benchmark("opus-4.1", "claude", 100.0, 0.075),
+            provider_benchmark("llama3.3:latest", "ollama", 80.0, 0.0),
+        ];
+
+        let recommendation =
+            select_optimal_model_v2(&analysis, &benchmarks, |_, provider| provider != "claude").unwrap();
+        assert_eq!(recommendation.primary_model, "gemini-2.5-pro-exp");
+        assert!(recommendation.reasoning.starts_with("opus-4.1 is unavailable"));
+        assert_eq!(recommendation.fallback_reason.as_deref(), Some("opus-4.1 is unavailable"));
+        assert!(recommendation.fallback_models.iter().all(|m| m != "sonnet-4"));
+
+        let recommendation = select_optimal_model_v2(&analysis, &benchmarks, |_, provider| provider == "ollama").unwrap();
+        assert_eq!(recommendation.primary_model, "llama3.3:latest");
+    }
+
+    #[test]
+    fn test_web_update_keeps_locally_measured_latency() {
+        let conn = Connection::open_in_memory().unwrap();
+        load_model_benchmarks(&conn).unwrap();
+        let stored = |conn: &Connection| {
+            get_current_benchmarks(conn).unwrap().into_iter().find(|b| b.model_id == "llama3.2:latest").unwrap()
+        };
+
+        let measured_at = Utc::now();
+        record_local_measurement(&conn, &BenchmarkUpdate {
+            model_id: "llama3.2:latest".to_string(),
+            provider: Some("ollama".to_string()),
+            speed_score: Some(42.0),
+            average_response_time: Some(1234.0),
+            as_of: measured_at,
+            ..Default::default()
+        }).unwrap();
+
+        // The built-in data predates the measurement, so only the other fields are refreshed
+        conn.execute("UPDATE ai_model_benchmarks SET intelligence_score = 1.0", []).unwrap();
+        update_default_benchmarks(&conn).unwrap();
+        let benchmark = stored(&conn);
+        assert_eq!((benchmark.average_response_time, benchmark.speed_score), (1234.0, 42.0));
+        assert_eq!((benchmark.intelligence_score, benchmark.context_window), (80.0, 131072));
+
+        // Newer data replaces only the measured fields it provides
+        merge_benchmark_update(&conn, &BenchmarkUpdate {
+            model_id: "llama3.2:latest".to_string(),
+            average_response_time: Some(500.0),
+            as_of: measured_at + chrono::Duration::days(1),
+            ..Default::default()
+        }).unwrap();
+        let benchmark = stored(&conn);
+        assert_eq!((benchmark.average_response_time, benchmark.speed_score), (500.0, 42.0));
+        assert_eq!(benchmark.provider, "ollama");
+    }
+
+    fn search_known_servers(package: &str) -> Vec<crate::commands::mcp_manager::McpServerInfo> {
+        let query = normalize_server_id(package);
+        crate::commands::mcp_manager::get_known_mcp_servers()
+            .into_iter()
+            .filter(|server| server.name.contains(&query) || server.description.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_install_request_becomes_playwright_plan() {
+        let request = parse_mcp_install_request("install the playwright mcp".to_string()).await.unwrap();
+        let plan = build_mcp_install_plan(request, search_known_servers);
+
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].server_id, "playwright");
+        assert_eq!(plan.steps[0].npm_package.as_deref(), Some("@modelcontextprotocol/server-playwright"));
+        assert_eq!(plan.steps[0].config["command"], "node");
+        assert!(plan.steps[0].required_env.is_empty());
+        assert!(!plan.needs_confirmation);
+
+        // Unsure parses are flagged, and placeholder tokens are listed as required
+        let request = parse_mcp_install_request("github".to_string()).await.unwrap();
+        let plan = build_mcp_install_plan(request, search_known_servers);
+        assert_eq!(plan.steps[0].server_id, "github");
+        assert_eq!(plan.steps[0].required_env, vec!["GITHUB_PERSONAL_ACCESS_TOKEN".to_string()]);
+        assert!(plan.needs_confirmation);
+    }
+}
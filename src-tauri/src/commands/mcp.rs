@@ -2,11 +2,45 @@ use anyhow::{Context, Result};
 use dirs;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use tauri::AppHandle;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Notify;
+
+use super::mcp_config::{
+    configured_servers, normalize_mcp_config, parse_export, redact_env, render_export_by_scope, render_scoped_export,
+    render_servers, restore_env, ScopedMcpServers,
+};
+use super::mcp_supervisor::{McpSupervisor, SupervisedState, SupervisionStatus};
+use super::operation_result::OperationResult;
+
+/// Upper bound for a single `claude mcp` invocation before it is killed
+const MCP_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Upper bound for starting a server and calling one of its tools with `mcp_invoke_tool`
+const MCP_TOOL_CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Protocol version offered in the `initialize` handshake
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Section of .mcp.json holding servers switched off by `mcp_toggle_project_server`.
+/// Claude only reads `mcpServers`, so parked entries are ignored but kept for re-enabling.
+const DISABLED_MCP_SERVERS_KEY: &str = "disabledMcpServers";
+
+lazy_static::lazy_static! {
+    /// Cancel signals for in-flight `claude mcp` invocations
+    static ref MCP_CANCEL_SIGNALS: Mutex<HashMap<u64, Arc<Notify>>> = Mutex::new(HashMap::new());
+    /// Serializes read-modify-write cycles on project .mcp.json files
+    static ref PROJECT_CONFIG_LOCK: Mutex<()> = Mutex::new(());
+}
+
+static NEXT_MCP_INVOCATION: AtomicU64 = AtomicU64::new(1);
 
 
 /// Helper function to create a std::process::Command with proper environment variables
@@ -46,8 +80,12 @@ pub struct MCPServer {
 }
 
 /// MCP server configuration for JSON export/import
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MCPServerConfig {
+    /// Transport type: "stdio", "sse" or "http"
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub transport: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub command: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub args: Vec<String>,
@@ -66,6 +104,9 @@ pub struct ServerStatus {
     pub error: Option<String>,
     /// Last checked timestamp
     pub last_checked: Option<u64>,
+    /// Supervisor state when the server is marked "keep running"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervision: Option<SupervisionStatus>,
 }
 
 /// MCP configuration for project scope (.mcp.json)
@@ -101,7 +142,7 @@ pub struct ImportServerResult {
 }
 
 /// Executes a claude mcp command
-fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result<String> {
+async fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result<String> {
     info!("Executing claude mcp command with args: {:?}", args);
 
     let claude_path = find_claude_binary(app_handle)?;
@@ -184,7 +225,18 @@ fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result
     };
     
     info!("Executing command: {:?}", cmd);
-    let output = cmd.output().context("Failed to execute claude command")?;
+
+    // Register a cancel signal so mcp_cancel_running can interrupt a wedged server
+    let invocation_id = NEXT_MCP_INVOCATION.fetch_add(1, Ordering::Relaxed);
+    let cancel = Arc::new(Notify::new());
+    if let Ok(mut signals) = MCP_CANCEL_SIGNALS.lock() {
+        signals.insert(invocation_id, cancel.clone());
+    }
+    let output = run_cancellable(cmd, MCP_COMMAND_TIMEOUT, cancel).await;
+    if let Ok(mut signals) = MCP_CANCEL_SIGNALS.lock() {
+        signals.remove(&invocation_id);
+    }
+    let output = output?;
 
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
@@ -194,6 +246,99 @@ fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result
     }
 }
 
+/// Spawn a pipe buffer reader that keeps whatever arrived even if the child is killed
+fn collect_pipe<R>(mut pipe: R) -> (Arc<Mutex<Vec<u8>>>, tokio::task::JoinHandle<()>)
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let sink = buffer.clone();
+    let handle = tokio::spawn(async move {
+        let mut chunk = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut chunk).await {
+            if n == 0 {
+                break;
+            }
+            if let Ok(mut buf) = sink.lock() {
+                buf.extend_from_slice(&chunk[..n]);
+            }
+        }
+    });
+    (buffer, handle)
+}
+
+/// Kill a child and everything it spawned
+pub(super) fn kill_process_tree(pid: u32) {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        let _ = Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        // The child leads its own process group, so this reaches its descendants too
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+    }
+}
+
+/// Run a command to completion, killing its process tree on timeout or cancel.
+/// The error carries whatever stdout/stderr the command produced before it was stopped.
+async fn run_cancellable(mut cmd: Command, timeout: Duration, cancel: Arc<Notify>) -> Result<Output> {
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = tokio::process::Command::from(cmd)
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute claude command")?;
+    let (stdout, stdout_task) = collect_pipe(child.stdout.take().context("Missing stdout pipe")?);
+    let (stderr, stderr_task) = collect_pipe(child.stderr.take().context("Missing stderr pipe")?);
+
+    let stopped_reason = tokio::select! {
+        status = child.wait() => {
+            let status = status.context("Failed to wait for claude command")?;
+            // Pipes may be held open by grandchildren, so don't wait on them forever
+            let _ = tokio::time::timeout(Duration::from_secs(2), async {
+                let _ = stdout_task.await;
+                let _ = stderr_task.await;
+            }).await;
+            let take = |buf: &Arc<Mutex<Vec<u8>>>| buf.lock().map(|b| b.clone()).unwrap_or_default();
+            return Ok(Output { status, stdout: take(&stdout), stderr: take(&stderr) });
+        }
+        _ = tokio::time::sleep(timeout) => format!("timed out after {}s", timeout.as_secs()),
+        _ = cancel.notified() => "was cancelled".to_string(),
+    };
+
+    if let Some(pid) = child.id() {
+        kill_process_tree(pid);
+    }
+    let _ = child.kill().await;
+    let _ = tokio::time::timeout(Duration::from_secs(1), async {
+        let _ = stdout_task.await;
+        let _ = stderr_task.await;
+    }).await;
+
+    let partial = |buf: &Arc<Mutex<Vec<u8>>>| {
+        buf.lock().map(|b| String::from_utf8_lossy(&b).trim().to_string()).unwrap_or_default()
+    };
+    Err(anyhow::anyhow!(
+        "claude mcp command {}\nPartial stdout: {}\nPartial stderr: {}",
+        stopped_reason,
+        partial(&stdout),
+        partial(&stderr)
+    ))
+}
+
 /// Adds a new MCP server
 #[tauri::command]
 pub async fn mcp_add(
@@ -266,7 +411,7 @@ pub async fn mcp_add(
         }
     }
 
-    match execute_claude_mcp_command(&app, cmd_args) {
+    match execute_claude_mcp_command(&app, cmd_args).await {
         Ok(output) => {
             info!("Successfully added MCP server: {}", name);
             Ok(AddServerResult {
@@ -291,7 +436,7 @@ pub async fn mcp_add(
 pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
     info!("Listing MCP servers");
 
-    match execute_claude_mcp_command(&app, vec!["list"]) {
+    match execute_claude_mcp_command(&app, vec!["list"]).await {
         Ok(output) => {
             info!("Raw output from 'claude mcp list': {:?}", output);
             let trimmed = output.trim();
@@ -377,6 +522,7 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
                                 running: false,
                                 error: None,
                                 last_checked: None,
+                                supervision: None,
                             },
                         });
                         info!("Added server: {:?}", name);
@@ -413,7 +559,7 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
 pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String> {
     info!("Getting MCP server details for: {}", name);
 
-    match execute_claude_mcp_command(&app, vec!["get", &name]) {
+    match execute_claude_mcp_command(&app, vec!["get", &name]).await {
         Ok(output) => {
             // Parse the structured text output
             let mut scope = "local".to_string();
@@ -484,6 +630,7 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
                     running: false,
                     error: None,
                     last_checked: None,
+                    supervision: None,
                 },
             })
         }
@@ -496,19 +643,20 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
 
 /// Removes an MCP server
 #[tauri::command]
-pub async fn mcp_remove(app: AppHandle, name: String) -> Result<String, String> {
+pub async fn mcp_remove(app: AppHandle, name: String) -> Result<OperationResult, String> {
     info!("Removing MCP server: {}", name);
 
-    match execute_claude_mcp_command(&app, vec!["remove", &name]) {
+    let result = match execute_claude_mcp_command(&app, vec!["remove", &name]).await {
         Ok(output) => {
             info!("Successfully removed MCP server: {}", name);
-            Ok(output.trim().to_string())
+            OperationResult::succeeded(output.trim())
         }
         Err(e) => {
             error!("Failed to remove MCP server: {}", e);
-            Err(e.to_string())
+            OperationResult::failed(e.to_string())
         }
-    }
+    };
+    Ok(result.with_details(serde_json::json!({ "server": name })))
 }
 
 /// Adds an MCP server from JSON configuration
@@ -524,155 +672,21 @@ pub async fn mcp_add_json(
         name, scope
     );
 
-    // Validate JSON structure before passing to command
-    match serde_json::from_str::<serde_json::Value>(&json_config) {
-        Ok(json_value) => {
-            // Check if it's a valid object
-            if !json_value.is_object() {
-                return Ok(AddServerResult {
-                    success: false,
-                    message: "Invalid JSON: Expected an object".to_string(),
-                    server_name: None,
-                });
-            }
-            
-            let obj = json_value.as_object().unwrap();
-            
-            // Check for required fields based on transport type
-            if let Some(type_value) = obj.get("type") {
-                if let Some(type_str) = type_value.as_str() {
-                    match type_str {
-                        "stdio" => {
-                            // Validate stdio requirements
-                            if !obj.contains_key("command") {
-                                return Ok(AddServerResult {
-                                    success: false,
-                                    message: "Invalid JSON: 'command' is required for stdio transport".to_string(),
-                                    server_name: None,
-                                });
-                            }
-                            
-                            // Validate command is a string
-                            if let Some(cmd) = obj.get("command") {
-                                if !cmd.is_string() || cmd.as_str().unwrap_or("").trim().is_empty() {
-                                    return Ok(AddServerResult {
-                                        success: false,
-                                        message: "Invalid JSON: 'command' must be a non-empty string".to_string(),
-                                        server_name: None,
-                                    });
-                                }
-                            }
-                            
-                            // Validate args if present
-                            if let Some(args) = obj.get("args") {
-                                if !args.is_array() {
-                                    return Ok(AddServerResult {
-                                        success: false,
-                                        message: "Invalid JSON: 'args' must be an array".to_string(),
-                                        server_name: None,
-                                    });
-                                }
-                                
-                                // Check all args are strings
-                                if let Some(args_array) = args.as_array() {
-                                    for (idx, arg) in args_array.iter().enumerate() {
-                                        if !arg.is_string() {
-                                            return Ok(AddServerResult {
-                                                success: false,
-                                                message: format!("Invalid JSON: args[{}] must be a string", idx),
-                                                server_name: None,
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        "sse" => {
-                            // Validate SSE requirements
-                            if !obj.contains_key("url") {
-                                return Ok(AddServerResult {
-                                    success: false,
-                                    message: "Invalid JSON: 'url' is required for SSE transport".to_string(),
-                                    server_name: None,
-                                });
-                            }
-                            
-                            // Validate URL is a string
-                            if let Some(url) = obj.get("url") {
-                                if !url.is_string() || url.as_str().unwrap_or("").trim().is_empty() {
-                                    return Ok(AddServerResult {
-                                        success: false,
-                                        message: "Invalid JSON: 'url' must be a non-empty string".to_string(),
-                                        server_name: None,
-                                    });
-                                }
-                                
-                                // Basic URL validation
-                                let url_str = url.as_str().unwrap();
-                                if !url_str.starts_with("http://") && !url_str.starts_with("https://") {
-                                    return Ok(AddServerResult {
-                                        success: false,
-                                        message: "Invalid JSON: 'url' must start with http:// or https://".to_string(),
-                                        server_name: None,
-                                    });
-                                }
-                            }
-                        }
-                        _ => {
-                            return Ok(AddServerResult {
-                                success: false,
-                                message: format!("Invalid JSON: Unknown transport type '{}'", type_str),
-                                server_name: None,
-                            });
-                        }
-                    }
-                } else {
-                    return Ok(AddServerResult {
-                        success: false,
-                        message: "Invalid JSON: 'type' must be a string".to_string(),
-                        server_name: None,
-                    });
-                }
-            } else {
-                return Ok(AddServerResult {
-                    success: false,
-                    message: "Invalid JSON: 'type' field is required".to_string(),
-                    server_name: None,
-                });
-            }
-            
-            // Validate env if present
-            if let Some(env) = obj.get("env") {
-                if !env.is_object() {
-                    return Ok(AddServerResult {
-                        success: false,
-                        message: "Invalid JSON: 'env' must be an object".to_string(),
-                        server_name: None,
-                    });
-                }
-                
-                // Check all env values are strings
-                if let Some(env_obj) = env.as_object() {
-                    for (key, value) in env_obj.iter() {
-                        if !value.is_string() {
-                            return Ok(AddServerResult {
-                                success: false,
-                                message: format!("Invalid JSON: env['{}'] must be a string", key),
-                                server_name: None,
-                            });
-                        }
-                    }
-                }
-            }
-        }
-        Err(e) => {
+    // Accept the formats servers are commonly copied in, and hand the CLI the canonical one
+    let json_config = match serde_json::from_str::<serde_json::Value>(&json_config)
+        .map_err(|e| format!("Invalid JSON: {}", e))
+        .and_then(|raw| normalize_mcp_config(&raw))
+        .and_then(|config| serde_json::to_string(&config).map_err(|e| e.to_string()))
+    {
+        Ok(json_config) => json_config,
+        Err(message) => {
             return Ok(AddServerResult {
                 success: false,
-                message: format!("Invalid JSON: {}", e),
+                message,
                 server_name: None,
             });
         }
-    }
+    };
 
     // Build command args
     let mut cmd_args = vec!["add-json", &name, &json_config];
@@ -682,7 +696,7 @@ pub async fn mcp_add_json(
     cmd_args.push(scope_flag);
     cmd_args.push(&scope);
 
-    match execute_claude_mcp_command(&app, cmd_args) {
+    match execute_claude_mcp_command(&app, cmd_args).await {
         Ok(output) => {
             info!("Successfully added MCP server from JSON: {}", name);
             Ok(AddServerResult {
@@ -762,51 +776,20 @@ pub async fn mcp_add_from_claude_desktop(
     for (name, server_config) in mcp_servers {
         info!("Importing server: {}", name);
 
-        // Convert Claude Desktop format to add-json format
-        let mut json_config = serde_json::Map::new();
-
-        // All Claude Desktop servers are stdio type
-        json_config.insert(
-            "type".to_string(),
-            serde_json::Value::String("stdio".to_string()),
-        );
-
-        // Add command
-        if let Some(command) = server_config.get("command").and_then(|v| v.as_str()) {
-            json_config.insert(
-                "command".to_string(),
-                serde_json::Value::String(command.to_string()),
-            );
-        } else {
-            failed_count += 1;
-            server_results.push(ImportServerResult {
-                name: name.clone(),
-                success: false,
-                error: Some("Missing command field".to_string()),
-            });
-            continue;
-        }
-
-        // Add args if present
-        if let Some(args) = server_config.get("args").and_then(|v| v.as_array()) {
-            json_config.insert("args".to_string(), args.clone().into());
-        } else {
-            json_config.insert("args".to_string(), serde_json::Value::Array(vec![]));
-        }
-
-        // Add env if present
-        if let Some(env) = server_config.get("env").and_then(|v| v.as_object()) {
-            json_config.insert("env".to_string(), env.clone().into());
-        } else {
-            json_config.insert(
-                "env".to_string(),
-                serde_json::Value::Object(serde_json::Map::new()),
-            );
-        }
-
-        // Convert to JSON string
-        let json_str = serde_json::to_string(&json_config)
-            .map_err(|e| format!("Failed to serialize config for {}: {}", name, e))?;
+        let json_str = match normalize_mcp_config(server_config)
+            .and_then(|config| serde_json::to_string(&config).map_err(|e| e.to_string()))
+        {
+            Ok(json_str) => json_str,
+            Err(e) => {
+                failed_count += 1;
+                server_results.push(ImportServerResult {
+                    name: name.clone(),
+                    success: false,
+                    error: Some(e),
+                });
+                continue;
+            }
+        };
 
         // Call add-json command
         match mcp_add_json(app.clone(), name.clone(), json_str, scope.clone()).await {
@@ -857,7 +840,7 @@ pub async fn mcp_add_from_claude_desktop(
 
 /// Starts Claude Code as an MCP server
 #[tauri::command]
-pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
+pub async fn mcp_serve(app: AppHandle) -> Result<OperationResult, String> {
     info!("Starting Claude Code as MCP server");
 
     // Start the server in a separate process
@@ -865,7 +848,7 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
         Ok(path) => path,
         Err(e) => {
             error!("Failed to find claude binary: {}", e);
-            return Err(e.to_string());
+            return Ok(OperationResult::failed(e.to_string()));
         }
     };
 
@@ -920,42 +903,217 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
     };
 
     match cmd.spawn() {
-        Ok(_) => {
+        Ok(child) => {
             info!("Successfully started Claude Code MCP server");
-            Ok("Claude Code MCP server started".to_string())
+            Ok(OperationResult::succeeded("Claude Code MCP server started")
+                .with_details(serde_json::json!({ "pid": child.id() })))
         }
         Err(e) => {
             error!("Failed to start MCP server: {}", e);
-            Err(e.to_string())
+            Ok(OperationResult::failed(e.to_string()))
         }
     }
 }
 
 /// Tests connection to an MCP server
 #[tauri::command]
-pub async fn mcp_test_connection(app: AppHandle, name: String) -> Result<String, String> {
+pub async fn mcp_test_connection(app: AppHandle, name: String) -> Result<OperationResult, String> {
     info!("Testing connection to MCP server: {}", name);
 
     // For now, we'll use the get command to test if the server exists
-    match execute_claude_mcp_command(&app, vec!["get", &name]) {
-        Ok(_) => Ok(format!("Connection to {} successful", name)),
-        Err(e) => Err(e.to_string()),
+    let result = match execute_claude_mcp_command(&app, vec!["get", &name]).await {
+        Ok(_) => OperationResult::succeeded(format!("Connection to {} successful", name)),
+        Err(e) => OperationResult::failed(e.to_string()),
+    };
+    Ok(result.with_details(serde_json::json!({ "server": name })))
+}
+
+/// Health of a server from `claude mcp get` output, which reports a `Status:` line
+///
+/// Output without one, from Claude versions that don't connect to the server,
+/// counts as healthy since the server is configured.
+fn parse_mcp_health(output: &str) -> Result<(), String> {
+    let Some(status) = output.lines().find_map(|line| line.trim().strip_prefix("Status:")) else {
+        return Ok(());
+    };
+    let status = status.trim();
+    if status.contains("Connected") {
+        Ok(())
+    } else {
+        Err(status.trim_start_matches(['✗', '✘', ' ']).to_string())
     }
 }
 
+/// Check that Claude can start and connect to a configured MCP server
+pub(crate) async fn mcp_check_health(app: AppHandle, name: String) -> Result<(), String> {
+    let output = execute_claude_mcp_command(&app, vec!["get", &name]).await.map_err(|e| e.to_string())?;
+    parse_mcp_health(&output)
+}
+
+/// Write one JSON-RPC message as a line on the server's stdin
+async fn send_mcp_message(stdin: &mut tokio::process::ChildStdin, message: serde_json::Value) -> Result<()> {
+    let mut line = serde_json::to_vec(&message)?;
+    line.push(b'\n');
+    stdin.write_all(&line).await.context("Failed to write to MCP server")?;
+    stdin.flush().await.context("Failed to write to MCP server")
+}
+
+/// Read messages until the response to `id`, skipping notifications and stray output
+async fn read_mcp_response<R>(lines: &mut tokio::io::Lines<R>, id: u64) -> Result<serde_json::Value>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+{
+    loop {
+        let line = lines
+            .next_line()
+            .await
+            .context("Failed to read from MCP server")?
+            .context("MCP server closed its output before responding")?;
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            continue;
+        };
+        if message.get("id").and_then(|v| v.as_u64()) != Some(id) {
+            continue;
+        }
+        if let Some(error) = message.get("error") {
+            return Err(anyhow::anyhow!(
+                "MCP error {}: {}",
+                error.get("code").and_then(|c| c.as_i64()).unwrap_or_default(),
+                error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error")
+            ));
+        }
+        return message.get("result").cloned().context("MCP response has no result");
+    }
+}
+
+/// Run the MCP handshake, then call `tool` and return its result
+async fn call_mcp_tool(
+    stdin: &mut tokio::process::ChildStdin,
+    stdout: tokio::process::ChildStdout,
+    tool: &str,
+    args: serde_json::Value,
+) -> Result<serde_json::Value> {
+    let mut lines = BufReader::new(stdout).lines();
+
+    send_mcp_message(stdin, serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "claudia", "version": env!("CARGO_PKG_VERSION") },
+        },
+    })).await?;
+    read_mcp_response(&mut lines, 1).await.context("MCP initialize failed")?;
+    send_mcp_message(stdin, serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/initialized",
+    })).await?;
+
+    send_mcp_message(stdin, serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": tool, "arguments": args },
+    })).await?;
+    read_mcp_response(&mut lines, 2).await.with_context(|| format!("Calling tool '{}' failed", tool))
+}
+
+/// Start a stdio MCP server, call one tool and kill the server again, whatever the outcome
+async fn invoke_stdio_tool(
+    mut cmd: Command,
+    tool: &str,
+    args: serde_json::Value,
+    timeout: Duration,
+) -> Result<serde_json::Value> {
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = tokio::process::Command::from(cmd)
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start MCP server")?;
+    let mut stdin = child.stdin.take().context("Missing stdin pipe")?;
+    let stdout = child.stdout.take().context("Missing stdout pipe")?;
+    let (stderr, stderr_task) = collect_pipe(child.stderr.take().context("Missing stderr pipe")?);
+
+    let outcome = tokio::time::timeout(timeout, call_mcp_tool(&mut stdin, stdout, tool, args)).await;
+
+    if let Some(pid) = child.id() {
+        kill_process_tree(pid);
+    }
+    let _ = child.kill().await;
+    let _ = tokio::time::timeout(Duration::from_secs(1), stderr_task).await;
+
+    let stderr = stderr.lock().map(|b| String::from_utf8_lossy(&b).trim().to_string()).unwrap_or_default();
+    let error = match outcome {
+        Ok(Ok(result)) => return Ok(result),
+        Ok(Err(e)) => e,
+        Err(_) => anyhow::anyhow!("MCP server did not answer within {}s", timeout.as_secs()),
+    };
+    if stderr.is_empty() {
+        Err(error)
+    } else {
+        Err(anyhow::anyhow!("{:#}\nServer stderr: {}", error, stderr))
+    }
+}
+
+/// Starts a stdio MCP server, calls one of its tools with `args` and returns the tool result
+///
+/// The server is killed afterwards. Errors name the failing step (handshake or tool call)
+/// and include whatever the server wrote to stderr.
+#[tauri::command]
+pub async fn mcp_invoke_tool(
+    app: AppHandle,
+    server: String,
+    tool: String,
+    args: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    info!("Invoking tool {} on MCP server {}", tool, server);
+
+    let config = mcp_get(app, server.clone()).await?;
+    let command = config
+        .command
+        .filter(|_| config.transport == "stdio")
+        .ok_or_else(|| format!("MCP server {} uses {} transport; only stdio servers can be invoked", server, config.transport))?;
+
+    let mut cmd = create_command_with_env(&command);
+    cmd.args(&config.args).envs(&config.env);
+    invoke_stdio_tool(cmd, &tool, args, MCP_TOOL_CALL_TIMEOUT).await.map_err(|e| {
+        error!("Invoking {} on MCP server {} failed: {:#}", tool, server, e);
+        format!("{:#}", e)
+    })
+}
+
+/// Cancels every in-flight `claude mcp` invocation, returning how many were signalled
+#[tauri::command]
+pub async fn mcp_cancel_running() -> Result<usize, String> {
+    let signals = MCP_CANCEL_SIGNALS.lock().map_err(|e| e.to_string())?;
+    for cancel in signals.values() {
+        cancel.notify_one();
+    }
+    info!("Cancelled {} running MCP command(s)", signals.len());
+    Ok(signals.len())
+}
+
 /// Resets project-scoped server approval choices
 #[tauri::command]
-pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<String, String> {
+pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<OperationResult, String> {
     info!("Resetting MCP project choices");
 
-    match execute_claude_mcp_command(&app, vec!["reset-project-choices"]) {
+    match execute_claude_mcp_command(&app, vec!["reset-project-choices"]).await {
         Ok(output) => {
             info!("Successfully reset MCP project choices");
-            Ok(output.trim().to_string())
+            Ok(OperationResult::succeeded(output.trim()))
         }
         Err(e) => {
             error!("Failed to reset project choices: {}", e);
-            Err(e.to_string())
+            Ok(OperationResult::failed(e.to_string()))
         }
     }
 }
@@ -969,19 +1127,34 @@ pub async fn mcp_get_server_status(app: AppHandle) -> Result<HashMap<String, Ser
     let servers = mcp_list(app.clone()).await?;
     let mut status_map = HashMap::new();
 
+    let supervisor = app.try_state::<McpSupervisor>();
     for server in servers {
-        // Check if server process is running by testing connection
-        let status = if let Ok(_) = mcp_test_connection(app.clone(), server.name.clone()).await {
+        let last_checked = Some(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs());
+        // Supervised servers report their last health check; others are checked by testing a connection
+        let supervision = supervisor.as_ref().and_then(|supervisor| supervisor.status(&server.name));
+        let status = if let Some(supervision) = supervision {
+            ServerStatus {
+                running: supervision.state == SupervisedState::Healthy,
+                error: match supervision.state {
+                    SupervisedState::Unhealthy | SupervisedState::Failed => supervision.last_error.clone(),
+                    _ => None,
+                },
+                last_checked,
+                supervision: Some(supervision),
+            }
+        } else if let Ok(_) = mcp_test_connection(app.clone(), server.name.clone()).await {
             ServerStatus {
                 running: true,
                 error: None,
-                last_checked: Some(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()),
+                last_checked,
+                supervision: None,
             }
         } else {
             ServerStatus {
                 running: false,
                 error: Some("Connection test failed".to_string()),
-                last_checked: Some(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()),
+                last_checked,
+                supervision: None,
             }
         };
         
@@ -1024,18 +1197,142 @@ pub async fn mcp_read_project_config(project_path: String) -> Result<MCPProjectC
 pub async fn mcp_save_project_config(
     project_path: String,
     config: MCPProjectConfig,
-) -> Result<String, String> {
+) -> Result<OperationResult, String> {
     info!("Saving .mcp.json to project: {}", project_path);
 
     let mcp_json_path = PathBuf::from(&project_path).join(".mcp.json");
+    let result = match save_project_config(&mcp_json_path, &config) {
+        Ok(()) => OperationResult::succeeded("Project MCP configuration saved"),
+        Err(e) => {
+            error!("Failed to save project MCP config: {}", e);
+            OperationResult::failed(e)
+        }
+    };
+    Ok(result.with_details(serde_json::json!({
+        "path": mcp_json_path,
+        "servers": config.mcp_servers.len(),
+    })))
+}
+
+/// Write a project's .mcp.json, keeping servers that were switched off
+fn save_project_config(mcp_json_path: &Path, config: &MCPProjectConfig) -> Result<(), String> {
+    let _guard = PROJECT_CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let mut json_value = serde_json::to_value(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    // Keep servers that were switched off rather than dropping them
+    let disabled = fs::read_to_string(mcp_json_path).ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|mut existing| existing.get_mut(DISABLED_MCP_SERVERS_KEY).map(|v| v.take()));
+    if let (Some(disabled), Some(object)) = (disabled, json_value.as_object_mut()) {
+        object.insert(DISABLED_MCP_SERVERS_KEY.to_string(), disabled);
+    }
+
+    let json_content = serde_json::to_string_pretty(&json_value)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    fs::write(mcp_json_path, json_content)
+        .map_err(|e| format!("Failed to write .mcp.json: {}", e))
+}
+
+/// Move server `name` between `mcpServers` and the disabled section.
+/// Returns false when it is already in the requested state.
+fn toggle_server_in_config(config: &mut serde_json::Value, name: &str, enabled: bool) -> Result<bool, String> {
+    let object = config.as_object_mut()
+        .ok_or_else(|| ".mcp.json must contain a JSON object".to_string())?;
+    let (from_key, to_key) = if enabled {
+        (DISABLED_MCP_SERVERS_KEY, "mcpServers")
+    } else {
+        ("mcpServers", DISABLED_MCP_SERVERS_KEY)
+    };
+
+    let already_there = object.get(to_key)
+        .and_then(|section| section.as_object())
+        .is_some_and(|section| section.contains_key(name));
+    if already_there {
+        return Ok(false);
+    }
+
+    let from = object.get_mut(from_key)
+        .and_then(|section| section.as_object_mut())
+        .ok_or_else(|| format!("Server {} is not configured in .mcp.json", name))?;
+    let server = from.shift_remove(name)
+        .ok_or_else(|| format!("Server {} is not configured in .mcp.json", name))?;
+    if from.is_empty() && from_key == DISABLED_MCP_SERVERS_KEY {
+        object.shift_remove(from_key);
+    }
+
+    object.entry(to_key)
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| format!("\"{}\" in .mcp.json must be an object", to_key))?
+        .insert(name.to_string(), server);
+    Ok(true)
+}
+
+/// Toggle one server in a project's .mcp.json under the config lock, leaving the rest as written
+fn toggle_project_server(mcp_json_path: &Path, name: &str, enabled: bool) -> Result<bool, String> {
+    let _guard = PROJECT_CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
 
-    let json_content = serde_json::to_string_pretty(&config)
+    let content = fs::read_to_string(mcp_json_path)
+        .map_err(|e| format!("Failed to read .mcp.json: {}", e))?;
+    let mut config: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse .mcp.json: {}", e))?;
+
+    if !toggle_server_in_config(&mut config, name, enabled)? {
+        return Ok(false);
+    }
+
+    // Both sections must still hold valid server definitions
+    serde_json::from_value::<MCPProjectConfig>(config.clone())
+        .map_err(|e| format!("Refusing to write invalid .mcp.json: {}", e))?;
+    if let Some(disabled) = config.get(DISABLED_MCP_SERVERS_KEY) {
+        serde_json::from_value::<HashMap<String, MCPServerConfig>>(disabled.clone())
+            .map_err(|e| format!("Refusing to write invalid .mcp.json: {}", e))?;
+    }
+
+    let mut json_content = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    if content.ends_with('\n') {
+        json_content.push('\n');
+    }
 
-    fs::write(&mcp_json_path, json_content)
+    // Write beside the original and rename so readers never see a partial file
+    let tmp_path = mcp_json_path.with_extension("json.tmp");
+    fs::write(&tmp_path, json_content)
         .map_err(|e| format!("Failed to write .mcp.json: {}", e))?;
+    fs::rename(&tmp_path, mcp_json_path)
+        .map_err(|e| format!("Failed to replace .mcp.json: {}", e))?;
+    Ok(true)
+}
 
-    Ok("Project MCP configuration saved".to_string())
+/// Enables or disables a single server in the project's .mcp.json
+#[tauri::command]
+pub async fn mcp_toggle_project_server(
+    project_path: String,
+    name: String,
+    enabled: bool,
+) -> Result<OperationResult, String> {
+    info!("Setting project MCP server {} enabled={} in {}", name, enabled, project_path);
+
+    let mcp_json_path = PathBuf::from(&project_path).join(".mcp.json");
+    Ok(toggle_project_server_result(&mcp_json_path, &name, enabled))
+}
+
+/// Outcome of `toggle_project_server`, with whether the file actually changed
+fn toggle_project_server_result(mcp_json_path: &Path, name: &str, enabled: bool) -> OperationResult {
+    let state = if enabled { "enabled" } else { "disabled" };
+    let (result, changed) = match toggle_project_server(mcp_json_path, name, enabled) {
+        Ok(true) => (OperationResult::succeeded(format!("Server {} {}", name, state)), true),
+        Ok(false) => (OperationResult::succeeded(format!("Server {} is already {}", name, state)), false),
+        Err(e) => (OperationResult::failed(e), false),
+    };
+    result.with_details(serde_json::json!({
+        "server": name,
+        "enabled": enabled,
+        "changed": changed,
+    }))
 }
 
 /// Updates an existing MCP server configuration
@@ -1126,54 +1423,365 @@ pub async fn mcp_update(
     }
 }
 
+/// Servers configured for the directory `claude mcp` runs in, read from its config files
+fn read_configured_servers() -> Result<ScopedMcpServers, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    let project_dir = std::env::current_dir()
+        .map_err(|e| format!("Failed to get current directory: {}", e))?;
+    let read = |path: PathBuf| -> Result<Option<serde_json::Value>, String> {
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    };
+    let claude_json = read(home.join(".claude.json"))?;
+    let mcp_json = read(project_dir.join(".mcp.json"))?;
+    configured_servers(claude_json.as_ref(), &project_dir, mcp_json.as_ref())
+}
+
+/// All MCP servers grouped by scope, with credential env values as `${KEY}` placeholders
+fn scoped_servers() -> Result<ScopedMcpServers, String> {
+    let mut servers = read_configured_servers()?;
+    redact_env(&mut servers);
+    Ok(servers)
+}
+
+/// Each server once, the scope `claude` prefers winning: local, then project, then user
+fn effective_servers(scoped: ScopedMcpServers) -> BTreeMap<String, MCPServerConfig> {
+    let mut servers = BTreeMap::new();
+    for scope in ["user", "project", "local"] {
+        if let Some(scope_servers) = scoped.get(scope) {
+            servers.extend(scope_servers.clone());
+        }
+    }
+    servers
+}
+
 /// Exports an MCP server configuration as JSON
 #[tauri::command]
-pub async fn mcp_export_json(
-    app: AppHandle,
-    name: String,
-) -> Result<String, String> {
+pub async fn mcp_export_json(name: String) -> Result<String, String> {
     info!("Exporting MCP server {} as JSON", name);
-    
-    // Get the server details
-    let server = mcp_get(app, name).await?;
-    
-    // Convert to MCPServerConfig format
-    let config = MCPServerConfig {
-        command: server.command,
-        args: if server.args.is_empty() { vec![] } else { server.args },
-        env: if server.env.is_empty() { HashMap::new() } else { server.env },
-        url: server.url,
-    };
-    
-    // Serialize to JSON
+
+    let config = effective_servers(scoped_servers()?)
+        .remove(&name)
+        .ok_or_else(|| format!("MCP server '{}' is not configured", name))?;
     serde_json::to_string_pretty(&config)
         .map_err(|e| format!("Failed to serialize server config: {}", e))
 }
 
-/// Gets all MCP servers as a JSON configuration
+/// Gets all MCP servers as a JSON configuration, with sorted keys so it diffs cleanly
 #[tauri::command]
-pub async fn mcp_export_all_json(
-    app: AppHandle,
-) -> Result<String, String> {
+pub async fn mcp_export_all_json() -> Result<String, String> {
     info!("Exporting all MCP servers as JSON");
-    
-    // Get all servers
-    let servers = mcp_list(app).await?;
-    
-    // Convert to a map of MCPServerConfig
-    let mut config_map: HashMap<String, MCPServerConfig> = HashMap::new();
-    
-    for server in servers {
-        let config = MCPServerConfig {
-            command: server.command,
-            args: if server.args.is_empty() { vec![] } else { server.args },
-            env: if server.env.is_empty() { HashMap::new() } else { server.env },
-            url: server.url,
-        };
-        config_map.insert(server.name, config);
+    render_servers(&effective_servers(scoped_servers()?))
+}
+
+/// Exports all MCP servers as one document keyed by scope, for checking into version control
+#[tauri::command]
+pub async fn mcp_export_all_json_scoped() -> Result<String, String> {
+    info!("Exporting all MCP servers by scope as JSON");
+    render_scoped_export(&scoped_servers()?)
+}
+
+/// Exports each scope's MCP servers as its own `{"mcpServers": ...}` file, keyed by scope
+#[tauri::command]
+pub async fn mcp_export_all_json_by_scope() -> Result<BTreeMap<String, String>, String> {
+    info!("Exporting MCP servers as one JSON file per scope");
+    render_export_by_scope(&scoped_servers()?)
+}
+
+/// Imports servers from any of the exports above
+///
+/// `scope` is required for exports that don't name their scopes. With `replace`,
+/// a server of the same name in the same scope is removed first, so importing
+/// an export restores exactly the servers it lists. `${KEY}` placeholders take
+/// the value the server already has, or else the one in the environment.
+#[tauri::command]
+pub async fn mcp_import_all_json(
+    app: AppHandle,
+    json_config: String,
+    scope: Option<String>,
+    replace: Option<bool>,
+) -> Result<ImportResult, String> {
+    let mut export = parse_export(&json_config, scope.as_deref())?;
+    let configured = read_configured_servers().unwrap_or_else(|e| {
+        error!("Failed to read configured MCP servers: {}", e);
+        ScopedMcpServers::new()
+    });
+    restore_env(&mut export, &configured, |name| std::env::var(name).ok());
+    let replace = replace.unwrap_or(false);
+
+    let mut imported_count = 0;
+    let mut failed_count = 0;
+    let mut server_results = Vec::new();
+    for (scope, servers) in export {
+        for (name, config) in servers {
+            info!("Importing server {} into scope {}", name, scope);
+            if replace {
+                // Missing servers fail to remove, which is fine
+                let _ = execute_claude_mcp_command(&app, vec!["remove", &name, "-s", &scope]).await;
+            }
+            let result = match serde_json::to_string(&config) {
+                Ok(json_str) => mcp_add_json(app.clone(), name.clone(), json_str, scope.clone()).await,
+                Err(e) => Err(e.to_string()),
+            };
+            let error = match result {
+                Ok(result) if result.success => None,
+                Ok(result) => Some(result.message),
+                Err(e) => Some(e),
+            };
+            match &error {
+                None => imported_count += 1,
+                Some(e) => {
+                    failed_count += 1;
+                    error!("Failed to import server {}: {}", name, e);
+                }
+            }
+            server_results.push(ImportServerResult { name, success: error.is_none(), error });
+        }
+    }
+
+    info!(
+        "Import complete: {} imported, {} failed",
+        imported_count, failed_count
+    );
+    Ok(ImportResult {
+        imported_count,
+        failed_count,
+        servers: server_results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_comes_from_the_status_line() {
+        let connected = "my-server:\n  Scope: Local\n  Status: ✓ Connected\n  Type: stdio\n  Command: npx\n";
+        assert_eq!(parse_mcp_health(connected), Ok(()));
+        let failed = "my-server:\n  Scope: Local\n  Status: ✗ Failed to connect\n  Type: stdio\n";
+        assert_eq!(parse_mcp_health(failed), Err("Failed to connect".to_string()));
+        assert_eq!(parse_mcp_health("my-server:\n  Scope: Local\n  Type: stdio\n"), Ok(()));
+    }
+    #[cfg(unix)]
+    use std::time::Instant;
+
+    #[cfg(unix)]
+    fn sleepy_command() -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("echo started; echo warming >&2; sleep 30; echo never");
+        cmd
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_kills_hung_command_and_keeps_partial_output() {
+        let cancel = Arc::new(Notify::new());
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            trigger.notify_one();
+        });
+
+        let started = Instant::now();
+        let err = run_cancellable(sleepy_command(), Duration::from_secs(30), cancel)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.contains("was cancelled"));
+        assert!(err.contains("Partial stdout: started"));
+        assert!(err.contains("Partial stderr: warming"));
+        assert!(!err.contains("never"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_stops_hung_command() {
+        let started = Instant::now();
+        let err = run_cancellable(sleepy_command(), Duration::from_millis(300), Arc::new(Notify::new()))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.contains("timed out"));
+    }
+
+    /// Answers the handshake and one tool call, then hangs until killed
+    #[cfg(unix)]
+    fn mock_mcp_server(tool_response: &str) -> Command {
+        let script = format!(
+            r#"read init
+echo 'server starting' >&2
+echo 'not json'
+echo '{{"jsonrpc":"2.0","id":1,"result":{{"protocolVersion":"2024-11-05","capabilities":{{"tools":{{}}}},"serverInfo":{{"name":"mock","version":"1"}}}}}}'
+read initialized
+read call
+for expected in '"method":"tools/call"' '"name":"echo"' '"text":"ping"'; do
+  case "$call" in *"$expected"*) ;; *) echo "unexpected call: $call" >&2; exit 1;; esac
+done
+echo '{{"jsonrpc":"2.0","method":"notifications/message","params":{{}}}}'
+echo '{tool_response}'
+sleep 30"#
+        );
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_invoke_tool_returns_result_and_kills_server() {
+        let server = mock_mcp_server(r#"{"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"pong"}]}}"#);
+        let started = Instant::now();
+        let result = invoke_stdio_tool(server, "echo", serde_json::json!({ "text": "ping" }), Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(result, serde_json::json!({ "content": [{ "type": "text", "text": "pong" }] }));
+        assert!(started.elapsed() < Duration::from_secs(5), "server was killed instead of awaited");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_invoke_tool_reports_tool_errors_and_timeouts() {
+        let server = mock_mcp_server(r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32602,"message":"Unknown tool: echo"}}"#);
+        let err = invoke_stdio_tool(server, "echo", serde_json::json!({ "text": "ping" }), Duration::from_secs(10))
+            .await
+            .unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("Calling tool 'echo' failed: MCP error -32602: Unknown tool: echo"), "{}", message);
+        assert!(message.contains("Server stderr: server starting"), "{}", message);
+
+        let started = Instant::now();
+        let err = invoke_stdio_tool(sleepy_command(), "echo", serde_json::json!({}), Duration::from_millis(300))
+            .await
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(format!("{:#}", err).contains("did not answer within"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_completed_command_returns_output() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("echo done");
+        let output = run_cancellable(cmd, Duration::from_secs(10), Arc::new(Notify::new()))
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+    }
+
+    const PROJECT_MCP_JSON: &str = r#"{
+  "mcpServers": {
+    "zeta": {
+      "command": "npx",
+      "args": ["-y", "zeta-server"]
+    },
+    "github": {
+      "command": "npx",
+      "args": ["-y", "@modelcontextprotocol/server-github"],
+      "env": {"GITHUB_TOKEN": "x"}
+    },
+    "alpha": {
+      "url": "http://localhost:3000/sse"
+    }
+  },
+  "customSetting": true
+}
+"#;
+
+    #[test]
+    fn test_toggle_project_server_leaves_rest_of_config_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        fs::write(&path, PROJECT_MCP_JSON).unwrap();
+        let original: serde_json::Value = serde_json::from_str(PROJECT_MCP_JSON).unwrap();
+
+        assert!(toggle_project_server(&path, "github", false).unwrap());
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.ends_with('\n'));
+        let disabled: serde_json::Value = serde_json::from_str(&written).unwrap();
+
+        let servers = disabled["mcpServers"].as_object().unwrap();
+        assert_eq!(servers.keys().collect::<Vec<_>>(), ["zeta", "alpha"]);
+        assert_eq!(servers["zeta"], original["mcpServers"]["zeta"]);
+        assert_eq!(servers["alpha"], original["mcpServers"]["alpha"]);
+        assert_eq!(disabled[DISABLED_MCP_SERVERS_KEY]["github"], original["mcpServers"]["github"]);
+        assert_eq!(disabled["customSetting"], true);
+        // Claude still reads the file as a valid project config
+        let parsed: MCPProjectConfig = serde_json::from_str(&written).unwrap();
+        assert!(!parsed.mcp_servers.contains_key("github"));
+
+        // Repeating the toggle is a no-op
+        assert!(!toggle_project_server(&path, "github", false).unwrap());
+
+        assert!(toggle_project_server(&path, "github", true).unwrap());
+        let restored: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn test_toggle_unknown_project_server_fails_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        fs::write(&path, PROJECT_MCP_JSON).unwrap();
+
+        assert!(toggle_project_server(&path, "missing", false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), PROJECT_MCP_JSON);
+    }
+
+    #[test]
+    fn test_toggle_project_server_reports_structured_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        fs::write(&path, PROJECT_MCP_JSON).unwrap();
+
+        let disabled = toggle_project_server_result(&path, "github", false);
+        assert!(disabled.success);
+        assert_eq!(disabled.message, "Server github disabled");
+        assert_eq!(
+            disabled.details,
+            Some(serde_json::json!({ "server": "github", "enabled": false, "changed": true }))
+        );
+
+        let repeated = toggle_project_server_result(&path, "github", false);
+        assert!(repeated.success);
+        assert_eq!(repeated.details.unwrap()["changed"], false);
+
+        let missing = toggle_project_server_result(&path, "missing", true);
+        assert!(!missing.success);
+        assert_eq!(missing.message, "Server missing is not configured in .mcp.json");
+        // The frontend reads the same keys for every outcome
+        let json = serde_json::to_value(&missing).unwrap();
+        assert_eq!(
+            json.as_object().unwrap().keys().collect::<Vec<_>>(),
+            ["success", "message", "details"]
+        );
+        assert_eq!(json["details"]["server"], "missing");
+    }
+
+    #[tokio::test]
+    async fn test_save_project_config_reports_success_and_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let config: MCPProjectConfig = serde_json::from_str(PROJECT_MCP_JSON).unwrap();
+
+        let saved = mcp_save_project_config(dir.path().to_string_lossy().to_string(), config.clone())
+            .await
+            .unwrap();
+        assert!(saved.success);
+        assert_eq!(saved.message, "Project MCP configuration saved");
+        assert_eq!(saved.details.unwrap()["servers"], 3);
+
+        let missing_dir = dir.path().join("does-not-exist");
+        let failed = mcp_save_project_config(missing_dir.to_string_lossy().to_string(), config)
+            .await
+            .unwrap();
+        assert!(!failed.success);
+        assert!(failed.message.starts_with("Failed to write .mcp.json"), "{}", failed.message);
     }
-    
-    // Serialize to JSON
-    serde_json::to_string_pretty(&config_map)
-        .map_err(|e| format!("Failed to serialize servers config: {}", e))
 }
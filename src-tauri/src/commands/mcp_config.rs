@@ -0,0 +1,526 @@
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::mcp::MCPServerConfig;
+use super::session_event_log::is_secret_name;
+
+/// Transports `claude mcp add-json` accepts
+const TRANSPORTS: [&str; 3] = ["stdio", "sse", "http"];
+
+/// Where an MCP config was copied from, judged by its shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum McpConfigFormat {
+    /// `{"mcpServers": {...}}` as in Claude Desktop, `.mcp.json` and Cursor
+    McpServers,
+    /// `{"servers": {...}}` as in VS Code's `mcp.json`
+    VsCode,
+    /// `{"mcp": {"servers": {...}}}` as in VS Code's settings.json
+    VsCodeSettings,
+    /// A single server object
+    Server,
+}
+
+/// Tell which format `raw` is written in
+pub fn detect_mcp_config_format(raw: &Value) -> Result<McpConfigFormat, String> {
+    let obj = raw
+        .as_object()
+        .ok_or("Invalid MCP config: expected a JSON object")?;
+    if obj.contains_key("mcpServers") {
+        Ok(McpConfigFormat::McpServers)
+    } else if obj.contains_key("servers") {
+        Ok(McpConfigFormat::VsCode)
+    } else if obj.get("mcp").is_some_and(|mcp| mcp.get("servers").is_some()) {
+        Ok(McpConfigFormat::VsCodeSettings)
+    } else if ["command", "url", "serverUrl"].iter().any(|key| obj.contains_key(*key)) {
+        Ok(McpConfigFormat::Server)
+    } else {
+        Err("Unrecognized MCP config: expected a server with 'command' or 'url', \
+             or servers under 'mcpServers', 'servers' or 'mcp.servers'"
+            .to_string())
+    }
+}
+
+/// The single server in a map of named servers
+fn only_server<'a>(servers: &'a Value, key: &str) -> Result<&'a Value, String> {
+    let servers = servers
+        .as_object()
+        .ok_or_else(|| format!("Invalid MCP config: '{}' must be an object", key))?;
+    let mut entries = servers.values();
+    match (entries.next(), servers.len()) {
+        (Some(server), 1) => Ok(server),
+        (None, _) => Err(format!("Invalid MCP config: '{}' has no servers", key)),
+        (_, count) => Err(format!(
+            "Invalid MCP config: '{}' has {} servers, add them one at a time",
+            key, count
+        )),
+    }
+}
+
+fn string_field(obj: &Map<String, Value>, key: &str) -> Result<Option<String>, String> {
+    match obj.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) if !value.trim().is_empty() => Ok(Some(value.trim().to_string())),
+        Some(_) => Err(format!("Invalid MCP config: '{}' must be a non-empty string", key)),
+    }
+}
+
+fn normalize_server(obj: &Map<String, Value>) -> Result<MCPServerConfig, String> {
+    let command = string_field(obj, "command")?;
+    let url = match string_field(obj, "url")? {
+        Some(url) => Some(url),
+        None => string_field(obj, "serverUrl")?,
+    };
+    let declared = match string_field(obj, "type")? {
+        Some(transport) => Some(transport),
+        None => string_field(obj, "transport")?,
+    };
+
+    let transport = match (declared, &command, &url) {
+        (Some(transport), _, _) => {
+            let transport = transport.to_lowercase();
+            if !TRANSPORTS.contains(&transport.as_str()) {
+                return Err(format!("Invalid MCP config: unknown transport type '{}'", transport));
+            }
+            transport
+        }
+        (None, Some(_), None) => "stdio".to_string(),
+        (None, None, Some(_)) => "sse".to_string(),
+        (None, Some(_), Some(_)) => {
+            return Err("Invalid MCP config: both 'command' and 'url' are set; add a 'type' to choose".to_string())
+        }
+        (None, None, None) => return Err("Invalid MCP config: 'command' or 'url' is required".to_string()),
+    };
+
+    if transport == "stdio" {
+        if command.is_none() {
+            return Err("Invalid MCP config: 'command' is required for stdio transport".to_string());
+        }
+    } else {
+        let url = url
+            .as_deref()
+            .ok_or_else(|| format!("Invalid MCP config: 'url' is required for {} transport", transport))?;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err("Invalid MCP config: 'url' must start with http:// or https://".to_string());
+        }
+    }
+
+    let args = match obj.get("args") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(args)) => args
+            .iter()
+            .enumerate()
+            .map(|(idx, arg)| {
+                arg.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("Invalid MCP config: args[{}] must be a string", idx))
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("Invalid MCP config: 'args' must be an array".to_string()),
+    };
+
+    // Numbers and booleans are written unquoted often enough to accept them
+    let env = match obj.get("env") {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(Value::Object(env)) => env
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(value) => Ok((key.clone(), value.clone())),
+                Value::Number(_) | Value::Bool(_) => Ok((key.clone(), value.to_string())),
+                _ => Err(format!("Invalid MCP config: env['{}'] must be a string", key)),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("Invalid MCP config: 'env' must be an object".to_string()),
+    };
+
+    let stdio = transport == "stdio";
+    Ok(MCPServerConfig {
+        command: if stdio { command } else { None },
+        args: if stdio { args } else { Vec::new() },
+        env,
+        url: if stdio { None } else { url },
+        transport: Some(transport),
+    })
+}
+
+/// Normalize a server config in any supported format to the canonical shape
+///
+/// The result is what `claude mcp add-json` is handed, with its transport always
+/// spelled out. Configs holding a map of servers must hold exactly one.
+pub fn normalize_mcp_config(raw: &Value) -> Result<MCPServerConfig, String> {
+    let server = match detect_mcp_config_format(raw)? {
+        McpConfigFormat::McpServers => only_server(&raw["mcpServers"], "mcpServers")?,
+        McpConfigFormat::VsCode => only_server(&raw["servers"], "servers")?,
+        McpConfigFormat::VsCodeSettings => only_server(&raw["mcp"]["servers"], "mcp.servers")?,
+        McpConfigFormat::Server => raw,
+    };
+    let obj = server
+        .as_object()
+        .ok_or("Invalid MCP config: a server must be a JSON object")?;
+    normalize_server(obj)
+}
+
+/// Scopes `claude mcp` keeps servers in
+pub const MCP_SCOPES: [&str; 3] = ["local", "project", "user"];
+
+/// Servers by scope, then by name
+pub type ScopedMcpServers = BTreeMap<String, BTreeMap<String, MCPServerConfig>>;
+
+/// Whether `key` of the `projects` map in `~/.claude.json` names `project_dir`
+fn is_project_key(key: &str, project_dir: &Path) -> bool {
+    let normalize = |path: &str| {
+        let path = path.replace('\\', "/").trim_end_matches('/').to_string();
+        if cfg!(windows) { path.to_lowercase() } else { path }
+    };
+    normalize(key) == normalize(&project_dir.to_string_lossy())
+}
+
+/// Servers `claude mcp` has configured for `project_dir`, read from its config files
+///
+/// `claude_json` is `~/.claude.json`: its `mcpServers` are the user scope and
+/// `projects.<dir>.mcpServers` the local scope. `mcp_json` is the project's
+/// `.mcp.json`. Only scopes with servers are listed.
+pub fn configured_servers(
+    claude_json: Option<&Value>,
+    project_dir: &Path,
+    mcp_json: Option<&Value>,
+) -> Result<ScopedMcpServers, String> {
+    let local = claude_json
+        .and_then(|json| json.get("projects"))
+        .and_then(Value::as_object)
+        .and_then(|projects| projects.iter().find(|(key, _)| is_project_key(key, project_dir)))
+        .and_then(|(_, project)| project.get("mcpServers"));
+    let sources = [
+        ("local", local),
+        ("project", mcp_json.and_then(|json| json.get("mcpServers"))),
+        ("user", claude_json.and_then(|json| json.get("mcpServers"))),
+    ];
+
+    let mut servers = ScopedMcpServers::new();
+    for (scope, source) in sources {
+        if let Some(source) = source {
+            let scope_servers = parse_servers(source, &format!("{}.mcpServers", scope))?;
+            if !scope_servers.is_empty() {
+                servers.insert(scope.to_string(), scope_servers);
+            }
+        }
+    }
+    Ok(servers)
+}
+
+/// The variable an env value refers to when it is a bare `${NAME}` placeholder
+fn placeholder_name(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("${")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Replace credential env values with `${KEY}` placeholders, so exports can be committed
+pub fn redact_env(servers: &mut ScopedMcpServers) {
+    for config in servers.values_mut().flat_map(|scope_servers| scope_servers.values_mut()) {
+        for (key, value) in config.env.iter_mut() {
+            if is_secret_name(key) && placeholder_name(value).is_none() {
+                *value = format!("${{{}}}", key);
+            }
+        }
+    }
+}
+
+/// Fill placeholders of an imported export back in before the servers are added
+///
+/// A placeholder takes the value the same server already has in `configured`.
+/// Failing that, local and user servers take it from `environment`; project
+/// servers keep the placeholder, which `.mcp.json` expands when a server starts,
+/// so no secret is written into the project.
+pub fn restore_env(
+    servers: &mut ScopedMcpServers,
+    configured: &ScopedMcpServers,
+    environment: impl Fn(&str) -> Option<String>,
+) {
+    for (scope, scope_servers) in servers.iter_mut() {
+        for (name, config) in scope_servers.iter_mut() {
+            let existing = configured.get(scope).and_then(|servers| servers.get(name));
+            for (key, value) in config.env.iter_mut() {
+                let Some(variable) = placeholder_name(value) else { continue };
+                let restored = existing
+                    .and_then(|existing| existing.env.get(key))
+                    .filter(|existing| placeholder_name(existing).is_none())
+                    .cloned()
+                    .or_else(|| if scope == "project" { None } else { environment(variable) });
+                if let Some(restored) = restored {
+                    *value = restored;
+                }
+            }
+        }
+    }
+}
+
+/// `value` with the keys of every object in sorted order
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(obj) => {
+            let mut entries: Vec<_> = obj.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(key, value)| (key, sorted(value))).collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sorted).collect()),
+        value => value,
+    }
+}
+
+/// Pretty-printed with sorted keys and a final newline, so an unchanged setup exports byte for byte the same file
+fn render(value: Value) -> Result<String, String> {
+    serde_json::to_string_pretty(&sorted(value))
+        .map(|json| json + "\n")
+        .map_err(|e| format!("Failed to serialize servers config: {}", e))
+}
+
+fn servers_value(servers: &BTreeMap<String, MCPServerConfig>) -> Result<Value, String> {
+    serde_json::to_value(servers).map_err(|e| format!("Failed to serialize servers config: {}", e))
+}
+
+/// Render servers as a map from name to config
+pub fn render_servers(servers: &BTreeMap<String, MCPServerConfig>) -> Result<String, String> {
+    render(servers_value(servers)?)
+}
+
+/// Render every scope in one document, `{"<scope>": {"mcpServers": {...}}}`
+pub fn render_scoped_export(servers: &ScopedMcpServers) -> Result<String, String> {
+    let mut document = Map::new();
+    for (scope, scope_servers) in servers {
+        document.insert(scope.clone(), serde_json::json!({ "mcpServers": servers_value(scope_servers)? }));
+    }
+    render(Value::Object(document))
+}
+
+/// Render each scope as its own `{"mcpServers": {...}}` file, the layout of `.mcp.json`
+pub fn render_export_by_scope(servers: &ScopedMcpServers) -> Result<BTreeMap<String, String>, String> {
+    servers
+        .iter()
+        .map(|(scope, scope_servers)| {
+            let file = render(serde_json::json!({ "mcpServers": servers_value(scope_servers)? }))?;
+            Ok((scope.clone(), file))
+        })
+        .collect()
+}
+
+fn parse_servers(servers: &Value, key: &str) -> Result<BTreeMap<String, MCPServerConfig>, String> {
+    servers
+        .as_object()
+        .ok_or_else(|| format!("Invalid MCP export: '{}' must be an object", key))?
+        .iter()
+        .map(|(name, server)| {
+            let config = server
+                .as_object()
+                .ok_or_else(|| format!("Invalid MCP export: server '{}' must be an object", name))
+                .and_then(normalize_server)
+                .map_err(|e| format!("{} (server '{}')", e, name))?;
+            Ok((name.clone(), config))
+        })
+        .collect()
+}
+
+/// Parse an export made by any of the renderers back into servers by scope
+///
+/// A scoped document names its scopes; a single `{"mcpServers": ...}` file or
+/// a bare map of servers needs `scope` to say where the servers go.
+pub fn parse_export(json: &str, scope: Option<&str>) -> Result<ScopedMcpServers, String> {
+    let raw: Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let obj = raw.as_object().ok_or("Invalid MCP export: expected a JSON object")?;
+    let scoped = !obj.is_empty() && obj.values().all(|value| value.get("mcpServers").is_some());
+
+    let mut export = ScopedMcpServers::new();
+    if scoped {
+        for (scope, value) in obj {
+            if !MCP_SCOPES.contains(&scope.as_str()) {
+                return Err(format!("Invalid MCP export: unknown scope '{}'", scope));
+            }
+            export.insert(scope.clone(), parse_servers(&value["mcpServers"], &format!("{}.mcpServers", scope))?);
+        }
+        return Ok(export);
+    }
+
+    let scope = scope.ok_or("This export has no scopes; choose the scope to import into")?;
+    if !MCP_SCOPES.contains(&scope) {
+        return Err(format!("Unknown scope '{}'", scope));
+    }
+    let servers = match obj.get("mcpServers") {
+        Some(servers) => parse_servers(servers, "mcpServers")?,
+        None => parse_servers(&raw, "servers")?,
+    };
+    export.insert(scope.to_string(), servers);
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_every_format_normalizes_to_the_same_config() {
+        let server = json!({
+            "command": "npx",
+            "args": ["-y", "@modelcontextprotocol/server-github"],
+            "env": { "GITHUB_TOKEN": "ghp_example" }
+        });
+        let typed = json!({
+            "type": "stdio",
+            "command": "npx",
+            "args": ["-y", "@modelcontextprotocol/server-github"],
+            "env": { "GITHUB_TOKEN": "ghp_example" }
+        });
+        let inputs = [
+            (json!({ "mcpServers": { "github": server.clone() } }), McpConfigFormat::McpServers),
+            (json!({ "servers": { "github": typed.clone() } }), McpConfigFormat::VsCode),
+            (json!({ "mcp": { "servers": { "github": typed.clone() } } }), McpConfigFormat::VsCodeSettings),
+            (server, McpConfigFormat::Server),
+            (typed, McpConfigFormat::Server),
+        ];
+
+        let expected = MCPServerConfig {
+            command: Some("npx".to_string()),
+            args: vec!["-y".to_string(), "@modelcontextprotocol/server-github".to_string()],
+            env: HashMap::from([("GITHUB_TOKEN".to_string(), "ghp_example".to_string())]),
+            url: None,
+            transport: Some("stdio".to_string()),
+        };
+        for (raw, format) in inputs {
+            assert_eq!(detect_mcp_config_format(&raw), Ok(format), "{}", raw);
+            assert_eq!(normalize_mcp_config(&raw), Ok(expected.clone()), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_remote_servers_keep_their_transport() {
+        let sse = MCPServerConfig {
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            url: Some("https://mcp.example.com/sse".to_string()),
+            transport: Some("sse".to_string()),
+        };
+        assert_eq!(normalize_mcp_config(&json!({ "url": "https://mcp.example.com/sse" })), Ok(sse.clone()));
+        assert_eq!(normalize_mcp_config(&json!({ "serverUrl": "https://mcp.example.com/sse" })), Ok(sse));
+
+        let http = normalize_mcp_config(&json!({
+            "servers": { "docs": { "type": "http", "url": "https://mcp.example.com/mcp" } }
+        }))
+        .unwrap();
+        assert_eq!(http.transport.as_deref(), Some("http"));
+        assert_eq!(
+            serde_json::to_value(&http).unwrap(),
+            json!({ "type": "http", "url": "https://mcp.example.com/mcp" })
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_and_invalid_configs_are_explained() {
+        let error = |raw: Value| normalize_mcp_config(&raw).unwrap_err();
+
+        assert!(error(json!({ "name": "github" })).contains("Unrecognized MCP config"));
+        assert!(error(json!(["npx"])).contains("expected a JSON object"));
+        assert!(error(json!({ "mcpServers": { "a": { "command": "x" }, "b": { "command": "y" } } }))
+            .contains("has 2 servers"));
+        assert!(error(json!({ "type": "websocket", "url": "wss://x" })).contains("unknown transport"));
+        assert!(error(json!({ "type": "stdio", "url": "https://x" })).contains("'command' is required"));
+        assert!(error(json!({ "command": "npx", "args": "-y" })).contains("'args' must be an array"));
+        assert!(error(json!({ "command": "npx", "env": { "A": { "b": 1 } } })).contains("env['A']"));
+        assert!(error(json!({ "url": "ftp://mcp.example.com" })).contains("http:// or https://"));
+    }
+
+    #[test]
+    fn test_export_import_export_is_byte_identical() {
+        // Written out of order, with the transport left implicit and env keys unsorted
+        let project = r#"{
+            "mcpServers": {
+                "search": { "url": "https://mcp.example.com/sse" },
+                "github": {
+                    "env": { "GITHUB_TOKEN": "ghp_example", "GITHUB_API": "https://api.github.com" },
+                    "args": ["-y", "@modelcontextprotocol/server-github"],
+                    "command": "npx"
+                }
+            }
+        }"#;
+        let mut servers = parse_export(project, Some("project")).unwrap();
+        servers.extend(
+            parse_export(r#"{ "docs": { "type": "http", "url": "https://mcp.example.com/mcp" } }"#, Some("user")).unwrap(),
+        );
+
+        let exported = render_scoped_export(&servers).unwrap();
+        let reexported = render_scoped_export(&parse_export(&exported, None).unwrap()).unwrap();
+        assert_eq!(reexported, exported);
+        assert!(exported.ends_with("}\n"));
+        let api = exported.find("GITHUB_API").unwrap();
+        assert!(api < exported.find("GITHUB_TOKEN").unwrap());
+        assert!(exported.find("\"args\"").unwrap() < exported.find("\"command\"").unwrap());
+        assert!(exported.find("\"project\"").unwrap() < exported.find("\"user\"").unwrap());
+
+        // Each per-scope file round-trips on its own as well
+        let files = render_export_by_scope(&servers).unwrap();
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["project", "user"]);
+        for (scope, file) in &files {
+            let parsed = parse_export(file, Some(scope)).unwrap();
+            assert_eq!(parsed[scope], servers[scope]);
+            assert_eq!(&render_export_by_scope(&parsed).unwrap()[scope], file);
+        }
+
+        assert!(parse_export(&files["user"], None).unwrap_err().contains("choose the scope"));
+        assert!(parse_export(r#"{ "team": { "mcpServers": {} } }"#, None).unwrap_err().contains("unknown scope"));
+    }
+
+    #[test]
+    fn test_configured_servers_round_trip_without_leaking_secrets() {
+        let project_dir = Path::new("/work/app");
+        let claude_json = json!({
+            "numStartups": 12,
+            "mcpServers": {
+                "docs": { "type": "http", "url": "https://mcp.example.com/mcp" }
+            },
+            "projects": {
+                "/work/other": { "mcpServers": { "stray": { "command": "stray" } } },
+                "/work/app/": {
+                    "mcpServers": {
+                        "github": {
+                            "type": "stdio",
+                            "command": "npx",
+                            "args": ["-y", "@modelcontextprotocol/server-github"],
+                            "env": { "GITHUB_TOKEN": "ghp_secret", "LOG_LEVEL": "debug" }
+                        }
+                    }
+                }
+            }
+        });
+        let mcp_json = json!({
+            "mcpServers": {
+                "db": { "command": "db-mcp", "args": ["--readonly"], "env": { "DB_URL": "${DATABASE_URL}" } }
+            }
+        });
+        let configured = configured_servers(Some(&claude_json), project_dir, Some(&mcp_json)).unwrap();
+        assert_eq!(configured.keys().collect::<Vec<_>>(), ["local", "project", "user"]);
+        let github = &configured["local"]["github"];
+        assert_eq!(github.transport.as_deref(), Some("stdio"));
+        assert_eq!(github.command.as_deref(), Some("npx"));
+        assert_eq!(github.args, ["-y", "@modelcontextprotocol/server-github"]);
+        assert_eq!(configured["user"]["docs"].transport.as_deref(), Some("http"));
+
+        let mut redacted = configured.clone();
+        redact_env(&mut redacted);
+        let exported = render_scoped_export(&redacted).unwrap();
+        assert!(!exported.contains("ghp_secret"));
+        assert!(exported.contains("${GITHUB_TOKEN}"));
+        assert!(exported.contains("\"LOG_LEVEL\": \"debug\""));
+        assert!(exported.contains("${DATABASE_URL}"));
+
+        let mut imported = parse_export(&exported, None).unwrap();
+        restore_env(&mut imported, &configured, |_| None);
+        assert_eq!(imported, configured);
+
+        // On a machine without the server, local values come from the environment
+        let mut fresh = parse_export(&exported, None).unwrap();
+        restore_env(&mut fresh, &ScopedMcpServers::new(), |name| Some(format!("env:{}", name)));
+        assert_eq!(fresh["local"]["github"].env["GITHUB_TOKEN"], "env:GITHUB_TOKEN");
+        assert_eq!(fresh["project"]["db"].env["DB_URL"], "${DATABASE_URL}");
+    }
+}
@@ -182,7 +182,7 @@ pub async fn auto_install_mcp(detected_packages: Vec<String>) -> Result<Vec<McpI
 
 // Helper functions
 
-fn get_known_mcp_servers() -> Vec<McpServerInfo> {
+pub(crate) fn get_known_mcp_servers() -> Vec<McpServerInfo> {
     vec![
         McpServerInfo {
             name: "playwright".to_string(),
@@ -0,0 +1,427 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+
+use super::agents::AgentDb;
+use super::settings_diff::{ResetFuture, Setting};
+
+pub(crate) const KEEP_RUNNING_SETTINGS_KEY: &str = "mcp_keep_running_servers";
+
+pub(crate) const SETTINGS: &[Setting] =
+    &[Setting::typed::<Vec<String>>(KEEP_RUNNING_SETTINGS_KEY).on_reset(stop_kept_servers)];
+
+/// No server is kept running by default, so stop the ones being supervised
+fn stop_kept_servers(app: AppHandle) -> ResetFuture {
+    let stopped = app.state::<McpSupervisor>().stop_all();
+    if !stopped.is_empty() {
+        log::info!("Stopped keeping MCP servers running: {}", stopped.join(", "));
+    }
+    Box::pin(std::future::ready(Ok(())))
+}
+
+/// Delay before the first re-check after a failed one; doubled on each further failure
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest delay between re-checks
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Failed re-checks allowed in a row before the supervisor gives up on a server
+const DEFAULT_MAX_FAILED_CHECKS: u32 = 5;
+/// Time between checks of a healthy server
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SupervisedState {
+    /// Connected on its last check
+    Healthy,
+    /// Failed its last check; waiting out the backoff before the next one
+    Unhealthy,
+    /// No longer checked, on request
+    Stopped,
+    /// Failed more checks in a row than allowed; no longer checked
+    Failed,
+}
+
+/// What the supervisor knows about one server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SupervisionStatus {
+    pub state: SupervisedState,
+    /// Re-checks after failures since supervision began
+    pub rechecks: u32,
+    /// Failed checks since the last one that connected
+    pub failed_checks: u32,
+    /// When a check last found the server connected
+    pub last_connected: Option<DateTime<Utc>>,
+    /// Why the last failed check failed
+    pub last_error: Option<String>,
+    /// When an unhealthy server is checked again
+    pub next_check: Option<DateTime<Utc>>,
+}
+
+type EventSink = Arc<dyn Fn(&str, Value) + Send + Sync>;
+
+struct SupervisedServer {
+    status: SupervisionStatus,
+    stop: Arc<Notify>,
+    /// Distinguishes this supervision from an earlier one of the same server
+    generation: u64,
+}
+
+/// Health-checks MCP servers and re-checks failing ones; cloning shares the server table
+///
+/// Claude starts MCP servers itself, so servers are neither spawned nor restarted
+/// here; their configured entries are checked through `claude mcp get`, which
+/// launches the server and reports whether it connected. A failed check emits
+/// `mcp-server-unhealthy` and is retried after an exponential backoff until the
+/// server connects again, emitting `mcp-server-recovered`, or fails too many
+/// checks in a row.
+#[derive(Clone)]
+pub struct McpSupervisor {
+    servers: Arc<Mutex<BTreeMap<String, SupervisedServer>>>,
+    next_generation: Arc<Mutex<u64>>,
+    events: EventSink,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_failed_checks: u32,
+    check_interval: Duration,
+}
+
+impl McpSupervisor {
+    pub fn new(events: impl Fn(&str, Value) + Send + Sync + 'static) -> Self {
+        Self::with_limits(events, DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_BACKOFF, DEFAULT_MAX_FAILED_CHECKS, DEFAULT_CHECK_INTERVAL)
+    }
+
+    pub fn with_limits(
+        events: impl Fn(&str, Value) + Send + Sync + 'static,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+        max_failed_checks: u32,
+        check_interval: Duration,
+    ) -> Self {
+        Self {
+            servers: Arc::new(Mutex::new(BTreeMap::new())),
+            next_generation: Arc::new(Mutex::new(0)),
+            events: Arc::new(events),
+            initial_backoff,
+            max_backoff,
+            max_failed_checks,
+            check_interval,
+        }
+    }
+
+    /// Supervision status of every server, by name
+    pub fn statuses(&self) -> BTreeMap<String, SupervisionStatus> {
+        match self.servers.lock() {
+            Ok(servers) => servers.iter().map(|(name, server)| (name.clone(), server.status.clone())).collect(),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+
+    pub fn status(&self, name: &str) -> Option<SupervisionStatus> {
+        self.servers.lock().ok()?.get(name).map(|server| server.status.clone())
+    }
+
+    /// Delay before re-checking after `failed_checks` failures in a row
+    fn backoff(&self, failed_checks: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failed_checks.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Apply `update` to a server's status unless a newer supervision replaced it
+    fn update(&self, name: &str, generation: u64, update: impl FnOnce(&mut SupervisionStatus)) {
+        if let Ok(mut servers) = self.servers.lock() {
+            if let Some(server) = servers.get_mut(name).filter(|server| server.generation == generation) {
+                update(&mut server.status);
+            }
+        }
+    }
+
+    /// Stop supervising `name`; false when it wasn't supervised
+    pub fn stop(&self, name: &str) -> bool {
+        let Ok(mut servers) = self.servers.lock() else {
+            return false;
+        };
+        match servers.get_mut(name) {
+            Some(server) if server.status.state != SupervisedState::Stopped => {
+                server.stop.notify_one();
+                server.status.state = SupervisedState::Stopped;
+                server.status.next_check = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Stop supervising every server, returning the names of those that were supervised
+    pub fn stop_all(&self) -> Vec<String> {
+        let names: Vec<String> = match self.servers.lock() {
+            Ok(servers) => servers.keys().cloned().collect(),
+            Err(_) => return Vec::new(),
+        };
+        names.into_iter().filter(|name| self.stop(name)).collect()
+    }
+
+    /// Keep checking the server with `check` until stopped
+    ///
+    /// Replaces any earlier supervision of the same server. `check` resolves
+    /// to an error describing why the server could not be reached.
+    pub fn supervise<F, Fut>(&self, name: &str, check: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send,
+    {
+        self.stop(name);
+        let generation = match self.next_generation.lock() {
+            Ok(mut next) => {
+                *next += 1;
+                *next
+            }
+            Err(_) => return,
+        };
+        let stop = Arc::new(Notify::new());
+        if let Ok(mut servers) = self.servers.lock() {
+            servers.insert(
+                name.to_string(),
+                SupervisedServer {
+                    status: SupervisionStatus {
+                        state: SupervisedState::Healthy,
+                        rechecks: 0,
+                        failed_checks: 0,
+                        last_connected: None,
+                        last_error: None,
+                        next_check: None,
+                    },
+                    stop: stop.clone(),
+                    generation,
+                },
+            );
+        }
+
+        let supervisor = self.clone();
+        let name = name.to_string();
+        tauri::async_runtime::spawn(async move {
+            supervisor.run(&name, generation, check, stop).await;
+        });
+    }
+
+    async fn run<F, Fut>(&self, name: &str, generation: u64, check: F, stop: Arc<Notify>)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<(), String>>,
+    {
+        let mut rechecks = 0;
+        let mut failed_checks = 0;
+        loop {
+            let result = tokio::select! {
+                result = check() => result,
+                _ = stop.notified() => break,
+            };
+
+            let delay = match result {
+                Ok(()) => {
+                    if failed_checks > 0 {
+                        log::info!("MCP server {} is healthy again (re-check {})", name, rechecks);
+                        (self.events)("mcp-server-recovered", json!({ "name": name, "rechecks": rechecks }));
+                    }
+                    failed_checks = 0;
+                    self.update(name, generation, |status| {
+                        status.state = SupervisedState::Healthy;
+                        status.failed_checks = 0;
+                        status.last_connected = Some(Utc::now());
+                        status.next_check = None;
+                    });
+                    self.check_interval
+                }
+                Err(error) => {
+                    failed_checks += 1;
+                    let gave_up = failed_checks > self.max_failed_checks;
+                    let delay = self.backoff(failed_checks);
+                    log::warn!("MCP server {} failed its health check: {} ({} in a row)", name, error, failed_checks);
+                    (self.events)(
+                        "mcp-server-unhealthy",
+                        json!({
+                            "name": name,
+                            "error": error,
+                            "rechecks": rechecks,
+                            "failed_checks": failed_checks,
+                            "will_recheck": !gave_up,
+                        }),
+                    );
+                    self.update(name, generation, |status| {
+                        status.failed_checks = failed_checks;
+                        status.last_error = Some(error);
+                        if gave_up {
+                            status.state = SupervisedState::Failed;
+                        } else {
+                            status.state = SupervisedState::Unhealthy;
+                            status.next_check = Some(Utc::now() + delay);
+                        }
+                    });
+                    if gave_up {
+                        log::error!("MCP server {} keeps failing its health check; giving up after {} re-check(s)", name, rechecks);
+                        return;
+                    }
+                    delay
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = stop.notified() => break,
+            }
+            if failed_checks > 0 {
+                rechecks += 1;
+                self.update(name, generation, |status| status.rechecks = rechecks);
+            }
+        }
+        log::info!("Stopped supervising MCP server {}", name);
+    }
+}
+
+/// Names of the servers marked "keep running"
+pub fn load_keep_running(conn: &Connection) -> Vec<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![KEEP_RUNNING_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_keep_running(conn: &Connection, names: &[String]) -> Result<(), String> {
+    let value = serde_json::to_string(names).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![KEEP_RUNNING_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save keep-running MCP servers: {}", e))?;
+    Ok(())
+}
+
+/// Start health-checking a server configured in Claude
+async fn supervise_configured(app: &AppHandle, supervisor: &McpSupervisor, name: &str) -> Result<(), String> {
+    // Fails for servers Claude doesn't know about
+    super::mcp::mcp_get(app.clone(), name.to_string()).await?;
+    let (app, server) = (app.clone(), name.to_string());
+    supervisor.supervise(name, move || super::mcp::mcp_check_health(app.clone(), server.clone()));
+    Ok(())
+}
+
+/// Start health-checking every server marked "keep running"; called once at startup
+pub async fn start_keep_running_servers(app: AppHandle) {
+    let names = load_keep_running(&app.state::<AgentDb>().lock_conn());
+    let supervisor = app.state::<McpSupervisor>();
+    for name in names {
+        if let Err(e) = supervise_configured(&app, &supervisor, &name).await {
+            log::warn!("Failed to start health-checking MCP server {}: {}", name, e);
+        }
+    }
+}
+
+/// Mark an MCP server "keep running", watching its health under supervision, or stop keeping it running
+#[tauri::command]
+pub async fn mcp_set_keep_running(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    supervisor: State<'_, McpSupervisor>,
+    name: String,
+    keep_running: bool,
+) -> Result<(), String> {
+    if keep_running {
+        supervise_configured(&app, &supervisor, &name).await?;
+    } else {
+        supervisor.stop(&name);
+    }
+
+    let conn = db.lock_conn();
+    let mut names = load_keep_running(&conn);
+    names.retain(|kept| kept != &name);
+    if keep_running {
+        names.push(name);
+    }
+    save_keep_running(&conn, &names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failing_server_is_rechecked_up_to_the_cap() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let supervisor = McpSupervisor::with_limits(
+            move |event, payload| sink.lock().unwrap().push((event.to_string(), payload)),
+            Duration::from_millis(20),
+            Duration::from_millis(50),
+            2,
+            Duration::from_secs(60),
+        );
+        supervisor.supervise("mock", || async { Err("Failed to connect".to_string()) });
+
+        let mut status = supervisor.status("mock").unwrap();
+        for _ in 0..200 {
+            if status.state == SupervisedState::Failed {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            status = supervisor.status("mock").unwrap();
+        }
+        assert_eq!(status.state, SupervisedState::Failed);
+        assert_eq!((status.rechecks, status.failed_checks), (2, 3));
+        assert_eq!(status.last_error.as_deref(), Some("Failed to connect"));
+        assert_eq!(status.last_connected, None);
+
+        let events = events.lock().unwrap();
+        let names: Vec<&str> = events.iter().map(|(event, _)| event.as_str()).collect();
+        assert_eq!(names, ["mcp-server-unhealthy", "mcp-server-unhealthy", "mcp-server-unhealthy"]);
+        assert_eq!(events[2].1["rechecks"], 2);
+        assert_eq!(events[2].1["will_recheck"], false);
+    }
+
+    #[tokio::test]
+    async fn test_recovered_server_is_reported_and_stopped_on_request() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let supervisor = McpSupervisor::with_limits(
+            move |event, _| sink.lock().unwrap().push(event.to_string()),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+            5,
+            Duration::from_secs(60),
+        );
+        let checks = Arc::new(Mutex::new(0));
+        let counter = checks.clone();
+        supervisor.supervise("mock", move || {
+            let counter = counter.clone();
+            async move {
+                let mut checks = counter.lock().unwrap();
+                *checks += 1;
+                if *checks == 1 { Err("Failed to connect".to_string()) } else { Ok(()) }
+            }
+        });
+        for _ in 0..100 {
+            if supervisor.status("mock").unwrap().last_connected.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let status = supervisor.status("mock").unwrap();
+        assert_eq!((status.state, status.rechecks, status.failed_checks), (SupervisedState::Healthy, 1, 0));
+        assert_eq!(*events.lock().unwrap(), ["mcp-server-unhealthy", "mcp-server-recovered"]);
+
+        assert_eq!(supervisor.stop_all(), ["mock"]);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(supervisor.status("mock").unwrap().state, SupervisedState::Stopped);
+        assert_eq!(*checks.lock().unwrap(), 2);
+        assert!(!supervisor.stop("mock"));
+    }
+}
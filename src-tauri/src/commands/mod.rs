@@ -1,4 +1,5 @@
 pub mod agents;
+pub mod agent_recovery;
 // Temporarily disabled for compilation
 // pub mod model_knowledge_base;
 // pub mod execution_service;
@@ -11,6 +12,7 @@ pub mod ai_session_integrator;
 // pub mod auto_model_selection;
 pub mod claude;
 pub mod claude_sync;
+pub mod claude_dir;
 pub mod dashboard;
 pub mod dashboard_seed;
 pub mod dashboard_utils;
@@ -28,6 +30,8 @@ pub mod gemini_request_validator;
 pub mod gemini_config_manager;
 pub mod gemini_observability;
 pub mod gemini_universal;
+pub mod gemini_structured;
+pub mod gemini_history;
 pub mod gemini_test_suite;
 // Temporarily disabled for compilation
 // pub mod health_analyzer;
@@ -38,21 +42,40 @@ pub mod gemini_test_suite;
 // pub mod workflow_visualizer;
 // pub mod realtime_collector;
 pub mod mcp;
+pub mod mcp_config;
+pub mod mcp_supervisor;
+pub mod operation_result;
 pub mod usage;
 pub mod storage;
 pub mod session_manager;
+pub mod session_compaction;
+pub mod session_event_log;
 pub mod slash_commands;
 pub mod proxy;
+pub mod provider_timeouts;
+pub mod request_hooks;
+pub mod prompt_secrets;
+pub mod settings_diff;
+pub mod provider_warmup;
+pub mod provider_quota;
 pub mod intelligent_routing;
+pub mod routing_decisions;
+pub mod project_permissions;
+pub mod project_env;
 pub mod mcp_manager;
 pub mod image_handler;
+pub mod model_catalog;
 pub mod ollama;
 pub mod session_deduplication;
+pub mod session_lifecycle;
+pub mod run_notifications;
+pub mod usage_reconciliation;
 pub mod universal_tool_executor;
 // pub mod universal_model_executor; // Temporarily disabled due to conflicts
 pub mod simple_model_validator;
 pub mod error_tracker;
 pub mod debug_system;
+pub mod diagnostics;
 pub mod universal_mcp;
 pub mod execution_control;
 pub mod app_info;
@@ -61,6 +84,10 @@ pub mod model_health_manager;
 pub mod comprehensive_model_validator;
 pub mod cross_model_memory;
 pub mod context_transfer;
+pub mod context_guard;
+pub mod file_attachments;
+pub mod background_tasks;
+pub mod workspace_backup;
 pub mod error_detection_system;
 pub mod model_disability_manager;
 pub mod intelligence_bridge;
@@ -0,0 +1,232 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::gemini_models::{ModelMetadata, MODEL_REGISTRY};
+use super::intelligent_routing::{load_model_benchmarks, AiModelBenchmark, ModelAvailability};
+use super::ollama::OllamaModel;
+use crate::models::ModelProvider;
+
+/// How long a listing is served from the cache; every model picker asks for it and
+/// listing Ollama models means a request to the daemon
+const MODEL_LIST_TTL: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+    static ref MODEL_LIST_CACHE: Mutex<Option<(Instant, Vec<ModelInfo>)>> = Mutex::new(None);
+}
+
+/// A model in the unified list, whichever provider serves it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+    pub provider: ModelProvider,
+    /// Maximum input tokens, when known
+    pub context_window: Option<u32>,
+    /// Any of `tools`, `vision`, `audio` and `json_mode`
+    pub capabilities: Vec<String>,
+    /// USD per 1k tokens; zero for local models
+    pub cost_per_1k_tokens: Option<f64>,
+    /// False while the provider is rate limited or the model's circuit breaker is open
+    pub available: bool,
+}
+
+fn benchmark_for<'a>(benchmarks: &'a [AiModelBenchmark], id: &str) -> Option<&'a AiModelBenchmark> {
+    benchmarks.iter().find(|b| crate::models::same_model(&b.model_id, id))
+}
+
+fn benchmark_capabilities(benchmark: &AiModelBenchmark) -> Vec<String> {
+    [
+        (benchmark.supports_tools, "tools"),
+        (benchmark.supports_vision, "vision"),
+        (benchmark.supports_audio, "audio"),
+    ]
+    .into_iter()
+    .filter(|(supported, _)| *supported)
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+fn benchmarked_model(id: &str, name: &str, provider: ModelProvider, benchmarks: &[AiModelBenchmark]) -> ModelInfo {
+    let benchmark = benchmark_for(benchmarks, id);
+    ModelInfo {
+        id: id.to_string(),
+        name: name.to_string(),
+        provider,
+        context_window: benchmark.map(|b| b.context_window),
+        capabilities: benchmark.map(benchmark_capabilities).unwrap_or_default(),
+        cost_per_1k_tokens: benchmark.map(|b| b.cost_per_1k_tokens),
+        available: true,
+    }
+}
+
+/// Claude models from the canonical registry, without the `auto` pseudo-model
+pub fn claude_models(benchmarks: &[AiModelBenchmark]) -> Vec<ModelInfo> {
+    crate::models::known_models(ModelProvider::Claude)
+        .filter(|model| model.id != "auto")
+        .map(|model| benchmarked_model(model.id, model.cli_name.unwrap_or(model.id), ModelProvider::Claude, benchmarks))
+        .collect()
+}
+
+/// Gemini models from the registry's metadata
+///
+/// Unbenchmarked models are priced at the mean of their input and output prices.
+pub fn gemini_models(models: &[ModelMetadata], benchmarks: &[AiModelBenchmark]) -> Vec<ModelInfo> {
+    models
+        .iter()
+        .map(|model| {
+            let mut info = benchmarked_model(&model.id, &model.name, ModelProvider::Gemini, benchmarks);
+            let capabilities = &model.capabilities;
+            info.context_window = Some(capabilities.max_input_tokens);
+            for (supported, name) in [
+                (capabilities.function_calling, "tools"),
+                (capabilities.image_understanding, "vision"),
+                (capabilities.json_mode, "json_mode"),
+            ] {
+                if supported && !info.capabilities.iter().any(|c| c == name) {
+                    info.capabilities.push(name.to_string());
+                }
+            }
+            if info.cost_per_1k_tokens.is_none() {
+                let pricing = &model.pricing;
+                info.cost_per_1k_tokens = Some((pricing.input_per_million + pricing.output_per_million) / 2.0 / 1000.0);
+            }
+            info
+        })
+        .collect()
+}
+
+/// Models installed in the local Ollama daemon
+pub fn ollama_models(models: &[OllamaModel], benchmarks: &[AiModelBenchmark]) -> Vec<ModelInfo> {
+    models
+        .iter()
+        .map(|model| {
+            let mut info = benchmarked_model(&model.name, &model.name, ModelProvider::Ollama, benchmarks);
+            info.cost_per_1k_tokens = Some(0.0);
+            info
+        })
+        .collect()
+}
+
+/// Merge per-provider listings, dropping providers that aren't configured
+///
+/// Models stay listed while their provider is throttled or their circuit is
+/// open, with `available` cleared, so pickers can show why they can't be used.
+pub fn unify_model_lists(
+    listings: Vec<(ModelProvider, Vec<ModelInfo>)>,
+    availability: &ModelAvailability,
+) -> Vec<ModelInfo> {
+    let mut models: Vec<ModelInfo> = listings
+        .into_iter()
+        .filter(|(provider, _)| availability.providers.is_available(provider.as_str()))
+        .flat_map(|(_, models)| models)
+        .map(|mut model| {
+            model.available = availability.allows(&model.id, model.provider.as_str());
+            model
+        })
+        .collect();
+    models.sort_by(|a, b| (a.provider.as_str(), &a.id).cmp(&(b.provider.as_str(), &b.id)));
+    models.dedup_by(|a, b| a.provider == b.provider && a.id == b.id);
+    models
+}
+
+async fn collect_models(app: &AppHandle) -> Vec<ModelInfo> {
+    let availability = ModelAvailability::detect(app).await;
+    let db = app.state::<AgentDb>();
+    let benchmarks = load_model_benchmarks(&db.lock_conn()).unwrap_or_else(|e| {
+        log::warn!("Listing models without benchmark data: {}", e);
+        Vec::new()
+    });
+
+    let mut listings = Vec::new();
+    for provider in ModelProvider::ALL {
+        if !availability.providers.is_available(provider.as_str()) {
+            continue;
+        }
+        let models = match provider {
+            ModelProvider::Claude => claude_models(&benchmarks),
+            ModelProvider::Gemini => gemini_models(&MODEL_REGISTRY.list_models(), &benchmarks),
+            ModelProvider::Ollama => match super::ollama::get_ollama_models().await {
+                Ok(installed) => ollama_models(&installed, &benchmarks),
+                Err(e) => {
+                    log::warn!("Skipping Ollama models: {}", e);
+                    continue;
+                }
+            },
+        };
+        listings.push((provider, models));
+    }
+    unify_model_lists(listings, &availability)
+}
+
+/// List the models of every configured provider in one uniform shape
+///
+/// Context windows, prices and capabilities missing from a provider's own listing
+/// are filled in from the model benchmarks.
+#[tauri::command]
+pub async fn list_all_available_models(app: AppHandle) -> Result<Vec<ModelInfo>, String> {
+    if let Some((listed_at, models)) = MODEL_LIST_CACHE.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        if listed_at.elapsed() < MODEL_LIST_TTL {
+            return Ok(models.clone());
+        }
+    }
+    let models = collect_models(&app).await;
+    *MODEL_LIST_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), models.clone()));
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::simple_model_validator::summarize_providers;
+    use rusqlite::Connection;
+
+    fn installed(name: &str) -> OllamaModel {
+        OllamaModel {
+            name: name.to_string(),
+            modified_at: "2025-08-01T10:00:00Z".to_string(),
+            size: 4_000_000_000,
+            digest: "sha256:abc".to_string(),
+            details: None,
+        }
+    }
+
+    #[test]
+    fn test_models_of_two_providers_are_tagged_in_one_list() {
+        let benchmarks = load_model_benchmarks(&Connection::open_in_memory().unwrap()).unwrap();
+        let gemini: Vec<ModelMetadata> =
+            MODEL_REGISTRY.list_models().into_iter().filter(|m| m.id == "gemini-2.5-flash").collect();
+        let availability = ModelAvailability {
+            providers: summarize_providers(false, true, true),
+            open_circuits: Vec::new(),
+            throttled_providers: vec!["ollama".to_string()],
+        };
+
+        let models = unify_model_lists(
+            vec![
+                (ModelProvider::Claude, claude_models(&benchmarks)),
+                (ModelProvider::Gemini, gemini_models(&gemini, &benchmarks)),
+                (ModelProvider::Ollama, ollama_models(&[installed("llama3.3:latest"), installed("my-coder:7b")], &benchmarks)),
+            ],
+            &availability,
+        );
+
+        let tags: Vec<(&str, ModelProvider)> = models.iter().map(|m| (m.id.as_str(), m.provider)).collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("gemini-2.5-flash", ModelProvider::Gemini),
+                ("llama3.3:latest", ModelProvider::Ollama),
+                ("my-coder:7b", ModelProvider::Ollama),
+            ]
+        );
+        let flash = &models[0];
+        assert!(flash.available);
+        assert!(flash.context_window.is_some_and(|window| window > 0));
+        assert!(flash.capabilities.contains(&"json_mode".to_string()));
+        assert!(models[1..].iter().all(|m| !m.available && m.cost_per_1k_tokens == Some(0.0)));
+        assert_eq!(models[2].context_window, None);
+    }
+}
@@ -380,7 +380,7 @@ async fn store_availability_report(
     report: &ModelAvailabilityReport,
     db: &State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     // Create table if it doesn't exist
     conn.execute(
@@ -471,7 +471,7 @@ pub async fn disable_model_manually(
     };
     
     // Store in database
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     conn.execute(
         "CREATE TABLE IF NOT EXISTS disabled_models (
@@ -501,7 +501,7 @@ pub async fn enable_model(
 ) -> Result<(), String> {
     info!("Enabling previously disabled model: {}", model_id);
     
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     conn.execute(
         "DELETE FROM disabled_models WHERE model_id = ?1",
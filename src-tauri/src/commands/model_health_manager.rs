@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, command};
+use tauri::{AppHandle, Emitter, command};
 use chrono::{DateTime, Utc, Duration};
 use log::{info, warn, error};
 
@@ -228,6 +228,32 @@ impl ModelHealthManager {
         *last_check = Some(Utc::now());
     }
 
+    /// Forget recent failures of a model so it is tried again, returning whether it was tracked
+    pub fn clear_failures(&self, model_id: &str) -> bool {
+        let mut cache = self.health_cache.lock().unwrap();
+        match cache.get_mut(model_id) {
+            Some(health) => {
+                Self::clear_health_failures(health);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget recent failures of every model
+    pub fn clear_all_failures(&self) {
+        let mut cache = self.health_cache.lock().unwrap();
+        cache.values_mut().for_each(Self::clear_health_failures);
+    }
+
+    fn clear_health_failures(health: &mut ModelHealth) {
+        health.consecutive_failures = 0;
+        health.error_messages.clear();
+        if health.status == ModelStatus::Unavailable {
+            health.status = ModelStatus::Unknown;
+        }
+    }
+
     /// Get all model health statuses
     pub fn get_all_health_status(&self) -> HashMap<String, ModelHealth> {
         let cache = self.health_cache.lock().unwrap();
@@ -383,6 +409,56 @@ pub async fn get_all_model_health(
     Ok(manager.get_all_health_status())
 }
 
+/// Payload of the `model-health-reset` event, also returned by the reset commands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelHealthReset {
+    /// Model that was reset, or `None` when every model was
+    pub model_id: Option<String>,
+    /// Models whose circuit breaker was closed
+    pub circuits_closed: Vec<String>,
+}
+
+fn emit_health_reset(app: &AppHandle, reset: &ModelHealthReset) {
+    if let Err(e) = app.emit("model-health-reset", reset) {
+        warn!("Failed to emit model-health-reset: {}", e);
+    }
+}
+
+/// Tauri command to close a model's circuit breaker and clear its recent failures
+#[command]
+pub async fn reset_model_health(
+    model_id: String,
+    app: AppHandle,
+    manager: tauri::State<'_, ModelHealthManager>,
+) -> Result<ModelHealthReset, String> {
+    let circuits_closed = super::gemini_backend::reset_gemini_circuit(&model_id);
+    let tracked = manager.clear_failures(&model_id);
+    if circuits_closed.is_empty() && !tracked {
+        info!("No health state recorded for {}, nothing to reset", model_id);
+    } else {
+        info!("Reset health state of {}", model_id);
+    }
+
+    let reset = ModelHealthReset { model_id: Some(model_id), circuits_closed };
+    emit_health_reset(&app, &reset);
+    Ok(reset)
+}
+
+/// Tauri command to close every circuit breaker and clear all recent failures
+#[command]
+pub async fn reset_all_model_health(
+    app: AppHandle,
+    manager: tauri::State<'_, ModelHealthManager>,
+) -> Result<ModelHealthReset, String> {
+    let circuits_closed = super::gemini_backend::reset_all_gemini_circuits();
+    manager.clear_all_failures();
+    info!("Reset health state of all models");
+
+    let reset = ModelHealthReset { model_id: None, circuits_closed };
+    emit_health_reset(&app, &reset);
+    Ok(reset)
+}
+
 /// Tauri command to check if model is available
 #[command]
 pub async fn is_model_available(
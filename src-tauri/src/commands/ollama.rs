@@ -1,9 +1,28 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use tauri::{command, AppHandle, Emitter};
+use tauri::{command, AppHandle, Emitter, Manager};
 use log;
 
+use super::agents::AgentDb;
+use super::ai_usage_tracker::{track_turn_usage, AIUsageEvent};
+use super::session_event_log::emit_session_event;
+use super::provider_timeouts::{load_provider_timeouts, ProviderTimeout};
+use super::request_hooks::request_hooks;
+use super::execution_control::ExecutionControlState;
+
+/// Standardized prompt used by `benchmark_ollama_model` when none is given
+const OLLAMA_BENCHMARK_PROMPT: &str = "Write a Rust function that returns the nth Fibonacci number iteratively, then explain how it works in three sentences.";
+
+/// Token cap for benchmark generations so runs are comparable
+const OLLAMA_BENCHMARK_NUM_PREDICT: u32 = 256;
+
+/// How many times a pull is issued before an interrupted download is reported
+const OLLAMA_PULL_MAX_ATTEMPTS: u32 = 3;
+
+/// Pause before re-issuing an interrupted pull
+const OLLAMA_PULL_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OllamaModel {
     pub name: String,
@@ -52,6 +71,308 @@ pub struct OllamaGenerateResponse {
     pub eval_duration: Option<u64>,
 }
 
+/// The usage of a completed generation, from the counts on its final chunk
+fn ollama_turn_usage(response: &OllamaGenerateResponse, project_path: &str, session_id: &str) -> AIUsageEvent {
+    let input_tokens = i64::from(response.prompt_eval_count.unwrap_or(0));
+    let output_tokens = i64::from(response.eval_count.unwrap_or(0));
+    AIUsageEvent {
+        project_id: project_path.to_string(),
+        model_name: response.model.clone(),
+        agent_type: None,
+        mcp_server: None,
+        token_count: input_tokens + output_tokens,
+        request_type: "completion".to_string(),
+        response_time_ms: response.total_duration.map(|d| (d / 1_000_000) as i64),
+        success: true,
+        error_message: None,
+        session_id: Some(session_id.to_string()),
+        user_prompt_tokens: Some(input_tokens),
+        assistant_response_tokens: Some(output_tokens),
+        cached_tokens: None,
+        estimated: response.prompt_eval_count.is_none() || response.eval_count.is_none(),
+        timestamp: chrono::Utc::now().timestamp(),
+        tags: None,
+    }
+}
+
+/// Measured local throughput for an Ollama model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaBenchmarkResult {
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub eval_tokens: u32,
+    pub eval_tokens_per_second: f64,
+    pub prompt_tokens_per_second: f64,
+    pub load_time_ms: u64,
+    pub total_time_ms: u64,
+    pub saved_to_benchmarks: bool,
+}
+
+/// An installed Ollama model with when this app last ran it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModelUsage {
+    pub name: String,
+    pub size: i64,
+    pub digest: String,
+    pub modified_at: String,
+    /// `None` if the model has never been used through `execute_ollama_request`
+    pub last_used_at: Option<String>,
+}
+
+/// Result of `prune_ollama_models`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaPruneReport {
+    pub deleted: Vec<String>,
+    pub failed: Vec<String>,
+    pub reclaimed_bytes: i64,
+}
+
+/// One NDJSON line of the `/api/pull` stream
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaPullStatus {
+    #[serde(default)]
+    status: String,
+    digest: Option<String>,
+    total: Option<u64>,
+    completed: Option<u64>,
+    error: Option<String>,
+}
+
+/// Download state of one model layer during a pull
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OllamaPullLayer {
+    pub digest: String,
+    pub total_bytes: u64,
+    pub completed_bytes: u64,
+    /// Bytes already on disk when the latest attempt reached this layer
+    pub resumed_bytes: u64,
+    pub percent: f64,
+}
+
+/// Payload of the `ollama-pull-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaPullProgressEvent {
+    pub model: String,
+    pub status: String,
+    pub attempt: u32,
+    pub layer: Option<OllamaPullLayer>,
+}
+
+/// Outcome of `pull_ollama_model`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaPullSummary {
+    pub model: String,
+    pub attempts: u32,
+    pub layers: Vec<OllamaPullLayer>,
+    /// Bytes the final attempt found already on disk and did not fetch again
+    pub resumed_bytes: u64,
+    /// Bytes the final attempt fetched
+    pub downloaded_bytes: u64,
+}
+
+/// Per-layer progress across every attempt of one pull
+#[derive(Default)]
+struct OllamaPullTracker {
+    layers: Vec<OllamaPullLayer>,
+    seen_this_attempt: std::collections::HashSet<String>,
+}
+
+impl OllamaPullTracker {
+    fn begin_attempt(&mut self) {
+        self.seen_this_attempt.clear();
+    }
+
+    /// Record a progress line, returning the updated layer if it names one.
+    ///
+    /// Ollama keeps partial blobs and reports them as already completed the first
+    /// time an attempt reaches a layer, so those bytes are counted as resumed.
+    fn apply(&mut self, status: &OllamaPullStatus) -> Option<OllamaPullLayer> {
+        let digest = status.digest.as_ref()?;
+        let completed = status.completed.unwrap_or(0);
+
+        let index = match self.layers.iter().position(|l| &l.digest == digest) {
+            Some(index) => index,
+            None => {
+                self.layers.push(OllamaPullLayer {
+                    digest: digest.clone(),
+                    total_bytes: 0,
+                    completed_bytes: 0,
+                    resumed_bytes: 0,
+                    percent: 0.0,
+                });
+                self.layers.len() - 1
+            }
+        };
+
+        let layer = &mut self.layers[index];
+        if self.seen_this_attempt.insert(digest.clone()) {
+            layer.resumed_bytes = completed;
+        }
+        if let Some(total) = status.total {
+            layer.total_bytes = total;
+        }
+        layer.completed_bytes = completed;
+        // A discarded partial blob restarts from zero; it no longer counts as resumed
+        layer.resumed_bytes = layer.resumed_bytes.min(completed);
+        layer.percent = if layer.total_bytes > 0 {
+            (completed as f64 / layer.total_bytes as f64 * 100.0).min(100.0)
+        } else {
+            0.0
+        };
+        Some(layer.clone())
+    }
+
+    fn summary(&self, model: &str, attempts: u32) -> OllamaPullSummary {
+        let resumed_bytes = self.layers.iter().map(|l| l.resumed_bytes).sum();
+        let downloaded_bytes = self.layers.iter()
+            .map(|l| l.completed_bytes.saturating_sub(l.resumed_bytes))
+            .sum();
+        OllamaPullSummary {
+            model: model.to_string(),
+            attempts,
+            layers: self.layers.clone(),
+            resumed_bytes,
+            downloaded_bytes,
+        }
+    }
+}
+
+enum OllamaPullAttempt {
+    Completed,
+    Interrupted(String),
+}
+
+/// Issue one streaming pull, feeding progress into `tracker`
+async fn stream_pull_attempt(
+    client: &reqwest::Client,
+    base_url: &str,
+    model: &str,
+    attempt: u32,
+    tracker: &mut OllamaPullTracker,
+    emit: &mut impl FnMut(OllamaPullProgressEvent),
+) -> Result<OllamaPullAttempt, String> {
+    use futures_util::StreamExt;
+
+    let response = match client
+        .post(format!("{}/api/pull", base_url))
+        .json(&json!({ "name": model, "stream": true }))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return Ok(OllamaPullAttempt::Interrupted(format!("Failed to send pull request: {}", e))),
+    };
+
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to pull model {}: {}", model, error_text));
+    }
+
+    let mut stream = response.bytes_stream();
+    let mut buffer = String::new();
+    while let Some(chunk_result) = stream.next().await {
+        let chunk_bytes = match chunk_result {
+            Ok(chunk_bytes) => chunk_bytes,
+            Err(e) => return Ok(OllamaPullAttempt::Interrupted(format!("Pull stream interrupted: {}", e))),
+        };
+        buffer.push_str(&String::from_utf8_lossy(&chunk_bytes));
+
+        while let Some(newline_pos) = buffer.find('\n') {
+            let line = buffer[..newline_pos].trim().to_string();
+            buffer.drain(..=newline_pos);
+            if line.is_empty() {
+                continue;
+            }
+
+            let status = match serde_json::from_str::<OllamaPullStatus>(&line) {
+                Ok(status) => status,
+                Err(e) => {
+                    log::warn!("Failed to parse Ollama pull line '{}': {}", line, e);
+                    continue;
+                }
+            };
+            if let Some(error) = status.error {
+                return Err(format!("Failed to pull model {}: {}", model, error));
+            }
+
+            let layer = tracker.apply(&status);
+            let done = status.status == "success";
+            emit(OllamaPullProgressEvent {
+                model: model.to_string(),
+                status: status.status,
+                attempt,
+                layer,
+            });
+            if done {
+                return Ok(OllamaPullAttempt::Completed);
+            }
+        }
+    }
+
+    Ok(OllamaPullAttempt::Interrupted("Pull stream ended before completion".to_string()))
+}
+
+/// Pull `model`, re-issuing the request after interruptions so Ollama resumes partial layers
+async fn pull_with_resume(
+    client: &reqwest::Client,
+    base_url: &str,
+    model: &str,
+    max_attempts: u32,
+    retry_delay: std::time::Duration,
+    mut emit: impl FnMut(OllamaPullProgressEvent),
+) -> Result<OllamaPullSummary, String> {
+    let mut tracker = OllamaPullTracker::default();
+    for attempt in 1..=max_attempts {
+        tracker.begin_attempt();
+        match stream_pull_attempt(client, base_url, model, attempt, &mut tracker, &mut emit).await? {
+            OllamaPullAttempt::Completed => return Ok(tracker.summary(model, attempt)),
+            OllamaPullAttempt::Interrupted(reason) if attempt < max_attempts => {
+                log::warn!("Pull of {} interrupted (attempt {}): {}; resuming", model, attempt, reason);
+                tokio::time::sleep(retry_delay).await;
+            }
+            OllamaPullAttempt::Interrupted(reason) => {
+                return Err(format!("Failed to pull model {} after {} attempts: {}", model, attempt, reason));
+            }
+        }
+    }
+    Err(format!("Failed to pull model {}: no attempts made", model))
+}
+
+/// How long a preloaded model stays in memory without requests
+const OLLAMA_WARMUP_KEEP_ALIVE: &str = "30m";
+
+/// Load `model` into memory ahead of its first prompt
+///
+/// A generate request without a prompt only loads the model, and `keep_alive`
+/// keeps it loaded long enough for the user's first message.
+pub(crate) async fn preload_ollama_model(client: &reqwest::Client, base_url: &str, model: &str) -> Result<(), String> {
+    let response = client
+        .post(format!("{}/api/generate", base_url))
+        .json(&json!({ "model": model, "keep_alive": OLLAMA_WARMUP_KEEP_ALIVE, "stream": false }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to connect to Ollama: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Ollama failed to load {}: {} {}", model, status, error_text));
+    }
+    Ok(())
+}
+
+/// Preload `model` on the local Ollama server
+pub(crate) async fn warm_up_ollama_model(app: &AppHandle, model: &str) -> Result<(), String> {
+    // Loading a large model can take most of the request timeout
+    let client = ollama_timeouts(app).build_client(false)?;
+    preload_ollama_model(&client, "http://localhost:11434", model).await
+}
+
+/// Configured Ollama timeouts, or the defaults if settings can't be read
+fn ollama_timeouts(app: &AppHandle) -> ProviderTimeout {
+    load_provider_timeouts(&app.state::<AgentDb>().lock_conn()).ollama
+}
+
 /// Check if Ollama is running and accessible
 #[command]
 pub async fn check_ollama_status() -> Result<bool, String> {
@@ -103,6 +424,48 @@ pub async fn get_ollama_models() -> Result<Vec<OllamaModel>, String> {
     }
 }
 
+/// Send a single non-streaming prompt to Ollama and return the response text
+///
+/// Used for internal helper calls (e.g. summarization) that don't belong to a chat session.
+pub(crate) async fn generate_ollama_text(
+    app_handle: &AppHandle,
+    model: &str,
+    prompt: &str,
+) -> Result<String, String> {
+    let client = ollama_timeouts(app_handle).build_client(false)?;
+    let request_payload = OllamaGenerateRequest {
+        model: model.to_string(),
+        prompt: prompt.to_string(),
+        stream: false,
+        system: None,
+        context: None,
+        options: None,
+    };
+
+    let request_payload = serde_json::to_value(&request_payload)
+        .map_err(|e| format!("Failed to serialize Ollama request: {}", e))?;
+    let response = request_hooks(app_handle)
+        .prepare("ollama", client.post("http://localhost:11434/api/generate"), request_payload)?
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request to Ollama: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Ollama API returned error {}: {}", status, error_text));
+    }
+
+    let generated: OllamaGenerateResponse = response.json().await
+        .map_err(|e| format!("Failed to parse Ollama response: {}", e))?;
+
+    if let Err(e) = record_ollama_model_usage(&app_handle.state::<AgentDb>().lock_conn(), model, chrono::Utc::now()) {
+        log::warn!("Failed to record Ollama model usage: {}", e);
+    }
+
+    Ok(generated.response)
+}
+
 /// Execute Ollama model with streaming support
 #[command]
 pub async fn execute_ollama_request(
@@ -112,8 +475,14 @@ pub async fn execute_ollama_request(
     project_path: String,
     system_instruction: Option<String>,
     options: Option<HashMap<String, Value>>,
+    tags: Option<HashMap<String, String>>,
+    attachments: Option<Vec<String>>,
 ) -> Result<(), String> {
     log::info!("Starting Ollama execution - model: {}, project: {}", model, project_path);
+    let prompt = super::file_attachments::attach_files(&prompt, &project_path, attachments.as_deref().unwrap_or_default())?;
+    // The system instruction shares the context window with the prompt
+    let sent_text = format!("{}{}", system_instruction.as_deref().unwrap_or_default(), prompt);
+    super::context_guard::ensure_prompt_fits(&app_handle, &model, &sent_text)?;
 
     // Generate unique session ID for this request
     let session_id = format!(
@@ -124,6 +493,10 @@ pub async fn execute_ollama_request(
             .unwrap()
             .as_millis()
     );
+    if let Some(tags) = &tags {
+        super::ai_usage_tracker::tag_usage_session(&app_handle, &session_id, tags);
+    }
+    super::routing_decisions::note_session_model(&app_handle, &session_id, &model);
 
     // Emit init message
     let init_message = json!({
@@ -140,13 +513,11 @@ pub async fn execute_ollama_request(
     });
     
     // Emit session-specific events ONLY to prevent cross-contamination
-    app_handle.emit(&format!("claude-output:{}", session_id), serde_json::to_string(&init_message).unwrap())
+    emit_session_event(&app_handle, &format!("claude-output:{}", session_id), serde_json::to_string(&init_message).unwrap())
         .map_err(|e| format!("Failed to emit session-specific init event: {}", e))?;
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(300)) // 5 minute timeout
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    // Streaming: fail on a stalled stream rather than capping total generation time
+    let client = ollama_timeouts(&app_handle).build_client(true)?;
 
     let request_payload = OllamaGenerateRequest {
         model: model.clone(),
@@ -159,14 +530,44 @@ pub async fn execute_ollama_request(
 
     log::info!("Sending request to Ollama API for model: {}", model);
 
-    let response = client
-        .post("http://localhost:11434/api/generate")
-        .json(&request_payload)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request to Ollama: {}", e))?;
+    // Local runs cost nothing; only how long they took and whether they finished is recorded
+    let run_started = std::time::Instant::now();
+    let record_outcome = |success: bool| {
+        super::routing_decisions::record_session_outcome(
+            &app_handle,
+            &session_id,
+            success,
+            Some(0.0),
+            Some(run_started.elapsed().as_millis() as i64),
+        )
+    };
+
+    // Each wait on Ollama is abandoned as soon as the session is stopped
+    let execution = app_handle.state::<ExecutionControlState>().inner().clone();
+    let stopped = || {
+        log::info!("Ollama execution stopped for session: {}", session_id);
+        emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), false)
+            .map_err(|e| format!("Failed to emit stop complete event: {}", e))
+    };
+
+    let request_payload = serde_json::to_value(&request_payload)
+        .map_err(|e| format!("Failed to serialize Ollama request: {}", e))?;
+    let request = request_hooks(&app_handle)
+        .prepare("ollama", client.post("http://localhost:11434/api/generate"), request_payload)?
+        .send();
+    let Some(sent) = execution.run_unless_stopped(&session_id, request).await else {
+        return stopped();
+    };
+    let response = match sent {
+        Ok(response) => response,
+        Err(e) => {
+            record_outcome(false);
+            return Err(format!("Failed to send request to Ollama: {}", e));
+        }
+    };
 
     if !response.status().is_success() {
+        record_outcome(false);
         let status = response.status();
         let error_text = response.text().await.unwrap_or_default();
         return Err(format!("Ollama API returned error {}: {}", status, error_text));
@@ -174,6 +575,10 @@ pub async fn execute_ollama_request(
 
     log::info!("Ollama API response received, processing stream...");
 
+    if let Err(e) = record_ollama_model_usage(&app_handle.state::<AgentDb>().lock_conn(), &model, chrono::Utc::now()) {
+        log::warn!("Failed to record Ollama model usage: {}", e);
+    }
+
     let mut stream = response.bytes_stream();
     let mut buffer = String::new();
     let mut total_tokens = 0;
@@ -181,7 +586,13 @@ pub async fn execute_ollama_request(
 
     use futures_util::StreamExt;
 
-    while let Some(chunk_result) = stream.next().await {
+    loop {
+        let Some(next) = execution.run_unless_stopped(&session_id, stream.next()).await else {
+            return stopped();
+        };
+        let Some(chunk_result) = next else {
+            break;
+        };
         match chunk_result {
             Ok(chunk_bytes) => {
                 let chunk_str = String::from_utf8_lossy(&chunk_bytes);
@@ -239,14 +650,19 @@ pub async fn execute_ollama_request(
                             });
 
                             // Emit session-specific events ONLY to prevent cross-contamination
-                            app_handle.emit(&format!("claude-output:{}", session_id), serde_json::to_string(&message).unwrap())
+                            emit_session_event(&app_handle, &format!("claude-output:{}", session_id), serde_json::to_string(&message).unwrap())
                                 .map_err(|e| format!("Failed to emit session-specific message: {}", e))?;
 
                             if ollama_response.done {
                                 log::info!("Ollama execution completed successfully for session: {}", session_id);
+                                let mut usage = ollama_turn_usage(&ollama_response, &project_path, &session_id);
+                                if let Err(e) = track_turn_usage(&app_handle, &mut usage) {
+                                    log::warn!("Failed to record Ollama turn usage: {}", e);
+                                }
+                                record_outcome(true);
                                 
                                 // Emit session-specific completion event
-                                app_handle.emit(&format!("claude-complete:{}", session_id), true)
+                                emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), true)
                                     .map_err(|e| format!("Failed to emit session-specific completion event: {}", e))?;
                                 
                                 return Ok(());
@@ -262,6 +678,7 @@ pub async fn execute_ollama_request(
             Err(e) => {
                 let error_msg = format!("Stream error from Ollama: {}", e);
                 log::error!("{}", error_msg);
+                record_outcome(false);
                 
                 // Emit error message
                 let error_message = json!({
@@ -274,7 +691,7 @@ pub async fn execute_ollama_request(
                         .as_secs()
                 });
                 
-                app_handle.emit(&format!("claude-error:{}", session_id), serde_json::to_string(&error_message).unwrap())
+                emit_session_event(&app_handle, &format!("claude-error:{}", session_id), serde_json::to_string(&error_message).unwrap())
                     .map_err(|e| format!("Failed to emit session-specific error: {}", e))?;
                 
                 return Err(error_msg);
@@ -284,37 +701,43 @@ pub async fn execute_ollama_request(
 
     // If we reach here, the stream ended without a "done" response
     log::warn!("Ollama stream ended unexpectedly for session: {}", session_id);
+    record_outcome(false);
     
-    app_handle.emit(&format!("claude-complete:{}", session_id), true)
+    emit_session_event(&app_handle, &format!("claude-complete:{}", session_id), true)
         .map_err(|e| format!("Failed to emit session-specific completion event: {}", e))?;
     
     Ok(())
 }
 
 /// Pull/Download a new Ollama model
+///
+/// Emits `ollama-pull-progress` events with per-layer progress. Interrupted downloads
+/// are re-requested, letting Ollama resume partial layers instead of starting over.
 #[command]
-pub async fn pull_ollama_model(model: String) -> Result<String, String> {
+pub async fn pull_ollama_model(app_handle: AppHandle, model: String) -> Result<OllamaPullSummary, String> {
     log::info!("Pulling Ollama model: {}", model);
-    
-    let client = reqwest::Client::new();
-    let request_payload = json!({
-        "name": model,
-        "stream": false
-    });
-    
-    let response = client
-        .post("http://localhost:11434/api/pull")
-        .json(&request_payload)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send pull request: {}", e))?;
-        
-    if response.status().is_success() {
-        Ok(format!("Successfully initiated pull for model: {}", model))
-    } else {
-        let error_text = response.text().await.unwrap_or_default();
-        Err(format!("Failed to pull model {}: {}", model, error_text))
-    }
+
+    // Streaming: large layers can take far longer than any total request timeout
+    let client = ollama_timeouts(&app_handle).build_client(true)?;
+
+    let summary = pull_with_resume(
+        &client,
+        "http://localhost:11434",
+        &model,
+        OLLAMA_PULL_MAX_ATTEMPTS,
+        OLLAMA_PULL_RETRY_DELAY,
+        |progress| {
+            if let Err(e) = app_handle.emit("ollama-pull-progress", &progress) {
+                log::warn!("Failed to emit pull progress: {}", e);
+            }
+        },
+    ).await?;
+
+    log::info!(
+        "Pulled {} in {} attempt(s): {} bytes resumed, {} bytes downloaded",
+        model, summary.attempts, summary.resumed_bytes, summary.downloaded_bytes
+    );
+    Ok(summary)
 }
 
 /// Delete an Ollama model
@@ -369,6 +792,248 @@ pub async fn get_ollama_model_info(model: String) -> Result<Value, String> {
     }
 }
 
+fn init_ollama_usage_table(conn: &rusqlite::Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ollama_model_usage (
+            model TEXT PRIMARY KEY,
+            last_used_at TEXT NOT NULL
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create Ollama usage table: {}", e))?;
+    Ok(())
+}
+
+fn record_ollama_model_usage(
+    conn: &rusqlite::Connection,
+    model: &str,
+    used_at: chrono::DateTime<chrono::Utc>,
+) -> Result<(), String> {
+    init_ollama_usage_table(conn)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO ollama_model_usage (model, last_used_at) VALUES (?1, ?2)",
+        rusqlite::params![normalize_model_name(model), used_at.to_rfc3339()],
+    ).map_err(|e| format!("Failed to record Ollama model usage: {}", e))?;
+    Ok(())
+}
+
+/// Attach recorded last-used times to the installed models
+fn merge_model_usage(
+    conn: &rusqlite::Connection,
+    models: Vec<OllamaModel>,
+) -> Result<Vec<OllamaModelUsage>, String> {
+    init_ollama_usage_table(conn)?;
+    // Rows recorded before names were normalized may still carry the `:latest` tag
+    let mut stmt = conn.prepare(
+        "SELECT MAX(last_used_at) FROM ollama_model_usage
+         WHERE model = ?1 OR model = ?1 || ':latest'",
+    ).map_err(|e| format!("Failed to query Ollama usage: {}", e))?;
+
+    models.into_iter().map(|model| {
+        let last_used_at = stmt
+            .query_row([normalize_model_name(&model.name)], |row| row.get::<_, Option<String>>(0))
+            .map_err(|e| format!("Failed to query Ollama usage: {}", e))?;
+        Ok(OllamaModelUsage {
+            name: model.name,
+            size: model.size,
+            digest: model.digest,
+            modified_at: model.modified_at,
+            last_used_at,
+        })
+    }).collect()
+}
+
+/// Strip the implicit `:latest` tag so `llama3` and `llama3:latest` compare equal
+fn normalize_model_name(name: &str) -> &str {
+    name.strip_suffix(":latest").unwrap_or(name)
+}
+
+/// Models unused since `cutoff` and not keep-listed. Never-used models age from
+/// when they were pulled.
+fn select_models_to_prune<'a>(
+    models: &'a [OllamaModelUsage],
+    cutoff: chrono::DateTime<chrono::Utc>,
+    keep: &[String],
+) -> Vec<&'a OllamaModelUsage> {
+    models.iter()
+        .filter(|model| !keep.iter().any(|k| normalize_model_name(k) == normalize_model_name(&model.name)))
+        .filter(|model| {
+            let last_activity = model.last_used_at.as_deref().unwrap_or(&model.modified_at);
+            match chrono::DateTime::parse_from_rfc3339(last_activity) {
+                Ok(at) => at.with_timezone(&chrono::Utc) < cutoff,
+                // Don't delete what we can't date
+                Err(_) => false,
+            }
+        })
+        .collect()
+}
+
+/// List installed Ollama models with their size and last-used time
+#[command]
+pub async fn list_ollama_models_with_usage(app_handle: AppHandle) -> Result<Vec<OllamaModelUsage>, String> {
+    let models = get_ollama_models().await?;
+    let conn = app_handle.state::<AgentDb>().lock_conn();
+    merge_model_usage(&conn, models)
+}
+
+/// Delete Ollama models unused for `older_than_days`, except those in `keep`
+#[command]
+pub async fn prune_ollama_models(
+    app_handle: AppHandle,
+    older_than_days: u32,
+    keep: Vec<String>,
+) -> Result<OllamaPruneReport, String> {
+    let models = list_ollama_models_with_usage(app_handle.clone()).await?;
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(older_than_days as i64);
+
+    let mut report = OllamaPruneReport { deleted: vec![], failed: vec![], reclaimed_bytes: 0 };
+    for model in select_models_to_prune(&models, cutoff, &keep) {
+        match delete_ollama_model(model.name.clone()).await {
+            Ok(_) => {
+                report.reclaimed_bytes += model.size;
+                report.deleted.push(model.name.clone());
+            }
+            Err(e) => report.failed.push(e),
+        }
+    }
+
+    if !report.deleted.is_empty() {
+        let conn = app_handle.state::<AgentDb>().lock_conn();
+        for name in &report.deleted {
+            conn.execute(
+                "DELETE FROM ollama_model_usage WHERE model = ?1 OR model = ?1 || ':latest'",
+                [normalize_model_name(name)],
+            )
+                .map_err(|e| format!("Failed to clear Ollama usage: {}", e))?;
+        }
+    }
+
+    log::info!(
+        "Pruned {} Ollama model(s), reclaimed {} bytes",
+        report.deleted.len(), report.reclaimed_bytes
+    );
+    Ok(report)
+}
+
+/// Convert a token count over a nanosecond duration into tokens per second
+fn tokens_per_second(tokens: u32, duration_ns: u64) -> f64 {
+    if duration_ns == 0 {
+        return 0.0;
+    }
+    tokens as f64 / (duration_ns as f64 / 1_000_000_000.0)
+}
+
+/// Summarize an NDJSON generate stream into benchmark numbers using the final `done` chunk
+fn summarize_benchmark_stream(model: &str, ndjson: &str) -> Result<OllamaBenchmarkResult, String> {
+    let final_chunk = ndjson
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<OllamaGenerateResponse>(line).ok())
+        .find(|chunk| chunk.done)
+        .ok_or_else(|| "Ollama stream ended without timing statistics".to_string())?;
+    
+    let eval_tokens = final_chunk.eval_count.unwrap_or(0);
+    let prompt_tokens = final_chunk.prompt_eval_count.unwrap_or(0);
+    
+    Ok(OllamaBenchmarkResult {
+        model: model.to_string(),
+        prompt_tokens,
+        eval_tokens,
+        eval_tokens_per_second: tokens_per_second(eval_tokens, final_chunk.eval_duration.unwrap_or(0)),
+        prompt_tokens_per_second: tokens_per_second(prompt_tokens, final_chunk.prompt_eval_duration.unwrap_or(0)),
+        load_time_ms: final_chunk.load_duration.unwrap_or(0) / 1_000_000,
+        total_time_ms: final_chunk.total_duration.unwrap_or(0) / 1_000_000,
+        saved_to_benchmarks: false,
+    })
+}
+
+/// Record measured local speed in `ai_model_benchmarks` so routing sees real numbers
+fn save_ollama_benchmark(conn: &rusqlite::Connection, result: &OllamaBenchmarkResult) -> Result<(), String> {
+    super::intelligent_routing::init_benchmark_tables(conn)
+        .map_err(|e| format!("Failed to initialize benchmark tables: {}", e))?;
+    
+    // 100 tokens/sec or more counts as the top speed score
+    let measurement = super::intelligent_routing::BenchmarkUpdate {
+        model_id: result.model.clone(),
+        provider: Some("ollama".to_string()),
+        speed_score: Some(result.eval_tokens_per_second.min(100.0)),
+        average_response_time: Some(result.total_time_ms as f64),
+        as_of: chrono::Utc::now(),
+        ..Default::default()
+    };
+    super::intelligent_routing::record_local_measurement(conn, &measurement)
+        .map_err(|e| format!("Failed to save benchmark: {}", e))
+}
+
+/// Benchmark a local Ollama model's generation speed on this machine
+#[command]
+pub async fn benchmark_ollama_model(
+    app: AppHandle,
+    model: String,
+    prompt: Option<String>,
+    save_results: Option<bool>,
+) -> Result<OllamaBenchmarkResult, String> {
+    log::info!("Benchmarking Ollama model: {}", model);
+    
+    // Make sure the model is available locally before timing anything
+    let installed = get_ollama_models().await?;
+    let is_pulled = installed.iter().any(|m| {
+        m.name == model || m.name == format!("{}:latest", model)
+    });
+    if !is_pulled {
+        return Err(format!(
+            "Model '{}' is not pulled yet. Pull it first (ollama pull {}) and run the benchmark again.",
+            model, model
+        ));
+    }
+    
+    let client = ollama_timeouts(&app).build_client(true)?;
+    
+    let request_payload = OllamaGenerateRequest {
+        model: model.clone(),
+        prompt: prompt.unwrap_or_else(|| OLLAMA_BENCHMARK_PROMPT.to_string()),
+        stream: true,
+        system: None,
+        context: None,
+        options: Some(HashMap::from([
+            ("temperature".to_string(), json!(0.0)),
+            ("seed".to_string(), json!(42)),
+            ("num_predict".to_string(), json!(OLLAMA_BENCHMARK_NUM_PREDICT)),
+        ])),
+    };
+    
+    let request_payload = serde_json::to_value(&request_payload)
+        .map_err(|e| format!("Failed to serialize Ollama request: {}", e))?;
+    let response = request_hooks(&app)
+        .prepare("ollama", client.post("http://localhost:11434/api/generate"), request_payload)?
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send benchmark request to Ollama: {}", e))?;
+    
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Ollama API returned error {}: {}", status, error_text));
+    }
+    
+    let body = response.text().await
+        .map_err(|e| format!("Failed to read Ollama benchmark stream: {}", e))?;
+    let mut result = summarize_benchmark_stream(&model, &body)?;
+    
+    if save_results.unwrap_or(true) {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        save_ollama_benchmark(&conn, &result)?;
+        result.saved_to_benchmarks = true;
+    }
+    
+    log::info!(
+        "Ollama benchmark for {}: {:.1} tokens/sec, load {}ms",
+        model, result.eval_tokens_per_second, result.load_time_ms
+    );
+    
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -399,4 +1064,193 @@ mod tests {
         assert!(json_str.contains("llama3.3:latest"));
         assert!(json_str.contains("Hello world"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_benchmark_from_mocked_stream() {
+        let ndjson = concat!(
+            r#"{"model":"llama3.2:latest","created_at":"2025-01-01T00:00:00Z","response":"fn","done":false}"#, "\n",
+            r#"{"model":"llama3.2:latest","created_at":"2025-01-01T00:00:01Z","response":" fib","done":false}"#, "\n",
+            r#"{"model":"llama3.2:latest","created_at":"2025-01-01T00:00:02Z","response":"","done":true,"#,
+            r#""total_duration":3500000000,"load_duration":500000000,"prompt_eval_count":20,"#,
+            r#""prompt_eval_duration":250000000,"eval_count":100,"eval_duration":2000000000}"#, "\n",
+        );
+        
+        let result = summarize_benchmark_stream("llama3.2:latest", ndjson).unwrap();
+        assert_eq!(result.eval_tokens, 100);
+        assert!((result.eval_tokens_per_second - 50.0).abs() < 1e-9);
+        assert!((result.prompt_tokens_per_second - 80.0).abs() < 1e-9);
+        assert_eq!(result.load_time_ms, 500);
+        assert_eq!(result.total_time_ms, 3500);
+    }
+
+    /// Serve one canned chunked response per connection, dropping it mid-stream unless `finish`
+    async fn spawn_pull_server(responses: Vec<(Vec<&'static str>, bool)>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (lines, finish) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n\r\n",
+                ).await;
+                for line in lines {
+                    let body = format!("{}\n", line);
+                    let _ = socket.write_all(format!("{:x}\r\n{}\r\n", body.len(), body).as_bytes()).await;
+                }
+                if finish {
+                    let _ = socket.write_all(b"0\r\n\r\n").await;
+                }
+                let _ = socket.shutdown().await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_pull_resumes_after_interruption() {
+        let base_url = spawn_pull_server(vec![
+            // First attempt: layer a finishes, layer b is cut off partway
+            (vec![
+                r#"{"status":"pulling manifest"}"#,
+                r#"{"status":"pulling a","digest":"sha256:a","total":100,"completed":0}"#,
+                r#"{"status":"pulling a","digest":"sha256:a","total":100,"completed":100}"#,
+                r#"{"status":"pulling b","digest":"sha256:b","total":1000,"completed":0}"#,
+                r#"{"status":"pulling b","digest":"sha256:b","total":1000,"completed":400}"#,
+            ], false),
+            // Retry: Ollama reports the partial layer and finishes it
+            (vec![
+                r#"{"status":"pulling manifest"}"#,
+                r#"{"status":"pulling a","digest":"sha256:a","total":100,"completed":100}"#,
+                r#"{"status":"pulling b","digest":"sha256:b","total":1000,"completed":400}"#,
+                r#"{"status":"pulling b","digest":"sha256:b","total":1000,"completed":1000}"#,
+                r#"{"status":"verifying sha256 digest"}"#,
+                r#"{"status":"success"}"#,
+            ], true),
+        ]).await;
+
+        let client = reqwest::Client::new();
+        let mut events = Vec::new();
+        let summary = pull_with_resume(
+            &client, &base_url, "llama3.2", 3, std::time::Duration::ZERO,
+            |event| events.push(event),
+        ).await.unwrap();
+
+        assert_eq!(summary.attempts, 2);
+        assert_eq!(summary.layers.len(), 2);
+        // The retry kept layer a and the first 400 bytes of layer b
+        assert_eq!(summary.layers[0].resumed_bytes, 100);
+        assert_eq!(summary.layers[1].resumed_bytes, 400);
+        assert_eq!(summary.resumed_bytes, 500);
+        assert_eq!(summary.downloaded_bytes, 600);
+
+        let layer_b: Vec<f64> = events.iter()
+            .filter_map(|e| e.layer.as_ref())
+            .filter(|l| l.digest == "sha256:b")
+            .map(|l| l.percent)
+            .collect();
+        assert_eq!(layer_b, vec![0.0, 40.0, 40.0, 100.0]);
+        assert_eq!(events.last().unwrap().status, "success");
+        assert_eq!(events.last().unwrap().attempt, 2);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_issues_keep_alive_preload() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read until the whole JSON body has arrived
+            while !String::from_utf8_lossy(&request).ends_with('}') {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = r#"{"model":"llama3.2:latest","done":true,"done_reason":"load"}"#;
+            let _ = socket.write_all(
+                format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).as_bytes(),
+            ).await;
+            String::from_utf8(request).unwrap()
+        });
+
+        preload_ollama_model(&reqwest::Client::new(), &base_url, "llama3.2:latest").await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api/generate "), "{}", request);
+        let body: Value = serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+        assert_eq!(body["model"], "llama3.2:latest");
+        assert_eq!(body["keep_alive"], OLLAMA_WARMUP_KEEP_ALIVE);
+        // Without a prompt Ollama only loads the model
+        assert!(body.get("prompt").is_none());
+    }
+
+    #[test]
+    fn test_pull_tracker_counts_partial_layers_as_resumed() {
+        let mut tracker = OllamaPullTracker::default();
+        tracker.begin_attempt();
+        for line in [
+            r#"{"status":"pulling a","digest":"sha256:a","total":100,"completed":100}"#,
+            r#"{"status":"pulling b","digest":"sha256:b","total":1000,"completed":250}"#,
+            r#"{"status":"pulling b","digest":"sha256:b","total":1000,"completed":1000}"#,
+            r#"{"status":"pulling c","digest":"sha256:c","total":50,"completed":0}"#,
+            r#"{"status":"pulling c","digest":"sha256:c","total":50,"completed":50}"#,
+        ] {
+            tracker.apply(&serde_json::from_str(line).unwrap());
+        }
+
+        let summary = tracker.summary("m", 1);
+        assert_eq!(summary.resumed_bytes, 350);
+        assert_eq!(summary.downloaded_bytes, 800);
+        assert_eq!(summary.layers[1].resumed_bytes, 250);
+        assert!(summary.layers.iter().all(|l| l.percent == 100.0));
+    }
+
+    #[test]
+    fn test_prune_selects_only_stale_unkept_models() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let now = chrono::Utc::now();
+        let pulled_long_ago = (now - chrono::Duration::days(90)).to_rfc3339();
+        record_ollama_model_usage(&conn, "stale:7b", now - chrono::Duration::days(45)).unwrap();
+        // Chat requests may name a model without its implicit tag
+        record_ollama_model_usage(&conn, "recent", now - chrono::Duration::days(1)).unwrap();
+        conn.execute(
+            "INSERT INTO ollama_model_usage (model, last_used_at) VALUES ('legacy:latest', ?1)",
+            [(now - chrono::Duration::days(2)).to_rfc3339()],
+        ).unwrap();
+
+        let installed = ["stale:7b", "recent:latest", "kept:latest", "never-used:latest", "legacy"]
+            .iter()
+            .map(|name| OllamaModel {
+                name: name.to_string(),
+                modified_at: pulled_long_ago.clone(),
+                size: 1_000,
+                digest: format!("sha256:{}", name),
+                details: None,
+            })
+            .collect();
+        let models = merge_model_usage(&conn, installed).unwrap();
+        assert!(models[0].last_used_at.is_some());
+        assert!(models[1].last_used_at.is_some());
+        assert!(models[3].last_used_at.is_none());
+        assert!(models[4].last_used_at.is_some());
+
+        let cutoff = now - chrono::Duration::days(30);
+        let pruned: Vec<&str> = select_models_to_prune(&models, cutoff, &["kept".to_string()])
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(pruned, ["stale:7b", "never-used:latest"]);
+    }
+
+    #[test]
+    fn test_benchmark_stream_without_done_chunk_fails() {
+        let ndjson = r#"{"model":"m","created_at":"t","response":"partial","done":false}"#;
+        assert!(summarize_benchmark_stream("m", ndjson).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a command, with a message for display and details for the UI to act on
+///
+/// Expected failures (the CLI rejected the request, a file could not be written)
+/// come back as `success: false` rather than as an error, like `AddServerResult`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationResult {
+    pub success: bool,
+    /// Human-readable summary, shown as is
+    pub message: String,
+    /// Structured facts about the outcome, e.g. the affected server
+    pub details: Option<serde_json::Value>,
+}
+
+impl OperationResult {
+    pub fn succeeded(message: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
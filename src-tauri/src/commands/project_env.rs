@@ -0,0 +1,314 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::process::Command;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::session_event_log::is_secret_name;
+
+const PROJECT_ENV_SETTINGS_KEY: &str = "project_env";
+/// Values of secret variables by project and name; a secret setting name keeps them out of backups
+const PROJECT_ENV_SECRETS_SETTINGS_KEY: &str = "project_env_secret";
+
+/// Variables the launched process needs to locate its user, shell and runtime
+const PROTECTED_VARS: &[&str] = &[
+    "HOME",
+    "USER",
+    "SHELL",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "SystemRoot",
+    "COMSPEC",
+    "PATHEXT",
+    "TEMP",
+    "TMP",
+    "NODE_PATH",
+    "NVM_DIR",
+    "NVM_BIN",
+];
+
+/// An environment variable set for every execution in a project, over the inherited environment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectEnvVar {
+    pub name: String,
+    pub value: String,
+    /// Masks the value in logs; names that look like credentials are masked regardless
+    #[serde(default)]
+    pub secret: bool,
+}
+
+impl ProjectEnvVar {
+    pub fn is_secret(&self) -> bool {
+        self.secret || is_secret_name(&self.name)
+    }
+
+    /// The value as it may appear in logs
+    pub fn display_value(&self) -> &str {
+        if self.is_secret() {
+            "********"
+        } else {
+            &self.value
+        }
+    }
+}
+
+/// Check names and values before they are saved
+pub fn validate_project_env(vars: &[ProjectEnvVar]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for var in vars {
+        let name = var.name.as_str();
+        let well_formed = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+        if !well_formed {
+            return Err(format!(
+                "'{}' is not a valid variable name; use letters, digits and underscores, not starting with a digit",
+                name
+            ));
+        }
+        if PROTECTED_VARS.iter().any(|protected| protected.eq_ignore_ascii_case(name)) {
+            return Err(format!("{} is required by the launcher and can't be set per project", name));
+        }
+        if !seen.insert(name.to_ascii_uppercase()) {
+            return Err(format!("{} is defined more than once", name));
+        }
+        if var.value.contains('\0') {
+            return Err(format!("The value of {} contains a NUL character", name));
+        }
+    }
+    Ok(())
+}
+
+/// `project_path` as a key, without trailing separators and, on Windows, in
+/// lower case with `\` separators, so every spelling of a path finds the same project
+fn normalize_project_key(project_path: &str, windows: bool) -> String {
+    let trimmed = project_path.trim();
+    let trimmed = match trimmed.trim_end_matches(['/', '\\']) {
+        "" => trimmed,
+        stripped => stripped,
+    };
+    if windows {
+        trimmed.replace('/', "\\").to_lowercase()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Projects are matched by path, on Windows regardless of case and separators
+fn project_key(project_path: &str) -> String {
+    normalize_project_key(project_path, cfg!(windows))
+}
+
+fn load_setting<T: serde::de::DeserializeOwned + Default>(conn: &Connection, key: &str) -> T {
+    conn.query_row("SELECT value FROM app_settings WHERE key = ?1", params![key], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+fn save_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    let value = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to serialize project environment: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    ).map_err(|e| format!("Failed to save project environment: {}", e))?;
+    Ok(())
+}
+
+/// Every project's variables by project key, secret values included
+///
+/// Keys saved before paths were normalized are folded into their normalized form.
+fn load_all(conn: &Connection) -> HashMap<String, Vec<ProjectEnvVar>> {
+    let stored: HashMap<String, Vec<ProjectEnvVar>> = load_setting(conn, PROJECT_ENV_SETTINGS_KEY);
+    let mut secrets: HashMap<String, HashMap<String, String>> = load_setting(conn, PROJECT_ENV_SECRETS_SETTINGS_KEY);
+
+    let mut all: HashMap<String, Vec<ProjectEnvVar>> = HashMap::new();
+    for (path, vars) in stored {
+        let values = secrets.remove(&path).unwrap_or_default();
+        let vars = vars.into_iter().filter_map(|mut var| {
+            if !var.is_secret() || !var.value.is_empty() {
+                return Some(var);
+            }
+            match values.get(&var.name) {
+                Some(value) => {
+                    var.value = value.clone();
+                    Some(var)
+                }
+                // Restored from a backup that left secrets out; unset rather than empty
+                None => {
+                    log::warn!("No value stored for secret project variable {} of {}", var.name, path);
+                    None
+                }
+            }
+        });
+        all.entry(project_key(&path)).or_default().extend(vars);
+    }
+    all
+}
+
+/// The variables configured for `project_path`; none when it has no configuration
+pub fn load_project_env(conn: &Connection, project_path: &str) -> Vec<ProjectEnvVar> {
+    load_all(conn).remove(&project_key(project_path)).unwrap_or_default()
+}
+
+/// Replace the variables of `project_path`; an empty list removes its configuration
+///
+/// Secret values are saved apart from the rest, which keeps each secret's name
+/// and place with an empty value.
+pub fn save_project_env(conn: &Connection, project_path: &str, vars: Vec<ProjectEnvVar>) -> Result<(), String> {
+    validate_project_env(&vars)?;
+    let mut all = load_all(conn);
+    if vars.is_empty() {
+        all.remove(&project_key(project_path));
+    } else {
+        all.insert(project_key(project_path), vars);
+    }
+
+    let mut secrets: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for (path, vars) in &mut all {
+        for var in vars.iter_mut().filter(|var| var.is_secret()) {
+            let value = std::mem::take(&mut var.value);
+            secrets.entry(path.clone()).or_default().insert(var.name.clone(), value);
+        }
+    }
+    save_setting(conn, PROJECT_ENV_SETTINGS_KEY, &all)?;
+    save_setting(conn, PROJECT_ENV_SECRETS_SETTINGS_KEY, &secrets)
+}
+
+/// The `PATH` the command would run with: its own override, or the inherited one
+fn command_path(cmd: &Command) -> Option<OsString> {
+    cmd.get_envs()
+        .find(|(key, _)| key.eq_ignore_ascii_case("PATH"))
+        .map(|(_, value)| value.map(OsString::from))
+        .unwrap_or_else(|| std::env::var_os("PATH"))
+}
+
+/// The variables to set for `vars`, with `PATH` entries ahead of `inherited_path`
+///
+/// Protected variables are skipped in case a configuration predates validation.
+pub fn resolve_project_env(vars: &[ProjectEnvVar], inherited_path: Option<OsString>) -> Vec<(String, OsString)> {
+    let mut resolved = Vec::new();
+    for var in vars {
+        if PROTECTED_VARS.iter().any(|protected| protected.eq_ignore_ascii_case(&var.name)) {
+            log::warn!("Not overriding protected variable {} from project environment", var.name);
+            continue;
+        }
+        if var.name.eq_ignore_ascii_case("PATH") {
+            let mut paths: Vec<_> = std::env::split_paths(&var.value).collect();
+            if let Some(existing) = &inherited_path {
+                paths.extend(std::env::split_paths(existing));
+            }
+            match std::env::join_paths(paths) {
+                Ok(joined) => resolved.push(("PATH".to_string(), joined)),
+                Err(e) => log::warn!("Ignoring project PATH entries {}: {}", var.value, e),
+            }
+        } else {
+            resolved.push((var.name.clone(), OsString::from(&var.value)));
+        }
+        log::debug!("Project env var: {}={}", var.name, var.display_value());
+    }
+    resolved
+}
+
+/// Set `vars` on `cmd`, over whatever environment it already has
+pub fn apply_project_env(cmd: &mut Command, vars: &[ProjectEnvVar]) {
+    for (name, value) in resolve_project_env(vars, command_path(cmd)) {
+        cmd.env(name, value);
+    }
+}
+
+/// The variables of `project_path` for an execution; none if they can't be read
+pub fn project_env_for(app: &AppHandle, project_path: &str) -> Vec<ProjectEnvVar> {
+    let Some(db) = app.try_state::<AgentDb>() else {
+        return Vec::new();
+    };
+    load_project_env(&db.lock_conn(), project_path)
+}
+
+/// Get the environment variables configured for a project
+#[tauri::command]
+pub async fn get_project_env(db: State<'_, AgentDb>, project_path: String) -> Result<Vec<ProjectEnvVar>, String> {
+    Ok(load_project_env(&*db.lock_conn(), &project_path))
+}
+
+/// Set the environment variables injected into executions in a project
+#[tauri::command]
+pub async fn set_project_env(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    vars: Vec<ProjectEnvVar>,
+) -> Result<(), String> {
+    save_project_env(&*db.lock_conn(), &project_path, vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> ProjectEnvVar {
+        ProjectEnvVar { name: name.to_string(), value: value.to_string(), secret: false }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_project_var_reaches_the_spawned_command() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        save_project_env(&conn, "/work/app", vec![var("APP_STAGE", "staging"), var("PATH", "/opt/app/bin")]).unwrap();
+        save_project_env(&conn, "/work/other", vec![var("APP_STAGE", "production")]).unwrap();
+
+        let mut cmd = crate::claude_binary::create_command_with_env("sh");
+        cmd.args(["-c", r#"printf '%s\n%s' "$APP_STAGE" "$PATH""#]);
+        apply_project_env(&mut cmd, &load_project_env(&conn, "/work/app"));
+        let output = String::from_utf8(cmd.output().unwrap().stdout).unwrap();
+        let (stage, path) = output.split_once('\n').unwrap();
+        assert_eq!(stage, "staging");
+        assert!(path.starts_with("/opt/app/bin:"), "{}", path);
+
+        assert!(save_project_env(&conn, "/work/app", vec![var("home", "/tmp")]).is_err());
+        assert!(save_project_env(&conn, "/work/app", vec![var("1ST", "x")]).is_err());
+        assert_eq!(load_project_env(&conn, "/work/app").len(), 2);
+        assert_eq!(var("GITHUB_TOKEN", "ghp_123").display_value(), "********");
+        assert_eq!(var("APP_STAGE", "staging").display_value(), "staging");
+    }
+
+    #[test]
+    fn test_secret_values_are_stored_apart_from_backed_up_settings() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        let vars = vec![var("APP_STAGE", "staging"), var("GITHUB_TOKEN", "ghp_123"), var("PATH", "/opt/app/bin")];
+        save_project_env(&conn, "/work/app", vars.clone()).unwrap();
+        assert_eq!(load_project_env(&conn, "/work/app/"), vars);
+
+        let stored: String = conn
+            .query_row("SELECT value FROM app_settings WHERE key = ?1", [PROJECT_ENV_SETTINGS_KEY], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.contains("ghp_123"), "{}", stored);
+        assert!(is_secret_name(PROJECT_ENV_SECRETS_SETTINGS_KEY));
+        assert!(!is_secret_name(PROJECT_ENV_SETTINGS_KEY));
+
+        // A backup restored without its secrets leaves the secret unset, not empty
+        conn.execute("DELETE FROM app_settings WHERE key = ?1", [PROJECT_ENV_SECRETS_SETTINGS_KEY]).unwrap();
+        let names: Vec<_> = load_project_env(&conn, "/work/app").into_iter().map(|var| var.name).collect();
+        assert_eq!(names, ["APP_STAGE", "PATH"]);
+    }
+
+    #[test]
+    fn test_windows_paths_match_regardless_of_case_and_separators() {
+        assert_eq!(normalize_project_key("C:/Users/Dev/App/", true), r"c:\users\dev\app");
+        assert_eq!(normalize_project_key(r"c:\users\dev\app", true), r"c:\users\dev\app");
+        assert_eq!(normalize_project_key("/work/App/", false), "/work/App");
+        assert_eq!(normalize_project_key("/", false), "/");
+    }
+
+    #[test]
+    fn test_path_entries_go_ahead_of_the_inherited_path() {
+        let inherited = std::env::join_paths(["/usr/bin"]).unwrap();
+        let resolved = resolve_project_env(&[var("PATH", "/opt/app/bin"), var("HOME", "/tmp")], Some(inherited));
+        let expected = std::env::join_paths(["/opt/app/bin", "/usr/bin"]).unwrap();
+        assert_eq!(resolved, vec![("PATH".to_string(), expected)]);
+    }
+}
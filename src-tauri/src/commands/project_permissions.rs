@@ -0,0 +1,437 @@
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::error_tracker::{track_error_internal, ErrorCategory, ErrorSeverity};
+use super::universal_tool_executor::{ToolContext, ToolType};
+
+/// File operations of the file tool that modify the target
+const WRITE_OPERATIONS: &[&str] = &["write", "create", "edit", "append", "delete", "move", "rename"];
+
+/// Shell syntax that runs more than the named program
+const SHELL_CONTROL_SEQUENCES: &[&str] = &["&", "|", ";", "`", "$(", ">", "<", "\n"];
+
+/// What a project lets models do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectPermissionProfile {
+    pub project_path: String,
+    /// Directories file operations may touch; relative entries are under the project root
+    pub allowed_directories: Vec<String>,
+    pub shell_allowed: bool,
+    /// Programs shell commands may run, by name; empty allows any program
+    pub allowed_commands: Vec<String>,
+}
+
+impl ProjectPermissionProfile {
+    /// The default profile: files under the project root, no shell
+    pub fn project_root_only(project_path: &str) -> Self {
+        Self {
+            project_path: project_path.to_string(),
+            allowed_directories: vec![".".to_string()],
+            shell_allowed: false,
+            allowed_commands: Vec::new(),
+        }
+    }
+
+    fn root(&self) -> Result<PathBuf, String> {
+        if self.project_path.trim().is_empty() {
+            return Err("no project is open".to_string());
+        }
+        Ok(resolve(Path::new(&self.project_path)))
+    }
+
+    /// Whether the profile allows `operation`, with the reason when it does not
+    pub fn check(&self, operation: &ProjectOperation) -> Result<(), String> {
+        match operation.kind {
+            ProjectOperationKind::Read | ProjectOperationKind::Write => self.check_path(&operation.target),
+            ProjectOperationKind::Shell => self.check_command(&operation.target),
+        }
+    }
+
+    fn check_path(&self, target: &str) -> Result<(), String> {
+        let root = self.root()?;
+        let path = resolve(&root.join(target));
+        let allowed = self
+            .allowed_directories
+            .iter()
+            .map(|dir| resolve(&root.join(dir)))
+            .any(|dir| path.starts_with(dir));
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("{} is outside the directories allowed for this project", path.display()))
+        }
+    }
+
+    fn check_command(&self, command: &str) -> Result<(), String> {
+        if !self.shell_allowed {
+            return Err("shell commands are not allowed for this project".to_string());
+        }
+        if self.allowed_commands.is_empty() {
+            return Ok(());
+        }
+        // With an allow-list, chaining or redirecting would run or touch more than the named program
+        if SHELL_CONTROL_SEQUENCES.iter().any(|sequence| command.contains(sequence)) {
+            return Err("only single commands without pipes, chaining or redirection are allowed".to_string());
+        }
+        let program = command
+            .split_whitespace()
+            .next()
+            .map(program_name)
+            .ok_or_else(|| "the command is empty".to_string())?;
+        if self.allowed_commands.iter().any(|allowed| program_name(allowed) == program) {
+            Ok(())
+        } else {
+            Err(format!("{} is not one of the commands allowed for this project", program))
+        }
+    }
+}
+
+/// Program name without directory, `.exe`-style extension or case
+fn program_name(program: &str) -> String {
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(program);
+    name.to_lowercase()
+}
+
+/// `path` without `.` and `..` components, with symlinks of its existing part resolved
+///
+/// Files about to be written do not exist yet, so the deepest existing
+/// ancestor is canonicalized and the rest appended; a symlinked directory
+/// inside the project cannot be used to reach outside it.
+fn resolve(path: &Path) -> PathBuf {
+    let mut lexical = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                lexical.pop();
+            }
+            other => lexical.push(other),
+        }
+    }
+
+    let mut existing = lexical.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing.iter().rev().fold(canonical, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return lexical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectOperationKind {
+    Read,
+    Write,
+    Shell,
+}
+
+impl ProjectOperationKind {
+    fn error_code(self) -> &'static str {
+        match self {
+            ProjectOperationKind::Read => "PERMISSION_DENIED_READ",
+            ProjectOperationKind::Write => "PERMISSION_DENIED_WRITE",
+            ProjectOperationKind::Shell => "PERMISSION_DENIED_SHELL",
+        }
+    }
+}
+
+/// A file or shell operation a tool call is about to perform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectOperation {
+    pub kind: ProjectOperationKind,
+    /// Path, relative to the project root or absolute, or the shell command
+    pub target: String,
+}
+
+impl ProjectOperation {
+    pub fn read(path: &str) -> Self {
+        Self { kind: ProjectOperationKind::Read, target: path.to_string() }
+    }
+
+    pub fn write(path: &str) -> Self {
+        Self { kind: ProjectOperationKind::Write, target: path.to_string() }
+    }
+
+    pub fn shell(command: &str) -> Self {
+        Self { kind: ProjectOperationKind::Shell, target: command.to_string() }
+    }
+}
+
+/// File and shell operations a tool call with these parameters performs
+pub fn tool_operations(tool_type: &ToolType, parameters: &HashMap<String, Value>) -> Vec<ProjectOperation> {
+    let string = |name: &str| parameters.get(name).and_then(Value::as_str);
+    match tool_type {
+        ToolType::FileOperation => {
+            let Some(path) = string("path").or_else(|| string("file_path")) else {
+                return Vec::new();
+            };
+            let operation = string("operation").unwrap_or("read").to_lowercase();
+            let mut operations = vec![if WRITE_OPERATIONS.contains(&operation.as_str()) {
+                ProjectOperation::write(path)
+            } else {
+                ProjectOperation::read(path)
+            }];
+            if let Some(destination) = string("destination") {
+                operations.push(ProjectOperation::write(destination));
+            }
+            operations
+        }
+        ToolType::ShellCommand => string("command").map(ProjectOperation::shell).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn create_project_permissions_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_permissions (
+            project_path TEXT PRIMARY KEY,
+            allowed_directories TEXT NOT NULL, -- JSON array
+            shell_allowed BOOLEAN NOT NULL DEFAULT 0,
+            allowed_commands TEXT NOT NULL, -- JSON array
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// The saved profile of a project, or the project-root-only default
+pub fn load_profile(conn: &Connection, project_path: &str) -> Result<ProjectPermissionProfile, String> {
+    create_project_permissions_table(conn).map_err(|e| e.to_string())?;
+    let saved = conn
+        .query_row(
+            "SELECT allowed_directories, shell_allowed, allowed_commands FROM project_permissions
+             WHERE project_path = ?1",
+            [project_path],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, row.get::<_, String>(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load project permissions: {}", e))?;
+
+    let Some((allowed_directories, shell_allowed, allowed_commands)) = saved else {
+        return Ok(ProjectPermissionProfile::project_root_only(project_path));
+    };
+    Ok(ProjectPermissionProfile {
+        project_path: project_path.to_string(),
+        allowed_directories: serde_json::from_str(&allowed_directories)
+            .map_err(|e| format!("Project permissions are damaged: {}", e))?,
+        shell_allowed,
+        allowed_commands: serde_json::from_str(&allowed_commands)
+            .map_err(|e| format!("Project permissions are damaged: {}", e))?,
+    })
+}
+
+pub fn save_profile(conn: &Connection, profile: &ProjectPermissionProfile) -> Result<(), String> {
+    create_project_permissions_table(conn).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO project_permissions
+         (project_path, allowed_directories, shell_allowed, allowed_commands, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            profile.project_path,
+            serde_json::to_string(&profile.allowed_directories).map_err(|e| e.to_string())?,
+            profile.shell_allowed,
+            serde_json::to_string(&profile.allowed_commands).map_err(|e| e.to_string())?,
+            chrono::Utc::now().timestamp(),
+        ],
+    )
+    .map_err(|e| format!("Failed to save project permissions: {}", e))?;
+    Ok(())
+}
+
+/// Check `operation` against the project's profile, recording a denial as an error
+///
+/// Tool calls made on behalf of a model are checked before they execute. Denials are
+/// recorded in the error knowledge base so a model probing outside its project shows up there.
+pub fn authorize(conn: &Connection, operation: &ProjectOperation, context: &ToolContext) -> Result<(), String> {
+    let profile = load_profile(conn, &context.project_path)?;
+    let Err(reason) = profile.check(operation) else {
+        return Ok(());
+    };
+
+    let verb = match operation.kind {
+        ProjectOperationKind::Read => "Read of",
+        ProjectOperationKind::Write => "Write to",
+        ProjectOperationKind::Shell => "Shell command",
+    };
+    let message = format!("{} {} denied: {}", verb, operation.target, reason);
+    log::warn!("{} (model {}, project {})", message, context.model_id, context.project_path);
+
+    let category = match operation.kind {
+        ProjectOperationKind::Shell => ErrorCategory::Configuration,
+        _ => ErrorCategory::FileSystem,
+    };
+    let details = HashMap::from([
+        ("project_path".to_string(), context.project_path.clone()),
+        ("model_id".to_string(), context.model_id.clone()),
+        ("target".to_string(), operation.target.clone()),
+    ]);
+    // The denial itself must not depend on recording it
+    if let Err(e) = track_error_internal(
+        conn,
+        operation.kind.error_code().to_string(),
+        message.clone(),
+        "Project permissions".to_string(),
+        category.as_str().to_string(),
+        ErrorSeverity::High.as_str().to_string(),
+        None,
+        details,
+        Some(context.session_id.clone()),
+        None,
+    ) {
+        log::warn!("Failed to record permission denial: {}", e);
+    }
+    Err(message)
+}
+
+/// Check every file and shell operation of a tool call before it runs
+pub fn authorize_tool_call(
+    conn: &Connection,
+    tool_type: &ToolType,
+    parameters: &HashMap<String, Value>,
+    context: &ToolContext,
+) -> Result<(), String> {
+    tool_operations(tool_type, parameters)
+        .iter()
+        .try_for_each(|operation| authorize(conn, operation, context))
+}
+
+/// Gets the permission profile of a project, the default one if none was saved
+#[tauri::command]
+pub async fn get_project_permissions(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<ProjectPermissionProfile, String> {
+    let conn = db.lock_conn();
+    load_profile(&conn, &project_path)
+}
+
+/// Saves the permission profile of a project
+#[tauri::command]
+pub async fn set_project_permissions(
+    db: State<'_, AgentDb>,
+    profile: ProjectPermissionProfile,
+) -> Result<(), String> {
+    if profile.project_path.trim().is_empty() {
+        return Err("Project path is required".to_string());
+    }
+    let conn = db.lock_conn();
+    save_profile(&conn, &profile)?;
+    log::info!("Saved permissions for project {}", profile.project_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error_tracker::create_error_tables;
+    use tempfile::TempDir;
+
+    fn context(project: &Path) -> ToolContext {
+        ToolContext {
+            session_id: "session-1".to_string(),
+            model_id: "gemini-2.5-pro".to_string(),
+            project_path: project.to_string_lossy().to_string(),
+            user_prompt: String::new(),
+            system_context: None,
+            history: vec![],
+        }
+    }
+
+    fn write_call(path: &str) -> HashMap<String, Value> {
+        HashMap::from([
+            ("operation".to_string(), Value::from("write")),
+            ("path".to_string(), Value::from(path)),
+        ])
+    }
+
+    #[test]
+    fn test_out_of_root_write_is_denied_and_in_root_write_allowed() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_error_tables(&conn).unwrap();
+        let context = context(&project);
+
+        authorize_tool_call(&conn, &ToolType::FileOperation, &write_call("src/new_module.rs"), &context).unwrap();
+        let inside = project.join("src").join("lib.rs");
+        authorize_tool_call(&conn, &ToolType::FileOperation, &write_call(inside.to_str().unwrap()), &context).unwrap();
+
+        let outside = dir.path().join("other").join("notes.txt");
+        for path in ["../other/notes.txt", "src/../../other/notes.txt", outside.to_str().unwrap()] {
+            let error = authorize_tool_call(&conn, &ToolType::FileOperation, &write_call(path), &context).unwrap_err();
+            assert!(error.contains("outside the directories allowed"), "{}", error);
+        }
+
+        let (occurrences, category, session): (u32, String, String) = conn
+            .query_row(
+                "SELECT occurrences, category, session_id FROM error_knowledge WHERE error_code = 'PERMISSION_DENIED_WRITE'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((occurrences, category.as_str(), session.as_str()), (3, "FileSystem", "session-1"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_inside_the_project_cannot_lead_outside() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(dir.path().join("secrets")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secrets"), project.join("linked")).unwrap();
+
+        let profile = ProjectPermissionProfile::project_root_only(project.to_str().unwrap());
+        assert!(profile.check(&ProjectOperation::write("linked/key.pem")).is_err());
+        assert!(profile.check(&ProjectOperation::read("README.md")).is_ok());
+    }
+
+    #[test]
+    fn test_saved_profile_governs_directories_and_shell() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(project.join("docs")).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_error_tables(&conn).unwrap();
+        let context = context(&project);
+        let shell = |command: &str| HashMap::from([("command".to_string(), Value::from(command))]);
+
+        // The default profile has no shell
+        let error = authorize_tool_call(&conn, &ToolType::ShellCommand, &shell("cargo test"), &context).unwrap_err();
+        assert!(error.contains("not allowed"), "{}", error);
+
+        let profile = ProjectPermissionProfile {
+            project_path: context.project_path.clone(),
+            allowed_directories: vec!["docs".to_string()],
+            shell_allowed: true,
+            allowed_commands: vec!["cargo".to_string(), "git".to_string()],
+        };
+        save_profile(&conn, &profile).unwrap();
+        assert_eq!(load_profile(&conn, &context.project_path).unwrap(), profile);
+
+        authorize_tool_call(&conn, &ToolType::FileOperation, &write_call("docs/guide.md"), &context).unwrap();
+        assert!(authorize_tool_call(&conn, &ToolType::FileOperation, &write_call("src/main.rs"), &context).is_err());
+        authorize_tool_call(&conn, &ToolType::ShellCommand, &shell("cargo test --workspace"), &context).unwrap();
+        authorize_tool_call(&conn, &ToolType::ShellCommand, &shell("/usr/bin/Git.exe status"), &context).unwrap();
+        assert!(authorize_tool_call(&conn, &ToolType::ShellCommand, &shell("curl example.com"), &context).is_err());
+        assert!(authorize_tool_call(&conn, &ToolType::ShellCommand, &shell("git status && rm -rf ~"), &context).is_err());
+    }
+}
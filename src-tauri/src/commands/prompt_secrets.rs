@@ -0,0 +1,408 @@
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Range;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::agents::AgentDb;
+use super::settings_diff::{typed_default, Setting};
+use crate::analysis::SECRET_PATTERNS;
+
+/// A quoted assignment such as `api_key = "..."`, shared with the security analysis
+const QUOTED_ASSIGNMENT: &str = SECRET_PATTERNS[0];
+
+pub(crate) const PROMPT_SECRET_SCAN_SETTINGS_KEY: &str = "prompt_secret_scan";
+
+pub(crate) const SETTINGS: &[Setting] =
+    &[Setting::new(PROMPT_SECRET_SCAN_SETTINGS_KEY, stored_mode, typed_default::<PromptSecretMode>)];
+
+/// The mode is stored as its bare name rather than as JSON
+fn stored_mode(stored: &str) -> Result<serde_json::Value, String> {
+    let mode: PromptSecretMode =
+        serde_json::from_value(serde_json::Value::String(stored.to_string())).map_err(|e| e.to_string())?;
+    serde_json::to_value(mode).map_err(|e| e.to_string())
+}
+
+/// Starts the error of a request held back for its secrets
+const HELD_BACK: &str = "Prompt looks like it contains";
+
+/// Replaces each secret in a redacted prompt
+const REDACTED: &str = "[REDACTED]";
+
+/// Assigned values shorter than this are taken for prose, like "password: none"
+const MIN_SECRET_LEN: usize = 8;
+
+/// An unquoted assignment counts only when its value looks like a key: a run of
+/// key characters with a digit in it, ending the line or the expression. This
+/// keeps code such as `token: Option<String>` from being taken for a secret.
+const UNQUOTED_ASSIGNMENT: &str =
+    r"(?im)(api[_\-]?key|apikey|secret|password|pwd|token|auth)\s*[:=]\s*([A-Za-z0-9_\-+/=.~]*[0-9][A-Za-z0-9_\-+/=.~]*)(?:[\s,;)}\]]|$)";
+
+/// Provider key formats recognised without a label
+const KEY_FORMATS: [(&str, &str); 5] = [
+    ("Anthropic API key", r"sk-ant-[A-Za-z0-9_\-]{10,}"),
+    ("OpenAI API key", r"sk-[A-Za-z0-9_\-]{20,}"),
+    ("Google API key", r"AIza[0-9A-Za-z_\-]{30,}"),
+    ("GitHub token", r"gh[pousr]_[A-Za-z0-9]{30,}"),
+    ("AWS access key", r"AKIA[0-9A-Z]{16}"),
+];
+
+lazy_static::lazy_static! {
+    static ref ASSIGNMENT_REGEXES: Vec<Regex> = [QUOTED_ASSIGNMENT, UNQUOTED_ASSIGNMENT]
+        .iter()
+        .map(|pattern| Regex::new(pattern).expect("secret pattern is valid"))
+        .collect();
+
+    static ref KEY_REGEXES: Vec<(&'static str, Regex)> = KEY_FORMATS
+        .iter()
+        .map(|(kind, pattern)| (*kind, Regex::new(pattern).expect("key format is valid")))
+        .collect();
+
+    /// Fingerprints of the secrets the user chose to send despite the warning
+    static ref CONFIRMED_SECRETS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// What happens to a prompt that looks like it contains secrets
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptSecretMode {
+    /// Prompts are not scanned
+    #[default]
+    Off,
+    /// The prompt is held back until the user confirms it
+    Warn,
+    /// The secrets are replaced before the prompt is sent
+    Redact,
+}
+
+/// A likely secret in a prompt
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SecretFinding {
+    /// The label it was assigned to, like `api_key`, or the key format it matches
+    pub kind: String,
+    /// The value with all but its first characters hidden
+    pub masked: String,
+    #[serde(skip)]
+    range: Range<usize>,
+}
+
+/// Payload of the `secret-in-prompt` event
+#[derive(Debug, Serialize, Clone)]
+pub struct SecretInPrompt {
+    pub provider: String,
+    /// Pass to `confirm_prompt_secrets` to send requests with these secrets anyway
+    pub fingerprint: String,
+    pub findings: Vec<SecretFinding>,
+}
+
+/// `value` with only a short prefix left visible, and nothing of short values
+fn mask(value: &str) -> String {
+    if value.chars().count() < 12 {
+        return "****".to_string();
+    }
+    format!("{}****", value.chars().take(4).collect::<String>())
+}
+
+/// `range` of `text` without the quotes and punctuation an unquoted match takes along
+fn unquoted(text: &str, range: Range<usize>) -> Range<usize> {
+    const WRAPPING: [char; 7] = ['"', '\'', '`', ',', ';', ')', '}'];
+    let value = &text[range.clone()];
+    let start = range.start + (value.len() - value.trim_start_matches(WRAPPING).len());
+    let end = range.start + value.trim_end_matches(WRAPPING).len();
+    start..end.max(start)
+}
+
+/// Likely secrets in `prompt`, in order, with overlapping matches merged
+pub fn scan_prompt(prompt: &str) -> Vec<SecretFinding> {
+    let mut matches: Vec<(String, Range<usize>)> = Vec::new();
+    for regex in ASSIGNMENT_REGEXES.iter() {
+        for captures in regex.captures_iter(prompt) {
+            let (Some(name), Some(value)) = (captures.get(1), captures.get(2)) else {
+                continue;
+            };
+            let range = unquoted(prompt, value.range());
+            if prompt[range.clone()].chars().count() >= MIN_SECRET_LEN {
+                matches.push((name.as_str().to_lowercase(), range));
+            }
+        }
+    }
+    for (kind, regex) in KEY_REGEXES.iter() {
+        matches.extend(regex.find_iter(prompt).map(|m| (kind.to_string(), m.range())));
+    }
+    matches.sort_by_key(|(_, range)| (range.start, std::cmp::Reverse(range.end)));
+
+    let mut merged: Vec<(String, Range<usize>)> = Vec::new();
+    for (kind, range) in matches {
+        match merged.last_mut() {
+            Some((_, last)) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => merged.push((kind, range)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(kind, range)| SecretFinding { kind, masked: mask(&prompt[range.clone()]), range })
+        .collect()
+}
+
+/// `prompt` with each finding replaced by a placeholder
+fn redact(prompt: &str, findings: &[SecretFinding]) -> String {
+    let mut redacted = prompt.to_string();
+    for finding in findings.iter().rev() {
+        redacted.replace_range(finding.range.clone(), REDACTED);
+    }
+    redacted
+}
+
+/// The prompt to send under `mode`, or the findings the user must confirm first
+pub fn screen(mode: PromptSecretMode, prompt: &str, confirmed: bool) -> Result<String, Vec<SecretFinding>> {
+    if mode == PromptSecretMode::Off {
+        return Ok(prompt.to_string());
+    }
+    let findings = scan_prompt(prompt);
+    match mode {
+        _ if findings.is_empty() => Ok(prompt.to_string()),
+        PromptSecretMode::Redact => Ok(redact(prompt, &findings)),
+        PromptSecretMode::Warn if confirmed => Ok(prompt.to_string()),
+        _ => Err(findings),
+    }
+}
+
+/// Each of `parts` to send under `mode`, or the findings across all of them the user must confirm first
+pub fn screen_parts(mode: PromptSecretMode, parts: &[&str], confirmed: bool) -> Result<Vec<String>, Vec<SecretFinding>> {
+    let mut screened = Vec::with_capacity(parts.len());
+    let mut findings = Vec::new();
+    for part in parts {
+        match screen(mode, part, confirmed) {
+            Ok(text) => screened.push(text),
+            Err(found) => findings.extend(found),
+        }
+    }
+    if findings.is_empty() {
+        Ok(screened)
+    } else {
+        Err(findings)
+    }
+}
+
+/// One fingerprint over the distinct secrets across all parts, so a single
+/// confirmation covers the whole request and any later one sending the same secrets
+fn fingerprint(provider: &str, parts: &[&str]) -> String {
+    let secrets: BTreeSet<&str> = parts
+        .iter()
+        .copied()
+        .flat_map(|part| scan_prompt(part).into_iter().map(move |finding| &part[finding.range]))
+        .collect();
+    let mut hasher = Sha256::new();
+    hasher.update(provider.as_bytes());
+    for secret in secrets {
+        hasher.update((secret.len() as u64).to_le_bytes());
+        hasher.update(secret.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Load the scan mode, off when unset or unreadable
+pub fn load_prompt_secret_mode(conn: &Connection) -> PromptSecretMode {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![PROMPT_SECRET_SCAN_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|stored| serde_json::from_value(serde_json::Value::String(stored)).ok())
+    .unwrap_or_default()
+}
+
+/// Scan a prompt bound for `provider` and return the text to send
+///
+/// Fails with the masked findings when the prompt is held back for
+/// confirmation, after emitting `secret-in-prompt`.
+pub fn screen_prompt(app: &AppHandle, provider: &str, prompt: &str) -> Result<String, String> {
+    screen_request(app, provider, &[prompt]).map(|mut screened| screened.remove(0))
+}
+
+/// Scan every text part of a request bound for `provider`, such as the prompt,
+/// system prompt and earlier turns, and return the parts to send in order
+///
+/// Fails with the masked findings when the request is held back for
+/// confirmation, after emitting `secret-in-prompt`. Once its fingerprint is
+/// confirmed, requests carrying the same secrets go through, so retries and
+/// follow-up turns are not held again. Secret values are only ever logged or
+/// emitted masked.
+pub fn screen_request(app: &AppHandle, provider: &str, parts: &[&str]) -> Result<Vec<String>, String> {
+    let mode = load_prompt_secret_mode(&app.state::<AgentDb>().lock_conn());
+    screen_request_with(mode, Some(app), provider, parts)
+}
+
+/// `screen_request` under an already loaded `mode`; `secret-in-prompt` is
+/// only emitted when there is an `app` to emit it on
+pub fn screen_request_with(
+    mode: PromptSecretMode,
+    app: Option<&AppHandle>,
+    provider: &str,
+    parts: &[&str],
+) -> Result<Vec<String>, String> {
+    if mode == PromptSecretMode::Off {
+        return Ok(parts.iter().map(|part| part.to_string()).collect());
+    }
+
+    let fingerprint = fingerprint(provider, parts);
+    let confirmed = mode == PromptSecretMode::Warn
+        && CONFIRMED_SECRETS.lock().map_err(|e| e.to_string())?.contains(&fingerprint);
+    match screen_parts(mode, parts, confirmed) {
+        Ok(screened) => {
+            if screened.iter().zip(parts).any(|(sent, part)| sent != part) || confirmed {
+                for finding in parts.iter().flat_map(|part| scan_prompt(part)) {
+                    log::warn!(
+                        "{} {} ({}) in a {} prompt",
+                        if confirmed { "Sending confirmed" } else { "Redacted" },
+                        finding.kind,
+                        finding.masked,
+                        provider
+                    );
+                }
+            }
+            Ok(screened)
+        }
+        Err(findings) => {
+            for finding in &findings {
+                log::warn!("Holding back a {} prompt with a likely {} ({})", provider, finding.kind, finding.masked);
+            }
+            let summary = findings
+                .iter()
+                .map(|finding| format!("{} {}", finding.kind, finding.masked))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let warning = SecretInPrompt { provider: provider.to_string(), fingerprint, findings };
+            if let Some(Err(e)) = app.map(|app| app.emit("secret-in-prompt", &warning)) {
+                log::warn!("Failed to emit secret-in-prompt: {}", e);
+            }
+            Err(format!(
+                "{} {} secret(s): {}. Remove them, or confirm to send it anyway",
+                HELD_BACK,
+                warning.findings.len(),
+                summary
+            ))
+        }
+    }
+}
+
+/// Whether `error` is from a request held back for its secrets, which a retry would only hold again
+pub fn is_held_back(error: &str) -> bool {
+    error.starts_with(HELD_BACK)
+}
+
+/// Let requests carrying the secrets with this fingerprint through despite the findings
+#[tauri::command]
+pub async fn confirm_prompt_secrets(fingerprint: String) -> Result<(), String> {
+    CONFIRMED_SECRETS.lock().map_err(|e| e.to_string())?.insert(fingerprint);
+    Ok(())
+}
+
+/// Get how prompts with likely secrets are handled
+#[tauri::command]
+pub async fn get_prompt_secret_mode(db: State<'_, AgentDb>) -> Result<PromptSecretMode, String> {
+    let conn = db.lock_conn();
+    Ok(load_prompt_secret_mode(&conn))
+}
+
+/// Set how prompts with likely secrets are handled
+#[tauri::command]
+pub async fn set_prompt_secret_mode(db: State<'_, AgentDb>, mode: PromptSecretMode) -> Result<(), String> {
+    let value = serde_json::to_value(mode)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .ok_or("Failed to serialize prompt secret mode")?;
+
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![PROMPT_SECRET_SCAN_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save prompt secret mode: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_in_prompt_is_held_back_or_redacted() {
+        let key = "AIzaSyD3mo7kQ2x9Vb8LpWc4Rt6Yh1Nj5Ue0Ia";
+        let prompt = format!("Why does this fail?\nconst client = init({{ apiKey: \"{}\" }});\npassword: none", key);
+
+        let findings = screen(PromptSecretMode::Warn, &prompt, false).unwrap_err();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, "apikey");
+        assert_eq!(findings[0].masked, "AIza****");
+        assert!(!serde_json::to_string(&findings).unwrap().contains(key));
+
+        // A confirmed prompt goes out unchanged
+        assert_eq!(screen(PromptSecretMode::Warn, &prompt, true).unwrap(), prompt);
+
+        let redacted = screen(PromptSecretMode::Redact, &prompt, false).unwrap();
+        assert!(!redacted.contains(key), "{}", redacted);
+        assert!(redacted.contains("init({ apiKey: \"[REDACTED]\" });"), "{}", redacted);
+
+        // A bare key is caught by its format, and nothing is touched when scanning is off
+        let bare = format!("use {} for the demo", key);
+        assert_eq!(screen(PromptSecretMode::Redact, &bare, false).unwrap(), "use [REDACTED] for the demo");
+        assert_eq!(scan_prompt(&bare)[0].kind, "Google API key");
+        assert_eq!(screen(PromptSecretMode::Off, &bare, false).unwrap(), bare);
+        assert_eq!(screen(PromptSecretMode::Warn, "What does the auth module do?", false).unwrap(), "What does the auth module do?");
+    }
+
+    #[test]
+    fn test_type_annotations_are_not_secrets() {
+        for code in [
+            "struct Session { token: Option<String>, auth: AuthState }",
+            "fn login(password: &str, token: CancellationToken)",
+            "let secret = config.secret.clone();",
+        ] {
+            assert!(scan_prompt(code).is_empty(), "{}", code);
+        }
+        let findings = scan_prompt("export TOKEN=ghx9Lm2Qr7Tz4Kp1\npwd = s3cr3t-value;");
+        assert_eq!(findings.iter().map(|f| f.kind.as_str()).collect::<Vec<_>>(), ["token", "pwd"]);
+    }
+
+    #[test]
+    fn test_every_part_of_a_request_is_screened() {
+        let key = "sk-ant-api03-Xk29fLq8ZpW4";
+        let system = "You are a reviewer.";
+        let earlier = format!("Here is my config: ANTHROPIC_KEY={}", key);
+        let parts = ["Why does the call fail?", system, earlier.as_str()];
+
+        let findings = screen_parts(PromptSecretMode::Warn, &parts, false).unwrap_err();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, "Anthropic API key");
+
+        let redacted = screen_parts(PromptSecretMode::Redact, &parts, false).unwrap();
+        assert_eq!(redacted[..2], parts[..2]);
+        assert_eq!(redacted[2], "Here is my config: ANTHROPIC_KEY=[REDACTED]");
+
+        // The fingerprint covers the secrets in every part, not just the prompt,
+        // and stays the same for a follow-up that sends them again
+        assert_ne!(fingerprint("gemini", &parts), fingerprint("gemini", &parts[..1]));
+        let follow_up = [earlier.as_str(), "And now?"];
+        assert_eq!(fingerprint("gemini", &parts), fingerprint("gemini", &follow_up));
+        assert_ne!(fingerprint("gemini", &parts), fingerprint("ollama", &parts));
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_secrets_stay_confirmed() {
+        let parts = ["deploy with token=9fQ2xLm7Rt4Kp1Zw"];
+        let error = screen_request_with(PromptSecretMode::Warn, None, "gemini", &parts).unwrap_err();
+        assert!(error.contains("token 9fQ2****"), "{}", error);
+        assert!(is_held_back(&error));
+
+        confirm_prompt_secrets(fingerprint("gemini", &parts)).await.unwrap();
+        for _ in 0..2 {
+            assert_eq!(screen_request_with(PromptSecretMode::Warn, None, "gemini", &parts).unwrap(), parts);
+        }
+    }
+}
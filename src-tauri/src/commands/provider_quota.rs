@@ -0,0 +1,284 @@
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Providers always listed in the status, throttled or not
+const KNOWN_PROVIDERS: &[&str] = &["claude", "gemini", "ollama"];
+
+/// Assumed wait after a first 429 that came without `Retry-After`, doubled per repeat
+const BACKOFF_INITIAL_SECS: i64 = 30;
+const BACKOFF_MAX_SECS: i64 = 15 * 60;
+
+/// Gemini's daily quotas reset at midnight Pacific time; standard time is assumed
+const PACIFIC_OFFSET_SECS: i32 = -8 * 60 * 60;
+
+lazy_static::lazy_static! {
+    pub static ref PROVIDER_QUOTAS: QuotaTracker = QuotaTracker::default();
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProviderQuotaState {
+    last_throttled_at: Option<DateTime<Utc>>,
+    retry_after_secs: Option<u64>,
+    resets_at: Option<DateTime<Utc>>,
+    /// The provider said the quota itself is used up, not just the request rate
+    quota_exhausted: bool,
+    consecutive_throttles: u32,
+}
+
+/// Current limit posture of one provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub provider: String,
+    /// Requests are expected to be rejected until `resets_at`
+    pub throttled: bool,
+    pub quota_exhausted: bool,
+    pub last_throttled_at: Option<DateTime<Utc>>,
+    /// `Retry-After` of the last 429, when the provider sent one
+    pub retry_after_secs: Option<u64>,
+    /// When the limit lifts: reported by the provider or estimated
+    pub resets_at: Option<DateTime<Utc>>,
+    pub resets_in_secs: Option<u64>,
+    pub message: String,
+}
+
+/// Records throttling per provider; shared through `PROVIDER_QUOTAS`
+///
+/// Every 429 from Gemini and every usage-limit notice from the Claude CLI is kept
+/// with when the limit lifts, when the provider says so, and model routing skips
+/// a provider until its estimated reset has passed.
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    states: Mutex<HashMap<String, ProviderQuotaState>>,
+}
+
+impl QuotaTracker {
+    /// Record a 429 or similar rejection from `provider`
+    pub fn record_throttled(&self, provider: &str, retry_after: Option<Duration>) {
+        self.record_throttled_at(provider, retry_after, Utc::now());
+    }
+
+    pub fn record_throttled_at(&self, provider: &str, retry_after: Option<Duration>, now: DateTime<Utc>) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let state = states.entry(provider.to_string()).or_default();
+        state.consecutive_throttles += 1;
+        state.last_throttled_at = Some(now);
+        state.retry_after_secs = retry_after.map(|delay| delay.as_secs());
+        state.quota_exhausted = false;
+        state.resets_at = Some(match retry_after {
+            Some(delay) => now + ChronoDuration::from_std(delay).unwrap_or(ChronoDuration::MAX),
+            None => {
+                let exponent = state.consecutive_throttles.saturating_sub(1).min(10);
+                now + ChronoDuration::seconds((BACKOFF_INITIAL_SECS << exponent).min(BACKOFF_MAX_SECS))
+            }
+        });
+        log::warn!(
+            "{} is rate limited until {} ({} in a row)",
+            provider,
+            state.resets_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+            state.consecutive_throttles
+        );
+    }
+
+    /// The last rejection was for a used-up quota rather than the request rate
+    ///
+    /// Without a `Retry-After`, the limit is assumed to last until the daily reset.
+    pub fn mark_quota_exhausted(&self, provider: &str) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = states.get_mut(provider) {
+            state.quota_exhausted = true;
+            if state.retry_after_secs.is_none() {
+                state.resets_at = state.last_throttled_at.map(next_pacific_midnight);
+            }
+        }
+    }
+
+    /// Record a limit whose reset time the provider reported directly
+    pub fn record_limited_until(&self, provider: &str, resets_at: DateTime<Utc>, now: DateTime<Utc>) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let state = states.entry(provider.to_string()).or_default();
+        state.consecutive_throttles += 1;
+        state.last_throttled_at = Some(now);
+        state.retry_after_secs = None;
+        state.quota_exhausted = true;
+        state.resets_at = Some(resets_at);
+        log::warn!("{} usage limit reached until {}", provider, resets_at.to_rfc3339());
+    }
+
+    /// A request went through, so whatever limit was recorded has lifted
+    pub fn record_success(&self, provider: &str) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        if states.remove(provider).is_some() {
+            log::info!("{} is no longer rate limited", provider);
+        }
+    }
+
+    pub fn is_throttled(&self, provider: &str) -> bool {
+        self.is_throttled_at(provider, Utc::now())
+    }
+
+    pub fn is_throttled_at(&self, provider: &str, now: DateTime<Utc>) -> bool {
+        let states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        states
+            .get(provider)
+            .and_then(|state| state.resets_at)
+            .is_some_and(|resets_at| resets_at > now)
+    }
+
+    /// Providers currently throttled, for routing
+    pub fn throttled_providers(&self) -> Vec<String> {
+        let now = Utc::now();
+        self.status_at(now).into_iter().filter(|status| status.throttled).map(|status| status.provider).collect()
+    }
+
+    /// Status of the known providers and any other provider that has been throttled
+    pub fn status_at(&self, now: DateTime<Utc>) -> Vec<QuotaStatus> {
+        let states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let mut providers: Vec<&str> = KNOWN_PROVIDERS.to_vec();
+        providers.extend(states.keys().map(String::as_str).filter(|name| !KNOWN_PROVIDERS.contains(name)));
+
+        providers
+            .into_iter()
+            .map(|provider| {
+                let state = states.get(provider).cloned().unwrap_or_default();
+                let resets_in = state
+                    .resets_at
+                    .map(|resets_at| (resets_at - now).num_seconds())
+                    .filter(|&secs| secs > 0)
+                    .map(|secs| secs as u64);
+                let throttled = resets_in.is_some();
+                let message = match resets_in {
+                    Some(secs) if state.quota_exhausted => {
+                        format!("{} quota exhausted, resets in {}", display_name(provider), format_wait(secs))
+                    }
+                    Some(secs) => format!("{} throttled, resets in {}", display_name(provider), format_wait(secs)),
+                    None => format!("{} is not rate limited", display_name(provider)),
+                };
+                QuotaStatus {
+                    provider: provider.to_string(),
+                    throttled,
+                    quota_exhausted: throttled && state.quota_exhausted,
+                    last_throttled_at: state.last_throttled_at,
+                    retry_after_secs: state.retry_after_secs,
+                    resets_at: state.resets_at,
+                    resets_in_secs: resets_in,
+                    message,
+                }
+            })
+            .collect()
+    }
+}
+
+fn display_name(provider: &str) -> String {
+    let mut chars = provider.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// `45s`, `12m` or `3h 5m`
+fn format_wait(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs.div_ceil(60)),
+        _ => format!("{}h {}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+fn next_pacific_midnight(now: DateTime<Utc>) -> DateTime<Utc> {
+    let pacific = FixedOffset::east_opt(PACIFIC_OFFSET_SECS).expect("valid offset");
+    let tomorrow = now.with_timezone(&pacific).date_naive() + ChronoDuration::days(1);
+    let midnight = tomorrow.and_hms_opt(0, 0, 0).expect("valid time");
+    pacific
+        .from_local_datetime(&midnight)
+        .single()
+        .map(|at| at.with_timezone(&Utc))
+        .unwrap_or(now + ChronoDuration::days(1))
+}
+
+/// Parse a `Retry-After` value: delay seconds or an HTTP date
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or_default())
+}
+
+/// `Retry-After` of an HTTP response, if present and valid
+pub fn retry_after_header(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, Utc::now()))
+}
+
+/// Reset time in a Claude CLI usage-limit notice, `Claude AI usage limit reached|<unix seconds>`
+pub fn parse_claude_usage_limit(line: &str) -> Option<DateTime<Utc>> {
+    let (_, rest) = line.split_once("usage limit reached|")?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Utc.timestamp_opt(digits.parse().ok()?, 0).single()
+}
+
+/// Record a Claude usage limit if `line` of CLI output announces one
+pub fn observe_claude_output(line: &str) {
+    if let Some(resets_at) = parse_claude_usage_limit(line) {
+        PROVIDER_QUOTAS.record_limited_until("claude", resets_at, Utc::now());
+    }
+}
+
+/// Rate-limit posture of each provider, for showing throttling before a request fails
+#[tauri::command]
+pub async fn get_quota_status() -> Result<Vec<QuotaStatus>, String> {
+    Ok(PROVIDER_QUOTAS.status_at(Utc::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_429_with_retry_after_populates_status() {
+        let tracker = QuotaTracker::default();
+        let now = Utc.with_ymd_and_hms(2025, 8, 1, 12, 0, 0).unwrap();
+        let retry_after = parse_retry_after("120", now);
+        assert_eq!(retry_after, Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Fri, 01 Aug 2025 12:10:00 GMT", now),
+            Some(Duration::from_secs(600))
+        );
+
+        tracker.record_throttled_at("gemini", retry_after, now);
+        let later = now + ChronoDuration::seconds(30);
+        let gemini = tracker.status_at(later).into_iter().find(|s| s.provider == "gemini").unwrap();
+        assert!(gemini.throttled);
+        assert_eq!(gemini.retry_after_secs, Some(120));
+        assert_eq!(gemini.last_throttled_at, Some(now));
+        assert_eq!(gemini.resets_at, Some(now + ChronoDuration::seconds(120)));
+        assert_eq!(gemini.resets_in_secs, Some(90));
+        assert_eq!(gemini.message, "Gemini throttled, resets in 2m");
+        assert!(tracker.is_throttled_at("gemini", later));
+        assert!(!tracker.is_throttled_at("gemini", now + ChronoDuration::seconds(121)));
+        assert!(!tracker.is_throttled_at("claude", later));
+
+        tracker.record_success("gemini");
+        assert!(!tracker.status_at(later).iter().any(|s| s.throttled));
+
+        // Without Retry-After an exhausted daily quota lasts until midnight Pacific
+        tracker.record_throttled_at("gemini", None, now);
+        assert_eq!(tracker.status_at(now)[1].resets_in_secs, Some(30));
+        tracker.mark_quota_exhausted("gemini");
+        let gemini = tracker.status_at(now).into_iter().find(|s| s.provider == "gemini").unwrap();
+        assert_eq!(gemini.resets_at, Some(Utc.with_ymd_and_hms(2025, 8, 2, 8, 0, 0).unwrap()));
+        assert!(gemini.message.starts_with("Gemini quota exhausted, resets in 20h"), "{}", gemini.message);
+
+        assert_eq!(
+            parse_claude_usage_limit(r#"{"type":"result","result":"Claude AI usage limit reached|1754056800"}"#),
+            Utc.timestamp_opt(1754056800, 0).single()
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::State;
+use rusqlite::{params, Connection};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings_diff::Setting;
+
+pub(crate) const PROVIDER_TIMEOUTS_SETTINGS_KEY: &str = "provider_timeouts";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<ProviderTimeouts>(PROVIDER_TIMEOUTS_SETTINGS_KEY)];
+
+lazy_static::lazy_static! {
+    /// Clients handed out by `shared_client`, by timeouts and whether they stream
+    static ref SHARED_CLIENTS: Mutex<HashMap<(ProviderTimeout, bool), reqwest::Client>> = Mutex::new(HashMap::new());
+}
+
+/// HTTP timeouts for a single provider, in seconds
+///
+/// `request_secs` bounds a whole non-streaming request. Streaming requests have no
+/// total limit and instead fail once the server sends nothing for `idle_secs`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ProviderTimeout {
+    pub connect_secs: u64,
+    pub request_secs: u64,
+    pub idle_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProviderTimeouts {
+    #[serde(default = "ProviderTimeouts::default_gemini")]
+    pub gemini: ProviderTimeout,
+    #[serde(default = "ProviderTimeouts::default_ollama")]
+    pub ollama: ProviderTimeout,
+}
+
+impl ProviderTimeouts {
+    fn default_gemini() -> ProviderTimeout {
+        ProviderTimeout { connect_secs: 30, request_secs: 120, idle_secs: 60 }
+    }
+
+    fn default_ollama() -> ProviderTimeout {
+        // Local models can take a while to load before the first token
+        ProviderTimeout { connect_secs: 10, request_secs: 300, idle_secs: 120 }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.gemini.validate("gemini")?;
+        self.ollama.validate("ollama")
+    }
+}
+
+impl Default for ProviderTimeouts {
+    fn default() -> Self {
+        Self {
+            gemini: Self::default_gemini(),
+            ollama: Self::default_ollama(),
+        }
+    }
+}
+
+impl ProviderTimeout {
+    fn validate(&self, provider: &str) -> Result<(), String> {
+        for (name, value) in [
+            ("connect_secs", self.connect_secs),
+            ("request_secs", self.request_secs),
+            ("idle_secs", self.idle_secs),
+        ] {
+            if value == 0 {
+                return Err(format!("{} timeout {} must be greater than 0", provider, name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build an HTTP client honouring these timeouts
+    pub fn build_client(&self, streaming: bool) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_secs))
+            .read_timeout(Duration::from_secs(self.idle_secs));
+        if !streaming {
+            builder = builder.timeout(Duration::from_secs(self.request_secs));
+        }
+        builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
+
+    /// An HTTP client honouring these timeouts whose connection pool outlives the request
+    ///
+    /// Requests with the same timeouts share the client, so a connection opened
+    /// by one, or by a warm-up, is reused by the next.
+    pub fn shared_client(&self, streaming: bool) -> Result<reqwest::Client, String> {
+        let mut clients = SHARED_CLIENTS.lock().map_err(|e| e.to_string())?;
+        if let Some(client) = clients.get(&(self.clone(), streaming)) {
+            return Ok(client.clone());
+        }
+        let client = self.build_client(streaming)?;
+        clients.insert((self.clone(), streaming), client.clone());
+        Ok(client)
+    }
+}
+
+/// Drop the shared clients, so the next requests pick up changed proxy settings
+pub fn clear_shared_clients() {
+    match SHARED_CLIENTS.lock() {
+        Ok(mut clients) => clients.clear(),
+        Err(e) => log::warn!("Failed to clear shared HTTP clients: {}", e),
+    }
+}
+
+/// Load provider timeouts, falling back to defaults when unset or unreadable
+pub fn load_provider_timeouts(conn: &Connection) -> ProviderTimeouts {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![PROVIDER_TIMEOUTS_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str::<ProviderTimeouts>(&value).ok())
+    .filter(|timeouts| timeouts.validate().is_ok())
+    .unwrap_or_default()
+}
+
+/// Get per-provider HTTP timeouts
+#[tauri::command]
+pub async fn get_provider_timeouts(db: State<'_, AgentDb>) -> Result<ProviderTimeouts, String> {
+    let conn = db.lock_conn();
+    Ok(load_provider_timeouts(&conn))
+}
+
+/// Save per-provider HTTP timeouts
+#[tauri::command]
+pub async fn set_provider_timeouts(
+    db: State<'_, AgentDb>,
+    timeouts: ProviderTimeouts,
+) -> Result<(), String> {
+    timeouts.validate()?;
+    let value = serde_json::to_string(&timeouts)
+        .map_err(|e| format!("Failed to serialize provider timeouts: {}", e))?;
+
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![PROVIDER_TIMEOUTS_SETTINGS_KEY, value],
+    ).map_err(|e| format!("Failed to save provider timeouts: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn short_timeout() -> ProviderTimeout {
+        ProviderTimeout { connect_secs: 1, request_secs: 1, idle_secs: 1 }
+    }
+
+    /// Accept one connection, optionally send a partial streaming response, then stall
+    async fn spawn_slow_server(send_headers: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            if send_headers {
+                let _ = socket.write_all(
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n",
+                ).await;
+            }
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_validate_rejects_zero() {
+        let mut timeouts = ProviderTimeouts::default();
+        assert!(timeouts.validate().is_ok());
+        timeouts.ollama.idle_secs = 0;
+        assert!(timeouts.validate().unwrap_err().contains("ollama"));
+    }
+
+    #[test]
+    fn test_load_falls_back_to_defaults() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        assert_eq!(load_provider_timeouts(&conn), ProviderTimeouts::default());
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![PROVIDER_TIMEOUTS_SETTINGS_KEY, r#"{"gemini":{"connect_secs":5,"request_secs":20,"idle_secs":10}}"#],
+        ).unwrap();
+        let timeouts = load_provider_timeouts(&conn);
+        assert_eq!(timeouts.gemini.request_secs, 20);
+        assert_eq!(timeouts.ollama, ProviderTimeouts::default().ollama);
+    }
+
+    #[tokio::test]
+    async fn test_short_request_timeout_fails_promptly() {
+        let url = spawn_slow_server(false).await;
+        let client = short_timeout().build_client(false).unwrap();
+
+        let started = Instant::now();
+        let err = client.get(&url).send().await.unwrap_err();
+        assert!(err.is_timeout());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_idle_timeout_fails_promptly() {
+        use futures_util::StreamExt;
+
+        let url = spawn_slow_server(true).await;
+        let client = short_timeout().build_client(true).unwrap();
+
+        let started = Instant::now();
+        let response = client.get(&url).send().await.unwrap();
+        let mut stream = response.bytes_stream();
+        assert_eq!(&stream.next().await.unwrap().unwrap()[..], b"hello");
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.is_timeout());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::agents::AgentDb;
+use crate::models::{canonicalize, ModelProvider};
+
+lazy_static::lazy_static! {
+    /// The latest status of each model's warm-up
+    static ref WARMUP_STATUSES: Mutex<HashMap<String, WarmupStatus>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WarmupState {
+    Warming,
+    Ready,
+    Failed,
+}
+
+/// Payload of the `provider-warmup` event
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupStatus {
+    pub model: String,
+    pub provider: ModelProvider,
+    pub state: WarmupState,
+    /// What was warmed, or why it failed
+    pub detail: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Provider serving `model`; names outside the catalog are local Ollama models
+fn model_provider(model: &str) -> ModelProvider {
+    canonicalize(model).map_or(ModelProvider::Ollama, |canonical| canonical.provider)
+}
+
+fn record_status(app: &AppHandle, status: WarmupStatus) {
+    match WARMUP_STATUSES.lock() {
+        Ok(mut statuses) => {
+            statuses.insert(status.model.clone(), status.clone());
+        }
+        Err(e) => log::warn!("Failed to record warm-up status: {}", e),
+    }
+    emit_status(app, &status);
+}
+
+fn emit_status(app: &AppHandle, status: &WarmupStatus) {
+    if let Err(e) = app.emit("provider-warmup", status) {
+        log::warn!("Failed to emit provider-warmup: {}", e);
+    }
+}
+
+/// Open a pooled connection to Gemini that the model's first request reuses
+async fn warm_up_gemini(app: &AppHandle, model: &str) -> Result<String, String> {
+    let id = canonicalize(model).map_or(model, |canonical| canonical.id);
+    let endpoint = super::gemini::gemini_model_endpoint(id)
+        .ok_or_else(|| format!("Model '{}' is not supported", model))?;
+    let (api_key, timeouts) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        let timeouts = super::provider_timeouts::load_provider_timeouts(&conn).gemini;
+        (super::gemini::get_gemini_api_key_sync(&conn)?, timeouts)
+    };
+    if api_key.is_empty() {
+        return Err("Gemini API key is not configured".to_string());
+    }
+
+    // Fetching the model's metadata costs no generation quota
+    let url = super::gemini_backend::gemini_url(&format!("v1beta/models/{}", endpoint), &api_key).await;
+    let response = timeouts
+        .shared_client(true)?
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to connect to Gemini: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Gemini returned {} for {}", response.status(), endpoint));
+    }
+    Ok(format!("Connected to Gemini endpoint {}", endpoint))
+}
+
+/// Run the Claude CLI once so its binary and runtime are in the OS caches
+///
+/// Each Claude run spawns a fresh process with its own prompt, so there is no
+/// process to hand over; a cold start is what this avoids.
+async fn warm_up_claude(app: &AppHandle) -> Result<String, String> {
+    let claude_path = crate::claude_binary::find_claude_binary(app)?;
+    let output = tokio::task::spawn_blocking(move || {
+        crate::claude_binary::create_command_with_env(&claude_path).arg("--version").output()
+    })
+    .await
+    .map_err(|e| format!("Failed to start Claude: {}", e))?
+    .map_err(|e| format!("Failed to start Claude: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Claude exited with {}", output.status));
+    }
+    Ok(format!("Started {}", String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+async fn warm_up(app: &AppHandle, model: &str, provider: ModelProvider) -> Result<String, String> {
+    match provider {
+        ModelProvider::Ollama => super::ollama::warm_up_ollama_model(app, model)
+            .await
+            .map(|()| format!("Loaded {} into memory", model)),
+        ModelProvider::Gemini => warm_up_gemini(app, model).await,
+        ModelProvider::Claude => warm_up_claude(app).await,
+    }
+}
+
+/// Warm up the provider of `model` in the background, so the first request is fast
+///
+/// Ollama loads the model into memory, Gemini opens a TLS connection and the
+/// Claude CLI warms its caches, work the first message would otherwise pay for.
+/// Returns the `warming` status right away; the outcome follows as a
+/// `provider-warmup` event. A model already warming is not warmed twice.
+#[tauri::command]
+pub async fn warmup_provider(app: AppHandle, model: String) -> Result<WarmupStatus, String> {
+    let model = model.trim().to_string();
+    if model.is_empty() {
+        return Err("Model cannot be empty".to_string());
+    }
+    let provider = model_provider(&model);
+
+    let warming = WarmupStatus { model: model.clone(), provider, state: WarmupState::Warming, detail: None, elapsed_ms: 0 };
+    // Checked and claimed under one lock so concurrent calls start a single warm-up
+    {
+        let mut statuses = WARMUP_STATUSES.lock().map_err(|e| e.to_string())?;
+        if let Some(status) = statuses.get(&model).filter(|status| status.state == WarmupState::Warming) {
+            return Ok(status.clone());
+        }
+        statuses.insert(model.clone(), warming.clone());
+    }
+    emit_status(&app, &warming);
+
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        let outcome = warm_up(&app, &model, provider).await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        let (state, detail) = match outcome {
+            Ok(detail) => {
+                log::info!("Warmed up {} in {}ms: {}", model, elapsed_ms, detail);
+                (WarmupState::Ready, detail)
+            }
+            Err(e) => {
+                log::warn!("Failed to warm up {}: {}", model, e);
+                (WarmupState::Failed, e)
+            }
+        };
+        record_status(&app, WarmupStatus { model, provider, state, detail: Some(detail), elapsed_ms });
+    });
+
+    Ok(warming)
+}
+
+/// The latest warm-up status of every model warmed since startup
+#[tauri::command]
+pub async fn get_warmup_statuses() -> Result<Vec<WarmupStatus>, String> {
+    let statuses = WARMUP_STATUSES.lock().map_err(|e| e.to_string())?;
+    let mut statuses: Vec<WarmupStatus> = statuses.values().cloned().collect();
+    statuses.sort_by(|a, b| a.model.cmp(&b.model));
+    Ok(statuses)
+}
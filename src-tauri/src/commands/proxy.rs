@@ -1,8 +1,23 @@
 use serde::{Deserialize, Serialize};
-use tauri::State;
-use rusqlite::params;
+use tauri::{AppHandle, Manager, State};
+use rusqlite::{params, Connection};
 
 use crate::commands::agents::AgentDb;
+use crate::commands::settings_diff::{flag, off, text, unset, ResetFuture, Setting};
+
+/// The proxy is applied at startup and when saved, so a reset applies it again
+pub(crate) const SETTINGS: &[Setting] = &[
+    Setting::new("proxy_enabled", flag, off).on_reset(reapply_proxy),
+    Setting::new("proxy_http", text, unset).on_reset(reapply_proxy),
+    Setting::new("proxy_https", text, unset).on_reset(reapply_proxy),
+    Setting::new("proxy_no", text, unset).on_reset(reapply_proxy),
+    Setting::new("proxy_all", text, unset).on_reset(reapply_proxy),
+];
+
+fn reapply_proxy(app: AppHandle) -> ResetFuture {
+    apply_proxy_settings(&load_proxy_settings(&app.state::<AgentDb>().lock_conn()));
+    Box::pin(std::future::ready(Ok(())))
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProxySettings {
@@ -25,11 +40,8 @@ impl Default for ProxySettings {
     }
 }
 
-/// Get proxy settings from the database
-#[tauri::command]
-pub async fn get_proxy_settings(db: State<'_, AgentDb>) -> Result<ProxySettings, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    
+/// Read proxy settings, with defaults for any that aren't stored
+pub fn load_proxy_settings(conn: &Connection) -> ProxySettings {
     let mut settings = ProxySettings::default();
     
     // Query each proxy setting
@@ -58,7 +70,14 @@ pub async fn get_proxy_settings(db: State<'_, AgentDb>) -> Result<ProxySettings,
         }
     }
     
-    Ok(settings)
+    settings
+}
+
+/// Get proxy settings from the database
+#[tauri::command]
+pub async fn get_proxy_settings(db: State<'_, AgentDb>) -> Result<ProxySettings, String> {
+    let conn = db.lock_conn();
+    Ok(load_proxy_settings(&conn))
 }
 
 /// Save proxy settings to the database
@@ -67,7 +86,7 @@ pub async fn save_proxy_settings(
     db: State<'_, AgentDb>,
     settings: ProxySettings,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Save each setting
     let values = vec![
@@ -107,6 +126,8 @@ pub fn apply_proxy_settings(settings: &ProxySettings) {
         std::env::remove_var("https_proxy");
         std::env::remove_var("no_proxy");
         std::env::remove_var("all_proxy");
+        // Pooled clients read the proxy environment only when they are built
+        super::provider_timeouts::clear_shared_clients();
         return;
     }
     
@@ -152,4 +173,7 @@ pub fn apply_proxy_settings(settings: &ProxySettings) {
             log::info!("  {}={}", key, value);
         }
     }
+
+    // Pooled clients read the proxy environment only when they are built
+    super::provider_timeouts::clear_shared_clients();
 }
\ No newline at end of file
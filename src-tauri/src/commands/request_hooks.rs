@@ -0,0 +1,384 @@
+use reqwest::header::{HeaderName, HeaderValue};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::prompt_secrets::{load_prompt_secret_mode, screen_request_with, PromptSecretMode};
+use crate::commands::settings_diff::Setting;
+
+pub(crate) const REQUEST_HOOKS_SETTINGS_KEY: &str = "request_hooks";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<RequestHooks>(REQUEST_HOOKS_SETTINGS_KEY)];
+
+/// Providers whose HTTP requests pass through the hooks
+pub const HOOK_PROVIDERS: [&str; 2] = ["gemini", "ollama"];
+
+/// Providers whose requests leave the machine and are screened for secrets
+const SCREENED_PROVIDERS: [&str; 1] = ["gemini"];
+
+/// Headers the HTTP client manages itself
+const RESERVED_HEADERS: [&str; 4] = ["host", "content-length", "content-type", "transfer-encoding"];
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// One transformation of a provider's outgoing requests
+///
+/// Hooks are declarative, addressing body fields by JSON pointer (RFC 6901) such
+/// as `/generationConfig/temperature` or `/safetySettings`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RequestHook {
+    /// Shown in logs and validation errors
+    pub name: String,
+    /// "gemini" or "ollama"
+    pub provider: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Headers to add, replacing any of the same name
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Body fields to set, by JSON pointer; missing parent objects are created
+    #[serde(default)]
+    pub set: BTreeMap<String, Value>,
+    /// Body fields to remove, by JSON pointer
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// All configured hooks, applied in order
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RequestHooks {
+    #[serde(default)]
+    pub hooks: Vec<RequestHook>,
+    /// How outgoing text is screened for secrets, loaded with the hooks
+    #[serde(skip)]
+    pub(crate) secret_mode: PromptSecretMode,
+    /// Where `secret-in-prompt` is emitted when a request is held back
+    #[serde(skip)]
+    app: Option<AppHandle>,
+}
+
+fn validate_pointer(hook: &str, pointer: &str) -> Result<(), String> {
+    if !pointer.starts_with('/') || pointer.len() < 2 {
+        return Err(format!(
+            "Request hook '{}': '{}' must be a JSON pointer to a field, like /generationConfig/temperature",
+            hook, pointer
+        ));
+    }
+    Ok(())
+}
+
+impl RequestHook {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Request hook name must not be empty".to_string());
+        }
+        if !HOOK_PROVIDERS.contains(&self.provider.as_str()) {
+            return Err(format!(
+                "Request hook '{}': unknown provider '{}', expected one of {}",
+                self.name,
+                self.provider,
+                HOOK_PROVIDERS.join(", ")
+            ));
+        }
+        for (name, value) in &self.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Request hook '{}': invalid header name '{}'", self.name, name))?;
+            if RESERVED_HEADERS.contains(&name.to_lowercase().as_str()) {
+                return Err(format!("Request hook '{}': header '{}' can't be overridden", self.name, name));
+            }
+            HeaderValue::from_str(value)
+                .map_err(|_| format!("Request hook '{}': invalid value for header '{}'", self.name, name))?;
+        }
+        for pointer in self.set.keys().chain(&self.remove) {
+            validate_pointer(&self.name, pointer)?;
+        }
+        Ok(())
+    }
+}
+
+/// The unescaped segments of a JSON pointer
+fn pointer_segments(pointer: &str) -> Vec<String> {
+    pointer[1..].split('/').map(|s| s.replace("~1", "/").replace("~0", "~")).collect()
+}
+
+/// Set the field at `pointer`, creating missing parent objects; `-` appends to an array
+fn set_pointer(body: &mut Value, pointer: &str, value: Value) -> Result<(), String> {
+    let segments = pointer_segments(pointer);
+    let (last, parents) = segments.split_last().ok_or_else(|| format!("Empty JSON pointer '{}'", pointer))?;
+    let mut target = body;
+    for segment in parents {
+        target = match target {
+            Value::Object(obj) => obj.entry(segment.clone()).or_insert_with(|| Value::Object(Default::default())),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index))
+                .ok_or_else(|| format!("No array element '{}' on the way to {}", segment, pointer))?,
+            _ => return Err(format!("'{}' doesn't lead through objects or arrays", pointer)),
+        };
+    }
+    match target {
+        Value::Object(obj) => {
+            obj.insert(last.clone(), value);
+        }
+        Value::Array(items) if last == "-" => items.push(value),
+        Value::Array(items) => match last.parse::<usize>() {
+            Ok(index) if index < items.len() => items[index] = value,
+            Ok(index) if index == items.len() => items.push(value),
+            _ => return Err(format!("No array element '{}' at {}", last, pointer)),
+        },
+        _ => return Err(format!("'{}' doesn't lead through objects or arrays", pointer)),
+    }
+    Ok(())
+}
+
+/// Every string under a `text` key in `body`, such as the text parts of Gemini contents
+fn text_parts(body: &mut Value) -> Vec<&mut String> {
+    match body {
+        Value::Object(obj) => obj
+            .iter_mut()
+            .flat_map(|(key, value)| match (key.as_str(), value) {
+                ("text", Value::String(text)) => vec![text],
+                (_, value) => text_parts(value),
+            })
+            .collect(),
+        Value::Array(items) => items.iter_mut().flat_map(text_parts).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Remove the field at `pointer`; a missing field is left alone
+fn remove_pointer(body: &mut Value, pointer: &str) {
+    let segments = pointer_segments(pointer);
+    let Some((last, parents)) = segments.split_last() else {
+        return;
+    };
+    let parent_pointer: String = parents
+        .iter()
+        .map(|s| format!("/{}", s.replace('~', "~0").replace('/', "~1")))
+        .collect();
+    match body.pointer_mut(&parent_pointer) {
+        Some(Value::Object(obj)) => {
+            obj.remove(last);
+        }
+        Some(Value::Array(items)) => {
+            if let Some(index) = last.parse::<usize>().ok().filter(|index| *index < items.len()) {
+                items.remove(index);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl RequestHooks {
+    pub fn validate(&self) -> Result<(), String> {
+        self.hooks.iter().try_for_each(RequestHook::validate)
+    }
+
+    /// Run `provider`'s enabled hooks over `body`, screen its text for secrets,
+    /// then attach it and the hooks' headers to `request`
+    ///
+    /// Every send path goes through here right before dispatch, so they share one
+    /// secret check. Fails without sending anything when the request is held back.
+    pub fn prepare(
+        &self,
+        provider: &str,
+        mut request: reqwest::RequestBuilder,
+        mut body: Value,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        for hook in self.hooks.iter().filter(|hook| hook.enabled && hook.provider == provider) {
+            for pointer in &hook.remove {
+                remove_pointer(&mut body, pointer);
+            }
+            for (pointer, value) in &hook.set {
+                set_pointer(&mut body, pointer, value.clone())
+                    .map_err(|e| format!("Request hook '{}' failed: {}", hook.name, e))?;
+            }
+            for (name, value) in &hook.headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            log::debug!("Applied request hook '{}' to a {} request", hook.name, provider);
+        }
+        if SCREENED_PROVIDERS.contains(&provider) {
+            let mut texts = text_parts(&mut body);
+            let parts: Vec<&str> = texts.iter().map(|text| text.as_str()).collect();
+            let screened = screen_request_with(self.secret_mode, self.app.as_ref(), provider, &parts)?;
+            for (text, sent) in texts.iter_mut().zip(screened) {
+                **text = sent;
+            }
+        }
+        Ok(request.json(&body))
+    }
+}
+
+/// Load request hooks, with none when unset or invalid
+pub fn load_request_hooks(conn: &Connection) -> RequestHooks {
+    let stored = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![REQUEST_HOOKS_SETTINGS_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok();
+    let secret_mode = load_prompt_secret_mode(conn);
+    let Some(stored) = stored else {
+        return RequestHooks { secret_mode, ..Default::default() };
+    };
+    match serde_json::from_str::<RequestHooks>(&stored)
+        .map_err(|e| e.to_string())
+        .and_then(|hooks| hooks.validate().map(|_| hooks))
+    {
+        Ok(hooks) => RequestHooks { secret_mode, ..hooks },
+        Err(e) => {
+            log::warn!("Ignoring stored request hooks: {}", e);
+            RequestHooks { secret_mode, ..Default::default() }
+        }
+    }
+}
+
+/// Request hooks from the app database, none if it can't be read, reporting held-back requests to `app`
+pub fn request_hooks(app: &AppHandle) -> RequestHooks {
+    let hooks = load_request_hooks(&app.state::<AgentDb>().lock_conn());
+    RequestHooks { app: Some(app.clone()), ..hooks }
+}
+
+/// Get the configured request hooks
+#[tauri::command]
+pub async fn get_request_hooks(db: State<'_, AgentDb>) -> Result<RequestHooks, String> {
+    let conn = db.lock_conn();
+    Ok(load_request_hooks(&conn))
+}
+
+/// Save request hooks, rejecting the whole set if any hook is invalid
+#[tauri::command]
+pub async fn set_request_hooks(db: State<'_, AgentDb>, hooks: RequestHooks) -> Result<(), String> {
+    hooks.validate()?;
+    let value = serde_json::to_string(&hooks).map_err(|e| format!("Failed to serialize request hooks: {}", e))?;
+
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![REQUEST_HOOKS_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save request hooks: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn hooks(value: Value) -> RequestHooks {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_header_injection_hook_reaches_the_outgoing_request() {
+        let hooks = hooks(json!({ "hooks": [
+            {
+                "name": "gateway",
+                "provider": "gemini",
+                "headers": { "X-Team-Gateway": "platform" },
+                "set": { "/safetySettings/0/threshold": "BLOCK_LOW_AND_ABOVE", "/labels/team": "platform" },
+                "remove": ["/generationConfig/topK"]
+            },
+            { "name": "local", "provider": "ollama", "headers": { "X-Ollama-Only": "1" } },
+            { "name": "off", "provider": "gemini", "enabled": false, "headers": { "X-Disabled": "1" } }
+        ] }));
+        hooks.validate().unwrap();
+
+        // Echo back the raw request
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 4096];
+            let complete = |raw: &[u8]| {
+                let raw = String::from_utf8_lossy(raw);
+                let Some((head, body)) = raw.split_once("\r\n\r\n") else {
+                    return false;
+                };
+                let length = head
+                    .lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|n| n.trim().parse::<usize>()))
+                    .and_then(Result::ok)
+                    .unwrap_or(0);
+                body.len() >= length
+            };
+            while !complete(&raw) {
+                let n = socket.read(&mut buf).await.unwrap();
+                assert!(n > 0, "request ended early");
+                raw.extend_from_slice(&buf[..n]);
+            }
+            socket.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+            String::from_utf8(raw).unwrap()
+        });
+
+        let body = json!({
+            "contents": [],
+            "generationConfig": { "temperature": 0.7, "topK": 40 },
+            "safetySettings": [{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }]
+        });
+        let request = hooks
+            .prepare("gemini", reqwest::Client::new().post(format!("http://{}/v1beta", addr)), body)
+            .unwrap();
+        request.send().await.unwrap();
+
+        let raw = server.await.unwrap();
+        let (head, sent) = raw.split_once("\r\n\r\n").unwrap();
+        let head = head.to_lowercase();
+        assert!(head.contains("x-team-gateway: platform"), "{}", head);
+        assert!(!head.contains("x-ollama-only") && !head.contains("x-disabled"), "{}", head);
+        let sent: Value = serde_json::from_str(sent).unwrap();
+        assert_eq!(sent["safetySettings"][0]["threshold"], "BLOCK_LOW_AND_ABOVE");
+        assert_eq!(sent["labels"], json!({ "team": "platform" }));
+        assert_eq!(sent["generationConfig"], json!({ "temperature": 0.7 }));
+    }
+
+    #[test]
+    fn test_text_parts_are_screened_after_the_hooks() {
+        let key = "AIzaSyD3mo7kQ2x9Vb8LpWc4Rt6Yh1Nj5Ue0Ia";
+        let body = json!({
+            "systemInstruction": { "parts": [{ "text": "You are a reviewer." }] },
+            "contents": [{ "role": "user", "parts": [{ "text": format!("Why is {} rejected?", key) }] }]
+        });
+        let request = || reqwest::Client::new().post("http://127.0.0.1:9/v1beta");
+
+        let mut hooks = RequestHooks { secret_mode: PromptSecretMode::Warn, ..Default::default() };
+        let error = hooks.prepare("gemini", request(), body.clone()).unwrap_err();
+        assert!(error.contains("Google API key AIza****"), "{}", error);
+        // Local providers are not screened
+        assert!(hooks.prepare("ollama", request(), body.clone()).is_ok());
+
+        hooks.secret_mode = PromptSecretMode::Redact;
+        let sent = hooks.prepare("gemini", request(), body).unwrap().build().unwrap();
+        let sent: Value = serde_json::from_slice(sent.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(sent["contents"][0]["parts"][0]["text"], "Why is [REDACTED] rejected?");
+        assert_eq!(sent["systemInstruction"]["parts"][0]["text"], "You are a reviewer.");
+    }
+
+    #[test]
+    fn test_invalid_hooks_are_rejected() {
+        let error = |hook: Value| hooks(json!({ "hooks": [hook] })).validate().unwrap_err();
+
+        assert!(error(json!({ "name": "a", "provider": "openai" })).contains("unknown provider 'openai'"));
+        assert!(error(json!({ "name": "a", "provider": "gemini", "headers": { "Bad Header": "x" } }))
+            .contains("invalid header name"));
+        assert!(error(json!({ "name": "a", "provider": "gemini", "headers": { "Content-Length": "1" } }))
+            .contains("can't be overridden"));
+        assert!(error(json!({ "name": "a", "provider": "gemini", "headers": { "X-A": "line\nbreak" } }))
+            .contains("invalid value"));
+        assert!(error(json!({ "name": "a", "provider": "gemini", "set": { "temperature": 1 } }))
+            .contains("must be a JSON pointer"));
+        assert!(error(json!({ "name": " ", "provider": "gemini" })).contains("name must not be empty"));
+    }
+}
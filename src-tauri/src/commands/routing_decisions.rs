@@ -0,0 +1,622 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::intelligent_routing::{ModelRecommendationV2, ScoreBreakdown, TaskComplexityAnalysis};
+
+/// Width of the confidence ranges in `RoutingAccuracyStats::by_confidence`
+const CONFIDENCE_BUCKET_WIDTH: f64 = 0.2;
+
+/// Log of routing decisions, each with the task analysis that led to it, and how they turned out
+///
+/// Outcomes let router confidence be compared with what actually happened, and
+/// decisions tied to a session, along with models picked by hand, let
+/// `explain_current_model` say why a session uses the model it does.
+pub fn create_routing_decision_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS routing_decisions (
+            id TEXT PRIMARY KEY,
+            input_hash TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            priority TEXT NOT NULL,
+            chosen_model TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            created_at INTEGER NOT NULL,
+            success INTEGER,
+            cost_usd REAL,
+            latency_ms INTEGER,
+            completed_at INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_routing_decisions_created_at ON routing_decisions(created_at);",
+    )?;
+
+    // Columns added for explaining a session's model; ignore errors when they already exist
+    for column in [
+        "session_id TEXT",
+        "reasoning TEXT",
+        "score_breakdown TEXT",
+        "alternatives TEXT",
+        "fallback_reason TEXT",
+        "manual_override INTEGER NOT NULL DEFAULT 0",
+    ] {
+        let _ = conn.execute(&format!("ALTER TABLE routing_decisions ADD COLUMN {}", column), []);
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_routing_decisions_session ON routing_decisions(session_id, created_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Store a routing decision and return its id, for attaching the outcome later
+///
+/// Only a hash of the prompt is kept, enough to spot repeated inputs.
+pub fn record_routing_decision(
+    conn: &Connection,
+    session_id: Option<&str>,
+    prompt: &str,
+    analysis: &TaskComplexityAnalysis,
+    recommendation: &ModelRecommendationV2,
+) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO routing_decisions (
+            id, input_hash, domain, priority, chosen_model, confidence, created_at,
+            session_id, reasoning, score_breakdown, alternatives, fallback_reason
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            id,
+            format!("{:x}", Sha256::digest(prompt.as_bytes())),
+            format!("{:?}", analysis.domain_classification),
+            format!("{:?}", analysis.priority_level),
+            recommendation.primary_model,
+            recommendation.confidence,
+            Utc::now().timestamp(),
+            session_id,
+            recommendation.reasoning,
+            serde_json::to_string(&recommendation.score_breakdown).unwrap_or_default(),
+            serde_json::to_string(&recommendation.fallback_models).unwrap_or_default(),
+            recommendation.fallback_reason,
+        ],
+    )
+    .map_err(|e| format!("Failed to record routing decision: {}", e))?;
+    Ok(id)
+}
+
+/// Record that the user picked `model_id` for a session by hand
+///
+/// Stored alongside the router's decisions, but left out of the accuracy stats.
+pub fn record_manual_model_choice(conn: &Connection, session_id: &str, model_id: &str) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO routing_decisions (
+            id, input_hash, domain, priority, chosen_model, confidence, created_at, session_id, manual_override
+         ) VALUES (?1, '', 'Manual', 'Manual', ?2, 1.0, ?3, ?4, 1)",
+        params![id, model_id, Utc::now().timestamp(), session_id],
+    )
+    .map_err(|e| format!("Failed to record model choice: {}", e))?;
+    Ok(id)
+}
+
+/// Note the model a session's run starts with
+///
+/// Nothing is stored when the session's latest decision already chose it;
+/// otherwise the user picked it, at the start of the session or by switching
+/// models, and it is recorded as their choice. Returns the new decision's id.
+pub fn record_session_model(conn: &Connection, session_id: &str, model_id: &str) -> Result<Option<String>, String> {
+    let latest: Option<String> = conn
+        .query_row(
+            "SELECT chosen_model FROM routing_decisions WHERE session_id = ?1
+             ORDER BY created_at DESC, rowid DESC LIMIT 1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if latest.as_deref() == Some(model_id) {
+        return Ok(None);
+    }
+    record_manual_model_choice(conn, session_id, model_id).map(Some)
+}
+
+/// Note the model a session's run starts with, logging instead of failing
+pub fn note_session_model(app: &AppHandle, session_id: &str, model_id: &str) {
+    if let Err(e) = record_session_model(&app.state::<AgentDb>().lock_conn(), session_id, model_id) {
+        log::warn!("Failed to record the model of session {}: {}", session_id, e);
+    }
+}
+
+/// Attach the outcome of the request a decision routed
+pub fn save_routing_outcome(
+    conn: &Connection,
+    decision_id: &str,
+    success: bool,
+    cost_usd: Option<f64>,
+    latency_ms: Option<i64>,
+) -> Result<(), String> {
+    let updated = conn
+        .execute(
+            "UPDATE routing_decisions SET success = ?2, cost_usd = ?3, latency_ms = ?4, completed_at = ?5
+             WHERE id = ?1",
+            params![decision_id, success, cost_usd, latency_ms, Utc::now().timestamp()],
+        )
+        .map_err(|e| format!("Failed to record routing outcome: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Unknown routing decision: {}", decision_id));
+    }
+    Ok(())
+}
+
+/// Attach the outcome of a session's run to its latest decision still waiting for one
+///
+/// Returns false when the session has no such decision.
+pub fn save_session_outcome(
+    conn: &Connection,
+    session_id: &str,
+    success: bool,
+    cost_usd: Option<f64>,
+    latency_ms: Option<i64>,
+) -> Result<bool, String> {
+    let updated = conn
+        .execute(
+            "UPDATE routing_decisions SET success = ?2, cost_usd = ?3, latency_ms = ?4, completed_at = ?5
+             WHERE id = (
+                 SELECT id FROM routing_decisions
+                 WHERE session_id = ?1 AND success IS NULL
+                 ORDER BY created_at DESC, rowid DESC LIMIT 1
+             )",
+            params![session_id, success, cost_usd, latency_ms, Utc::now().timestamp()],
+        )
+        .map_err(|e| format!("Failed to record routing outcome: {}", e))?;
+    Ok(updated > 0)
+}
+
+/// Record how a session's run ended, called from each provider's completion path
+pub fn record_session_outcome(
+    app: &AppHandle,
+    session_id: &str,
+    success: bool,
+    cost_usd: Option<f64>,
+    latency_ms: Option<i64>,
+) {
+    let saved = save_session_outcome(&app.state::<AgentDb>().lock_conn(), session_id, success, cost_usd, latency_ms);
+    if let Err(e) = saved {
+        log::warn!("Failed to record routing outcome for session {}: {}", session_id, e);
+    }
+}
+
+/// Outcomes of decisions made with confidence in `[min_confidence, max_confidence)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceBucket {
+    pub min_confidence: f64,
+    pub max_confidence: f64,
+    pub decisions: i64,
+    pub success_rate: f64,
+    pub average_cost_usd: Option<f64>,
+    pub average_latency_ms: Option<f64>,
+}
+
+/// How often decisions that chose a model succeeded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelRoutingAccuracy {
+    pub model: String,
+    pub decisions: i64,
+    pub average_confidence: f64,
+    pub success_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingAccuracyStats {
+    pub total_decisions: i64,
+    /// Decisions whose outcome has been recorded; the figures below cover only these
+    pub decisions_with_outcome: i64,
+    pub success_rate: f64,
+    /// Pearson correlation between confidence and success, when there is enough spread to compute it
+    pub confidence_success_correlation: Option<f64>,
+    pub by_confidence: Vec<ConfidenceBucket>,
+    pub by_model: Vec<ModelRoutingAccuracy>,
+}
+
+struct Outcome {
+    model: String,
+    confidence: f64,
+    success: bool,
+    cost_usd: Option<f64>,
+    latency_ms: Option<i64>,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+fn success_rate(outcomes: &[&Outcome]) -> f64 {
+    mean(outcomes.iter().map(|o| if o.success { 1.0 } else { 0.0 })).unwrap_or(0.0)
+}
+
+fn correlation(outcomes: &[Outcome]) -> Option<f64> {
+    let xs: Vec<f64> = outcomes.iter().map(|o| o.confidence).collect();
+    let ys: Vec<f64> = outcomes.iter().map(|o| if o.success { 1.0 } else { 0.0 }).collect();
+    let (mean_x, mean_y) = (mean(xs.iter().copied())?, mean(ys.iter().copied())?);
+    let covariance: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let spread_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let spread_y: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+    if spread_x == 0.0 || spread_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (spread_x * spread_y).sqrt())
+}
+
+pub fn compute_routing_accuracy_stats(conn: &Connection, days_limit: Option<i64>) -> Result<RoutingAccuracyStats, String> {
+    let since = days_limit
+        .map(|days| Utc::now().timestamp() - days * 24 * 60 * 60)
+        .unwrap_or(0);
+
+    let total_decisions: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM routing_decisions WHERE created_at >= ?1 AND manual_override = 0",
+            params![since],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT chosen_model, confidence, success, cost_usd, latency_ms FROM routing_decisions
+             WHERE created_at >= ?1 AND success IS NOT NULL AND manual_override = 0",
+        )
+        .map_err(|e| e.to_string())?;
+    let outcomes = stmt
+        .query_map(params![since], |row| {
+            Ok(Outcome {
+                model: row.get(0)?,
+                confidence: row.get(1)?,
+                success: row.get(2)?,
+                cost_usd: row.get(3)?,
+                latency_ms: row.get(4)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<SqliteResult<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+
+    let bucket_count = (1.0 / CONFIDENCE_BUCKET_WIDTH).round() as usize;
+    let mut buckets: Vec<Vec<&Outcome>> = vec![Vec::new(); bucket_count];
+    let mut models: HashMap<&str, Vec<&Outcome>> = HashMap::new();
+    for outcome in &outcomes {
+        let index = ((outcome.confidence.clamp(0.0, 1.0) / CONFIDENCE_BUCKET_WIDTH) as usize).min(bucket_count - 1);
+        buckets[index].push(outcome);
+        models.entry(outcome.model.as_str()).or_default().push(outcome);
+    }
+
+    let by_confidence = buckets
+        .iter()
+        .enumerate()
+        .filter(|(_, bucket)| !bucket.is_empty())
+        .map(|(index, bucket)| ConfidenceBucket {
+            min_confidence: index as f64 * CONFIDENCE_BUCKET_WIDTH,
+            max_confidence: (index + 1) as f64 * CONFIDENCE_BUCKET_WIDTH,
+            decisions: bucket.len() as i64,
+            success_rate: success_rate(bucket),
+            average_cost_usd: mean(bucket.iter().filter_map(|o| o.cost_usd)),
+            average_latency_ms: mean(bucket.iter().filter_map(|o| o.latency_ms.map(|ms| ms as f64))),
+        })
+        .collect();
+
+    let mut by_model: Vec<ModelRoutingAccuracy> = models
+        .into_iter()
+        .map(|(model, decisions)| ModelRoutingAccuracy {
+            model: model.to_string(),
+            decisions: decisions.len() as i64,
+            average_confidence: mean(decisions.iter().map(|o| o.confidence)).unwrap_or(0.0),
+            success_rate: success_rate(&decisions),
+        })
+        .collect();
+    by_model.sort_by(|a, b| b.decisions.cmp(&a.decisions).then_with(|| a.model.cmp(&b.model)));
+
+    Ok(RoutingAccuracyStats {
+        total_decisions,
+        decisions_with_outcome: outcomes.len() as i64,
+        success_rate: success_rate(&outcomes.iter().collect::<Vec<_>>()),
+        confidence_success_correlation: correlation(&outcomes),
+        by_confidence,
+        by_model,
+    })
+}
+
+/// A stored routing decision with what the router weighed to make it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingDecisionRecord {
+    pub id: String,
+    pub domain: String,
+    pub priority: String,
+    pub chosen_model: String,
+    pub confidence: f64,
+    pub created_at: i64,
+    pub reasoning: Option<String>,
+    /// Per-candidate scoring, best first
+    pub score_breakdown: Vec<ScoreBreakdown>,
+    /// Models the router would fall back to, in order
+    pub alternatives: Vec<String>,
+    pub fallback_reason: Option<String>,
+}
+
+/// Why a session is using its current model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelExplanation {
+    pub session_id: String,
+    pub model: String,
+    /// Whether the user picked the model by hand instead of auto mode
+    pub manual_override: bool,
+    /// The decision that picked the model; for an override, the automatic one it replaced, if any
+    pub decision: Option<RoutingDecisionRecord>,
+    pub summary: String,
+}
+
+fn decision_record(row: &rusqlite::Row) -> SqliteResult<(RoutingDecisionRecord, bool)> {
+    let json_list = |index: usize| -> SqliteResult<String> {
+        Ok(row.get::<_, Option<String>>(index)?.unwrap_or_default())
+    };
+    let record = RoutingDecisionRecord {
+        id: row.get(0)?,
+        domain: row.get(1)?,
+        priority: row.get(2)?,
+        chosen_model: row.get(3)?,
+        confidence: row.get(4)?,
+        created_at: row.get(5)?,
+        reasoning: row.get(6)?,
+        score_breakdown: serde_json::from_str(&json_list(7)?).unwrap_or_default(),
+        alternatives: serde_json::from_str(&json_list(8)?).unwrap_or_default(),
+        fallback_reason: row.get(9)?,
+    };
+    Ok((record, row.get::<_, i64>(10)? != 0))
+}
+
+/// Explain the latest model choice for a session from the stored decisions
+pub fn explain_session_model(conn: &Connection, session_id: &str) -> Result<ModelExplanation, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, domain, priority, chosen_model, confidence, created_at, reasoning,
+                    score_breakdown, alternatives, fallback_reason, manual_override
+             FROM routing_decisions WHERE session_id = ?1
+             ORDER BY created_at DESC, rowid DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let decisions = stmt
+        .query_map(params![session_id], decision_record)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    let (latest, manual_override) = decisions
+        .first()
+        .cloned()
+        .ok_or_else(|| format!("No routing decision recorded for session {}", session_id))?;
+    let model = latest.chosen_model.clone();
+
+    let (decision, summary) = if manual_override {
+        let replaced = decisions.into_iter().find(|(_, manual)| !manual).map(|(record, _)| record);
+        let summary = match &replaced {
+            Some(auto) if auto.chosen_model != model => format!(
+                "You selected {} manually, overriding auto mode's choice of {}.",
+                model, auto.chosen_model
+            ),
+            _ => format!("You selected {} manually.", model),
+        };
+        (replaced, summary)
+    } else {
+        let mut summary = format!(
+            "Auto mode chose {} for a {} task at {} priority with {:.0}% confidence.",
+            model,
+            latest.domain,
+            latest.priority,
+            latest.confidence * 100.0
+        );
+        if let Some(reason) = &latest.fallback_reason {
+            summary.push_str(&format!(" It was used as a fallback because {}.", reason));
+        }
+        (Some(latest), summary)
+    };
+
+    Ok(ModelExplanation {
+        session_id: session_id.to_string(),
+        model,
+        manual_override,
+        decision,
+        summary,
+    })
+}
+
+/// Record that the user picked a model for a session by hand
+#[tauri::command]
+pub async fn record_model_override(
+    db: State<'_, AgentDb>,
+    session_id: String,
+    model_id: String,
+) -> Result<String, String> {
+    let conn = db.lock_conn();
+    record_manual_model_choice(&conn, &session_id, &model_id)
+}
+
+/// Explain why a session is using its current model
+#[tauri::command]
+pub async fn explain_current_model(
+    db: State<'_, AgentDb>,
+    session_id: String,
+) -> Result<ModelExplanation, String> {
+    let conn = db.lock_conn();
+    explain_session_model(&conn, &session_id)
+}
+
+/// Record how a routed request turned out
+#[tauri::command]
+pub async fn record_routing_outcome(
+    db: State<'_, AgentDb>,
+    decision_id: String,
+    success: bool,
+    cost_usd: Option<f64>,
+    latency_ms: Option<i64>,
+) -> Result<(), String> {
+    let conn = db.lock_conn();
+    save_routing_outcome(&conn, &decision_id, success, cost_usd, latency_ms)
+}
+
+/// Compare routing confidence with the recorded outcomes
+#[tauri::command]
+pub async fn get_routing_accuracy_stats(
+    db: State<'_, AgentDb>,
+    days_limit: Option<i64>,
+) -> Result<RoutingAccuracyStats, String> {
+    let conn = db.lock_conn();
+    compute_routing_accuracy_stats(&conn, days_limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::intelligent_routing::{
+        analyze_task_complexity_v2, select_optimal_model_v2, AiModelBenchmark, TaskPriority,
+    };
+
+    fn decide(conn: &Connection, prompt: &str, confidence: f64, model: &str) -> String {
+        let analysis = analyze_task_complexity_v2(prompt, None);
+        let benchmarks: Vec<AiModelBenchmark> =
+            crate::commands::intelligent_routing::load_model_benchmarks(conn).unwrap();
+        let mut recommendation = select_optimal_model_v2(&analysis, &benchmarks, |_, _| true).unwrap();
+        recommendation.primary_model = model.to_string();
+        recommendation.confidence = confidence;
+        record_routing_decision(conn, None, prompt, &analysis, &recommendation).unwrap()
+    }
+
+    #[test]
+    fn test_outcomes_correlate_with_confidence() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_routing_decision_table(&conn).unwrap();
+        let confident = decide(&conn, "fix the failing login test", 0.95, "sonnet-4");
+        let also_confident = decide(&conn, "refactor the api client", 0.92, "sonnet-4");
+        let unsure = decide(&conn, "hello", 0.5, "gemini-2.5-flash");
+        decide(&conn, "still running", 0.9, "opus-4.1");
+
+        save_routing_outcome(&conn, &confident, true, Some(0.04), Some(1200)).unwrap();
+        save_routing_outcome(&conn, &also_confident, true, Some(0.02), Some(800)).unwrap();
+        save_routing_outcome(&conn, &unsure, false, None, Some(300)).unwrap();
+        assert!(save_routing_outcome(&conn, "missing", true, None, None).is_err());
+
+        let hash: String = conn
+            .query_row("SELECT input_hash FROM routing_decisions WHERE id = ?1", [&confident], |row| row.get(0))
+            .unwrap();
+        assert_eq!(hash.len(), 64);
+
+        let stats = compute_routing_accuracy_stats(&conn, Some(1)).unwrap();
+        assert_eq!(stats.total_decisions, 4);
+        assert_eq!(stats.decisions_with_outcome, 3);
+        assert!((stats.success_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert!(stats.confidence_success_correlation.unwrap() > 0.9);
+
+        assert_eq!(stats.by_confidence.len(), 2);
+        let high = &stats.by_confidence[1];
+        assert!((high.min_confidence - 0.8).abs() < 1e-9);
+        assert_eq!(high.decisions, 2);
+        assert_eq!(high.success_rate, 1.0);
+        assert!((high.average_cost_usd.unwrap() - 0.03).abs() < 1e-9);
+        assert_eq!(high.average_latency_ms, Some(1000.0));
+        assert_eq!(stats.by_confidence[0].success_rate, 0.0);
+        assert_eq!(stats.by_confidence[0].average_cost_usd, None);
+
+        assert_eq!(stats.by_model[0].model, "sonnet-4");
+        assert_eq!(stats.by_model[0].decisions, 2);
+    }
+
+    #[test]
+    fn test_session_run_outcome_lands_on_its_latest_open_decision() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_routing_decision_table(&conn).unwrap();
+        let analysis = analyze_task_complexity_v2("fix the failing login test", None);
+        let benchmarks = crate::commands::intelligent_routing::load_model_benchmarks(&conn).unwrap();
+        let recommendation = select_optimal_model_v2(&analysis, &benchmarks, |_, _| true).unwrap();
+        let first = record_routing_decision(&conn, Some("s1"), "fix it", &analysis, &recommendation).unwrap();
+        save_routing_outcome(&conn, &first, false, None, None).unwrap();
+        let second = record_routing_decision(&conn, Some("s1"), "fix it again", &analysis, &recommendation).unwrap();
+
+        assert!(save_session_outcome(&conn, "s1", true, Some(0.01), Some(900)).unwrap());
+        assert!(!save_session_outcome(&conn, "s2", true, None, None).unwrap());
+        assert!(!save_session_outcome(&conn, "s1", false, None, None).unwrap());
+
+        let outcome = |id: &str| {
+            conn.query_row("SELECT success, latency_ms FROM routing_decisions WHERE id = ?1", [id], |row| {
+                Ok((row.get::<_, Option<bool>>(0)?, row.get::<_, Option<i64>>(1)?))
+            })
+            .unwrap()
+        };
+        assert_eq!(outcome(&first), (Some(false), None));
+        assert_eq!(outcome(&second), (Some(true), Some(900)));
+    }
+
+    #[test]
+    fn test_explanation_reflects_recorded_decision() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_routing_decision_table(&conn).unwrap();
+        assert!(explain_session_model(&conn, "session-1").is_err());
+
+        // Critical tasks go to opus-4.1; with it unavailable the router has to fall back
+        let mut analysis = analyze_task_complexity_v2("Audit the authentication flow", None);
+        analysis.priority_level = TaskPriority::Critical;
+        analysis.context_requirements.context_complexity = 0.1;
+        let benchmarks = crate::commands::intelligent_routing::load_model_benchmarks(&conn).unwrap();
+        let recommendation = select_optimal_model_v2(&analysis, &benchmarks, |model, _| model != "opus-4.1").unwrap();
+        let decision_id = record_routing_decision(&conn, Some("session-1"), "prompt", &analysis, &recommendation).unwrap();
+        record_routing_decision(&conn, Some("session-2"), "other", &analysis, &recommendation).unwrap();
+
+        let explanation = explain_session_model(&conn, "session-1").unwrap();
+        assert!(!explanation.manual_override);
+        assert_eq!(explanation.model, recommendation.primary_model);
+        let decision = explanation.decision.unwrap();
+        assert_eq!(decision.id, decision_id);
+        assert_eq!(decision.domain, format!("{:?}", analysis.domain_classification));
+        assert_eq!(decision.priority, format!("{:?}", analysis.priority_level));
+        assert_eq!(decision.alternatives, recommendation.fallback_models);
+        assert_eq!(decision.score_breakdown.len(), recommendation.score_breakdown.len());
+        assert_eq!(decision.fallback_reason.as_deref(), Some("opus-4.1 is unavailable"));
+        assert!(explanation.summary.contains(&recommendation.primary_model));
+        assert!(explanation.summary.contains("fallback"));
+
+        record_manual_model_choice(&conn, "session-1", "opus-4.1").unwrap();
+        let explanation = explain_session_model(&conn, "session-1").unwrap();
+        assert!(explanation.manual_override);
+        assert_eq!(explanation.model, "opus-4.1");
+        assert_eq!(explanation.decision.unwrap().id, decision_id);
+        assert!(explanation.summary.contains("manually"));
+        // Manual choices say nothing about router accuracy
+        assert_eq!(compute_routing_accuracy_stats(&conn, None).unwrap().total_decisions, 2);
+    }
+
+    #[test]
+    fn test_runs_record_the_model_they_start_with() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_routing_decision_table(&conn).unwrap();
+
+        // A session started on a model the user picked
+        assert!(record_session_model(&conn, "session-1", "sonnet").unwrap().is_some());
+        assert_eq!(record_session_model(&conn, "session-1", "sonnet").unwrap(), None);
+        let explanation = explain_session_model(&conn, "session-1").unwrap();
+        assert_eq!((explanation.model.as_str(), explanation.manual_override), ("sonnet", true));
+        assert_eq!(explanation.summary, "You selected sonnet manually.");
+
+        // A run on the model the router chose for the session keeps the router's explanation
+        let analysis = analyze_task_complexity_v2("fix the failing login test", None);
+        let benchmarks = crate::commands::intelligent_routing::load_model_benchmarks(&conn).unwrap();
+        let recommendation = select_optimal_model_v2(&analysis, &benchmarks, |_, _| true).unwrap();
+        record_routing_decision(&conn, Some("session-2"), "fix it", &analysis, &recommendation).unwrap();
+        assert_eq!(record_session_model(&conn, "session-2", &recommendation.primary_model).unwrap(), None);
+        assert!(!explain_session_model(&conn, "session-2").unwrap().manual_override);
+
+        // Switching models mid-session is an override of the router's choice
+        assert!(record_session_model(&conn, "session-2", "opus").unwrap().is_some());
+        let explanation = explain_session_model(&conn, "session-2").unwrap();
+        assert!(explanation.manual_override);
+        assert!(explanation.summary.contains("overriding auto mode"), "{}", explanation.summary);
+    }
+}
@@ -0,0 +1,331 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use super::agents::{read_session_jsonl, AgentDb, AgentRunMetrics};
+use super::settings_diff::Setting;
+
+pub(crate) const RUN_NOTIFICATION_SETTINGS_KEY: &str = "run_notification_settings";
+
+pub(crate) const SETTINGS: &[Setting] = &[Setting::typed::<RunNotificationSettings>(RUN_NOTIFICATION_SETTINGS_KEY)];
+
+/// Longest a webhook POST may take, connection included
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunNotificationSettings {
+    /// Endpoint that receives a `RunSummary` for every notified run
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+/// What a finished run reports to its notifications
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub run_id: i64,
+    pub agent_name: String,
+    pub task: String,
+    pub status: String,
+    pub duration_ms: Option<i64>,
+    pub total_tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+}
+
+/// A finished run that asked to be notified, with where its session is kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingNotification {
+    pub summary: RunSummary,
+    pub project_path: String,
+    pub session_id: String,
+    pub webhook_url: Option<String>,
+}
+
+/// Accept https URLs, and plain http only for endpoints on this machine
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid webhook URL {}: {}", url, e))?;
+    let host = parsed.host_str().ok_or_else(|| format!("Webhook URL {} has no host", url))?;
+    let loopback = host == "localhost"
+        || host
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        "http" => Err(format!("Webhook URL {} must use https", url)),
+        scheme => Err(format!("Unsupported webhook URL scheme: {}", scheme)),
+    }
+}
+
+fn load_settings(conn: &Connection) -> RunNotificationSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![RUN_NOTIFICATION_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_settings(conn: &Connection, settings: &RunNotificationSettings) -> Result<(), String> {
+    let value = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![RUN_NOTIFICATION_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save run notification settings: {}", e))?;
+    Ok(())
+}
+
+/// The notification owed for `run_id`, if it asked for one
+///
+/// Duration comes from the run's timestamps here; tokens and cost are only
+/// known once its session file is read.
+pub fn pending_notification(conn: &Connection, run_id: i64) -> Result<Option<PendingNotification>, String> {
+    let run = conn
+        .query_row(
+            "SELECT agent_name, task, status, project_path, session_id,
+                    CAST(ROUND((julianday(completed_at) - julianday(COALESCE(process_started_at, created_at))) * 86400000) AS INTEGER)
+             FROM agent_runs WHERE id = ?1 AND notify_on_complete = 1",
+            params![run_id],
+            |row| {
+                Ok((
+                    RunSummary {
+                        run_id,
+                        agent_name: row.get(0)?,
+                        task: row.get(1)?,
+                        status: row.get(2)?,
+                        duration_ms: row.get(5)?,
+                        total_tokens: None,
+                        cost_usd: None,
+                    },
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(run.map(|(summary, project_path, session_id)| PendingNotification {
+        summary,
+        project_path,
+        session_id,
+        webhook_url: load_settings(conn).webhook_url,
+    }))
+}
+
+/// POST `summary` as JSON to `url`, failing on any non-success status
+pub async fn post_run_summary(url: &str, summary: &RunSummary) -> Result<(), String> {
+    validate_webhook_url(url)?;
+    let client = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .post(url)
+        .json(summary)
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Webhook responded with {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Fill in the run's metrics, `show` its notification and post it to the webhook
+async fn deliver_run_notification<F>(pending: PendingNotification, show: F) -> Result<(), String>
+where
+    F: FnOnce(&RunSummary),
+{
+    let PendingNotification { mut summary, project_path, session_id, webhook_url } = pending;
+    if !session_id.is_empty() {
+        if let Ok(jsonl) = read_session_jsonl(&session_id, &project_path).await {
+            let metrics = AgentRunMetrics::from_jsonl(&jsonl);
+            summary.duration_ms = metrics.duration_ms.or(summary.duration_ms);
+            summary.total_tokens = metrics.total_tokens;
+            summary.cost_usd = metrics.cost_usd;
+        }
+    }
+
+    show(&summary);
+
+    if let Some(url) = webhook_url {
+        post_run_summary(&url, &summary).await?;
+        log::info!("Posted summary of agent run {} to webhook", summary.run_id);
+    }
+    Ok(())
+}
+
+async fn send_run_notifications(app: &AppHandle, run_id: i64) -> Result<(), String> {
+    let pending = pending_notification(&*app.state::<AgentDb>().lock_conn(), run_id)?;
+    let Some(pending) = pending else {
+        return Ok(());
+    };
+
+    deliver_run_notification(pending, |summary| {
+        let shown = app
+            .notification()
+            .builder()
+            .title(format!("{} {}", summary.agent_name, summary.status))
+            .body(&summary.task)
+            .show();
+        if let Err(e) = shown {
+            log::warn!("Failed to show notification for agent run {}: {}", run_id, e);
+        }
+    })
+    .await
+}
+
+/// Notify about a run that just finished, if it asked to be
+///
+/// A run flagged `notify_on_complete` raises an OS notification and, when a webhook
+/// is configured, POSTs its summary there. That happens off the completion path, so
+/// a slow or dead endpoint never holds up the run itself.
+pub fn notify_run_finished(app: &AppHandle, run_id: i64) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = send_run_notifications(&app, run_id).await {
+            log::warn!("Failed to notify completion of agent run {}: {}", run_id, e);
+        }
+    });
+}
+
+/// Choose whether a run raises notifications when it finishes
+#[tauri::command]
+pub async fn set_run_notify_on_complete(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+    notify_on_complete: bool,
+) -> Result<(), String> {
+    let updated = db
+        .lock_conn()
+        .execute(
+            "UPDATE agent_runs SET notify_on_complete = ?1 WHERE id = ?2",
+            params![notify_on_complete, run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Agent run {} not found", run_id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_run_notification_settings(db: State<'_, AgentDb>) -> Result<RunNotificationSettings, String> {
+    Ok(load_settings(&*db.lock_conn()))
+}
+
+/// Save notification settings; an empty webhook URL turns the webhook off
+#[tauri::command]
+pub async fn set_run_notification_settings(
+    db: State<'_, AgentDb>,
+    settings: RunNotificationSettings,
+) -> Result<(), String> {
+    let webhook_url = settings
+        .webhook_url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &webhook_url {
+        validate_webhook_url(url)?;
+    }
+    save_settings(&*db.lock_conn(), &RunNotificationSettings { webhook_url })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Accept one request, answer 200 and hand back its body
+    async fn spawn_webhook_server() -> (String, tokio::sync::oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length || n == 0 {
+                        socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+                        let _ = tx.send(format!("{}\n{}", head.lines().next().unwrap_or(""), body));
+                        return;
+                    }
+                }
+            }
+        });
+        (format!("http://{}/hooks/agent-runs", addr), rx)
+    }
+
+    #[tokio::test]
+    async fn test_completion_posts_summary_to_webhook() {
+        let (url, received) = spawn_webhook_server().await;
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, agent_name TEXT NOT NULL, task TEXT NOT NULL,
+                 project_path TEXT NOT NULL, session_id TEXT NOT NULL, status TEXT NOT NULL, process_started_at TEXT,
+                 created_at TEXT NOT NULL, completed_at TEXT, notify_on_complete BOOLEAN DEFAULT 0);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO agent_runs VALUES (1, 'Refactorer', 'Split the parser', '/work/app', 'sess-1', 'completed',
+             '2025-08-01T10:00:00Z', '2025-08-01 09:59:58', '2025-08-01 10:02:30', 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO agent_runs VALUES (2, 'Reviewer', 'Review', '/work/app', '', 'completed',
+             NULL, '2025-08-01 10:00:00', '2025-08-01 10:01:00', 0)",
+            [],
+        )
+        .unwrap();
+        save_settings(&conn, &RunNotificationSettings { webhook_url: Some(url.clone()) }).unwrap();
+
+        assert_eq!(pending_notification(&conn, 2).unwrap(), None);
+        let pending = pending_notification(&conn, 1).unwrap().unwrap();
+        assert_eq!(pending.webhook_url.as_deref(), Some(url.as_str()));
+        assert_eq!(pending.session_id, "sess-1");
+
+        let mut shown = Vec::new();
+        deliver_run_notification(pending, |summary| shown.push(summary.clone())).await.unwrap();
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].agent_name, "Refactorer");
+        let request = received.await.unwrap();
+        let (request_line, body) = request.split_once('\n').unwrap();
+        assert_eq!(request_line, "POST /hooks/agent-runs HTTP/1.1");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(body).unwrap(),
+            serde_json::json!({
+                "run_id": 1,
+                "agent_name": "Refactorer",
+                "task": "Split the parser",
+                "status": "completed",
+                "duration_ms": 150000,
+                "total_tokens": null,
+                "cost_usd": null,
+            })
+        );
+
+        assert!(validate_webhook_url("https://hooks.example.com/run").is_ok());
+        assert!(validate_webhook_url("http://[::1]:9000/run").is_ok());
+        assert!(validate_webhook_url("http://hooks.example.com/run").is_err());
+        assert!(validate_webhook_url("ftp://hooks.example.com").is_err());
+        assert!(validate_webhook_url("not a url").is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use super::agents::AgentDb;
+use super::cross_model_memory::estimate_token_count;
+use super::intelligence_bridge::{Checkpoint, IntelligenceBridge};
+use super::intelligent_routing::{load_model_benchmarks, select_cheapest_model};
+
+/// Number of trailing turns copied verbatim into the compacted session
+const DEFAULT_KEEP_RECENT_TURNS: usize = 6;
+/// Upper bound on transcript text sent to the summarizer; the newest text is kept
+const SUMMARY_INPUT_CHAR_BUDGET: usize = 60_000;
+/// Length of each user request quoted in the fallback summary
+const FALLBACK_REQUEST_CHARS: usize = 200;
+/// Checkpoint name used to find compactions again when reverting
+const COMPACTION_CHECKPOINT_NAME: &str = "session_compaction";
+/// Extension of the record kept next to a compacted session, replacing its `.jsonl`
+const COMPACTION_RECORD_EXTENSION: &str = "compaction.json";
+
+/// Outcome of compacting a Claude session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCompactionResult {
+    pub original_session_id: String,
+    pub new_session_id: String,
+    /// Model that wrote the summary, or None when the extractive fallback was used
+    pub summary_model: Option<String>,
+    pub summarized_turns: usize,
+    pub kept_turns: usize,
+    pub original_tokens: i32,
+    pub compacted_tokens: i32,
+    pub tokens_saved: i32,
+    pub checkpoint_id: String,
+}
+
+/// A session transcript split at the point where verbatim turns begin
+#[derive(Debug)]
+struct CompactionSplit<'a> {
+    older: &'a [JsonValue],
+    recent: &'a [JsonValue],
+}
+
+/// Whether a JSONL entry is a conversation turn (as opposed to summaries or metadata)
+fn is_turn(entry: &JsonValue) -> bool {
+    matches!(entry["type"].as_str(), Some("user") | Some("assistant")) && entry.get("message").is_some()
+}
+
+/// Whether a turn is a prompt typed by the user rather than a tool result
+fn is_user_prompt(entry: &JsonValue) -> bool {
+    if entry["type"].as_str() != Some("user") {
+        return false;
+    }
+    match &entry["message"]["content"] {
+        JsonValue::Array(blocks) => !blocks.iter().any(|b| b["type"].as_str() == Some("tool_result")),
+        _ => true,
+    }
+}
+
+/// Flatten a turn's message content into plain text
+fn turn_text(entry: &JsonValue) -> String {
+    let content = &entry["message"]["content"];
+    let Some(blocks) = content.as_array() else {
+        return content.as_str().unwrap_or_default().to_string();
+    };
+
+    blocks
+        .iter()
+        .filter_map(|block| match block["type"].as_str() {
+            Some("text") => block["text"].as_str().map(|t| t.to_string()),
+            Some("tool_use") => Some(format!(
+                "[tool {}] {}",
+                block["name"].as_str().unwrap_or("unknown"),
+                block["input"]
+            )),
+            Some("tool_result") => Some(match &block["content"] {
+                JsonValue::String(text) => format!("[tool result] {}", text),
+                other => format!("[tool result] {}", other),
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn turns_token_count<'a>(turns: impl IntoIterator<Item = &'a JsonValue>) -> i32 {
+    turns.into_iter().map(|turn| estimate_token_count(&turn_text(turn))).sum()
+}
+
+/// Split turns so the last `keep_recent` are kept, moving the boundary back to a user prompt
+///
+/// Starting the verbatim part on a user prompt keeps tool calls paired with their results.
+/// Returns None when there is nothing older to summarize.
+fn split_turns(turns: &[JsonValue], keep_recent: usize) -> Option<CompactionSplit<'_>> {
+    let mut start = turns.len().saturating_sub(keep_recent.max(1));
+    while start > 0 && !is_user_prompt(&turns[start]) {
+        start -= 1;
+    }
+    if start == 0 {
+        return None;
+    }
+    Some(CompactionSplit { older: &turns[..start], recent: &turns[start..] })
+}
+
+/// Build the prompt asking a model to condense the older turns
+fn build_summary_prompt(older: &[JsonValue]) -> String {
+    let mut transcript = older
+        .iter()
+        .map(|turn| {
+            let speaker = if turn["type"].as_str() == Some("assistant") { "Assistant" } else { "User" };
+            format!("{}: {}", speaker, turn_text(turn))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    if transcript.len() > SUMMARY_INPUT_CHAR_BUDGET {
+        let mut cut = transcript.len() - SUMMARY_INPUT_CHAR_BUDGET;
+        while !transcript.is_char_boundary(cut) {
+            cut += 1;
+        }
+        transcript.replace_range(..cut, "[earlier turns omitted]\n");
+    }
+
+    format!(
+        "Summarize the following coding session so it can be continued without the full history.\n\
+         Keep the user's goals, decisions made, files touched, open problems and next steps.\n\
+         Be concise and use bullet points.\n\n{}",
+        transcript
+    )
+}
+
+/// Summary built without a model: the user's earlier requests, one per line
+fn fallback_summary(older: &[JsonValue]) -> String {
+    let requests: Vec<String> = older
+        .iter()
+        .filter(|turn| is_user_prompt(turn))
+        .filter_map(|turn| {
+            let text = turn_text(turn);
+            let line = text.lines().find(|l| !l.trim().is_empty())?.trim().to_string();
+            Some(format!("- {}", line.chars().take(FALLBACK_REQUEST_CHARS).collect::<String>()))
+        })
+        .collect();
+
+    format!("Earlier requests in this session:\n{}", requests.join("\n"))
+}
+
+/// Build the JSONL entries for the compacted session: a summary preamble, then the recent turns
+fn build_compacted_entries(
+    split: &CompactionSplit<'_>,
+    summary: &str,
+    original_session_id: &str,
+    new_session_id: &str,
+) -> Vec<JsonValue> {
+    let first = split.recent.first().or(split.older.first());
+    let preamble_uuid = Uuid::new_v4().to_string();
+    let preamble = json!({
+        "parentUuid": null,
+        "isSidechain": false,
+        "userType": "external",
+        "cwd": first.map(|e| e["cwd"].clone()).unwrap_or(JsonValue::Null),
+        "sessionId": new_session_id,
+        "version": first.map(|e| e["version"].clone()).unwrap_or(JsonValue::Null),
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": format!(
+                "This session continues session {} which was compacted. \
+                 Summary of the {} earlier turns:\n\n{}\n\nThe most recent turns follow verbatim.",
+                original_session_id,
+                split.older.len(),
+                summary.trim()
+            ),
+        },
+        "isCompactSummary": true,
+        "uuid": preamble_uuid,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+
+    let mut entries = vec![preamble];
+    for (index, turn) in split.recent.iter().enumerate() {
+        let mut turn = turn.clone();
+        turn["sessionId"] = json!(new_session_id);
+        if index == 0 {
+            turn["parentUuid"] = json!(preamble_uuid);
+        }
+        entries.push(turn);
+    }
+    entries
+}
+
+/// Locate a session's JSONL file under ~/.claude/projects
+fn find_session_file(projects_dir: &Path, session_id: &str) -> Option<PathBuf> {
+    std::fs::read_dir(projects_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join(format!("{}.jsonl", session_id)))
+        .find(|path| path.is_file())
+}
+
+/// Where the compaction that produced `session_file` is recorded
+///
+/// The record is what a revert works from; the checkpoint on the intelligence
+/// bridge only lives in memory.
+fn compaction_record_path(session_file: &Path) -> PathBuf {
+    session_file.with_extension(COMPACTION_RECORD_EXTENSION)
+}
+
+/// Undo the compaction recorded in `record`, returning the original session ID
+///
+/// The compacted session and its record are deleted; the original is never touched.
+async fn revert_compaction(record: &JsonValue, record_path: &Path) -> Result<String, String> {
+    let original_session_id = record["original_session_id"].as_str()
+        .ok_or("Compaction record is missing the original session")?
+        .to_string();
+    let original_file = record["original_file"].as_str().map(PathBuf::from);
+    if !original_file.is_some_and(|path| path.is_file()) {
+        return Err(format!("Original session '{}' no longer exists", original_session_id));
+    }
+
+    if let Some(compacted_file) = record["compacted_file"].as_str() {
+        tokio::fs::remove_file(compacted_file).await
+            .map_err(|e| format!("Failed to remove compacted session: {}", e))?;
+    }
+    if let Err(e) = tokio::fs::remove_file(record_path).await {
+        log::warn!("Failed to remove compaction record {}: {}", record_path.display(), e);
+    }
+    Ok(original_session_id)
+}
+
+fn claude_projects_dir() -> Result<PathBuf, String> {
+    Ok(super::claude_dir::claude_dir_path()?.join("projects"))
+}
+
+/// Summarize with the cheapest available Gemini or local Ollama model
+async fn summarize_with_cheap_model(app: &AppHandle, prompt: &str) -> Result<(String, String), String> {
+    let providers = super::simple_model_validator::detect_available_providers(app).await;
+    let installed_ollama: Vec<String> = if providers.is_available("ollama") {
+        super::ollama::get_ollama_models().await
+            .map(|models| models.into_iter().map(|m| m.name).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    let (benchmarks, gemini_settings) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        let gemini_settings = super::gemini::get_gemini_api_key_sync(&conn).ok().map(|key| {
+            let config = super::gemini::load_gemini_config_sync(&conn).unwrap_or_default();
+            let timeouts = super::provider_timeouts::load_provider_timeouts(&conn).gemini;
+            (key, config, timeouts)
+        });
+        (load_model_benchmarks(&conn)?, gemini_settings)
+    };
+
+    let model = select_cheapest_model(&benchmarks, |benchmark| match benchmark.provider.as_str() {
+        "gemini" => gemini_settings.is_some() && providers.is_available("gemini"),
+        "ollama" => installed_ollama.contains(&benchmark.model_id),
+        _ => false,
+    })
+    .ok_or("No Gemini or Ollama model is available for summarization")?;
+
+    let summary = match (model.provider.as_str(), &gemini_settings) {
+        ("gemini", Some((key, config, timeouts))) => {
+            let hooks = super::request_hooks::request_hooks(app);
+            super::gemini::generate_gemini_text(key, config, timeouts, &hooks, &model.model_id, prompt).await?
+        }
+        _ => super::ollama::generate_ollama_text(app, &model.model_id, prompt).await?,
+    };
+    Ok((summary, model.model_id.clone()))
+}
+
+/// Compact a Claude session into a new resumable session
+///
+/// Older turns are replaced by a summary from a cheap model and the most recent turns are
+/// copied verbatim. The original session file is left untouched and the compaction is
+/// recorded next to the new session, and as a checkpoint on it, so it can be reverted.
+#[tauri::command]
+pub async fn compact_claude_session(
+    app: AppHandle,
+    bridge: State<'_, IntelligenceBridge>,
+    session_id: String,
+    keep_recent_turns: Option<usize>,
+) -> Result<SessionCompactionResult, String> {
+    let session_file = find_session_file(&claude_projects_dir()?, &session_id)
+        .ok_or_else(|| format!("Session '{}' not found", session_id))?;
+    let content = tokio::fs::read_to_string(&session_file).await
+        .map_err(|e| format!("Failed to read session file: {}", e))?;
+
+    let turns: Vec<JsonValue> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter(is_turn)
+        .collect();
+    let split = split_turns(&turns, keep_recent_turns.unwrap_or(DEFAULT_KEEP_RECENT_TURNS))
+        .ok_or("Session is too short to compact")?;
+
+    let (summary, summary_model) = match summarize_with_cheap_model(&app, &build_summary_prompt(split.older)).await {
+        Ok((summary, model)) => (summary, Some(model)),
+        Err(e) => {
+            log::warn!("Falling back to extractive summary for session {}: {}", session_id, e);
+            (fallback_summary(split.older), None)
+        }
+    };
+
+    let new_session_id = Uuid::new_v4().to_string();
+    let entries = build_compacted_entries(&split, &summary, &session_id, &new_session_id);
+    let mut jsonl = String::new();
+    for entry in &entries {
+        jsonl.push_str(&serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize session entry: {}", e))?);
+        jsonl.push('\n');
+    }
+    let new_file = session_file.with_file_name(format!("{}.jsonl", new_session_id));
+    tokio::fs::write(&new_file, jsonl).await
+        .map_err(|e| format!("Failed to write compacted session: {}", e))?;
+
+    let original_tokens = turns_token_count(&turns);
+    let compacted_tokens = turns_token_count(&entries);
+
+    // Carry task continuity over to the new session and record the compaction on it
+    let project_id = session_file.parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown-project")
+        .to_string();
+    let model = summary_model.clone().unwrap_or_else(|| "claude".to_string());
+    if bridge.get_context(&session_id).is_some() {
+        bridge.transfer_context(&session_id, &new_session_id, &model)
+    } else {
+        bridge.create_context(&new_session_id, &project_id, &model)
+    }
+    .map_err(|e| format!("Failed to carry over session context: {}", e))?;
+
+    let checkpoint_id = Uuid::new_v4().to_string();
+    let record = json!({
+        "checkpoint_id": checkpoint_id,
+        "original_session_id": session_id,
+        "original_file": session_file,
+        "compacted_file": new_file,
+        "summarized_turns": split.older.len(),
+        "kept_turns": split.recent.len(),
+    });
+    tokio::fs::write(compaction_record_path(&new_file), record.to_string()).await
+        .map_err(|e| format!("Failed to record compaction: {}", e))?;
+    bridge.add_checkpoint(&new_session_id, Checkpoint {
+        id: checkpoint_id.clone(),
+        name: COMPACTION_CHECKPOINT_NAME.to_string(),
+        state: record,
+        can_resume_from: true,
+        timestamp: Utc::now(),
+        created_by: model,
+    }).map_err(|e| format!("Failed to record compaction checkpoint: {}", e))?;
+
+    log::info!(
+        "Compacted session {} into {} ({} -> {} tokens)",
+        session_id, new_session_id, original_tokens, compacted_tokens
+    );
+
+    Ok(SessionCompactionResult {
+        original_session_id: session_id,
+        new_session_id,
+        summary_model,
+        summarized_turns: split.older.len(),
+        kept_turns: split.recent.len(),
+        original_tokens,
+        compacted_tokens,
+        tokens_saved: (original_tokens - compacted_tokens).max(0),
+        checkpoint_id,
+    })
+}
+
+/// Undo a compaction by deleting the compacted session, returning the original session ID
+#[tauri::command]
+pub async fn revert_claude_session_compaction(session_id: String) -> Result<String, String> {
+    let record_path = find_session_file(&claude_projects_dir()?, &session_id)
+        .map(|session_file| compaction_record_path(&session_file))
+        .filter(|path| path.is_file())
+        .ok_or_else(|| format!("Session '{}' was not created by a compaction", session_id))?;
+    let record: JsonValue = serde_json::from_str(
+        &tokio::fs::read_to_string(&record_path).await
+            .map_err(|e| format!("Failed to read compaction record: {}", e))?,
+    ).map_err(|e| format!("Compaction record is unreadable: {}", e))?;
+    revert_compaction(&record, &record_path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> JsonValue {
+        json!({
+            "type": "user",
+            "sessionId": "orig",
+            "uuid": Uuid::new_v4().to_string(),
+            "cwd": "/tmp/project",
+            "message": { "role": "user", "content": text },
+        })
+    }
+
+    fn assistant(text: &str) -> JsonValue {
+        json!({
+            "type": "assistant",
+            "sessionId": "orig",
+            "uuid": Uuid::new_v4().to_string(),
+            "message": { "role": "assistant", "content": [{ "type": "text", "text": text }] },
+        })
+    }
+
+    fn tool_result(text: &str) -> JsonValue {
+        json!({
+            "type": "user",
+            "sessionId": "orig",
+            "uuid": Uuid::new_v4().to_string(),
+            "message": { "role": "user", "content": [{ "type": "tool_result", "content": text }] },
+        })
+    }
+
+    #[test]
+    fn test_compaction_reduces_tokens_and_keeps_recent_turns_verbatim() {
+        let mut turns = Vec::new();
+        for i in 0..10 {
+            turns.push(user(&format!("request {} {}", i, "details ".repeat(50))));
+            turns.push(assistant(&format!("answer {} {}", i, "explanation ".repeat(100))));
+        }
+        // The last exchange includes a tool call that must stay paired with its result
+        turns.push(user("run the tests"));
+        turns.push(assistant("running"));
+        turns.push(tool_result("all tests passed"));
+        turns.push(assistant("done"));
+
+        let split = split_turns(&turns, 3).unwrap();
+        assert!(is_user_prompt(&split.recent[0]));
+        assert_eq!(split.recent.len(), 4);
+
+        let summary = fallback_summary(split.older);
+        assert!(summary.contains("- request 0"));
+
+        let entries = build_compacted_entries(&split, &summary, "orig", "new");
+        assert_eq!(entries[0]["isCompactSummary"], json!(true));
+        assert_eq!(entries[1]["parentUuid"], entries[0]["uuid"]);
+        for (kept, original) in entries[1..].iter().zip(split.recent) {
+            assert_eq!(kept["message"], original["message"]);
+            assert_eq!(kept["uuid"], original["uuid"]);
+            assert_eq!(kept["sessionId"], json!("new"));
+        }
+
+        let original_tokens = turns_token_count(&turns);
+        let compacted_tokens = turns_token_count(&entries);
+        assert!(compacted_tokens < original_tokens / 2);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_compaction_reverts_without_the_bridge() {
+        let project = tempfile::tempdir().unwrap();
+        let original = project.path().join("orig.jsonl");
+        let compacted = project.path().join("new.jsonl");
+        std::fs::write(&original, "{}\n").unwrap();
+        std::fs::write(&compacted, "{}\n").unwrap();
+        let record_path = compaction_record_path(&compacted);
+        assert_eq!(record_path.file_name().unwrap(), "new.compaction.json");
+        let record = json!({
+            "original_session_id": "orig",
+            "original_file": original,
+            "compacted_file": compacted,
+        });
+        std::fs::write(&record_path, record.to_string()).unwrap();
+
+        assert_eq!(revert_compaction(&record, &record_path).await.unwrap(), "orig");
+        assert!(original.is_file());
+        assert!(!compacted.exists() && !record_path.exists());
+    }
+
+    #[test]
+    fn test_short_session_is_not_compacted() {
+        let turns = vec![user("hello"), assistant("hi")];
+        assert!(split_turns(&turns, 6).is_none());
+    }
+}
@@ -6,6 +6,23 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::State;
 use log;
 
+/// How repeated content is treated in a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeduplicationMode {
+    /// Drop repeated content, as sessions always have
+    #[default]
+    Strict,
+    /// Drop repeated content only when it repeats within `LENIENT_WINDOW_MS`,
+    /// so a question asked twice on purpose goes through
+    Lenient,
+    /// Never drop anything
+    Off,
+}
+
+/// Repeats further apart than this are intentional in lenient mode
+pub const LENIENT_WINDOW_MS: u64 = 2_000;
+
 /// Message deduplication manager
 pub struct MessageDeduplicationManager {
     /// Track message IDs by session to prevent duplicates
@@ -14,6 +31,8 @@ pub struct MessageDeduplicationManager {
     message_hashes: Mutex<HashMap<String, u64>>,
     /// Track last message timestamp by session
     last_message_time: Mutex<HashMap<String, u64>>,
+    /// Mode chosen at session creation; sessions without one are strict
+    session_modes: Mutex<HashMap<String, DeduplicationMode>>,
 }
 
 impl MessageDeduplicationManager {
@@ -22,20 +41,39 @@ impl MessageDeduplicationManager {
             session_messages: Mutex::new(HashMap::new()),
             message_hashes: Mutex::new(HashMap::new()),
             last_message_time: Mutex::new(HashMap::new()),
+            session_modes: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Set how repeated content is treated in a session
+    pub fn set_session_mode(&self, session_id: &str, mode: DeduplicationMode) {
+        self.session_modes.lock().unwrap().insert(session_id.to_string(), mode);
+    }
+
+    /// Deduplication mode of a session
+    pub fn session_mode(&self, session_id: &str) -> DeduplicationMode {
+        self.session_modes.lock().unwrap().get(session_id).copied().unwrap_or_default()
+    }
+
     /// Check if a message is a duplicate
     pub fn is_duplicate(&self, session_id: &str, message_id: &str, content: &str) -> bool {
-        let mut session_messages = self.session_messages.lock().unwrap();
-        let mut message_hashes = self.message_hashes.lock().unwrap();
-        let mut last_time = self.last_message_time.lock().unwrap();
-        
-        // Get current timestamp
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64;
+        self.is_duplicate_at(session_id, message_id, content, current_time)
+    }
+
+    /// `is_duplicate` at a given time in milliseconds since the epoch
+    pub(crate) fn is_duplicate_at(&self, session_id: &str, message_id: &str, content: &str, current_time: u64) -> bool {
+        let mode = self.session_mode(session_id);
+        if mode == DeduplicationMode::Off {
+            return false;
+        }
+
+        let mut session_messages = self.session_messages.lock().unwrap();
+        let mut message_hashes = self.message_hashes.lock().unwrap();
+        let mut last_time = self.last_message_time.lock().unwrap();
         
         // Check if this exact message ID was already seen
         let session_set = session_messages.entry(session_id.to_string()).or_insert_with(HashSet::new);
@@ -46,22 +84,31 @@ impl MessageDeduplicationManager {
         
         // Calculate content hash
         let content_hash = calculate_hash(content);
+        let hash_key = format!("{}:{}", session_id, content_hash);
         
-        // Check if we've seen this exact content recently (within 100ms)
-        if let Some(&last_timestamp) = last_time.get(session_id) {
-            if current_time - last_timestamp < 100 {
-                // Check content hash
-                let hash_key = format!("{}:{}", session_id, content_hash);
-                if message_hashes.contains_key(&hash_key) {
-                    log::warn!("Duplicate content detected within 100ms for session: {}", session_id);
-                    return true;
+        match mode {
+            DeduplicationMode::Strict => {
+                // Check if we've seen this exact content recently (within 100ms)
+                if let Some(&last_timestamp) = last_time.get(session_id) {
+                    if current_time.saturating_sub(last_timestamp) < 100 && message_hashes.contains_key(&hash_key) {
+                        log::warn!("Duplicate content detected within 100ms for session: {}", session_id);
+                        return true;
+                    }
+                }
+            }
+            DeduplicationMode::Lenient => {
+                if let Some(&seen_at) = message_hashes.get(&hash_key) {
+                    if current_time.saturating_sub(seen_at) < LENIENT_WINDOW_MS {
+                        log::warn!("Duplicate content detected within {}ms for session: {}", LENIENT_WINDOW_MS, session_id);
+                        return true;
+                    }
                 }
             }
+            DeduplicationMode::Off => {}
         }
         
         // Not a duplicate, record it
         session_set.insert(message_id.to_string());
-        let hash_key = format!("{}:{}", session_id, content_hash);
         message_hashes.insert(hash_key, current_time);
         last_time.insert(session_id.to_string(), current_time);
         
@@ -83,6 +130,7 @@ impl MessageDeduplicationManager {
         
         session_messages.remove(session_id);
         last_time.remove(session_id);
+        self.session_modes.lock().unwrap().remove(session_id);
         
         // Remove hashes for this session
         let prefix = format!("{}:", session_id);
@@ -91,6 +139,15 @@ impl MessageDeduplicationManager {
         log::info!("Cleared deduplication data for session: {}", session_id);
     }
     
+    /// Whether any deduplication data is kept for a session
+    pub fn has_session_data(&self, session_id: &str) -> bool {
+        let prefix = format!("{}:", session_id);
+        self.session_messages.lock().unwrap().contains_key(session_id)
+            || self.last_message_time.lock().unwrap().contains_key(session_id)
+            || self.session_modes.lock().unwrap().contains_key(session_id)
+            || self.message_hashes.lock().unwrap().keys().any(|key| key.starts_with(&prefix))
+    }
+    
     /// Clean up old sessions (older than 1 hour)
     pub fn cleanup_old_sessions(&self) {
         let current_time = SystemTime::now()
@@ -101,6 +158,7 @@ impl MessageDeduplicationManager {
         let mut last_time = self.last_message_time.lock().unwrap();
         let mut session_messages = self.session_messages.lock().unwrap();
         let mut message_hashes = self.message_hashes.lock().unwrap();
+        let mut session_modes = self.session_modes.lock().unwrap();
         
         let one_hour_ms = 3600000u64; // 1 hour in milliseconds
         let mut sessions_to_remove = Vec::new();
@@ -114,6 +172,7 @@ impl MessageDeduplicationManager {
         for session_id in sessions_to_remove {
             last_time.remove(&session_id);
             session_messages.remove(&session_id);
+            session_modes.remove(&session_id);
             
             // Remove hashes for this session
             let prefix = format!("{}:", session_id);
@@ -257,14 +316,17 @@ pub async fn clear_session_deduplication(
     Ok(())
 }
 
-/// Create an isolated session
+/// Create an isolated session, optionally choosing how repeated content is deduplicated
 #[tauri::command]
 pub async fn create_isolated_session(
     session_id: String,
     project_id: String,
     model: String,
+    dedup_mode: Option<DeduplicationMode>,
     isolation_manager: State<'_, SessionIsolationManager>,
+    dedup_manager: State<'_, MessageDeduplicationManager>,
 ) -> Result<SessionIsolationState, String> {
+    dedup_manager.set_session_mode(&session_id, dedup_mode.unwrap_or_default());
     Ok(isolation_manager.create_isolated_session(session_id, project_id, model))
 }
 
@@ -294,4 +356,33 @@ pub async fn cleanup_old_sessions(
 ) -> Result<(), String> {
     dedup_manager.cleanup_old_sessions();
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deduplication_modes() {
+        let dedup_manager = MessageDeduplicationManager::new();
+        let content = "What does this function do?";
+        let start = 1_000_000;
+
+        // Strict (the default): repeated content right after the last message is dropped
+        assert_eq!(dedup_manager.session_mode("strict"), DeduplicationMode::Strict);
+        assert!(!dedup_manager.is_duplicate_at("strict", "m1", content, start));
+        assert!(dedup_manager.is_duplicate_at("strict", "m2", content, start + 50));
+
+        // Lenient: the same content within the window is dropped, later it is a new question
+        dedup_manager.set_session_mode("lenient", DeduplicationMode::Lenient);
+        assert!(!dedup_manager.is_duplicate_at("lenient", "m1", content, start));
+        assert!(dedup_manager.is_duplicate_at("lenient", "m2", content, start + 50));
+        assert!(!dedup_manager.is_duplicate_at("lenient", "m3", content, start + LENIENT_WINDOW_MS + 1));
+        // A repeated message id is still a redelivery
+        assert!(dedup_manager.is_duplicate_at("lenient", "m3", "other", start + 3 * LENIENT_WINDOW_MS));
+
+        // Off: nothing is dropped, not even a repeated message id
+        dedup_manager.set_session_mode("off", DeduplicationMode::Off);
+        assert!(!dedup_manager.is_duplicate_at("off", "m1", content, start));
+        assert!(!dedup_manager.is_duplicate_at("off", "m1", content, start + 10));
+    }
+}
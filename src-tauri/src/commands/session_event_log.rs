@@ -0,0 +1,311 @@
+use chrono::Utc;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+/// Oldest events are dropped past this many per session
+const MAX_EVENTS_PER_SESSION: usize = 5_000;
+
+/// Oldest events are also dropped once a session's payloads exceed this many bytes
+const MAX_BYTES_PER_SESSION: usize = 8 * 1024 * 1024;
+
+/// Least recently active session logs are dropped past this many sessions
+const MAX_SESSIONS: usize = 32;
+
+const RECORDED_EVENT_PREFIXES: [&str; 4] = ["claude-output:", "claude-error:", "claude-complete:", "claude-blocked:"];
+
+/// Trailing words of setting, field, header and variable names whose values are credentials
+const SECRET_NAME_ENDINGS: &[&[&str]] = &[
+    &["api", "key"], &["apikey"], &["access", "key"], &["private", "key"], &["secret", "key"],
+    &["token"], &["secret"], &["password"], &["passwd"], &["credential"], &["credentials"],
+    &["authorization"],
+];
+
+lazy_static::lazy_static! {
+    static ref SESSION_EVENT_LOGS: Mutex<HashMap<String, SessionEventLog>> = Mutex::new(HashMap::new());
+
+    static ref SECRET_PATTERNS: Vec<Regex> = [
+        r"sk-ant-[A-Za-z0-9_\-]{10,}",
+        r"sk-[A-Za-z0-9_\-]{20,}",
+        r"AIza[0-9A-Za-z_\-]{30,}",
+        r"gh[pousr]_[A-Za-z0-9]{30,}",
+        r"(?i)bearer\s+[A-Za-z0-9._\-]{16,}",
+        r#"(?i)((?:api[_-]?key|token|secret|password)["']?\s*[:=]\s*["']?)[^\s"',}]{8,}"#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).expect("secret pattern is valid"))
+    .collect();
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RecordedEvent {
+    seq: u64,
+    /// Milliseconds since the Unix epoch
+    timestamp: i64,
+    event: String,
+    payload: Value,
+    /// Serialized size of the event name and payload
+    #[serde(skip)]
+    bytes: usize,
+}
+
+/// Events emitted for one session, bounded so the log can be exported for support
+#[derive(Debug, Default)]
+struct SessionEventLog {
+    events: VecDeque<RecordedEvent>,
+    bytes: usize,
+    next_seq: u64,
+    dropped: u64,
+    last_recorded: i64,
+}
+
+impl SessionEventLog {
+    fn push(&mut self, event: &str, payload: Value, timestamp: i64) {
+        let bytes = event.len() + payload.to_string().len();
+        // The newest event is always kept, even when it alone is over the byte cap
+        while !self.events.is_empty()
+            && (self.events.len() >= MAX_EVENTS_PER_SESSION || self.bytes + bytes > MAX_BYTES_PER_SESSION)
+        {
+            if let Some(oldest) = self.events.pop_front() {
+                self.bytes -= oldest.bytes;
+            }
+            self.dropped += 1;
+        }
+        self.bytes += bytes;
+        self.events.push_back(RecordedEvent {
+            seq: self.next_seq,
+            timestamp,
+            event: event.to_string(),
+            payload,
+            bytes,
+        });
+        self.next_seq += 1;
+        self.last_recorded = timestamp;
+    }
+}
+
+/// Session id of a recorded event name, e.g. `claude-output:abc` -> `abc`
+fn recorded_session(event: &str) -> Option<&str> {
+    RECORDED_EVENT_PREFIXES
+        .iter()
+        .find_map(|prefix| event.strip_prefix(prefix))
+        .filter(|session_id| !session_id.is_empty())
+}
+
+/// Record a session-scoped event without emitting it
+pub fn record_session_event<S: Serialize>(event: &str, payload: &S) {
+    let Some(session_id) = recorded_session(event) else {
+        return;
+    };
+    let payload = serde_json::to_value(payload).unwrap_or(Value::Null);
+    let timestamp = Utc::now().timestamp_millis();
+
+    let Ok(mut logs) = SESSION_EVENT_LOGS.lock() else {
+        return;
+    };
+    if !logs.contains_key(session_id) && logs.len() >= MAX_SESSIONS {
+        let stalest = logs
+            .iter()
+            .min_by_key(|(_, log)| log.last_recorded)
+            .map(|(id, _)| id.clone());
+        if let Some(stalest) = stalest {
+            logs.remove(&stalest);
+        }
+    }
+    logs.entry(session_id.to_string())
+        .or_default()
+        .push(event, payload, timestamp);
+}
+
+/// Emit an event to the frontend and record it in the session's event log
+///
+/// Session-scoped `claude-output`, `claude-error`, `claude-complete` and `claude-blocked`
+/// events are recorded so a session can be replayed exactly as the frontend received it.
+pub fn emit_session_event<S: Serialize + Clone>(
+    app: &AppHandle,
+    event: &str,
+    payload: S,
+) -> tauri::Result<()> {
+    record_session_event(event, &payload);
+    app.emit(event, payload)
+}
+
+pub(crate) fn mask_secrets(text: &str) -> String {
+    SECRET_PATTERNS.iter().fold(text.to_string(), |masked, pattern| {
+        pattern
+            .replace_all(&masked, |caps: &regex::Captures| match caps.get(1) {
+                // Keep the `api_key=` part so the masked line still reads sensibly
+                Some(label) => format!("{}[REDACTED]", label.as_str()),
+                None => "[REDACTED]".to_string(),
+            })
+            .into_owned()
+    })
+}
+
+/// Whether a setting, field, header or environment variable with this name holds a credential
+///
+/// Names are compared by their last words in any case or separator style, so
+/// `GITHUB_TOKEN`, `x-api-key` and `apiKey` are secrets while `max_tokens` is not.
+pub(crate) fn is_secret_name(name: &str) -> bool {
+    let mut words = vec![String::new()];
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        let camel_case_boundary = c.is_uppercase() && previous.is_some_and(char::is_lowercase);
+        if !c.is_alphanumeric() || camel_case_boundary {
+            words.push(String::new());
+        }
+        if let Some(word) = words.last_mut().filter(|_| c.is_alphanumeric()) {
+            word.extend(c.to_lowercase());
+        }
+        previous = Some(c);
+    }
+    words.retain(|word| !word.is_empty());
+    SECRET_NAME_ENDINGS
+        .iter()
+        .any(|ending| words.len() >= ending.len() && words[words.len() - ending.len()..] == **ending)
+}
+
+pub(crate) fn mask_value(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(mask_secrets(text)),
+        Value::Array(items) => Value::Array(items.iter().map(mask_value).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), mask_value(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Header line followed by one masked event per line, oldest first
+fn export_ndjson(session_id: &str, log: &SessionEventLog) -> String {
+    let header = json!({
+        "type": "session_event_log",
+        "session_id": session_id,
+        "exported_at": Utc::now().to_rfc3339(),
+        "event_count": log.events.len(),
+        "truncated": log.dropped > 0,
+        "dropped_events": log.dropped,
+        "max_events": MAX_EVENTS_PER_SESSION,
+        "max_bytes": MAX_BYTES_PER_SESSION,
+    });
+
+    let mut lines = vec![header.to_string()];
+    for event in &log.events {
+        let masked = RecordedEvent {
+            payload: mask_value(&event.payload),
+            ..event.clone()
+        };
+        lines.push(serde_json::to_string(&masked).unwrap_or_default());
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Export every recorded event of a session as an NDJSON bundle
+#[tauri::command]
+pub async fn export_session_event_log(session_id: String) -> Result<String, String> {
+    let logs = SESSION_EVENT_LOGS
+        .lock()
+        .map_err(|e| format!("Failed to lock session event logs: {}", e))?;
+    let log = logs
+        .get(&session_id)
+        .ok_or_else(|| format!("No events recorded for session {}", session_id))?;
+    Ok(export_ndjson(&session_id, log))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bundle: &str) -> Vec<Value> {
+        bundle
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_recorded_events_round_trip_in_order() {
+        let sid = "event-log-roundtrip";
+        record_session_event(&format!("claude-output:{}", sid), &r#"{"type":"system"}"#);
+        record_session_event(&format!("claude-error:{}", sid), &"warning: slow network");
+        record_session_event(
+            &format!("claude-output:{}", sid),
+            &"using key sk-ant-REDACTED",
+        );
+        record_session_event(&format!("claude-complete:{}", sid), &true);
+        // Unscoped and unrelated events are not recorded
+        record_session_event("claude-output", &"generic");
+        record_session_event(&format!("claude-progress:{}", sid), &"tick");
+
+        let lines = parse(&export_session_event_log(sid.to_string()).await.unwrap());
+        assert_eq!(lines[0]["session_id"], sid);
+        assert_eq!(lines[0]["event_count"], 4);
+        assert_eq!(lines[0]["truncated"], false);
+
+        let events: Vec<&str> = lines[1..].iter().map(|l| l["event"].as_str().unwrap()).collect();
+        assert_eq!(
+            events,
+            vec![
+                format!("claude-output:{}", sid),
+                format!("claude-error:{}", sid),
+                format!("claude-output:{}", sid),
+                format!("claude-complete:{}", sid),
+            ]
+        );
+        let seqs: Vec<u64> = lines[1..].iter().map(|l| l["seq"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, vec![0, 1, 2, 3]);
+        assert_eq!(lines[1]["payload"], r#"{"type":"system"}"#);
+        assert_eq!(lines[3]["payload"], "using key [REDACTED]");
+        assert_eq!(lines[4]["payload"], true);
+
+        assert!(export_session_event_log("never-recorded".to_string()).await.is_err());
+    }
+
+    #[test]
+    fn test_buffer_is_bounded_and_notes_truncation() {
+        let mut log = SessionEventLog::default();
+        for i in 0..MAX_EVENTS_PER_SESSION + 3 {
+            log.push("claude-output:s", json!(i), 0);
+        }
+
+        let lines = parse(&export_ndjson("s", &log));
+        assert_eq!(lines.len(), MAX_EVENTS_PER_SESSION + 1);
+        assert_eq!(lines[0]["truncated"], true);
+        assert_eq!(lines[0]["dropped_events"], 3);
+        assert_eq!(lines[1]["seq"], 3);
+        assert_eq!(mask_secrets(r#""api_key": "abcd1234efgh""#), r#""api_key": "[REDACTED]""#);
+    }
+
+    #[test]
+    fn test_buffer_is_bounded_by_bytes() {
+        let mut log = SessionEventLog::default();
+        let chunk = "x".repeat(MAX_BYTES_PER_SESSION / 4);
+        for _ in 0..6 {
+            log.push("claude-output:s", json!(chunk), 0);
+        }
+        assert_eq!(log.events.len(), 3);
+        assert_eq!(log.dropped, 3);
+        assert!(log.bytes <= MAX_BYTES_PER_SESSION);
+        assert_eq!(log.events.front().unwrap().seq, 3);
+
+        // A single oversized event still replaces everything before it
+        log.push("claude-output:s", json!("y".repeat(MAX_BYTES_PER_SESSION)), 0);
+        assert_eq!(log.events.len(), 1);
+        assert_eq!(log.dropped, 6);
+    }
+
+    #[test]
+    fn test_secret_names_match_by_their_last_words() {
+        for name in ["GITHUB_TOKEN", "gemini_api_key", "x-api-key", "apiKey", "AWS_SECRET_ACCESS_KEY", "Authorization", "project_env_secret"] {
+            assert!(is_secret_name(name), "{}", name);
+        }
+        for name in ["max_tokens", "key", "sort_key", "MONKEY", "secret_count", "DEBUG"] {
+            assert!(!is_secret_name(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use super::execution_control::{ExecutionControlState, ExecutionState, ExecutionStatus};
+use super::gemini::GeminiSessionRegistry;
+use super::session_deduplication::{DeduplicationMode, MessageDeduplicationManager, SessionIsolationManager};
+
+/// The managers that keep state for a running session
+///
+/// They are registered and released together so that every way an execution ends,
+/// whether it completes, fails or is stopped, leaves none of them holding state.
+pub struct SessionManagers<'a> {
+    pub registry: &'a GeminiSessionRegistry,
+    pub dedup: &'a MessageDeduplicationManager,
+    pub isolation: &'a SessionIsolationManager,
+    pub execution: &'a ExecutionControlState,
+}
+
+impl SessionManagers<'_> {
+    /// Register a session with all four managers, marking it executing
+    pub async fn register(
+        &self,
+        session_id: &str,
+        project_id: &str,
+        model: &str,
+        dedup_mode: DeduplicationMode,
+    ) -> Result<(), String> {
+        self.registry.register_session_with_mode(session_id, project_id, model, dedup_mode)?;
+        self.dedup.set_session_mode(session_id, dedup_mode);
+        self.isolation.create_isolated_session(session_id.to_string(), project_id.to_string(), model.to_string());
+        self.execution.sessions.lock().await.insert(
+            session_id.to_string(),
+            ExecutionState {
+                session_id: session_id.to_string(),
+                status: ExecutionStatus::Executing,
+                can_continue: false,
+                checkpoint_data: None,
+                elapsed_time: 0,
+                total_tokens: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Drop everything the managers hold for a session
+    pub async fn release(&self, session_id: &str) {
+        self.dedup.clear_session(session_id);
+        self.registry.unregister_session(session_id);
+        self.isolation.cleanup_session(session_id);
+        self.execution.sessions.lock().await.remove(session_id);
+        self.execution.active_processes.lock().await.remove(session_id);
+    }
+
+    /// Names of the managers still holding state for a session
+    pub async fn residual_state(&self, session_id: &str) -> Vec<&'static str> {
+        let mut residual = Vec::new();
+        if self.registry.validate_session(session_id).is_ok() {
+            residual.push("session registry");
+        }
+        if self.dedup.has_session_data(session_id) {
+            residual.push("deduplication");
+        }
+        if self.isolation.get_session_state(session_id).is_some() {
+            residual.push("isolation");
+        }
+        if self.execution.sessions.lock().await.contains_key(session_id)
+            || self.execution.active_processes.lock().await.contains_key(session_id)
+        {
+            residual.push("execution control");
+        }
+        residual
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::execution_control::halt_execution;
+
+    #[tokio::test]
+    async fn test_cancelled_execution_leaves_no_session_state() {
+        let registry = GeminiSessionRegistry::new();
+        let dedup = MessageDeduplicationManager::new();
+        let isolation = SessionIsolationManager::new();
+        let execution = ExecutionControlState::default();
+        let managers = SessionManagers { registry: &registry, dedup: &dedup, isolation: &isolation, execution: &execution };
+
+        managers.register("gemini-a", "app", "gemini-2.5-flash", DeduplicationMode::Lenient).await.unwrap();
+        managers.register("gemini-b", "app", "gemini-2.5-flash", DeduplicationMode::Strict).await.unwrap();
+        assert_eq!(
+            managers.residual_state("gemini-a").await,
+            vec!["session registry", "deduplication", "isolation", "execution control"]
+        );
+
+        // Execute: the response passes both deduplication checks
+        assert!(!registry.is_duplicate_message("gemini-a", "Partial answer").unwrap());
+        assert!(!dedup.is_duplicate("gemini-a", "gemini-a", "gemini-response-Partial answer"));
+
+        // Cancel mid-run, then the execution winds down and releases the session
+        let stopped = halt_execution(&execution, "gemini-a").await;
+        assert_eq!(stopped.status, ExecutionStatus::Stopped);
+        managers.release("gemini-a").await;
+
+        assert!(managers.residual_state("gemini-a").await.is_empty());
+        assert_eq!(managers.residual_state("gemini-b").await.len(), 4);
+        managers.release("gemini-b").await;
+        assert!(managers.residual_state("gemini-b").await.is_empty());
+    }
+}
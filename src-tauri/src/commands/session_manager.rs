@@ -1,15 +1,20 @@
 use anyhow::Result;
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use log::{info, warn};
+use regex::{Regex, RegexBuilder};
 use uuid::Uuid;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
 use super::agents::AgentDb;
+use super::ai_usage_tracker::{record_usage_event, AIUsageEvent};
+use super::gemini_history::ConversationTurn;
+use crate::models::ModelProvider;
 
 /// Generate secure session ID using UUID v4 + timestamp + salt
 fn generate_secure_session_id(project_id: &str) -> String {
@@ -73,8 +78,11 @@ pub struct SessionMetadata {
 
 /// Initialize session management tables
 pub async fn init_session_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
-    
+    let conn = db.lock_conn();
+    create_session_tables(&conn)
+}
+
+pub(crate) fn create_session_tables(conn: &rusqlite::Connection) -> Result<(), String> {
     // Create sessions table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS chat_sessions (
@@ -139,7 +147,33 @@ pub async fn store_session_message(
     is_gemini: bool,
     db: &State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
+    insert_session_message(
+        &conn,
+        session_id,
+        project_id,
+        project_path,
+        message_type,
+        &content,
+        model_used,
+        tokens_used,
+        is_gemini,
+    )?;
+    Ok(())
+}
+
+/// Insert a message and bump its session's activity, returning the message id and sequence number
+fn insert_session_message(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    project_id: &str,
+    project_path: &str,
+    message_type: &str,
+    content: &JsonValue,
+    model_used: Option<String>,
+    tokens_used: Option<i32>,
+    is_gemini: bool,
+) -> Result<(String, i64), String> {
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
     
     // Get next sequence number
@@ -150,9 +184,12 @@ pub async fn store_session_message(
     ).unwrap_or(1);
 
     let message_id = format!("{}-{}", session_id, sequence_number);
-    let content_str = serde_json::to_string(&content)
+    let content_str = serde_json::to_string(content)
         .map_err(|e| format!("Failed to serialize content: {}", e))?;
 
+    // Update or create session metadata first, since messages reference their session
+    upsert_session_metadata(session_id, project_id, project_path, model_used.as_deref(), tokens_used.unwrap_or(0), is_gemini, conn)?;
+
     // Insert the message
     conn.execute(
         "INSERT OR REPLACE INTO session_messages 
@@ -172,11 +209,8 @@ pub async fn store_session_message(
         ],
     ).map_err(|e| format!("Failed to insert message: {}", e))?;
 
-    // Update or create session metadata
-    upsert_session_metadata(session_id, project_id, project_path, model_used.as_deref(), tokens_used.unwrap_or(0), is_gemini, &conn)?;
-
     info!("Stored message {} for session {}", message_id, session_id);
-    Ok(())
+    Ok((message_id, sequence_number))
 }
 
 /// Update session metadata
@@ -228,7 +262,7 @@ pub async fn load_session_messages(
     project_id: &str,
     db: &State<'_, AgentDb>,
 ) -> Result<Vec<JsonValue>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let mut stmt = conn.prepare(
         "SELECT content FROM session_messages 
@@ -267,7 +301,7 @@ pub async fn create_empty_session(
     is_gemini: bool,
     db: &State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
     conn.execute(
@@ -286,7 +320,7 @@ pub async fn get_session_metadata(
     session_id: &str,
     db: &State<'_, AgentDb>,
 ) -> Result<Option<SessionMetadata>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let result = conn.query_row(
         "SELECT session_id, project_id, project_path, created_at, updated_at, 
@@ -323,7 +357,7 @@ pub async fn list_project_sessions(
     project_id: &str,
     db: &State<'_, AgentDb>,
 ) -> Result<Vec<SessionMetadata>, String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     let mut stmt = conn.prepare(
         "SELECT session_id, project_id, project_path, created_at, updated_at, 
@@ -429,7 +463,7 @@ pub async fn delete_session(
     session_id: String,
     db: State<'_, AgentDb>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+    let conn = db.lock_conn();
     
     // Delete messages first (foreign key constraint)
     conn.execute(
@@ -562,7 +596,7 @@ pub async fn add_secure_message(
     
     // Get next sequence number and check for duplicates in a scope
     let (sequence_number, existing_id) = {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let conn = db.lock_conn();
         
         // Get next sequence number
         let sequence_number = conn.query_row(
@@ -603,4 +637,814 @@ pub async fn add_secure_message(
     ).await?;
     
     Ok(message_id)
-}
\ No newline at end of file
+}
+/// A user turn recorded by `send_session_message`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentSessionMessage {
+    pub session_id: String,
+    pub message_id: String,
+    pub sequence_number: i64,
+    /// The same content was already stored, so nothing new was recorded
+    pub duplicate: bool,
+    pub cost: f64,
+}
+
+/// Persist a user turn, its usage and the session's activity in one transaction
+///
+/// Either every row is written or none is, so session history and usage totals
+/// can't drift apart when one of the writes fails.
+fn record_user_turn(
+    conn: &rusqlite::Connection,
+    project_path: &str,
+    content: &JsonValue,
+    is_gemini: bool,
+    usage: &mut AIUsageEvent,
+) -> Result<SentSessionMessage, String> {
+    let session_id = usage
+        .session_id
+        .clone()
+        .ok_or("A session id is required to send a message")?;
+    let content_str = serde_json::to_string(content)
+        .map_err(|e| format!("Failed to serialize content: {}", e))?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start message transaction: {}", e))?;
+
+    let existing = tx
+        .query_row(
+            "SELECT id, sequence_number FROM session_messages WHERE session_id = ? AND content = ?",
+            params![&session_id, &content_str],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check for duplicate message: {}", e))?;
+    if let Some((message_id, sequence_number)) = existing {
+        info!("Duplicate message detected, returning existing ID: {}", message_id);
+        return Ok(SentSessionMessage {
+            session_id,
+            message_id,
+            sequence_number,
+            duplicate: true,
+            cost: 0.0,
+        });
+    }
+
+    let (message_id, sequence_number) = insert_session_message(
+        &tx,
+        &session_id,
+        &usage.project_id,
+        project_path,
+        "user",
+        content,
+        Some(usage.model_name.clone()),
+        Some(usage.token_count as i32),
+        is_gemini,
+    )?;
+    let cost = record_usage_event(&tx, usage)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit message transaction: {}", e))?;
+
+    Ok(SentSessionMessage {
+        session_id,
+        message_id,
+        sequence_number,
+        duplicate: false,
+        cost,
+    })
+}
+
+/// Record a user turn in session history, usage tracking and dashboard metrics at once
+///
+/// Events are only emitted once everything has been committed.
+#[tauri::command]
+pub async fn send_session_message(
+    app: AppHandle,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    content: JsonValue,
+    model: String,
+    tokens_used: Option<i64>,
+    is_gemini: bool,
+    tags: Option<HashMap<String, String>>,
+    db: State<'_, AgentDb>,
+) -> Result<SentSessionMessage, String> {
+    let _ = init_session_tables(&db).await;
+
+    let mut usage = AIUsageEvent {
+        project_id,
+        model_name: model,
+        agent_type: None,
+        mcp_server: None,
+        token_count: tokens_used.unwrap_or(0),
+        request_type: "message".to_string(),
+        response_time_ms: None,
+        success: true,
+        error_message: None,
+        session_id: Some(session_id),
+        user_prompt_tokens: tokens_used,
+        assistant_response_tokens: None,
+        cached_tokens: None,
+        estimated: false,
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+        tags,
+    };
+
+    let sent = {
+        let conn = db.lock_conn();
+        record_user_turn(&conn, &project_path, &content, is_gemini, &mut usage)?
+    };
+
+    if !sent.duplicate {
+        if let Err(e) = app.emit(&format!("session-message-stored:{}", sent.session_id), &sent) {
+            warn!("Failed to emit session message event: {}", e);
+        }
+        if let Err(e) = app.emit("ai-usage-tracked", &usage) {
+            warn!("Failed to emit usage event: {}", e);
+        }
+    }
+
+    Ok(sent)
+}
+
+/// The prompt a retry re-sends and where it goes
+#[derive(Debug, Clone, PartialEq)]
+struct RetryPlan {
+    project_id: String,
+    project_path: String,
+    prompt: String,
+    /// Sequence number of the turn that first sent the prompt
+    retry_of: i64,
+    /// 1 for the first retry of that turn
+    attempt: i64,
+    provider: ModelProvider,
+}
+
+/// Plan a retry of the session's last user turn on `model`
+///
+/// User turns without any text, such as tool results, are passed over. A
+/// retry of a retry still points at the turn that first sent the prompt.
+fn plan_retry(conn: &rusqlite::Connection, session_id: &str, model: &str) -> Result<RetryPlan, String> {
+    let provider = super::agents::model_provider(model, None)?;
+    let mut stmt = conn.prepare(
+        "SELECT m.sequence_number, m.content, m.project_id, s.project_path
+         FROM session_messages m JOIN chat_sessions s ON s.session_id = m.session_id
+         WHERE m.session_id = ?1 AND m.message_type = 'user'
+         ORDER BY m.sequence_number ASC"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let turns = stmt
+        .query_map(params![session_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read message: {}", e))?
+        .into_iter()
+        .filter_map(|(sequence_number, content, project_id, project_path)| {
+            let content = serde_json::from_str::<JsonValue>(&content).ok()?;
+            Some((sequence_number, content, project_id, project_path))
+        })
+        .collect::<Vec<_>>();
+
+    let (sequence_number, content, project_id, project_path) = turns
+        .iter()
+        .rev()
+        .find(|(_, content, _, _)| !message_text(content).trim().is_empty())
+        .ok_or_else(|| format!("Session {} has no user turn to retry", session_id))?;
+    let retry_of = content["retry"]["of"].as_i64().unwrap_or(*sequence_number);
+    let attempt = turns
+        .iter()
+        .filter(|(_, content, _, _)| content["retry"]["of"].as_i64() == Some(retry_of))
+        .count() as i64
+        + 1;
+
+    Ok(RetryPlan {
+        project_id: project_id.clone(),
+        project_path: project_path.clone(),
+        prompt: message_text(content),
+        retry_of,
+        attempt,
+        provider,
+    })
+}
+
+/// Text of the session's user and assistant turns before `before`, oldest first
+///
+/// Only this session's messages are read, so a model is never handed another
+/// session's conversation. Turns without text, such as tool results, are left out.
+fn conversation_history(conn: &rusqlite::Connection, session_id: &str, before: i64) -> Result<Vec<ConversationTurn>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT message_type, content FROM session_messages
+         WHERE session_id = ?1 AND sequence_number < ?2 AND message_type IN ('user', 'assistant')
+         ORDER BY sequence_number ASC"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let messages = stmt
+        .query_map(params![session_id, before], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read message: {}", e))?;
+    Ok(messages
+        .into_iter()
+        .filter_map(|(message_type, content)| {
+            let text = message_text(&serde_json::from_str::<JsonValue>(&content).ok()?);
+            (!text.trim().is_empty()).then_some((message_type, text))
+        })
+        .collect())
+}
+
+/// The prompt and conversation history a retry sends to `provider`
+///
+/// Gemini takes the earlier turns as conversation history. A retry on Claude or
+/// Ollama starts a fresh run that is sent a single prompt, so the earlier turns
+/// go ahead of the prompt as a transcript.
+fn retry_request(provider: ModelProvider, prompt: &str, history: Vec<ConversationTurn>) -> (String, Option<Vec<ConversationTurn>>) {
+    if provider == ModelProvider::Gemini {
+        return (prompt.to_string(), Some(history));
+    }
+    if history.is_empty() {
+        return (prompt.to_string(), None);
+    }
+    let transcript = history
+        .iter()
+        .map(|(role, text)| format!("{}: {}", if role == "assistant" { "Assistant" } else { "User" }, text))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (
+        format!("Earlier in this conversation:\n\n{}\n\nContinue the conversation by answering:\n\n{}", transcript, prompt),
+        None,
+    )
+}
+
+/// Spend tags marking the execution a retry starts, which runs as a session of its own
+fn retry_tags(session_id: &str, plan: &RetryPlan) -> HashMap<String, String> {
+    HashMap::from([
+        ("retry_of".to_string(), format!("{}#{}", session_id, plan.retry_of)),
+        ("retry_attempt".to_string(), plan.attempt.to_string()),
+    ])
+}
+
+/// Record the retry as a new user turn sent to `model`
+fn record_retry_turn(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    plan: &RetryPlan,
+    model: &str,
+) -> Result<(SentSessionMessage, AIUsageEvent), String> {
+    let content = serde_json::json!({
+        "type": "user",
+        "message": { "role": "user", "content": plan.prompt },
+        "retry": { "of": plan.retry_of, "attempt": plan.attempt, "model": model },
+    });
+    let tokens = super::context_guard::estimate_tokens(&plan.prompt) as i64;
+    let mut usage = AIUsageEvent {
+        project_id: plan.project_id.clone(),
+        model_name: model.to_string(),
+        agent_type: None,
+        mcp_server: None,
+        token_count: tokens,
+        request_type: "retry".to_string(),
+        response_time_ms: None,
+        success: true,
+        error_message: None,
+        session_id: Some(session_id.to_string()),
+        user_prompt_tokens: Some(tokens),
+        assistant_response_tokens: None,
+        cached_tokens: None,
+        estimated: true,
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+        // Tags apply to a whole session, so the retry is marked on the turn itself
+        tags: None,
+    };
+    let sent = record_user_turn(
+        conn,
+        &plan.project_path,
+        &content,
+        plan.provider == ModelProvider::Gemini,
+        &mut usage,
+    )?;
+    Ok((sent, usage))
+}
+
+/// A retry recorded in session history and dispatched to its model
+#[derive(Debug, Clone, Serialize)]
+pub struct RetriedTurn {
+    #[serde(flatten)]
+    pub turn: SentSessionMessage,
+    pub model: String,
+    pub provider: ModelProvider,
+    /// Sequence number of the turn that first sent the prompt
+    pub retry_of: i64,
+    pub attempt: i64,
+}
+
+/// Re-send the session's last prompt to `model`, on whichever provider serves it
+///
+/// The retry is recorded as a new user turn tagged with the turn it repeats,
+/// then run in the session's project like a fresh prompt to that model.
+#[tauri::command]
+pub async fn retry_with_model(
+    app: AppHandle,
+    session_id: String,
+    model: String,
+    db: State<'_, AgentDb>,
+) -> Result<RetriedTurn, String> {
+    let _ = init_session_tables(&db).await;
+
+    let (plan, sent, usage, history) = {
+        let conn = db.lock_conn();
+        let plan = plan_retry(&conn, &session_id, &model)?;
+        if plan.project_path.is_empty() {
+            return Err(format!("Session {} has no project to run the retry in", session_id));
+        }
+        // The retry runs without the original conversation, so it is sent the turns before the retried one
+        let history = conversation_history(&conn, &session_id, plan.retry_of)?;
+        let (sent, usage) = record_retry_turn(&conn, &session_id, &plan, &model)?;
+        (plan, sent, usage, history)
+    };
+    info!(
+        "Retrying turn {} of session {} on {} ({})",
+        plan.retry_of,
+        session_id,
+        model,
+        plan.provider.as_str()
+    );
+    if let Err(e) = app.emit(&format!("session-message-stored:{}", sent.session_id), &sent) {
+        warn!("Failed to emit session message event: {}", e);
+    }
+    if let Err(e) = app.emit("ai-usage-tracked", &usage) {
+        warn!("Failed to emit usage event: {}", e);
+    }
+
+    let tags = Some(retry_tags(&session_id, &plan));
+    let (prompt, history) = retry_request(plan.provider, &plan.prompt, history);
+    let project_path = plan.project_path.clone();
+    match plan.provider {
+        ModelProvider::Claude => {
+            super::claude::execute_claude_code(app.clone(), project_path, prompt, model.clone(), tags, None).await?
+        }
+        ModelProvider::Gemini => {
+            super::gemini::execute_gemini_code(
+                prompt,
+                model.clone(),
+                project_path,
+                Some(super::gemini::GeminiExecuteOptions {
+                    tags,
+                    conversation_history: history,
+                    ..Default::default()
+                }),
+                app.clone(),
+                db,
+                app.state::<super::claude::ClaudeProcessState>(),
+                app.state::<super::gemini::GeminiSessionRegistry>(),
+                app.state::<super::session_deduplication::MessageDeduplicationManager>(),
+                app.state::<super::session_deduplication::SessionIsolationManager>(),
+                app.state::<super::execution_control::ExecutionControlState>(),
+            )
+            .await?
+        }
+        ModelProvider::Ollama => {
+            let model_id = crate::models::canonicalize(&model).map_or(model.clone(), |m| m.id.to_string());
+            super::ollama::execute_ollama_request(app.clone(), model_id, prompt, project_path, None, None, tags, None).await?
+        }
+    }
+
+    Ok(RetriedTurn { turn: sent, model, provider: plan.provider, retry_of: plan.retry_of, attempt: plan.attempt })
+}
+
+/// Characters of context kept on each side of a search hit
+const SEARCH_SNIPPET_CONTEXT: usize = 60;
+
+/// Default number of matches returned by `search_session_history`
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// A message in stored session history that matched a search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchMatch {
+    pub session_id: String,
+    pub project_id: String,
+    /// Sequence number of the message within its session, for jumping to it
+    pub position: i64,
+    pub role: String,
+    pub timestamp: i64,
+    pub snippet: String,
+    /// Byte range of the hit inside `snippet`
+    pub match_start: usize,
+    pub match_end: usize,
+}
+
+/// Collect the human-readable text of a stored message
+fn message_text(content: &JsonValue) -> String {
+    fn collect(value: &JsonValue, out: &mut Vec<String>) {
+        match value {
+            JsonValue::String(text) => out.push(text.clone()),
+            JsonValue::Array(items) => {
+                for item in items {
+                    match item.get("text") {
+                        Some(text) => collect(text, out),
+                        None => collect(item, out),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut parts = Vec::new();
+    if let Some(inner) = content.get("message").and_then(|m| m.get("content")) {
+        collect(inner, &mut parts);
+    }
+    for key in ["content", "text", "result"] {
+        if let Some(value) = content.get(key) {
+            collect(value, &mut parts);
+        }
+    }
+    parts.join("\n")
+}
+
+/// Cut a snippet around `start..end`, returning it with the hit's offsets inside it
+fn build_snippet(text: &str, start: usize, end: usize) -> (String, usize, usize) {
+    let mut from = start.saturating_sub(SEARCH_SNIPPET_CONTEXT);
+    while !text.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (end + SEARCH_SNIPPET_CONTEXT).min(text.len());
+    while !text.is_char_boundary(to) {
+        to += 1;
+    }
+
+    let prefix = if from > 0 { "…" } else { "" };
+    let suffix = if to < text.len() { "…" } else { "" };
+    let snippet = format!("{}{}{}", prefix, &text[from..to], suffix);
+    let offset = prefix.len() + start - from;
+    (snippet, offset, offset + (end - start))
+}
+
+fn build_search_pattern(query: &str, use_regex: bool, case_sensitive: bool) -> Result<Regex, String> {
+    if query.trim().is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+    let pattern = if use_regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+/// Scan stored messages in order, stopping as soon as `limit` matches are found.
+/// Secure sessions only differ in how their ids are generated; their messages are
+/// stored as plain JSON like any other and are searched the same way.
+fn search_session_messages(
+    conn: &rusqlite::Connection,
+    pattern: &Regex,
+    project_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<SessionSearchMatch>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT session_id, project_id, sequence_number, message_type, content, timestamp
+         FROM session_messages
+         WHERE (?1 IS NULL OR project_id = ?1)
+         ORDER BY timestamp DESC, session_id, sequence_number"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let mut rows = stmt.query(params![project_id])
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut matches = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| format!("Failed to read message: {}", e))? {
+        let content_str: String = row.get(4).map_err(|e| e.to_string())?;
+        let content = match serde_json::from_str::<JsonValue>(&content_str) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        let text = message_text(&content);
+        if let Some(hit) = pattern.find(&text) {
+            let message_type: String = row.get(3).map_err(|e| e.to_string())?;
+            let role = content.get("message")
+                .and_then(|m| m.get("role"))
+                .and_then(|r| r.as_str())
+                .map(|r| r.to_string())
+                .unwrap_or(message_type);
+            let (snippet, match_start, match_end) = build_snippet(&text, hit.start(), hit.end());
+
+            matches.push(SessionSearchMatch {
+                session_id: row.get(0).map_err(|e| e.to_string())?,
+                project_id: row.get(1).map_err(|e| e.to_string())?,
+                position: row.get(2).map_err(|e| e.to_string())?,
+                role,
+                timestamp: row.get(5).map_err(|e| e.to_string())?,
+                snippet,
+                match_start,
+                match_end,
+            });
+            if matches.len() >= limit {
+                break;
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+/// Search the content of stored session messages, newest first
+#[tauri::command]
+pub async fn search_session_history(
+    query: String,
+    project_id: Option<String>,
+    limit: Option<usize>,
+    use_regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    db: State<'_, AgentDb>,
+) -> Result<Vec<SessionSearchMatch>, String> {
+    let pattern = build_search_pattern(&query, use_regex.unwrap_or(false), case_sensitive.unwrap_or(false))?;
+    let _ = init_session_tables(&db).await;
+
+    let conn = db.lock_conn();
+    let matches = search_session_messages(
+        &conn,
+        &pattern,
+        project_id.as_deref(),
+        limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    )?;
+
+    info!("Session search for {:?} returned {} match(es)", query, matches.len());
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn seed(conn: &Connection, session_id: &str, project_id: &str, seq: i64, timestamp: i64, content: &str) {
+        conn.execute(
+            "INSERT OR IGNORE INTO chat_sessions (session_id, project_id, project_path, created_at, updated_at)
+             VALUES (?1, ?2, '', ?3, ?3)",
+            params![session_id, project_id, timestamp],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO session_messages
+             (id, session_id, project_id, sequence_number, message_type, content, timestamp)
+             VALUES (?1, ?2, ?3, ?4, 'assistant', ?5, ?6)",
+            params![format!("{}-{}", session_id, seq), session_id, project_id, seq, content, timestamp],
+        ).unwrap();
+    }
+
+    fn seeded_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_session_tables(&conn).unwrap();
+        seed(&conn, "s1", "proj-a", 1, 100,
+            r#"{"type":"user","message":{"role":"user","content":"How do I keep a WebSocket alive?"}}"#);
+        seed(&conn, "s1", "proj-a", 2, 101,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Send a ping frame every 30 seconds."}]}}"#);
+        seed(&conn, "s2", "proj-b", 1, 200,
+            r#"{"type":"user","message":{"role":"user","content":"websocket reconnect with backoff"}}"#);
+        conn
+    }
+
+    #[test]
+    fn test_plain_search_is_case_insensitive_by_default() {
+        let conn = seeded_db();
+        let pattern = build_search_pattern("websocket", false, false).unwrap();
+        let matches = search_session_messages(&conn, &pattern, None, 10).unwrap();
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[0].session_id.as_str(), matches[0].position), ("s2", 1));
+        assert_eq!((matches[1].session_id.as_str(), matches[1].position), ("s1", 1));
+        assert_eq!(matches[1].role, "user");
+        assert_eq!(matches[1].snippet, "How do I keep a WebSocket alive?");
+        assert_eq!(&matches[1].snippet[matches[1].match_start..matches[1].match_end], "WebSocket");
+    }
+
+    #[test]
+    fn test_secure_sessions_are_searched_like_any_other() {
+        let conn = seeded_db();
+        let secure_id = generate_secure_session_id("proj-a");
+        seed(&conn, &secure_id, "proj-a", 1, 400,
+            r#"{"type":"user","message":{"role":"user","content":"Rotate the signing key monthly"}}"#);
+
+        let pattern = build_search_pattern("signing key", false, false).unwrap();
+        let matches = search_session_messages(&conn, &pattern, Some("proj-a"), 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].session_id, secure_id);
+        assert_eq!(matches[0].snippet, "Rotate the signing key monthly");
+    }
+
+    #[test]
+    fn test_case_sensitive_and_project_filter() {
+        let conn = seeded_db();
+        let pattern = build_search_pattern("WebSocket", false, true).unwrap();
+        let matches = search_session_messages(&conn, &pattern, None, 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].session_id, "s1");
+
+        let pattern = build_search_pattern("websocket", false, false).unwrap();
+        let matches = search_session_messages(&conn, &pattern, Some("proj-b"), 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].project_id, "proj-b");
+    }
+
+    #[test]
+    fn test_regex_search_reads_content_blocks_and_respects_limit() {
+        let conn = seeded_db();
+        let pattern = build_search_pattern(r"ping frame every \d+ seconds", true, false).unwrap();
+        let matches = search_session_messages(&conn, &pattern, None, 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].position, 2);
+        assert_eq!(matches[0].role, "assistant");
+
+        let pattern = build_search_pattern("websocket", false, false).unwrap();
+        assert_eq!(search_session_messages(&conn, &pattern, None, 1).unwrap().len(), 1);
+        assert!(build_search_pattern("(unclosed", true, false).is_err());
+        assert!(build_search_pattern("(unclosed", false, false).is_ok());
+    }
+
+    #[test]
+    fn test_snippet_is_trimmed_around_match() {
+        let text = format!("{}needle{}", "a".repeat(100), "é".repeat(100));
+        let start = text.find("needle").unwrap();
+        let (snippet, s, e) = build_snippet(&text, start, start + 6);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert_eq!(&snippet[s..e], "needle");
+    }
+
+    fn user_turn(session_id: &str) -> AIUsageEvent {
+        AIUsageEvent {
+            project_id: "proj-a".to_string(),
+            model_name: "sonnet".to_string(),
+            agent_type: None,
+            mcp_server: None,
+            token_count: 42,
+            request_type: "message".to_string(),
+            response_time_ms: None,
+            success: true,
+            error_message: None,
+            session_id: Some(session_id.to_string()),
+            user_prompt_tokens: Some(42),
+            assistant_response_tokens: None,
+            cached_tokens: None,
+            estimated: false,
+            timestamp: 1_700_000_000,
+            tags: None,
+        }
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn turn_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_session_tables(&conn).unwrap();
+        conn.execute_batch(include_str!("../../migrations/002_dashboard.sql")).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_user_turn_records_session_and_usage_together() {
+        let conn = turn_db();
+        let content = serde_json::json!({"type": "user", "message": {"content": "hello"}});
+
+        let sent = record_user_turn(&conn, "/tmp/a", &content, false, &mut user_turn("s1")).unwrap();
+        assert!(!sent.duplicate);
+        assert_eq!(sent.sequence_number, 1);
+        assert_eq!(count(&conn, "session_messages"), 1);
+        assert_eq!(count(&conn, "ai_usage_events"), 1);
+        assert_eq!(count(&conn, "ai_usage_metrics"), 1);
+        let (messages, tokens): (i64, i64) = conn
+            .query_row(
+                "SELECT message_count, total_tokens FROM chat_sessions WHERE session_id = 's1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((messages, tokens), (1, 42));
+
+        // Resending the same turn is recognised and records nothing
+        let again = record_user_turn(&conn, "/tmp/a", &content, false, &mut user_turn("s1")).unwrap();
+        assert!(again.duplicate);
+        assert_eq!(again.message_id, sent.message_id);
+        assert_eq!(count(&conn, "ai_usage_events"), 1);
+    }
+
+    #[test]
+    fn test_failed_user_turn_leaves_nothing_behind() {
+        let conn = turn_db();
+        // The message and usage event are written before the dashboard aggregate fails
+        conn.execute_batch("DROP TABLE ai_usage_metrics").unwrap();
+        let content = serde_json::json!({"type": "user", "message": {"content": "hello"}});
+
+        let result = record_user_turn(&conn, "/tmp/a", &content, false, &mut user_turn("s1"));
+        assert!(result.is_err());
+        assert_eq!(count(&conn, "session_messages"), 0);
+        assert_eq!(count(&conn, "chat_sessions"), 0);
+        assert_eq!(count(&conn, "ai_usage_events"), 0);
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_retry_reuses_last_prompt_on_chosen_model() {
+        let conn = turn_db();
+        let prompt = serde_json::json!({"type": "user", "message": {"role": "user", "content": "Explain lifetimes"}});
+        record_user_turn(&conn, "/work/app", &prompt, false, &mut user_turn("s1")).unwrap();
+        let store = |message_type: &str, content: &str| {
+            let content: JsonValue = serde_json::from_str(content).unwrap();
+            insert_session_message(&conn, "s1", "proj-a", "/work/app", message_type, &content, None, None, false).unwrap();
+        };
+        store("assistant", r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Meh"}]}}"#);
+        // Tool results arrive as user messages but carry no prompt
+        store("user", r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1"}]}}"#);
+
+        let plan = plan_retry(&conn, "s1", "gemini-2.5-pro").unwrap();
+        assert_eq!(plan.prompt, "Explain lifetimes");
+        assert_eq!((plan.provider, plan.retry_of, plan.attempt), (ModelProvider::Gemini, 1, 1));
+        assert_eq!(plan.project_path, "/work/app");
+
+        let (sent, usage) = record_retry_turn(&conn, "s1", &plan, "gemini-2.5-pro").unwrap();
+        assert!(!sent.duplicate);
+        assert_eq!(sent.sequence_number, 4);
+        assert_eq!((usage.model_name.as_str(), usage.request_type.as_str()), ("gemini-2.5-pro", "retry"));
+        let stored: String = conn
+            .query_row("SELECT content FROM session_messages WHERE id = ?1", [&sent.message_id], |row| row.get(0))
+            .unwrap();
+        let stored: JsonValue = serde_json::from_str(&stored).unwrap();
+        assert_eq!(stored["retry"], serde_json::json!({"of": 1, "attempt": 1, "model": "gemini-2.5-pro"}));
+        assert_eq!(retry_tags("s1", &plan)["retry_of"], "s1#1");
+
+        // Retrying the retry sends the same prompt elsewhere and still points at the original turn
+        let again = plan_retry(&conn, "s1", "llama3.3:latest").unwrap();
+        assert_eq!(again.prompt, "Explain lifetimes");
+        assert_eq!((again.provider, again.retry_of, again.attempt), (ModelProvider::Ollama, 1, 2));
+        let (resent, _) = record_retry_turn(&conn, "s1", &again, "llama3.3:latest").unwrap();
+        assert!(!resent.duplicate);
+
+        assert!(plan_retry(&conn, "missing", "sonnet").unwrap_err().contains("no user turn"));
+    }
+
+    #[test]
+    fn test_retried_request_carries_the_earlier_turns() {
+        let conn = turn_db();
+        let store = |message_type: &str, content: &str| {
+            let content: JsonValue = serde_json::from_str(content).unwrap();
+            insert_session_message(&conn, "s1", "proj-a", "/work/app", message_type, &content, None, None, false).unwrap();
+        };
+        store("user", r#"{"type":"user","message":{"role":"user","content":"What is a borrow?"}}"#);
+        store("assistant", r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"A reference that does not own."}]}}"#);
+        store("user", r#"{"type":"user","message":{"role":"user","content":"Explain lifetimes"}}"#);
+        store("assistant", r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Meh"}]}}"#);
+
+        let request = |model: &str| {
+            let plan = plan_retry(&conn, "s1", model).unwrap();
+            let history = conversation_history(&conn, "s1", plan.retry_of).unwrap();
+            retry_request(plan.provider, &plan.prompt, history)
+        };
+
+        // Claude and Ollama start fresh runs, so the earlier turns travel in the prompt
+        for model in ["sonnet", "llama3.3:latest"] {
+            let (prompt, history) = request(model);
+            assert_eq!(history, None);
+            assert!(prompt.contains("User: What is a borrow?"), "{}", prompt);
+            assert!(prompt.contains("Assistant: A reference that does not own."), "{}", prompt);
+            assert!(prompt.ends_with("Explain lifetimes"), "{}", prompt);
+            // The answer being retried is not part of it
+            assert!(!prompt.contains("Meh"), "{}", prompt);
+        }
+
+        let (prompt, history) = request("gemini-2.5-pro");
+        assert_eq!(prompt, "Explain lifetimes");
+        assert_eq!(history.unwrap().len(), 2);
+
+        // A first turn has nothing to carry
+        assert_eq!(retry_request(ModelProvider::Claude, "Hi", Vec::new()), ("Hi".to_string(), None));
+    }
+
+    #[test]
+    fn test_conversation_history_reads_only_this_sessions_text_turns() {
+        let conn = turn_db();
+        let store = |session_id: &str, message_type: &str, content: &str| {
+            let content: JsonValue = serde_json::from_str(content).unwrap();
+            insert_session_message(&conn, session_id, "proj-a", "/work/app", message_type, &content, None, None, false).unwrap();
+        };
+        store("s1", "user", r#"{"type":"user","message":{"role":"user","content":"Explain lifetimes"}}"#);
+        store("s2", "user", r#"{"type":"user","message":{"role":"user","content":"Another session"}}"#);
+        store("s1", "assistant", r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"They bound borrows."}]}}"#);
+        store("s1", "user", r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1"}]}}"#);
+        store("s1", "system", r#"{"type":"system","subtype":"init","text":"ignored"}"#);
+        store("s1", "user", r#"{"type":"user","message":{"role":"user","content":"Show an example"}}"#);
+
+        let history = conversation_history(&conn, "s1", 5).unwrap();
+        assert_eq!(
+            history,
+            vec![
+                ("user".to_string(), "Explain lifetimes".to_string()),
+                ("assistant".to_string(), "They bound borrows.".to_string()),
+            ]
+        );
+        assert_eq!(conversation_history(&conn, "s1", 6).unwrap().len(), 3);
+        assert_eq!(conversation_history(&conn, "s2", 10).unwrap().len(), 1);
+    }
+
+}
@@ -0,0 +1,226 @@
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use tauri::{AppHandle, State};
+
+use super::agents::AgentDb;
+
+pub(crate) type ResetFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// Applies a setting's default once its stored value is gone, for settings
+/// whose value is only read at startup or has already taken effect
+pub(crate) type ResetHook = fn(AppHandle) -> ResetFuture;
+
+/// A known setting and how its stored value is read
+///
+/// Modules list theirs in a `SETTINGS` const, registered in [`REGISTRY`].
+pub(crate) struct Setting {
+    key: &'static str,
+    /// The effective value of what is stored, comparable with `default`
+    effective: fn(&str) -> Result<Value, String>,
+    default: fn() -> Value,
+    on_reset: Option<ResetHook>,
+}
+
+impl Setting {
+    pub(crate) const fn new(
+        key: &'static str,
+        effective: fn(&str) -> Result<Value, String>,
+        default: fn() -> Value,
+    ) -> Self {
+        Self { key, effective, default, on_reset: None }
+    }
+
+    /// A JSON document deserialized as `T`, whose `Default` is the default
+    pub(crate) const fn typed<T: DeserializeOwned + Serialize + Default>(key: &'static str) -> Self {
+        Self::new(key, typed::<T>, typed_default::<T>)
+    }
+
+    pub(crate) const fn on_reset(mut self, hook: ResetHook) -> Self {
+        self.on_reset = Some(hook);
+        self
+    }
+}
+
+fn typed<T: DeserializeOwned + Serialize>(stored: &str) -> Result<Value, String> {
+    let value: T = serde_json::from_str(stored).map_err(|e| e.to_string())?;
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+pub(crate) fn typed_default<T: Default + Serialize>() -> Value {
+    serde_json::to_value(T::default()).unwrap_or(Value::Null)
+}
+
+/// A bare string; empty counts as unset
+pub(crate) fn text(stored: &str) -> Result<Value, String> {
+    Ok(Some(stored.trim())
+        .filter(|value| !value.is_empty())
+        .map_or(Value::Null, |value| Value::String(value.to_string())))
+}
+
+pub(crate) fn flag(stored: &str) -> Result<Value, String> {
+    Ok(Value::Bool(stored == "true"))
+}
+
+pub(crate) fn unset() -> Value {
+    Value::Null
+}
+
+pub(crate) fn off() -> Value {
+    Value::Bool(false)
+}
+
+/// Every module's settings, grouped by area
+static REGISTRY: &[&[Setting]] = &[
+    super::proxy::SETTINGS,
+    super::provider_timeouts::SETTINGS,
+    super::request_hooks::SETTINGS,
+    super::prompt_secrets::SETTINGS,
+    super::gemini::SETTINGS,
+    super::intelligent_routing::SETTINGS,
+    super::error_tracker::SETTINGS,
+    super::run_notifications::SETTINGS,
+    super::mcp_supervisor::SETTINGS,
+    super::dashboard::SETTINGS,
+    super::storage::SETTINGS,
+    super::debug_system::SETTINGS,
+    super::claude_dir::SETTINGS,
+    super::agents::SETTINGS,
+];
+
+fn settings() -> impl Iterator<Item = &'static Setting> {
+    REGISTRY.iter().flat_map(|settings| settings.iter())
+}
+
+/// A setting whose value differs from its default
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingDiff {
+    pub key: String,
+    /// The effective value; an unreadable one, which its module ignores, as stored
+    pub current: Value,
+    pub default: Value,
+}
+
+fn known_setting(key: &str) -> Result<&'static Setting, String> {
+    settings()
+        .find(|setting| setting.key == key)
+        .ok_or_else(|| format!("Unknown setting '{}'", key))
+}
+
+/// Every known setting whose stored value differs from its default
+///
+/// Stored values are read back into the effective value their module would load,
+/// so one equal to the default, or differing only in formatting, isn't reported.
+/// Secrets and bookkeeping such as the last maintenance run have no default and
+/// aren't known settings.
+pub fn settings_diff(conn: &Connection) -> Result<Vec<SettingDiff>, String> {
+    let mut diff = Vec::new();
+    for setting in settings() {
+        let stored = conn
+            .query_row("SELECT value FROM app_settings WHERE key = ?1", params![setting.key], |row| {
+                row.get::<_, String>(0)
+            })
+            .ok();
+        let Some(stored) = stored else {
+            continue;
+        };
+        let default = (setting.default)();
+        let current = (setting.effective)(&stored).unwrap_or_else(|e| {
+            log::warn!("Stored setting {} is unreadable: {}", setting.key, e);
+            Value::String(stored)
+        });
+        if current != default {
+            diff.push(SettingDiff { key: setting.key.to_string(), current, default });
+        }
+    }
+    Ok(diff)
+}
+
+/// Delete the stored value of a known setting, so its default applies
+///
+/// Every module falls back to its default when its key is missing.
+pub fn reset_stored_setting(conn: &Connection, key: &str) -> Result<(), String> {
+    let setting = known_setting(key)?;
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", params![setting.key])
+        .map_err(|e| format!("Failed to reset setting {}: {}", key, e))?;
+    Ok(())
+}
+
+/// List every setting that differs from its default, with both values
+#[tauri::command]
+pub async fn get_settings_diff(db: State<'_, AgentDb>) -> Result<Vec<SettingDiff>, String> {
+    let conn = db.lock_conn();
+    settings_diff(&conn)
+}
+
+/// Restore one setting to its default
+///
+/// Settings that only take effect at startup, such as the proxy, the Claude
+/// directory and the servers kept running, have their default applied right away.
+#[tauri::command]
+pub async fn reset_setting(app: AppHandle, db: State<'_, AgentDb>, key: String) -> Result<(), String> {
+    let setting = known_setting(&key)?;
+    reset_stored_setting(&*db.lock_conn(), &key)?;
+    if let Some(hook) = setting.on_reset {
+        hook(app).await?;
+    }
+    log::info!("Reset setting {} to its default", key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::prompt_secrets::PROMPT_SECRET_SCAN_SETTINGS_KEY;
+    use crate::commands::provider_timeouts::{ProviderTimeouts, PROVIDER_TIMEOUTS_SETTINGS_KEY};
+    use crate::commands::request_hooks::REQUEST_HOOKS_SETTINGS_KEY;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registered_keys_are_unique() {
+        let mut seen = HashSet::new();
+        for setting in settings() {
+            assert!(seen.insert(setting.key), "{} is registered twice", setting.key);
+        }
+        assert!(seen.contains("debug_retention"));
+    }
+
+    #[test]
+    fn test_changed_setting_is_listed_until_reset() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        let store = |key: &str, value: &str| {
+            conn.execute("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)", params![key, value])
+                .unwrap();
+        };
+        assert!(settings_diff(&conn).unwrap().is_empty());
+
+        // Stored values that amount to the defaults aren't differences
+        store("proxy_http", "");
+        store(REQUEST_HOOKS_SETTINGS_KEY, r#"{ "hooks": [] }"#);
+        store("claude_installation_preference", "system");
+        store(PROVIDER_TIMEOUTS_SETTINGS_KEY, r#"{"gemini":{"connect_secs":5,"request_secs":20,"idle_secs":10}}"#);
+        store(PROMPT_SECRET_SCAN_SETTINGS_KEY, "redact");
+        store("gemini_api_key", "AIza-not-a-setting");
+
+        let diff = settings_diff(&conn).unwrap();
+        let keys: Vec<_> = diff.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(keys, [PROVIDER_TIMEOUTS_SETTINGS_KEY, PROMPT_SECRET_SCAN_SETTINGS_KEY]);
+        assert_eq!(diff[0].current["gemini"]["request_secs"], 20);
+        assert_eq!(diff[0].default["gemini"]["request_secs"], 120);
+        // Fields the stored value omits show their defaults
+        assert_eq!(diff[0].current["ollama"], diff[0].default["ollama"]);
+        assert_eq!((diff[1].current.as_str(), diff[1].default.as_str()), (Some("redact"), Some("off")));
+
+        reset_stored_setting(&conn, PROVIDER_TIMEOUTS_SETTINGS_KEY).unwrap();
+        let diff = settings_diff(&conn).unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].key, PROMPT_SECRET_SCAN_SETTINGS_KEY);
+        assert_eq!(crate::commands::provider_timeouts::load_provider_timeouts(&conn), ProviderTimeouts::default());
+
+        assert_eq!(reset_stored_setting(&conn, "gemini_api_key").unwrap_err(), "Unknown setting 'gemini_api_key'");
+    }
+}
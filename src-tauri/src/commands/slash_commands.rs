@@ -1,11 +1,16 @@
 use anyhow::{Context, Result};
 use dirs;
 use log::{debug, error, info};
-use tauri::{Emitter, State, Manager};
+use tauri::{State, Manager};
 use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use super::{claude::ClaudeProcessState, agents::AgentDb};
+use super::execution_control::{register_process, ExecutionControlState, ExecutionStatus};
+use super::session_event_log::emit_session_event;
+use crate::models::ModelProvider;
 
 #[cfg(target_os = "windows")]
 #[allow(unused_imports)]  // Used by creation_flags() method calls within Windows-specific conditional blocks
@@ -504,8 +509,8 @@ pub async fn slash_commands_list(
     }
     
     // Load user commands
-    if let Some(home_dir) = dirs::home_dir() {
-        let user_commands_dir = home_dir.join(".claude").join("commands");
+    if let Ok(claude_dir) = super::claude_dir::claude_dir_path() {
+        let user_commands_dir = claude_dir.join("commands");
         if user_commands_dir.exists() {
             debug!("Scanning user commands at: {:?}", user_commands_dir);
             
@@ -578,10 +583,7 @@ pub async fn slash_command_save(
             return Err("Project path required for project scope".to_string());
         }
     } else {
-        dirs::home_dir()
-            .ok_or_else(|| "Could not find home directory".to_string())?
-            .join(".claude")
-            .join("commands")
+        super::claude_dir::claude_dir_path()?.join("commands")
     };
     
     // Build file path
@@ -632,6 +634,10 @@ pub async fn slash_command_save(
 }
 
 /// Execute a slash command with intelligent model routing
+///
+/// Claude runs stream under `session_id` (generated when not given), which is returned so
+/// the caller can follow `claude-output:{session_id}` events. Gemini and Ollama runs pick
+/// their own session IDs and announce them in their init event, so None is returned.
 #[tauri::command]
 pub async fn execute_claude_slash_command(
     app: tauri::AppHandle,
@@ -639,9 +645,10 @@ pub async fn execute_claude_slash_command(
     arguments: String,
     project_path: String,
     model: Option<String>,
+    session_id: Option<String>,
     db: State<'_, AgentDb>,
     claude_state: State<'_, ClaudeProcessState>,
-) -> Result<(), String> {
+) -> Result<Option<String>, String> {
     use std::process::Stdio;
     use tokio::process::Command;
     
@@ -659,8 +666,14 @@ pub async fn execute_claude_slash_command(
     // Determine model to use (default to auto if not specified)
     let selected_model = model.unwrap_or_else(|| "auto".to_string());
     
-    // Route to appropriate model execution based on model provider
-    if selected_model.starts_with("gemini-") || selected_model.contains("gemini") {
+    // Route to appropriate model execution based on model provider, guessing from the
+    // name for models missing from the alias table (e.g. custom Ollama tags)
+    let canonical = crate::models::canonicalize(&selected_model);
+    let provider = canonical.map(|m| m.provider);
+    let is_ollama_name = selected_model.contains(":latest") || selected_model.starts_with("llama") ||
+        selected_model.starts_with("phi") || selected_model.starts_with("mistral") ||
+        selected_model.starts_with("qwen") || selected_model.starts_with("codellama");
+    if provider == Some(ModelProvider::Gemini) || (provider.is_none() && selected_model.contains("gemini")) {
         // Route to Gemini
         info!("Routing slash command to Gemini: {}", selected_model);
         
@@ -674,6 +687,7 @@ pub async fn execute_claude_slash_command(
             processed_content,
             selected_model,
             project_path,
+            None, // stored config, strict dedup and the saved CLAUDE.md
             app.clone(),
             db,
             claude_state,
@@ -681,25 +695,27 @@ pub async fn execute_claude_slash_command(
             dedup_manager,
             isolation_manager,
             execution_state,
-        ).await;
-    } else if selected_model.contains(":latest") || selected_model.starts_with("llama") || 
-              selected_model.starts_with("phi") || selected_model.starts_with("mistral") ||
-              selected_model.starts_with("qwen") || selected_model.starts_with("codellama") {
+        ).await.map(|_| None);
+    } else if provider == Some(ModelProvider::Ollama) || (provider.is_none() && is_ollama_name) {
         // Route to Ollama
         info!("Routing slash command to Ollama: {}", selected_model);
         return crate::commands::ollama::execute_ollama_request(
             app,
-            selected_model,
+            canonical.map_or(selected_model, |m| m.id.to_string()),
             processed_content,
             project_path,
             None, // system_instruction
             None, // options
-        ).await;
+            None, // tags
+            None, // attachments
+        ).await.map(|_| None);
     } else {
         // Route to Claude (default)
         info!("Routing slash command to Claude: {}", selected_model);
     }
     
+    let session_id = session_id.unwrap_or_else(|| format!("slash-{}", uuid::Uuid::new_v4()));
+    
     // Find Claude binary for Claude routing
     let claude_binary = crate::claude_binary::find_claude_binary(&app)
         .map_err(|e| format!("Claude binary not found: {}", e))?;
@@ -714,7 +730,7 @@ pub async fn execute_claude_slash_command(
     // Add model specification if not auto
     if selected_model != "auto" {
         claude_args.push("--model".to_string());
-        claude_args.push(selected_model);
+        claude_args.push(crate::models::claude_cli_model(&selected_model));
     }
     
     // Add allowed tools as flags if specified
@@ -798,60 +814,96 @@ pub async fn execute_claude_slash_command(
         .stderr(Stdio::piped());
     
     // Spawn the process
-    let mut child = cmd.spawn()
+    let child = cmd.spawn()
         .map_err(|e| format!("Failed to spawn Claude process: {}", e))?;
     
     // Get the process ID for tracking
     let pid = child.id().unwrap_or(0);
-    info!("Spawned Claude process with PID: {}", pid);
+    info!("Spawned Claude process with PID: {} for session: {}", pid, session_id);
     
-    // Handle streaming output (similar to existing execute_claude_code)
-    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+    // Stream output in the background; the session can be stopped via `stop_execution`
+    let app_events = app.clone();
+    let stream_session_id = session_id.clone();
+    tokio::spawn(async move {
+        let control = app_events.state::<ExecutionControlState>();
+        let emit = |event: String, payload: serde_json::Value| {
+            let _ = emit_session_event(&app_events, &event, payload);
+        };
+        if let Err(e) = stream_slash_command(child, &stream_session_id, &control, emit).await {
+            error!("Slash command session {} failed: {}", stream_session_id, e);
+        }
+    });
     
+    Ok(Some(session_id))
+}
+
+/// Stream a slash command process using the `claude-output:{session_id}` event contract
+///
+/// The child is registered with execution control so `stop_execution` can kill it.
+/// Emits `claude-complete:{session_id}` with whether the command succeeded and
+/// returns the same value; a stopped command counts as unsuccessful.
+async fn stream_slash_command<F>(
+    mut child: tokio::process::Child,
+    session_id: &str,
+    control: &ExecutionControlState,
+    emit: F,
+) -> Result<bool, String>
+where
+    F: Fn(String, serde_json::Value),
+{
     use tokio::io::{AsyncBufReadExt, BufReader};
     
-    let stdout_reader = BufReader::new(stdout);
-    let stderr_reader = BufReader::new(stderr);
+    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+    
+    register_process(session_id.to_string(), child, control).await
+        .map_err(|e| format!("Failed to register slash command process: {}", e))?;
     
-    // Spawn tasks to read stdout and stderr
-    let app_stdout = app.clone();
-    let stdout_task = tokio::spawn(async move {
-        let mut lines = stdout_reader.lines();
+    let output_event = format!("claude-output:{}", session_id);
+    let error_event = format!("claude-error:{}", session_id);
+    let read_stdout = async {
+        let mut lines = BufReader::new(stdout).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             debug!("Claude stdout: {}", line);
-            let _ = app_stdout.emit("claude-slash-output", &line);
+            emit(output_event.clone(), serde_json::Value::String(line));
         }
-    });
-    
-    let app_stderr = app.clone();  
-    let stderr_task = tokio::spawn(async move {
-        let mut lines = stderr_reader.lines();
+    };
+    let read_stderr = async {
+        let mut lines = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             error!("Claude stderr: {}", line);
-            let _ = app_stderr.emit("claude-slash-error", &line);
+            emit(error_event.clone(), serde_json::Value::String(line));
         }
-    });
+    };
+    tokio::join!(read_stdout, read_stderr);
     
-    // Wait for completion in background
-    let app_wait = app.clone();
-    tokio::spawn(async move {
-        let _ = stdout_task.await;
-        let _ = stderr_task.await;
-        
-        match child.wait().await {
+    // A missing process means `stop_execution` already killed it
+    let child = control.active_processes.lock().await.remove(session_id);
+    let success = match child {
+        Some(mut child) => match child.wait().await {
             Ok(status) => {
                 info!("Claude slash command completed with status: {}", status);
-                let _ = app_wait.emit("claude-slash-complete", status.success());
+                status.success()
             }
             Err(e) => {
                 error!("Failed to wait for Claude process: {}", e);
-                let _ = app_wait.emit("claude-slash-complete", false);
+                false
             }
+        },
+        None => {
+            info!("Slash command session {} was stopped", session_id);
+            false
         }
-    });
+    };
     
-    Ok(())
+    if let Some(state) = control.sessions.lock().await.get_mut(session_id) {
+        if state.status != ExecutionStatus::Stopped {
+            state.status = if success { ExecutionStatus::Completed } else { ExecutionStatus::Error };
+        }
+    }
+    emit(format!("claude-complete:{}", session_id), serde_json::Value::Bool(success));
+    
+    Ok(success)
 }
 
 /// Delete a slash command
@@ -912,3 +964,289 @@ fn remove_empty_dirs(dir: &Path) -> Result<()> {
     
     Ok(())
 }
+
+lazy_static::lazy_static! {
+    /// `@path` file references and `@agent-name` agent mentions, not email addresses
+    static ref AT_REFERENCE: Regex = Regex::new(r#"(?:^|[\s(])@([^\s`'"()\[\]]+)"#).unwrap();
+    /// Commands invoked from a template, written in backticks: `` `/ns:name args` ``
+    static ref COMMAND_REFERENCE: Regex = Regex::new(r"`(/[A-Za-z][\w-]*(?::[\w-]+)*)(?:\s[^`]*)?`").unwrap();
+}
+
+/// JSDoc-style tags, plus the `@types` package scope, that read like `@` references in templates
+const DOC_COMMENT_TAGS: &[&str] = &[
+    "param", "returns", "return", "type", "types", "typedef", "template", "throws", "example",
+    "see", "link", "deprecated", "since", "default", "property", "callback", "override",
+];
+
+/// Whether an `@` reference is a doc-comment tag such as `@param` or `@types/node`
+fn is_doc_comment_tag(reference: &str) -> bool {
+    let tag = reference.split(['/', '{', '.']).next().unwrap_or(reference);
+    DOC_COMMENT_TAGS.contains(&tag.to_lowercase().as_str())
+}
+
+/// The lines of `content` outside `/** ... */` blocks and `///` doc comments
+fn lines_outside_doc_comments(content: &str) -> impl Iterator<Item = &str> {
+    let mut in_block = false;
+    content.lines().filter(move |line| {
+        let line = line.trim_start();
+        if in_block {
+            in_block = !line.contains("*/");
+            return false;
+        }
+        if line.starts_with("/**") {
+            in_block = !line.contains("*/");
+            return false;
+        }
+        !line.starts_with("///")
+    })
+}
+
+/// A slash command that would fail or misbehave when run, with why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrokenSlashCommand {
+    pub id: String,
+    pub full_command: String,
+    pub file_path: String,
+    pub reasons: Vec<String>,
+}
+
+/// Problems with a template's syntax, independent of what it references
+fn template_problems(content: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    if content.trim().is_empty() {
+        problems.push("Template is empty".to_string());
+    }
+    if content.lines().next() == Some("---") {
+        problems.push("Frontmatter is not closed or is not valid YAML".to_string());
+    }
+    for (number, line) in content.lines().enumerate() {
+        for (start, _) in line.match_indices("!`") {
+            if !line[start + 2..].contains('`') {
+                problems.push(format!("Bash command on line {} is missing its closing backtick", number + 1));
+            }
+        }
+    }
+    problems
+}
+
+/// Agent names a command may mention: app agents and `.claude/agents` files, lowercased
+pub fn known_agent_names(conn: Option<&rusqlite::Connection>, project_path: Option<&Path>) -> HashSet<String> {
+    let mut names = HashSet::new();
+    if let Some(conn) = conn {
+        if let Ok(mut stmt) = conn.prepare("SELECT name FROM agents") {
+            if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
+                names.extend(rows.flatten().map(|name| name.to_lowercase()));
+            }
+        }
+    }
+    let agent_dirs = project_path
+        .map(|project| project.join(".claude").join("agents"))
+        .into_iter()
+        .chain(super::claude_dir::claude_dir_path().ok().map(|claude_dir| claude_dir.join("agents")));
+    for dir in agent_dirs {
+        let mut files = Vec::new();
+        if find_markdown_files(&dir, &mut files).is_ok() {
+            names.extend(
+                files.iter()
+                    .filter_map(|file| file.file_stem())
+                    .map(|stem| stem.to_string_lossy().to_lowercase()),
+            );
+        }
+    }
+    names
+}
+
+/// The file-backed commands among `commands` whose file, template or references are broken
+///
+/// Relative file references resolve against `project_path` and are only checked when it's known.
+pub fn find_broken_commands(
+    commands: &[SlashCommand],
+    project_path: Option<&Path>,
+    agent_names: &HashSet<String>,
+) -> Vec<BrokenSlashCommand> {
+    let command_names: HashSet<&str> = commands.iter().map(|cmd| cmd.full_command.as_str()).collect();
+    let mut broken = Vec::new();
+
+    for command in commands.iter().filter(|cmd| !cmd.file_path.is_empty()) {
+        let mut reasons = Vec::new();
+        if !Path::new(&command.file_path).is_file() {
+            reasons.push(format!("Command file {} no longer exists", command.file_path));
+        }
+        reasons.extend(template_problems(&command.content));
+
+        let references = lines_outside_doc_comments(&command.content)
+            .flat_map(|line| AT_REFERENCE.captures_iter(line));
+        for caps in references {
+            let reference = caps[1].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            if reference.is_empty() || reference.contains('$') || is_doc_comment_tag(reference) {
+                continue;
+            }
+            if let Some(agent) = reference.strip_prefix("agent-") {
+                if !agent_names.contains(&agent.to_lowercase()) {
+                    reasons.push(format!("References agent {} which does not exist", agent));
+                }
+                continue;
+            }
+            let path = match reference.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+                None if Path::new(reference).is_absolute() => Some(PathBuf::from(reference)),
+                None => project_path.map(|project| project.join(reference)),
+            };
+            if path.is_some_and(|path| !path.exists()) {
+                reasons.push(format!("References file {} which does not exist", reference));
+            }
+        }
+
+        for caps in COMMAND_REFERENCE.captures_iter(&command.content) {
+            if !command_names.contains(&caps[1]) {
+                reasons.push(format!("Invokes command {} which does not exist", &caps[1]));
+            }
+        }
+
+        if !reasons.is_empty() {
+            reasons.dedup();
+            broken.push(BrokenSlashCommand {
+                id: command.id.clone(),
+                full_command: command.full_command.clone(),
+                file_path: command.file_path.clone(),
+                reasons,
+            });
+        }
+    }
+    broken
+}
+
+/// Check every slash command's file, template and references, returning the broken ones
+#[tauri::command]
+pub async fn validate_slash_commands(
+    project_path: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Vec<BrokenSlashCommand>, String> {
+    let commands = slash_commands_list(project_path.clone(), app.clone()).await?;
+    let project_path = project_path.map(PathBuf::from);
+    let agent_names = match app.try_state::<AgentDb>() {
+        Some(db) => known_agent_names(Some(&*db.lock_conn()), project_path.as_deref()),
+        None => known_agent_names(None, project_path.as_deref()),
+    };
+
+    let broken = find_broken_commands(&commands, project_path.as_deref(), &agent_names);
+    for command in &broken {
+        log::warn!("Slash command {} is broken: {}", command.full_command, command.reasons.join("; "));
+    }
+    info!("Validated {} slash commands, {} broken", commands.len(), broken.len());
+    Ok(broken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use std::sync::Mutex;
+
+    #[cfg(unix)]
+    fn spawn_shell(script: &str) -> tokio::process::Child {
+        tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_multi_line_output_streams_session_events() {
+        let control = ExecutionControlState::default();
+        let events = Mutex::new(Vec::new());
+        let child = spawn_shell("printf 'first\\nsecond\\nthird\\n'");
+
+        let success = stream_slash_command(child, "slash-test", &control, |event, payload| {
+            events.lock().unwrap().push((event, payload));
+        }).await.unwrap();
+
+        assert!(success);
+        let events = events.into_inner().unwrap();
+        let output: Vec<_> = events.iter()
+            .filter(|(event, _)| event == "claude-output:slash-test")
+            .map(|(_, payload)| payload.as_str().unwrap())
+            .collect();
+        assert_eq!(output, vec!["first", "second", "third"]);
+        assert_eq!(events.last().unwrap(), &("claude-complete:slash-test".to_string(), serde_json::json!(true)));
+        assert_eq!(control.sessions.lock().await["slash-test"].status, ExecutionStatus::Completed);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stopped_command_completes_unsuccessfully() {
+        let control = ExecutionControlState::default();
+        let processes = control.active_processes.clone();
+        let child = spawn_shell("echo started; exec sleep 30");
+
+        // Kill the process the way `stop_execution` does
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            let child = processes.lock().await.remove("slash-stop");
+            child.unwrap().kill().await.unwrap();
+        });
+
+        let started = std::time::Instant::now();
+        let success = stream_slash_command(child, "slash-stop", &control, |_, _| {}).await.unwrap();
+        assert!(!success);
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_dangling_command_is_reported_and_valid_one_is_not() {
+        let project = tempfile::tempdir().unwrap();
+        let commands_dir = project.path().join(".claude").join("commands");
+        let agents_dir = project.path().join(".claude").join("agents");
+        fs::create_dir_all(&commands_dir).unwrap();
+        fs::create_dir_all(&agents_dir).unwrap();
+        fs::create_dir_all(project.path().join("src")).unwrap();
+        fs::write(project.path().join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(agents_dir.join("reviewer.md"), "Reviews code\n").unwrap();
+        fs::write(
+            commands_dir.join("review.md"),
+            "---\ndescription: Review main\n---\nAsk @agent-reviewer to review @src/main.rs, then run `/check-style`.\n",
+        ).unwrap();
+        fs::write(commands_dir.join("check-style.md"), "Run !`cargo fmt --check` and report $ARGUMENTS.\n").unwrap();
+        fs::write(
+            commands_dir.join("release.md"),
+            "Read @docs/RELEASING.md, ask @agent-publisher, run `/changelog` and !`git tag\n",
+        ).unwrap();
+        // Doc-comment tags and the @types scope are not file or agent references
+        fs::write(
+            commands_dir.join("document.md"),
+            "Add JSDoc with @param and @returns to @src/main.rs, installing @types/node if needed.\n\
+             /**\n * @example add(1, 2)\n * @throws @src/missing.rs\n */\n",
+        ).unwrap();
+
+        let mut files = Vec::new();
+        find_markdown_files(&commands_dir, &mut files).unwrap();
+        let commands: Vec<SlashCommand> = files
+            .iter()
+            .map(|file| load_command_from_file(file, &commands_dir, "project").unwrap())
+            .collect();
+        let agents = known_agent_names(None, Some(project.path()));
+        assert!(agents.contains("reviewer"));
+
+        let broken = find_broken_commands(&commands, Some(project.path()), &agents);
+        assert_eq!(broken.len(), 1, "{:?}", broken);
+        assert_eq!(broken[0].full_command, "/release");
+        assert_eq!(
+            broken[0].reasons,
+            [
+                "Bash command on line 1 is missing its closing backtick",
+                "References file docs/RELEASING.md which does not exist",
+                "References agent publisher which does not exist",
+                "Invokes command /changelog which does not exist",
+            ]
+        );
+
+        fs::remove_file(commands_dir.join("check-style.md")).unwrap();
+        let broken = find_broken_commands(&commands, Some(project.path()), &agents);
+        let check_style = broken.iter().find(|cmd| cmd.full_command == "/check-style").unwrap();
+        assert!(check_style.reasons[0].contains("no longer exists"));
+    }
+}
@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value as JsonValue};
 use std::collections::HashMap;
 use tauri::{AppHandle, Manager, State};
-use super::agents::AgentDb;
+use super::agents::{AgentDb, DB_BUSY_TIMEOUT};
+use super::debug_system::{enforce_debug_retention, load_debug_retention_config};
+use super::settings_diff::{unset, Setting};
 
 /// Represents metadata about a database table
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,7 +51,7 @@ pub struct QueryResult {
 /// List all tables in the database
 #[tauri::command]
 pub async fn storage_list_tables(db: State<'_, AgentDb>) -> Result<Vec<TableInfo>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Query for all tables
     let mut stmt = conn
@@ -116,7 +118,7 @@ pub async fn storage_read_table(
     pageSize: i64,
     searchQuery: Option<String>,
 ) -> Result<TableData, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Validate table name to prevent SQL injection
     if !is_valid_table_name(&conn, &tableName)? {
@@ -192,20 +194,7 @@ pub async fn storage_read_table(
             let mut row_map = Map::new();
             
             for (idx, col) in columns.iter().enumerate() {
-                let value = match row.get_ref(idx)? {
-                    ValueRef::Null => JsonValue::Null,
-                    ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                    ValueRef::Real(f) => {
-                        if let Some(n) = serde_json::Number::from_f64(f) {
-                            JsonValue::Number(n)
-                        } else {
-                            JsonValue::String(f.to_string())
-                        }
-                    }
-                    ValueRef::Text(s) => JsonValue::String(String::from_utf8_lossy(s).to_string()),
-                    ValueRef::Blob(b) => JsonValue::String(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, b)),
-                };
-                row_map.insert(col.name.clone(), value);
+                row_map.insert(col.name.clone(), sql_to_json_value(row.get_ref(idx)?));
             }
             
             Ok(row_map)
@@ -234,7 +223,7 @@ pub async fn storage_update_row(
     primaryKeyValues: HashMap<String, JsonValue>,
     updates: HashMap<String, JsonValue>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Validate table name
     if !is_valid_table_name(&conn, &tableName)? {
@@ -289,7 +278,7 @@ pub async fn storage_delete_row(
     tableName: String,
     primaryKeyValues: HashMap<String, JsonValue>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Validate table name
     if !is_valid_table_name(&conn, &tableName)? {
@@ -330,7 +319,7 @@ pub async fn storage_insert_row(
     tableName: String,
     values: HashMap<String, JsonValue>,
 ) -> Result<i64, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Validate table name
     if !is_valid_table_name(&conn, &tableName)? {
@@ -369,7 +358,7 @@ pub async fn storage_execute_sql(
     db: State<'_, AgentDb>,
     query: String,
 ) -> Result<QueryResult, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.lock_conn();
     
     // Check if it's a SELECT query
     let is_select = query.trim().to_uppercase().starts_with("SELECT");
@@ -435,8 +424,7 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
     {
         // Drop all existing tables within a scoped block
         let db_state = app.state::<AgentDb>();
-        let conn = db_state.0.lock()
-            .map_err(|e| e.to_string())?;
+        let conn = db_state.lock_conn();
         
         // Disable foreign key constraints temporarily to allow dropping tables
         conn.execute("PRAGMA foreign_keys = OFF", [])
@@ -463,23 +451,220 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
     // Update the managed state with the new connection
     {
         let db_state = app.state::<AgentDb>();
-        let mut conn_guard = db_state.0.lock()
-            .map_err(|e| e.to_string())?;
+        let mut conn_guard = db_state.lock_conn();
         *conn_guard = new_conn;
     }
     
     // Run VACUUM to optimize the database
-    {
-        let db_state = app.state::<AgentDb>();
-        let conn = db_state.0.lock()
-            .map_err(|e| e.to_string())?;
-        conn.execute("VACUUM", [])
-            .map_err(|e| e.to_string())?;
+    with_own_connection(&app, |conn| {
+        conn.execute("VACUUM", []).map(|_| ()).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Run `work` on a connection of its own to the database file, off the async runtime
+///
+/// VACUUM rewrites the whole file; doing it on the shared connection would hold
+/// its lock, and stall every other command, for as long as that takes.
+async fn with_own_connection<T, F>(app: &AppHandle, work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Connection) -> Result<T, String> + Send + 'static,
+{
+    let db_path = {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        conn.path()
+            .filter(|path| !path.is_empty())
+            .map(std::path::PathBuf::from)
+            .ok_or("The database has no file to maintain")?
+    };
+    tokio::task::spawn_blocking(move || {
+        let conn = Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database for maintenance: {}", e))?;
+        conn.busy_timeout(DB_BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+        work(&conn)
+    })
+    .await
+    .map_err(|e| format!("Database maintenance task failed: {}", e))?
+}
+
+pub(crate) const MAINTENANCE_INTERVAL_SETTINGS_KEY: &str = "database_maintenance_interval_hours";
+const MAINTENANCE_LAST_RUN_SETTINGS_KEY: &str = "database_maintenance_last_run";
+
+/// The last run is bookkeeping rather than a setting, so only the schedule is listed
+pub(crate) const SETTINGS: &[Setting] = &[Setting::new(MAINTENANCE_INTERVAL_SETTINGS_KEY, stored_interval, unset)];
+
+/// A stored maintenance interval; 0 counts as unscheduled
+fn stored_interval(stored: &str) -> Result<JsonValue, String> {
+    let hours = stored.parse::<u64>().map_err(|e| e.to_string())?;
+    Ok(if hours == 0 { JsonValue::Null } else { hours.into() })
+}
+
+/// How often the scheduler checks whether maintenance is due
+pub const MAINTENANCE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Outcome of a VACUUM / ANALYZE / WAL checkpoint pass
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceReport {
+    pub size_before_bytes: i64,
+    pub size_after_bytes: i64,
+    pub free_pages_before: i64,
+    pub free_pages_after: i64,
+    pub duration_ms: u64,
+    pub completed_at: String,
+}
+
+/// Database size in bytes and number of unused pages
+pub(crate) fn page_stats(conn: &Connection) -> Result<(i64, i64), String> {
+    let pragma = |name: &str| -> Result<i64, String> {
+        conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read {}: {}", name, e))
+    };
+    Ok((pragma("page_count")? * pragma("page_size")?, pragma("freelist_count")?))
+}
+
+/// Checkpoint the WAL, rebuild the file and refresh planner statistics
+///
+/// Refuses to run inside an open transaction or while another connection
+/// keeps the WAL busy, so it never competes with in-flight writes.
+pub fn run_database_maintenance(conn: &Connection) -> Result<MaintenanceReport, String> {
+    if !conn.is_autocommit() {
+        return Err("A transaction is in progress; skipping database maintenance".to_string());
     }
-    
+
+    let started = std::time::Instant::now();
+    let (size_before_bytes, free_pages_before) = page_stats(conn)?;
+
+    let busy: i64 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to checkpoint WAL: {}", e))?;
+    if busy != 0 {
+        return Err("Other connections are writing; skipping database maintenance".to_string());
+    }
+
+    conn.execute_batch("VACUUM; ANALYZE;")
+        .map_err(|e| format!("Database maintenance failed: {}", e))?;
+
+    let (size_after_bytes, free_pages_after) = page_stats(conn)?;
+    Ok(MaintenanceReport {
+        size_before_bytes,
+        size_after_bytes,
+        free_pages_before,
+        free_pages_after,
+        duration_ms: started.elapsed().as_millis() as u64,
+        completed_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Vacuum and analyze the database, unless agent runs are writing to it
+#[tauri::command]
+pub async fn database_maintenance(app: AppHandle) -> Result<MaintenanceReport, String> {
+    let running = app
+        .state::<crate::process::ProcessRegistryState>()
+        .0
+        .get_running_agent_processes()?
+        .len();
+    if running > 0 {
+        return Err(format!(
+            "{} agent run(s) in progress; database maintenance postponed",
+            running
+        ));
+    }
+
+    let report = with_own_connection(&app, run_database_maintenance).await?;
+    let db = app.state::<AgentDb>();
+    let conn = db.lock_conn();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![MAINTENANCE_LAST_RUN_SETTINGS_KEY, chrono::Utc::now().timestamp().to_string()],
+    ).map_err(|e| format!("Failed to record maintenance run: {}", e))?;
+
+    log::info!(
+        "Database maintenance reclaimed {} bytes ({} free pages) in {}ms",
+        report.size_before_bytes - report.size_after_bytes,
+        report.free_pages_before,
+        report.duration_ms
+    );
+    Ok(report)
+}
+
+fn read_setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+}
+
+/// Hours between scheduled maintenance runs, `None` when unscheduled
+#[tauri::command]
+pub async fn get_database_maintenance_schedule(db: State<'_, AgentDb>) -> Result<Option<u64>, String> {
+    let conn = db.lock_conn();
+    Ok(read_setting(&conn, MAINTENANCE_INTERVAL_SETTINGS_KEY).and_then(|value| value.parse().ok()))
+}
+
+/// Schedule maintenance every `interval_hours`; `None` or 0 turns it off
+#[tauri::command]
+pub async fn set_database_maintenance_schedule(
+    db: State<'_, AgentDb>,
+    interval_hours: Option<u64>,
+) -> Result<(), String> {
+    let conn = db.lock_conn();
+    match interval_hours.filter(|hours| *hours > 0) {
+        Some(hours) => conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![MAINTENANCE_INTERVAL_SETTINGS_KEY, hours.to_string()],
+        ),
+        None => conn.execute(
+            "DELETE FROM app_settings WHERE key = ?1",
+            params![MAINTENANCE_INTERVAL_SETTINGS_KEY],
+        ),
+    }
+    .map_err(|e| format!("Failed to save maintenance schedule: {}", e))?;
     Ok(())
 }
 
+/// Whether the configured interval has passed since the last maintenance run
+fn maintenance_due(conn: &Connection, now: i64) -> bool {
+    let Some(interval_hours) = read_setting(conn, MAINTENANCE_INTERVAL_SETTINGS_KEY)
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|hours| *hours > 0)
+    else {
+        return false;
+    };
+    let last_run = read_setting(conn, MAINTENANCE_LAST_RUN_SETTINGS_KEY)
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(0);
+    now - last_run >= interval_hours * 60 * 60
+}
+
+/// One scheduled maintenance check, running maintenance if it is due
+///
+/// Debug table retention is enforced on every check, whether or not a VACUUM is due.
+pub async fn run_scheduled_database_maintenance(app: AppHandle) -> Result<(), String> {
+    let due = {
+        let db = app.state::<AgentDb>();
+        let conn = db.lock_conn();
+        let now = chrono::Utc::now().timestamp();
+        let retention = load_debug_retention_config(&conn);
+        match enforce_debug_retention(&conn, &retention, now) {
+            Ok(report) if report.total() > 0 => {
+                log::info!("Pruned {} debug row(s) past their retention limits", report.total())
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to enforce debug retention: {}", e),
+        }
+        maintenance_due(&conn, now)
+    };
+    if !due {
+        return Ok(());
+    }
+
+    database_maintenance(app).await.map(|_| ())
+}
+
 /// Helper function to validate table name exists
 fn is_valid_table_name(conn: &Connection, table_name: &str) -> Result<bool, String> {
     let count: i64 = conn
@@ -493,8 +678,25 @@ fn is_valid_table_name(conn: &Connection, table_name: &str) -> Result<bool, Stri
     Ok(count > 0)
 }
 
+/// Helper function to convert SQL value to JSON value
+pub(crate) fn sql_to_json_value(value: ValueRef) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
+        ValueRef::Real(f) => {
+            if let Some(n) = serde_json::Number::from_f64(f) {
+                JsonValue::Number(n)
+            } else {
+                JsonValue::String(f.to_string())
+            }
+        }
+        ValueRef::Text(s) => JsonValue::String(String::from_utf8_lossy(s).to_string()),
+        ValueRef::Blob(b) => JsonValue::String(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, b)),
+    }
+}
+
 /// Helper function to convert JSON value to SQL value
-fn json_to_sql_value(value: &JsonValue) -> Result<Box<dyn rusqlite::ToSql>, String> {
+pub(crate) fn json_to_sql_value(value: &JsonValue) -> Result<Box<dyn rusqlite::ToSql>, String> {
     match value {
         JsonValue::Null => Ok(Box::new(rusqlite::types::Null)),
         JsonValue::Bool(b) => Ok(Box::new(*b)),
@@ -513,4 +715,50 @@ fn json_to_sql_value(value: &JsonValue) -> Result<Box<dyn rusqlite::ToSql>, Stri
 }
 
 /// Initialize the agents database (re-exported from agents module)
-use super::agents::init_database; 
\ No newline at end of file
+use super::agents::init_database; 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_reclaims_free_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open(dir.path().join("fragmented.db")).unwrap();
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE error_knowledge (id INTEGER PRIMARY KEY, body TEXT NOT NULL);",
+        )
+        .unwrap();
+        for i in 0..2_000 {
+            conn.execute(
+                "INSERT INTO error_knowledge (body) VALUES (?1)",
+                params![format!("{:0>500}", i)],
+            )
+            .unwrap();
+        }
+        conn.execute("DELETE FROM error_knowledge WHERE id % 10 != 0", []).unwrap();
+
+        let report = run_database_maintenance(&conn).unwrap();
+        assert!(report.free_pages_before > 0);
+        assert!(report.free_pages_after < report.free_pages_before);
+        assert!(report.size_after_bytes < report.size_before_bytes);
+
+        conn.execute_batch("BEGIN").unwrap();
+        assert!(run_database_maintenance(&conn).is_err());
+        conn.execute_batch("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_maintenance_due_follows_schedule() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)", []).unwrap();
+        assert!(!maintenance_due(&conn, 100_000));
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, '24'), (?2, '10000')",
+            params![MAINTENANCE_INTERVAL_SETTINGS_KEY, MAINTENANCE_LAST_RUN_SETTINGS_KEY],
+        ).unwrap();
+        assert!(!maintenance_due(&conn, 10_000 + 23 * 3600));
+        assert!(maintenance_due(&conn, 10_000 + 24 * 3600));
+    }
+}
@@ -5,6 +5,7 @@ use log::{info, warn, error, debug};
 use anyhow::{Context, Result};
 
 use super::agents::AgentDb;
+use super::intelligent_routing::{PatternMatcher, RoutingResult, ToolType};
 use super::mcp::{MCPServer, MCPServerConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +42,35 @@ pub struct MCPIntegrationResult {
     pub tools_available: Vec<MCPToolInfo>,
     pub execution_time_ms: u64,
     pub error_message: Option<String>,
+    /// The invocation plan the request followed (or would follow, in plan-only mode)
+    #[serde(default)]
+    pub plan: Option<MCPExecutionPlan>,
+}
+
+/// One MCP server a request would use, in invocation order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPPlannedInvocation {
+    pub order: usize,
+    pub server_name: String,
+    pub tools: Vec<String>,
+    pub reason: String,
+}
+
+/// A configured server that would not be used, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPSkippedServer {
+    pub server_name: String,
+    pub reason: String,
+}
+
+/// What `execute_with_universal_mcp` intends to invoke for a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPExecutionPlan {
+    pub invocations: Vec<MCPPlannedInvocation>,
+    pub skipped: Vec<MCPSkippedServer>,
+    pub routing: RoutingResult,
+    /// False in plan-only mode, where installed servers are not queried
+    pub availability_checked: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,7 +98,7 @@ pub async fn get_universal_mcp_config(
 
     // Scope the database connection to avoid Send issues
     let config_result = {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let conn = db.lock_conn();
 
         // Check if configuration exists
         conn.query_row(
@@ -178,7 +208,7 @@ pub async fn save_universal_mcp_config(
 
     // Scope the database connection to avoid Send issues
     {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let conn = db.lock_conn();
 
         // Upsert the configuration
         conn.execute(
@@ -193,98 +223,209 @@ pub async fn save_universal_mcp_config(
 }
 
 /// Execute model request with MCP integration
+///
+/// With `plan_only`, routing and server selection run but nothing is contacted:
+/// the returned result carries the plan and no servers are listed, tested or executed.
 #[command]
 pub async fn execute_with_universal_mcp(
     provider: String,
     model_id: String,
     prompt: String,
     context: Option<String>,
+    plan_only: Option<bool>,
     app_handle: AppHandle,
     db: State<'_, AgentDb>,
 ) -> Result<MCPIntegrationResult, String> {
-    let start_time = std::time::Instant::now();
-    
     // Get MCP configuration for this model
-    let config = get_universal_mcp_config(provider.clone(), model_id.clone(), db.clone()).await?
-        .unwrap_or_else(|| {
-            // Fallback to default if no config exists
-            tauri::async_runtime::block_on(async {
-                create_default_mcp_config(
-                    match provider.as_str() {
-                        "claude" => ModelProvider::Claude,
-                        "gemini" => ModelProvider::Gemini,
-                        "ollama" => ModelProvider::Ollama,
-                        _ => ModelProvider::Claude,
-                    },
-                    model_id.clone()
-                ).await.unwrap_or_default()
-            })
+    let config = match get_universal_mcp_config(provider.clone(), model_id.clone(), db.clone()).await? {
+        Some(config) => config,
+        // Fallback to default if no config exists
+        None => create_default_mcp_config(
+            match provider.as_str() {
+                "claude" => ModelProvider::Claude,
+                "gemini" => ModelProvider::Gemini,
+                "ollama" => ModelProvider::Ollama,
+                _ => ModelProvider::Claude,
+            },
+            model_id.clone(),
+        ).await.unwrap_or_default(),
+    };
+
+    let ops = TauriMcpOps { app_handle };
+    Ok(run_universal_mcp(config, model_id, &prompt, context.as_deref(), plan_only.unwrap_or(false), &ops).await)
+}
+
+/// Server-facing operations used by `execute_with_universal_mcp`
+#[async_trait::async_trait]
+trait McpServerOps {
+    async fn list_servers(&self) -> Result<Vec<MCPServer>, String>;
+    async fn test_connection(&self, server_name: &str) -> Result<String, String>;
+    async fn execute(
+        &self,
+        provider: &ModelProvider,
+        model_id: &str,
+        prompt: &str,
+        context: Option<&str>,
+        tools: &[MCPToolInfo],
+    ) -> Result<String, String>;
+}
+
+struct TauriMcpOps {
+    app_handle: AppHandle,
+}
+
+#[async_trait::async_trait]
+impl McpServerOps for TauriMcpOps {
+    async fn list_servers(&self) -> Result<Vec<MCPServer>, String> {
+        super::mcp::mcp_list(self.app_handle.clone()).await
+    }
+
+    async fn test_connection(&self, server_name: &str) -> Result<String, String> {
+        let result = super::mcp::mcp_test_connection(self.app_handle.clone(), server_name.to_string()).await?;
+        if result.success {
+            Ok(result.message)
+        } else {
+            Err(result.message)
+        }
+    }
+
+    async fn execute(
+        &self,
+        provider: &ModelProvider,
+        model_id: &str,
+        prompt: &str,
+        context: Option<&str>,
+        tools: &[MCPToolInfo],
+    ) -> Result<String, String> {
+        execute_model_with_mcp_context(provider, model_id, prompt, context, tools, self.app_handle.clone())
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Decide which configured servers a request would use, highest priority first.
+/// `installed` is `None` when availability hasn't been checked.
+fn build_mcp_plan(
+    config: &UniversalMCPConfig,
+    installed: Option<&[MCPServer]>,
+    routing: RoutingResult,
+) -> MCPExecutionPlan {
+    let mut candidates: Vec<(&String, Option<&MCPServerPreference>)> = config.mcp_servers.iter()
+        .map(|name| (name, config.server_preferences.get(name)))
+        .collect();
+    // Stable sort keeps configuration order among equal priorities
+    candidates.sort_by_key(|(_, pref)| std::cmp::Reverse(pref.map(|p| p.priority).unwrap_or(0)));
+
+    let mut invocations = Vec::new();
+    let mut skipped = Vec::new();
+    for (name, preference) in candidates {
+        let skip_reason = match preference {
+            None => Some("no preferences configured for this model"),
+            Some(pref) if !pref.enabled => Some("disabled in preferences"),
+            _ if installed.is_some_and(|servers| !servers.iter().any(|s| &s.name == name)) => {
+                Some("not installed")
+            }
+            _ => None,
+        };
+        if let Some(reason) = skip_reason {
+            skipped.push(MCPSkippedServer { server_name: name.clone(), reason: reason.to_string() });
+            continue;
+        }
+        let preference = preference.expect("skipped above when missing");
+
+        let requested_by = routing.invocations.iter().find_map(|inv| match &inv.tool_type {
+            ToolType::McpServer(server) if server.eq_ignore_ascii_case(name) => Some(inv.reason.clone()),
+            _ => None,
+        });
+        let reason = match requested_by {
+            Some(why) => format!("priority {}; requested by prompt ({})", preference.priority, why),
+            None => format!("priority {}; enabled for {}", preference.priority, config.model_id),
+        };
+
+        let mut tools: Vec<String> = preference.tool_mapping.keys().cloned().collect();
+        tools.sort();
+
+        invocations.push(MCPPlannedInvocation {
+            order: invocations.len() + 1,
+            server_name: name.clone(),
+            tools,
+            reason,
         });
+    }
+
+    MCPExecutionPlan {
+        invocations,
+        skipped,
+        routing,
+        availability_checked: installed.is_some(),
+    }
+}
+
+async fn run_universal_mcp(
+    config: UniversalMCPConfig,
+    model_id: String,
+    prompt: &str,
+    context: Option<&str>,
+    plan_only: bool,
+    ops: &impl McpServerOps,
+) -> MCPIntegrationResult {
+    let start_time = std::time::Instant::now();
+    let routing = PatternMatcher::shared().analyze_input(prompt);
+
+    if plan_only {
+        let plan = build_mcp_plan(&config, None, routing);
+        info!("Planned {} MCP invocation(s) for {} without executing", plan.invocations.len(), model_id);
+        return MCPIntegrationResult {
+            success: true,
+            provider: config.provider,
+            model_id,
+            servers_used: vec![],
+            tools_available: vec![],
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            error_message: None,
+            plan: Some(plan),
+        };
+    }
 
     let mut servers_used = Vec::new();
     let mut tools_available = Vec::new();
     let mut success = false;
     let mut error_message = None;
+    let mut plan = None;
 
     // Get available MCP servers
-    match super::mcp::mcp_list(app_handle.clone()).await {
+    match ops.list_servers().await {
         Ok(available_servers) => {
-            // Filter servers based on configuration
-            let configured_servers: Vec<_> = available_servers.into_iter()
-                .filter(|server| config.mcp_servers.contains(&server.name))
-                .collect();
-
-            // Sort by priority
-            let mut prioritized_servers = configured_servers.clone();
-            prioritized_servers.sort_by_key(|server| {
-                config.server_preferences.get(&server.name)
-                    .map(|pref| 10 - pref.priority) // Reverse for descending order
-                    .unwrap_or(10)
-            });
-
-            // Test and use servers
-            for server in prioritized_servers {
-                if let Some(preferences) = config.server_preferences.get(&server.name) {
-                    if !preferences.enabled {
-                        continue;
+            let execution_plan = build_mcp_plan(&config, Some(&available_servers), routing);
+
+            // Test and use servers in plan order
+            for invocation in &execution_plan.invocations {
+                match ops.test_connection(&invocation.server_name).await {
+                    Ok(status_msg) => {
+                        info!("MCP server {} connection successful: {}", invocation.server_name, status_msg);
+                        servers_used.push(invocation.server_name.clone());
+
+                        // Mock tool info (in real implementation, this would query the MCP server)
+                        tools_available.push(MCPToolInfo {
+                            server_name: invocation.server_name.clone(),
+                            tool_name: "filesystem_operations".to_string(),
+                            description: "File system operations via MCP".to_string(),
+                            available_for_provider: true,
+                            mapped_implementation: config.server_preferences.get(&invocation.server_name)
+                                .and_then(|pref| pref.tool_mapping.get("filesystem_operations").cloned()),
+                        });
                     }
-
-                    // Test server connection
-                    match super::mcp::mcp_test_connection(app_handle.clone(), server.name.clone()).await {
-                        Ok(status_msg) => {
-                            info!("MCP server {} connection successful: {}", server.name, status_msg);
-                            servers_used.push(server.name.clone());
-                            
-                            // Get available tools for this server
-                            if let Ok(server_config) = super::mcp::mcp_get(app_handle.clone(), server.name.clone()).await {
-                                // Mock tool info (in real implementation, this would query the MCP server)
-                                tools_available.push(MCPToolInfo {
-                                    server_name: server.name.clone(),
-                                    tool_name: "filesystem_operations".to_string(),
-                                    description: "File system operations via MCP".to_string(),
-                                    available_for_provider: true,
-                                    mapped_implementation: preferences.tool_mapping.get("filesystem_operations").cloned(),
-                                });
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to test MCP server {}: {}", server.name, e);
-                        }
+                    Err(e) => {
+                        warn!("Failed to test MCP server {}: {}", invocation.server_name, e);
                     }
                 }
             }
+            plan = Some(execution_plan);
 
             // Execute the actual model request with MCP context
-            match execute_model_with_mcp_context(
-                &config.provider,
-                &model_id,
-                &prompt,
-                context.as_deref(),
-                &tools_available,
-                app_handle.clone()
-            ).await {
+            match ops.execute(&config.provider, &model_id, prompt, context, &tools_available).await {
                 Ok(_) => success = true,
-                Err(e) => error_message = Some(e.to_string()),
+                Err(e) => error_message = Some(e),
             }
         }
         Err(e) => {
@@ -292,17 +433,16 @@ pub async fn execute_with_universal_mcp(
         }
     }
 
-    let execution_time = start_time.elapsed().as_millis() as u64;
-
-    Ok(MCPIntegrationResult {
+    MCPIntegrationResult {
         success,
         provider: config.provider,
         model_id,
         servers_used,
         tools_available,
-        execution_time_ms: execution_time,
+        execution_time_ms: start_time.elapsed().as_millis() as u64,
         error_message,
-    })
+        plan,
+    }
 }
 
 /// Execute model request with MCP context
@@ -434,6 +574,7 @@ pub async fn test_universal_mcp_integration(
             model.to_string(),
             test_prompt,
             None,
+            None,
             app_handle.clone(),
             db.clone()
         ).await {
@@ -454,6 +595,7 @@ pub async fn test_universal_mcp_integration(
                     tools_available: vec![],
                     execution_time_ms: 0,
                     error_message: Some(e),
+                    plan: None,
                 });
             }
         }
@@ -466,7 +608,7 @@ pub async fn test_universal_mcp_integration(
 pub async fn init_universal_mcp_tables(db: &State<'_, AgentDb>) -> Result<(), String> {
     // Scope the database connection to avoid Send issues
     {
-        let conn = db.0.lock().map_err(|e| format!("Database lock error: {}", e))?;
+        let conn = db.lock_conn();
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS universal_mcp_configs (
@@ -502,4 +644,122 @@ impl Default for UniversalMCPConfig {
             timeout_ms: 30000,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::mcp::ServerStatus;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingOps {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl McpServerOps for CountingOps {
+        async fn list_servers(&self) -> Result<Vec<MCPServer>, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![installed_server("filesystem"), installed_server("github")])
+        }
+
+        async fn test_connection(&self, _server_name: &str) -> Result<String, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("ok".to_string())
+        }
+
+        async fn execute(
+            &self,
+            _provider: &ModelProvider,
+            _model_id: &str,
+            _prompt: &str,
+            _context: Option<&str>,
+            _tools: &[MCPToolInfo],
+        ) -> Result<String, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("done".to_string())
+        }
+    }
+
+    fn installed_server(name: &str) -> MCPServer {
+        MCPServer {
+            name: name.to_string(),
+            transport: "stdio".to_string(),
+            command: None,
+            args: vec![],
+            env: HashMap::new(),
+            url: None,
+            scope: "user".to_string(),
+            is_active: true,
+            status: ServerStatus { running: true, error: None, last_checked: None, supervision: None },
+        }
+    }
+
+    fn preference(enabled: bool, priority: u8) -> MCPServerPreference {
+        MCPServerPreference {
+            enabled,
+            priority,
+            tool_mapping: HashMap::from([("read_file".to_string(), "read_file".to_string())]),
+            custom_prompts: HashMap::new(),
+        }
+    }
+
+    fn test_config() -> UniversalMCPConfig {
+        UniversalMCPConfig {
+            mcp_servers: vec![
+                "filesystem".to_string(),
+                "github".to_string(),
+                "disabled".to_string(),
+                "unconfigured".to_string(),
+            ],
+            server_preferences: HashMap::from([
+                ("filesystem".to_string(), preference(true, 5)),
+                ("github".to_string(), preference(true, 9)),
+                ("disabled".to_string(), preference(false, 10)),
+            ]),
+            ..UniversalMCPConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_only_makes_no_server_calls() {
+        let ops = CountingOps::default();
+        let result = run_universal_mcp(test_config(), "default".to_string(), "read a file", None, true, &ops).await;
+
+        assert_eq!(ops.calls.load(Ordering::SeqCst), 0);
+        assert!(result.success);
+        assert!(result.servers_used.is_empty());
+
+        let plan = result.plan.expect("plan-only mode returns a plan");
+        assert!(!plan.availability_checked);
+        let order: Vec<_> = plan.invocations.iter().map(|i| i.server_name.as_str()).collect();
+        assert_eq!(order, ["github", "filesystem"]);
+        assert_eq!(plan.invocations[0].order, 1);
+        assert_eq!(plan.invocations[0].tools, ["read_file"]);
+        let skipped: Vec<_> = plan.skipped.iter().map(|s| s.server_name.as_str()).collect();
+        assert_eq!(skipped, ["disabled", "unconfigured"]);
+    }
+
+    #[tokio::test]
+    async fn test_execution_follows_plan_order() {
+        let ops = CountingOps::default();
+        let result = run_universal_mcp(test_config(), "default".to_string(), "read a file", None, false, &ops).await;
+
+        // list + two connection tests + execute
+        assert_eq!(ops.calls.load(Ordering::SeqCst), 4);
+        assert!(result.success);
+        assert_eq!(result.servers_used, ["github", "filesystem"]);
+        assert!(result.plan.unwrap().availability_checked);
+    }
+
+    #[test]
+    fn test_plan_skips_servers_that_are_not_installed() {
+        let installed = [installed_server("filesystem")];
+        let routing = PatternMatcher::new().analyze_input("hello");
+        let plan = build_mcp_plan(&test_config(), Some(&installed), routing);
+
+        assert_eq!(plan.invocations.len(), 1);
+        assert_eq!(plan.invocations[0].server_name, "filesystem");
+        assert!(plan.skipped.iter().any(|s| s.server_name == "github" && s.reason == "not installed"));
+    }
+}
@@ -76,6 +76,17 @@ pub async fn execute_with_universal_tools(
     let mut auto_selected = false;
     let mut selection_reasoning = "User specified model".to_string();
 
+    // Generate unique session ID for this execution
+    let session_id = format!(
+        "universal-{}-{}",
+        request.model_id.replace(':', "-").replace('.', "-"),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    );
+    let started = std::time::Instant::now();
+
     // Auto model selection if requested or "auto" model specified
     if request.use_auto_selection || request.model_id == "auto" {
         info!("Using Auto Smart Selection for model recommendation");
@@ -83,6 +94,7 @@ pub async fn execute_with_universal_tools(
         let recommendation = get_intelligent_model_recommendation(
             request.prompt.clone(),
             request.context.clone(),
+            Some(session_id.clone()),
             app_handle.clone()
         ).await?;
 
@@ -94,16 +106,6 @@ pub async fn execute_with_universal_tools(
               final_model, recommendation.confidence);
     }
 
-    // Generate unique session ID for this execution
-    let session_id = format!(
-        "universal-{}-{}",
-        final_model.replace(':', "-").replace('.', "-"),
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
-    );
-
     // Check tool capabilities before execution
     let capabilities = check_model_capabilities(&final_model);
     info!("Model capabilities: MCP={}, Agents={}, Tools={}", 
@@ -151,6 +153,14 @@ pub async fn execute_with_universal_tools(
         _ => Err(format!("Unknown model provider for model: {}", final_model))
     };
 
+    crate::commands::routing_decisions::record_session_outcome(
+        &app_handle,
+        &session_id,
+        result.is_ok(),
+        None,
+        Some(started.elapsed().as_millis() as i64),
+    );
+
     match result {
         Ok(_) => {
             info!("Universal execution successful for model: {}", final_model);
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use async_trait::async_trait;
-use log::info;
+use log::{info, warn};
 use serde_json::{json, Value};
 use uuid::Uuid;
 use std::time::Instant;
@@ -12,6 +12,8 @@ use std::time::Instant;
 // Import existing command modules
 use crate::commands::mcp::mcp_list;
 use crate::commands::agents::AgentDb;
+use crate::commands::project_permissions::authorize_tool_call;
+use crate::commands::session_event_log::emit_session_event;
 use crate::commands::slash_commands::slash_commands_list;
 
 // =============================================================================
@@ -25,6 +27,7 @@ pub enum ToolType {
     Agent,
     SlashCommand,
     FileOperation,
+    ShellCommand,
     WebSearch,
     CodeAnalysis,
     Custom(String),
@@ -93,6 +96,9 @@ pub struct UniversalExecutionRequest {
     pub options: Option<HashMap<String, Value>>,
     pub use_auto_selection: bool,
     pub tools_requested: Option<Vec<String>>,
+    /// Session the run belongs to; a new one is started when absent
+    #[serde(default)]
+    pub session_id: Option<String>,
 }
 
 /// Universal execution result
@@ -101,6 +107,8 @@ pub struct UniversalExecutionResult {
     pub success: bool,
     pub model_used: String,
     pub session_id: String,
+    /// Output of the tools that ran
+    pub response: String,
     pub reasoning: String,
     pub error: Option<String>,
     pub auto_selected: bool,
@@ -656,6 +664,13 @@ pub async fn execute_universal_tool(
         history: vec![],
     };
     
+    // Keep file and shell access within what the project permits
+    {
+        let db = app_handle.state::<AgentDb>();
+        let conn = db.lock_conn();
+        authorize_tool_call(&conn, &tool.tool_type(), &parameters, &context)?;
+    }
+    
     // Create request
     let request = ToolExecutionRequest {
         tool_type: tool.tool_type(),
@@ -741,6 +756,118 @@ pub fn determine_provider(model_id: &str) -> String {
     }
 }
 
+/// Most tool runs one universal execution makes before it answers
+const MAX_TOOL_ITERATIONS: usize = 10;
+
+/// Run tools one iteration at a time, streaming progress on the session event contract
+///
+/// Each iteration emits a `system` message with the counter and bound on
+/// `claude-output:{session_id}`, then the call as a `tool_use` and its outcome as a
+/// `tool_result` with how long it took. A closing `result` message carries the
+/// final answer, the successful tools' output, followed by
+/// `claude-complete:{session_id}`. Returns the tools that succeeded and the answer.
+async fn run_tool_loop<X, Fut, E>(
+    session_id: &str,
+    model_id: &str,
+    tool_names: &[String],
+    tools_available: usize,
+    execute: X,
+    emit: E,
+) -> (Vec<String>, String)
+where
+    X: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<ToolExecutionResult, String>>,
+    E: Fn(String, Value),
+{
+    let output_event = format!("claude-output:{}", session_id);
+    let emit_message = |message: Value| emit(output_event.clone(), Value::String(message.to_string()));
+    let mut tools_executed = Vec::new();
+    let mut outputs = Vec::new();
+    let mut iterations = 0;
+    
+    for (index, tool_name) in tool_names.iter().take(MAX_TOOL_ITERATIONS).enumerate() {
+        let iteration = index + 1;
+        iterations = iteration;
+        emit_message(json!({
+            "type": "system",
+            "subtype": "tool_iteration",
+            "session_id": session_id,
+            "iteration": iteration,
+            "max_iterations": MAX_TOOL_ITERATIONS,
+            "tool": tool_name,
+        }));
+        
+        let tool_use_id = format!("{}-tool-{}", session_id, iteration);
+        emit_message(json!({
+            "type": "assistant",
+            "iteration": iteration,
+            "message": {
+                "role": "assistant",
+                "model": model_id,
+                "content": [{
+                    "type": "tool_use",
+                    "id": tool_use_id,
+                    "name": tool_name,
+                    "input": {}
+                }]
+            }
+        }));
+        
+        let started = Instant::now();
+        let outcome = execute(tool_name.clone()).await.and_then(|result| match result.error {
+            Some(error) if !result.success => Err(error),
+            _ if !result.success => Err(format!("Tool {} failed", tool_name)),
+            _ => Ok(result.output),
+        });
+        let duration_ms = started.elapsed().as_millis() as u64;
+        
+        let (content, is_error) = match outcome {
+            Ok(output) => {
+                tools_executed.push(tool_name.clone());
+                let text = match output {
+                    Value::String(text) => text,
+                    other => serde_json::to_string_pretty(&other).unwrap_or_default(),
+                };
+                outputs.push(text.clone());
+                (text, false)
+            }
+            Err(error) => {
+                warn!("Tool {} failed in session {}: {}", tool_name, session_id, error);
+                (error, true)
+            }
+        };
+        emit_message(json!({
+            "type": "user",
+            "iteration": iteration,
+            "message": {
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": tool_use_id,
+                    "content": content,
+                    "is_error": is_error,
+                    "duration_ms": duration_ms
+                }]
+            }
+        }));
+    }
+    
+    let answer = outputs.join("\n\n");
+    emit_message(json!({
+        "type": "result",
+        "subtype": "success",
+        "session_id": session_id,
+        "result": answer,
+        "num_turns": iterations,
+        "max_iterations": MAX_TOOL_ITERATIONS,
+        "tools_available": tools_available,
+        "tools_executed": tools_executed,
+    }));
+    emit(format!("claude-complete:{}", session_id), Value::Bool(true));
+    
+    (tools_executed, answer)
+}
+
 /// Execute with universal tools - enhanced main execution function
 #[command]
 pub async fn execute_with_universal_tools(
@@ -750,11 +877,16 @@ pub async fn execute_with_universal_tools(
     info!("Universal execution request - model: {}, tools: {:?}", 
           request.model_id, request.tools_requested);
     
+    // Fail early with one actionable message when nothing is configured
+    crate::commands::simple_model_validator::detect_available_providers(&app_handle).await
+        .ensure_any()?;
+    
     let registry = app_handle.state::<UniversalToolRegistry>();
-    let mut tools_executed = Vec::new();
     
-    // Generate session ID
-    let session_id = Uuid::new_v4().to_string();
+    // Continue the caller's session, or start one
+    let session_id = request.session_id.clone()
+        .filter(|id| !id.trim().is_empty())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
     
     // Get provider and adapter
     let provider = determine_provider(&request.model_id);
@@ -778,35 +910,41 @@ pub async fn execute_with_universal_tools(
     let enhanced_prompt = adapter.translate_prompt(&request.prompt, tools_to_use.clone()).await;
     
     // Execute tools if any are explicitly requested in the prompt
-    for tool_name in &tools_to_use {
-        if request.prompt.contains(tool_name) || request.prompt.contains(&tool_name.replace('_', " ")) {
+    let mentioned: Vec<String> = tools_to_use.iter()
+        .filter(|t| request.prompt.contains(t.as_str()) || request.prompt.contains(&t.replace('_', " ")))
+        .cloned()
+        .collect();
+    if mentioned.len() > MAX_TOOL_ITERATIONS {
+        warn!("Prompt mentions {} tools; only the first {} run", mentioned.len(), MAX_TOOL_ITERATIONS);
+    }
+    
+    let context = ToolContext {
+        session_id: session_id.clone(),
+        model_id: request.model_id.clone(),
+        project_path: request.project_path.clone(),
+        user_prompt: request.prompt.clone(),
+        system_context: request.system_instruction.clone(),
+        history: vec![],
+    };
+    let (registry_ref, adapter_ref, app_ref) = (&*registry, &adapter, &app_handle);
+    let execute = move |tool_name: String| {
+        let context = context.clone();
+        async move {
+            let tool = registry_ref.get_tool(&tool_name).await
+                .ok_or_else(|| format!("Tool not found: {}", tool_name))?;
             info!("Auto-executing tool: {}", tool_name);
-            
-            if let Some(tool) = registry.get_tool(tool_name).await {
-                let context = ToolContext {
-                    session_id: session_id.clone(),
-                    model_id: request.model_id.clone(),
-                    project_path: request.project_path.clone(),
-                    user_prompt: request.prompt.clone(),
-                    system_context: request.system_instruction.clone(),
-                    history: vec![],
-                };
-                
-                let exec_request = ToolExecutionRequest {
-                    tool_type: tool.tool_type(),
-                    tool_name: tool_name.clone(),
-                    parameters: HashMap::new(),
-                    context,
-                };
-                
-                if let Ok(result) = adapter.execute_tool(tool, exec_request, app_handle.clone()).await {
-                    if result.success {
-                        tools_executed.push(tool_name.clone());
-                    }
-                }
-            }
+            let exec_request = ToolExecutionRequest {
+                tool_type: tool.tool_type(),
+                tool_name,
+                parameters: HashMap::new(),
+                context,
+            };
+            adapter_ref.execute_tool(tool, exec_request, app_ref.clone()).await
         }
-    }
+    };
+    let emit = |event: String, payload: Value| {
+        let _ = emit_session_event(&app_handle, &event, payload);
+    };
     
     // Emit execution start event
     let event = json!({
@@ -814,19 +952,89 @@ pub async fn execute_with_universal_tools(
         "session_id": session_id,
         "model": request.model_id,
         "tools_available": tools_to_use,
-        "tools_executed": tools_executed,
+        "tools_requested": mentioned,
     });
     
     app_handle.emit("universal-execution", event)
         .map_err(|e| format!("Failed to emit event: {}", e))?;
     
+    let (tools_executed, response) = run_tool_loop(
+        &session_id,
+        &request.model_id,
+        &mentioned,
+        tools_to_use.len(),
+        execute,
+        emit,
+    ).await;
+    let reasoning = format!(
+        "Ran {} of {} requested tool(s) with {} available",
+        tools_executed.len(),
+        mentioned.len().min(MAX_TOOL_ITERATIONS),
+        tools_to_use.len()
+    );
+    
     Ok(UniversalExecutionResult {
         success: true,
         model_used: request.model_id,
         session_id,
-        reasoning: format!("Executed with {} tools available", tools_to_use.len()),
+        response,
+        reasoning,
         error: None,
         auto_selected: false,
         tools_executed,
     })
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_tool_loop_streams_each_iteration() {
+        let events = Mutex::new(Vec::new());
+        let tools = vec!["read_file".to_string(), "run_agent".to_string()];
+        let execute = |tool_name: String| async move {
+            if tool_name == "run_agent" {
+                return Err("agent not found".to_string());
+            }
+            Ok(ToolExecutionResult {
+                success: true,
+                output: json!("fn main() {}"),
+                error: None,
+                execution_time_ms: 1,
+                tokens_used: None,
+            })
+        };
+        let emit = |event: String, payload: Value| events.lock().unwrap().push((event, payload));
+
+        let (executed, answer) = run_tool_loop("s1", "gemini-2.5-pro", &tools, 5, execute, emit).await;
+        assert_eq!(executed, vec!["read_file".to_string()]);
+        assert_eq!(answer, "fn main() {}");
+
+        let events = events.into_inner().unwrap();
+        let (last_event, last_payload) = events.last().unwrap();
+        assert_eq!((last_event.as_str(), last_payload), ("claude-complete:s1", &json!(true)));
+        let messages: Vec<Value> = events[..events.len() - 1]
+            .iter()
+            .map(|(event, payload)| {
+                assert_eq!(event, "claude-output:s1");
+                serde_json::from_str(payload.as_str().unwrap()).unwrap()
+            })
+            .collect();
+        let kinds: Vec<&str> = messages.iter().map(|m| m["type"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["system", "assistant", "user", "system", "assistant", "user", "result"]);
+
+        assert_eq!(messages[3]["iteration"], 2);
+        assert_eq!(messages[3]["max_iterations"], MAX_TOOL_ITERATIONS);
+        assert_eq!(messages[4]["message"]["content"][0]["name"], "run_agent");
+        let first_result = &messages[2]["message"]["content"][0];
+        assert_eq!(first_result["tool_use_id"], "s1-tool-1");
+        assert_eq!(first_result["is_error"], false);
+        assert!(first_result["duration_ms"].is_u64());
+        let second_result = &messages[5]["message"]["content"][0];
+        assert_eq!((&second_result["content"], &second_result["is_error"]), (&json!("agent not found"), &json!(true)));
+        assert_eq!(messages[6]["num_turns"], 2);
+        assert_eq!(messages[6]["result"], "fn main() {}");
+        assert_eq!(messages[6]["tools_executed"], json!(["read_file"]));
+    }
+}
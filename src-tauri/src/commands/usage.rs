@@ -4,7 +4,11 @@ use serde_json;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
-use tauri::command;
+use std::sync::{Mutex, OnceLock};
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::ai_usage_tracker::load_usage_tags;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsageEntry {
@@ -31,6 +35,9 @@ pub struct UsageStats {
     by_model: Vec<ModelUsage>,
     by_date: Vec<DailyUsage>,
     by_project: Vec<ProjectUsage>,
+    /// Rollup by the tag requested with `group_by_tag`, empty otherwise
+    #[serde(default)]
+    by_tag: Vec<TagUsage>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,6 +70,17 @@ pub struct ProjectUsage {
     last_used: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagUsage {
+    tag_key: String,
+    /// `None` groups usage from sessions that don't carry the tag
+    tag_value: Option<String>,
+    total_cost: f64,
+    total_tokens: u64,
+    session_count: u64,
+    request_count: u64,
+}
+
 // Claude 4 pricing constants (per million tokens)
 const OPUS_4_INPUT_PRICE: f64 = 15.0;
 const OPUS_4_OUTPUT_PRICE: f64 = 75.0;
@@ -74,6 +92,47 @@ const SONNET_4_OUTPUT_PRICE: f64 = 15.0;
 const SONNET_4_CACHE_WRITE_PRICE: f64 = 3.75;
 const SONNET_4_CACHE_READ_PRICE: f64 = 0.30;
 
+const OPUS_4_PRICES: (f64, f64, f64, f64) =
+    (OPUS_4_INPUT_PRICE, OPUS_4_OUTPUT_PRICE, OPUS_4_CACHE_WRITE_PRICE, OPUS_4_CACHE_READ_PRICE);
+const SONNET_4_PRICES: (f64, f64, f64, f64) =
+    (SONNET_4_INPUT_PRICE, SONNET_4_OUTPUT_PRICE, SONNET_4_CACHE_WRITE_PRICE, SONNET_4_CACHE_READ_PRICE);
+
+/// Log `message` the first time `model` hits it, so usage scans don't repeat it per line
+fn warn_once(model: &str, message: String) {
+    static WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let mut warned = WARNED.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if warned.insert(model.to_string()) {
+        log::warn!("{}", message);
+    }
+}
+
+/// Input, output, cache write and cache read prices for `model`, per million tokens
+///
+/// Variants we have no entry for, such as a new Opus snapshot, are priced as
+/// their family; models from no known family cost nothing.
+fn model_prices(model: &str) -> (f64, f64, f64, f64) {
+    match crate::models::canonicalize(model).map(|m| m.id) {
+        Some("opus-4.1") => return OPUS_4_PRICES,
+        Some("sonnet-4") => return SONNET_4_PRICES,
+        _ => {}
+    }
+
+    let lowered = model.to_lowercase();
+    let family = [("opus", OPUS_4_PRICES), ("sonnet", SONNET_4_PRICES)]
+        .into_iter()
+        .find(|(family, _)| lowered.contains(family));
+    match family {
+        Some((family, prices)) => {
+            warn_once(model, format!("No pricing for model '{}'; using {} 4 prices", model, family));
+            prices
+        }
+        None => {
+            warn_once(model, format!("No pricing for model '{}'; counting its usage as free", model));
+            (0.0, 0.0, 0.0, 0.0)
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct JsonlEntry {
     timestamp: String,
@@ -107,26 +166,7 @@ fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
     let cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
     let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0) as f64;
 
-    // Calculate cost based on model
-    let (input_price, output_price, cache_write_price, cache_read_price) =
-        if model.contains("opus-4") || model.contains("claude-opus-4") {
-            (
-                OPUS_4_INPUT_PRICE,
-                OPUS_4_OUTPUT_PRICE,
-                OPUS_4_CACHE_WRITE_PRICE,
-                OPUS_4_CACHE_READ_PRICE,
-            )
-        } else if model.contains("sonnet-4") || model.contains("claude-sonnet-4") {
-            (
-                SONNET_4_INPUT_PRICE,
-                SONNET_4_OUTPUT_PRICE,
-                SONNET_4_CACHE_WRITE_PRICE,
-                SONNET_4_CACHE_READ_PRICE,
-            )
-        } else {
-            // Return 0 for unknown models to avoid incorrect cost estimations.
-            (0.0, 0.0, 0.0, 0.0)
-        };
+    let (input_price, output_price, cache_write_price, cache_read_price) = model_prices(model);
 
     // Calculate cost (prices are per million tokens)
     let cost = (input_tokens * input_price / 1_000_000.0)
@@ -290,9 +330,7 @@ fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
 
 #[command]
 pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+    let claude_path = super::claude_dir::claude_dir_path()?;
 
     let all_entries = get_all_usage_entries(&claude_path);
 
@@ -308,6 +346,7 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
             by_model: vec![],
             by_date: vec![],
             by_project: vec![],
+            by_tag: vec![],
         });
     }
 
@@ -445,17 +484,85 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
         by_model,
         by_date,
         by_project,
+        by_tag: vec![],
     })
 }
 
+/// Whether a session's tags include every key/value pair of the filter
+fn matches_tag_filter(
+    tags: Option<&HashMap<String, String>>,
+    filter: &HashMap<String, String>,
+) -> bool {
+    filter
+        .iter()
+        .all(|(key, value)| tags.and_then(|tags| tags.get(key)) == Some(value))
+}
+
+/// Roll usage up by the value each entry's session has for `tag_key`
+fn rollup_by_tag(
+    entries: &[UsageEntry],
+    session_tags: &HashMap<String, HashMap<String, String>>,
+    tag_key: &str,
+) -> Vec<TagUsage> {
+    let mut groups: HashMap<Option<String>, (TagUsage, HashSet<&str>)> = HashMap::new();
+    for entry in entries {
+        let tag_value = session_tags
+            .get(&entry.session_id)
+            .and_then(|tags| tags.get(tag_key))
+            .cloned();
+        let (usage, sessions) = groups.entry(tag_value.clone()).or_insert_with(|| {
+            (
+                TagUsage {
+                    tag_key: tag_key.to_string(),
+                    tag_value,
+                    total_cost: 0.0,
+                    total_tokens: 0,
+                    session_count: 0,
+                    request_count: 0,
+                },
+                HashSet::new(),
+            )
+        });
+        usage.total_cost += entry.cost;
+        usage.total_tokens += entry.input_tokens
+            + entry.output_tokens
+            + entry.cache_creation_tokens
+            + entry.cache_read_tokens;
+        usage.request_count += 1;
+        sessions.insert(&entry.session_id);
+    }
+
+    let mut by_tag: Vec<TagUsage> = groups
+        .into_values()
+        .map(|(mut usage, sessions)| {
+            usage.session_count = sessions.len() as u64;
+            usage
+        })
+        .collect();
+    by_tag.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+    by_tag
+}
+
 #[command]
-pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<UsageStats, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+pub fn get_usage_by_date_range(
+    db: State<'_, AgentDb>,
+    start_date: String,
+    end_date: String,
+    tag_filter: Option<HashMap<String, String>>,
+    group_by_tag: Option<String>,
+) -> Result<UsageStats, String> {
+    let claude_path = super::claude_dir::claude_dir_path()?;
 
     let all_entries = get_all_usage_entries(&claude_path);
 
+    let tag_filter = tag_filter.unwrap_or_default();
+    let session_tags = if tag_filter.is_empty() && group_by_tag.is_none() {
+        HashMap::new()
+    } else {
+        let conn = db.lock_conn();
+        load_usage_tags(&conn)?
+    };
+
     // Parse dates
     let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").or_else(|_| {
         // Try parsing ISO datetime format
@@ -481,6 +588,7 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
                 false
             }
         })
+        .filter(|e| matches_tag_filter(session_tags.get(&e.session_id), &tag_filter))
         .collect();
 
     if filtered_entries.is_empty() {
@@ -495,6 +603,7 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
             by_model: vec![],
             by_date: vec![],
             by_project: vec![],
+            by_tag: vec![],
         });
     }
 
@@ -604,6 +713,10 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
     let mut by_project: Vec<ProjectUsage> = project_stats.into_values().collect();
     by_project.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
 
+    let by_tag = group_by_tag
+        .map(|tag_key| rollup_by_tag(&filtered_entries, &session_tags, &tag_key))
+        .unwrap_or_default();
+
     Ok(UsageStats {
         total_cost,
         total_tokens,
@@ -615,6 +728,7 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
         by_model,
         by_date,
         by_project,
+        by_tag,
     })
 }
 
@@ -623,9 +737,7 @@ pub fn get_usage_details(
     project_path: Option<String>,
     date: Option<String>,
 ) -> Result<Vec<UsageEntry>, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+    let claude_path = super::claude_dir::claude_dir_path()?;
 
     let mut all_entries = get_all_usage_entries(&claude_path);
 
@@ -648,9 +760,7 @@ pub fn get_session_stats(
     until: Option<String>,
     order: Option<String>,
 ) -> Result<Vec<ProjectUsage>, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+    let claude_path = super::claude_dir::claude_dir_path()?;
 
     let all_entries = get_all_usage_entries(&claude_path);
 
@@ -712,3 +822,76 @@ pub fn get_session_stats(
 
     Ok(by_session)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ai_usage_tracker::record_usage_tags;
+    use rusqlite::Connection;
+
+    fn entry(session_id: &str, cost: f64, input_tokens: u64) -> UsageEntry {
+        UsageEntry {
+            timestamp: "2025-08-01T10:00:00Z".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            input_tokens,
+            output_tokens: 10,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost,
+            session_id: session_id.to_string(),
+            project_path: "/work/app".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_unlisted_variants_are_priced_as_their_family() {
+        assert_eq!(model_prices("claude-opus-4-1-20250805"), OPUS_4_PRICES);
+        assert_eq!(model_prices("claude-opus-4-5-20251101"), OPUS_4_PRICES);
+        assert_eq!(model_prices("claude-3-7-sonnet-20250219"), SONNET_4_PRICES);
+        assert_eq!(model_prices("gpt-4o"), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_tagged_usage_rolls_up_by_tag() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../../migrations/002_dashboard.sql")).unwrap();
+        let tags = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        record_usage_tags(&conn, "s1", &tags(&[("team", "web"), ("ticket", "APP-1")])).unwrap();
+        record_usage_tags(&conn, "s2", &tags(&[("team", "web")])).unwrap();
+        record_usage_tags(&conn, "s3", &tags(&[("team", "infra")])).unwrap();
+        let session_tags = load_usage_tags(&conn).unwrap();
+
+        let entries = vec![
+            entry("s1", 1.0, 100),
+            entry("s1", 0.5, 50),
+            entry("s2", 2.0, 200),
+            entry("s3", 4.0, 400),
+            entry("untagged", 0.25, 20),
+        ];
+
+        let by_team = rollup_by_tag(&entries, &session_tags, "team");
+        let rollup: Vec<(Option<&str>, f64, u64, u64, u64)> = by_team
+            .iter()
+            .map(|u| (u.tag_value.as_deref(), u.total_cost, u.total_tokens, u.session_count, u.request_count))
+            .collect();
+        assert_eq!(
+            rollup,
+            vec![
+                (Some("infra"), 4.0, 410, 1, 1),
+                (Some("web"), 3.5, 380, 2, 3),
+                (None, 0.25, 30, 1, 1),
+            ]
+        );
+
+        let filter = tags(&[("team", "web"), ("ticket", "APP-1")]);
+        let matching: Vec<&str> = entries
+            .iter()
+            .filter(|e| matches_tag_filter(session_tags.get(&e.session_id), &filter))
+            .map(|e| e.session_id.as_str())
+            .collect();
+        assert_eq!(matching, vec!["s1", "s1"]);
+        assert!(matches_tag_filter(None, &HashMap::new()));
+    }
+}
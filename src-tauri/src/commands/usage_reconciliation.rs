@@ -0,0 +1,237 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::session_manager::create_session_tables;
+
+/// One session whose totals disagree with its records
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionDrift {
+    pub session_id: String,
+    /// `message_count` as stored on the session
+    pub recorded_messages: i64,
+    /// Messages actually stored for the session
+    pub message_count: i64,
+    /// `total_tokens` as stored on the session
+    pub recorded_tokens: i64,
+    /// Sum of the tokens of the session's messages
+    pub message_tokens: i64,
+    /// Sum of the session's usage records, when usage is tracked
+    pub usage_tokens: Option<i64>,
+    /// What disagrees, in words
+    pub discrepancies: Vec<String>,
+    /// The session's totals were reset to the per-message sums
+    pub corrected: bool,
+}
+
+/// Outcome of a reconciliation pass
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub sessions_checked: usize,
+    pub drifted: Vec<SessionDrift>,
+    pub corrected: usize,
+}
+
+fn usage_tracked(conn: &Connection) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'ai_usage_events'",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to look up usage tables: {}", e))
+}
+
+/// Cross-check every session's totals against its messages and usage records
+///
+/// A replaced message or a write that failed halfway leaves the running
+/// `message_count` and `total_tokens` out of step with the messages, whose sums
+/// are authoritative. With `fix`, drifted totals are reset to those sums in one
+/// transaction, and each correction is logged. Usage records that fall short are
+/// only reported, since the missing events can't be reconstructed.
+pub fn reconcile_sessions(conn: &Connection, fix: bool) -> Result<ReconciliationReport, String> {
+    let usage_query = if usage_tracked(conn)? {
+        "(SELECT COALESCE(SUM(token_count), 0) FROM ai_usage_events u WHERE u.session_id = s.session_id)"
+    } else {
+        "NULL"
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT s.session_id, COALESCE(s.message_count, 0), COALESCE(s.total_tokens, 0),
+                    COUNT(m.id), COALESCE(SUM(m.tokens_used), 0), {}
+             FROM chat_sessions s LEFT JOIN session_messages m ON m.session_id = s.session_id
+             GROUP BY s.session_id
+             ORDER BY s.session_id",
+            usage_query
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let sessions = stmt
+        .query_map([], |row| {
+            Ok(SessionDrift {
+                session_id: row.get(0)?,
+                recorded_messages: row.get(1)?,
+                recorded_tokens: row.get(2)?,
+                message_count: row.get(3)?,
+                message_tokens: row.get(4)?,
+                usage_tokens: row.get(5)?,
+                discrepancies: Vec::new(),
+                corrected: false,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read session totals: {}", e))?;
+    drop(stmt);
+
+    let sessions_checked = sessions.len();
+    let mut drifted = Vec::new();
+    for mut session in sessions {
+        if session.recorded_messages != session.message_count {
+            session.discrepancies.push(format!(
+                "message_count is {} but {} message(s) are stored",
+                session.recorded_messages, session.message_count
+            ));
+        }
+        if session.recorded_tokens != session.message_tokens {
+            session.discrepancies.push(format!(
+                "total_tokens is {} but its messages sum to {}",
+                session.recorded_tokens, session.message_tokens
+            ));
+        }
+        if let Some(usage_tokens) = session.usage_tokens.filter(|tokens| *tokens < session.message_tokens) {
+            session.discrepancies.push(format!(
+                "usage records sum to {} tokens, fewer than the {} in its messages",
+                usage_tokens, session.message_tokens
+            ));
+        }
+        if !session.discrepancies.is_empty() {
+            drifted.push(session);
+        }
+    }
+
+    let mut corrected = 0;
+    if fix {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start reconciliation transaction: {}", e))?;
+        for session in drifted.iter_mut() {
+            if session.recorded_messages == session.message_count && session.recorded_tokens == session.message_tokens {
+                continue;
+            }
+            tx.execute(
+                "UPDATE chat_sessions SET message_count = ?1, total_tokens = ?2 WHERE session_id = ?3",
+                params![session.message_count, session.message_tokens, session.session_id],
+            )
+            .map_err(|e| format!("Failed to correct session {}: {}", session.session_id, e))?;
+            log::info!(
+                "Corrected session {} totals from {} message(s)/{} tokens to {} message(s)/{} tokens",
+                session.session_id,
+                session.recorded_messages,
+                session.recorded_tokens,
+                session.message_count,
+                session.message_tokens
+            );
+            session.corrected = true;
+            corrected += 1;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit session corrections: {}", e))?;
+    }
+
+    Ok(ReconciliationReport { sessions_checked, drifted, corrected })
+}
+
+/// Report sessions whose totals drifted from their records, optionally correcting them
+#[tauri::command]
+pub async fn reconcile_usage_accounting(
+    db: State<'_, AgentDb>,
+    fix: Option<bool>,
+) -> Result<ReconciliationReport, String> {
+    let conn = db.lock_conn();
+    create_session_tables(&conn)?;
+    let report = reconcile_sessions(&conn, fix.unwrap_or(false))?;
+    if !report.drifted.is_empty() {
+        log::warn!(
+            "{} of {} session(s) have drifted usage accounting, {} corrected",
+            report.drifted.len(),
+            report.sessions_checked,
+            report.corrected
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounting_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_session_tables(&conn).unwrap();
+        conn.execute_batch(include_str!("../../migrations/002_dashboard.sql")).unwrap();
+        for (session_id, message_count, total_tokens) in [("drifted", 5, 900), ("healthy", 2, 300)] {
+            conn.execute(
+                "INSERT INTO chat_sessions (session_id, project_id, project_path, created_at, updated_at, message_count, total_tokens)
+                 VALUES (?1, 'proj-a', '/work/app', 0, 0, ?2, ?3)",
+                params![session_id, message_count, total_tokens],
+            )
+            .unwrap();
+        }
+        let messages = [("drifted", 1, 100), ("drifted", 2, 250), ("healthy", 1, 120), ("healthy", 2, 180)];
+        for (session_id, sequence_number, tokens) in messages {
+            conn.execute(
+                "INSERT INTO session_messages (id, session_id, project_id, sequence_number, message_type, content, timestamp, tokens_used)
+                 VALUES (?1, ?2, 'proj-a', ?3, 'user', '{}', 0, ?4)",
+                params![format!("{}-{}", session_id, sequence_number), session_id, sequence_number, tokens],
+            )
+            .unwrap();
+            if session_id == "healthy" || sequence_number == 1 {
+                conn.execute(
+                    "INSERT INTO ai_usage_events (project_id, model_name, token_count, session_id, session_date)
+                     VALUES ('proj-a', 'sonnet', ?1, ?2, '2025-08-01')",
+                    params![tokens, session_id],
+                )
+                .unwrap();
+            }
+        }
+        conn
+    }
+
+    #[test]
+    fn test_drifted_session_is_reported_then_corrected() {
+        let conn = accounting_db();
+
+        let report = reconcile_sessions(&conn, false).unwrap();
+        assert_eq!(report.sessions_checked, 2);
+        assert_eq!(report.corrected, 0);
+        assert_eq!(report.drifted.len(), 1);
+        let drift = &report.drifted[0];
+        assert_eq!(drift.session_id, "drifted");
+        assert_eq!((drift.recorded_messages, drift.message_count), (5, 2));
+        assert_eq!((drift.recorded_tokens, drift.message_tokens, drift.usage_tokens), (900, 350, Some(100)));
+        assert_eq!(drift.discrepancies.len(), 3);
+        assert!(!drift.corrected);
+
+        let totals = |session_id: &str| -> (i64, i64) {
+            conn.query_row(
+                "SELECT message_count, total_tokens FROM chat_sessions WHERE session_id = ?1",
+                params![session_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(totals("drifted"), (5, 900));
+
+        let fixed = reconcile_sessions(&conn, true).unwrap();
+        assert_eq!(fixed.corrected, 1);
+        assert!(fixed.drifted[0].corrected);
+        assert_eq!(totals("drifted"), (2, 350));
+        assert_eq!(totals("healthy"), (2, 300));
+
+        // Only the missing usage records remain, which can't be repaired
+        let after = reconcile_sessions(&conn, true).unwrap();
+        assert_eq!(after.corrected, 0);
+        assert_eq!(after.drifted[0].discrepancies.len(), 1);
+        assert!(after.drifted[0].discrepancies[0].contains("usage records"));
+    }
+}
@@ -0,0 +1,984 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::session_event_log::is_secret_name;
+use super::storage::{json_to_sql_value, sql_to_json_value};
+use crate::checkpoint::integrity::subdirectory_names;
+use crate::checkpoint::state::CheckpointState;
+
+/// Identifies a workspace archive
+const ARCHIVE_FORMAT: &str = "claudia-workspace";
+/// Version written by this build; archives with a higher version are refused
+pub const ARCHIVE_VERSION: u32 = 1;
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// A table carried in the archive and how its rows are matched on import
+struct TableSpec {
+    name: &'static str,
+    /// `id` is an INTEGER autoincrement key, reassigned when merging
+    auto_id: bool,
+    /// Columns identifying a row on any machine, used to carry local secrets over
+    /// and to recognize rows a merge already brought in; without them, all columns do
+    key: &'static [&'static str],
+    /// Text columns holding JSON documents that may contain secrets
+    secret_json: &'static [&'static str],
+    /// Rows are settings named by their `key` column; secret settings stay local
+    settings: bool,
+}
+
+const fn table(name: &'static str, auto_id: bool) -> TableSpec {
+    TableSpec { name, auto_id, key: &[], secret_json: &[], settings: false }
+}
+
+/// Tables in the archive, referenced tables before the ones referring to them
+const TABLES: &[TableSpec] = &[
+    table("agents", true),
+    TableSpec { settings: true, ..table("app_settings", false) },
+    TableSpec {
+        key: &["provider", "model_id"],
+        secret_json: &["mcp_config"],
+        ..table("universal_mcp_configs", true)
+    },
+    table("error_patterns", false),
+    table("error_knowledge", false),
+    table("resolution_history", false),
+    table("ai_usage_metrics", true),
+    table("ai_usage_events", true),
+    table("ai_usage_tags", false),
+    table("project_permissions", false),
+];
+
+/// Everything needed to recreate a workspace on another machine
+///
+/// Written as zstd-compressed JSON with a format version, so an archive from a
+/// newer app is refused instead of half imported. API keys, tokens and passwords
+/// are left out unless the export asks for them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceArchive {
+    pub format: String,
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub app_version: String,
+    pub secrets_included: bool,
+    /// Rows of each table as column → value objects
+    pub tables: BTreeMap<String, Vec<Map<String, Value>>>,
+    /// `~/.claude/settings.json`
+    pub claude_settings: Option<Value>,
+    /// `mcpServers` of `~/.claude.json`, the user-scope MCP servers
+    pub mcp_servers: Option<Value>,
+    /// Session transcripts and checkpoint timelines under `~/.claude/projects`
+    pub project_files: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    /// Relative to `~/.claude/projects`, with `/` separators
+    pub path: String,
+    /// Base64 of the file contents
+    pub data: String,
+}
+
+/// How `import_workspace` combines the archive with the local workspace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Add archived data next to the local data; local entries win conflicts
+    Merge,
+    /// Swap local data for the archived data; agent runs of replaced agents go with them
+    Replace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceExportReport {
+    pub path: String,
+    pub version: u32,
+    /// Rows exported per table
+    pub rows: BTreeMap<String, usize>,
+    pub project_files: usize,
+    pub secrets_included: bool,
+    /// Secret settings and fields left out of the archive
+    pub secrets_removed: usize,
+    /// Size of the archive on disk
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceImportReport {
+    pub mode: ImportMode,
+    pub version: u32,
+    pub rows_imported: BTreeMap<String, usize>,
+    /// Rows left out because a local row already had the same key
+    pub rows_skipped: BTreeMap<String, usize>,
+    /// Archived rows inserted under a new id because the old one was taken
+    pub ids_remapped: usize,
+    pub sessions_restored: usize,
+    /// Sessions with local checkpoints, which merging leaves alone
+    pub sessions_skipped: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Where the Claude files of a workspace live
+#[derive(Debug, Clone)]
+pub struct ClaudeLocations {
+    /// `~/.claude`
+    pub claude_dir: PathBuf,
+    /// `~/.claude.json`, which holds the user-scope MCP servers
+    pub claude_json: PathBuf,
+}
+
+impl ClaudeLocations {
+    fn current() -> Result<Self, String> {
+        let home = dirs::home_dir().ok_or("Could not find home directory")?;
+        // A fresh machine may not have ~/.claude yet; importing creates it
+        let claude_dir = super::claude::get_claude_dir().unwrap_or_else(|_| home.join(".claude"));
+        Ok(Self { claude_dir, claude_json: home.join(".claude.json") })
+    }
+
+    fn projects_dir(&self) -> PathBuf {
+        crate::windows_command::long_path(&self.claude_dir.join("projects"))
+    }
+}
+
+/// Remove every field whose name is a secret, at any depth; returns how many were removed
+fn strip_secrets(value: &mut Value) -> usize {
+    match value {
+        Value::Object(fields) => {
+            let before = fields.len();
+            fields.retain(|name, _| !is_secret_name(name));
+            let removed = before - fields.len();
+            removed + fields.values_mut().map(strip_secrets).sum::<usize>()
+        }
+        Value::Array(items) => items.iter_mut().map(strip_secrets).sum(),
+        _ => 0,
+    }
+}
+
+/// Copy the secret fields of `local` that `archived` lacks, at any depth
+fn keep_local_secrets(archived: &mut Value, local: &Value) {
+    let (Value::Object(archived), Value::Object(local)) = (archived, local) else {
+        return;
+    };
+    for (name, local_value) in local {
+        match archived.get_mut(name) {
+            Some(archived_value) => keep_local_secrets(archived_value, local_value),
+            None if is_secret_name(name) => {
+                archived.insert(name.clone(), local_value.clone());
+            }
+            None => {}
+        }
+    }
+}
+
+/// Archived JSON document combined with its local counterpart according to `mode`
+///
+/// Merging adds the top-level entries the local document lacks. Replacing
+/// takes the archived document, keeping local secrets the archive left out.
+fn combine_documents(local: Option<Value>, mut archived: Value, mode: ImportMode, secrets_included: bool) -> Value {
+    match (mode, local) {
+        (_, None) => archived,
+        (ImportMode::Merge, Some(Value::Object(mut local))) => {
+            if let Value::Object(archived) = archived {
+                for (name, value) in archived {
+                    local.entry(name).or_insert(value);
+                }
+            }
+            Value::Object(local)
+        }
+        (ImportMode::Merge, Some(local)) => local,
+        (ImportMode::Replace, Some(local)) => {
+            if !secrets_included {
+                keep_local_secrets(&mut archived, &local);
+            }
+            archived
+        }
+    }
+}
+
+fn read_json_file(path: &Path) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn write_json_file(path: &Path, value: &Value) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to inspect database schema: {}", e))
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .map_err(|e| e.to_string())?;
+    let columns = stmt
+        .query_map([], |row| row.get(1))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e));
+    columns
+}
+
+fn read_rows(conn: &Connection, table: &str) -> Result<Vec<Map<String, Value>>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {}", table))
+        .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let rows = stmt
+        .query_map([], |row| {
+            let mut values = Map::new();
+            for (index, column) in columns.iter().enumerate() {
+                values.insert(column.clone(), sql_to_json_value(row.get_ref(index)?));
+            }
+            Ok(values)
+        })
+        .and_then(|rows| rows.collect())
+        .map_err(|e| format!("Failed to read {}: {}", table, e));
+    rows
+}
+
+/// Apply `edit` to the JSON document in a text column, leaving unparsable text alone
+fn edit_json_column<T>(row: &mut Map<String, Value>, column: &str, edit: impl FnOnce(&mut Value) -> T) -> Option<T> {
+    let Some(Value::String(text)) = row.get(column) else {
+        return None;
+    };
+    let mut document: Value = serde_json::from_str(text).ok()?;
+    let result = edit(&mut document);
+    row.insert(column.to_string(), Value::String(document.to_string()));
+    Some(result)
+}
+
+fn export_table(conn: &Connection, spec: &TableSpec, include_secrets: bool) -> Result<(Vec<Map<String, Value>>, usize), String> {
+    let mut rows = read_rows(conn, spec.name)?;
+    if include_secrets {
+        return Ok((rows, 0));
+    }
+
+    let before = rows.len();
+    if spec.settings {
+        rows.retain(|row| !row.get("key").and_then(Value::as_str).is_some_and(is_secret_name));
+    }
+    let mut removed = before - rows.len();
+    for row in &mut rows {
+        for column in spec.secret_json {
+            removed += edit_json_column(row, column, strip_secrets).unwrap_or(0);
+        }
+    }
+    Ok((rows, removed))
+}
+
+/// Path relative to the projects directory, with `/` separators
+fn archive_path(projects_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(projects_dir).ok()?;
+    let parts: Option<Vec<&str>> = relative.components().map(|part| part.as_os_str().to_str()).collect();
+    Some(parts?.join("/"))
+}
+
+/// Transcripts and checkpoint files of every session that has a timeline
+fn collect_project_files(projects_dir: &Path) -> Result<Vec<ArchivedFile>, String> {
+    let mut paths = Vec::new();
+    let mut project_ids = subdirectory_names(projects_dir);
+    project_ids.sort();
+    for project_id in project_ids {
+        let project_dir = projects_dir.join(&project_id);
+        let mut session_ids = subdirectory_names(&project_dir.join(".timelines"));
+        session_ids.sort();
+        for session_id in session_ids {
+            let transcript = project_dir.join(format!("{}.jsonl", session_id));
+            if transcript.is_file() {
+                paths.push(transcript);
+            }
+            let timeline_dir = project_dir.join(".timelines").join(&session_id);
+            for entry in walkdir::WalkDir::new(&timeline_dir).sort_by_file_name() {
+                let entry = entry.map_err(|e| format!("Failed to read {}: {}", timeline_dir.display(), e))?;
+                if entry.file_type().is_file() {
+                    paths.push(entry.into_path());
+                }
+            }
+        }
+    }
+
+    paths
+        .into_iter()
+        .filter_map(|path| Some((archive_path(projects_dir, &path)?, path)))
+        .map(|(relative, path)| {
+            let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            Ok(ArchivedFile {
+                path: relative,
+                data: base64::engine::general_purpose::STANDARD.encode(data),
+            })
+        })
+        .collect()
+}
+
+/// Gather the workspace into an archive; also returns the number of secrets left out
+///
+/// The tables are read under the guard `lock` returns, which is released
+/// before the Claude files are read.
+pub fn build_archive<G: Deref<Target = Connection>>(
+    lock: impl Fn() -> G,
+    locations: &ClaudeLocations,
+    include_secrets: bool,
+) -> Result<(WorkspaceArchive, usize), String> {
+    let mut secrets_removed = 0;
+
+    let mut tables = BTreeMap::new();
+    {
+        let conn = lock();
+        for spec in TABLES {
+            if !table_exists(&conn, spec.name)? {
+                continue;
+            }
+            let (rows, removed) = export_table(&conn, spec, include_secrets)?;
+            secrets_removed += removed;
+            tables.insert(spec.name.to_string(), rows);
+        }
+    }
+
+    let mut claude_settings = read_json_file(&locations.claude_dir.join("settings.json"))?;
+    let mut mcp_servers = read_json_file(&locations.claude_json)?.and_then(|mut config| config.get_mut("mcpServers").map(Value::take));
+    if !include_secrets {
+        for document in [&mut claude_settings, &mut mcp_servers].into_iter().flatten() {
+            secrets_removed += strip_secrets(document);
+        }
+    }
+
+    let archive = WorkspaceArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: Utc::now(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        secrets_included: include_secrets,
+        tables,
+        claude_settings,
+        mcp_servers,
+        project_files: collect_project_files(&locations.projects_dir())?,
+    };
+    Ok((archive, secrets_removed))
+}
+
+/// Write `archive` to `path`, returning its size
+pub fn write_archive(path: &Path, archive: &WorkspaceArchive) -> Result<u64, String> {
+    let json = serde_json::to_vec(archive).map_err(|e| format!("Failed to serialize workspace: {}", e))?;
+    let compressed = zstd::stream::encode_all(&json[..], COMPRESSION_LEVEL)
+        .map_err(|e| format!("Failed to compress workspace: {}", e))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    // Write beside the target first so a failed export never leaves a truncated archive
+    let partial = path.with_extension("partial");
+    fs::write(&partial, &compressed).map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    fs::rename(&partial, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(compressed.len() as u64)
+}
+
+/// Check the format and version before the rest of the archive is interpreted
+fn validate_header(header: &Value) -> Result<u32, String> {
+    if header["format"].as_str() != Some(ARCHIVE_FORMAT) {
+        return Err("Not a workspace archive".to_string());
+    }
+    match header["version"].as_u64() {
+        Some(version) if (1..=u64::from(ARCHIVE_VERSION)).contains(&version) => Ok(version as u32),
+        Some(version) if version > u64::from(ARCHIVE_VERSION) => Err(format!(
+            "Workspace archive version {} was written by a newer version of the app, which reads up to version {}; update before importing",
+            version, ARCHIVE_VERSION
+        )),
+        _ => Err("Workspace archive has no valid version".to_string()),
+    }
+}
+
+pub fn read_archive(path: &Path) -> Result<WorkspaceArchive, String> {
+    let compressed = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let json = zstd::stream::decode_all(&compressed[..])
+        .map_err(|_| format!("{} is not a workspace archive", path.display()))?;
+    let archive: Value = serde_json::from_slice(&json)
+        .map_err(|_| format!("{} is not a workspace archive", path.display()))?;
+    validate_header(&archive)?;
+    serde_json::from_value(archive).map_err(|e| format!("Workspace archive is damaged: {}", e))
+}
+
+/// Project and session a file under the projects directory belongs to
+fn file_session(path: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let safe = parts
+        .iter()
+        .all(|part| !part.is_empty() && *part != "." && *part != ".." && !part.contains(['\\', ':']));
+    if !safe {
+        return None;
+    }
+    match parts.as_slice() {
+        [project, transcript] => transcript
+            .strip_suffix(".jsonl")
+            .map(|session| (project.to_string(), session.to_string())),
+        [project, ".timelines", session, _, ..] => Some((project.to_string(), session.to_string())),
+        _ => None,
+    }
+}
+
+/// Decoded files of each (project, session), with paths relative to the projects directory
+type SessionFiles = BTreeMap<(String, String), Vec<(PathBuf, Vec<u8>)>>;
+
+/// Decoded archive files grouped by session, rejecting paths that leave the projects directory
+fn group_sessions(files: &[ArchivedFile]) -> Result<SessionFiles, String> {
+    let mut sessions: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for file in files {
+        let session = file_session(&file.path)
+            .ok_or_else(|| format!("Workspace archive contains an unexpected path: {}", file.path))?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&file.data)
+            .map_err(|e| format!("Workspace archive is damaged at {}: {}", file.path, e))?;
+        let relative: PathBuf = file.path.split('/').collect();
+        sessions.entry(session).or_default().push((relative, data));
+    }
+    Ok(sessions)
+}
+
+/// Secret JSON documents of local rows, by the row's key
+fn local_secret_documents(conn: &Connection, spec: &TableSpec) -> Result<HashMap<Vec<String>, Map<String, Value>>, String> {
+    let mut documents = HashMap::new();
+    for row in read_rows(conn, spec.name)? {
+        let key = spec.key.iter().map(|column| row.get(*column).map(Value::to_string).unwrap_or_default()).collect();
+        let columns = spec
+            .secret_json
+            .iter()
+            .filter_map(|column| {
+                let text = row.get(*column)?.as_str()?;
+                Some((column.to_string(), serde_json::from_str(text).ok()?))
+            })
+            .collect();
+        documents.insert(key, columns);
+    }
+    Ok(documents)
+}
+
+/// Empty a table before replacing its rows, keeping secret settings the archive lacks
+fn clear_table(conn: &Connection, spec: &TableSpec, secrets_included: bool) -> Result<(), String> {
+    let cleared = if spec.settings && !secrets_included {
+        let keys: Vec<String> = read_rows(conn, spec.name)?
+            .iter()
+            .filter_map(|row| row.get("key")?.as_str().map(str::to_string))
+            .filter(|key| !is_secret_name(key))
+            .collect();
+        keys.iter().try_for_each(|key| {
+            conn.execute(&format!("DELETE FROM {} WHERE key = ?1", spec.name), [key]).map(|_| ())
+        })
+    } else {
+        conn.execute(&format!("DELETE FROM {}", spec.name), []).map(|_| ())
+    };
+    cleared.map_err(|e| format!("Failed to clear {}: {}", spec.name, e))
+}
+
+/// Whether a local row already matches an archived one on the table's key, or on every archived column
+///
+/// Merged rows get a fresh id, so without this importing the same archive twice would add them twice.
+fn merged_before(conn: &Connection, spec: &TableSpec, values: &[(&String, &Value)]) -> Result<bool, String> {
+    let matched: Vec<&(&String, &Value)> = if spec.key.is_empty() {
+        values.iter().collect()
+    } else {
+        values.iter().filter(|(column, _)| spec.key.contains(&column.as_str())).collect()
+    };
+    if matched.is_empty() {
+        return Ok(false);
+    }
+    let query = format!(
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE {})",
+        spec.name,
+        matched
+            .iter()
+            .enumerate()
+            .map(|(i, (column, _))| format!("\"{}\" IS ?{}", column, i + 1))
+            .collect::<Vec<_>>()
+            .join(" AND "),
+    );
+    let params: Vec<Box<dyn rusqlite::ToSql>> = matched
+        .iter()
+        .map(|(_, value)| json_to_sql_value(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    conn.query_row(&query, rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), |row| row.get(0))
+        .map_err(|e| format!("Failed to compare a row of {}: {}", spec.name, e))
+}
+
+fn insert_row(
+    conn: &Connection,
+    spec: &TableSpec,
+    columns: &[String],
+    mut row: Map<String, Value>,
+    mode: ImportMode,
+    report: &mut WorkspaceImportReport,
+) -> Result<(), String> {
+    // Merged rows get a fresh id so they never overwrite or collide with local ones
+    let old_id = match (spec.auto_id, mode) {
+        (true, ImportMode::Merge) => row.remove("id").and_then(|id| id.as_i64()),
+        _ => None,
+    };
+    // Columns this database does not have, e.g. from a newer schema, are dropped
+    let values: Vec<(&String, &Value)> = row.iter().filter(|(column, _)| columns.contains(column)).collect();
+    if values.is_empty() || (old_id.is_some() && merged_before(conn, spec, &values)?) {
+        *report.rows_skipped.entry(spec.name.to_string()).or_default() += 1;
+        return Ok(());
+    }
+
+    let query = format!(
+        "{} INTO {} ({}) VALUES ({})",
+        match mode {
+            ImportMode::Merge => "INSERT OR IGNORE",
+            ImportMode::Replace => "INSERT OR REPLACE",
+        },
+        spec.name,
+        values.iter().map(|(column, _)| format!("\"{}\"", column)).collect::<Vec<_>>().join(", "),
+        (1..=values.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", "),
+    );
+    let params: Vec<Box<dyn rusqlite::ToSql>> = values
+        .iter()
+        .map(|(_, value)| json_to_sql_value(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let inserted = conn
+        .execute(&query, rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())))
+        .map_err(|e| format!("Failed to import a row into {}: {}", spec.name, e))?;
+
+    if inserted == 0 {
+        *report.rows_skipped.entry(spec.name.to_string()).or_default() += 1;
+        return Ok(());
+    }
+    *report.rows_imported.entry(spec.name.to_string()).or_default() += 1;
+    if old_id.is_some_and(|old_id| old_id != conn.last_insert_rowid()) {
+        report.ids_remapped += 1;
+    }
+    Ok(())
+}
+
+fn restore_tables(
+    conn: &Connection,
+    archive: &WorkspaceArchive,
+    mode: ImportMode,
+    report: &mut WorkspaceImportReport,
+) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start import: {}", e))?;
+
+    let mut specs = Vec::new();
+    for spec in TABLES {
+        let Some(rows) = archive.tables.get(spec.name) else {
+            continue;
+        };
+        if !table_exists(&tx, spec.name)? {
+            report.warnings.push(format!("Table {} does not exist here; {} row(s) not imported", spec.name, rows.len()));
+            continue;
+        }
+        specs.push((spec, rows, table_columns(&tx, spec.name)?));
+    }
+
+    // Emptied in reverse so no row is deleted while something still refers to it
+    let mut local_secrets = HashMap::new();
+    if mode == ImportMode::Replace {
+        for (spec, _, _) in specs.iter().rev() {
+            if !archive.secrets_included && !spec.secret_json.is_empty() {
+                local_secrets.insert(spec.name, local_secret_documents(&tx, spec)?);
+            }
+            clear_table(&tx, spec, archive.secrets_included)?;
+        }
+    }
+
+    for (spec, rows, columns) in &specs {
+        for row in rows.iter().cloned() {
+            let mut row = row;
+            if let Some(documents) = local_secrets.get(spec.name) {
+                let key: Vec<String> =
+                    spec.key.iter().map(|column| row.get(*column).map(Value::to_string).unwrap_or_default()).collect();
+                for (column, local) in documents.get(&key).into_iter().flatten() {
+                    edit_json_column(&mut row, column, |archived| keep_local_secrets(archived, local));
+                }
+            }
+            insert_row(&tx, spec, columns, row, mode, report)?;
+        }
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit import: {}", e))
+}
+
+fn restore_claude_files(locations: &ClaudeLocations, archive: &WorkspaceArchive, mode: ImportMode) -> Result<(), String> {
+    if let Some(archived) = &archive.claude_settings {
+        let path = locations.claude_dir.join("settings.json");
+        let settings = combine_documents(read_json_file(&path)?, archived.clone(), mode, archive.secrets_included);
+        write_json_file(&path, &settings)?;
+    }
+
+    if let Some(archived) = &archive.mcp_servers {
+        // ~/.claude.json holds much more than MCP servers; only that entry is touched
+        let mut config = read_json_file(&locations.claude_json)?.unwrap_or_else(|| Value::Object(Map::new()));
+        let Value::Object(fields) = &mut config else {
+            return Err(format!("{} is not a JSON object", locations.claude_json.display()));
+        };
+        let servers = combine_documents(fields.remove("mcpServers"), archived.clone(), mode, archive.secrets_included);
+        fields.insert("mcpServers".to_string(), servers);
+        write_json_file(&locations.claude_json, &config)?;
+    }
+    Ok(())
+}
+
+fn restore_sessions(
+    projects_dir: &Path,
+    sessions: SessionFiles,
+    mode: ImportMode,
+    report: &mut WorkspaceImportReport,
+) -> Result<(), String> {
+    for ((project_id, session_id), files) in sessions {
+        let timeline_dir = projects_dir.join(&project_id).join(".timelines").join(&session_id);
+        if timeline_dir.exists() {
+            match mode {
+                ImportMode::Merge => {
+                    report.sessions_skipped += 1;
+                    continue;
+                }
+                ImportMode::Replace => fs::remove_dir_all(&timeline_dir)
+                    .map_err(|e| format!("Failed to replace checkpoints of session {}: {}", session_id, e))?,
+            }
+        }
+
+        for (relative, data) in files {
+            let path = projects_dir.join(relative);
+            // A local transcript may have grown since the export
+            if mode == ImportMode::Merge && path.exists() {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        report.sessions_restored += 1;
+    }
+    Ok(())
+}
+
+/// Restore `archive` into the database and Claude directories
+///
+/// The tables are restored under the guard `lock` returns, which is released
+/// before any Claude file is written.
+pub fn restore_archive<G: Deref<Target = Connection>>(
+    lock: impl Fn() -> G,
+    locations: &ClaudeLocations,
+    archive: &WorkspaceArchive,
+    mode: ImportMode,
+) -> Result<WorkspaceImportReport, String> {
+    validate_header(&serde_json::json!({ "format": archive.format, "version": archive.version }))?;
+    // Checked before anything is written so a bad archive changes nothing
+    let sessions = group_sessions(&archive.project_files)?;
+
+    let mut report = WorkspaceImportReport {
+        mode,
+        version: archive.version,
+        rows_imported: BTreeMap::new(),
+        rows_skipped: BTreeMap::new(),
+        ids_remapped: 0,
+        sessions_restored: 0,
+        sessions_skipped: 0,
+        warnings: Vec::new(),
+    };
+    if !archive.secrets_included {
+        report.warnings.push("The archive has no API keys or tokens; add them again where needed".to_string());
+    }
+
+    restore_tables(&lock(), archive, mode, &mut report)?;
+    restore_claude_files(locations, archive, mode)?;
+    restore_sessions(&locations.projects_dir(), sessions, mode, &mut report)?;
+    Ok(report)
+}
+
+/// Exports agents, settings, MCP configs, error knowledge, usage history, project permissions and checkpoints to one archive
+#[tauri::command]
+pub async fn export_workspace(
+    db: State<'_, AgentDb>,
+    path: String,
+    include_secrets: bool,
+) -> Result<WorkspaceExportReport, String> {
+    log::info!("Exporting workspace to {} (secrets included: {})", path, include_secrets);
+    let locations = ClaudeLocations::current()?;
+    let (archive, secrets_removed) = build_archive(|| db.lock_conn(), &locations, include_secrets)?;
+    let bytes = write_archive(Path::new(&path), &archive)?;
+
+    Ok(WorkspaceExportReport {
+        path,
+        version: archive.version,
+        rows: archive.tables.iter().map(|(table, rows)| (table.clone(), rows.len())).collect(),
+        project_files: archive.project_files.len(),
+        secrets_included: include_secrets,
+        secrets_removed,
+        bytes,
+    })
+}
+
+/// Restores a workspace archive, merging with or replacing the local workspace
+#[tauri::command]
+pub async fn import_workspace(
+    db: State<'_, AgentDb>,
+    checkpoints: State<'_, CheckpointState>,
+    path: String,
+    mode: ImportMode,
+) -> Result<WorkspaceImportReport, String> {
+    log::info!("Importing workspace from {} ({:?})", path, mode);
+    let archive = read_archive(Path::new(&path))?;
+    let locations = ClaudeLocations::current()?;
+
+    // Cached managers hold timelines in memory and would write back replaced ones
+    checkpoints.clear_all().await;
+
+    let report = restore_archive(|| db.lock_conn(), &locations, &archive, mode)?;
+    log::info!(
+        "Imported workspace: {} row(s), {} session(s)",
+        report.rows_imported.values().sum::<usize>(),
+        report.sessions_restored
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::storage::CheckpointStorage;
+    use crate::checkpoint::{Checkpoint, CheckpointMetadata, FileSnapshot};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const PROJECT: &str = "-home-dev-app";
+    const SESSION: &str = "3f6c1a52-session";
+
+    /// Schema of the tables in the archive, as the app creates it
+    fn open_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, icon TEXT NOT NULL,
+                system_prompt TEXT NOT NULL, default_task TEXT, model TEXT NOT NULL DEFAULT 'sonnet',
+                default_provider TEXT, created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP);
+             CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE universal_mcp_configs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, provider TEXT NOT NULL, model_id TEXT NOT NULL,
+                mcp_config TEXT NOT NULL, UNIQUE(provider, model_id));",
+        )
+        .unwrap();
+        super::super::dashboard::apply_dashboard_migration(&conn).unwrap();
+        super::super::error_tracker::create_error_tables(&conn).unwrap();
+        conn
+    }
+
+    fn locations(dir: &TempDir) -> ClaudeLocations {
+        ClaudeLocations {
+            claude_dir: dir.path().join(".claude"),
+            claude_json: dir.path().join(".claude.json"),
+        }
+    }
+
+    fn count(conn: &Connection, query: &str) -> i64 {
+        conn.query_row(query, [], |row| row.get(0)).unwrap()
+    }
+
+    fn save_checkpoint(claude_dir: &Path) {
+        let storage = CheckpointStorage::new(claude_dir.to_path_buf());
+        storage.init_storage(PROJECT, SESSION).unwrap();
+        let checkpoint = Checkpoint {
+            id: "cp-1".to_string(),
+            session_id: SESSION.to_string(),
+            project_id: PROJECT.to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: Some("before refactor".to_string()),
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "sonnet".to_string(),
+                user_prompt: String::new(),
+                file_changes: 1,
+                snapshot_size: 0,
+            },
+            unrestorable_reason: None,
+        };
+        let snapshot = FileSnapshot {
+            checkpoint_id: "cp-1".to_string(),
+            file_path: PathBuf::from("src/main.rs"),
+            content: "fn main() {}".to_string(),
+            hash: CheckpointStorage::calculate_file_hash("fn main() {}"),
+            is_deleted: false,
+            permissions: None,
+            size: 12,
+        };
+        storage.save_checkpoint(PROJECT, SESSION, &checkpoint, vec![snapshot], "{}").unwrap();
+        fs::write(claude_dir.join("projects").join(PROJECT).join(format!("{}.jsonl", SESSION)), "{\"type\":\"user\"}\n").unwrap();
+    }
+
+    /// A workspace with an agent, settings, an MCP config, an error, usage and a checkpoint
+    fn populate(conn: &Connection, locations: &ClaudeLocations) {
+        conn.execute_batch(
+            "INSERT INTO agents (id, name, icon, system_prompt, model, default_provider)
+                VALUES (1, 'Reviewer', 'bot', 'Review the diff', 'opus', 'claude');
+             INSERT INTO app_settings (key, value) VALUES ('theme', 'dark'), ('gemini_api_key', 'AIza-exported');
+             INSERT INTO universal_mcp_configs (provider, model_id, mcp_config)
+                VALUES ('gemini', 'gemini-2.5-pro', '{\"server\":\"github\",\"env\":{\"GITHUB_TOKEN\":\"ghp-exported\",\"LOG\":\"1\"}}');
+             INSERT INTO error_knowledge (id, error_code, title, description, severity, category, occurred_at, status, last_occurrence)
+                VALUES ('err-1', 'E_TIMEOUT', 'Timeout', 'Request timed out', 'high', 'Network', 1, 'resolved', 1);
+             INSERT INTO resolution_history (id, error_id, strategy_type, started_at) VALUES ('res-1', 'err-1', 'retry', 1);
+             INSERT INTO ai_usage_events (project_id, model_name, token_count, cost, session_date)
+                VALUES ('app', 'sonnet', 1200, 0.02, '2026-10-01');",
+        )
+        .unwrap();
+        fs::create_dir_all(&locations.claude_dir).unwrap();
+        write_json_file(
+            &locations.claude_dir.join("settings.json"),
+            &json!({ "model": "opus", "env": { "ANTHROPIC_API_KEY": "sk-exported", "DEBUG": "1" } }),
+        )
+        .unwrap();
+        write_json_file(
+            &locations.claude_json,
+            &json!({ "numStartups": 3, "mcpServers": { "github": { "command": "gh-mcp", "env": { "GITHUB_TOKEN": "ghp-exported" } } } }),
+        )
+        .unwrap();
+        save_checkpoint(&locations.claude_dir);
+    }
+
+    #[test]
+    fn test_workspace_round_trips_through_merge_import() {
+        let source_dir = TempDir::new().unwrap();
+        let source = locations(&source_dir);
+        let source_db = open_database();
+        populate(&source_db, &source);
+
+        let archive_path = source_dir.path().join("backup").join("workspace.claudia");
+        let (archive, secrets_removed) = build_archive(|| &source_db, &source, false).unwrap();
+        write_archive(&archive_path, &archive).unwrap();
+        // API key setting, MCP config token, settings.json key and MCP server token
+        assert_eq!(secrets_removed, 4);
+        let raw = zstd::stream::decode_all(&fs::read(&archive_path).unwrap()[..]).unwrap();
+        assert!(!String::from_utf8(raw).unwrap().contains("exported"));
+
+        // The target already has an agent with id 1, a theme and its own API key
+        let target_dir = TempDir::new().unwrap();
+        let target = locations(&target_dir);
+        let target_db = open_database();
+        target_db
+            .execute_batch(
+                "INSERT INTO agents (id, name, icon, system_prompt) VALUES (1, 'Local', 'cpu', 'Local agent');
+                 INSERT INTO app_settings (key, value) VALUES ('theme', 'light'), ('gemini_api_key', 'AIza-local');",
+            )
+            .unwrap();
+        write_json_file(&target.claude_json, &json!({ "userID": "target" })).unwrap();
+
+        let archive = read_archive(&archive_path).unwrap();
+        let report = restore_archive(|| &target_db, &target, &archive, ImportMode::Merge).unwrap();
+        assert_eq!(report.rows_imported["agents"], 1);
+        assert_eq!(report.ids_remapped, 1);
+        assert_eq!(report.rows_skipped["app_settings"], 1);
+        assert_eq!(report.rows_imported["resolution_history"], 1);
+        assert_eq!((report.sessions_restored, report.sessions_skipped), (1, 0));
+
+        let imported: (i64, String, Option<String>) = target_db
+            .query_row("SELECT id, model, default_provider FROM agents WHERE name = 'Reviewer'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(imported, (2, "opus".to_string(), Some("claude".to_string())));
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM app_settings WHERE key = 'theme' AND value = 'light'"), 1);
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM app_settings WHERE value = 'AIza-local'"), 1);
+        assert_eq!(count(&target_db, "SELECT SUM(token_count) FROM ai_usage_events"), 1200);
+        let mcp_config: String = target_db
+            .query_row("SELECT mcp_config FROM universal_mcp_configs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&mcp_config).unwrap(), json!({ "server": "github", "env": { "LOG": "1" } }));
+
+        let settings = read_json_file(&target.claude_dir.join("settings.json")).unwrap().unwrap();
+        assert_eq!(settings, json!({ "model": "opus", "env": { "DEBUG": "1" } }));
+        let claude_json = read_json_file(&target.claude_json).unwrap().unwrap();
+        assert_eq!(claude_json, json!({ "userID": "target", "mcpServers": { "github": { "command": "gh-mcp", "env": {} } } }));
+
+        // The checkpoint restores on the new machine
+        let storage = CheckpointStorage::new(target.claude_dir.clone());
+        let (checkpoint, snapshots, _) = storage.load_checkpoint(PROJECT, SESSION, "cp-1").unwrap();
+        assert_eq!(checkpoint.description.as_deref(), Some("before refactor"));
+        assert_eq!(snapshots[0].content, "fn main() {}");
+        assert!(target.claude_dir.join("projects").join(PROJECT).join(format!("{}.jsonl", SESSION)).exists());
+
+        // Merging the same archive again adds no rows and leaves the restored session alone
+        let again = restore_archive(|| &target_db, &target, &archive, ImportMode::Merge).unwrap();
+        assert_eq!(again.rows_imported.values().sum::<usize>(), 0, "{:?}", again.rows_imported);
+        assert_eq!(again.rows_skipped["agents"], 1);
+        assert_eq!(again.rows_skipped["ai_usage_events"], 1);
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM agents WHERE name = 'Reviewer'"), 1);
+        assert_eq!(count(&target_db, "SELECT SUM(token_count) FROM ai_usage_events"), 1200);
+        assert_eq!((again.sessions_restored, again.sessions_skipped), (0, 1));
+    }
+
+    #[test]
+    fn test_replace_import_keeps_local_secrets_and_rejects_newer_archives() {
+        let source_dir = TempDir::new().unwrap();
+        let source = locations(&source_dir);
+        let source_db = open_database();
+        populate(&source_db, &source);
+        let (archive, _) = build_archive(|| &source_db, &source, false).unwrap();
+
+        let target_dir = TempDir::new().unwrap();
+        let target = locations(&target_dir);
+        let target_db = open_database();
+        target_db
+            .execute_batch(
+                "INSERT INTO agents (name, icon, system_prompt) VALUES ('Stale', 'cpu', 'Old agent');
+                 INSERT INTO app_settings (key, value) VALUES ('theme', 'light'), ('gemini_api_key', 'AIza-local');
+                 INSERT INTO universal_mcp_configs (provider, model_id, mcp_config)
+                    VALUES ('gemini', 'gemini-2.5-pro', '{\"env\":{\"GITHUB_TOKEN\":\"ghp-local\"}}');",
+            )
+            .unwrap();
+        write_json_file(&target.claude_dir.join("settings.json"), &json!({ "env": { "ANTHROPIC_API_KEY": "sk-local" } })).unwrap();
+
+        let report = restore_archive(|| &target_db, &target, &archive, ImportMode::Replace).unwrap();
+        assert_eq!(report.ids_remapped, 0);
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM agents WHERE name = 'Stale'"), 0);
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM agents WHERE id = 1 AND name = 'Reviewer'"), 1);
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM app_settings WHERE key = 'theme' AND value = 'dark'"), 1);
+        assert_eq!(count(&target_db, "SELECT COUNT(*) FROM app_settings WHERE value = 'AIza-local'"), 1);
+        let mcp_config: String = target_db
+            .query_row("SELECT mcp_config FROM universal_mcp_configs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&mcp_config).unwrap(),
+            json!({ "server": "github", "env": { "LOG": "1", "GITHUB_TOKEN": "ghp-local" } })
+        );
+        let settings = read_json_file(&target.claude_dir.join("settings.json")).unwrap().unwrap();
+        assert_eq!(settings, json!({ "model": "opus", "env": { "DEBUG": "1", "ANTHROPIC_API_KEY": "sk-local" } }));
+
+        let mut newer = archive.clone();
+        newer.version = ARCHIVE_VERSION + 1;
+        let newer_path = target_dir.path().join("newer.claudia");
+        write_archive(&newer_path, &newer).unwrap();
+        let error = read_archive(&newer_path).unwrap_err();
+        assert!(error.contains("newer version of the app"), "{}", error);
+
+        let mut escaping = archive;
+        escaping.project_files[0].path = format!("{}/../../outside.jsonl", PROJECT);
+        let error = restore_archive(|| &target_db, &target, &escaping, ImportMode::Merge).unwrap_err();
+        assert!(error.contains("unexpected path"), "{}", error);
+    }
+}
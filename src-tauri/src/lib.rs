@@ -12,6 +12,7 @@ pub mod runtime_utils;
 pub mod adapters;
 pub mod auto_resolution;
 pub mod rollback;
+pub mod models;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
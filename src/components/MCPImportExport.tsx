@@ -219,7 +219,12 @@ export const MCPImportExport: React.FC<MCPImportExportProps> = ({
    */
   const handleStartMCPServer = async () => {
     try {
-      await api.mcpServe();
+      const result = await api.mcpServe();
+      if (!result.success) {
+        console.error("Failed to start MCP server:", result.message);
+        onError("Failed to start Claude Code as MCP server");
+        return;
+      }
       onError("Claude Code MCP server started. You can now connect to it from other applications.");
     } catch (error) {
       console.error("Failed to start MCP server:", error);
@@ -101,7 +101,11 @@ export const MCPServerList: React.FC<MCPServerListProps> = React.memo(({
   const handleRemoveServer = useCallback(async (name: string) => {
     try {
       setRemovingServer(name);
-      await api.mcpRemove(name);
+      const result = await api.mcpRemove(name);
+      if (!result.success) {
+        console.error("Failed to remove server:", result.message);
+        return;
+      }
       onServerRemoved(name);
     } catch (error) {
       console.error("Failed to remove server:", error);
@@ -106,6 +106,22 @@ const StreamMessageComponent: React.FC<StreamMessageProps> = ({ message, classNa
       );
     }
 
+    // Rate-limited request waiting to be retried
+    if (message.type === "system" && message.subtype === "retry") {
+      return (
+        <Card className={cn("border-yellow-500/20 bg-yellow-500/5", className)}>
+          <CardContent className="p-4">
+            <div className="flex items-start gap-3">
+              <AlertCircle className="h-5 w-5 text-yellow-500 mt-0.5" />
+              <p className="text-sm text-muted-foreground">
+                {message.message || `Rate limited, retrying in ${message.retry_in_secs}s`}
+              </p>
+            </div>
+          </CardContent>
+        </Card>
+      );
+    }
+
     // Assistant message
     if (message.type === "assistant" && message.message) {
       const msg = message.message;
@@ -4,7 +4,8 @@ import { Button } from '@/components/ui/button';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import { ArrowLeft, RefreshCw, BarChart3, Database } from 'lucide-react';
 import { api } from '@/lib/api';
-import type { DashboardSummary } from '@/lib/api';
+import type { DashboardSummary, RiskItem } from '@/lib/api';
+import { listen } from '@tauri-apps/api/event';
 import { SkeletonDashboard } from '@/components/ui/skeleton';
 import { dashboardVariants, pageVariants, pageTransition, buttonVariants } from '@/lib/animations';
 import { performanceMonitor, measureAsync } from '@/lib/performance';
@@ -84,6 +85,25 @@ function DashboardMain({ projectId, projectPath, onBack }: DashboardMainProps) {
     });
   }, [projectId]);
 
+  // Show risks as the scan finds them; the refresh after analysis replaces them with the saved list
+  useEffect(() => {
+    if (!analyzing) return;
+    const unlisten = listen<RiskItem>('dashboard-risk-found', (event) => {
+      const risk = event.payload;
+      if (risk.project_id !== projectId) return;
+      setData((current) => {
+        if (!current) return current;
+        const known = current.risk_items.some(
+          (item) => item.category === risk.category && item.title === risk.title && item.file_paths === risk.file_paths
+        );
+        return known ? current : { ...current, risk_items: [...current.risk_items, risk] };
+      });
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [analyzing, projectId]);
+
   const handleRefresh = async () => {
     setLoading(true);
     await fetchDashboardData();
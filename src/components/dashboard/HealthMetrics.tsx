@@ -8,6 +8,7 @@ import {
   TrendingUp, 
   AlertTriangle,
   Activity,
+  FlaskConical,
   ChevronUp,
   ChevronDown,
   Minus
@@ -32,6 +33,8 @@ export function HealthMetrics({ metrics, loading = false }: HealthMetricsProps)
         return <TrendingUp className="h-5 w-5" />;
       case 'error_rate':
         return <AlertTriangle className="h-5 w-5" />;
+      case 'test_coverage':
+        return <FlaskConical className="h-5 w-5" />;
       default:
         return <Activity className="h-5 w-5" />;
     }
@@ -131,7 +134,7 @@ export function HealthMetrics({ metrics, loading = false }: HealthMetricsProps)
                 <div className="flex items-center gap-2">
                   <span className={`text-xl font-bold ${getMetricColor(metric.value, metric.metric_type)}`}>
                     {metric.value.toFixed(0)}
-                    {metric.metric_type === 'error_rate' ? '%' : ''}
+                    {metric.metric_type === 'error_rate' || metric.metric_type === 'test_coverage' ? '%' : ''}
                   </span>
                   {getTrendIcon(metric.trend)}
                 </div>
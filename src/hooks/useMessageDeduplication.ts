@@ -1,5 +1,6 @@
 import { useRef, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import type { DeduplicationMode } from '@/lib/api-types';
 
 interface MessageDeduplicationOptions {
   sessionId: string;
@@ -124,7 +125,12 @@ export function useMessageDeduplication(options: MessageDeduplicationOptions) {
 /**
  * Hook for session isolation to prevent cross-session contamination
  */
-export function useSessionIsolation(sessionId: string, projectId: string, model: string) {
+export function useSessionIsolation(
+  sessionId: string,
+  projectId: string,
+  model: string,
+  dedupMode: DeduplicationMode = 'strict'
+) {
   const isolationState = useRef<any>(null);
   
   /**
@@ -135,7 +141,8 @@ export function useSessionIsolation(sessionId: string, projectId: string, model:
       const state = await invoke('create_isolated_session', {
         sessionId,
         projectId,
-        model
+        model,
+        dedupMode
       });
       isolationState.current = state;
       console.log('[Isolation] Created isolated session:', state);
@@ -144,7 +151,7 @@ export function useSessionIsolation(sessionId: string, projectId: string, model:
       console.error('[Isolation] Failed to create isolated session:', error);
       throw error;
     }
-  }, [sessionId, projectId, model]);
+  }, [sessionId, projectId, model, dedupMode]);
   
   /**
    * Validate that an operation doesn't cross session boundaries
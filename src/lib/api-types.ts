@@ -97,10 +97,32 @@ export interface GeminiRequest {
   topK?: number;
   topP?: number;
   stopSequences?: string[];
+  /** Persona sent as Gemini's system instruction; the saved CLAUDE.md system prompt when omitted */
   systemInstruction?: string;
   tools?: GeminiTool[];
+  /** Universal tool names (see listToolsForModel) Gemini may call through function calling */
+  toolNames?: string[];
+  /** Spend attribution tags (team, ticket, feature) recorded against the session */
+  tags?: Record<string, string>;
+  /** How repeated responses are deduplicated in the session; strict when omitted */
+  dedupMode?: DeduplicationMode;
+  /** Files to attach ahead of the prompt, optionally narrowed to lines, e.g. `src/main.rs:40-120` */
+  attachments?: string[];
+  /**
+   * Earlier turns of this conversation as `[role, text]`, role being `user` or `assistant`;
+   * the oldest are dropped when they don't fit the model's context window
+   */
+  conversationHistory?: [string, string][];
+  /** Images sent along with the prompt as base64 `data:` URLs, for models with vision support */
+  images?: string[];
 }
 
+/**
+ * How repeated content is treated in a session: strict drops any repeat,
+ * lenient only repeats within a couple of seconds, off keeps everything
+ */
+export type DeduplicationMode = 'strict' | 'lenient' | 'off';
+
 /**
  * Gemini tool definition for function calling
  */
@@ -119,6 +141,17 @@ export interface GeminiResponse {
   modelVersion?: string;
 }
 
+/**
+ * Schema-checked JSON answer from execute_gemini_structured
+ */
+export interface GeminiStructuredResult<T = unknown> {
+  data: T;
+  model: string;
+  tokens_used?: number | null;
+  /** The answer hit the output token limit */
+  truncated: boolean;
+}
+
 /**
  * Gemini response candidate
  */
@@ -190,6 +223,24 @@ export type GeminiSafetyProbability =
   | 'MEDIUM'
   | 'HIGH';
 
+/**
+ * Payload of the `claude-blocked:{session_id}` event, sent when Gemini
+ * refuses a prompt or withholds its answer
+ */
+export interface GeminiBlock {
+  session_id: string;
+  /** blockReason or finishReason, e.g. SAFETY or RECITATION */
+  reason: string;
+  /** The prompt itself was rejected rather than the generated answer */
+  prompt_blocked: boolean;
+  /** Harm category that triggered the block, when reported */
+  category: GeminiSafetyCategory | null;
+  probability: GeminiSafetyProbability | null;
+  ratings: Array<GeminiSafetyRating & { blocked: boolean }>;
+  message: string;
+  can_rephrase: boolean;
+}
+
 /**
  * Gemini usage metadata
  */
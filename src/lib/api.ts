@@ -6,6 +6,7 @@ import {
   type GeminiRequest,
   type GeminiResponse,
   type GeminiStreamChunk,
+  type GeminiStructuredResult,
   type VersionInfo,
   type DetectedOllamaModel,
   validateGeminiRequest,
@@ -164,6 +165,8 @@ export interface Agent {
   default_task?: string;
   model: string;
   hooks?: string; // JSON string of HooksConfiguration
+  /** Provider runs use unless overridden: 'claude', 'gemini' or 'ollama' */
+  default_provider?: string;
   created_at: string;
   updated_at: string;
 }
@@ -178,6 +181,7 @@ export interface AgentExport {
     default_task?: string;
     model: string;
     hooks?: string;
+    default_provider?: string;
   };
 }
 
@@ -198,7 +202,7 @@ export interface AgentRun {
   model: string;
   project_path: string;
   session_id: string;
-  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled'
+  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled', 'interrupted'
   pid?: number;
   process_started_at?: string;
   created_at: string;
@@ -221,7 +225,7 @@ export interface AgentRunWithMetrics {
   model: string;
   project_path: string;
   session_id: string;
-  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled'
+  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled', 'interrupted'
   pid?: number;
   process_started_at?: string;
   created_at: string;
@@ -280,6 +284,271 @@ export interface UsageStats {
   by_model: ModelUsage[];
   by_date: DailyUsage[];
   by_project: ProjectUsage[];
+  /** Rollup by the tag requested with `groupByTag`, empty otherwise */
+  by_tag: TagUsage[];
+}
+
+/**
+ * Usage attributed to one value of a spend attribution tag
+ */
+export interface TagUsage {
+  tag_key: string;
+  /** null groups usage from sessions that don't carry the tag */
+  tag_value: string | null;
+  total_cost: number;
+  total_tokens: number;
+  session_count: number;
+  request_count: number;
+}
+
+/**
+ * Result of cancelling a single agent run
+ */
+export interface AgentRunCancellation {
+  run_id: number;
+  /** false when the run had already finished and was left untouched */
+  cancelled: boolean;
+  status: string;
+}
+
+/**
+ * An agent run found running after a restart with its process gone
+ */
+export interface RecoveredRun {
+  run_id: number;
+  outcome: 'completed' | 'interrupted';
+  /** Messages in the session file, which is kept as the run's partial output */
+  output_messages: number;
+  /** Pass to resumeClaudeCode to continue an interrupted run */
+  resume_session_id?: string | null;
+}
+
+/**
+ * Where finished agent runs that asked to be notified report to, besides the OS notification
+ */
+export interface RunNotificationSettings {
+  /** https endpoint (or http on localhost) that receives a JSON summary of each notified run */
+  webhook_url?: string | null;
+}
+
+/**
+ * A user turn recorded by sendSessionMessage
+ */
+export interface SentSessionMessage {
+  session_id: string;
+  message_id: string;
+  sequence_number: number;
+  /** true when the same content was already stored and nothing new was recorded */
+  duplicate: boolean;
+  cost: number;
+}
+
+/**
+ * A retry recorded by retryWithModel; the new turn's content carries a `retry` field
+ */
+export interface RetriedTurn extends SentSessionMessage {
+  model: string;
+  provider: 'claude' | 'gemini' | 'ollama';
+  /** Sequence number of the turn that first sent the prompt */
+  retry_of: number;
+  attempt: number;
+}
+
+/**
+ * Payload of the `session-usage-updated:{session_id}` event, sent after each completed turn
+ */
+export interface SessionUsageUpdate {
+  session_id: string;
+  input_tokens: number;
+  output_tokens: number;
+  total_tokens: number;
+  /** USD, each turn priced for the model that served it */
+  cost: number;
+}
+
+/**
+ * Result of a database maintenance pass
+ */
+export interface MaintenanceReport {
+  size_before_bytes: number;
+  size_after_bytes: number;
+  free_pages_before: number;
+  free_pages_after: number;
+  duration_ms: number;
+  completed_at: string;
+}
+
+/**
+ * A session whose stored totals disagree with its messages or usage records
+ */
+export interface SessionDrift {
+  session_id: string;
+  recorded_messages: number;
+  message_count: number;
+  recorded_tokens: number;
+  /** Sum of the tokens of the session's messages, the authoritative total */
+  message_tokens: number;
+  usage_tokens: number | null;
+  discrepancies: string[];
+  corrected: boolean;
+}
+
+/**
+ * Result of reconcileUsageAccounting
+ */
+export interface ReconciliationReport {
+  sessions_checked: number;
+  drifted: SessionDrift[];
+  corrected: number;
+}
+
+/**
+ * Age and row limits for one debug table; null leaves that limit off
+ */
+export interface TableRetention {
+  max_age_days: number | null;
+  max_rows: number | null;
+}
+
+/**
+ * Retention limits for the debug tables, enforced by scheduled maintenance
+ */
+export interface DebugRetentionConfig {
+  debug_logs: TableRetention;
+  operation_traces: TableRetention;
+  performance_metrics: TableRetention;
+}
+
+/**
+ * Result of exporting the workspace to an archive
+ */
+export interface WorkspaceExportReport {
+  path: string;
+  version: number;
+  /** Rows exported per table */
+  rows: Record<string, number>;
+  projectFiles: number;
+  secretsIncluded: boolean;
+  /** Secret settings and fields left out of the archive */
+  secretsRemoved: number;
+  bytes: number;
+}
+
+/**
+ * How importWorkspace combines an archive with the local workspace
+ */
+export type WorkspaceImportMode = 'merge' | 'replace';
+
+/**
+ * Result of importing a workspace archive
+ */
+export interface WorkspaceImportReport {
+  mode: WorkspaceImportMode;
+  version: number;
+  rowsImported: Record<string, number>;
+  /** Rows left out because a local row already had the same key */
+  rowsSkipped: Record<string, number>;
+  /** Archived rows inserted under a new id */
+  idsRemapped: number;
+  sessionsRestored: number;
+  /** Sessions with local checkpoints, which merging leaves alone */
+  sessionsSkipped: number;
+  warnings: string[];
+}
+
+/**
+ * What models may do in a project; without a saved profile, files under the project root and no shell
+ */
+export interface ProjectPermissionProfile {
+  project_path: string;
+  /** Directories file operations may touch; relative entries are under the project root */
+  allowed_directories: string[];
+  shell_allowed: boolean;
+  /** Programs shell commands may run, by name; empty allows any program */
+  allowed_commands: string[];
+}
+
+/**
+ * An environment variable set for every execution in a project
+ */
+export interface ProjectEnvVar {
+  name: string;
+  value: string;
+  /** Masks the value in logs; names that look like credentials are masked regardless */
+  secret?: boolean;
+}
+
+/**
+ * Rate-limit posture of a provider, e.g. "Gemini throttled, resets in 12m"
+ */
+export interface QuotaStatus {
+  provider: string;
+  throttled: boolean;
+  quota_exhausted: boolean;
+  last_throttled_at?: string | null;
+  /** Retry-After of the last 429, when the provider sent one */
+  retry_after_secs?: number | null;
+  /** Reported by the provider or estimated */
+  resets_at?: string | null;
+  resets_in_secs?: number | null;
+  message: string;
+}
+
+/**
+ * A model of any configured provider, as listed by listAllAvailableModels
+ */
+export interface ModelInfo {
+  id: string;
+  name: string;
+  provider: 'claude' | 'gemini' | 'ollama';
+  /** Maximum input tokens, when known */
+  context_window?: number | null;
+  /** Any of 'tools', 'vision', 'audio' and 'json_mode' */
+  capabilities: string[];
+  /** USD per 1k tokens; zero for local models */
+  cost_per_1k_tokens?: number | null;
+  /** false while the provider is rate limited or the model's circuit breaker is open */
+  available: boolean;
+}
+
+/**
+ * A Gemini request assembled exactly as it would be sent, as returned by previewGeminiEnhancedRequest
+ */
+export interface RequestPreview {
+  model: string;
+  /** URL the request would be posted to, with the API key masked */
+  endpoint: string;
+  /** Request body with any secrets in its text masked */
+  body: any;
+  system_instruction?: string | null;
+  /** Earlier messages of the session sent ahead of the prompt */
+  history_messages: number;
+  /** Estimated input tokens over every text part of the body */
+  estimated_tokens: number;
+}
+
+/**
+ * The Claude directory in use and whether it came from settings, CLAUDE_CONFIG_DIR or the default ~/.claude
+ */
+export interface ClaudeDirInfo {
+  path: string;
+  source: 'setting' | 'environment' | 'default';
+  exists: boolean;
+}
+
+/**
+ * Status of a supervised background task, such as the daily benchmark update
+ */
+export interface BackgroundTaskStatus {
+  name: string;
+  state: 'scheduled' | 'running' | 'failed' | 'finished';
+  runs: number;
+  failures: number;
+  consecutive_failures: number;
+  last_run: string | null;
+  last_success: string | null;
+  last_error: string | null;
+  next_run: string | null;
 }
 
 /**
@@ -294,6 +563,8 @@ export interface Checkpoint {
   description?: string;
   parentCheckpointId?: string;
   metadata: CheckpointMetadata;
+  /** Set when the checkpoint's data is missing and it can no longer be restored */
+  unrestorableReason?: string;
 }
 
 /**
@@ -355,6 +626,76 @@ export interface CheckpointResult {
   warnings: string[];
 }
 
+/**
+ * Inconsistency between the checkpoint store and the session tables
+ */
+export type IntegrityIssueKind =
+  | 'orphaned_timeline'
+  | 'unreadable_timeline'
+  | 'missing_checkpoint_data'
+  | 'missing_blob'
+  | 'untracked_checkpoint'
+  | 'orphaned_messages';
+
+export interface IntegrityIssue {
+  kind: IntegrityIssueKind;
+  projectId?: string;
+  sessionId: string;
+  checkpointId?: string;
+  detail: string;
+}
+
+/**
+ * Result of verifying checkpoint integrity
+ */
+export interface IntegrityReport {
+  sessionsScanned: number;
+  checkpointsScanned: number;
+  issues: IntegrityIssue[];
+}
+
+/**
+ * How repairCheckpoints handles the issues it finds
+ */
+export type RepairMode = 'prune' | 'mark_unrestorable';
+
+export interface RepairReport {
+  mode: RepairMode;
+  repaired: IntegrityIssue[];
+  remaining: IntegrityIssue[];
+}
+
+/**
+ * Size of the checkpoint content pools and how well they deduplicate
+ */
+export interface CheckpointStorageStats {
+  sessions: number;
+  blobCount: number;
+  blobBytes: number;
+  fileReferences: number;
+  /** Bytes the referenced blobs would take without deduplication */
+  logicalBytes: number;
+  dedupRatio: number;
+  /** Blobs no checkpoint references, which gcCheckpoints deletes */
+  unreferencedBlobs: number;
+  unreferencedBytes: number;
+}
+
+export interface CheckpointStateStats {
+  active_managers: number;
+  active_sessions: string[];
+  storage: CheckpointStorageStats;
+}
+
+/**
+ * What a checkpoint garbage collection run removed
+ */
+export interface GcReport {
+  sessionsScanned: number;
+  blobsRemoved: number;
+  bytesReclaimed: number;
+}
+
 /**
  * Diff between two checkpoints
  */
@@ -364,17 +705,49 @@ export interface CheckpointDiff {
   modifiedFiles: FileDiff[];
   addedFiles: string[];
   deletedFiles: string[];
+  /** Files moved without content changes */
+  renamedFiles: FileDiff[];
   tokenDelta: number;
 }
 
+/**
+ * How checkpoint diffs are returned: unified text or structured hunks
+ */
+export type DiffFormat = "unified" | "structured";
+
 /**
  * Diff for a single file
  */
 export interface FileDiff {
   path: string;
+  /** Previous path, for renamed files */
+  oldPath?: string;
   additions: number;
   deletions: number;
+  /** Binary files are reported without line changes */
+  isBinary: boolean;
   diffContent?: string;
+  /** Present when requested with the "structured" format */
+  hunks?: DiffHunk[];
+}
+
+/**
+ * A run of changes with surrounding context
+ */
+export interface DiffHunk {
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  lines: DiffLine[];
+}
+
+export interface DiffLine {
+  kind: "context" | "added" | "removed";
+  oldLine?: number;
+  newLine?: number;
+  content: string;
+  missingNewline: boolean;
 }
 
 /**
@@ -411,6 +784,25 @@ export interface ServerStatus {
   error?: string;
   /** Last checked timestamp */
   last_checked?: number;
+  /** Health-check state when the server is marked "keep running" */
+  supervision?: SupervisionStatus;
+}
+
+/**
+ * Health-check state of a "keep running" MCP server
+ */
+export interface SupervisionStatus {
+  state: "healthy" | "unhealthy" | "stopped" | "failed";
+  /** Re-checks after failed health checks since supervision began */
+  rechecks: number;
+  /** Failed health checks since the last one that connected */
+  failed_checks: number;
+  /** When a health check last found the server connected */
+  last_connected?: string;
+  /** Why the last failed health check failed */
+  last_error?: string;
+  /** When an unhealthy server is checked again */
+  next_check?: string;
 }
 
 /**
@@ -459,6 +851,16 @@ export interface SlashCommand {
   accepts_arguments: boolean;
 }
 
+/**
+ * A slash command whose file, template or referenced files, agents or commands are broken
+ */
+export interface BrokenSlashCommand {
+  id: string;
+  full_command: string;
+  file_path: string;
+  reasons: string[];
+}
+
 /**
  * Result of adding a server
  */
@@ -477,6 +879,83 @@ export interface ImportResult {
   servers: ImportServerResult[];
 }
 
+/**
+ * A declarative change to one provider's outgoing requests
+ */
+export interface RequestHook {
+  name: string;
+  provider: 'gemini' | 'ollama';
+  enabled?: boolean;
+  /** Headers to add, replacing any of the same name */
+  headers?: Record<string, string>;
+  /** Body fields to set, keyed by JSON pointer such as `/generationConfig/temperature` */
+  set?: Record<string, any>;
+  /** Body fields to remove, by JSON pointer */
+  remove?: string[];
+}
+
+/**
+ * All request hooks, applied in order before a request is sent
+ */
+export interface RequestHooks {
+  hooks: RequestHook[];
+}
+
+/**
+ * How prompts that look like they contain secrets are handled before sending
+ */
+export type PromptSecretMode = 'off' | 'warn' | 'redact';
+
+/**
+ * A likely secret found in a prompt
+ */
+export interface SecretFinding {
+  /** The label it was assigned to, like `api_key`, or the key format it matches */
+  kind: string;
+  masked: string;
+}
+
+/**
+ * Payload of the `secret-in-prompt` event
+ */
+export interface SecretInPrompt {
+  provider: string;
+  /** Pass to `confirmPromptSecrets` to send prompts with these secrets anyway */
+  fingerprint: string;
+  findings: SecretFinding[];
+}
+
+/**
+ * A setting whose value differs from its default
+ */
+export interface SettingDiff {
+  key: string;
+  current: any;
+  default: any;
+}
+
+/**
+ * Progress of a provider warm-up, also emitted as `provider-warmup`
+ */
+export interface WarmupStatus {
+  model: string;
+  provider: "claude" | "gemini" | "ollama";
+  state: "warming" | "ready" | "failed";
+  /** What was warmed, or why it failed */
+  detail?: string | null;
+  elapsed_ms: number;
+}
+
+/**
+ * How the project analyzer looks up dependency advisories
+ */
+export interface AdvisoryOptions {
+  /** Lookups in flight at once, 1 to 64 */
+  concurrency: number;
+  /** Use only the advisory snapshot bundled with the app */
+  offline: boolean;
+}
+
 /**
  * Result for individual server import
  */
@@ -515,6 +994,27 @@ export interface RoutingResult {
   domain: string;
 }
 
+/**
+ * Tool categories intelligent routing may invoke; all are enabled by default
+ */
+export interface RoutingToolCategories {
+  agents: boolean;
+  slash_commands: boolean;
+  mcp_servers: boolean;
+  superclaude: boolean;
+}
+
+/**
+ * Outcome of a command that only reports success or failure
+ */
+export interface OperationResult {
+  success: boolean;
+  /** Human-readable summary, shown as is */
+  message: string;
+  /** Structured facts about the outcome, e.g. the affected server */
+  details: Record<string, any> | null;
+}
+
 /**
  * MCP installation request
  */
@@ -524,6 +1024,32 @@ export interface McpInstallRequest {
   confidence: number;
 }
 
+/**
+ * One server to install as part of an MCP install plan
+ */
+export interface McpInstallStep {
+  server_id: string;
+  display_name: string;
+  npm_package?: string;
+  install_command: string;
+  config: any;
+  /** Environment variables the user has to fill in, e.g. API tokens */
+  required_env: string[];
+}
+
+/**
+ * Servers to install for a natural language request, ready to confirm in one step
+ */
+export interface McpInstallPlan {
+  query: string;
+  confidence: number;
+  steps: McpInstallStep[];
+  /** Detected names with no matching server in the registry */
+  unresolved: string[];
+  needs_confirmation: boolean;
+  confirmation_reason?: string;
+}
+
 /**
  * MCP Server metadata
  */
@@ -565,10 +1091,34 @@ export interface ClaudeSyncState {
   commands_cache: Record<string, any>;
   sync_enabled: boolean;
   auto_sync_interval_hours: number;
+  last_auth_ok?: number | null;
+}
+
+export type ClaudeAuthState = "valid" | "expiring" | "expired" | "unknown";
+
+/**
+ * Claude credential health from the background auth probe. The same payload is
+ * emitted with the `claude-auth-expiring` and `claude-auth-expired` events.
+ */
+export interface ClaudeAuthHealth {
+  state: ClaudeAuthState;
+  message: string;
+  checked_at: number;
+  last_known_good: number | null;
+  expires_at: number | null;
+}
+
+export interface SystemStatus {
+  claude_version: string | null;
+  last_sync: number | null;
+  next_sync: number | null;
+  claude_auth: ClaudeAuthHealth;
 }
 
 export interface ClaudeSyncResult {
   success: boolean;
+  /** Human-readable summary, shown as is */
+  message: string;
   commands_found: number;
   new_commands: number;
   updated_commands: number;
@@ -618,6 +1168,24 @@ export interface RiskItem {
   file_paths?: string;
 }
 
+/** How much of a project the analysis reads; over budget it reads one file in `stride` */
+export interface AnalysisCoverage {
+  total_files: number;
+  total_bytes: number;
+  sampled_files: number;
+  stride: number;
+  /** Share of the project's files read, from 0 to 100 */
+  percent: number;
+}
+
+/** Payload of the `dashboard-analysis-progress` event */
+export interface AnalysisProgress {
+  project_id: string;
+  stage: 'health' | 'features' | 'risks' | 'documentation' | 'complete';
+  files_scanned: number;
+  coverage: AnalysisCoverage;
+}
+
 export interface DocumentationStatus {
   id?: number;
   project_id: string;
@@ -700,6 +1268,159 @@ export interface DashboardSummary {
   config?: DashboardConfig;
 }
 
+export type FailoverReason = 'rate_limited' | 'unavailable' | 'timeout' | 'network' | 'model_error';
+
+export interface FailoverModelCount {
+  model: string;
+  count: number;
+  top_reason: FailoverReason;
+  last_failover_at: number;
+}
+
+/**
+ * Provider failover counts over a time range
+ */
+export interface FailoverStats {
+  total_failovers: number;
+  by_reason: { reason: FailoverReason; count: number }[];
+  /** Models requests most often failed over from, most unreliable first */
+  top_failed_models: FailoverModelCount[];
+}
+
+export interface ConfidenceBucket {
+  min_confidence: number;
+  max_confidence: number;
+  decisions: number;
+  success_rate: number;
+  average_cost_usd?: number | null;
+  average_latency_ms?: number | null;
+}
+
+export interface ModelRoutingAccuracy {
+  model: string;
+  decisions: number;
+  average_confidence: number;
+  success_rate: number;
+}
+
+/**
+ * Routing confidence compared with the recorded outcomes of routed requests
+ */
+export interface RoutingAccuracyStats {
+  total_decisions: number;
+  /** Decisions with a recorded outcome; the remaining figures cover only these */
+  decisions_with_outcome: number;
+  success_rate: number;
+  confidence_success_correlation?: number | null;
+  by_confidence: ConfidenceBucket[];
+  by_model: ModelRoutingAccuracy[];
+}
+
+export interface RoutingScoreComponent {
+  name: string;
+  raw_score: number;
+  weight: number;
+  weighted_score: number;
+}
+
+export interface RoutingScoreBreakdown {
+  model_id: string;
+  components: RoutingScoreComponent[];
+  final_score: number;
+  reasoning: string;
+}
+
+/**
+ * A stored routing decision with what the router weighed to make it
+ */
+export interface RoutingDecisionRecord {
+  id: string;
+  domain: string;
+  priority: string;
+  chosen_model: string;
+  confidence: number;
+  created_at: number;
+  reasoning?: string | null;
+  /** Per-candidate scoring, best first */
+  score_breakdown: RoutingScoreBreakdown[];
+  /** Models the router would fall back to, in order */
+  alternatives: string[];
+  fallback_reason?: string | null;
+}
+
+/**
+ * Why a session is using its current model
+ */
+export interface ModelExplanation {
+  session_id: string;
+  model: string;
+  /** Whether the user picked the model by hand instead of auto mode */
+  manual_override: boolean;
+  /** The decision that picked the model; for an override, the automatic one it replaced */
+  decision?: RoutingDecisionRecord | null;
+  summary: string;
+}
+
+/**
+ * Download state of one layer during an Ollama pull
+ */
+export interface OllamaPullLayer {
+  digest: string;
+  total_bytes: number;
+  completed_bytes: number;
+  /** Bytes already on disk from an earlier, interrupted pull */
+  resumed_bytes: number;
+  percent: number;
+}
+
+/**
+ * Payload of the `ollama-pull-progress` event
+ */
+export interface OllamaPullProgressEvent {
+  model: string;
+  status: string;
+  attempt: number;
+  layer?: OllamaPullLayer;
+}
+
+export interface OllamaModelUsage {
+  name: string;
+  size: number;
+  digest: string;
+  modified_at: string;
+  last_used_at?: string;
+}
+
+/**
+ * Result of compacting a Claude session
+ */
+export interface SessionCompactionResult {
+  original_session_id: string;
+  new_session_id: string;
+  /** Model that wrote the summary, or null when the extractive fallback was used */
+  summary_model: string | null;
+  summarized_turns: number;
+  kept_turns: number;
+  original_tokens: number;
+  compacted_tokens: number;
+  tokens_saved: number;
+  checkpoint_id: string;
+}
+
+export interface OllamaPruneReport {
+  deleted: string[];
+  failed: string[];
+  reclaimed_bytes: number;
+}
+
+export interface OllamaPullSummary {
+  model: string;
+  attempts: number;
+  layers: OllamaPullLayer[];
+  resumed_bytes: number;
+  downloaded_bytes: number;
+}
+
 /**
  * API client for interacting with the Rust backend
  */
@@ -929,6 +1650,7 @@ export const api = {
    * @param default_task - Optional default task
    * @param model - Optional model (defaults to 'sonnet')
    * @param hooks - Optional hooks configuration as JSON string
+   * @param default_provider - Optional provider runs use unless overridden
    * @returns Promise resolving to the created agent
    */
   async createAgent(
@@ -937,7 +1659,8 @@ export const api = {
     system_prompt: string, 
     default_task?: string, 
     model?: string,
-    hooks?: string
+    hooks?: string,
+    default_provider?: string
   ): Promise<Agent> {
     try {
       return await invoke<Agent>('create_agent', { 
@@ -946,7 +1669,8 @@ export const api = {
         systemPrompt: system_prompt,
         defaultTask: default_task,
         model,
-        hooks
+        hooks,
+        defaultProvider: default_provider
       });
     } catch (error) {
       console.error("Failed to create agent:", error);
@@ -963,6 +1687,7 @@ export const api = {
    * @param default_task - Optional default task
    * @param model - Optional model
    * @param hooks - Optional hooks configuration as JSON string
+   * @param default_provider - Optional provider runs use unless overridden
    * @returns Promise resolving to the updated agent
    */
   async updateAgent(
@@ -972,7 +1697,8 @@ export const api = {
     system_prompt: string, 
     default_task?: string, 
     model?: string,
-    hooks?: string
+    hooks?: string,
+    default_provider?: string
   ): Promise<Agent> {
     try {
       return await invoke<Agent>('update_agent', { 
@@ -982,7 +1708,8 @@ export const api = {
         systemPrompt: system_prompt,
         defaultTask: default_task,
         model,
-        hooks
+        hooks,
+        defaultProvider: default_provider
       });
     } catch (error) {
       console.error("Failed to update agent:", error);
@@ -1065,12 +1792,13 @@ export const api = {
    * @param agentId - The agent ID to execute
    * @param projectPath - The project path to run the agent in
    * @param task - The task description
-   * @param model - Optional model override
+   * @param model - Optional model override, defaulting to the agent's model
+   * @param provider - Optional provider override, defaulting to the agent's provider
    * @returns Promise resolving to the run ID when execution starts
    */
-  async executeAgent(agentId: number, projectPath: string, task: string, model?: string): Promise<number> {
+  async executeAgent(agentId: number, projectPath: string, task: string, model?: string, provider?: string): Promise<number> {
     try {
-      return await invoke<number>('execute_agent', { agentId, projectPath, task, model });
+      return await invoke<number>('execute_agent', { agentId, projectPath, task, model, provider });
     } catch (error) {
       console.error("Failed to execute agent:", error);
       // Return a sentinel value to indicate error
@@ -1148,6 +1876,73 @@ export const api = {
     }
   },
 
+  /**
+   * Cancels one agent run by ID, leaving other runs of the same batch running
+   * @param runId - The run ID to cancel
+   * @returns Promise resolving to whether the run was cancelled and its final status
+   */
+  async cancelAgentRun(runId: number): Promise<AgentRunCancellation> {
+    try {
+      return await invoke<AgentRunCancellation>('cancel_agent_run', { runId });
+    } catch (error) {
+      console.error("Failed to cancel agent run:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Settles agent runs still marked running whose process is gone; also done on startup
+   * @returns Promise resolving to the runs marked completed or interrupted
+   */
+  async recoverInterruptedAgentRuns(): Promise<RecoveredRun[]> {
+    try {
+      return await invoke<RecoveredRun[]>('recover_interrupted_agent_runs');
+    } catch (error) {
+      console.error("Failed to recover agent runs:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Chooses whether an agent run raises a notification and webhook POST when it finishes
+   * @param runId - The run to flag
+   * @param notifyOnComplete - Whether to notify on completion
+   */
+  async setRunNotifyOnComplete(runId: number, notifyOnComplete: boolean): Promise<void> {
+    try {
+      await invoke('set_run_notify_on_complete', { runId, notifyOnComplete });
+    } catch (error) {
+      console.error("Failed to set run notification:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the webhook finished runs are reported to
+   * @returns Promise resolving to the run notification settings
+   */
+  async getRunNotificationSettings(): Promise<RunNotificationSettings> {
+    try {
+      return await invoke<RunNotificationSettings>('get_run_notification_settings');
+    } catch (error) {
+      console.error("Failed to get run notification settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the run notification settings; an empty webhook URL turns the webhook off
+   * @param settings - The settings to save
+   */
+  async setRunNotificationSettings(settings: RunNotificationSettings): Promise<void> {
+    try {
+      await invoke('set_run_notification_settings', { settings });
+    } catch (error) {
+      console.error("Failed to save run notification settings:", error);
+      throw error;
+    }
+  },
+
   /**
    * Gets the status of a specific agent session
    * @param runId - The run ID to check
@@ -1287,23 +2082,153 @@ export const api = {
 
   /**
    * Executes a new interactive Claude Code session with streaming output
+   * @param attachments - Files to attach ahead of the prompt, optionally narrowed to lines, e.g. `src/main.rs:40-120`
+   */
+  async executeClaudeCode(
+    projectPath: string,
+    prompt: string,
+    model: string,
+    tags?: Record<string, string>,
+    attachments?: string[]
+  ): Promise<void> {
+    return invoke("execute_claude_code", { projectPath, prompt, model, tags, attachments });
+  },
+
+  /**
+   * Continues an existing Claude Code conversation with streaming output
+   */
+  async continueClaudeCode(projectPath: string, prompt: string, model: string, tags?: Record<string, string>): Promise<void> {
+    return invoke("continue_claude_code", { projectPath, prompt, model, tags });
+  },
+
+  /**
+   * Resumes an existing Claude Code session by ID with streaming output
+   */
+  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string, tags?: Record<string, string>): Promise<void> {
+    return invoke("resume_claude_code", { projectPath, sessionId, prompt, model, tags });
+  },
+
+  /**
+   * Compacts a Claude session into a new resumable session seeded with a summary
+   * of older turns plus the most recent turns verbatim
+   * @param sessionId - The session to compact
+   * @param keepRecentTurns - Number of trailing turns to keep verbatim
+   */
+  async compactClaudeSession(sessionId: string, keepRecentTurns?: number): Promise<SessionCompactionResult> {
+    try {
+      return await invoke<SessionCompactionResult>("compact_claude_session", { sessionId, keepRecentTurns });
+    } catch (error) {
+      console.error("Failed to compact Claude session:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reverts a compaction by deleting the compacted session
+   * @returns The original session ID to resume instead
+   */
+  async revertClaudeSessionCompaction(sessionId: string): Promise<string> {
+    try {
+      return await invoke<string>("revert_claude_session_compaction", { sessionId });
+    } catch (error) {
+      console.error("Failed to revert session compaction:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Records a user turn in session history, usage tracking and dashboard metrics in one transaction
+   * @param sessionId - The session the message belongs to
+   * @param projectId - The project the session belongs to
+   * @param projectPath - The project's path
+   * @param content - The message as stored in session history
+   * @param model - The model the message is sent to
+   * @param tokensUsed - Prompt tokens for the message, if known
+   * @param isGemini - Whether this is a Gemini session
+   * @param tags - Optional spend attribution tags
+   * @returns Promise resolving to the stored message; nothing is recorded if any part fails
+   */
+  async sendSessionMessage(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    content: unknown,
+    model: string,
+    tokensUsed?: number,
+    isGemini = false,
+    tags?: Record<string, string>
+  ): Promise<SentSessionMessage> {
+    try {
+      return await invoke<SentSessionMessage>("send_session_message", {
+        sessionId,
+        projectId,
+        projectPath,
+        content,
+        model,
+        tokensUsed,
+        isGemini,
+        tags,
+      });
+    } catch (error) {
+      console.error("Failed to send session message:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Re-sends the session's last prompt to another model, on whichever provider serves it
+   * @param sessionId - Session whose last user turn to retry
+   * @param model - Model to run the prompt on
+   * @returns Promise resolving to the recorded retry turn
+   */
+  async retryWithModel(sessionId: string, model: string): Promise<RetriedTurn> {
+    try {
+      return await invoke<RetriedTurn>("retry_with_model", { sessionId, model });
+    } catch (error) {
+      console.error("Failed to retry with model:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Turns a natural language MCP install request into concrete install steps
+   * @param input - The user's request, e.g. "install the playwright mcp"
+   * @returns Promise resolving to the plan; check needs_confirmation before installing
    */
-  async executeClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return invoke("execute_claude_code", { projectPath, prompt, model });
+  async planMcpInstall(input: string): Promise<McpInstallPlan> {
+    try {
+      return await invoke<McpInstallPlan>("plan_mcp_install", { input });
+    } catch (error) {
+      console.error("Failed to plan MCP install:", error);
+      throw error;
+    }
   },
 
   /**
-   * Continues an existing Claude Code conversation with streaming output
+   * Collects versions, environment, provider status, recent logs, error metrics and running processes for a bug report
+   * @returns Promise resolving to a JSON document with secrets replaced by [REDACTED] and long values truncated
    */
-  async continueClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return invoke("continue_claude_code", { projectPath, prompt, model });
+  async generateDiagnosticsBundle(): Promise<string> {
+    try {
+      return await invoke<string>("generate_diagnostics_bundle");
+    } catch (error) {
+      console.error("Failed to generate diagnostics bundle:", error);
+      throw error;
+    }
   },
 
   /**
-   * Resumes an existing Claude Code session by ID with streaming output
+   * Exports every output, error and completion event recorded for a session
+   * @param sessionId - The session whose events to export
+   * @returns Promise resolving to an NDJSON bundle: a header line, then one event per line with secrets masked
    */
-  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string): Promise<void> {
-    return invoke("resume_claude_code", { projectPath, sessionId, prompt, model });
+  async exportSessionEventLog(sessionId: string): Promise<string> {
+    try {
+      return await invoke<string>("export_session_event_log", { sessionId });
+    } catch (error) {
+      console.error("Failed to export session event log:", error);
+      throw error;
+    }
   },
 
   /**
@@ -1362,11 +2287,18 @@ export const api = {
    * Gets usage statistics filtered by date range
    * @param startDate - Start date (ISO format)
    * @param endDate - End date (ISO format)
+   * @param tagFilter - Only count sessions carrying all of these tags
+   * @param groupByTag - Tag key to roll usage up by in `by_tag`
    * @returns Promise resolving to usage statistics
    */
-  async getUsageByDateRange(startDate: string, endDate: string): Promise<UsageStats> {
+  async getUsageByDateRange(
+    startDate: string,
+    endDate: string,
+    tagFilter?: Record<string, string>,
+    groupByTag?: string
+  ): Promise<UsageStats> {
     try {
-      return await invoke<UsageStats>("get_usage_by_date_range", { startDate, endDate });
+      return await invoke<UsageStats>("get_usage_by_date_range", { startDate, endDate, tagFilter, groupByTag });
     } catch (error) {
       console.error("Failed to get usage by date range:", error);
       throw error;
@@ -1519,19 +2451,22 @@ export const api = {
 
   /**
    * Gets diff between two checkpoints
+   * @param format - "unified" text (default) or "structured" hunks for side-by-side views
    */
   async getCheckpointDiff(
     fromCheckpointId: string,
     toCheckpointId: string,
     sessionId: string,
-    projectId: string
+    projectId: string,
+    format?: DiffFormat
   ): Promise<CheckpointDiff> {
     try {
       return await invoke<CheckpointDiff>("get_checkpoint_diff", {
         fromCheckpointId,
         toCheckpointId,
         sessionId,
-        projectId
+        projectId,
+        format
       });
     } catch (error) {
       console.error("Failed to get checkpoint diff:", error);
@@ -1605,6 +2540,54 @@ export const api = {
     }
   },
 
+  /**
+   * Scans the checkpoint store and session tables for dangling references and missing blobs
+   */
+  async verifyCheckpointIntegrity(): Promise<IntegrityReport> {
+    try {
+      return await invoke<IntegrityReport>("verify_checkpoint_integrity");
+    } catch (error) {
+      console.error("Failed to verify checkpoint integrity:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Prunes dangling checkpoint entries or marks checkpoints with missing data unrestorable
+   */
+  async repairCheckpoints(mode: RepairMode): Promise<RepairReport> {
+    try {
+      return await invoke<RepairReport>("repair_checkpoints", { mode });
+    } catch (error) {
+      console.error("Failed to repair checkpoints:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets active checkpoint managers and content pool storage statistics
+   */
+  async getCheckpointStateStats(): Promise<CheckpointStateStats> {
+    try {
+      return await invoke<CheckpointStateStats>("get_checkpoint_state_stats");
+    } catch (error) {
+      console.error("Failed to get checkpoint state stats:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes checkpoint content no longer referenced by any checkpoint
+   */
+  async gcCheckpoints(): Promise<GcReport> {
+    try {
+      return await invoke<GcReport>("gc_checkpoints");
+    } catch (error) {
+      console.error("Failed to collect checkpoint garbage:", error);
+      throw error;
+    }
+  },
+
   /**
    * Gets checkpoint settings for a session
    */
@@ -1711,9 +2694,9 @@ export const api = {
   /**
    * Removes an MCP server
    */
-  async mcpRemove(name: string): Promise<string> {
+  async mcpRemove(name: string): Promise<OperationResult> {
     try {
-      return await invoke<string>("mcp_remove", { name });
+      return await invoke<OperationResult>("mcp_remove", { name });
     } catch (error) {
       console.error("Failed to remove MCP server:", error);
       throw error;
@@ -1747,9 +2730,9 @@ export const api = {
   /**
    * Starts Claude Code as an MCP server
    */
-  async mcpServe(): Promise<string> {
+  async mcpServe(): Promise<OperationResult> {
     try {
-      return await invoke<string>("mcp_serve");
+      return await invoke<OperationResult>("mcp_serve");
     } catch (error) {
       console.error("Failed to start MCP server:", error);
       throw error;
@@ -1759,15 +2742,30 @@ export const api = {
   /**
    * Tests connection to an MCP server
    */
-  async mcpTestConnection(name: string): Promise<string> {
+  async mcpTestConnection(name: string): Promise<OperationResult> {
     try {
-      return await invoke<string>("mcp_test_connection", { name });
+      return await invoke<OperationResult>("mcp_test_connection", { name });
     } catch (error) {
       console.error("Failed to test MCP connection:", error);
       throw error;
     }
   },
 
+  /**
+   * Starts a stdio MCP server, calls one of its tools and returns the tool result
+   * @param server - Name of the configured MCP server
+   * @param tool - Tool to call
+   * @param args - Tool arguments
+   */
+  async mcpInvokeTool(server: string, tool: string, args: Record<string, any> = {}): Promise<any> {
+    try {
+      return await invoke<any>("mcp_invoke_tool", { server, tool, args });
+    } catch (error) {
+      console.error("Failed to invoke MCP tool:", error);
+      throw error;
+    }
+  },
+
   /**
    * Updates an existing MCP server
    */
@@ -1820,12 +2818,51 @@ export const api = {
     }
   },
 
+  /**
+   * Exports all MCP servers as one document keyed by scope, with sorted keys for version control
+   */
+  async mcpExportAllJsonScoped(): Promise<string> {
+    try {
+      return await invoke<string>("mcp_export_all_json_scoped");
+    } catch (error) {
+      console.error("Failed to export MCP servers by scope:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Exports each scope's MCP servers as its own `{"mcpServers": ...}` file, keyed by scope
+   */
+  async mcpExportAllJsonByScope(): Promise<Record<string, string>> {
+    try {
+      return await invoke<Record<string, string>>("mcp_export_all_json_by_scope");
+    } catch (error) {
+      console.error("Failed to export MCP servers per scope:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports servers from an MCP export
+   * @param jsonConfig - A scoped export, a `{"mcpServers": ...}` file or a map of servers
+   * @param scope - Scope to import into, required when the export doesn't name its scopes
+   * @param replace - Remove same-named servers in the scope first, so the export is restored exactly
+   */
+  async mcpImportAllJson(jsonConfig: string, scope?: string, replace?: boolean): Promise<ImportResult> {
+    try {
+      return await invoke<ImportResult>("mcp_import_all_json", { jsonConfig, scope, replace });
+    } catch (error) {
+      console.error("Failed to import MCP servers from JSON:", error);
+      throw error;
+    }
+  },
+
   /**
    * Resets project-scoped server approval choices
    */
-  async mcpResetProjectChoices(): Promise<string> {
+  async mcpResetProjectChoices(): Promise<OperationResult> {
     try {
-      return await invoke<string>("mcp_reset_project_choices");
+      return await invoke<OperationResult>("mcp_reset_project_choices");
     } catch (error) {
       console.error("Failed to reset project choices:", error);
       throw error;
@@ -1844,6 +2881,18 @@ export const api = {
     }
   },
 
+  /**
+   * Marks an MCP server "keep running" so its health is watched and re-checked after failures, or stops supervising it
+   */
+  async mcpSetKeepRunning(name: string, keepRunning: boolean): Promise<void> {
+    try {
+      await invoke("mcp_set_keep_running", { name, keepRunning });
+    } catch (error) {
+      console.error("Failed to set keep running:", error);
+      throw error;
+    }
+  },
+
   /**
    * Reads .mcp.json from the current project
    */
@@ -1859,15 +2908,27 @@ export const api = {
   /**
    * Saves .mcp.json to the current project
    */
-  async mcpSaveProjectConfig(projectPath: string, config: MCPProjectConfig): Promise<string> {
+  async mcpSaveProjectConfig(projectPath: string, config: MCPProjectConfig): Promise<OperationResult> {
     try {
-      return await invoke<string>("mcp_save_project_config", { projectPath, config });
+      return await invoke<OperationResult>("mcp_save_project_config", { projectPath, config });
     } catch (error) {
       console.error("Failed to save project MCP config:", error);
       throw error;
     }
   },
 
+  /**
+   * Enables or disables a single server in the project's .mcp.json
+   */
+  async mcpToggleProjectServer(projectPath: string, name: string, enabled: boolean): Promise<OperationResult> {
+    try {
+      return await invoke<OperationResult>("mcp_toggle_project_server", { projectPath, name, enabled });
+    } catch (error) {
+      console.error("Failed to toggle project MCP server:", error);
+      throw error;
+    }
+  },
+
   /**
    * Get the stored Claude binary path from settings
    * @returns Promise resolving to the path if set, null otherwise
@@ -1980,65 +3041,262 @@ export const api = {
    * @param primaryKeyValues - Map of primary key column names to values
    * @returns Promise resolving when the row is deleted
    */
-  async storageDeleteRow(
-    tableName: string,
-    primaryKeyValues: Record<string, any>
-  ): Promise<void> {
+  async storageDeleteRow(
+    tableName: string,
+    primaryKeyValues: Record<string, any>
+  ): Promise<void> {
+    try {
+      return await invoke<void>("storage_delete_row", {
+        tableName,
+        primaryKeyValues,
+      });
+    } catch (error) {
+      console.error("Failed to delete row:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Inserts a new row into a table
+   * @param tableName - Name of the table
+   * @param values - Map of column names to values
+   * @returns Promise resolving to the last insert row ID
+   */
+  async storageInsertRow(
+    tableName: string,
+    values: Record<string, any>
+  ): Promise<number> {
+    try {
+      return await invoke<number>("storage_insert_row", {
+        tableName,
+        values,
+      });
+    } catch (error) {
+      console.error("Failed to insert row:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Executes a raw SQL query
+   * @param query - SQL query string
+   * @returns Promise resolving to query result
+   */
+  async storageExecuteSql(query: string): Promise<any> {
+    try {
+      return await invoke<any>("storage_execute_sql", { query });
+    } catch (error) {
+      console.error("Failed to execute SQL:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Resets the entire database
+   * @returns Promise resolving when the database is reset
+   */
+  async storageResetDatabase(): Promise<void> {
+    try {
+      return await invoke<void>("storage_reset_database");
+    } catch (error) {
+      console.error("Failed to reset database:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Checkpoints the WAL, vacuums and analyzes the database
+   * @returns Sizes and free pages before/after, and how long it took
+   */
+  async databaseMaintenance(): Promise<MaintenanceReport> {
+    try {
+      return await invoke<MaintenanceReport>("database_maintenance");
+    } catch (error) {
+      console.error("Failed to run database maintenance:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Cross-checks session totals against their messages and usage records
+   * @param fix - Reset drifted session totals to the per-message sums
+   * @returns Promise resolving to the drifted sessions and how many were corrected
+   */
+  async reconcileUsageAccounting(fix?: boolean): Promise<ReconciliationReport> {
+    try {
+      return await invoke<ReconciliationReport>("reconcile_usage_accounting", { fix });
+    } catch (error) {
+      console.error("Failed to reconcile usage accounting:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the hours between scheduled maintenance runs, null when unscheduled
+   */
+  async getDatabaseMaintenanceSchedule(): Promise<number | null> {
+    try {
+      return await invoke<number | null>("get_database_maintenance_schedule");
+    } catch (error) {
+      console.error("Failed to get database maintenance schedule:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Schedules database maintenance every `intervalHours`; null or 0 turns it off
+   */
+  async setDatabaseMaintenanceSchedule(intervalHours: number | null): Promise<void> {
+    try {
+      return await invoke<void>("set_database_maintenance_schedule", { intervalHours });
+    } catch (error) {
+      console.error("Failed to set database maintenance schedule:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the retention limits for debug logs, operation traces and performance metrics
+   */
+  async getDebugRetentionConfig(): Promise<DebugRetentionConfig> {
+    try {
+      return await invoke<DebugRetentionConfig>("get_debug_retention_config");
+    } catch (error) {
+      console.error("Failed to get debug retention config:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the retention limits for debug logs, operation traces and performance metrics
+   */
+  async setDebugRetentionConfig(config: DebugRetentionConfig): Promise<void> {
+    try {
+      return await invoke<void>("set_debug_retention_config", { config });
+    } catch (error) {
+      console.error("Failed to set debug retention config:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Exports agents, settings, MCP configs, error knowledge, usage history and checkpoints to one archive
+   * @param path - File to write the archive to
+   * @param includeSecrets - Whether API keys and tokens go into the archive
+   */
+  async exportWorkspace(path: string, includeSecrets: boolean): Promise<WorkspaceExportReport> {
+    try {
+      return await invoke<WorkspaceExportReport>("export_workspace", { path, includeSecrets });
+    } catch (error) {
+      console.error("Failed to export workspace:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Restores a workspace archive, merging with or replacing the local workspace
+   */
+  async importWorkspace(path: string, mode: WorkspaceImportMode): Promise<WorkspaceImportReport> {
+    try {
+      return await invoke<WorkspaceImportReport>("import_workspace", { path, mode });
+    } catch (error) {
+      console.error("Failed to import workspace:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the permission profile of a project, the default one if none was saved
+   */
+  async getProjectPermissions(projectPath: string): Promise<ProjectPermissionProfile> {
+    try {
+      return await invoke<ProjectPermissionProfile>("get_project_permissions", { projectPath });
+    } catch (error) {
+      console.error("Failed to get project permissions:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the permission profile of a project
+   */
+  async setProjectPermissions(profile: ProjectPermissionProfile): Promise<void> {
+    try {
+      return await invoke<void>("set_project_permissions", { profile });
+    } catch (error) {
+      console.error("Failed to save project permissions:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the environment variables injected into executions in a project
+   */
+  async getProjectEnv(projectPath: string): Promise<ProjectEnvVar[]> {
+    try {
+      return await invoke<ProjectEnvVar[]>("get_project_env", { projectPath });
+    } catch (error) {
+      console.error("Failed to get project environment:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the environment variables of a project; protected variables such as HOME are rejected
+   */
+  async setProjectEnv(projectPath: string, vars: ProjectEnvVar[]): Promise<void> {
+    try {
+      return await invoke<void>("set_project_env", { projectPath, vars });
+    } catch (error) {
+      console.error("Failed to save project environment:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets each provider's rate-limit state, so throttling can be shown before a request fails
+   */
+  async getQuotaStatus(): Promise<QuotaStatus[]> {
     try {
-      return await invoke<void>("storage_delete_row", {
-        tableName,
-        primaryKeyValues,
-      });
+      return await invoke<QuotaStatus[]>("get_quota_status");
     } catch (error) {
-      console.error("Failed to delete row:", error);
+      console.error("Failed to get quota status:", error);
       throw error;
     }
   },
 
   /**
-   * Inserts a new row into a table
-   * @param tableName - Name of the table
-   * @param values - Map of column names to values
-   * @returns Promise resolving to the last insert row ID
+   * Gets the Claude directory sessions, commands and agents are read from
    */
-  async storageInsertRow(
-    tableName: string,
-    values: Record<string, any>
-  ): Promise<number> {
+  async getClaudeDir(): Promise<ClaudeDirInfo> {
     try {
-      return await invoke<number>("storage_insert_row", {
-        tableName,
-        values,
-      });
+      return await invoke<ClaudeDirInfo>("get_claude_dir");
     } catch (error) {
-      console.error("Failed to insert row:", error);
+      console.error("Failed to get Claude directory:", error);
       throw error;
     }
   },
 
   /**
-   * Executes a raw SQL query
-   * @param query - SQL query string
-   * @returns Promise resolving to query result
+   * Sets the Claude directory; omit the path to go back to CLAUDE_CONFIG_DIR or ~/.claude
    */
-  async storageExecuteSql(query: string): Promise<any> {
+  async setClaudeDir(path?: string): Promise<ClaudeDirInfo> {
     try {
-      return await invoke<any>("storage_execute_sql", { query });
+      return await invoke<ClaudeDirInfo>("set_claude_dir", { path: path ?? null });
     } catch (error) {
-      console.error("Failed to execute SQL:", error);
+      console.error("Failed to set Claude directory:", error);
       throw error;
     }
   },
 
   /**
-   * Resets the entire database
-   * @returns Promise resolving when the database is reset
+   * Gets last run, last error and next run of each background task
    */
-  async storageResetDatabase(): Promise<void> {
+  async getBackgroundTaskStatus(): Promise<BackgroundTaskStatus[]> {
     try {
-      return await invoke<void>("storage_reset_database");
+      return await invoke<BackgroundTaskStatus[]>("get_background_task_status");
     } catch (error) {
-      console.error("Failed to reset database:", error);
+      console.error("Failed to get background task status:", error);
       throw error;
     }
   },
@@ -2239,26 +3497,44 @@ export const api = {
     }
   },
 
+  /**
+   * Checks every slash command for missing files, agents and commands and malformed templates
+   * @param projectPath - Optional project path, to include project commands and resolve file references
+   * @returns Promise resolving to the broken commands, with reasons
+   */
+  async validateSlashCommands(projectPath?: string): Promise<BrokenSlashCommand[]> {
+    try {
+      return await invoke<BrokenSlashCommand[]>("validate_slash_commands", { projectPath });
+    } catch (error) {
+      console.error("Failed to validate slash commands:", error);
+      throw error;
+    }
+  },
+
   /**
    * Executes a slash command by routing it to Claude Code CLI
    * @param command - The slash command to execute
    * @param args - Arguments to substitute in the command
    * @param projectPath - Working directory for the command
    * @param model - Optional model to use for execution
-   * @returns Promise resolving when execution starts (not when complete)
+   * @param sessionId - Optional session ID to stream Claude output under
+   * @returns Promise resolving to the session ID emitting `claude-output:{sessionId}` events
+   * once execution starts (not when complete), or null when routed to Gemini or Ollama
    */
   async executeClaudeSlashCommand(
     command: SlashCommand,
     args: string,
     projectPath: string,
-    model?: string
-  ): Promise<void> {
+    model?: string,
+    sessionId?: string
+  ): Promise<string | null> {
     try {
-      return await invoke<void>("execute_claude_slash_command", {
+      return await invoke<string | null>("execute_claude_slash_command", {
         command,
         arguments: args,
         projectPath,
-        model: model || null
+        model: model || null,
+        sessionId: sessionId || null
       });
     } catch (error) {
       console.error("Failed to execute slash command:", error);
@@ -2357,6 +3633,20 @@ export const api = {
     }
   },
 
+  /**
+   * Cancels a running project analysis; results gathered so far are kept
+   * @param projectId - The project whose analysis should stop
+   * @returns Promise resolving to whether an analysis was running
+   */
+  async cancelAnalysis(projectId: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>("cancel_analysis", { projectId });
+    } catch (error) {
+      console.error("Failed to cancel analysis:", error);
+      throw error;
+    }
+  },
+
   /**
    * Seeds the dashboard with sample data for demonstration
    * @param projectId - The project ID to seed data for
@@ -2399,6 +3689,110 @@ export const api = {
     }
   },
 
+  /**
+   * Gets provider failover counts by reason and the models most often failed over from
+   * @param daysLimit - Only count failovers from the last N days; all time when omitted
+   * @returns Promise resolving to failover stats
+   */
+  async dashboardGetFailoverStats(daysLimit?: number): Promise<FailoverStats> {
+    try {
+      return await invoke<FailoverStats>("dashboard_get_failover_stats", { daysLimit });
+    } catch (error) {
+      console.error("Failed to get failover stats:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Records how a request routed by the intelligent router turned out
+   * @param decisionId - `decision_id` from the model recommendation
+   * @param success - Whether the request completed successfully
+   * @param costUsd - Cost of the request, if known
+   * @param latencyMs - Time to complete the request, if known
+   */
+  async recordRoutingOutcome(
+    decisionId: string,
+    success: boolean,
+    costUsd?: number,
+    latencyMs?: number
+  ): Promise<void> {
+    try {
+      return await invoke("record_routing_outcome", { decisionId, success, costUsd, latencyMs });
+    } catch (error) {
+      console.error("Failed to record routing outcome:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets routing accuracy: success rates by router confidence and by chosen model
+   * @param daysLimit - Only include decisions from the last N days; all time when omitted
+   * @returns Promise resolving to routing accuracy stats
+   */
+  async getRoutingAccuracyStats(daysLimit?: number): Promise<RoutingAccuracyStats> {
+    try {
+      return await invoke<RoutingAccuracyStats>("get_routing_accuracy_stats", { daysLimit });
+    } catch (error) {
+      console.error("Failed to get routing accuracy stats:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Records that the user picked a model for a session by hand
+   * @param sessionId - Session the model was picked for
+   * @param modelId - Model the user picked
+   * @returns Promise resolving to the id of the stored choice
+   */
+  async recordModelOverride(sessionId: string, modelId: string): Promise<string> {
+    try {
+      return await invoke<string>("record_model_override", { sessionId, modelId });
+    } catch (error) {
+      console.error("Failed to record model override:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Explains why a session is using its current model, from the stored routing decisions
+   * @param sessionId - Session to explain
+   * @returns Promise resolving to the explanation
+   */
+  async explainCurrentModel(sessionId: string): Promise<ModelExplanation> {
+    try {
+      return await invoke<ModelExplanation>("explain_current_model", { sessionId });
+    } catch (error) {
+      console.error("Failed to explain current model:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the tool categories intelligent routing may invoke
+   * @returns Promise resolving to the enabled categories
+   */
+  async getRoutingToolCategories(): Promise<RoutingToolCategories> {
+    try {
+      return await invoke<RoutingToolCategories>("get_routing_tool_categories");
+    } catch (error) {
+      console.error("Failed to get routing tool categories:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Enables or disables the tool categories intelligent routing may invoke
+   * @param categories - Category switches to save
+   */
+  async setRoutingToolCategories(categories: RoutingToolCategories): Promise<void> {
+    try {
+      await invoke("set_routing_tool_categories", { categories });
+    } catch (error) {
+      console.error("Failed to set routing tool categories:", error);
+      throw error;
+    }
+  },
+
   // Claude Sync API methods
 
   /**
@@ -2507,6 +3901,19 @@ export const api = {
     }
   },
 
+  /**
+   * Get Claude version, sync schedule and auth health
+   * @returns Promise resolving to the system status
+   */
+  async getSystemStatus(): Promise<SystemStatus> {
+    try {
+      return await invoke<SystemStatus>("get_system_status");
+    } catch (error) {
+      console.error("Failed to get system status:", error);
+      throw error;
+    }
+  },
+
   // Dashboard Utils
   /**
    * Get the current working directory project if it exists
@@ -2650,12 +4057,19 @@ export const api = {
         prompt: request.prompt,
         model: request.model,
         projectPath: projectPath.trim(),
-        temperature: request.temperature,
-        maxOutputTokens: request.maxOutputTokens,
-        topK: request.topK,
-        topP: request.topP,
-        stopSequences: request.stopSequences,
-        systemInstruction: request.systemInstruction
+        options: {
+          temperature: request.temperature,
+          maxOutputTokens: request.maxOutputTokens,
+          topK: request.topK,
+          topP: request.topP,
+          systemPrompt: request.systemInstruction,
+          tools: request.toolNames,
+          tags: request.tags,
+          dedupMode: request.dedupMode,
+          attachments: request.attachments,
+          conversationHistory: request.conversationHistory,
+          images: request.images
+        }
       });
     } catch (error) {
       console.error("Failed to execute Gemini code:", error);
@@ -2674,6 +4088,35 @@ export const api = {
     }
   },
 
+  /**
+   * Execute Gemini in JSON mode; rejects with the schema violations if the answer doesn't conform
+   * @param prompt - The prompt to send
+   * @param model - The Gemini model ID
+   * @param responseSchema - Gemini response schema (OBJECT, ARRAY, STRING, ... types)
+   * @param options - Optional MIME type and generation parameters
+   * @returns Promise resolving to the parsed answer
+   */
+  async executeGeminiStructured<T = unknown>(
+    prompt: string,
+    model: string,
+    responseSchema?: Record<string, unknown>,
+    options?: { responseMimeType?: string; temperature?: number; maxOutputTokens?: number }
+  ): Promise<GeminiStructuredResult<T>> {
+    try {
+      return await invoke<GeminiStructuredResult<T>>('execute_gemini_structured', {
+        prompt,
+        model,
+        responseSchema,
+        responseMimeType: options?.responseMimeType,
+        temperature: options?.temperature,
+        maxOutputTokens: options?.maxOutputTokens
+      });
+    } catch (error) {
+      console.error("Failed to execute structured Gemini request:", error);
+      throw error;
+    }
+  },
+
   /**
    * Execute Gemini model with streaming support (future enhancement)
    * @param prompt - The prompt to send
@@ -2953,6 +4396,36 @@ export const api = {
     }
   },
 
+  /**
+   * Lists the models of every configured provider in one shape; cached for a minute
+   * @returns Promise resolving to Claude, Gemini and Ollama models sorted by provider
+   */
+  async listAllAvailableModels(): Promise<ModelInfo[]> {
+    try {
+      return await invoke<ModelInfo[]>('list_all_available_models');
+    } catch (error) {
+      console.error('Failed to list available models:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Assembles the request the enhanced Gemini backend would send for a prompt in a session, without sending it
+   * (sessions run through executeGeminiCode are not covered)
+   * @param sessionId - Session whose system instruction and history to include
+   * @param prompt - The prompt to preview
+   * @param model - Gemini model id
+   * @returns Promise resolving to the request body, endpoint and token estimate
+   */
+  async previewGeminiEnhancedRequest(sessionId: string | null, prompt: string, model: string): Promise<RequestPreview> {
+    try {
+      return await invoke<RequestPreview>('preview_gemini_enhanced_request', { sessionId, prompt, model });
+    } catch (error) {
+      console.error('Failed to preview request:', error);
+      throw error;
+    }
+  },
+
   /**
    * Get list of available Ollama models (original API)
    * @returns Promise resolving to array of Ollama models
@@ -3014,6 +4487,7 @@ export const api = {
    * @param projectPath - The project path
    * @param systemInstruction - Optional system instruction
    * @param options - Optional model options
+   * @param attachments - Files to attach ahead of the prompt, optionally narrowed to lines, e.g. `src/main.rs:40-120`
    * @returns Promise resolving when execution starts
    */
   async executeOllamaRequest(
@@ -3021,7 +4495,9 @@ export const api = {
     prompt: string,
     projectPath: string,
     systemInstruction?: string,
-    options?: Record<string, any>
+    options?: Record<string, any>,
+    tags?: Record<string, string>,
+    attachments?: string[]
   ): Promise<void> {
     try {
       return await invoke('execute_ollama_request', {
@@ -3029,7 +4505,9 @@ export const api = {
         prompt,
         projectPath,
         systemInstruction,
-        options
+        options,
+        tags,
+        attachments
       });
     } catch (error) {
       console.error('Failed to execute Ollama request:', error);
@@ -3038,13 +4516,14 @@ export const api = {
   },
 
   /**
-   * Pull/Download a new Ollama model
+   * Pull/Download a new Ollama model, resuming partially downloaded layers.
+   * Per-layer progress is emitted as `ollama-pull-progress` events.
    * @param model - The model name to pull
-   * @returns Promise resolving to success message
+   * @returns Promise resolving to resumed vs. freshly downloaded byte counts
    */
-  async pullOllamaModel(model: string): Promise<string> {
+  async pullOllamaModel(model: string): Promise<OllamaPullSummary> {
     try {
-      return await invoke('pull_ollama_model', { model });
+      return await invoke<OllamaPullSummary>('pull_ollama_model', { model });
     } catch (error) {
       console.error(`Failed to pull Ollama model ${model}:`, error);
       throw error;
@@ -3065,6 +4544,34 @@ export const api = {
     }
   },
 
+  /**
+   * List installed Ollama models with their size and last-used time
+   * @returns Promise resolving to models annotated with usage
+   */
+  async listOllamaModelsWithUsage(): Promise<OllamaModelUsage[]> {
+    try {
+      return await invoke<OllamaModelUsage[]>('list_ollama_models_with_usage');
+    } catch (error) {
+      console.error('Failed to list Ollama model usage:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Delete Ollama models that haven't been used recently
+   * @param olderThanDays - Delete models unused for at least this many days
+   * @param keep - Model names that are never deleted
+   * @returns Promise resolving to the deleted models and reclaimed bytes
+   */
+  async pruneOllamaModels(olderThanDays: number, keep: string[] = []): Promise<OllamaPruneReport> {
+    try {
+      return await invoke<OllamaPruneReport>('prune_ollama_models', { olderThanDays, keep });
+    } catch (error) {
+      console.error('Failed to prune Ollama models:', error);
+      throw error;
+    }
+  },
+
   /**
    * Get information about a specific Ollama model
    * @param model - The model name to get info for
@@ -3077,5 +4584,147 @@ export const api = {
       console.error(`Failed to get Ollama model info for ${model}:`, error);
       throw error;
     }
+  },
+
+  /**
+   * Get the configured request hooks
+   * @returns Promise resolving to the hooks, in the order they're applied
+   */
+  async getRequestHooks(): Promise<RequestHooks> {
+    try {
+      return await invoke<RequestHooks>('get_request_hooks');
+    } catch (error) {
+      console.error('Failed to get request hooks:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Save request hooks; nothing is saved if any hook is invalid
+   * @param hooks - The full set of hooks, replacing the current one
+   */
+  async setRequestHooks(hooks: RequestHooks): Promise<void> {
+    try {
+      await invoke('set_request_hooks', { hooks });
+    } catch (error) {
+      console.error('Failed to save request hooks:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Get how prompts that look like they contain secrets are handled
+   */
+  async getPromptSecretMode(): Promise<PromptSecretMode> {
+    try {
+      return await invoke<PromptSecretMode>('get_prompt_secret_mode');
+    } catch (error) {
+      console.error('Failed to get prompt secret mode:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Set how prompts that look like they contain secrets are handled
+   * @param mode - Send them as is, hold them back for confirmation, or redact the secrets
+   */
+  async setPromptSecretMode(mode: PromptSecretMode): Promise<void> {
+    try {
+      await invoke('set_prompt_secret_mode', { mode });
+    } catch (error) {
+      console.error('Failed to save prompt secret mode:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Send a prompt held back by a `secret-in-prompt` warning: from then on,
+   * requests carrying the same secrets go through
+   * @param fingerprint - The fingerprint from the warning
+   */
+  async confirmPromptSecrets(fingerprint: string): Promise<void> {
+    try {
+      await invoke('confirm_prompt_secrets', { fingerprint });
+    } catch (error) {
+      console.error('Failed to confirm prompt secrets:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * List every setting whose value differs from its default
+   * @returns Promise resolving to the changed settings with current and default values
+   */
+  async getSettingsDiff(): Promise<SettingDiff[]> {
+    try {
+      return await invoke<SettingDiff[]>('get_settings_diff');
+    } catch (error) {
+      console.error('Failed to get settings diff:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Restore one setting to its default
+   * @param key - A key from `getSettingsDiff`
+   */
+  async resetSetting(key: string): Promise<void> {
+    try {
+      await invoke('reset_setting', { key });
+    } catch (error) {
+      console.error(`Failed to reset setting ${key}:`, error);
+      throw error;
+    }
+  },
+
+  /**
+   * Warm up the provider of a model in the background so its first request is fast
+   * @param model - The model about to be used
+   * @returns Promise resolving to the `warming` status; the outcome arrives as a `provider-warmup` event
+   */
+  async warmupProvider(model: string): Promise<WarmupStatus> {
+    try {
+      return await invoke<WarmupStatus>('warmup_provider', { model });
+    } catch (error) {
+      console.error(`Failed to warm up ${model}:`, error);
+      throw error;
+    }
+  },
+
+  /**
+   * Get the latest warm-up status of every model warmed since startup
+   */
+  async getWarmupStatuses(): Promise<WarmupStatus[]> {
+    try {
+      return await invoke<WarmupStatus[]>('get_warmup_statuses');
+    } catch (error) {
+      console.error('Failed to get warm-up statuses:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets how dependency advisories are looked up during project analysis
+   */
+  async getAdvisoryOptions(): Promise<AdvisoryOptions> {
+    try {
+      return await invoke<AdvisoryOptions>('get_advisory_options');
+    } catch (error) {
+      console.error('Failed to get advisory options:', error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves how dependency advisories are looked up during project analysis
+   * @param options - Lookup concurrency and whether to stay offline
+   */
+  async setAdvisoryOptions(options: AdvisoryOptions): Promise<void> {
+    try {
+      await invoke('set_advisory_options', { options });
+    } catch (error) {
+      console.error('Failed to save advisory options:', error);
+      throw error;
+    }
   }
 };
@@ -30,6 +30,13 @@ export interface ErrorEntry {
   occurrences: number;
 }
 
+/**
+ * Per-category auto-resolution switches; categories left out stay enabled
+ */
+export interface AutoResolutionConfig {
+  categories: Record<string, boolean>;
+}
+
 export interface ErrorMetrics {
   total_errors: number;
   resolved_errors: number;
@@ -39,6 +46,8 @@ export interface ErrorMetrics {
   errors_by_severity: Record<string, number>;
   resolution_rate: number;
   auto_resolution_rate: number;
+  auto_resolution_coverage: number;
+  auto_resolution_share: number;
   mean_time_to_resolution?: number;
   top_errors: Array<{
     error_code: string;
@@ -176,6 +185,30 @@ export class ErrorDetectionService {
     }
   }
 
+  /**
+   * Get per-category auto-resolution switches (every category is listed)
+   */
+  async getAutoResolutionConfig(): Promise<AutoResolutionConfig> {
+    try {
+      return await invoke<AutoResolutionConfig>('get_auto_resolution_config');
+    } catch (error) {
+      console.error('Failed to get auto-resolution config:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Enable or disable auto-resolution per error category
+   */
+  async setAutoResolutionConfig(config: AutoResolutionConfig): Promise<void> {
+    try {
+      await invoke('set_auto_resolution_config', { config });
+    } catch (error) {
+      console.error('Failed to set auto-resolution config:', error);
+      throw error;
+    }
+  }
+
   /**
    * Manually resolve an error
    */
@@ -29,6 +29,18 @@ export class ExecutionControlAPI {
     return await invoke('stop_execution', { sessionId });
   }
 
+  /**
+   * Stop execution and checkpoint the state it stopped in
+   * @returns The id of the checkpoint to resume from
+   */
+  static async stopAndCheckpoint(
+    sessionId: string,
+    projectId: string,
+    projectPath: string
+  ): Promise<string> {
+    return await invoke('stop_and_checkpoint', { sessionId, projectId, projectPath });
+  }
+
   /**
    * Continue execution from where it was stopped
    */
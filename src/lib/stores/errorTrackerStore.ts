@@ -7,7 +7,8 @@ export enum ErrorSeverity {
   Low = 'Low',
   Medium = 'Medium',
   High = 'High',
-  Critical = 'Critical'
+  Critical = 'Critical',
+  Unknown = 'Unknown'
 }
 
 export enum ErrorCategory {
@@ -30,7 +31,8 @@ export enum ErrorStatus {
   KnownIssue = 'KnownIssue',
   WontFix = 'WontFix',
   Recurring = 'Recurring',
-  AutoResolved = 'AutoResolved'
+  AutoResolved = 'AutoResolved',
+  Unknown = 'Unknown'
 }
 
 export interface ErrorEntry {
@@ -53,6 +55,8 @@ export interface ErrorEntry {
   session_id?: string;
   auto_resolved: boolean;
   pattern_id?: string;
+  /** Most recent distinct raw messages of this error */
+  sample_messages: string[];
 }
 
 export interface ErrorMetrics {
@@ -64,6 +68,8 @@ export interface ErrorMetrics {
   errors_by_severity: Record<string, number>;
   resolution_rate: number;
   auto_resolution_rate: number;
+  auto_resolution_coverage: number;
+  auto_resolution_share: number;
   mean_time_to_resolution?: number;
   top_errors: ErrorSummary[];
 }
@@ -339,6 +345,44 @@ function createErrorTrackerStore() {
     }
   }
 
+  // Find errors split across entries that only differ in ids, numbers or paths
+  async function findDuplicateErrors(): Promise<string[][]> {
+    try {
+      return await invoke<string[][]>('find_duplicate_errors');
+    } catch (err) {
+      console.error('Failed to find duplicate errors:', err);
+      update(state => ({
+        ...state,
+        error: `Failed to find duplicate errors: ${err}`
+      }));
+      return [];
+    }
+  }
+
+  // Merge split entries into the oldest one
+  async function mergeErrors(errorIds: string[]) {
+    try {
+      const merged = await invoke<ErrorEntry>('merge_errors', { errorIds });
+
+      update(state => ({
+        ...state,
+        errors: state.errors
+          .filter(err => err.id === merged.id || !errorIds.includes(err.id))
+          .map(err => (err.id === merged.id ? merged : err))
+      }));
+
+      await loadMetrics();
+      return merged;
+    } catch (err) {
+      console.error('Failed to merge errors:', err);
+      update(state => ({
+        ...state,
+        error: `Failed to merge errors: ${err}`
+      }));
+      return null;
+    }
+  }
+
   // Toggle auto-tracking
   function toggleAutoTracking() {
     update(state => ({
@@ -374,6 +418,8 @@ function createErrorTrackerStore() {
     loadMetrics,
     getError,
     resolveError,
+    findDuplicateErrors,
+    mergeErrors,
     toggleAutoTracking,
     toggleRealTimeUpdates,
     initialize,
@@ -54,6 +54,13 @@ export interface ModelSummary {
   fallback_model?: string;
 }
 
+/** Payload of the `model-health-reset` event */
+export interface ModelHealthReset {
+  /** Model that was reset, or null when every model was */
+  model_id: string | null;
+  circuits_closed: string[];
+}
+
 interface ModelHealthState {
   healthData: Map<string, ModelHealth>;
   lastValidation?: ValidationSummary;
@@ -230,6 +237,30 @@ function createModelHealthStore() {
       }
     },
 
+    // Close a model's circuit breaker and clear its recent failures
+    async resetModelHealth(modelId: string): Promise<ModelHealthReset> {
+      try {
+        const reset = await invoke<ModelHealthReset>('reset_model_health', { modelId });
+        await this.quickHealthCheck();
+        return reset;
+      } catch (error) {
+        console.error(`Failed to reset health for ${modelId}:`, error);
+        throw error;
+      }
+    },
+
+    // Close every circuit breaker and clear all recent failures
+    async resetAllModelHealth(): Promise<ModelHealthReset> {
+      try {
+        const reset = await invoke<ModelHealthReset>('reset_all_model_health');
+        await this.quickHealthCheck();
+        return reset;
+      } catch (error) {
+        console.error('Failed to reset model health:', error);
+        throw error;
+      }
+    },
+
     // Get list of healthy models
     async getHealthyModels(): Promise<string[]> {
       try {